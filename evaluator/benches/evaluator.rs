@@ -11,6 +11,7 @@ fn simulate(
     samples: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
     let mut interpreter = Interpreter::new(&parsed.table);
+    interpreter.set_types(&parsed.types);
     let mut env = Env::with_rand_state(interpreter.var_storage.clone(), 0x42);
 
     let init = interpreter.compile(&parsed.init);
@@ -0,0 +1,57 @@
+//! Benchmarks of the hasher of sets and maps of values, to compare the ones
+//! picked with features (see `src/hashing.rs`), i.e. with
+//! `cargo bench --bench hashing --features ahash`.
+
+use std::hash::BuildHasher;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use quint_evaluator::hashing::{ValueHasher, VALUE_HASHER};
+use quint_evaluator::value::{ImmutableMap, ImmutableSet, Str, Value};
+
+fn record(i: i64) -> Value {
+    let fields = [
+        ("id", Value::Int(i)),
+        ("name", Value::Str(Str::from(format!("process number {i}")))),
+        ("active", Value::Bool(i % 2 == 0)),
+    ];
+    Value::Record(
+        fields
+            .into_iter()
+            .map(|(name, value)| (name.into(), value))
+            .collect(),
+    )
+}
+
+fn hash(value: &Value) -> u64 {
+    ValueHasher::default().hash_one(value)
+}
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("hashing/{VALUE_HASHER}"));
+
+    group.bench_function("insert ints", |b| {
+        b.iter(|| (0..1000).map(Value::Int).collect::<ImmutableSet<Value>>())
+    });
+    group.bench_function("insert records", |b| {
+        b.iter(|| (0..1000).map(record).collect::<ImmutableSet<Value>>())
+    });
+
+    let map = (0..1000)
+        .map(|i| (Value::Int(i), record(i)))
+        .collect::<ImmutableMap<Value, Value>>();
+    group.bench_function("lookup", |b| {
+        b.iter(|| (0..1000).all(|i| map.contains_key(&Value::Int(black_box(i)))))
+    });
+
+    let states = (0..100)
+        .map(|i| Value::Set((i..i + 100).map(record).collect()))
+        .collect::<Vec<_>>();
+    group.bench_function("hash states", |b| {
+        b.iter(|| states.iter().map(hash).fold(0, u64::wrapping_add))
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,112 @@
+//! A suite of benchmarks representative of the work of the evaluator, to have
+//! a standard baseline to report against in changes motivated by performance:
+//! set algebra, state updates of maps and sampling from powersets (see
+//! `fixtures/workloads.qnt`), and simulation steps of bundled examples.
+//!
+//! Specs are compiled with `quint`, which must be installed. Save a baseline
+//! with `cargo bench --bench suite -- --save-baseline main` and compare a
+//! change against it with `cargo bench --bench suite -- --baseline main`.
+
+use std::path::Path;
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use quint_evaluator::evaluator::{Env, Interpreter};
+use quint_evaluator::helpers;
+use quint_evaluator::simulator::ParsedQuint;
+
+/// A spec to be simulated, with the names of its definitions.
+struct Workload {
+    name: &'static str,
+    path: &'static str,
+    main: Option<&'static str>,
+    step: &'static str,
+    invariant: Option<&'static str>,
+}
+
+const WORKLOADS: &[Workload] = &[
+    Workload {
+        name: "set algebra",
+        path: "fixtures/workloads.qnt",
+        main: None,
+        step: "setAlgebra",
+        invariant: None,
+    },
+    Workload {
+        name: "map updates",
+        path: "fixtures/workloads.qnt",
+        main: None,
+        step: "transfer",
+        invariant: Some("totalBalance"),
+    },
+    Workload {
+        name: "powerset sampling",
+        path: "fixtures/workloads.qnt",
+        main: None,
+        step: "samplePowerset",
+        invariant: None,
+    },
+    Workload {
+        name: "tictactoe",
+        path: "fixtures/tictactoe.qnt",
+        main: None,
+        step: "step",
+        invariant: Some("inv"),
+    },
+    Workload {
+        name: "two phase commit",
+        path: "../examples/classic/distributed/TwoPhaseCommit/two_phase_commit.qnt",
+        main: Some("two_phase_commit_3"),
+        step: "step",
+        invariant: Some("consistencyInv"),
+    },
+    Workload {
+        name: "ewd840",
+        path: "../examples/classic/distributed/ewd840/ewd840.qnt",
+        main: Some("ewd840_3"),
+        step: "step",
+        invariant: Some("Inv"),
+    },
+];
+
+/// Run one sample of `steps` steps, checking the invariant on every state.
+fn simulate(parsed: &ParsedQuint, steps: usize) -> Result<(), Box<dyn std::error::Error>> {
+    let mut interpreter = Interpreter::new(&parsed.table);
+    interpreter.set_types(&parsed.types);
+    let mut env = Env::with_rand_state(interpreter.var_storage.clone(), 0x42);
+
+    let init = interpreter.compile(&parsed.init);
+    let step = interpreter.compile(&parsed.step);
+    let invariant = interpreter.compile(&parsed.invariant);
+
+    init.execute(&mut env)?;
+    for _ in 1..=steps {
+        interpreter.shift();
+        invariant.execute(&mut env)?;
+        step.execute(&mut env)?;
+    }
+
+    Ok(())
+}
+
+pub fn criterion_benchmark(c: &mut Criterion) {
+    let mut group = c.benchmark_group("suite");
+
+    for workload in WORKLOADS {
+        let parsed = helpers::parse_from_path(
+            Path::new(workload.path),
+            "init",
+            workload.step,
+            workload.invariant,
+            workload.main,
+        )
+        .unwrap_or_else(|e| panic!("Failed to compile {}: {e}", workload.path));
+        group.bench_function(workload.name, |b| {
+            b.iter(|| simulate(black_box(&parsed), 20).unwrap())
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, criterion_benchmark);
+criterion_main!(benches);
@@ -0,0 +1,28 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Values and traces are encoded with the messages generated from their
+    // protobuf definition, also with a vendored `protoc`
+    #[cfg(feature = "protobuf")]
+    {
+        let mut config = prost_build::Config::new();
+        config.protoc_executable(protoc_bin_vendored::protoc_bin_path()?);
+        config.compile_protos(&["proto/values.proto"], &["proto"])?;
+    }
+
+    // The gRPC service is generated from its protobuf definition, with a
+    // vendored `protoc`, so it doesn't need to be installed
+    #[cfg(feature = "grpc")]
+    {
+        let mut config = tonic_prost_build::Config::new();
+        config.protoc_executable(protoc_bin_vendored::protoc_bin_path()?);
+        tonic_prost_build::configure().compile_with_config(
+            config,
+            &["proto/evaluator.proto"],
+            &["proto"],
+        )?;
+    }
+
+    #[cfg(feature = "napi")]
+    napi_build::setup();
+
+    Ok(())
+}
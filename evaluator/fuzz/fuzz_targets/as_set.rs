@@ -0,0 +1,11 @@
+//! Sets in any representation enumerate to equal sets.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use quint_evaluator::fuzzing::check_as_set;
+use quint_evaluator::value::Value;
+
+fuzz_target!(|value: Value| {
+    check_as_set(&value);
+});
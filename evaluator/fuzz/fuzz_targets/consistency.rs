@@ -0,0 +1,12 @@
+//! Equality, hashing and ordering of values agree with each other.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use quint_evaluator::fuzzing::check_consistency;
+use quint_evaluator::value::Value;
+
+fuzz_target!(|values: (Value, Value, Value)| {
+    let (a, b, c) = values;
+    check_consistency(&a, &b, &c);
+});
@@ -0,0 +1,12 @@
+//! Well-typed expressions compile and evaluate deterministically, without
+//! panicking.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use quint_evaluator::fuzzing::check_evaluation;
+use quint_evaluator::ir::QuintEx;
+
+fuzz_target!(|expr: QuintEx| {
+    check_evaluation(&expr);
+});
@@ -0,0 +1,60 @@
+/*
+ * C interface to the Quint evaluator, built with the `ffi` feature (see
+ * `src/ffi.rs`) as a dynamic library:
+ *
+ *   cargo rustc --lib --release --features ffi --crate-type cdylib
+ *
+ * Values, traces and outcomes are JSON strings, in the same formats as the
+ * methods of the `server` command. Every handle and string returned must be
+ * freed with the matching `*_free` function. Functions returning a pointer
+ * return NULL on failure, and then set `*error` (unless `error` is NULL) to a
+ * JSON error `{"code", "message"}`, to be freed with `quint_string_free`.
+ */
+
+#ifndef QUINT_EVALUATOR_H
+#define QUINT_EVALUATOR_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* A compiled spec. */
+typedef struct QuintSpec QuintSpec;
+
+/* A simulation of a spec, with its options. */
+typedef struct QuintSimulator QuintSimulator;
+
+/* Load a spec, given as the `parsed` field of the input of
+ * `simulate-from-stdin`. */
+QuintSpec *quint_spec_new(const char *parsed_json, char **error);
+
+void quint_spec_free(QuintSpec *spec);
+
+/* Evaluate an expression in a spec, returning its value in ITF. `table_json`
+ * has the definitions the expression refers to, if they are not in the spec
+ * already, or is NULL. */
+char *quint_spec_evaluate(QuintSpec *spec, const char *expr_json,
+                          const char *table_json, char **error);
+
+/* Create a simulator with the given options (`{"source", "nruns", "nsteps",
+ * "ntraces", "seed"}`). It has its own copy of the spec, which can then be
+ * freed. */
+QuintSimulator *quint_simulator_new(const QuintSpec *spec,
+                                    const char *options_json, char **error);
+
+void quint_simulator_free(QuintSimulator *simulator);
+
+/* Run a simulation on the calling thread, returning the same outcome as
+ * `simulate-from-stdin`. */
+char *quint_simulator_run(QuintSimulator *simulator, char **error);
+
+/* Stop a running simulation at the next sample, from another thread. */
+void quint_simulator_cancel(const QuintSimulator *simulator);
+
+void quint_string_free(char *string);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* QUINT_EVALUATOR_H */
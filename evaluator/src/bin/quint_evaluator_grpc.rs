@@ -0,0 +1,26 @@
+//! Serve the evaluator over gRPC, see [`quint_evaluator::grpc`].
+
+use argh::FromArgs;
+use quint_evaluator::grpc::EvaluatorService;
+use quint_evaluator::log;
+
+/// Quint evaluator gRPC server
+#[derive(FromArgs)]
+struct Args {
+    /// the address to listen on (default: 127.0.0.1:50051)
+    #[argh(option, default = "\"127.0.0.1:50051\".to_string()")]
+    listen: String,
+}
+
+#[tokio::main]
+async fn main() -> eyre::Result<()> {
+    let args: Args = argh::from_env();
+    let address = args.listen.parse()?;
+
+    log!("Server", "Listening on {address}");
+    tonic::transport::Server::builder()
+        .add_service(EvaluatorService::default().into_server())
+        .serve(address)
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,250 @@
+//! A browser for traces, stepping through their states interactively in a
+//! terminal (see the `browse` command), i.e. to understand a counterexample.
+//!
+//! The browser shows one state at a time, marking the variables that changed
+//! since the previous state. Nested values are collapsed beyond their first
+//! level, showing their sizes, unless their variable is expanded. Variables can
+//! be filtered, and the trace can be searched for a value, or for the first
+//! state where an expression holds, when the browser is given a way to
+//! compile expressions over the spec of the trace (see [`Compile`]).
+//!
+//! Like the REPL (see [`crate::repl`]), the browser reads commands line by
+//! line (see [`HELP`]), so it works in any terminal.
+
+use crate::trace::Trace;
+use crate::value::Value;
+use itertools::Itertools;
+use std::collections::BTreeSet;
+
+pub const HELP: &str = "\
+n, next [<k>]          go forward one step (or k steps)
+p, prev [<k>]          go back one step (or k steps)
+g, goto <step>         go to a step (`goto last` for the last one)
+filter [<var>...]      only show these variables (all of them, without any)
+expand <var>...        show the nested values of these variables
+collapse [<var>...]    collapse the nested values of these variables (of all)
+search <text>          go to the next state with a value containing the text
+until <expr>           go to the first state where the expression is true
+help                   show this help
+q, quit                exit the browser";
+
+/// Compiles an expression over the spec of the trace, for `until`, once for
+/// all the states it is evaluated in.
+pub type Compile = Box<dyn FnMut(&str) -> Result<Evaluate, String>>;
+
+/// Evaluates a compiled expression in a state of the trace.
+pub type Evaluate = Box<dyn FnMut(&Value) -> Result<Value, String>>;
+
+pub struct Browser {
+    trace: Trace,
+    step: usize,
+    /// The variables to show, or all of them if empty.
+    filter: BTreeSet<String>,
+    /// The variables whose nested values are shown.
+    expanded: BTreeSet<String>,
+    compile: Option<Compile>,
+}
+
+impl Browser {
+    pub fn new(trace: Trace) -> Self {
+        Self {
+            trace,
+            step: 0,
+            filter: BTreeSet::new(),
+            expanded: BTreeSet::new(),
+            compile: None,
+        }
+    }
+
+    /// Compile expressions for `until` with `compile`.
+    pub fn with_compiler(mut self, compile: Compile) -> Self {
+        self.compile = Some(compile);
+        self
+    }
+
+    /// The index of the state being shown.
+    pub fn step(&self) -> usize {
+        self.step
+    }
+
+    /// Handle a command, returning the text to print or an error. Commands
+    /// that move to another state return the view of that state.
+    pub fn handle(&mut self, input: &str) -> Result<String, String> {
+        let input = input.trim();
+        let (command, arg) = input
+            .split_once(char::is_whitespace)
+            .map_or((input, ""), |(command, arg)| (command, arg.trim()));
+        let args = || arg.split_whitespace().map(String::from);
+        match command {
+            "" => Ok(String::new()),
+            "n" | "next" => self.go(self.step.saturating_add(count(arg)?)),
+            "p" | "prev" => self.go(self.step.saturating_sub(count(arg)?)),
+            "g" | "goto" if arg == "last" => self.go(self.last()),
+            "g" | "goto" => self.go(arg.parse().map_err(|_| "Expected goto <step>")?),
+            "filter" => {
+                self.filter = args().collect();
+                Ok(self.show())
+            }
+            "expand" => {
+                if arg.is_empty() {
+                    return Err("Expected expand <var>...".to_string());
+                }
+                self.expanded.extend(args());
+                Ok(self.show())
+            }
+            "collapse" if arg.is_empty() => {
+                self.expanded.clear();
+                Ok(self.show())
+            }
+            "collapse" => {
+                for var in args() {
+                    self.expanded.remove(&var);
+                }
+                Ok(self.show())
+            }
+            "search" => self.search(arg),
+            "until" => self.until(arg),
+            "help" => Ok(HELP.to_string()),
+            _ => Err(format!("Unknown command {command}, see help")),
+        }
+    }
+
+    /// The view of the current state.
+    pub fn show(&self) -> String {
+        let Some(state) = self.trace.states.get(self.step) else {
+            return "The trace has no states".to_string();
+        };
+        let previous = self
+            .step
+            .checked_sub(1)
+            .map(|i| self.trace.states[i].vars.as_record_map());
+
+        let mut output = format!("[State {}/{}]", self.step, self.last());
+        if self.trace.violation && self.step == self.last() {
+            output.push_str(" (violation)");
+        }
+        for (name, value) in state.vars.as_record_map().iter().sorted_by_key(|(n, _)| *n) {
+            if !self.filter.is_empty() && !self.filter.contains(name.as_str()) {
+                continue;
+            }
+            let changed = previous.is_some_and(|p| p.get(name) != Some(value));
+            let value = if self.expanded.contains(name.as_str()) {
+                value.to_string()
+            } else {
+                collapsed(value, 1)
+            };
+            let marker = if changed { "*" } else { " " };
+            output.push_str(&format!("\n{marker} {name}: {value}"));
+        }
+        output
+    }
+
+    fn last(&self) -> usize {
+        self.trace.states.len().saturating_sub(1)
+    }
+
+    fn go(&mut self, step: usize) -> Result<String, String> {
+        if step > self.last() {
+            return Err(format!("The trace has no state {step}"));
+        }
+        self.step = step;
+        Ok(self.show())
+    }
+
+    /// Go to the next state with a shown value containing `text`.
+    fn search(&mut self, text: &str) -> Result<String, String> {
+        if text.is_empty() {
+            return Err("Expected search <text>".to_string());
+        }
+        let found = (self.step + 1..self.trace.states.len()).find(|&i| {
+            self.trace.states[i]
+                .vars
+                .as_record_map()
+                .iter()
+                .filter(|(name, _)| self.filter.is_empty() || self.filter.contains(name.as_str()))
+                .any(|(_, value)| value.to_string().contains(text))
+        });
+        match found {
+            Some(step) => self.go(step),
+            None => Err(format!("No later state has a value containing {text}")),
+        }
+    }
+
+    /// Go to the first state where `expr` evaluates to true.
+    fn until(&mut self, expr: &str) -> Result<String, String> {
+        if expr.is_empty() {
+            return Err("Expected until <expr>".to_string());
+        }
+        let compile = self
+            .compile
+            .as_mut()
+            .ok_or("Expressions can only be evaluated with the spec of the trace")?;
+        let mut evaluate = compile(expr)?;
+        for (i, state) in self.trace.vars().enumerate() {
+            if evaluate(state)? == Value::Bool(true) {
+                return self.go(i);
+            }
+        }
+        Err(format!("{expr} is not true in any state"))
+    }
+}
+
+/// The number of steps to move, by default 1.
+fn count(arg: &str) -> Result<usize, String> {
+    if arg.is_empty() {
+        return Ok(1);
+    }
+    arg.parse()
+        .map_err(|_| format!("Expected a number of steps, got {arg}"))
+}
+
+/// Show a value with the values nested `depth` levels deep collapsed to their
+/// sizes, i.e. `Set(<3 elements>)`.
+fn collapsed(value: &Value, depth: usize) -> String {
+    let elements = |n: usize| match n {
+        1 => "<1 element>".to_string(),
+        n => format!("<{n} elements>"),
+    };
+    let all = |values: &mut dyn Iterator<Item = &Value>| {
+        values.map(|v| collapsed(v, depth - 1)).join(", ")
+    };
+    match value {
+        Value::Set(set) if depth == 0 => format!("Set({})", elements(set.len())),
+        Value::List(list) if depth == 0 => format!("List({})", elements(list.len())),
+        Value::Tuple(elems) if depth == 0 && !elems.is_empty() => {
+            format!("({})", elements(elems.len()))
+        }
+        Value::Map(map) if depth == 0 => format!("Map({})", elements(map.len())),
+        Value::Record(fields) if depth == 0 => {
+            let fields = match fields.len() {
+                1 => "<1 field>".to_string(),
+                n => format!("<{n} fields>"),
+            };
+            format!("{{ {fields} }}")
+        }
+        Value::Set(set) => format!("Set({})", all(&mut set.iter())),
+        Value::List(list) => format!("List({})", all(&mut list.iter())),
+        Value::Tuple(elems) => format!("({})", all(&mut elems.iter())),
+        Value::Map(map) => {
+            let entries = map.iter().map(|(k, v)| {
+                format!(
+                    "Tup({}, {})",
+                    collapsed(k, depth - 1),
+                    collapsed(v, depth - 1)
+                )
+            });
+            format!("Map({})", entries.format(", "))
+        }
+        Value::Record(fields) => {
+            let fields = fields
+                .iter()
+                .map(|(name, v)| format!("{name}: {}", collapsed(v, depth - 1)));
+            format!("{{ {} }}", fields.format(", "))
+        }
+        // Variants show their payload at the same level
+        Value::Variant(label, payload) if !matches!(&**payload, Value::Tuple(t) if t.is_empty()) => {
+            format!("{label}({})", collapsed(payload, depth))
+        }
+        _ => value.to_string(),
+    }
+}
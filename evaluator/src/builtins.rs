@@ -19,39 +19,33 @@
 //! while lazy ops will be compiled into closures that take colusures as
 //! arguments (which should be called to evaluate each argument).
 
-use crate::evaluator::{CompiledExprWithArgs, CompiledExprWithLazyArgs};
-use crate::ir::QuintError;
-use crate::value::{ImmutableMap, ImmutableSet, ImmutableVec, Value};
+use crate::enumeration;
+use crate::evaluator::{
+    CompiledExpr, CompiledExprWithArgs, CompiledExprWithLazyArgs, Env, EvalResult,
+};
+use crate::ir::{ErrorKind, QuintError};
+use crate::picker::{sample_zipf, Distribution, PickMode};
+use crate::symbol::Symbol;
+use crate::value::{
+    unexpected_kind, CachedCardinality, Cardinality, ImmutableMap, ImmutableSet, ImmutableVec, Str,
+    Value,
+};
 use fxhash::FxHashSet;
 use itertools::Itertools;
+use std::borrow::Cow;
+use std::cmp::{Ordering, Reverse};
 use std::rc::Rc;
 
-/// A list of operators that need to be compiled lazily (with `compile_lazy_op`).
-pub const LAZY_OPS: [&str; 13] = [
-    "assign",
-    "actionAny",
-    "actionAll",
-    "ite",
-    "matchVariant",
-    "oneOf",
-    "and",
-    "or",
-    "next",
-    "implies",
-    "then",
-    "reps",
-    "expect",
-];
-
 /// Compile an operator in a lazy way, where the arguments should only be
-/// evaluated when needed.
+/// evaluated when needed. Lazy operators are the ones described as such in
+/// [`crate::reflection`].
 pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
     CompiledExprWithLazyArgs::from_fn(match op {
         "and" => |env, args| {
             // Short-circuit logical AND
             for arg in args {
                 let result = arg.execute(env)?;
-                if !result.as_bool() {
+                if !result.try_bool()? {
                     return Ok(Value::Bool(false));
                 }
             }
@@ -61,7 +55,7 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
             // Short-circuit logical OR
             for arg in args {
                 let result = arg.execute(env)?;
-                if result.as_bool() {
+                if result.try_bool()? {
                     return Ok(Value::Bool(true));
                 }
             }
@@ -70,7 +64,7 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
         "implies" => |env, args| {
             // Short-circuit logical implication
             let lhs = args[0].execute(env)?;
-            if !lhs.as_bool() {
+            if !lhs.try_bool()? {
                 return Ok(Value::Bool(true));
             }
 
@@ -79,7 +73,12 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
         "actionAny" => |env, args| {
             // Executes the first enabled action from a randomized list of actions.
             // Returns false if no enabled actions are found.
-            let next_vars_snapshot = env.var_storage.borrow().take_snapshot();
+            if env.pick_mode == PickMode::Exhaustive {
+                // Each action is its own choice, as the outcomes of the others
+                // are explored separately
+                let i = env.choices.next(args.len());
+                return args[i].execute_action(env);
+            }
 
             // Create array of indices and shuffle them
             let mut indices: Vec<usize> = (0..args.len()).collect();
@@ -89,20 +88,7 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
                 indices.swap(i, j);
             }
 
-            // Try actions in shuffled order until we find one that's enabled
-            for i in indices {
-                let result = args[i].execute(env)?;
-
-                if result.as_bool() {
-                    // Found an enabled action - record it and return true
-                    // TODO: Record in the trace recorder
-                    return Ok(Value::Bool(true));
-                }
-
-                // Reset state before trying next action
-                env.var_storage.borrow_mut().restore(&next_vars_snapshot);
-            }
-            Ok(Value::Bool(false))
+            first_enabled_action(env, args, indices)
         },
         "actionAll" => |env, args| {
             // Executes all of the given actions, or none of them if any of them results in false.
@@ -110,7 +96,7 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
 
             for action in args {
                 let result = action.execute(env)?;
-                if !result.as_bool() {
+                if !result.try_bool()? {
                     env.var_storage.borrow_mut().restore(&next_vars_snapshot);
                     return Ok(Value::Bool(false));
                 }
@@ -119,7 +105,7 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
         },
         "ite" => |env, args| {
             // if-then-else
-            let cond = args[0].execute(env).map(|c| c.as_bool())?;
+            let cond = args[0].execute(env)?.try_bool()?;
             if cond {
                 args[1].execute(env)
             } else {
@@ -129,30 +115,27 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
         "matchVariant" => |env, args| {
             // Pattern matching on variants
             let matched_expr = args[0].execute(env)?;
-            let (variant_label, variant_value) = matched_expr.as_variant();
+            let (variant_label, variant_value) = matched_expr.try_variant()?;
             let cases = &args[1..];
 
-            let matching_case = cases.chunks_exact(2).find_map(|chunk| match chunk {
-                [case_label_expr, case_elim_expr] => {
-                    let case_label = case_label_expr.execute(env).ok()?;
-                    if case_label.as_str() == *variant_label || case_label.as_str() == "_" {
-                        // We found a matching case (or a wildcard "_")
-                        Some(case_elim_expr)
-                    } else {
-                        None
-                    }
+            let mut matching_case = None;
+            for chunk in cases.chunks_exact(2) {
+                let case_label = chunk[0].execute(env)?.try_str()?;
+                if *variant_label == *case_label || case_label == "_" {
+                    // We found a matching case (or a wildcard "_")
+                    matching_case = Some(&chunk[1]);
+                    break;
                 }
-                _ => None,
-            });
+            }
 
             match matching_case {
                 Some(case_elim_expr) => {
                     let case_elim = case_elim_expr.execute(env)?;
-                    let closure = case_elim.as_closure();
+                    let closure = case_elim.try_closure()?;
                     closure(env, vec![variant_value.clone()])
                 }
                 None => Err(QuintError::new(
-                    "QNT505",
+                    ErrorKind::NoElement,
                     &format!("No match for variant {}", variant_label),
                 )),
             }
@@ -160,62 +143,40 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
         "oneOf" => |env, args| {
             // Randomly selects one element of the set.
             let set = args[0].execute(env)?;
-            // Some sets require multiple random numbers in order to pick an element efficiently.
-            // For example, a cross product will require one random number per set, and return a tuple like
-            // (set1.pick(r1), set2.pick(r2), ..., setn.pick(rn))
-
-            // The ranges in which to generate which random number
-            let bounds = set.bounds();
-            // The generated random number for each bound
-            let mut positions = Vec::with_capacity(bounds.len());
-
-            for bound in bounds {
-                if bound == 0 {
-                    return Err(QuintError::new("QNT509", "Applied oneOf on an empty set"));
-                }
-
-                // TODO: The old simulator generates a limited bound for infinite sets
-                // Not sure if we want to keep this behavior
-                // Related: https://github.com/informalsystems/quint/issues/279
-
-                positions.push(env.rand.next(bound))
-            }
-
-            Ok(set.pick(&mut positions.into_iter()))
+            pick_element(env, &set)
         },
         "then" => |env, args| {
             // Compose two actions, executing the second one only if the first one results in true.
             let first = args[0].execute(env)?;
-            if !first.as_bool() {
+            if !first.try_bool()? {
                 return Err(QuintError::new(
-                    "QNT513",
+                    ErrorKind::RunFailed,
                     "Cannot continue in A.then(B), A evaluates to 'false'",
                 ));
             }
 
-            env.shift();
-            // TODO: record state on recorder
+            env.shift_step();
             args[1].execute(env)
         },
         "reps" => {
             |env, args| {
                 // Repeats the given action n times, stopping if the action evaluates to false.
-                let reps = args[0].execute(env).map(|r| r.as_int())?;
+                let reps = args[0].execute(env)?.try_int()?;
                 let action = &args[1];
                 let mut result = Value::Bool(true);
                 for i in 0..reps {
                     let closure = action.execute(env)?;
-                    result = closure.as_closure()(env, vec![Value::Int(i)])?;
-                    if !result.as_bool() {
+                    result = closure.try_closure()?(env, vec![Value::Int(i)])?;
+                    if !result.try_bool()? {
                         return Err(QuintError::new(
-                            "QNT513",
+                            ErrorKind::RunFailed,
                             format!("Reps loop could not continue after iteration #{} evaluated to false", i+1).as_str(),
                         ));
                     }
 
                     // Don't shift the last one
                     if i < reps - 1 {
-                        env.shift();
+                        env.shift_step();
                     }
                 }
                 Ok(result)
@@ -235,8 +196,11 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
                 let predicate = &args[1];
 
                 let action_result = action.execute(env)?;
-                if !action_result.as_bool() {
-                    return Err(QuintError::new("QNT508", "Cannot continue to \"expect\""));
+                if !action_result.try_bool()? {
+                    return Err(QuintError::new(
+                        ErrorKind::AssertionFailed,
+                        "Cannot continue to \"expect\"",
+                    ));
                 }
 
                 let next_vars_snapshot = env.var_storage.borrow().take_snapshot();
@@ -244,9 +208,9 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
                 let predicate_result = predicate.execute(env)?;
                 env.var_storage.borrow_mut().restore(&next_vars_snapshot);
 
-                if !predicate_result.as_bool() {
+                if !predicate_result.try_bool()? {
                     return Err(QuintError::new(
-                        "QNT508",
+                        ErrorKind::AssertionFailed,
                         "Expect condition does not hold true",
                     ));
                 }
@@ -254,21 +218,186 @@ pub fn compile_lazy_op(op: &str) -> CompiledExprWithLazyArgs {
                 Ok(Value::Bool(true))
             }
         }
+        "next" => |env, args| {
+            // Evaluate the argument on the next state, given by the primed
+            // values, and go back to the current state afterwards.
+            let snapshot = env.snapshot();
+            env.shift();
+            let result = args[0].execute(env);
+            env.restore(&snapshot);
+            result
+        },
+        "withProbability" => |env, args| {
+            // withProbability(n, d, A) is A with probability n/d, and false
+            // (i.e. disabled) otherwise.
+            let numerator = args[0].execute(env)?.try_int()?;
+            let denominator = args[1].execute(env)?.try_int()?;
+            if denominator <= 0 || !(0..=denominator).contains(&numerator) {
+                return Err(QuintError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("Invalid probability in withProbability: {numerator}/{denominator}")
+                        .as_str(),
+                ));
+            }
+
+            if draw(env, numerator, denominator) {
+                args[2].execute(env)
+            } else {
+                Ok(Value::Bool(false))
+            }
+        },
         _ => {
-            panic!("Unknown lazy op: {op}")
+            let error = unknown_op(op);
+            return CompiledExprWithLazyArgs::new(move |_env, _args| Err(error.clone()));
+        }
+    })
+}
+
+/// Pick an element of a set, as `oneOf` does in the current pick mode.
+fn pick_element(env: &mut Env, set: &Value) -> EvalResult {
+    match env.pick_mode {
+        PickMode::Deterministic => {
+            return set.min_element().ok_or_else(|| {
+                QuintError::new(ErrorKind::EmptySet, "Applied oneOf on an empty set")
+            });
+        }
+        PickMode::Exhaustive => {
+            let choices = &mut env.choices;
+            return enumerable(set)?.pick_by(|bound| choices.next(bound));
+        }
+        PickMode::Random => {}
+    }
+
+    // Infinite sets (i.e. `Int`) are sampled from the environment's distribution
+    set.sample(&mut env.rand, &env.int_distribution)
+}
+
+/// Whether an outcome with probability `numerator / denominator` happens,
+/// which must be a valid probability.
+fn draw(env: &mut Env, numerator: i64, denominator: i64) -> bool {
+    match env.pick_mode {
+        // Whenever it is possible
+        PickMode::Deterministic => numerator > 0,
+        // Explore both outcomes, unless one of them is impossible
+        PickMode::Exhaustive => {
+            numerator == denominator || (numerator > 0 && env.choices.next(2) == 0)
+        }
+        PickMode::Random => (env.rand.next_u64(denominator as u64) as i64) < numerator,
+    }
+}
+
+/// Compile `actionAny` picking its actions with the given weights (one for
+/// each argument) instead of uniformly. Actions with weight 0 are never taken.
+pub fn compile_weighted_action_any(weights: Vec<u32>) -> CompiledExprWithLazyArgs {
+    CompiledExprWithLazyArgs::new(move |env, args| {
+        if env.pick_mode == PickMode::Exhaustive {
+            // All actions are explored anyway, so weights don't matter
+            return compile_lazy_op("actionAny").execute(env, args);
+        }
+
+        // Order the actions by weighted sampling without replacement, so the
+        // first action is picked with probability proportional to its weight,
+        // and the following ones are the fallbacks if it is disabled
+        let mut remaining: Vec<usize> = (0..args.len()).filter(|&i| weights[i] > 0).collect();
+        let mut indices = Vec::with_capacity(remaining.len());
+        while !remaining.is_empty() {
+            let total: usize = remaining.iter().map(|&i| weights[i] as usize).sum();
+            let mut pick = env.rand.next(total);
+            // The pick is under the total weight, so it always falls in one
+            // of the actions, and the last one is only a fallback
+            let position = remaining
+                .iter()
+                .position(|&i| {
+                    let weight = weights[i] as usize;
+                    if pick < weight {
+                        return true;
+                    }
+                    pick -= weight;
+                    false
+                })
+                .unwrap_or(remaining.len() - 1);
+            indices.push(remaining.remove(position));
         }
+
+        first_enabled_action(env, args, indices)
     })
 }
 
+/// Compile `actionAny` taking its starving fair actions first (see
+/// [`crate::fairness`]), from the longest starving one. `fair` has the names of
+/// the fair actions, for the arguments that are fair. If none of them is
+/// starving or enabled, `action_any` picks an action as usual.
+pub fn compile_fair_action_any(
+    fair: Vec<Option<String>>,
+    action_any: CompiledExprWithLazyArgs,
+) -> CompiledExprWithLazyArgs {
+    CompiledExprWithLazyArgs::new(move |env, args| {
+        if env.pick_mode != PickMode::Exhaustive {
+            let mut starving = fair
+                .iter()
+                .enumerate()
+                .filter_map(|(i, name)| Some((i, env.fairness.starving(name.as_deref()?)?)))
+                .collect::<Vec<_>>();
+            starving.sort_by_key(|&(_, steps)| Reverse(steps));
+            let indices = starving.into_iter().map(|(i, _)| i).collect();
+            if first_enabled_action(env, args, indices)?.try_bool()? {
+                return Ok(Value::Bool(true));
+            }
+        }
+
+        action_any.execute(env, args)
+    })
+}
+
+/// The number of elements at the start of a list that satisfy a predicate.
+fn prefix_length(
+    env: &mut Env,
+    list: &ImmutableVec<Value>,
+    predicate: &Value,
+) -> Result<usize, QuintError> {
+    let predicate = predicate.try_closure()?;
+    for (i, v) in list.iter().enumerate() {
+        if !predicate(env, vec![v.clone()])?.try_bool()? {
+            return Ok(i);
+        }
+    }
+    Ok(list.len())
+}
+
+/// Executes the first enabled action, trying them in the order given by
+/// `indices`. Returns false if no enabled actions are found, or the first
+/// recoverable error of the actions tried, if any.
+fn first_enabled_action(env: &mut Env, args: &[CompiledExpr], indices: Vec<usize>) -> EvalResult {
+    let next_vars_snapshot = env.var_storage.borrow().take_snapshot();
+    let mut discarded = None;
+
+    for i in indices {
+        if args[i].execute_alternative(env, &mut discarded)? {
+            // Found an enabled action - record it and return true
+            // TODO: Record in the trace recorder
+            return Ok(Value::Bool(true));
+        }
+
+        // Reset state before trying next action
+        env.var_storage.borrow_mut().restore(&next_vars_snapshot);
+    }
+    discarded.map_or(Ok(Value::Bool(false)), Err)
+}
+
 /// Compile an operator in an eager way, where the arguments are evaluated beforehand
 pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
     // To be used at `item` and `nth` which share the same behavior
     fn at_index(list: &ImmutableVec<Value>, index: i64) -> Result<Value, QuintError> {
-        if index < 0 || index >= list.len().try_into().unwrap() {
-            return Err(QuintError::new("QNT510", "Out of bounds, nth(${index})"));
-        }
-
-        Ok(list[index as usize].clone())
+        usize::try_from(index)
+            .ok()
+            .and_then(|i| list.get(i))
+            .cloned()
+            .ok_or_else(|| {
+                QuintError::new(
+                    ErrorKind::OutOfBounds,
+                    format!("Out of bounds, nth({index})").as_str(),
+                )
+            })
     }
 
     CompiledExprWithArgs::from_fn(match op {
@@ -278,8 +407,8 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
             // Constructs a record from the given arguments. Arguments are lists like [key1, value1, key2, value2, ...]
             Ok(Value::Record(
                 args.chunks_exact(2)
-                    .map(|chunk| (chunk[0].as_str(), chunk[1].clone()))
-                    .collect(),
+                    .map(|chunk| Ok((Symbol::from(chunk[0].try_str()?), chunk[1].clone())))
+                    .collect::<Result<_, QuintError>>()?,
             ))
         },
         // Constructs a tuple from the given arguments.
@@ -289,119 +418,223 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
         // Constructs a map from the given arguments. Arguments are lists like [[key1, value1], [key2, value2], ...]
         "Map" => |_env, args| {
             Ok(Value::Map(
-                args.into_iter().map(|kv| kv.as_tuple2()).collect(),
+                args.iter()
+                    .map(Value::try_tuple2)
+                    .collect::<Result<_, _>>()?,
             ))
         },
         // Constructs a variant from the given arguments.
-        "variant" => |_env, args| Ok(Value::Variant(args[0].as_str(), Rc::new(args[1].clone()))),
+        "variant" => |_env, args| {
+            Ok(Value::Variant(
+                args[0].try_str()?.into(),
+                Rc::new(args[1].clone()),
+            ))
+        },
         // Logical negation
-        "not" => |_env, args| Ok(Value::Bool(!args[0].as_bool())),
+        "not" => |_env, args| Ok(Value::Bool(!args[0].try_bool()?)),
         // Logical equivalence/bi-implication
-        "iff" => |_env, args| Ok(Value::Bool(args[0].as_bool() == args[1].as_bool())),
+        "iff" => |_env, args| Ok(Value::Bool(args[0].try_bool()? == args[1].try_bool()?)),
         // Equality
         "eq" => |_env, args| Ok(Value::Bool(args[0] == args[1])),
         // Inequality
         "neq" => |_env, args| Ok(Value::Bool(args[0] != args[1])),
+        // A digest of the value, the same for equal values on every run and
+        // platform (see `Value::fingerprint`)
+        "hashOf" => |_env, args| {
+            if has_operator(&args[0]) {
+                return Err(QuintError::new(
+                    ErrorKind::InvalidArgument,
+                    "Cannot hash an operator in hashOf",
+                ));
+            }
+            Ok(Value::Int(args[0].fingerprint() as i64))
+        },
         // Integer addition
-        "iadd" => |_env, args| Ok(Value::Int(args[0].as_int() + args[1].as_int())),
+        "iadd" => |_env, args| {
+            let (a, b) = (args[0].try_int()?, args[1].try_int()?);
+            a.checked_add(b)
+                .map(Value::Int)
+                .ok_or_else(|| integer_overflow(&format!("{a} + {b}")))
+        },
         // Integer subtraction
-        "isub" => |_env, args| Ok(Value::Int(args[0].as_int() - args[1].as_int())),
+        "isub" => |_env, args| {
+            let (a, b) = (args[0].try_int()?, args[1].try_int()?);
+            a.checked_sub(b)
+                .map(Value::Int)
+                .ok_or_else(|| integer_overflow(&format!("{a} - {b}")))
+        },
         // Integer multiplication
-        "imul" => |_env, args| Ok(Value::Int(args[0].as_int() * args[1].as_int())),
+        "imul" => |_env, args| {
+            let (a, b) = (args[0].try_int()?, args[1].try_int()?);
+            a.checked_mul(b)
+                .map(Value::Int)
+                .ok_or_else(|| integer_overflow(&format!("{a} * {b}")))
+        },
         // Integer division
         "idiv" => |_env, args| {
-            let divisor = args[1].as_int();
+            let divisor = args[1].try_int()?;
             if divisor == 0 {
-                return Err(QuintError::new("QNT503", "Division by zero"));
+                return Err(QuintError::new(ErrorKind::Arithmetic, "Division by zero"));
             }
 
-            Ok(Value::Int(args[0].as_int() / divisor))
+            let dividend = args[0].try_int()?;
+            dividend
+                .checked_div(divisor)
+                .map(Value::Int)
+                .ok_or_else(|| integer_overflow(&format!("{dividend} / {divisor}")))
+        },
+        // Integer modulus. The remainder always fits in 64 bits, and
+        // `wrapping_rem` only wraps for `i64::MIN % -1`, where the result is 0.
+        "imod" => |_env, args| {
+            let divisor = args[1].try_int()?;
+            if divisor == 0 {
+                return Err(QuintError::new(ErrorKind::Arithmetic, "Division by zero"));
+            }
+            Ok(Value::Int(args[0].try_int()?.wrapping_rem(divisor)))
         },
-        // Integer modulus
-        "imod" => |_env, args| Ok(Value::Int(args[0].as_int() % args[1].as_int())),
         // Integer exponentiation
         "ipow" => |_env, args| {
-            let base = args[0].as_int();
-            let exp = args[1].as_int();
+            let base = args[0].try_int()?;
+            let exp = args[1].try_int()?;
             if base == 0 && exp == 0 {
-                return Err(QuintError::new("QNT503", "0^0 is undefined"));
+                return Err(QuintError::new(ErrorKind::Arithmetic, "0^0 is undefined"));
             }
             if exp < 0 {
-                return Err(QuintError::new("QNT503", "i^j is undefined for j < 0"));
+                return Err(QuintError::new(
+                    ErrorKind::Arithmetic,
+                    "i^j is undefined for j < 0",
+                ));
             }
 
-            Ok(Value::Int(base.pow(exp as u32)))
+            let result = match u32::try_from(exp) {
+                Ok(exp) => base.checked_pow(exp),
+                // Huge exponents only fit in 64 bits for these bases
+                Err(_) => match base {
+                    0 | 1 => Some(base),
+                    -1 => Some(if exp % 2 == 0 { 1 } else { -1 }),
+                    _ => None,
+                },
+            };
+
+            result
+                .map(Value::Int)
+                .ok_or_else(|| integer_overflow(&format!("{base}^{exp}")))
         },
         // Integer unary minus
-        "iuminus" => |_env, args| Ok(Value::Int(-args[0].as_int())),
+        "iuminus" => |_env, args| {
+            let a = args[0].try_int()?;
+            a.checked_neg()
+                .map(Value::Int)
+                .ok_or_else(|| integer_overflow(&format!("-({a})")))
+        },
+        // Bitwise operators, on integers in two's complement with as many bits
+        // as needed, so negative integers have infinitely many leading ones,
+        // i.e. -1 is all ones. As Quint integers are unbounded, shifts to the
+        // left don't drop bits, and fail when the result doesn't fit.
+        "bitAnd" => |_env, args| Ok(Value::Int(args[0].try_int()? & args[1].try_int()?)),
+        "bitOr" => |_env, args| Ok(Value::Int(args[0].try_int()? | args[1].try_int()?)),
+        "bitXor" => |_env, args| Ok(Value::Int(args[0].try_int()? ^ args[1].try_int()?)),
+        "shiftLeft" => |_env, args| {
+            let (a, n) = (args[0].try_int()?, shift_amount(&args[1], "shiftLeft")?);
+            let shifted = a << n.min(63);
+            if a != 0 && (n >= 64 || shifted >> n != a) {
+                return Err(integer_overflow(&format!("{a}.shiftLeft({n})")));
+            }
+            Ok(Value::Int(shifted))
+        },
+        // Rounds towards negative infinity, i.e. -1.shiftRight(1) is -1
+        "shiftRight" => |_env, args| {
+            let (a, n) = (args[0].try_int()?, shift_amount(&args[1], "shiftRight")?);
+            Ok(Value::Int(a >> n.min(63)))
+        },
+        "popCount" => |_env, args| {
+            let a = args[0].try_int()?;
+            if a < 0 {
+                return Err(QuintError::new(
+                    ErrorKind::Arithmetic,
+                    format!("{a}.popCount() is undefined, as it has infinitely many ones").as_str(),
+                ));
+            }
+            Ok(Value::Int(a.count_ones().into()))
+        },
         // Integer less than
-        "ilt" => |_env, args| Ok(Value::Bool(args[0].as_int() < args[1].as_int())),
+        "ilt" => |_env, args| Ok(Value::Bool(args[0].try_int()? < args[1].try_int()?)),
         // Integer less than or equal to
-        "ilte" => |_env, args| Ok(Value::Bool(args[0].as_int() <= args[1].as_int())),
+        "ilte" => |_env, args| Ok(Value::Bool(args[0].try_int()? <= args[1].try_int()?)),
         // Integer greater than
-        "igt" => |_env, args| Ok(Value::Bool(args[0].as_int() > args[1].as_int())),
+        "igt" => |_env, args| Ok(Value::Bool(args[0].try_int()? > args[1].try_int()?)),
         // Integer greater than or equal to
-        "igte" => |_env, args| Ok(Value::Bool(args[0].as_int() >= args[1].as_int())),
+        "igte" => |_env, args| Ok(Value::Bool(args[0].try_int()? >= args[1].try_int()?)),
 
         // Access a tuple: tuples are 1-indexed, that is, _1, _2, etc.
-        "item" => |_env, args| at_index(args[0].as_list(), args[1].as_int() - 1),
+        "item" => |_env, args| at_index(args[0].try_list()?, args[1].try_int()? - 1),
         // A set of all possible tuples from the elements of the respective given sets.
-        "tuples" => |_env, args| Ok(Value::CrossProduct(args)),
+        "tuples" => |_env, args| {
+            args.iter()
+                .try_for_each(|set| expect_set(set).map(|_| ()))?;
+            Ok(Value::CrossProduct(args, CachedCardinality::default()))
+        },
 
         // Constructs a list of integers from start to end.
         "range" => |_env, args| {
-            let start = args[0].as_int();
-            let end = args[1].as_int();
+            let start = args[0].try_int()?;
+            let end = args[1].try_int()?;
             Ok(Value::List((start..end).map(Value::Int).collect()))
         },
         // List access
-        "nth" => |_env, args| at_index(args[0].as_list(), args[1].as_int()),
+        "nth" => |_env, args| at_index(args[0].try_list()?, args[1].try_int()?),
         // Replace an element at a given index in a list.
         "replaceAt" => |_env, args| {
-            let mut list = args[0].as_list().clone();
-            let index = args[1].as_int();
+            let mut list = args[0].try_list()?.clone();
+            let index = args[1].try_int()?;
 
-            if index < 0 || index >= list.len().try_into().unwrap() {
+            let Some(elem) = usize::try_from(index).ok().and_then(|i| list.get_mut(i)) else {
                 return Err(QuintError::new(
-                    "QNT510",
-                    "Out of bounds, replaceAt(${index})",
+                    ErrorKind::OutOfBounds,
+                    format!("Out of bounds, replaceAt({index})").as_str(),
                 ));
-            }
+            };
 
-            list[index as usize] = args[2].clone();
+            *elem = args[2].clone();
             Ok(Value::List(list))
         },
 
         // Get the first element of a list. Not allowed in empty lists.
         "head" => |_env, args| {
-            let list = args[0].as_list();
+            let list = args[0].try_list()?;
             match list.head() {
                 Some(h) => Ok(h.clone()),
-                None => Err(QuintError::new("QNT505", "Called 'head' on an empty list")),
+                None => Err(QuintError::new(
+                    ErrorKind::NoElement,
+                    "Called 'head' on an empty list",
+                )),
             }
         },
 
         // Get the tail (all elements but the head) of a list. Not allowed in empty lists.
         "tail" => |_env, args| {
-            let list = args[0].as_list();
+            let list = args[0].try_list()?;
             if !list.is_empty() {
                 Ok(Value::List(list.iter().skip(1).cloned().collect()))
             } else {
-                Err(QuintError::new("QNT505", "Called 'tail' on an empty list"))
+                Err(QuintError::new(
+                    ErrorKind::NoElement,
+                    "Called 'tail' on an empty list",
+                ))
             }
         },
 
         // Get a sublist of a list from start to end.
         "slice" => |_env, args| {
-            let list = args[0].as_list();
-            let start = args[1].as_int();
-            let end = args[2].as_int() as usize;
+            let list = args[0].try_list()?;
+            let start = args[1].try_int()?;
+            let end = args[2].try_int()? as usize;
 
             if start >= 0 && end <= list.len() && start as usize <= end {
                 Ok(Value::List(list.clone().slice(start as usize..end)))
             } else {
                 Err(QuintError::new(
-                    "QNT506",
+                    ErrorKind::InvalidSlice,
                     format!(
                         "slice(..., {start}, {end}) applied to a list of size {size}",
                         start = start,
@@ -414,54 +647,65 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
         },
 
         // The length of a list.
-        "length" => |_env, args| Ok(Value::Int(args[0].cardinality().try_into().unwrap())),
+        "length" => |_env, args| Ok(Value::Int(args[0].try_list()?.len() as i64)),
         // Append an element to a list.
         "append" => |_env, args| {
-            let mut list = args[0].as_list().clone();
+            let mut list = args[0].try_list()?.clone();
             list.push_back(args[1].clone());
             Ok(Value::List(list))
         },
         // Concatenate two lists.
         "concat" => |_env, args| {
-            let mut list = args[0].as_list().clone();
-            list.extend(args[1].as_list().iter().cloned());
+            let mut list = args[0].try_list()?.clone();
+            list.extend(args[1].try_list()?.iter().cloned());
             Ok(Value::List(list))
         },
         // A set with the indices of a list.
         "indices" => |_env, args| {
-            let size: i64 = args[0].cardinality().try_into().unwrap();
+            let size = args[0].try_list()?.len() as i64;
             Ok(Value::Interval(0, size - 1))
         },
 
         // Access a field in a record.
         "field" => |_env, args| {
-            Ok(args[0]
-                .as_record_map()
-                .get(&args[1].as_str())
-                .unwrap()
-                .clone())
+            args[0]
+                .try_record_map()?
+                .get(&Symbol::from(args[1].try_str()?))
+                .cloned()
+                .ok_or_else(|| {
+                    QuintError::new(
+                        ErrorKind::Unsupported,
+                        format!("Missing field {}", args[1]).as_str(),
+                    )
+                    .with_value(&args[0])
+                })
         },
 
         // A set with the field names of a record.
         "fieldNames" => |_env, args| {
             Ok(Value::Set(
                 args[0]
-                    .as_record_map()
+                    .try_record_map()?
                     .keys()
-                    .map(|s| Value::Str(s.clone()))
+                    .map(|s| Value::Str(Str::from(s.as_str())))
                     .collect(),
             ))
         },
 
         // Replace a field value in a record.
         "with" => |_env, args| {
-            let mut record = args[0].as_record_map().clone();
-            record.insert(args[1].as_str(), args[2].clone());
+            let mut record = args[0].try_record_map()?.clone();
+            record.insert(args[1].try_str()?.into(), args[2].clone());
             Ok(Value::Record(record))
         },
 
         // The powerset of a set.
-        "powerset" => |_env, args| Ok(Value::PowerSet(Rc::new(args[0].clone()))),
+        "powerset" => |_env, args| {
+            Ok(Value::PowerSet(
+                Rc::new(expect_set(&args[0])?.clone()),
+                CachedCardinality::default(),
+            ))
+        },
         // Check if a set contains an element.
         "contains" => |_env, args| Ok(Value::Bool(args[0].contains(&args[1]))),
         // Check if an element is in a set.
@@ -470,44 +714,82 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
         "subseteq" => |_env, args| Ok(Value::Bool(args[0].subseteq(&args[1]))),
         // Set difference.
         "exclude" => |_env, args| {
+            if let Some(set) = args[0].combine_bitsets(&args[1], |a, b| a & !b) {
+                return Ok(set);
+            }
+            if args[1].is_infinite() {
+                let set = enumerable(&args[0])?.iter_set();
+                return Ok(Value::Set(set.filter(|v| !args[1].contains(v)).collect()));
+            }
+
             Ok(Value::Set(
-                args[0]
-                    .as_set()
+                enumerate(&args[0])?
                     .into_owned()
-                    .relative_complement(args[1].as_set().into_owned()),
+                    .relative_complement(enumerate(&args[1])?.into_owned()),
             ))
         },
         // Set union.
         "union" => |_env, args| {
+            if let Some(set) = args[0].combine_bitsets(&args[1], |a, b| a | b) {
+                return Ok(set);
+            }
             Ok(Value::Set(
-                args[0]
-                    .as_set()
+                enumerate(&args[0])?
                     .into_owned()
-                    .union(args[1].as_set().into_owned()),
+                    .union(enumerate(&args[1])?.into_owned()),
             ))
         },
         // Set intersection.
         "intersect" => |_env, args| {
+            if let Some(set) = args[0].combine_bitsets(&args[1], |a, b| a & b) {
+                return Ok(set);
+            }
+            // If one of the sets is infinite, filter the other one by membership
+            if args[0].is_infinite() || args[1].is_infinite() {
+                let (finite, infinite) = if args[0].is_infinite() {
+                    (&args[1], &args[0])
+                } else {
+                    (&args[0], &args[1])
+                };
+                let set = enumerable(finite)?.iter_set();
+                return Ok(Value::Set(set.filter(|v| infinite.contains(v)).collect()));
+            }
+
             Ok(Value::Set(
-                args[0]
-                    .as_set()
+                enumerate(&args[0])?
                     .into_owned()
-                    .intersection(args[1].as_set().into_owned()),
+                    .intersection(enumerate(&args[1])?.into_owned()),
             ))
         },
 
         // The size of a set.
-        "size" => |_env, args| Ok(Value::Int(args[0].cardinality().try_into().unwrap())),
+        "size" => |_env, args| {
+            if expect_set(&args[0])?.is_infinite() {
+                return Err(infinite_cardinality(&args[0]));
+            }
 
-        // Whether a set is finite.
-        "isFinite" => |_env, _args| {
-            // at the moment, we support only finite sets, so just return true
-            Ok(Value::Bool(true))
+            args[0]
+                .checked_cardinality()
+                .and_then(|size| size.try_into().ok())
+                .map(Value::Int)
+                .ok_or_else(|| integer_overflow("size").with_value(&args[0]))
         },
+
+        // The least and greatest elements of a set, and the sum of a set of
+        // integers. Intervals are not iterated over (see `extreme_element`).
+        // These are not built-ins of Quint, where `min` and `max` are defined
+        // by `basicSpells` and `sum` is a common name of parameters, so only
+        // IR from other frontends applies them.
+        "min" => |_env, args| extreme_element(&args[0], Ordering::Less, "min"),
+        "max" => |_env, args| extreme_element(&args[0], Ordering::Greater, "max"),
+        "sum" => |_env, args| sum(&args[0]),
+
+        // Whether a set is finite.
+        "isFinite" => |_env, args| Ok(Value::Bool(!args[0].is_infinite())),
         // Construct a set of integers from a to b.
         "to" => |_env, args| {
-            let start = args[0].as_int();
-            let end = args[1].as_int();
+            let start = args[0].try_int()?;
+            let end = args[1].try_int()?;
             if start > end {
                 // Avoid having different intervals that represent the same thing (empty set)
                 return Ok(Value::Set(ImmutableSet::default()));
@@ -515,11 +797,12 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
             Ok(Value::Interval(start, end))
         },
 
-        // Fold a set
+        // Fold a set. Elements are produced on demand, so intermediate values
+        // like intervals and cross products are never enumerated in memory.
         "fold" => |env, args| {
-            let reducer = args[2].as_closure();
+            let reducer = args[2].try_closure()?;
             fold_left(
-                args[0].as_set().iter().cloned(),
+                enumerable(&args[0])?.iter_set(),
                 args[1].clone(),
                 |acc, arg| reducer(env, vec![acc, arg]),
             )
@@ -527,9 +810,9 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
 
         // Fold a list from left to right.
         "foldl" => |env, args| {
-            let reducer = args[2].as_closure();
+            let reducer = args[2].try_closure()?;
             fold_left(
-                args[0].as_list().iter().cloned(),
+                args[0].try_list()?.iter().cloned(),
                 args[1].clone(),
                 |acc, arg| reducer(env, vec![acc, arg]),
             )
@@ -537,33 +820,32 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
 
         // Fold a list from right to left.
         "foldr" => |env, args| {
-            let reducer = args[2].as_closure();
+            let reducer = args[2].try_closure()?;
             fold_right(
-                args[0].as_list().iter().cloned(),
+                args[0].try_list()?.iter().cloned(),
                 args[1].clone(),
                 |arg, acc| reducer(env, vec![arg, acc]),
             )
         },
 
-        // Flatten a set of sets.
+        // Flatten a set of sets. Each of the sets is checked too, as a finite
+        // set can contain infinite ones, i.e. `Set(Set(1), Int)`.
         "flatten" => |_env, args| {
-            Ok(Value::Set(
-                args[0]
-                    .as_set()
-                    .iter()
-                    .flat_map(|v| v.as_set().into_owned())
-                    .collect(),
-            ))
+            let mut flattened = ImmutableSet::default();
+            for set in enumerate(&args[0])?.iter() {
+                flattened = flattened.union(enumerate(set)?.into_owned());
+            }
+            Ok(Value::Set(flattened))
         },
 
         // Get a value from a map.
         "get" => |_env, args| {
-            let map = args[0].as_map();
+            let map = args[0].try_map()?;
             let key = args[1].clone().normalize();
             match map.get(&key) {
                 Some(value) => Ok(value.clone()),
                 None => Err(QuintError::new(
-                    "QNT507",
+                    ErrorKind::MissingKey,
                     format!(
                         "Called 'get' with a non-existing key. Key is {key}. Map has keys: {keys}",
                         key = key,
@@ -576,12 +858,12 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
 
         // Set a value for an existing key in a map.
         "set" => |_env, args| {
-            let mut map = args[0].as_map().clone();
+            let mut map = args[0].try_map()?.clone();
             let key = args[1].clone().normalize();
 
             if !map.contains_key(&key) {
                 return Err(QuintError::new(
-                    "QNT507",
+                    ErrorKind::MissingKey,
                     "Called 'set' with a non-existing key",
                 ));
             }
@@ -592,7 +874,7 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
 
         // Set a value for any key in a map.
         "put" => |_env, args| {
-            let mut map = args[0].as_map().clone();
+            let mut map = args[0].try_map()?.clone();
             let key = args[1].clone().normalize();
             let value = args[2].clone();
             map.insert(key, value);
@@ -601,38 +883,98 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
 
         // Set a value for an existing key in a map using a lambda over the current value.
         "setBy" => |env, args| {
-            let mut map = args[0].as_map().clone();
+            let mut map = args[0].try_map()?.clone();
             let key = args[1].clone().normalize();
             match map.get(&key) {
                 Some(value) => {
-                    let new_value = args[2].as_closure()(env, vec![value.clone()])?;
+                    let new_value = args[2].try_closure()?(env, vec![value.clone()])?;
                     map.insert(key, new_value);
                     Ok(Value::Map(map))
                 }
                 None => Err(QuintError::new(
-                    "QNT507",
+                    ErrorKind::MissingKey,
                     format!("Called 'setBy' with a non- existing key {}", key).as_str(),
                 )),
             }
         },
 
+        // Apply a lambda to the values of a map. The map is updated in place
+        // of a copy, so it shares the structure of the original one.
+        "mapValues" => |env, args| {
+            let f = args[1].try_closure()?;
+            let mut map = args[0].try_map()?.clone();
+            for (_, value) in map.iter_mut() {
+                *value = f(env, vec![value.clone()])?;
+            }
+            Ok(Value::Map(map))
+        },
+
+        // Keep the entries of a map that satisfy a lambda over keys and values.
+        "filterEntries" => |env, args| {
+            let predicate = args[1].try_closure()?;
+            let mut map = args[0].try_map()?.clone();
+            for (key, value) in args[0].try_map()? {
+                if !predicate(env, vec![key.clone(), value.clone()])?.try_bool()? {
+                    map.remove(key);
+                }
+            }
+            Ok(Value::Map(map))
+        },
+
+        // Merge two maps, combining the values of the keys in both with a
+        // lambda.
+        "mergeWith" => |env, args| {
+            let combine = args[2].try_closure()?;
+            let mut map = args[0].try_map()?.clone();
+            for (key, value) in args[1].try_map()? {
+                let merged = match map.get(key) {
+                    Some(first) => combine(env, vec![first.clone(), value.clone()])?,
+                    None => value.clone(),
+                };
+                map.insert(key.clone(), merged);
+            }
+            Ok(Value::Map(map))
+        },
+
+        // Apply a lambda to the keys of a map, which must give different keys.
+        "mapKeys" => |env, args| {
+            let f = args[1].try_closure()?;
+            let mut map = ImmutableMap::new();
+            for (key, value) in args[0].try_map()? {
+                let new_key = f(env, vec![key.clone()])?.normalize();
+                if map.insert(new_key.clone(), value.clone()).is_some() {
+                    return Err(QuintError::new(
+                        ErrorKind::InvalidArgument,
+                        format!("Called 'mapKeys' with a lambda giving the key {new_key} twice")
+                            .as_str(),
+                    ));
+                }
+            }
+            Ok(Value::Map(map))
+        },
+
         // A set with the keys of a map.
-        "keys" => |_env, args| Ok(Value::Set(args[0].as_map().keys().cloned().collect())),
-        // Check if a predicate holds for some element in a set.
+        "keys" => |_env, args| Ok(Value::Set(args[0].try_map()?.keys().cloned().collect())),
+        // Check if a predicate holds for some element in a set. Elements are
+        // produced on demand, so we stop at the first witness without
+        // enumerating the whole set.
         "exists" => |env, args| {
-            for v in args[0].as_set().iter() {
-                let result = args[1].as_closure()(env, vec![v.clone()])?;
-                if result.as_bool() {
+            let predicate = args[1].try_closure()?;
+            for v in enumerable(&args[0])?.iter_set() {
+                if predicate(env, vec![v])?.try_bool()? {
                     return Ok(Value::Bool(true));
                 }
             }
             Ok(Value::Bool(false))
         },
 
+        // Check if a predicate holds for all elements in a set. Elements are
+        // produced on demand, so we stop at the first counterexample without
+        // enumerating the whole set.
         "forall" => |env, args| {
-            for v in args[0].as_set().iter() {
-                let result = args[1].as_closure()(env, vec![v.clone()])?;
-                if !result.as_bool() {
+            let predicate = args[1].try_closure()?;
+            for v in enumerable(&args[0])?.iter_set() {
+                if !predicate(env, vec![v])?.try_bool()? {
                     return Ok(Value::Bool(false));
                 }
             }
@@ -641,34 +983,38 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
 
         // Map a lambda over a set.
         "map" => |env, args| {
+            // Intermediate sets are iterated over instead of enumerated, as
+            // only the results are kept
+            let f = args[1].try_closure()?;
             Ok(Value::Set(
-                args[0]
-                    .as_set()
-                    .iter()
-                    .map(|v| args[1].as_closure()(env, vec![v.clone()]))
+                enumerable(&args[0])?
+                    .iter_set()
+                    .map(|v| f(env, vec![v]))
                     .collect::<Result<_, _>>()?,
             ))
         },
 
         // Filter a set using a lambda.
         "filter" => |env, args| {
-            Ok(Value::Set(args[0].as_set().iter().try_fold(
-                ImmutableSet::default(),
-                |mut acc, v| {
-                    if args[1].as_closure()(env, vec![v.clone()])?.as_bool() {
-                        acc.insert(v.clone());
-                    }
-                    Ok(acc)
-                },
-            )?))
+            // Intermediate sets are iterated over instead of enumerated, as
+            // only the kept elements are
+            let predicate = args[1].try_closure()?;
+            let mut filtered = ImmutableSet::default();
+            for v in enumerable(&args[0])?.iter_set() {
+                if predicate(env, vec![v.clone()])?.try_bool()? {
+                    filtered.insert(v);
+                }
+            }
+            Ok(Value::Set(filtered))
         },
 
         // Filter a list using a lambda
         "select" => |env, args| {
-            Ok(Value::List(args[0].as_list().iter().try_fold(
+            let predicate = args[1].try_closure()?;
+            Ok(Value::List(args[0].try_list()?.iter().try_fold(
                 ImmutableVec::new(),
                 |mut acc, v| {
-                    if args[1].as_closure()(env, vec![v.clone()])?.as_bool() {
+                    if predicate(env, vec![v.clone()])?.try_bool()? {
                         acc.push_back(v.clone());
                     }
                     Ok(acc)
@@ -676,45 +1022,133 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
             )?))
         },
 
+        // The elements of a set in ascending order (see `Ord for Value`), so
+        // the list is the same on every run and platform, whatever the
+        // hasher that lays the set out.
+        "setToSortedList" => |_env, args| {
+            let mut elems = enumerable(&args[0])?.iter_set().collect::<Vec<_>>();
+            elems.sort_unstable();
+            Ok(Value::List(elems.into()))
+        },
+
+        // Pair up the elements of two lists, up to the end of the shorter one.
+        "zip" => |_env, args| {
+            let pairs = args[0].try_list()?.iter().zip(args[1].try_list()?.iter());
+            Ok(Value::List(
+                pairs
+                    .map(|(a, b)| Value::Tuple(ImmutableVec::from(vec![a.clone(), b.clone()])))
+                    .collect(),
+            ))
+        },
+
+        // Pair up the elements of a list with their indices.
+        "enumerate" => |_env, args| {
+            Ok(Value::List(
+                args[0]
+                    .try_list()?
+                    .iter()
+                    .enumerate()
+                    .map(|(i, v)| {
+                        Value::Tuple(ImmutableVec::from(vec![Value::Int(i as i64), v.clone()]))
+                    })
+                    .collect(),
+            ))
+        },
+
+        // Sort a list by the keys given by a lambda, keeping the order of
+        // elements with equal keys.
+        "sortBy" => |env, args| {
+            let key = args[1].try_closure()?;
+            let mut keyed = args[0]
+                .try_list()?
+                .iter()
+                .map(|v| Ok((key(env, vec![v.clone()])?, v.clone())))
+                .collect::<Result<Vec<_>, QuintError>>()?;
+            keyed.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Ok(Value::List(keyed.into_iter().map(|(_, v)| v).collect()))
+        },
+
+        // The longest prefix of a list whose elements satisfy a lambda.
+        "takeWhile" => |env, args| {
+            let list = args[0].try_list()?;
+            let prefix = prefix_length(env, list, &args[1])?;
+            Ok(Value::List(list.take(prefix)))
+        },
+
+        // A list without the longest prefix whose elements satisfy a lambda.
+        "dropWhile" => |env, args| {
+            let list = args[0].try_list()?;
+            let prefix = prefix_length(env, list, &args[1])?;
+            Ok(Value::List(list.skip(prefix)))
+        },
+
+        // Split a list into lists of the given size, the last one possibly
+        // shorter.
+        "chunks" => |_env, args| {
+            let size = args[1].try_int()?;
+            if size <= 0 {
+                return Err(QuintError::new(
+                    ErrorKind::InvalidArgument,
+                    format!("Invalid size in chunks: {size}").as_str(),
+                ));
+            }
+
+            let mut rest = args[0].try_list()?.clone();
+            let mut chunks = ImmutableVec::new();
+            while !rest.is_empty() {
+                let tail = rest.split_off((size as usize).min(rest.len()));
+                chunks.push_back(Value::List(rest));
+                rest = tail;
+            }
+            Ok(Value::List(chunks))
+        },
+
         // Construct a map by applying a lambda to the values of a set.
         "mapBy" => |env, args| {
-            let closure = args[1].as_closure();
-            let keys = args[0].as_set();
+            let closure = args[1].try_closure()?;
 
-            Ok(Value::Map(keys.iter().try_fold(
+            Ok(Value::Map(enumerable(&args[0])?.iter_set().try_fold(
                 ImmutableMap::new(),
                 |mut acc, key| {
                     let value = closure(env, vec![key.clone()])?;
-                    acc.insert(key.clone().normalize(), value);
+                    acc.insert(key.normalize(), value);
                     Ok(acc)
                 },
             )?))
         },
         // Convert a set of key-value tuples to a map.
         "setToMap" => |_env, args| {
-            let set = args[0].as_set();
-            Ok(Value::Map(set.iter().map(|v| v.as_tuple2()).collect()))
+            let set = enumerable(&args[0])?;
+            Ok(Value::Map(
+                set.iter_set()
+                    .map(|v| v.try_tuple2())
+                    .collect::<Result<_, _>>()?,
+            ))
         },
         // A set of all possible maps with keys and values from the given sets.
         "setOfMaps" => |_env, args| {
             Ok(Value::MapSet(
-                Rc::new(args[0].clone()),
-                Rc::new(args[1].clone()),
+                Rc::new(expect_set(&args[0])?.clone()),
+                Rc::new(expect_set(&args[1])?.clone()),
+                CachedCardinality::default(),
             ))
         },
         // Expect a value to be false
-        "fail" => |_env, args| Ok(Value::Bool(!args[0].as_bool())),
+        "fail" => |_env, args| Ok(Value::Bool(!args[0].try_bool()?)),
         // Expect a value to be true, returning a runtime error if it is not
         "assert" => |_env, args| {
-            if !args[0].as_bool() {
-                return Err(QuintError::new("QNT508", "Assertion failed"));
+            if !args[0].try_bool()? {
+                return Err(QuintError::new(
+                    ErrorKind::AssertionFailed,
+                    "Assertion failed",
+                ));
             }
             Ok(Value::Bool(true))
         },
         // Generate all lists of length up to the given number, from a set
         "allListsUpTo" => |_env, args| {
-            let set = args[0].as_set();
-            let length = args[1].as_int();
+            let set = enumerate(&args[0])?;
+            let length = args[1].try_int()?;
             let mut lists = FxHashSet::default();
             let mut last_lists = FxHashSet::<ImmutableVec<Value>>::default();
             lists.insert(ImmutableVec::default());
@@ -739,51 +1173,278 @@ pub fn compile_eager_op(op: &str) -> CompiledExprWithArgs {
 
         // Get the only element of a set, or an error if the set is empty or has more than one element.
         "getOnlyElement" => |_env, args| {
-            let set = args[0].as_set();
+            let set = enumerate(&args[0])?;
             let size = set.len();
-            if size != 1 {
+            let (Some(elem), 1) = (set.iter().next(), size) else {
                 return Err(QuintError::new(
-                    "QNT505",
+                    ErrorKind::NoElement,
                     format!(
                         "Called 'getOnlyElement' on a set with {size} elements.\
                         Make sure the set has exactly one element."
                     )
                     .as_str(),
                 ));
-            }
+            };
 
-            Ok(set.iter().next().cloned().unwrap())
+            Ok(elem.clone())
         },
 
-        // Print a value to the console, and return it
-        "q::debug" => |_env, args| {
-            println!("> {} {}", args[0].as_str(), args[1]);
+        // Output a value, i.e. print it to the console, and return it
+        "q::debug" => |env, args| {
+            env.output.debug(&args[0].try_str()?, &args[1]);
             Ok(args[1].clone())
         },
 
         // `allLists` is not supported in the REPL, but we have `allListsUpTo`
         "allLists" => |_env, _args| {
             Err(QuintError::new(
-                "QNT501",
+                ErrorKind::Unsupported,
                 "Runtime does not support allLists. Use `allListsUpTo` instead",
             ))
         },
 
+        // Deterministically choose one element of a set: its minimum
+        "chooseSome" => |_env, args| {
+            args[0].min_element().ok_or_else(|| {
+                QuintError::new(ErrorKind::EmptySet, "Applied chooseSome on an empty set")
+            })
+        },
+
+        // Draws from common distributions, to model probabilistic workloads.
+        // As with `oneOf`, deterministic picks give the most likely value,
+        // and exhaustive ones go over all the values, when there are finitely
+        // many.
+        "uniform" => |env, args| {
+            let (low, high) = (args[0].try_int()?, args[1].try_int()?);
+            if low > high {
+                return Err(invalid_distribution(&format!("uniform({low}, {high})")));
+            }
+            pick_element(env, &Value::Interval(low, high))
+        },
+        "bernoulli" => |env, args| {
+            let (numerator, denominator) = (args[0].try_int()?, args[1].try_int()?);
+            if denominator <= 0 || !(0..=denominator).contains(&numerator) {
+                return Err(invalid_distribution(&format!(
+                    "bernoulli({numerator}, {denominator})"
+                )));
+            }
+            Ok(Value::Bool(draw(env, numerator, denominator)))
+        },
+        "geometric" => |env, args| {
+            let mean = args[0].try_int()?;
+            let Ok(mean) = u32::try_from(mean) else {
+                return Err(invalid_distribution(&format!("geometric({mean})")));
+            };
+            match env.pick_mode {
+                PickMode::Deterministic => Ok(Value::Int(0)),
+                PickMode::Exhaustive => Err(QuintError::new(
+                    ErrorKind::InfiniteSet,
+                    "geometric has infinitely many values, which can't be explored",
+                )),
+                PickMode::Random => Ok(Value::Int(
+                    Distribution::Geometric { mean }.sample_nat(&mut env.rand),
+                )),
+            }
+        },
+        "zipf" => |env, args| {
+            let (n, exponent) = (args[0].try_int()?, args[1].try_int()?);
+            if n < 1 || exponent < 0 {
+                return Err(invalid_distribution(&format!("zipf({n}, {exponent})")));
+            }
+            let k = match env.pick_mode {
+                PickMode::Deterministic => 1,
+                PickMode::Exhaustive => 1 + env.choices.next(n as usize) as i64,
+                PickMode::Random => sample_zipf(&mut env.rand, n as u64, exponent as f64) as i64,
+            };
+            Ok(Value::Int(k))
+        },
+
+        // A new identifier, distinct from all the others given in this run
+        "fresh" => |env, _args| {
+            let id = env.next_fresh;
+            env.next_fresh += 1;
+            Ok(Value::Int(id))
+        },
+
         // These are not supported in the REPL
-        "chooseSome" | "always" | "eventually" | "enabled" | "orKeep" | "mustChange"
-        | "weakFair" | "strongFair" => |_env, _args| {
+        "always" | "eventually" | "enabled" | "orKeep" | "mustChange" | "weakFair"
+        | "strongFair" => |_env, _args| {
             Err(QuintError::new(
-                "QNT501",
+                ErrorKind::Unsupported,
                 "Runtime does not support this built-in operator",
             ))
         },
 
         _ => {
-            panic!("Unknown eager op: {op}")
+            let error = unknown_op(op);
+            return CompiledExprWithArgs::new(move |_env, _args| Err(error.clone()));
+        }
+    })
+}
+
+/// Whether the value has an operator (i.e. a lambda) in it, which can't be
+/// hashed. Sets, and the keys of maps, never do, as they hash their elements.
+fn has_operator(value: &Value) -> bool {
+    match value {
+        Value::Lambda(..) => true,
+        Value::Tuple(elems) | Value::List(elems) => elems.iter().any(has_operator),
+        Value::Record(fields) => fields.values().any(has_operator),
+        Value::Map(map) => map.values().any(has_operator),
+        Value::Variant(_, value) => has_operator(value),
+        _ => false,
+    }
+}
+
+/// Check that a value is a set, as the operators that build intermediate sets
+/// from other sets (i.e. `tuples`) do, so that their components can be
+/// enumerated later on.
+fn expect_set(value: &Value) -> Result<&Value, QuintError> {
+    if !value.is_set() {
+        return Err(unexpected_kind("a set", value));
+    }
+    Ok(value)
+}
+
+/// Check that a set can be enumerated, returning an error for infinite sets
+/// (i.e. `Int` and `Nat`), which only support membership checks and picks, and
+/// for sets too large to be counted.
+fn enumerable(set: &Value) -> Result<&Value, QuintError> {
+    let set = expect_set(set)?;
+    if set.is_infinite() {
+        return Err(QuintError::new(
+            ErrorKind::InfiniteSet,
+            format!("Infinite set {set} is non-enumerable").as_str(),
+        )
+        .with_value(set));
+    }
+    if set.checked_cardinality().is_none() {
+        return Err(QuintError::new(
+            ErrorKind::InfiniteSet,
+            format!("Set {set} is too large to be enumerated").as_str(),
+        )
+        .with_value(set));
+    }
+
+    Ok(set)
+}
+
+/// Enumerate a set in memory (see [`Value::as_set`]), if it's [`enumerable`]
+/// and not over the limit of strict enumeration (see [`enumeration`]), which
+/// is checked before building it.
+fn enumerate(set: &Value) -> Result<Cow<'_, ImmutableSet<Value>>, QuintError> {
+    enumeration::check_size(enumerable(set)?)?;
+    Ok(set.as_set())
+}
+
+/// The error for operators that are not built-ins. Malformed IR is reported
+/// before compiling (see [`crate::validator`]), so this fails when applied
+/// instead of when compiling.
+fn unknown_op(op: &str) -> QuintError {
+    QuintError::new(
+        ErrorKind::Unsupported,
+        format!("Unknown built-in operator {op}").as_str(),
+    )
+}
+
+/// Compare the size of a set with an integer, without converting the size to
+/// an integer, so that comparisons like `size(S) > 10` hold even when `S` is
+/// too large to be counted, i.e. the powerset of a powerset (see
+/// [`Cardinality`]).
+pub fn compare_size(set: &Value, n: i64) -> Result<Ordering, QuintError> {
+    let cardinality = expect_set(set)?.symbolic_cardinality();
+    if cardinality == Cardinality::Infinite {
+        return Err(infinite_cardinality(set));
+    }
+
+    cardinality
+        .partial_cmp(&n)
+        .ok_or_else(|| integer_overflow("size").with_value(set))
+}
+
+fn infinite_cardinality(set: &Value) -> QuintError {
+    QuintError::new(
+        ErrorKind::InfiniteSet,
+        format!("Cardinality of {set} is infinite").as_str(),
+    )
+    .with_value(set)
+}
+
+/// The least (with `Ordering::Less`) or greatest element of a set, under the
+/// total order of values. The bounds of intervals are taken as they are, and
+/// other sets are iterated over, so intermediate sets are not enumerated.
+fn extreme_element(set: &Value, order: Ordering, op: &str) -> EvalResult {
+    let extreme = match set {
+        Value::Interval(start, end) if start <= end => {
+            Some(Value::Int(if order == Ordering::Less {
+                *start
+            } else {
+                *end
+            }))
         }
+        _ => enumerable(set)?
+            .iter_set()
+            .reduce(|extreme, v| if v.cmp(&extreme) == order { v } else { extreme }),
+    };
+    extreme.ok_or_else(|| {
+        QuintError::new(
+            ErrorKind::NoElement,
+            format!("Called '{op}' on an empty set").as_str(),
+        )
     })
 }
 
+/// The sum of a set of integers. The sum of an interval is computed in closed
+/// form, and other sets are iterated over.
+fn sum(set: &Value) -> EvalResult {
+    match set {
+        Value::Interval(start, end) if start <= end => {
+            let (first, last) = (i128::from(*start), i128::from(*end));
+            // Either the sum of the bounds or the number of elements is even
+            (first + last)
+                .checked_mul(last - first + 1)
+                .and_then(|doubled| i64::try_from(doubled / 2).ok())
+                .map(Value::Int)
+                .ok_or_else(|| integer_overflow(&format!("the sum of {start}.to({end})")))
+        }
+        _ => enumerable(set)?
+            .iter_set()
+            .try_fold(0_i64, |acc, v| {
+                acc.checked_add(v.try_int()?)
+                    .ok_or_else(|| integer_overflow("sum"))
+            })
+            .map(Value::Int),
+    }
+}
+
+/// The number of bits to shift an integer by, which can't be negative.
+fn shift_amount(n: &Value, op: &str) -> Result<u32, QuintError> {
+    let n = n.try_int()?;
+    if n < 0 {
+        return Err(QuintError::new(
+            ErrorKind::Arithmetic,
+            format!("{op}({n}) is undefined for negative amounts").as_str(),
+        ));
+    }
+    Ok(n.min(64) as u32)
+}
+
+/// The error for draws from distributions with invalid parameters.
+fn invalid_distribution(draw: &str) -> QuintError {
+    QuintError::new(
+        ErrorKind::InvalidArgument,
+        format!("Invalid parameters in {draw}").as_str(),
+    )
+}
+
+/// The error for integer operations whose result does not fit in 64 bits.
+/// Quint integers are unbounded, so we can't give a result that wraps around.
+fn integer_overflow(operation: &str) -> QuintError {
+    QuintError::new(
+        ErrorKind::Arithmetic,
+        format!("Integer overflow in {operation}: the result does not fit in 64 bits").as_str(),
+    )
+}
+
 /// Utility to fold iterables from left to right. This exists mostly to keep a
 /// symmetry with `fold_right`, which is a bit more interesting.
 fn fold_left<T>(
@@ -825,19 +1486,19 @@ mod tests {
             list.into_iter(),
             Value::List(ImmutableVec::new()),
             |acc, arg| {
-                let mut acc = acc.as_list().clone();
+                let mut acc = acc.try_list()?.clone();
                 acc.push_front(arg);
                 Ok(Value::List(acc))
             },
         );
 
         assert_eq!(
-            result.unwrap(),
-            Value::List(ImmutableVec::from(vec![
+            result,
+            Ok(Value::List(ImmutableVec::from(vec![
                 Value::Int(3),
                 Value::Int(2),
                 Value::Int(1),
-            ]))
+            ])))
         );
     }
 
@@ -850,19 +1511,19 @@ mod tests {
             list.into_iter(),
             Value::List(ImmutableVec::new()),
             |arg, acc| {
-                let mut acc = acc.as_list().clone();
+                let mut acc = acc.try_list()?.clone();
                 acc.push_front(arg);
                 Ok(Value::List(acc))
             },
         );
 
         assert_eq!(
-            result.unwrap(),
-            Value::List(ImmutableVec::from(vec![
+            result,
+            Ok(Value::List(ImmutableVec::from(vec![
                 Value::Int(1),
                 Value::Int(2),
                 Value::Int(3)
-            ]))
+            ])))
         );
     }
 }
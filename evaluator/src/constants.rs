@@ -0,0 +1,240 @@
+//! Instantiation of the constants of the main module at run time, with values
+//! given as JSON (i.e. in a config file or in CLI flags), so the same spec can
+//! be run with different parameters without editing it (see
+//! [`crate::evaluator::Interpreter::set_constants`]).
+//!
+//! Values are read by the types annotated in the declarations of the
+//! constants, and rejected if they don't have those types:
+//!  - integers are JSON numbers, or strings with their digits for integers
+//!    that don't fit in JSON numbers;
+//!  - booleans and strings are JSON booleans and strings;
+//!  - sets, lists and tuples are arrays;
+//!  - records are objects with their fields;
+//!  - maps are arrays of `[key, value]` pairs, or objects if the keys are
+//!    strings;
+//!  - variants are objects with a `tag` and a `value`, or just the name of the
+//!    tag for variants without a value.
+//!
+//! Values in ITF (see [`crate::itf`]) are also accepted, e.g. sets written as
+//! `{"#set": [...]}`. Values of types that can't be checked (type aliases and
+//! type variables) must be written in ITF.
+//!
+//! Library users can also give the values directly, which are checked
+//! against the same types (see [`check_value`] and
+//! [`crate::evaluator::Interpreter::set_constant_values`]).
+
+use crate::ir::QuintType;
+use crate::symbol::Symbol;
+use crate::value::{ImmutableMap, Value};
+use serde_json::Value as Json;
+use std::rc::Rc;
+
+/// Read a value of type `typ` from JSON, or from ITF if the type is not known.
+pub fn value_from_json(json: &Json, typ: Option<&QuintType>) -> Result<Value, String> {
+    let mismatch = |expected: &str| format!("expected {expected}, got {json}");
+
+    match typ {
+        None | Some(QuintType::Other) => Value::from_itf(json).map_err(|e| e.to_string()),
+        Some(QuintType::Bool) => json
+            .as_bool()
+            .map(Value::Bool)
+            .ok_or_else(|| mismatch("a boolean")),
+        Some(QuintType::Int) => match itf(json, "#bigint").unwrap_or(json) {
+            Json::Number(n) => n.as_i64(),
+            Json::String(s) => s.parse().ok(),
+            _ => None,
+        }
+        .map(Value::Int)
+        .ok_or_else(|| mismatch("a 64-bit integer")),
+        Some(QuintType::Str) => json
+            .as_str()
+            .map(|s| Value::Str(s.into()))
+            .ok_or_else(|| mismatch("a string")),
+        Some(QuintType::Set { elem }) => array(json, "#set")
+            .ok_or_else(|| mismatch("a set"))?
+            .iter()
+            .map(|e| value_from_json(e, Some(elem)).map(Value::normalize))
+            .collect::<Result<_, _>>()
+            .map(Value::Set),
+        Some(QuintType::List { elem }) => json
+            .as_array()
+            .ok_or_else(|| mismatch("a list"))?
+            .iter()
+            .map(|e| value_from_json(e, Some(elem)))
+            .collect::<Result<_, _>>()
+            .map(Value::List),
+        Some(QuintType::Tup { fields }) => {
+            let (fields, _) = fields.fields();
+            let elems = array(json, "#tup")
+                .filter(|elems| elems.len() == fields.len())
+                .ok_or_else(|| mismatch(&format!("a tuple of {} elements", fields.len())))?;
+            elems
+                .iter()
+                .zip(fields)
+                .map(|(e, field)| value_from_json(e, Some(&field.field_type)))
+                .collect::<Result<_, _>>()
+                .map(Value::Tuple)
+        }
+        Some(QuintType::Fun { arg, res }) => {
+            if let (QuintType::Str, Some(entries)) = (arg.as_ref(), json.as_object()) {
+                return entries
+                    .iter()
+                    .map(|(k, v)| {
+                        Ok((
+                            Value::Str(k.as_str().into()),
+                            value_from_json(v, Some(res))?,
+                        ))
+                    })
+                    .collect::<Result<_, String>>()
+                    .map(Value::Map);
+            }
+            array(json, "#map")
+                .ok_or_else(|| mismatch("a map"))?
+                .iter()
+                .map(|entry| match entry.as_array().map(Vec::as_slice) {
+                    Some([k, v]) => Ok((
+                        value_from_json(k, Some(arg))?.normalize(),
+                        value_from_json(v, Some(res))?,
+                    )),
+                    _ => Err(format!("expected a [key, value] pair, got {entry}")),
+                })
+                .collect::<Result<_, _>>()
+                .map(Value::Map)
+        }
+        Some(QuintType::Rec { fields }) => {
+            let entries = json.as_object().ok_or_else(|| mismatch("a record"))?;
+            let (fields, open) = fields.fields();
+            let mut record = ImmutableMap::default();
+            for field in &fields {
+                let value = entries
+                    .get(field.field_name.as_str())
+                    .ok_or_else(|| format!("missing field {} in {json}", field.field_name))?;
+                record.insert(
+                    Symbol::from(&field.field_name),
+                    value_from_json(value, Some(&field.field_type))?,
+                );
+            }
+            for (name, value) in entries {
+                if fields.iter().all(|f| f.field_name.as_str() != name) {
+                    if !open {
+                        return Err(format!("unexpected field {name} in {json}"));
+                    }
+                    record.insert(name.as_str().into(), value_from_json(value, None)?);
+                }
+            }
+            Ok(Value::Record(record))
+        }
+        Some(QuintType::Sum { fields }) => {
+            let (tag, value) = match json {
+                Json::String(tag) => (tag.as_str(), None),
+                Json::Object(entries) if entries.len() == 2 => {
+                    match (entries.get("tag"), entries.get("value")) {
+                        (Some(Json::String(tag)), Some(value)) => (tag.as_str(), Some(value)),
+                        _ => return Err(mismatch("a variant")),
+                    }
+                }
+                _ => return Err(mismatch("a variant")),
+            };
+            let (variants, _) = fields.fields();
+            let variant = variants
+                .iter()
+                .find(|v| v.field_name.as_str() == tag)
+                .ok_or_else(|| format!("unknown variant {tag} in {json}"))?;
+            let value = match value {
+                Some(value) => value_from_json(value, Some(&variant.field_type))?,
+                None if is_unit(&variant.field_type) => Value::Tuple(Default::default()),
+                None => return Err(format!("missing the value of variant {tag}")),
+            };
+            Ok(Value::Variant(tag.into(), Rc::new(value)))
+        }
+    }
+}
+
+/// Check that a value has type `typ`, if the type is known.
+pub fn check_value(value: &Value, typ: Option<&QuintType>) -> Result<(), String> {
+    let mismatch = |expected: &str| Err(format!("expected {expected}, got {value}"));
+
+    match (typ, value) {
+        (None | Some(QuintType::Other), _) => Ok(()),
+        (Some(QuintType::Bool), Value::Bool(_))
+        | (Some(QuintType::Int), Value::Int(_))
+        | (Some(QuintType::Str), Value::Str(_)) => Ok(()),
+        (Some(QuintType::Bool), _) => mismatch("a boolean"),
+        (Some(QuintType::Int), _) => mismatch("a 64-bit integer"),
+        (Some(QuintType::Str), _) => mismatch("a string"),
+        // Infinite sets can't be enumerated, and only come from the built-in
+        // sets of the right types anyway
+        (Some(QuintType::Set { .. }), set) if set.is_infinite() => Ok(()),
+        (Some(QuintType::Set { elem }), set) if set.is_set() => {
+            set.iter_set().try_for_each(|e| check_value(&e, Some(elem)))
+        }
+        (Some(QuintType::Set { .. }), _) => mismatch("a set"),
+        (Some(QuintType::List { elem }), Value::List(elems)) => {
+            elems.iter().try_for_each(|e| check_value(e, Some(elem)))
+        }
+        (Some(QuintType::List { .. }), _) => mismatch("a list"),
+        (Some(QuintType::Tup { fields }), Value::Tuple(elems))
+            if elems.len() == fields.fields().0.len() =>
+        {
+            elems
+                .iter()
+                .zip(fields.fields().0)
+                .try_for_each(|(e, field)| check_value(e, Some(&field.field_type)))
+        }
+        (Some(QuintType::Tup { fields }), _) => {
+            mismatch(&format!("a tuple of {} elements", fields.fields().0.len()))
+        }
+        (Some(QuintType::Fun { arg, res }), Value::Map(map)) => {
+            map.iter().try_for_each(|(k, v)| {
+                check_value(k, Some(arg))?;
+                check_value(v, Some(res))
+            })
+        }
+        (Some(QuintType::Fun { .. }), _) => mismatch("a map"),
+        (Some(QuintType::Rec { fields }), Value::Record(record)) => {
+            let (fields, open) = fields.fields();
+            for field in &fields {
+                let value = record
+                    .get(&Symbol::from(&field.field_name))
+                    .ok_or_else(|| format!("missing field {} in {value}", field.field_name))?;
+                check_value(value, Some(&field.field_type))?;
+            }
+            match record
+                .keys()
+                .find(|name| fields.iter().all(|f| **name != *f.field_name))
+            {
+                Some(name) if !open => Err(format!("unexpected field {name} in {value}")),
+                _ => Ok(()),
+            }
+        }
+        (Some(QuintType::Rec { .. }), _) => mismatch("a record"),
+        (Some(QuintType::Sum { fields }), Value::Variant(tag, payload)) => {
+            let (variants, _) = fields.fields();
+            let variant = variants
+                .iter()
+                .find(|v| *tag == *v.field_name)
+                .ok_or_else(|| format!("unknown variant {tag} in {value}"))?;
+            check_value(payload, Some(&variant.field_type))
+        }
+        (Some(QuintType::Sum { .. }), _) => mismatch("a variant"),
+    }
+}
+
+/// The content of an ITF object with a single `key`, e.g. `{"#set": [...]}`.
+fn itf<'a>(json: &'a Json, key: &str) -> Option<&'a Json> {
+    json.as_object()
+        .filter(|entries| entries.len() == 1)
+        .and_then(|entries| entries.get(key))
+}
+
+/// The elements of an array, or of an ITF object with a single `key`.
+fn array<'a>(json: &'a Json, key: &str) -> Option<&'a Vec<Json>> {
+    json.as_array()
+        .or_else(|| itf(json, key).and_then(Json::as_array))
+}
+
+/// Whether the type is the unit type, i.e. the type of variants without a
+/// value.
+fn is_unit(typ: &QuintType) -> bool {
+    matches!(typ, QuintType::Tup { fields } if fields.fields().0.is_empty())
+}
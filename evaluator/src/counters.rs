@@ -0,0 +1,162 @@
+//! Opt-in counters for evaluation statistics, used to track performance
+//! regressions.
+//!
+//! Counting is disabled by default, as it adds a (small) cost to the hot paths
+//! of the evaluator. Call [`set_enabled`] before a run, and [`get`] after it to
+//! retrieve the counters. The counters, and whether they are enabled, are
+//! thread-local, as evaluation happens on a single thread, so that runs on
+//! other threads (e.g. tests, or requests to the server) aren't counted.
+//! Parallel simulations enable counting in their workers if it's enabled on
+//! the thread that starts them.
+//!
+//! Measuring how much of each state is shared with the previous one (see
+//! [`crate::sharing`]) goes over all of the values of the state, so it is only
+//! done if enabled separately, with [`set_sharing_enabled`].
+
+use serde::Serialize;
+use std::cell::Cell;
+
+use crate::value::Value;
+
+thread_local! {
+    static ENABLED: Cell<bool> = const { Cell::new(false) };
+    static SHARING_ENABLED: Cell<bool> = const { Cell::new(false) };
+    static COUNTERS: Cell<Counters> = Cell::new(Counters::default());
+}
+
+/// Statistics collected during evaluation.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Counters {
+    /// Number of applications of operators, both built-in and user-defined.
+    pub operator_applications: u64,
+    /// Number of times a lazy set (i.e. an interval) had to be enumerated.
+    pub set_enumerations: u64,
+    /// Number of elements produced when enumerating lazy sets.
+    pub elements_enumerated: u64,
+    /// Number of times a cached value was re-used.
+    pub cache_hits: u64,
+    /// Number of times a cacheable value had to be computed.
+    pub cache_misses: u64,
+    /// Number of collection values (sets, maps, lists, tuples and records)
+    /// produced by built-in operators.
+    pub allocations: u64,
+    /// Number of state shifts, i.e. steps taken.
+    pub steps: u64,
+    /// Number of values cloned.
+    pub values_cloned: u64,
+    /// Estimated bytes of the states shared with the previous states.
+    pub bytes_shared: u64,
+    /// Estimated bytes of the states copied or built anew on each step.
+    pub bytes_copied: u64,
+}
+
+impl std::ops::AddAssign for Counters {
+    /// Sum counters, i.e. to merge the counters from different threads.
+    fn add_assign(&mut self, other: Self) {
+        self.operator_applications += other.operator_applications;
+        self.set_enumerations += other.set_enumerations;
+        self.elements_enumerated += other.elements_enumerated;
+        self.cache_hits += other.cache_hits;
+        self.cache_misses += other.cache_misses;
+        self.allocations += other.allocations;
+        self.steps += other.steps;
+        self.values_cloned += other.values_cloned;
+        self.bytes_shared += other.bytes_shared;
+        self.bytes_copied += other.bytes_copied;
+    }
+}
+
+impl Counters {
+    /// The average number of allocations per step.
+    pub fn allocations_per_step(&self) -> f64 {
+        if self.steps == 0 {
+            return self.allocations as f64;
+        }
+
+        self.allocations as f64 / self.steps as f64
+    }
+
+    /// The average number of values cloned per step.
+    pub fn clones_per_step(&self) -> f64 {
+        if self.steps == 0 {
+            return self.values_cloned as f64;
+        }
+
+        self.values_cloned as f64 / self.steps as f64
+    }
+
+    /// The average number of bytes copied per step, when measuring sharing.
+    pub fn bytes_copied_per_step(&self) -> f64 {
+        if self.steps == 0 {
+            return self.bytes_copied as f64;
+        }
+
+        self.bytes_copied as f64 / self.steps as f64
+    }
+
+    /// The fraction of the bytes of states shared with the previous states,
+    /// when measuring sharing.
+    pub fn shared_fraction(&self) -> f64 {
+        let total = self.bytes_shared + self.bytes_copied;
+        if total == 0 {
+            return 0.0;
+        }
+
+        self.bytes_shared as f64 / total as f64
+    }
+}
+
+pub fn set_enabled(value: bool) {
+    ENABLED.with(|enabled| enabled.set(value));
+}
+
+#[inline]
+pub fn is_enabled() -> bool {
+    ENABLED.with(|enabled| enabled.get())
+}
+
+pub fn set_sharing_enabled(value: bool) {
+    SHARING_ENABLED.with(|enabled| enabled.set(value));
+}
+
+pub fn is_sharing_enabled() -> bool {
+    is_enabled() && SHARING_ENABLED.with(|enabled| enabled.get())
+}
+
+/// Update the counters with the given function, if counting is enabled.
+#[inline]
+pub fn record(update: impl FnOnce(&mut Counters)) {
+    if !is_enabled() {
+        return;
+    }
+
+    COUNTERS.with(|counters| {
+        let mut value = counters.get();
+        update(&mut value);
+        counters.set(value);
+    });
+}
+
+/// Count an allocation if the value is a collection.
+#[inline]
+pub fn record_allocation(value: &Value) {
+    record(|c| {
+        if matches!(
+            value,
+            Value::Set(_) | Value::Map(_) | Value::List(_) | Value::Tuple(_) | Value::Record(_)
+        ) {
+            c.allocations += 1;
+        }
+    });
+}
+
+/// Get the current value of the counters.
+pub fn get() -> Counters {
+    COUNTERS.with(|counters| counters.get())
+}
+
+/// Reset all counters to zero.
+pub fn reset() {
+    COUNTERS.with(|counters| counters.set(Counters::default()));
+}
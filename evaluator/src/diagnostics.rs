@@ -0,0 +1,218 @@
+//! Diagnostics for the errors found when compiling and evaluating specs, in the
+//! shape of the diagnostics of the Language Server Protocol (LSP), so editors
+//! and CI can annotate specs with them instead of scraping the output of the
+//! CLI.
+//!
+//! A diagnostic is the LSP `Diagnostic` object, i.e. `{"range", "severity",
+//! "code", "source", "message", "relatedInformation"}`, with the `file` the
+//! range is in (which LSP gives separately). Ranges are 0-based and their end
+//! is exclusive, as in LSP. Errors without a known location are at the start
+//! of an unknown file.
+//!
+//! Evaluation errors reference the expressions where they happened by their
+//! ids, which are located with the source map written by `quint parse
+//! --source-map` (see [`SourceMap`]). Compiled expressions keep the ids of the
+//! expressions they were compiled from, so the actions taken on trace steps
+//! can be located in the same way. Compilation errors are read from the JSON
+//! output of the Quint CLI, where they are already located.
+
+use crate::ir::{QuintError, QuintId};
+use fxhash::FxHashMap;
+use serde::{Serialize, Serializer};
+use serde_json::Value as Json;
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A diagnostic, in the shape of an LSP diagnostic with its file.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Diagnostic {
+    /// The file of the range, if known.
+    pub file: Option<String>,
+    pub range: Range,
+    pub severity: Severity,
+    /// The code of the error, e.g. `QNT503`.
+    pub code: String,
+    /// Always `quint`.
+    pub source: String,
+    pub message: String,
+    /// Other locations relevant to the diagnostic.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub related_information: Vec<RelatedInformation>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Position {
+    pub line: u64,
+    pub character: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+/// A range in a file, as the LSP `Location`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Location {
+    pub uri: String,
+    pub range: Range,
+}
+
+/// Shown as `<file>:<line>:<column>`, with 1-based lines and columns, as
+/// editors and terminals recognize them.
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Position { line, character } = self.range.start;
+        write!(f, "{}:{}:{}", self.uri, line + 1, character + 1)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RelatedInformation {
+    pub location: Location,
+    pub message: String,
+}
+
+/// The severity of a diagnostic, serialized as its LSP number.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    Error = 1,
+    Warning = 2,
+    Information = 3,
+    Hint = 4,
+}
+
+impl Serialize for Severity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*self as u8)
+    }
+}
+
+/// The locations of the expressions (and other IR components) of a spec, by
+/// their ids.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    locations: FxHashMap<QuintId, Location>,
+}
+
+impl SourceMap {
+    /// Read the compact source map written by `quint parse --source-map`,
+    /// i.e. `{"sourceIndex": {"<index>": "<file>"}, "map": {"<id>": [<index>,
+    /// <start>, <end>]}}`, with the start and end positions of Quint
+    /// locations.
+    pub fn from_json(json: &Json) -> Result<SourceMap, String> {
+        let invalid = || format!("Invalid source map: {json}");
+        let sources = json["sourceIndex"].as_object().ok_or_else(invalid)?;
+        let locations = json["map"]
+            .as_object()
+            .ok_or_else(invalid)?
+            .iter()
+            .map(|(id, entry)| {
+                let id = id.parse().map_err(|_| invalid())?;
+                let file = sources
+                    .get(&entry[0].to_string())
+                    .and_then(Json::as_str)
+                    .ok_or_else(invalid)?;
+                let range = read_range(&entry[1], &entry[2]).ok_or_else(invalid)?;
+                Ok((
+                    id,
+                    Location {
+                        uri: file.to_string(),
+                        range,
+                    },
+                ))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(SourceMap { locations })
+    }
+
+    pub fn get(&self, id: QuintId) -> Option<&Location> {
+        self.locations.get(&id)
+    }
+
+    /// The files of the located components, i.e. the file of a spec and the
+    /// files of the modules it imports.
+    pub fn files(&self) -> BTreeSet<&str> {
+        self.locations.values().map(|l| l.uri.as_str()).collect()
+    }
+}
+
+impl Diagnostic {
+    /// The diagnostic for an error, located at the expression it references.
+    pub fn from_error(error: &QuintError, source_map: &SourceMap) -> Diagnostic {
+        let location = error.reference.and_then(|id| source_map.get(id));
+        Diagnostic {
+            file: location.map(|l| l.uri.clone()),
+            range: location.map(|l| l.range).unwrap_or_default(),
+            severity: Severity::Error,
+            code: error.code().to_string(),
+            source: "quint".to_string(),
+            message: error.message.clone(),
+            related_information: Vec::new(),
+        }
+    }
+
+    /// The location of the diagnostic, if its file is known.
+    pub fn location(&self) -> Option<Location> {
+        self.file.as_ref().map(|file| Location {
+            uri: file.clone(),
+            range: self.range,
+        })
+    }
+
+    /// The diagnostic for an error message in the JSON output of the Quint
+    /// CLI, i.e. `{"explanation": "[<code>] <message>", "locs": [...]}`, at
+    /// its first location, with the other locations as related information.
+    pub fn from_error_message(error: &Json) -> Diagnostic {
+        let explanation = error["explanation"].as_str().unwrap_or_default();
+        let (code, message) = explanation
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once("] "))
+            .unwrap_or(("", explanation));
+        let mut locations = error["locs"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter_map(|loc| {
+                Some(Location {
+                    uri: loc["source"].as_str()?.to_string(),
+                    range: read_range(&loc["start"], &loc["end"])?,
+                })
+            });
+        let location = locations.next();
+        Diagnostic {
+            file: location.as_ref().map(|l| l.uri.clone()),
+            range: location.map(|l| l.range).unwrap_or_default(),
+            severity: Severity::Error,
+            code: code.to_string(),
+            source: "quint".to_string(),
+            message: message.to_string(),
+            related_information: locations
+                .map(|location| RelatedInformation {
+                    location,
+                    message: message.to_string(),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Read the range of a Quint location, from its `start` and `end` positions
+/// (`{"line", "col", "index"}`). Quint positions are 0-based, as in LSP, but
+/// the end is inclusive, and it may be missing (i.e. `{}`).
+fn read_range(start: &Json, end: &Json) -> Option<Range> {
+    let position = |pos: &Json| {
+        Some(Position {
+            line: pos["line"].as_u64()?,
+            character: pos["col"].as_u64()?,
+        })
+    };
+    let start = position(start)?;
+    let end = position(end).map_or(start, |end| Position {
+        character: end.character + 1,
+        ..end
+    });
+    Some(Range { start, end })
+}
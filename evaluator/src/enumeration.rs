@@ -0,0 +1,76 @@
+//! Strict enumeration: an optional limit on the size of the intermediate sets
+//! (i.e. intervals and powersets, see [`crate::value`]) that are enumerated
+//! implicitly, when an operator needs all of their elements in memory. It's
+//! meant for development, to find blowups like `1.to(10000000).union(S)`
+//! early, rather than hours into a long simulation.
+//!
+//! Built-in operators check the size of a set against the limit before
+//! enumerating it (see [`check_size`]), so sets over the limit are never
+//! built. Other enumerations go through [`crate::value::Value::as_set`], which
+//! can't fail, so the ones over the limit are recorded here, and the evaluator
+//! turns them into a QNT514 error after the operator that caused them,
+//! pointing at it. Sets iterated over on demand (see
+//! [`crate::value::Value::iter_set`]) are not limited, as they are never built
+//! in memory.
+//!
+//! The limit is thread-local, like the [`crate::counters`], as evaluation
+//! happens on a single thread. Simulations set it from
+//! [`crate::simulator::SimulatorConfig::max_enumeration`] on every worker.
+
+use crate::ir::{ErrorKind, QuintError};
+use crate::value::Value;
+use std::cell::Cell;
+
+thread_local! {
+    static LIMIT: Cell<Option<usize>> = const { Cell::new(None) };
+    // The size of the first set enumerated over the limit since the last check
+    static EXCEEDED: Cell<Option<usize>> = const { Cell::new(None) };
+}
+
+/// Set the maximum number of elements of a set enumerated implicitly on this
+/// thread, or remove the limit with `None`.
+pub fn set_limit(limit: Option<usize>) {
+    LIMIT.set(limit);
+    EXCEEDED.set(None);
+}
+
+/// The limit set with [`set_limit`], if any.
+pub fn limit() -> Option<usize> {
+    LIMIT.get()
+}
+
+/// Record the enumeration of an intermediate set with `size` elements.
+pub(crate) fn record(size: usize) {
+    if limit().is_some_and(|limit| size > limit) && EXCEEDED.get().is_none() {
+        EXCEEDED.set(Some(size));
+    }
+}
+
+/// Fail if enumerating `set` would go over the limit, before enumerating it.
+/// Sets that are already in memory (i.e. [`Value::Set`]) are not limited.
+pub(crate) fn check_size(set: &Value) -> Result<(), QuintError> {
+    if matches!(set, Value::Set(_)) || !set.is_set() {
+        return Ok(());
+    }
+    match limit() {
+        // Without the set as the value of the error, as showing it would
+        // enumerate it
+        Some(limit) if set.cardinality() > limit => Err(exceeded(set.cardinality(), limit)),
+        _ => Ok(()),
+    }
+}
+
+/// Fail if a set was enumerated over the limit since the last check.
+pub fn check() -> Result<(), QuintError> {
+    match EXCEEDED.take() {
+        Some(size) => Err(exceeded(size, limit().unwrap_or_default())),
+        None => Ok(()),
+    }
+}
+
+fn exceeded(size: usize, limit: usize) -> QuintError {
+    QuintError::new(
+        ErrorKind::InfiniteSet,
+        format!("Enumerating a set of {size} elements, over the limit of {limit}").as_str(),
+    )
+}
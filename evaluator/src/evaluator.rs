@@ -3,20 +3,48 @@
 //! Includes the compilation types and stateful datastructures used for
 //! memoization, caching, state variable storage, etc.
 
+use crate::constants;
+use crate::counters;
+use crate::enumeration;
+use crate::fairness::Fairness;
+use crate::output::Output;
+use crate::picker::{Choices, Distribution, PickMode};
 use crate::rand::Rand;
-use crate::storage::{Storage, VariableRegister};
+use crate::reflection;
+use crate::representation::Representation;
+use crate::storage::{StateSnapshot, Storage, VariableRegister};
+use crate::trace::{Alternative, StepMeta};
 use crate::{builtins::*, ir::*, value::*};
-use fxhash::FxHashMap;
-use std::cell::RefCell;
+use fxhash::{FxHashMap, FxHashSet};
+use serde_json::Value as Json;
+use std::cell::{OnceCell, RefCell};
+use std::cmp::Ordering;
 use std::fmt;
+use std::hash::Hasher;
 use std::rc::Rc;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
 
 /// The result of evaluating a Quint expression: either a [`Value`] or an error.
 pub type EvalResult = Result<Value, QuintError>;
 
+/// How many times a recursive definition can be called with the same
+/// arguments within its own evaluation, unless set otherwise (see
+/// [`Interpreter::set_max_repeated_calls`]).
+pub const DEFAULT_MAX_REPEATED_CALLS: usize = 3;
+
 /// A compiled expression that can be executed in a given environment.
+///
+/// Expressions compiled from the IR keep the id of the expression (or
+/// definition) they were compiled from, so whatever happens when executing
+/// them can be located in the spec (see [`crate::diagnostics::SourceMap`]).
 #[derive(Clone)]
-pub struct CompiledExpr(Rc<dyn Fn(&mut Env) -> EvalResult>);
+pub struct CompiledExpr {
+    closure: Rc<dyn Fn(&mut Env) -> EvalResult>,
+    id: Option<QuintId>,
+}
 
 /// A compiled expression that takes arguments and can be executed in a given environment.
 #[derive(Clone)]
@@ -32,17 +60,82 @@ pub struct CompiledExprWithLazyArgs(
 
 impl CompiledExpr {
     pub fn new(closure: impl Fn(&mut Env) -> EvalResult + 'static) -> Self {
-        CompiledExpr(Rc::new(closure))
+        CompiledExpr {
+            closure: Rc::new(closure),
+            id: None,
+        }
+    }
+
+    /// The same expression, compiled from the IR component with the given id.
+    pub fn with_id(self, id: QuintId) -> Self {
+        CompiledExpr {
+            id: Some(id),
+            ..self
+        }
+    }
+
+    /// An expression that fails with the given error when it's evaluated, for
+    /// malformed IR, which is reported where it's evaluated (as operators
+    /// unknown to [`crate::builtins`] are).
+    pub fn failing(error: QuintError) -> Self {
+        CompiledExpr::new(move |_| Err(error.clone()))
+    }
+
+    /// The id of the IR component the expression was compiled from, if any.
+    pub fn id(&self) -> Option<QuintId> {
+        self.id
+    }
+
+    /// The address of the compiled closure, which clones of the expression
+    /// share. Operators can't be compared by what they compute, so lambdas
+    /// are compared by this instead.
+    pub fn address(&self) -> usize {
+        Rc::as_ptr(&self.closure) as *const () as usize
     }
 
     pub fn execute(&self, env: &mut Env) -> EvalResult {
-        self.0(env)
+        (self.closure)(env)
+    }
+
+    /// Execute the expression as an action, where a pick from an empty set
+    /// (i.e. in `nondet m = messages.filter(...).oneOf()`, when no message
+    /// matches) means that the action is disabled, instead of an error: there
+    /// is nothing it can be taken with.
+    pub fn execute_action(&self, env: &mut Env) -> EvalResult {
+        match self.execute(env) {
+            Err(error) if error.kind == ErrorKind::EmptySet => Ok(Value::Bool(false)),
+            result => result,
+        }
+    }
+
+    /// Execute the expression as an action that is one of several
+    /// alternatives, i.e. an action of `any { ... }` or a combination of
+    /// nondeterministic choices, returning whether it was enabled. Recoverable
+    /// errors (see [`ErrorKind::is_recoverable`]) only discard this
+    /// alternative: the first one is kept in `discarded`, to be reported if no
+    /// alternative is enabled.
+    pub fn execute_alternative(
+        &self,
+        env: &mut Env,
+        discarded: &mut Option<QuintError>,
+    ) -> Result<bool, QuintError> {
+        match self.execute_action(env) {
+            Ok(result) => result.try_bool(),
+            Err(error) if error.is_recoverable() => {
+                discarded.get_or_insert(error);
+                Ok(false)
+            }
+            Err(error) => Err(error),
+        }
     }
 }
 
 impl fmt::Debug for CompiledExpr {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "<CompiledExpr>")
+        match self.id {
+            Some(id) => write!(f, "<CompiledExpr #{id}>"),
+            None => write!(f, "<CompiledExpr>"),
+        }
     }
 }
 
@@ -81,8 +174,44 @@ pub struct Env {
 
     // The random number generator, used for nondeterministic choices. This is stateful.
     pub rand: Rand,
-    // TODO: trace recorder (for --verbosity) and trace collector (for proper
-    // trace tracking in runs)
+
+    // How nondeterministic choices pick elements: randomly, or always the
+    // canonical element (for reproducible, seed-independent evaluation).
+    pub pick_mode: PickMode,
+
+    // How integers are sampled when picking from the infinite sets `Int` and `Nat`.
+    pub int_distribution: Distribution,
+
+    // The choices made in the exhaustive pick mode, see `PickMode::Exhaustive`.
+    pub choices: Choices,
+
+    // The action taken and nondeterministic picks on the current step, for
+    // model-based testing (see `Interpreter::set_record_mbt`).
+    pub mbt: StepMeta,
+
+    // The recorded step being replayed, if any, whose alternatives and picks
+    // are taken in `any`s and `nondet` definitions instead of random ones
+    // (see `ParsedQuint::replay`).
+    pub replay: Option<StepMeta>,
+
+    // The steps since each fair action was taken (see
+    // `Interpreter::set_fair_actions`).
+    pub fairness: Fairness,
+
+    // The states reached by a run (see `then` and `reps`), when they are
+    // being collected (see `crate::tester`).
+    pub trace: Option<Vec<Value>>,
+
+    // The limits on the evaluation, if any (see `Budget`).
+    pub budget: Option<Budget>,
+
+    // Where the values given to `q::debug` go (see `crate::output`).
+    pub output: Output,
+
+    // The next identifier given by `fresh`, so they are all distinct within a
+    // run. Simulations start every sample from the same one, so samples can
+    // be reproduced on their own.
+    pub next_fresh: i64,
 }
 
 impl Env {
@@ -90,6 +219,16 @@ impl Env {
         Self {
             var_storage,
             rand: Rand::new(),
+            pick_mode: PickMode::default(),
+            int_distribution: Distribution::default(),
+            choices: Choices::default(),
+            mbt: StepMeta::default(),
+            replay: None,
+            fairness: Fairness::default(),
+            trace: None,
+            budget: None,
+            output: Output::default(),
+            next_fresh: 0,
         }
     }
 
@@ -98,13 +237,116 @@ impl Env {
         Self {
             var_storage,
             rand: Rand::with_state(state),
+            pick_mode: PickMode::default(),
+            int_distribution: Distribution::default(),
+            choices: Choices::default(),
+            mbt: StepMeta::default(),
+            replay: None,
+            fairness: Fairness::default(),
+            trace: None,
+            budget: None,
+            output: Output::default(),
+            next_fresh: 0,
         }
     }
 
-    /// Shift the state, moving `next_vars` to `vars`.
+    /// Shift the state, making the primed values current.
     pub fn shift(&mut self) {
         self.var_storage.borrow_mut().shift_vars();
     }
+
+    /// Shift the state like [`Env::shift`], as a step of a run, adding the
+    /// new state to the trace if it is being collected.
+    pub fn shift_step(&mut self) {
+        self.shift();
+        if let Some(trace) = &mut self.trace {
+            trace.push(self.var_storage.borrow().as_record());
+        }
+    }
+
+    /// Take a snapshot of the environment: the values of all state variables
+    /// (current and next), the state of the random number generator and the
+    /// next fresh identifier.
+    ///
+    /// Restoring it with [`Env::restore`] brings the evaluation back to this
+    /// exact point between steps, which enables backtracking and undoing
+    /// them. The registers of parameters and constants are held by the
+    /// interpreter, so snapshots taken while evaluating an expression, or
+    /// across changes of constants, are taken with [`Interpreter::snapshot`].
+    pub fn snapshot(&self) -> EnvSnapshot {
+        EnvSnapshot {
+            storage: self.var_storage.borrow().take_state_snapshot(),
+            rand_state: self.rand.get_state(),
+            next_fresh: self.next_fresh,
+            registers: Vec::new(),
+        }
+    }
+
+    /// Restore the environment to a snapshot taken with [`Env::snapshot`],
+    /// and the registers in it if it was taken with [`Interpreter::snapshot`].
+    pub fn restore(&mut self, snapshot: &EnvSnapshot) {
+        self.var_storage
+            .borrow_mut()
+            .restore_state(&snapshot.storage);
+        self.rand.set_state(snapshot.rand_state);
+        self.next_fresh = snapshot.next_fresh;
+        for (register, value) in &snapshot.registers {
+            *register.borrow_mut() = value.clone();
+        }
+    }
+}
+
+/// A snapshot of an [`Env`], see [`Env::snapshot`].
+#[derive(Clone)]
+pub struct EnvSnapshot {
+    storage: StateSnapshot,
+    rand_state: u64,
+    next_fresh: i64,
+    // The registers of parameters and constants, with their contents
+    registers: Vec<(Rc<RefCell<EvalResult>>, EvalResult)>,
+}
+
+/// Limits on an evaluation, for callers that must stay responsive, e.g. an
+/// editor showing the values of expressions as they are typed. Going over
+/// them fails the evaluation with a QNT515 error.
+#[derive(Debug, Clone, Copy)]
+pub struct Budget {
+    /// When the evaluation must end by
+    pub deadline: Instant,
+    /// The maximum number of elements of a collection (set, map, list, tuple
+    /// or record) built by the evaluation, bounding the memory it takes.
+    /// Lazy sets (e.g. intervals) are not built, so they can be larger.
+    pub max_elements: usize,
+}
+
+impl Budget {
+    /// Check the budget after a value was computed.
+    fn check(&self, value: &Value) -> Result<(), QuintError> {
+        if Instant::now() > self.deadline {
+            return Err(QuintError::new(
+                ErrorKind::BudgetExceeded,
+                "Evaluation budget exceeded: timed out",
+            ));
+        }
+
+        let elements = match value {
+            Value::Set(set) => set.len(),
+            Value::Map(map) => map.len(),
+            Value::List(elems) | Value::Tuple(elems) => elems.len(),
+            Value::Record(fields) => fields.len(),
+            _ => 0,
+        };
+        if elements > self.max_elements {
+            return Err(QuintError::new(
+                ErrorKind::BudgetExceeded,
+                &format!(
+                    "Evaluation budget exceeded: a collection has {elements} elements, over the limit of {}",
+                    self.max_elements
+                ),
+            ));
+        }
+        Ok(())
+    }
 }
 
 /// A stateful interpreter, with memoization, caching, state variable storage
@@ -146,6 +388,41 @@ pub struct Interpreter<'a> {
     // import/instantiation history. Here, we track that history to know which
     // variable from the storage to use during evaluation.
     namespaces: Vec<QuintName>,
+
+    // Weights for the alternatives of `any`, by the name of their actions (see
+    // `set_action_weights`), and the ones that were actually applied, in the
+    // order they were found.
+    action_weights: FxHashMap<String, u32>,
+    applied_action_weights: Vec<(String, u32)>,
+
+    // The names of the actions of `any` to be taken with weak fairness (see
+    // `set_fair_actions`).
+    fair_actions: FxHashSet<String>,
+
+    // Whether to record the actions taken and the nondeterministic picks in
+    // `Env::mbt`, for model-based testing.
+    record_mbt: bool,
+
+    // Specialized representations for the values of operator applications,
+    // by the id of the application, picked from their types (see
+    // `set_types`).
+    representations: FxHashMap<QuintId, Representation>,
+
+    // Cached values of definitions that don't depend on the state, which
+    // still need to be cleared when constants are set (see
+    // `set_constant_values`).
+    constant_caches: Vec<Rc<RefCell<Option<Value>>>>,
+
+    // The definitions being compiled, with a slot for their compiled
+    // expressions, so definitions that refer to themselves (i.e. recursive
+    // operators) are compiled into references to these slots.
+    compiling: FxHashMap<QuintId, Rc<OnceCell<CompiledExpr>>>,
+
+    // The arguments of the active calls of recursive definitions, by their
+    // ids, and how many times a definition can be called with the same
+    // arguments while it's active (see `set_max_repeated_calls`).
+    recursive_calls: FxHashMap<QuintId, Rc<RefCell<ActiveCalls>>>,
+    max_repeated_calls: usize,
     // TODO: Other params from Typescript implementation, for future reference:
     // initialNondetPicks: Map<string, RuntimeValue | undefined> = new Map()
 }
@@ -161,66 +438,303 @@ impl<'a> Interpreter<'a> {
             memo: Rc::new(RefCell::new(FxHashMap::default())),
             memo_by_instance: FxHashMap::default(),
             namespaces: Vec::new(),
+            action_weights: FxHashMap::default(),
+            applied_action_weights: Vec::new(),
+            fair_actions: FxHashSet::default(),
+            record_mbt: false,
+            representations: FxHashMap::default(),
+            constant_caches: Vec::new(),
+            compiling: FxHashMap::default(),
+            recursive_calls: FxHashMap::default(),
+            max_repeated_calls: DEFAULT_MAX_REPEATED_CALLS,
+        }
+    }
+
+    /// Set weights for the actions in `any { ... }` expressions, by the name
+    /// of the action definitions. Alternatives without a weight have weight 1.
+    /// This only affects expressions compiled afterwards.
+    pub fn set_action_weights(&mut self, weights: &[(String, u32)]) {
+        self.action_weights = weights.iter().cloned().collect();
+    }
+
+    /// The action weights that were applied to some `any` expression, to be
+    /// reported for reproducibility.
+    pub fn applied_action_weights(&self) -> &[(String, u32)] {
+        &self.applied_action_weights
+    }
+
+    /// Set the actions in `any { ... }` expressions to be taken with weak
+    /// fairness, by the name of the action definitions: the ones starving in
+    /// `Env::fairness` are tried first (see [`crate::fairness`]). This only
+    /// affects expressions compiled afterwards.
+    pub fn set_fair_actions(&mut self, actions: &[String]) {
+        self.fair_actions = actions.iter().cloned().collect();
+    }
+
+    /// Set how many times a recursive definition can be called with the same
+    /// arguments within its own evaluation before failing with QNT518, as
+    /// such calls likely never end. This only affects expressions compiled
+    /// afterwards.
+    pub fn set_max_repeated_calls(&mut self, max: usize) {
+        self.max_repeated_calls = max;
+    }
+
+    /// Set the values of constants of the main module, by name, from JSON
+    /// (see [`crate::constants`]), checked against the types of the
+    /// constants. Fails with QNT516 if a constant is not used by the spec or a
+    /// value doesn't have the type of its constant.
+    pub fn set_constants(&mut self, values: &[(String, Json)]) -> Result<(), QuintError> {
+        let values = values
+            .iter()
+            .map(|(name, json)| {
+                let constant = self.main_constant(name)?;
+                constants::value_from_json(json, constant.type_annotation.as_ref())
+                    .map(|value| (name.clone(), value))
+                    .map_err(|e| invalid_constant(name, &e))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.set_constant_values(&values)
+    }
+
+    /// Set the values of constants of the main module, by name, like
+    /// [`Interpreter::set_constants`] but with values built by the caller.
+    ///
+    /// Constants can be set again at any time, with no need to compile the
+    /// spec again: values computed from the previous ones are dropped, so
+    /// the same compiled spec can be run for many values of its constants
+    /// (i.e. for parameter sweeps).
+    pub fn set_constant_values(&mut self, values: &[(String, Value)]) -> Result<(), QuintError> {
+        for (name, value) in values {
+            let constant = self.main_constant(name)?;
+            constants::check_value(value, constant.type_annotation.as_ref())
+                .map_err(|e| invalid_constant(name, &e))?;
+            *self.get_or_create_const(constant.id, name).borrow_mut() = Ok(value.clone());
+        }
+        for cache in &self.constant_caches {
+            *cache.borrow_mut() = None;
+        }
+        self.var_storage.borrow_mut().clear_caches();
+        Ok(())
+    }
+
+    /// The declaration of a constant of the main module, by name.
+    fn main_constant(&self, name: &str) -> Result<&'a QuintConst, QuintError> {
+        self.table
+            .values()
+            .find_map(|def| match def {
+                LookupDefinition::Definition(QuintDeclaration::QuintConst(c))
+                    if c.name.as_str() == name && c.imported_from.is_none() =>
+                {
+                    Some(c)
+                }
+                _ => None,
+            })
+            .ok_or_else(|| {
+                QuintError::new(
+                    ErrorKind::InvalidConstant,
+                    format!("Unknown constant {name}, or not used by the spec").as_str(),
+                )
+            })
+    }
+
+    /// Record the alternatives taken in `any { ... }` expressions and the
+    /// values picked by `nondet` definitions in `Env::mbt`, i.e. for the steps
+    /// of traces and for model-based testing.
+    /// This only affects expressions compiled afterwards.
+    pub fn set_record_mbt(&mut self, record: bool) {
+        self.record_mbt = record;
+    }
+
+    /// Represent the values of operator applications according to their
+    /// types, as inferred in the IR (see [`crate::representation`]). This
+    /// only affects expressions compiled afterwards.
+    pub fn set_types(&mut self, types: &TypeTable) {
+        self.representations = Representation::for_types(types).collect();
+    }
+
+    /// The names of the actions in the arguments of an `any` expression, for
+    /// the arguments that are (applications of) action definitions.
+    fn action_any_names<'b>(&self, args: &'b [QuintEx]) -> Vec<Option<&'b QuintName>> {
+        args.iter()
+            .map(|arg| match arg {
+                QuintEx::QuintName { id, name }
+                | QuintEx::QuintApp {
+                    id, opcode: name, ..
+                } if self.table.contains_key(id) => Some(name),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// The weights for the actions of an `any` expression, by their names
+    /// (see `action_any_names`), if any of them has one.
+    fn action_any_weights(&mut self, names: &[Option<&QuintName>]) -> Option<Vec<u32>> {
+        let weighted = names
+            .iter()
+            .flatten()
+            .filter_map(|name| Some((*name, *self.action_weights.get(name.as_str())?)))
+            .collect::<Vec<_>>();
+        if weighted.is_empty() {
+            return None;
+        }
+
+        for (name, weight) in weighted {
+            if !self
+                .applied_action_weights
+                .iter()
+                .any(|(n, _)| n == name.as_str())
+            {
+                self.applied_action_weights.push((name.to_string(), weight));
+            }
         }
+
+        Some(
+            names
+                .iter()
+                .map(|name| {
+                    name.and_then(|name| self.action_weights.get(name.as_str()).copied())
+                        .unwrap_or(1)
+                })
+                .collect(),
+        )
     }
 
-    /// Shift the state, moving `next_vars` to `vars`.
+    /// Shift the state, making the primed values current.
     pub fn shift(&mut self) {
         self.var_storage.borrow_mut().shift_vars();
     }
 
+    /// Take a snapshot of an environment like [`Env::snapshot`], including
+    /// the contents of the registers of parameters and constants, i.e. the
+    /// arguments of the operators being evaluated and the values of
+    /// constants.
+    pub fn snapshot(&self, env: &Env) -> EnvSnapshot {
+        let mut snapshot = env.snapshot();
+        snapshot.registers = self
+            .param_registry
+            .values()
+            .chain(self.const_registry.values())
+            .map(|register| (Rc::clone(register), register.borrow().clone()))
+            .collect();
+        snapshot
+    }
+
+    /// Restore an environment to a snapshot taken with
+    /// [`Interpreter::snapshot`], dropping the values computed from the
+    /// constants, which may have changed since.
+    pub fn restore(&mut self, env: &mut Env, snapshot: &EnvSnapshot) {
+        env.restore(snapshot);
+        for cache in &self.constant_caches {
+            *cache.borrow_mut() = None;
+        }
+    }
+
+    /// The names of the fair actions of an `any` expression, by the names of
+    /// all of its actions (see `action_any_names`), if it has any.
+    fn action_any_fair_names(&self, names: &[Option<&QuintName>]) -> Option<Vec<Option<String>>> {
+        let names = names
+            .iter()
+            .map(|name| {
+                name.filter(|name| self.fair_actions.contains(name.as_str()))
+                    .map(|name| name.to_string())
+            })
+            .collect::<Vec<_>>();
+        names.iter().any(Option::is_some).then_some(names)
+    }
+
+    /// Compile an `any` expression over the given actions, with the weights
+    /// and fairness of the ones with names, recording the action taken if
+    /// recording for model-based testing.
+    fn compile_action_any(
+        &mut self,
+        names: &[Option<&QuintName>],
+        mut actions: Vec<CompiledExpr>,
+    ) -> CompiledExpr {
+        if self.record_mbt {
+            actions = actions
+                .into_iter()
+                .zip(names)
+                .enumerate()
+                .map(|(index, (action, name))| record_action_taken(action, name.cloned(), index))
+                .collect();
+        }
+        let fair = self.action_any_fair_names(names);
+        if let Some(fair) = &fair {
+            actions = actions
+                .into_iter()
+                .zip(fair)
+                .map(|(action, name)| match name {
+                    Some(name) => record_fair_action_taken(action, name.clone()),
+                    None => action,
+                })
+                .collect();
+        }
+
+        let op = match self.action_any_weights(names) {
+            Some(weights) => compile_weighted_action_any(weights),
+            None => compile_lazy_op("actionAny"),
+        };
+        let op = match fair {
+            Some(fair) => compile_fair_action_any(fair, op),
+            None => op,
+        };
+        CompiledExpr::new(move |env| {
+            counters::record(|c| c.operator_applications += 1);
+            let replayed = env.replay.as_mut().and_then(StepMeta::take_alternative);
+            if let Some(action) = replayed.and_then(|index| actions.get(index)) {
+                return action.execute_action(env);
+            }
+            op.execute(env, &actions)
+        })
+    }
+
+    /// Compile the interleaving of the actions of composed components, e.g.
+    /// instances of a node module and a network module, by their names. On
+    /// each step, one of the enabled actions is taken, as in `any { ... }`
+    /// (with the weights and fairness set for the names). Variables not
+    /// assigned by the action keep their values, so each component only
+    /// assigns its own variables and the shared ones it changes.
+    pub fn compile_interleaving(&mut self, components: &[(QuintName, QuintEx)]) -> CompiledExpr {
+        let names = components
+            .iter()
+            .map(|(name, _)| Some(name))
+            .collect::<Vec<_>>();
+        let actions = components
+            .iter()
+            .map(|(_, action)| self.compile(action))
+            .collect();
+        let any = self.compile_action_any(&names, actions);
+
+        CompiledExpr::new(move |env| {
+            let enabled = any.execute(env)?;
+            if enabled.try_bool()? {
+                env.var_storage.borrow_mut().state.keep_unassigned();
+            }
+            Ok(enabled)
+        })
+    }
+
     fn get_or_create_param(&mut self, param: &QuintLambdaParameter) -> Rc<RefCell<EvalResult>> {
         self.param_registry
             .entry(param.id)
             .or_insert_with(|| {
                 Rc::new(RefCell::new(Err(QuintError::new(
-                    "QNT500",
+                    ErrorKind::Uninitialized,
                     format!("Param {} not set", param.name).as_str(),
                 ))))
             })
             .clone()
     }
 
-    fn create_var(&mut self, id: QuintId, name: &QuintName) {
-        let key = var_with_namespaces(id, &self.namespaces);
-
-        if self.var_storage.borrow().vars.contains_key(&key) {
-            return;
-        }
-
-        let var_name = name_with_namespaces(name, &self.namespaces);
-        let register_for_current = Rc::new(RefCell::new(VariableRegister {
-            name: var_name.clone(),
-            value: None,
-        }));
-
-        let register_for_next = Rc::new(RefCell::new(VariableRegister {
-            name: var_name,
-            value: None,
-        }));
-
-        self.var_storage
-            .borrow_mut()
-            .vars
-            .insert(key.clone(), register_for_current);
-        self.var_storage
-            .borrow_mut()
-            .next_vars
-            .insert(key, register_for_next);
-    }
-
     fn get_or_create_var(
         &mut self,
         id: QuintId,
         name: &QuintName,
     ) -> Rc<RefCell<VariableRegister>> {
         let key = var_with_namespaces(id, &self.namespaces);
+        let var_name = name_with_namespaces(name, &self.namespaces);
 
-        if !self.var_storage.borrow().vars.contains_key(&key) {
-            self.create_var(id, name);
-        }
-
-        Rc::clone(&self.var_storage.borrow().vars[&key])
+        self.var_storage.borrow_mut().state.register(key, var_name)
     }
 
     fn get_or_create_const(&mut self, id: QuintId, name: &str) -> Rc<RefCell<EvalResult>> {
@@ -228,7 +742,7 @@ impl<'a> Interpreter<'a> {
             .entry(id)
             .or_insert_with(|| {
                 Rc::new(RefCell::new(Err(QuintError::new(
-                    "QNT500",
+                    ErrorKind::Uninitialized,
                     format!(
                         "Uninitialized const {name}. Use: import <moduleName>(${name}=<value>).*",
                     )
@@ -238,17 +752,6 @@ impl<'a> Interpreter<'a> {
             .clone()
     }
 
-    fn get_next_var(&self, id: QuintId) -> Rc<RefCell<VariableRegister>> {
-        let key = var_with_namespaces(id, &self.namespaces);
-
-        self.var_storage
-            .borrow()
-            .next_vars
-            .get(&key)
-            .unwrap()
-            .clone()
-    }
-
     pub fn compile_under_context(
         &mut self,
         def: &LookupDefinition,
@@ -281,7 +784,13 @@ impl<'a> Interpreter<'a> {
                 .iter()
                 .cloned()
                 .map(|(param, expr)| {
-                    let id = self.table.get(&param.id).unwrap().id();
+                    let Some(constant) = self.table.get(&param.id) else {
+                        return Err(QuintError::new(
+                            ErrorKind::MalformedIr,
+                            format!("Unknown constant {} in an instance", param.name).as_str(),
+                        ));
+                    };
+                    let id = constant.id();
                     let register = self.get_or_create_const(id, &param.name);
 
                     // Build the expr as a pure val def so it gets properly cached
@@ -297,18 +806,23 @@ impl<'a> Interpreter<'a> {
                         }));
 
                     let compiled_pure_val = self.compile_def(&pure_val_def);
-                    (register, compiled_pure_val)
+                    Ok((register, compiled_pure_val))
                 })
-                .collect::<Vec<_>>();
+                .collect::<Result<Vec<_>, _>>();
 
             // Here, we have the right context to build the function. That is, all constants are pointing to the right registers,
             // and all namespaces are set for unambiguous variable access and update.
-            let result = compilation(self);
+            let compiled = overrides.map(|overrides| (overrides, compilation(self)));
 
             // Restore the builder to its previous state
             self.namespaces = namespace_before;
             self.memo = memo_before;
 
+            let (overrides, result) = match compiled {
+                Ok(compiled) => compiled,
+                Err(error) => return CompiledExpr::failing(error),
+            };
+
             // And then, in runtime, we only need to evaluate the override expressions, update the respective registers
             // and then call the function that was built
             return CompiledExpr::new(move |env| {
@@ -334,6 +848,37 @@ impl<'a> Interpreter<'a> {
             return cached.clone();
         }
 
+        // A definition that refers to itself, directly or through others, is
+        // compiled into a reference to the result of this compilation
+        if let Some(slot) = self.compiling.get(&def.id()) {
+            let slot = Rc::clone(slot);
+            let name = def.name().clone();
+            // The slot is set when the compilation ends, before anything is
+            // evaluated, so it's only empty if the definition is evaluated
+            // while compiling itself
+            let reference = CompiledExpr::new(move |env| match slot.get() {
+                Some(compiled) => compiled.execute(env),
+                None => Err(self_dependency(&name)),
+            });
+            if is_operator(def) {
+                // The operator is tracked where it's applied (see `compile_op`)
+                self.recursive_calls.entry(def.id()).or_default();
+                return reference;
+            }
+            // Other definitions are evaluated right here, with no arguments
+            let calls = self.track_recursive_calls(def, move |env, _| reference.execute(env));
+            return CompiledExpr::new(move |env| calls.execute(env, Vec::new()));
+        }
+
+        self.compiling.insert(def.id(), Rc::new(OnceCell::new()));
+        let compiled_def = self.compile_def_uncached(def);
+        if let Some(slot) = self.compiling.remove(&def.id()) {
+            let _ = slot.set(compiled_def.clone());
+        }
+        compiled_def
+    }
+
+    fn compile_def_uncached(&mut self, def: &LookupDefinition) -> CompiledExpr {
         let compiled_def = match def {
             LookupDefinition::Definition(QuintDeclaration::QuintOpDef(op)) => {
                 if matches!(op.expr, QuintEx::QuintLambda { .. }) || op.depth.is_none_or(|x| x == 0)
@@ -342,19 +887,63 @@ impl<'a> Interpreter<'a> {
                     // We still have memoization. This caching is special for scoped defs (let-ins)
                     self.compile(&op.expr)
                 } else {
+                    // Registered by the `let` expression, unless the definition
+                    // is referenced out of it, when it's never cached
                     let cached_value = {
-                        let cached = self.scoped_cached_values.get(&op.id).unwrap();
+                        let cached = self
+                            .scoped_cached_values
+                            .entry(op.id)
+                            .or_insert_with(|| Rc::new(RefCell::new(None)));
                         Rc::clone(cached)
                     };
 
                     let compiled_expr = self.compile(&op.expr);
+                    // Name of the nondet definition, if its picks are recorded
+                    let nondet_pick = (self.record_mbt && op.qualifier == OpQualifier::Nondet)
+                        .then(|| op.name.to_string());
+                    // The set a nondet definition picks from, so that replayed
+                    // picks are only taken when they are in it
+                    let picked_from = match &op.expr {
+                        QuintEx::QuintApp { opcode, args, .. }
+                            if op.qualifier == OpQualifier::Nondet
+                                && opcode == "oneOf"
+                                && args.len() == 1 =>
+                        {
+                            Some(self.compile(&args[0]))
+                        }
+                        _ => None,
+                    };
+                    let name = op.name.clone();
                     CompiledExpr::new(move |env| {
-                        let mut cached = cached_value.borrow_mut();
+                        // The value is borrowed while it's computed, so it
+                        // is only borrowed again when the definition is
+                        // evaluated again from a recursive call
+                        let Ok(mut cached) = cached_value.try_borrow_mut() else {
+                            return Err(QuintError::new(
+                                ErrorKind::Unsupported,
+                                format!(
+                                    "Local definition {name} in a recursive call is not supported"
+                                )
+                                .as_str(),
+                            ));
+                        };
                         if let Some(value) = cached.as_ref() {
                             // If the value is already cached, return it
+                            counters::record(|c| c.cache_hits += 1);
                             value.clone()
                         } else {
-                            let result = compiled_expr.execute(env);
+                            counters::record(|c| c.cache_misses += 1);
+                            let replayed = match &picked_from {
+                                Some(set) => replayed_pick(env, set, &name)?,
+                                None => None,
+                            };
+                            let result = match replayed {
+                                Some(value) => Ok(value),
+                                None => compiled_expr.execute(env),
+                            };
+                            if let (Some(name), Ok(value)) = (&nondet_pick, &result) {
+                                env.mbt.nondet_picks.push((name.clone(), value.clone()));
+                            }
                             *cached = Some(result.clone());
                             result
                         }
@@ -369,7 +958,7 @@ impl<'a> Interpreter<'a> {
 
                 CompiledExpr::new(move |_| {
                     register.borrow().clone().value.ok_or(QuintError::new(
-                        "QNT502",
+                        ErrorKind::UnsetVariable,
                         format!("Variable {} not set", name).as_str(),
                     ))
                 })
@@ -386,11 +975,15 @@ impl<'a> Interpreter<'a> {
                 let register = self.get_or_create_param(p);
                 CompiledExpr::new(move |_| register.borrow().clone())
             }
-            d => unimplemented!("{:#?}", d),
+            def => CompiledExpr::failing(QuintError::new(
+                ErrorKind::MalformedIr,
+                format!("{} is not a value, and can't be evaluated", def.name()).as_str(),
+            )),
         };
 
         // For top-level value definitions, we can cache the resulting value,
         // as long as we are careful with state changes.
+        let compiled_def = compiled_def.with_id(def.id());
         match can_cache(def) {
             Cache::None => {
                 self.memo
@@ -410,19 +1003,30 @@ impl<'a> Interpreter<'a> {
                         .borrow_mut()
                         .caches_to_clear
                         .push(cached_value.clone());
+                } else {
+                    // This definition may use constants, so we need to clear the cache when they are set
+                    self.constant_caches.push(cached_value.clone());
                 }
-                // Wrap the evaluation function with caching
+                // Wrap the evaluation function with caching. The value is
+                // borrowed while it's computed, so it can only be borrowed
+                // again if it depends on itself
+                let name = def.name().clone();
                 let wrapped_expr = CompiledExpr::new(move |env| {
-                    let mut cached = cached_value.borrow_mut();
+                    let Ok(mut cached) = cached_value.try_borrow_mut() else {
+                        return Err(self_dependency(&name));
+                    };
                     if let Some(value) = cached.as_ref() {
                         // If the value is already cached, return it
+                        counters::record(|c| c.cache_hits += 1);
                         Ok(value.clone())
                     } else {
+                        counters::record(|c| c.cache_misses += 1);
                         let result = compiled_def.execute(env)?;
                         *cached = Some(result.clone());
                         Ok(result)
                     }
-                });
+                })
+                .with_id(def.id());
                 self.memo
                     .borrow_mut()
                     .insert(def.id(), wrapped_expr.clone());
@@ -460,7 +1064,8 @@ impl<'a> Interpreter<'a> {
                 }
                 err
             })
-        });
+        })
+        .with_id(id);
         self.memo.borrow_mut().insert(id, wrapped_expr.clone());
         wrapped_expr
     }
@@ -499,42 +1104,80 @@ impl<'a> Interpreter<'a> {
             }
 
             QuintEx::QuintApp { id, opcode, args } => {
+                if let Some(comparison) = self.compile_size_comparison(id, opcode, args) {
+                    return comparison;
+                }
                 let compiled_args = args.iter().map(|arg| self.compile(arg)).collect::<Vec<_>>();
 
-                if opcode == "assign" {
+                if opcode == "actionAny" {
+                    let names = self.action_any_names(args);
+                    self.compile_action_any(&names, compiled_args)
+                } else if opcode == "assign" {
                     // Assign is too special, so we handle it separately.
                     // We need to build things under the context of the variable being assigned,
                     // as it may come from an instance, and that changed everything
-                    let var_def = self.table.get(&args[0].id()).unwrap();
+                    let Some(var_def) = self.table.get(&args[0].id()) else {
+                        return CompiledExpr::failing(QuintError::new(
+                            ErrorKind::MalformedIr,
+                            "Assignment to an unknown variable",
+                        ));
+                    };
                     self.compile_under_context(var_def, |interpreter| {
-                        interpreter.create_var(var_def.id(), var_def.name());
-                        let register = interpreter.get_next_var(var_def.id());
+                        // Make sure the variable has a register, so its value is committed
+                        interpreter.get_or_create_var(var_def.id(), var_def.name());
+                        let key = var_with_namespaces(var_def.id(), &interpreter.namespaces);
                         let expr = interpreter.compile(&args[1]);
 
                         CompiledExpr::new(move |env| {
                             let value = expr.execute(env)?;
-                            register.borrow_mut().value = Some(value.clone());
+                            env.var_storage.borrow_mut().state.prime(key.clone(), value);
                             Ok(Value::Bool(true))
                         })
                     })
-                } else if LAZY_OPS.contains(&opcode.as_str()) {
+                } else if reflection::is_lazy(opcode) {
                     // Lazy operator, compile the arguments and give their
                     // closures to the operator so it decides when to eval
                     let opcode = opcode.clone();
                     CompiledExpr::new(move |env| {
+                        counters::record(|c| c.operator_applications += 1);
                         let op = compile_lazy_op(&opcode);
-                        op.execute(env, &compiled_args)
+                        let result = op.execute(env, &compiled_args);
+                        enumeration::check()?;
+                        let result = result?;
+                        if let Some(budget) = &env.budget {
+                            budget.check(&result)?;
+                        }
+                        Ok(result)
                     })
                 } else {
                     // Otherwise, this is either a normal (eager) builtin, or an user-defined operator.
                     // For both, we first evaluate the arguments and then apply the operator.
                     let compiled_op = self.compile_op(id, opcode);
+                    let is_builtin = !self.table.contains_key(id);
+                    let representation = self.representations.get(id).cloned();
                     CompiledExpr::new(move |env| {
                         let evaluated_args = compiled_args
                             .iter()
                             .map(|arg| arg.execute(env))
                             .collect::<Result<Vec<_>, _>>()?;
-                        compiled_op.execute(env, evaluated_args)
+                        counters::record(|c| c.operator_applications += 1);
+                        let result = compiled_op.execute(env, evaluated_args);
+                        // Also when the operator failed, so that its
+                        // enumerations are not blamed on a later one
+                        enumeration::check()?;
+                        let mut result = result?;
+                        if let Some(representation) = &representation {
+                            result = representation.apply(result);
+                        }
+                        if is_builtin {
+                            counters::record_allocation(&result);
+                            #[cfg(feature = "invariants")]
+                            crate::invariants::sample(&result);
+                        }
+                        if let Some(budget) = &env.budget {
+                            budget.check(&result)?;
+                        }
+                        Ok(result)
                     })
                 }
             }
@@ -562,39 +1205,285 @@ impl<'a> Interpreter<'a> {
         }
     }
 
+    /// Whether an expression applies the built-in `size`, and not an operator
+    /// defined with the same name.
+    fn is_builtin_size(&self, expr: &QuintEx) -> bool {
+        matches!(expr, QuintEx::QuintApp { id, opcode, args }
+            if opcode == "size" && args.len() == 1 && !self.table.contains_key(id))
+    }
+
+    /// Compile a comparison of the size of a set with an integer, like
+    /// `size(S) > 10`, so that it can be evaluated even when the size doesn't
+    /// fit in an integer (see [`compare_size`]). `None` for other expressions,
+    /// including comparisons with a user-defined operator of the same name.
+    fn compile_size_comparison(
+        &mut self,
+        id: &QuintId,
+        opcode: &str,
+        args: &[QuintEx],
+    ) -> Option<CompiledExpr> {
+        let holds: fn(Ordering) -> bool = match opcode {
+            "eq" => Ordering::is_eq,
+            "neq" => Ordering::is_ne,
+            "ilt" => Ordering::is_lt,
+            "ilte" => Ordering::is_le,
+            "igt" => Ordering::is_gt,
+            "igte" => Ordering::is_ge,
+            _ => return None,
+        };
+        if self.table.contains_key(id) || args.len() != 2 {
+            return None;
+        }
+        // The size may be on either side, i.e. `10 < size(S)`
+        let flipped = !self.is_builtin_size(&args[0]);
+        let (size, other) = if flipped {
+            (&args[1], &args[0])
+        } else {
+            (&args[0], &args[1])
+        };
+        if !self.is_builtin_size(size) {
+            return None;
+        }
+        let QuintEx::QuintApp {
+            id: size_id,
+            args: set,
+            ..
+        } = size
+        else {
+            return None;
+        };
+        let size_id = *size_id;
+        let set = self.compile(&set[0]);
+        let other = self.compile(other);
+        let representation = self.representations.get(id).cloned();
+
+        Some(CompiledExpr::new(move |env| {
+            let set = set.execute(env)?;
+            let n = other.execute(env)?.try_int()?;
+            counters::record(|c| c.operator_applications += 2);
+            // Errors of the size are located at it, as when it's evaluated
+            // on its own
+            let order = compare_size(&set, n).map_err(|e| e.with_reference(size_id));
+            enumeration::check()?;
+            let order = order?;
+            let order = if flipped { order.reverse() } else { order };
+            let mut result = Value::Bool(holds(order));
+            if let Some(representation) = &representation {
+                result = representation.apply(result);
+            }
+            counters::record_allocation(&result);
+            if let Some(budget) = &env.budget {
+                budget.check(&result)?;
+            }
+            Ok(result)
+        }))
+    }
+
     pub fn compile_op(&mut self, id: &QuintId, op: &str) -> CompiledExprWithArgs {
         match self.table.get(id) {
             Some(def) => {
                 // A user-defined operator
                 let op = self.compile_def(def);
-                CompiledExprWithArgs::new(move |env, args| {
+                let apply = move |env: &mut Env, args: Vec<Value>| {
                     let lambda = op.execute(env)?;
-                    let closure = lambda.as_closure();
+                    let closure = lambda.try_closure()?;
                     closure(env, args)
-                })
+                };
+                if self.recursive_calls.contains_key(&def.id()) {
+                    self.track_recursive_calls(def, apply)
+                } else {
+                    CompiledExprWithArgs::new(apply)
+                }
             }
             // A built-in. We already checked that this is not lazy before.
             None => compile_eager_op(op),
         }
     }
 
+    /// Wrap the evaluation of a recursive definition, failing with QNT518
+    /// when it's called with the same arguments as more than the allowed
+    /// number of its active calls (see `set_max_repeated_calls`), instead of
+    /// recursing until the stack overflows.
+    fn track_recursive_calls(
+        &mut self,
+        def: &LookupDefinition,
+        evaluate: impl Fn(&mut Env, Vec<Value>) -> EvalResult + 'static,
+    ) -> CompiledExprWithArgs {
+        let calls = Rc::clone(self.recursive_calls.entry(def.id()).or_default());
+        let max_repeated_calls = self.max_repeated_calls;
+        let name = def.name().clone();
+
+        CompiledExprWithArgs::new(move |env, args| {
+            // Operators given as arguments can't be compared, so calls with
+            // them are never the same and need no tracking
+            if args.iter().any(|arg| matches!(arg, Value::Lambda(..))) {
+                return evaluate(env, args);
+            }
+
+            let fingerprint = arguments_fingerprint(&args);
+            let repeated = calls.borrow().get(&fingerprint).map_or(0, |active| {
+                active.iter().filter(|active| **active == args).count()
+            });
+            if repeated >= max_repeated_calls {
+                return Err(infinite_recursion(&name, &args));
+            }
+
+            calls
+                .borrow_mut()
+                .entry(fingerprint)
+                .or_default()
+                .push(args.clone());
+            let result = evaluate(env, args);
+            // Calls end in the reverse order they start, so this call's
+            // arguments are the last ones with their fingerprint
+            let mut calls = calls.borrow_mut();
+            if let Some(active) = calls.get_mut(&fingerprint) {
+                active.pop();
+                if active.is_empty() {
+                    calls.remove(&fingerprint);
+                }
+            }
+            result
+        })
+    }
+
     /// Utility to compile and evaluate an expression
     pub fn eval(&mut self, env: &mut Env, expr: QuintEx) -> EvalResult {
         self.compile(&expr).execute(env)
     }
 }
 
+/// Wrap the alternative at `index` given to `any`, recording it (and its name)
+/// in `Env::mbt` when it is taken. If it is not taken, anything recorded while
+/// evaluating it (i.e. its nondeterministic picks) is discarded.
+fn record_action_taken(
+    action: CompiledExpr,
+    name: Option<QuintName>,
+    index: usize,
+) -> CompiledExpr {
+    let id = action.id();
+    CompiledExpr::new(move |env| {
+        let picks = env.mbt.nondet_picks.len();
+        let alternatives = env.mbt.alternatives.len();
+        let result = action.execute(env)?;
+        if result.try_bool()? {
+            // Nested actions are taken first, so the innermost one is kept
+            if env.mbt.action_taken.is_none() {
+                env.mbt.action_taken = name.as_ref().map(|name| name.to_string());
+                env.mbt.action_id = id;
+            }
+            // and the alternatives taken in this one go after it
+            let alternative = Alternative {
+                index,
+                name: name.as_ref().map(|name| name.to_string()),
+            };
+            env.mbt.alternatives.insert(alternatives, alternative);
+        } else {
+            env.mbt.nondet_picks.truncate(picks);
+            env.mbt.alternatives.truncate(alternatives);
+            env.mbt.action_taken = None;
+            env.mbt.action_id = None;
+        }
+        Ok(result)
+    })
+}
+
+/// The value recorded for a `nondet` definition picking from `set`, when
+/// replaying a step that has one in the set.
+fn replayed_pick(
+    env: &mut Env,
+    set: &CompiledExpr,
+    name: &QuintName,
+) -> Result<Option<Value>, QuintError> {
+    let Some(value) = env
+        .replay
+        .as_mut()
+        .and_then(|step| step.take_pick(name.as_str()))
+    else {
+        return Ok(None);
+    };
+    Ok(set.execute(env)?.contains(&value).then_some(value))
+}
+
+/// Wrap a fair action given to `any`, recording in `Env::fairness` that it
+/// was taken, unless an outer action is not taken after all.
+fn record_fair_action_taken(action: CompiledExpr, name: String) -> CompiledExpr {
+    CompiledExpr::new(move |env| {
+        let taken = env.fairness.taken();
+        let result = action.execute(env)?;
+        if result.try_bool()? {
+            env.fairness.take(&name);
+        } else {
+            env.fairness.discard(taken);
+        }
+        Ok(result)
+    })
+}
+
+/// The arguments of the active calls of a recursive definition, by the
+/// fingerprint of the arguments (see [`arguments_fingerprint`]), so only the
+/// calls with the same fingerprint are compared with a new one.
+type ActiveCalls = FxHashMap<u64, Vec<Vec<Value>>>;
+
+/// A fingerprint of the arguments of a call, the same for equal arguments
+/// (see [`Value::fingerprint`]).
+fn arguments_fingerprint(args: &[Value]) -> u64 {
+    let mut hasher = fxhash::FxHasher64::default();
+    for arg in args {
+        hasher.write_u64(arg.fingerprint());
+    }
+    hasher.finish()
+}
+
+fn infinite_recursion(name: &str, args: &[Value]) -> QuintError {
+    let args = args
+        .iter()
+        .map(|arg| arg.to_string())
+        .collect::<Vec<_>>()
+        .join(", ");
+    QuintError::new(
+        ErrorKind::InfiniteRecursion,
+        format!("Likely infinite recursion in def {name}, called again with the same arguments ({args})")
+            .as_str(),
+    )
+}
+
+fn self_dependency(name: &str) -> QuintError {
+    QuintError::new(
+        ErrorKind::InfiniteRecursion,
+        format!("Likely infinite recursion in def {name}, whose value depends on itself").as_str(),
+    )
+}
+
+/// Whether a definition is an operator, i.e. has parameters.
+fn is_operator(def: &LookupDefinition) -> bool {
+    matches!(
+        def,
+        LookupDefinition::Definition(QuintDeclaration::QuintOpDef(OpDef {
+            expr: QuintEx::QuintLambda { .. },
+            ..
+        }))
+    )
+}
+
+fn invalid_constant(name: &str, error: &str) -> QuintError {
+    QuintError::new(
+        ErrorKind::InvalidConstant,
+        format!("Invalid value for constant {name}: {error}").as_str(),
+    )
+}
+
 fn builtin_value(name: &str) -> CompiledExpr {
     match name {
         "true" => CompiledExpr::new(move |_| Ok(Value::Bool(true))),
         "false" => CompiledExpr::new(move |_| Ok(Value::Bool(false))),
-        "Bool" => CompiledExpr::new(move |_| {
-            Ok(Value::Set(ImmutableSet::from(vec![
-                Value::Bool(true),
-                Value::Bool(false),
-            ])))
-        }),
-        _ => unimplemented!("Unknown builtin name: {}", name),
+        "Bool" => CompiledExpr::new(move |_| Ok(Value::Universe(Universe::Bool))),
+        "Int" => CompiledExpr::new(move |_| Ok(Value::Universe(Universe::Int))),
+        "Nat" => CompiledExpr::new(move |_| Ok(Value::Universe(Universe::Nat))),
+        _ => CompiledExpr::failing(QuintError::new(
+            ErrorKind::MalformedIr,
+            format!("Unknown name {name}").as_str(),
+        )),
     }
 }
 
@@ -0,0 +1,334 @@
+//! Exhaustive, breadth-first exploration of the states of Quint models.
+//!
+//! Instead of picking random values for nondeterministic choices like the
+//! [simulator](crate::simulator), the explorer evaluates actions in the
+//! [`PickMode::Exhaustive`] mode, repeating each evaluation until all
+//! combinations of choices were made. This gives all the initial states and
+//! all the successors of each state.
+//!
+//! States are deduplicated by their fingerprints (hashes), so each distinct
+//! state is only explored once. As in other explicit-state model checkers,
+//! two different states with the same fingerprint would be taken as the same
+//! state, which is very unlikely with 64-bit hashes.
+//!
+//! For small models, the graph of the explored states can also be collected
+//! (see [`StateGraph`]), to visualize it with Graphviz.
+
+use crate::evaluator::{CompiledExpr, Env, Interpreter};
+use crate::ir::QuintError;
+use crate::output::Output;
+use crate::picker::PickMode;
+use crate::simulator::ParsedQuint;
+use crate::trace::Trace;
+use crate::value::{ImmutableMap, Value};
+use fxhash::{FxHashMap, FxHashSet};
+use serde_json::Value as Json;
+
+/// Exploration output.
+pub struct ExplorationResult {
+    /// Whether the invariant holds for all explored states.
+    pub result: bool,
+    /// The number of distinct states explored.
+    pub states: usize,
+    /// The number of steps from the initial states that were explored. This
+    /// is smaller than the depth bound if all reachable states were found.
+    pub depth: usize,
+    /// Whether all reachable states were explored, i.e. the invariant holds
+    /// for the whole model, and not only up to the depth bound.
+    pub complete: bool,
+    /// A shortest trace to a state violating the invariant, if any.
+    pub counterexample: Option<Trace>,
+    /// With [`ExplorerConfig::graph`], the graph of the explored states.
+    pub graph: Option<StateGraph>,
+}
+
+/// Parameters of an exploration, see [`ParsedQuint::explore_with_config`].
+#[derive(Debug, Clone, Default)]
+pub struct ExplorerConfig {
+    /// The maximum number of steps from the initial states.
+    pub max_depth: usize,
+    /// Collect the graph of the explored states.
+    pub graph: bool,
+    /// Values for the constants of the main module, by name, as JSON (see
+    /// [`crate::constants`]).
+    pub constants: Vec<(String, Json)>,
+    /// Where the values given to `q::debug` in the spec go (see
+    /// [`crate::output`]).
+    pub output: Output,
+}
+
+/// The graph of the explored states, with an edge for each transition
+/// between them, labeled with the name of the action taken (in `any { ... }`
+/// expressions), if any.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StateGraph {
+    /// The label of each state, in the order they were explored: the value
+    /// of [`ParsedQuint::state_label`] in the state, or the whole state.
+    pub nodes: Vec<String>,
+    /// The indices of the initial states in `nodes`.
+    pub initial: Vec<usize>,
+    /// The transitions between states, by their indices in `nodes`, in the
+    /// order they were found.
+    pub edges: Vec<(usize, usize, Option<String>)>,
+}
+
+impl StateGraph {
+    /// Write the graph in the DOT language, to be rendered with Graphviz.
+    pub fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph states {\n");
+        dot.push_str("  init [shape=point];\n");
+        for (i, label) in self.nodes.iter().enumerate() {
+            dot.push_str(&format!("  s{i} [label=\"{}\"];\n", escape(label)));
+        }
+        for i in &self.initial {
+            dot.push_str(&format!("  init -> s{i};\n"));
+        }
+        for (from, to, action) in &self.edges {
+            match action {
+                Some(action) => dot.push_str(&format!(
+                    "  s{from} -> s{to} [label=\"{}\"];\n",
+                    escape(action)
+                )),
+                None => dot.push_str(&format!("  s{from} -> s{to};\n")),
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+}
+
+/// Escape a label to be written between double quotes in DOT.
+fn escape(label: &str) -> String {
+    label.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// A state found during the exploration, with the index of the state it was
+/// reached from, to rebuild traces.
+struct ExploredState {
+    state: Value,
+    parent: Option<usize>,
+}
+
+impl ParsedQuint {
+    /// Explore all states reachable in up to `max_depth` steps, breadth-first,
+    /// checking that `invariant` holds in each of them. Stops at the first
+    /// violation, which is at the smallest possible depth.
+    ///
+    /// Nondeterministic choices must be over finite sets, as all of their
+    /// elements are tried.
+    pub fn explore(&self, max_depth: usize) -> Result<ExplorationResult, QuintError> {
+        self.explore_with_config(&ExplorerConfig {
+            max_depth,
+            ..ExplorerConfig::default()
+        })
+    }
+
+    /// Explore the states of a Quint model like [`ParsedQuint::explore`],
+    /// with all the parameters given by `config`.
+    pub fn explore_with_config(
+        &self,
+        config: &ExplorerConfig,
+    ) -> Result<ExplorationResult, QuintError> {
+        let max_depth = config.max_depth;
+        let mut interpreter = Interpreter::new(&self.table);
+        let mut env = Env::new(interpreter.var_storage.clone());
+        env.pick_mode = PickMode::Exhaustive;
+        env.output = config.output.clone();
+        // The actions taken label the edges of the graph
+        interpreter.set_record_mbt(config.graph);
+        interpreter.set_constants(&config.constants)?;
+        interpreter.set_types(&self.types);
+
+        let init = interpreter.compile(&self.init);
+        let step = self.compile_step(&mut interpreter);
+        let invariant = interpreter.compile(&self.invariant);
+
+        let mut explored: Vec<ExploredState> = Vec::new();
+        let mut fingerprints: FxHashMap<u64, usize> = FxHashMap::default();
+        let empty = Value::Record(ImmutableMap::default());
+        let mut graph = Graph::default();
+
+        // Initial states are the successors of the empty state
+        let mut frontier = Vec::new();
+        for (state, _) in successors(&mut env, &init, &empty)? {
+            let (index, new) = insert(&mut explored, &mut fingerprints, state, None);
+            if new {
+                frontier.push(index);
+                graph.initial.push(index);
+            }
+        }
+
+        let mut depth = 0;
+        loop {
+            for &index in &frontier {
+                env.var_storage
+                    .borrow_mut()
+                    .load(&explored[index].state, &empty);
+
+                if !invariant.execute(&mut env)?.try_bool()? {
+                    return Ok(ExplorationResult {
+                        result: false,
+                        states: explored.len(),
+                        depth,
+                        complete: false,
+                        counterexample: Some(trace_to(&explored, index)),
+                        graph: self.state_graph(
+                            config,
+                            &mut interpreter,
+                            &mut env,
+                            &explored,
+                            graph,
+                        )?,
+                    });
+                }
+            }
+
+            if frontier.is_empty() || depth == max_depth {
+                break;
+            }
+
+            let mut next_frontier = Vec::new();
+            for index in frontier {
+                let state = explored[index].state.clone();
+                for (successor, action) in successors(&mut env, &step, &state)? {
+                    let (successor, new) =
+                        insert(&mut explored, &mut fingerprints, successor, Some(index));
+                    if new {
+                        next_frontier.push(successor);
+                    }
+                    if config.graph && graph.edges.insert((index, successor, action.clone())) {
+                        graph.edge_order.push((index, successor, action));
+                    }
+                }
+            }
+
+            frontier = next_frontier;
+            if !frontier.is_empty() {
+                depth += 1;
+            }
+        }
+
+        Ok(ExplorationResult {
+            result: true,
+            states: explored.len(),
+            depth,
+            complete: frontier.is_empty(),
+            counterexample: None,
+            graph: self.state_graph(config, &mut interpreter, &mut env, &explored, graph)?,
+        })
+    }
+
+    /// Build the state graph from the explored states and the transitions
+    /// found, labeling the states. Only done with [`ExplorerConfig::graph`].
+    fn state_graph(
+        &self,
+        config: &ExplorerConfig,
+        interpreter: &mut Interpreter,
+        env: &mut Env,
+        explored: &[ExploredState],
+        graph: Graph,
+    ) -> Result<Option<StateGraph>, QuintError> {
+        if !config.graph {
+            return Ok(None);
+        }
+
+        let label = self.state_label.as_ref().map(|l| interpreter.compile(l));
+        let empty = Value::Record(ImmutableMap::default());
+        let nodes = explored
+            .iter()
+            .map(|explored| match &label {
+                Some(label) => {
+                    env.var_storage.borrow_mut().load(&explored.state, &empty);
+                    Ok(label.execute(env)?.to_string())
+                }
+                None => Ok(explored.state.to_string()),
+            })
+            .collect::<Result<_, QuintError>>()?;
+
+        Ok(Some(StateGraph {
+            nodes,
+            initial: graph.initial,
+            edges: graph.edge_order,
+        }))
+    }
+}
+
+/// The state graph while it is collected, see [`StateGraph`].
+#[derive(Default)]
+struct Graph {
+    initial: Vec<usize>,
+    // The edges found so far, to skip the repeated ones, which are common as
+    // different choices can lead to the same state
+    edges: FxHashSet<(usize, usize, Option<String>)>,
+    edge_order: Vec<(usize, usize, Option<String>)>,
+}
+
+/// All the states the action can produce from the given state, going over
+/// every combination of nondeterministic choices, with the names of the
+/// actions taken to produce them (if recorded, see `Env::mbt`).
+fn successors(
+    env: &mut Env,
+    action: &CompiledExpr,
+    state: &Value,
+) -> Result<Vec<(Value, Option<String>)>, QuintError> {
+    let empty = Value::Record(ImmutableMap::default());
+    let mut successors = Vec::new();
+    // Choices with recoverable errors are skipped, unless no choice is enabled
+    let mut discarded = None;
+
+    loop {
+        // Start from no primed values, so only the action's assignments are primed
+        env.var_storage.borrow_mut().load(state, &empty);
+        env.mbt.clear();
+
+        if action.execute_alternative(env, &mut discarded)? {
+            let successor = env.var_storage.borrow().primed_as_record();
+            successors.push((successor, env.mbt.action_taken.take()));
+        }
+
+        if !env.choices.advance() {
+            return match discarded {
+                Some(error) if successors.is_empty() => Err(error),
+                _ => Ok(successors),
+            };
+        }
+    }
+}
+
+/// Add a state to the explored ones, unless a state with the same fingerprint
+/// was already explored. Returns the index of the state, and whether it was
+/// added.
+fn insert(
+    explored: &mut Vec<ExploredState>,
+    fingerprints: &mut FxHashMap<u64, usize>,
+    state: Value,
+    parent: Option<usize>,
+) -> (usize, bool) {
+    let fingerprint = state.fingerprint();
+    if let Some(&index) = fingerprints.get(&fingerprint) {
+        return (index, false);
+    }
+
+    // Only new states are normalized, so the explored ones are written with
+    // enumerated sets
+    let index = explored.len();
+    fingerprints.insert(fingerprint, index);
+    explored.push(ExploredState {
+        state: state.normalize(),
+        parent,
+    });
+    (index, true)
+}
+
+/// Rebuild the trace from an initial state to the state at `index`.
+fn trace_to(explored: &[ExploredState], index: usize) -> Trace {
+    let mut states = Vec::new();
+    let mut current = Some(index);
+    while let Some(i) = current {
+        states.push(explored[i].state.clone());
+        current = explored[i].parent;
+    }
+    states.reverse();
+
+    Trace::new(states, true)
+}
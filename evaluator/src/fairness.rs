@@ -0,0 +1,81 @@
+//! Weak fairness for simulation: actions declared fair should be taken
+//! infinitely often when they are continuously enabled.
+//!
+//! This can't be enforced in finite traces, so it is approximated by counting
+//! the steps since each fair action was last taken. Once an action has starved
+//! for a whole period, the `any { ... }` expressions with it try to take it
+//! before picking among their actions as usual (see
+//! `Interpreter::set_fair_actions`). Otherwise, random simulation of specs
+//! that can always stutter (or take some other trivial action) rarely gets
+//! to the interesting behavior.
+
+use fxhash::FxHashMap;
+
+/// The steps since each fair action was taken, in the current trace.
+#[derive(Debug, Clone, Default)]
+pub struct Fairness {
+    /// The number of steps after which a fair action is starving. Nothing
+    /// starves with 0.
+    period: usize,
+    starving: FxHashMap<String, usize>,
+    /// The fair actions taken on the current step.
+    taken: Vec<String>,
+}
+
+impl Fairness {
+    pub fn new(actions: &[String], period: usize) -> Self {
+        Self {
+            period,
+            starving: actions.iter().map(|name| (name.clone(), 0)).collect(),
+            taken: Vec::new(),
+        }
+    }
+
+    /// The number of steps since the action was taken, if the action is fair
+    /// and starving.
+    pub fn starving(&self, action: &str) -> Option<usize> {
+        let steps = *self.starving.get(action)?;
+        (self.period > 0 && steps >= self.period).then_some(steps)
+    }
+
+    /// Record that an action was taken on the current step.
+    pub fn take(&mut self, action: &str) {
+        self.taken.push(action.to_string());
+    }
+
+    /// The number of actions taken on the current step, to discard the ones
+    /// taken after this point with [`Fairness::discard`].
+    pub fn taken(&self) -> usize {
+        self.taken.len()
+    }
+
+    /// Discard the actions taken after the point given by [`Fairness::taken`],
+    /// i.e. when an outer action was not taken after all.
+    pub fn discard(&mut self, taken: usize) {
+        self.taken.truncate(taken);
+    }
+
+    /// Discard the actions taken on the current step, before evaluating it
+    /// (again).
+    pub fn clear(&mut self) {
+        self.taken.clear();
+    }
+
+    /// Finish the current step, after it is taken.
+    pub fn end_step(&mut self) {
+        for steps in self.starving.values_mut() {
+            *steps += 1;
+        }
+        for action in self.taken.drain(..) {
+            if let Some(steps) = self.starving.get_mut(&action) {
+                *steps = 0;
+            }
+        }
+    }
+
+    /// Start a new trace, where no action has starved yet.
+    pub fn reset(&mut self) {
+        self.starving.values_mut().for_each(|steps| *steps = 0);
+        self.taken.clear();
+    }
+}
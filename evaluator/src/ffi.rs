@@ -0,0 +1,239 @@
+//! A C interface to embed the evaluator in other languages (e.g. Go or C++
+//! test harnesses), declared in `include/quint_evaluator.h`. The library to
+//! link is the crate built as a dynamic library, with `cargo rustc --lib
+//! --release --features ffi --crate-type cdylib`.
+//!
+//! Compiled specs and simulators are opaque handles, and values, traces and
+//! outcomes are exchanged as JSON strings, in the same formats as the
+//! [`crate::server`] methods. Every handle and string returned must be freed
+//! with the matching `*_free` function. Functions that can fail take an
+//! `error` pointer, which is set to a JSON [`QuintError`] (to be freed with
+//! [`quint_string_free`]) when they return `NULL`, unless `error` is `NULL`.
+//!
+//! Handles can be used from any thread, but not from several at once, except
+//! for [`quint_simulator_cancel`].
+
+use crate::evaluator::{Env, Interpreter};
+use crate::ir::{ErrorKind, LookupTable, QuintError, QuintEx};
+use crate::server::{to_outcome, SimulateOptions};
+use crate::simulator::{ParsedQuint, SimulatorConfig};
+use serde::de::DeserializeOwned;
+use std::ffi::{c_char, CStr, CString};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A compiled spec.
+pub struct QuintSpec {
+    parsed: ParsedQuint,
+}
+
+/// A simulation of a spec, with its options.
+pub struct QuintSimulator {
+    parsed: ParsedQuint,
+    options: SimulateOptions,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Load a spec, given as the `parsed` field of the input of
+/// `simulate-from-stdin`.
+///
+/// # Safety
+///
+/// `parsed_json` must be a valid C string, and `error` either `NULL` or valid
+/// for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quint_spec_new(
+    parsed_json: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut QuintSpec {
+    guard(error, || {
+        let parsed = unsafe { from_json(parsed_json) }?;
+        Ok(Box::into_raw(Box::new(QuintSpec { parsed })))
+    })
+}
+
+/// Free a spec returned by [`quint_spec_new`].
+///
+/// # Safety
+///
+/// `spec` must be `NULL` or a spec that has not been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quint_spec_free(spec: *mut QuintSpec) {
+    if !spec.is_null() {
+        drop(unsafe { Box::from_raw(spec) });
+    }
+}
+
+/// Evaluate an expression in a spec, with the definitions it refers to added
+/// to the spec's table (if not already there), returning its value in ITF.
+/// `table_json` can be `NULL` if there are no definitions to add.
+///
+/// # Safety
+///
+/// `spec` must be a valid spec, `expr_json` a valid C string, `table_json`
+/// `NULL` or a valid C string, and `error` either `NULL` or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quint_spec_evaluate(
+    spec: *mut QuintSpec,
+    expr_json: *const c_char,
+    table_json: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let spec = unsafe { &mut *spec };
+    guard(error, || {
+        let expr: QuintEx = unsafe { from_json(expr_json) }?;
+        let table: LookupTable = if table_json.is_null() {
+            LookupTable::default()
+        } else {
+            unsafe { from_json(table_json) }?
+        };
+        for (id, def) in table {
+            spec.parsed.table.entry(id).or_insert(def);
+        }
+
+        let mut interpreter = Interpreter::new(&spec.parsed.table);
+        let mut env = Env::new(interpreter.var_storage.clone());
+        let value = interpreter.compile(&expr).execute(&mut env)?;
+        Ok(to_c_string(value.to_itf().to_string()))
+    })
+}
+
+/// Create a simulator for a spec, with the options of the `simulate` server
+/// method (`{"source", "nruns", "nsteps", "ntraces", "seed"}`). The simulator
+/// has its own copy of the spec, which can then be freed.
+///
+/// # Safety
+///
+/// `spec` must be a valid spec, `options_json` a valid C string, and `error`
+/// either `NULL` or valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quint_simulator_new(
+    spec: *const QuintSpec,
+    options_json: *const c_char,
+    error: *mut *mut c_char,
+) -> *mut QuintSimulator {
+    let spec = unsafe { &*spec };
+    guard(error, || {
+        let options = unsafe { from_json(options_json) }?;
+        let model = serde_json::to_string(&spec.parsed).map_err(|e| {
+            QuintError::new(ErrorKind::Unsupported, &format!("Failed to copy spec: {e}"))
+        })?;
+        let parsed = serde_json::from_str(&model).map_err(|e| {
+            QuintError::new(ErrorKind::Unsupported, &format!("Failed to copy spec: {e}"))
+        })?;
+        Ok(Box::into_raw(Box::new(QuintSimulator {
+            parsed,
+            options,
+            cancel: Arc::new(AtomicBool::new(false)),
+        })))
+    })
+}
+
+/// Free a simulator returned by [`quint_simulator_new`].
+///
+/// # Safety
+///
+/// `simulator` must be `NULL` or a simulator that has not been freed yet, and
+/// is not running.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quint_simulator_free(simulator: *mut QuintSimulator) {
+    if !simulator.is_null() {
+        drop(unsafe { Box::from_raw(simulator) });
+    }
+}
+
+/// Run a simulation on the calling thread, returning the same outcome as
+/// `simulate-from-stdin`. Errors in the spec are reported in the outcome,
+/// not in `error`.
+///
+/// # Safety
+///
+/// `simulator` must be a valid simulator, and `error` either `NULL` or valid
+/// for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quint_simulator_run(
+    simulator: *mut QuintSimulator,
+    error: *mut *mut c_char,
+) -> *mut c_char {
+    let simulator = unsafe { &*simulator };
+    guard(error, || {
+        let config = SimulatorConfig {
+            cancel: Some(simulator.cancel.clone()),
+            ..simulator.options.config()
+        };
+        let result = simulator.parsed.simulate_with_config(&config, None);
+        // Cleared after the run rather than before, so that a cancellation
+        // racing with the start of the run isn't lost
+        simulator.cancel.store(false, Ordering::Relaxed);
+        let outcome = to_outcome(simulator.options.source.clone(), result);
+        Ok(to_c_string(serde_json::json!(outcome).to_string()))
+    })
+}
+
+/// Stop a running simulation at the next sample. This can be called from any
+/// thread, while [`quint_simulator_run`] runs on another. If the simulation
+/// hasn't started yet, it stops before its first sample.
+///
+/// # Safety
+///
+/// `simulator` must be a valid simulator.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quint_simulator_cancel(simulator: *const QuintSimulator) {
+    unsafe { &*simulator }.cancel.store(true, Ordering::Relaxed);
+}
+
+/// Free a string returned by any of these functions.
+///
+/// # Safety
+///
+/// `string` must be `NULL` or a string returned by these functions that has
+/// not been freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn quint_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
+
+/// Run `f`, returning `NULL` and setting `error` if it fails or panics, since
+/// panics can't unwind into C.
+fn guard<T>(error: *mut *mut c_char, f: impl FnOnce() -> Result<*mut T, QuintError>) -> *mut T {
+    let result = panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|panic| {
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "unknown panic".to_string());
+        Err(QuintError::new(
+            ErrorKind::Unsupported,
+            &format!("Panic: {message}"),
+        ))
+    });
+    match result {
+        Ok(value) => value,
+        Err(e) => {
+            if !error.is_null() {
+                // SAFETY: the callers require `error` to be valid for writes
+                unsafe { *error = to_c_string(serde_json::json!(e).to_string()) };
+            }
+            ptr::null_mut()
+        }
+    }
+}
+
+unsafe fn from_json<T: DeserializeOwned>(json: *const c_char) -> Result<T, QuintError> {
+    let json = unsafe { CStr::from_ptr(json) }
+        .to_str()
+        .map_err(|e| QuintError::new(ErrorKind::Unsupported, &format!("Invalid UTF-8: {e}")))?;
+    serde_json::from_str(json)
+        .map_err(|e| QuintError::new(ErrorKind::Unsupported, &format!("Invalid JSON: {e}")))
+}
+
+fn to_c_string(string: String) -> *mut c_char {
+    // JSON escapes nul characters in strings, so there are none
+    CString::new(string)
+        .expect("JSON should have no nul characters")
+        .into_raw()
+}
@@ -0,0 +1,349 @@
+//! Flattening of modules, replacing their imports, instances and exports with
+//! the definitions they bring in, so each module declares all the
+//! definitions it refers to, as in the output of `quint compile`. This way,
+//! specs split across many modules can be processed by the Rust toolchain
+//! (i.e. lowered and written by the `compile` command, see
+//! [`crate::lowering`]) from the IR the evaluator reads, where modules still
+//! import each other.
+//!
+//! Like the flattener of the Quint tool:
+//!  - definitions are added in topological order, before their first use, and
+//!    only if they are used (or exported);
+//!  - definitions imported under a qualifier or from instances are renamed
+//!    with their namespaces, i.e. `B::x` for `import A as B`, and so are the
+//!    names in them;
+//!  - exports add all the definitions of the exported module.
+//!
+//! The definitions used by a module are found in the lookup table, so the
+//! modules it imports don't need to be in the IR (`quint compile` only writes
+//! the main module). Exports of modules that are not in the IR only add the
+//! exported definitions that are used.
+//!
+//! Instanced modules are not copied with new ids: the constants of an
+//! instance are declared as pure values of their overrides (i.e.
+//! `pure val I::N = 3` for `import A(N = 3) as I`). The lookup table is kept
+//! as is, as the evaluator instantiates the definitions of instances from it
+//! at run time (see [`crate::evaluator::Interpreter::compile_under_context`]).
+
+use crate::ir::{
+    ImportedFrom, LookupDefinition, LookupTable, OpDef, OpQualifier, QuintDeclaration, QuintEx,
+    QuintId, QuintImport, QuintInstance, QuintLambdaParameter, QuintName, QuintOutput,
+};
+use fxhash::{FxHashMap, FxHashSet};
+
+type Overrides = [(QuintLambdaParameter, QuintEx)];
+
+impl QuintOutput {
+    /// Whether some module still has imports, instances or exports.
+    pub fn needs_flattening(&self) -> bool {
+        self.modules.iter().any(|module| {
+            module.declarations.iter().any(|decl| {
+                matches!(
+                    decl,
+                    QuintDeclaration::QuintImport(_)
+                        | QuintDeclaration::QuintInstance(_)
+                        | QuintDeclaration::QuintExport(_)
+                )
+            })
+        })
+    }
+
+    /// Flatten all modules, in order.
+    pub fn flatten(&mut self) {
+        let mut modules: FxHashMap<QuintName, Vec<QuintDeclaration>> = self
+            .modules
+            .iter()
+            .map(|module| (module.name.clone(), module.declarations.clone()))
+            .collect();
+        for module in &mut self.modules {
+            let mut flattener = Flattener::new(&self.table, &modules);
+            for decl in &module.declarations {
+                flattener.declaration(decl);
+            }
+            module.declarations = flattener.declarations;
+            // Modules exporting this one export its flattened definitions
+            modules.insert(module.name.clone(), module.declarations.clone());
+        }
+    }
+}
+
+struct Flattener<'a> {
+    table: &'a LookupTable,
+    modules: &'a FxHashMap<QuintName, Vec<QuintDeclaration>>,
+    /// The modules imported under qualifiers, by their qualifiers.
+    qualified: FxHashMap<QuintName, QuintName>,
+    declarations: Vec<QuintDeclaration>,
+    /// The names of the definitions declared so far, and the ids of the
+    /// declarations without meaningful names (i.e. `assume _ = ...`).
+    names: FxHashSet<QuintName>,
+    ids: FxHashSet<QuintId>,
+    /// The namespace and overrides of the definition whose dependencies are
+    /// being added, which apply to those dependencies too.
+    nested: Option<(Option<QuintName>, &'a Overrides)>,
+}
+
+impl<'a> Flattener<'a> {
+    fn new(
+        table: &'a LookupTable,
+        modules: &'a FxHashMap<QuintName, Vec<QuintDeclaration>>,
+    ) -> Self {
+        Self {
+            table,
+            modules,
+            qualified: FxHashMap::default(),
+            declarations: Vec::new(),
+            names: FxHashSet::default(),
+            ids: FxHashSet::default(),
+            nested: None,
+        }
+    }
+
+    fn declaration(&mut self, decl: &'a QuintDeclaration) {
+        match decl {
+            QuintDeclaration::QuintImport(QuintImport {
+                proto_name,
+                qualified_name: Some(qualifier),
+                ..
+            })
+            | QuintDeclaration::QuintInstance(QuintInstance {
+                proto_name,
+                qualified_name: Some(qualifier),
+                ..
+            }) => {
+                self.qualified.insert(qualifier.clone(), proto_name.clone());
+            }
+            QuintDeclaration::QuintImport(_) | QuintDeclaration::QuintInstance(_) => {}
+            QuintDeclaration::QuintExport(export) => {
+                let name = self
+                    .qualified
+                    .get(&export.proto_name)
+                    .unwrap_or(&export.proto_name);
+                let namespace = export.qualified_name.clone();
+                let decls = self.modules.get(name).map_or(&[][..], Vec::as_slice);
+                for decl in decls {
+                    if !matches!(decl, QuintDeclaration::QuintTypeDef(_)) {
+                        self.add(decl, namespace.clone(), &[]);
+                    }
+                }
+            }
+            decl => self.add(decl, None, &[]),
+        }
+    }
+
+    /// Add a declaration after the definitions it uses, renamed with its
+    /// namespace, unless a declaration with the same name was added already.
+    fn add(
+        &mut self,
+        decl: &'a QuintDeclaration,
+        namespace: Option<QuintName>,
+        overrides: &'a Overrides,
+    ) {
+        let is_new = match decl {
+            QuintDeclaration::QuintOpDef(_)
+            | QuintDeclaration::QuintVar(_)
+            | QuintDeclaration::QuintConst(_) => self
+                .names
+                .insert(namespaced(namespace.as_ref(), decl.name())),
+            _ => self.ids.insert(decl.id()),
+        };
+        if !is_new {
+            return;
+        }
+
+        let decl = match decl {
+            // The constants of instances are replaced by their overrides,
+            // which are in the scope of the instance, not of the module
+            QuintDeclaration::QuintConst(constant) => {
+                match self.find_override(constant.id, overrides) {
+                    Some(expr) => {
+                        self.walk_nested(None, &[], expr);
+                        QuintDeclaration::QuintOpDef(OpDef {
+                            id: constant.id,
+                            name: namespaced(namespace.as_ref(), &constant.name),
+                            qualifier: OpQualifier::PureVal,
+                            expr: expr.clone(),
+                            imported_from: None,
+                            namespaces: None,
+                            depth: None,
+                        })
+                    }
+                    None => rename(self.table, decl, namespace.as_ref()),
+                }
+            }
+            _ => {
+                for expr in expressions(decl) {
+                    self.walk_nested(namespace.clone(), overrides, expr);
+                }
+                rename(self.table, decl, namespace.as_ref())
+            }
+        };
+        self.declarations.push(decl);
+    }
+
+    /// The override given to a constant, if any.
+    fn find_override(&self, constant: QuintId, overrides: &'a Overrides) -> Option<&'a QuintEx> {
+        overrides
+            .iter()
+            .find(|(param, _)| self.table.get(&param.id).map(|def| def.id()) == Some(constant))
+            .map(|(_, expr)| expr)
+    }
+
+    /// Add the definitions used by an expression, which is in the given
+    /// namespace and instance.
+    fn walk_nested(
+        &mut self,
+        namespace: Option<QuintName>,
+        overrides: &'a Overrides,
+        expr: &'a QuintEx,
+    ) {
+        let nested = self.nested.replace((namespace, overrides));
+        self.walk(expr);
+        self.nested = nested;
+    }
+
+    fn walk(&mut self, expr: &'a QuintEx) {
+        match expr {
+            QuintEx::QuintName { id, .. } => self.used(*id),
+            QuintEx::QuintBool { .. } | QuintEx::QuintInt { .. } | QuintEx::QuintStr { .. } => {}
+            QuintEx::QuintApp { id, args, .. } => {
+                self.used(*id);
+                args.iter().for_each(|arg| self.walk(arg));
+            }
+            QuintEx::QuintLambda { expr, .. } => self.walk(expr),
+            QuintEx::QuintLet { opdef, expr, .. } => {
+                self.walk(&opdef.expr);
+                self.walk(expr);
+            }
+        }
+    }
+
+    /// Add the top-level definition a name refers to, if any.
+    fn used(&mut self, id: QuintId) {
+        // Parameters, built-in names and local definitions are not declared
+        let Some(LookupDefinition::Definition(def)) = self.table.get(&id) else {
+            return;
+        };
+        if matches!(def, QuintDeclaration::QuintOpDef(op) if op.depth.is_some_and(|d| d > 0)) {
+            return;
+        }
+
+        // Namespaces are innermost first, i.e. `["x", "B"]` for `B::x`
+        let own_namespace = def.namespaces().and_then(|namespaces| {
+            namespaces
+                .iter()
+                .cloned()
+                .reduce(|inner, outer| format!("{outer}::{inner}").into())
+        });
+        let own_overrides: &'a Overrides = match def.imported_from() {
+            Some(ImportedFrom::Instance { overrides, .. }) => overrides,
+            _ => &[],
+        };
+
+        // Definitions used by definitions in a namespace are in that
+        // namespace too, and in the same instance if they are in the same
+        // module
+        let (outer_namespace, outer_overrides) = self.nested.clone().unwrap_or((None, &[]));
+        let namespace = match (outer_namespace, own_namespace.as_ref()) {
+            (Some(outer), Some(own)) => Some(format!("{outer}::{own}").into()),
+            (outer, own) => outer.or(own.cloned()),
+        };
+        let overrides = if own_namespace.is_none() && own_overrides.is_empty() {
+            outer_overrides
+        } else {
+            own_overrides
+        };
+        self.add(def, namespace, overrides)
+    }
+}
+
+/// The expressions of a declaration.
+fn expressions(decl: &QuintDeclaration) -> Vec<&QuintEx> {
+    match decl {
+        QuintDeclaration::QuintOpDef(def) => vec![&def.expr],
+        QuintDeclaration::QuintAssume(assume) => vec![&assume.assumption],
+        _ => Vec::new(),
+    }
+}
+
+/// A name in a namespace, i.e. `B::x`.
+fn namespaced(namespace: Option<&QuintName>, name: &QuintName) -> QuintName {
+    match namespace {
+        Some(namespace) if name != "_" && !name.starts_with(&format!("{namespace}::")) => {
+            format!("{namespace}::{name}").into()
+        }
+        _ => name.clone(),
+    }
+}
+
+/// A declaration renamed with its namespace, with the names in it that refer
+/// to other definitions or to parameters renamed too, as the definitions they
+/// refer to are in the same namespace.
+fn rename(
+    table: &LookupTable,
+    decl: &QuintDeclaration,
+    namespace: Option<&QuintName>,
+) -> QuintDeclaration {
+    let mut decl = decl.clone();
+    let Some(namespace) = namespace else {
+        return decl;
+    };
+
+    let rename_expr = |expr: &mut QuintEx| rename_names(table, expr, namespace);
+    match &mut decl {
+        QuintDeclaration::QuintOpDef(def) => {
+            def.name = namespaced(Some(namespace), &def.name);
+            rename_expr(&mut def.expr);
+            def.imported_from = None;
+            def.namespaces = None;
+        }
+        QuintDeclaration::QuintVar(var) => {
+            var.name = namespaced(Some(namespace), &var.name);
+            var.imported_from = None;
+            var.namespaces = None;
+        }
+        QuintDeclaration::QuintConst(constant) => {
+            constant.name = namespaced(Some(namespace), &constant.name);
+            constant.imported_from = None;
+            constant.namespaces = None;
+        }
+        QuintDeclaration::QuintAssume(assume) => {
+            assume.name = namespaced(Some(namespace), &assume.name);
+            rename_expr(&mut assume.assumption);
+            assume.imported_from = None;
+            assume.namespaces = None;
+        }
+        _ => {}
+    }
+    decl
+}
+
+/// Rename the names that are not built-in, which are the ones in the lookup
+/// table, and the parameters and local definitions they may refer to.
+fn rename_names(table: &LookupTable, expr: &mut QuintEx, namespace: &QuintName) {
+    match expr {
+        QuintEx::QuintName { id, name } => {
+            if table.contains_key(id) {
+                *name = namespaced(Some(namespace), name);
+            }
+        }
+        QuintEx::QuintBool { .. } | QuintEx::QuintInt { .. } | QuintEx::QuintStr { .. } => {}
+        QuintEx::QuintApp { id, opcode, args } => {
+            if table.contains_key(id) {
+                *opcode = namespaced(Some(namespace), opcode);
+            }
+            args.iter_mut()
+                .for_each(|arg| rename_names(table, arg, namespace));
+        }
+        QuintEx::QuintLambda { params, expr, .. } => {
+            for param in params {
+                param.name = namespaced(Some(namespace), &param.name);
+            }
+            rename_names(table, expr, namespace);
+        }
+        QuintEx::QuintLet { opdef, expr, .. } => {
+            opdef.name = namespaced(Some(namespace), &opdef.name);
+            rename_names(table, &mut opdef.expr, namespace);
+            rename_names(table, expr, namespace);
+        }
+    }
+}
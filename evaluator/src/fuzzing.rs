@@ -0,0 +1,494 @@
+//! Arbitrary values and expressions, for fuzzing with the [`arbitrary`] crate,
+//! and the properties the fuzz targets in `fuzz/` check on them.
+//!
+//! Values are generated from a random type, so that sets, maps and lists are
+//! homogeneous as in type-checked specs. Sets are given any representation
+//! that can hold their elements (i.e. an interval or a powerset instead of an
+//! enumerated set), as equality, hashing and ordering must not depend on it,
+//! and set elements and map keys are normalized, as the evaluator does (see
+//! [`crate::normalizer`]). Collections are small, so that every set can be
+//! enumerated.
+//!
+//! Expressions are closed and well-typed: literals and applications of
+//! built-in operators over integers, booleans, sets and lists, with unique
+//! ids, so they are compiled without a lookup table. They can fail to
+//! evaluate, but they must never panic.
+//!
+//! The fuzz targets are run with `cargo fuzz run <target>` from `evaluator/`
+//! (targets are `consistency`, `as_set` and `evaluation`), which needs a
+//! nightly toolchain.
+
+use crate::evaluator::{Env, Interpreter};
+use crate::hashing::ValueHasher;
+use crate::ir::{LookupTable, QuintEx, QuintId};
+use crate::representation::Representation;
+use crate::symbol::Symbol;
+use crate::value::{CachedCardinality, Universe, Value};
+use arbitrary::{Arbitrary, Result, Unstructured};
+use std::cmp::Ordering;
+use std::hash::BuildHasher;
+use std::rc::Rc;
+
+/// The field names and variant labels of generated values, sorted. There are
+/// few of them, so that values often have the same ones.
+const LABELS: [&str; 4] = ["A", "B", "C", "D"];
+
+/// The strings of generated values, one of them too long to be inlined.
+const STRINGS: [&str; 4] = ["", "a", "b", "a string too long to be stored inline"];
+
+/// How deeply generated values and expressions are nested.
+const MAX_DEPTH: usize = 3;
+
+/// The most elements of a generated collection.
+const MAX_ELEMENTS: usize = 4;
+
+impl<'a> Arbitrary<'a> for Symbol {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Ok(Symbol::from(*u.choose(&LABELS)?))
+    }
+}
+
+impl<'a> Arbitrary<'a> for Value {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        Type::arbitrary(u, MAX_DEPTH)?.value(u)
+    }
+}
+
+impl<'a> Arbitrary<'a> for QuintEx {
+    fn arbitrary(u: &mut Unstructured<'a>) -> Result<Self> {
+        let typ = *u.choose(&[
+            ExprType::Int,
+            ExprType::Bool,
+            ExprType::Set,
+            ExprType::SetOfSets,
+            ExprType::List,
+        ])?;
+        Expressions { last_id: 0 }.expr(u, typ, MAX_DEPTH)
+    }
+}
+
+/// Check that equality, hashing and ordering agree on some values, panicking
+/// when they don't:
+///  - equality and ordering are reflexive, and the order is antisymmetric and
+///    transitive;
+///  - values are equal exactly when they are ordered as equal;
+///  - equal values have the same fingerprint, and the same hash once
+///    normalized.
+pub fn check_consistency(a: &Value, b: &Value, c: &Value) {
+    for x in [a, b, c] {
+        assert_eq!(x, &x.clone(), "{x} is not equal to itself");
+        assert_eq!(
+            x.cmp(&x.clone()),
+            Ordering::Equal,
+            "{x} is not ordered as equal to itself"
+        );
+    }
+
+    for (x, y) in [(a, b), (b, c), (a, c)] {
+        let order = x.cmp(y);
+        assert_eq!(
+            order,
+            y.cmp(x).reverse(),
+            "{x} and {y} are not ordered antisymmetrically"
+        );
+        assert_eq!(
+            x == y,
+            order == Ordering::Equal,
+            "{x} and {y} are ordered as {order:?}, but their equality is {}",
+            x == y
+        );
+        if x == y {
+            assert_eq!(
+                x.fingerprint(),
+                y.fingerprint(),
+                "{x} and {y} have different fingerprints"
+            );
+            assert_eq!(hash(x), hash(y), "{x} and {y} have different hashes");
+        }
+    }
+
+    for (x, y, z) in [
+        (a, b, c),
+        (a, c, b),
+        (b, a, c),
+        (b, c, a),
+        (c, a, b),
+        (c, b, a),
+    ] {
+        if x <= y && y <= z {
+            assert!(x <= z, "{x} <= {y} and {y} <= {z}, but not {x} <= {z}");
+        }
+    }
+}
+
+/// Check that every finite set in a value enumerates to an equal set, with as
+/// many elements as its cardinality and all of them members of it, panicking
+/// when one doesn't.
+pub fn check_as_set(value: &Value) {
+    if value.is_set() && !value.is_infinite() {
+        let elems = value.as_set();
+        assert_eq!(
+            elems.len(),
+            value.cardinality(),
+            "Wrong cardinality of {value}"
+        );
+        assert_eq!(
+            value.iter_set().count(),
+            elems.len(),
+            "Wrong iteration over {value}"
+        );
+        for elem in elems.iter() {
+            assert!(
+                value.contains(elem),
+                "{elem} is enumerated from {value}, but not in it"
+            );
+            check_as_set(elem);
+        }
+
+        let enumerated = Value::Set(elems.into_owned());
+        assert_eq!(value, &enumerated, "{value} is not equal to its elements");
+        assert_eq!(
+            &enumerated, value,
+            "The elements of {value} are not equal to it"
+        );
+        assert_eq!(
+            value.fingerprint(),
+            enumerated.fingerprint(),
+            "{value} and its elements have different fingerprints"
+        );
+        return;
+    }
+
+    match value {
+        Value::Tuple(elems) | Value::List(elems) => elems.iter().for_each(check_as_set),
+        Value::Record(fields) => fields.values().for_each(check_as_set),
+        Value::Map(map) => map.iter().for_each(|(key, value)| {
+            check_as_set(key);
+            check_as_set(value);
+        }),
+        Value::Variant(_, value) => check_as_set(value),
+        _ => {}
+    }
+}
+
+/// Compile an expression and evaluate it twice, checking that both results
+/// are the same and that the sets in it enumerate correctly (see
+/// [`check_as_set`]). Errors are fine, but evaluation must not panic.
+pub fn check_evaluation(expr: &QuintEx) {
+    let table = LookupTable::default();
+    let mut interpreter = Interpreter::new(&table);
+    let mut env = Env::new(interpreter.var_storage.clone());
+    let compiled = interpreter.compile(expr);
+
+    match (compiled.execute(&mut env), compiled.execute(&mut env)) {
+        (Ok(first), Ok(second)) => {
+            assert_eq!(first, second, "{expr:?} evaluates to different values");
+            check_as_set(&first);
+        }
+        (Err(first), Err(second)) => {
+            assert_eq!(
+                first.kind, second.kind,
+                "{expr:?} fails with different errors"
+            )
+        }
+        (first, second) => panic!("{expr:?} evaluates to {first:?}, and then to {second:?}"),
+    }
+}
+
+/// The hash of a value as an element of a set, which is normalized.
+fn hash(value: &Value) -> u64 {
+    ValueHasher::default().hash_one(value.clone().normalize())
+}
+
+/// Generate a collection of up to [`MAX_ELEMENTS`] elements.
+fn collection<'a, T>(
+    u: &mut Unstructured<'a>,
+    mut element: impl FnMut(&mut Unstructured<'a>) -> Result<T>,
+) -> Result<Vec<T>> {
+    let len = u.int_in_range(0..=MAX_ELEMENTS)?;
+    (0..len).map(|_| element(u)).collect()
+}
+
+/// The type of a generated value.
+#[derive(Debug, Clone)]
+enum Type {
+    Int,
+    Bool,
+    Str,
+    Set(Box<Type>),
+    List(Box<Type>),
+    Tuple(Vec<Type>),
+    Record(Vec<(Symbol, Type)>),
+    Map(Box<Type>, Box<Type>),
+    Sum(Vec<(Symbol, Type)>),
+}
+
+impl Type {
+    fn arbitrary(u: &mut Unstructured<'_>, depth: usize) -> Result<Type> {
+        if depth == 0 {
+            return Ok(u.choose(&[Type::Int, Type::Bool, Type::Str])?.clone());
+        }
+
+        let nested = |u: &mut Unstructured<'_>| Type::arbitrary(u, depth - 1).map(Box::new);
+        Ok(match u.int_in_range(0..=8)? {
+            0 => Type::Int,
+            1 => Type::Bool,
+            2 => Type::Str,
+            3 => Type::Set(nested(u)?),
+            4 => Type::List(nested(u)?),
+            5 => Type::Tuple(collection(u, |u| Type::arbitrary(u, depth - 1))?),
+            6 => Type::Record(Type::fields(u, depth - 1)?),
+            7 => Type::Map(nested(u)?, nested(u)?),
+            _ => {
+                let mut variants = Type::fields(u, depth - 1)?;
+                if variants.is_empty() {
+                    variants.push((Symbol::from(LABELS[0]), Type::unit()));
+                }
+                Type::Sum(variants)
+            }
+        })
+    }
+
+    /// The fields of a record, or the variants of a sum type, in the order of
+    /// their names. Sometimes all of them are units, making an enumeration.
+    fn fields(u: &mut Unstructured<'_>, depth: usize) -> Result<Vec<(Symbol, Type)>> {
+        let enumeration = u.arbitrary::<bool>()?;
+        let mut fields = Vec::new();
+        for label in LABELS {
+            if u.arbitrary()? {
+                let typ = if enumeration {
+                    Type::unit()
+                } else {
+                    Type::arbitrary(u, depth)?
+                };
+                fields.push((Symbol::from(label), typ));
+            }
+        }
+        Ok(fields)
+    }
+
+    fn unit() -> Type {
+        Type::Tuple(vec![])
+    }
+
+    fn is_unit(&self) -> bool {
+        matches!(self, Type::Tuple(elems) if elems.is_empty())
+    }
+
+    /// A value of this type.
+    fn value(&self, u: &mut Unstructured<'_>) -> Result<Value> {
+        Ok(match self {
+            // Mostly small integers, which are more likely to be equal
+            Type::Int if u.ratio(1, 8)? => Value::Int(u.arbitrary()?),
+            Type::Int => Value::Int(u.int_in_range(-4..=4)?),
+            Type::Bool => Value::Bool(u.arbitrary()?),
+            Type::Str => Value::Str((*u.choose(&STRINGS)?).into()),
+            Type::Set(elem) => elem.set(u)?,
+            Type::List(elem) => {
+                Value::List(collection(u, |u| elem.value(u))?.into_iter().collect())
+            }
+            Type::Tuple(elems) => Value::Tuple(
+                elems
+                    .iter()
+                    .map(|elem| elem.value(u))
+                    .collect::<Result<_>>()?,
+            ),
+            Type::Record(fields) => Value::Record(
+                fields
+                    .iter()
+                    .map(|(name, typ)| Ok((name.clone(), typ.value(u)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            Type::Map(key, value) => Value::Map(
+                collection(u, |u| Ok((key.value(u)?.normalize(), value.value(u)?)))?
+                    .into_iter()
+                    .collect(),
+            ),
+            Type::Sum(variants) => {
+                let (label, typ) = u.choose(variants)?;
+                Value::Variant(label.clone(), Rc::new(typ.value(u)?))
+            }
+        })
+    }
+
+    /// A set of values of this type, in any representation that can hold them.
+    fn set(&self, u: &mut Unstructured<'_>) -> Result<Value> {
+        Ok(match (self, u.int_in_range(0..=3)?) {
+            (Type::Int, 0) => {
+                let start = u.int_in_range(-4..=4)?;
+                Value::Interval(start, start + u.int_in_range(0..=4)?)
+            }
+            (Type::Int, 1) => Value::Universe(*u.choose(&[Universe::Int, Universe::Nat])?),
+            (Type::Bool, 0) => Value::Universe(Universe::Bool),
+            (Type::Set(elem), 0) => Value::PowerSet(
+                Rc::new(elem.enumerated_set(u)?),
+                CachedCardinality::default(),
+            ),
+            (Type::Tuple(elems), 0) if !elems.is_empty() => Value::CrossProduct(
+                elems
+                    .iter()
+                    .map(|elem| elem.enumerated_set(u))
+                    .collect::<Result<_>>()?,
+                CachedCardinality::default(),
+            ),
+            (Type::Map(key, value), 0) => Value::MapSet(
+                Rc::new(key.enumerated_set(u)?),
+                Rc::new(value.enumerated_set(u)?),
+                CachedCardinality::default(),
+            ),
+            (Type::Sum(variants), 0) if variants.iter().all(|(_, typ)| typ.is_unit()) => {
+                let labels = variants.iter().map(|(label, _)| label.clone()).collect();
+                Representation::EnumerationSet(labels).apply(self.enumerated_set(u)?)
+            }
+            _ => self.enumerated_set(u)?,
+        })
+    }
+
+    /// An enumerated set of values of this type.
+    fn enumerated_set(&self, u: &mut Unstructured<'_>) -> Result<Value> {
+        Ok(Value::Set(
+            collection(u, |u| Ok(self.value(u)?.normalize()))?
+                .into_iter()
+                .collect(),
+        ))
+    }
+}
+
+/// The type of a generated expression.
+#[derive(Debug, Clone, Copy)]
+enum ExprType {
+    Int,
+    Bool,
+    Set,
+    SetOfSets,
+    List,
+}
+
+/// Generates expressions, numbering them as it goes.
+struct Expressions {
+    last_id: QuintId,
+}
+
+impl Expressions {
+    fn id(&mut self) -> QuintId {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    fn expr(&mut self, u: &mut Unstructured<'_>, typ: ExprType, depth: usize) -> Result<QuintEx> {
+        use ExprType::*;
+
+        if depth == 0 || u.ratio(1, 4)? {
+            return self.leaf(u, typ);
+        }
+
+        let operators: &[(&str, &[ExprType])] = match typ {
+            Int => &[
+                ("iadd", &[Int, Int]),
+                ("isub", &[Int, Int]),
+                ("imul", &[Int, Int]),
+                ("idiv", &[Int, Int]),
+                ("imod", &[Int, Int]),
+                ("iuminus", &[Int]),
+                ("size", &[Set]),
+                ("size", &[SetOfSets]),
+                ("length", &[List]),
+                ("nth", &[List, Int]),
+                ("head", &[List]),
+                ("getOnlyElement", &[Set]),
+                ("ite", &[Bool, Int, Int]),
+            ],
+            Bool => &[
+                ("ilt", &[Int, Int]),
+                ("ilte", &[Int, Int]),
+                ("igt", &[Int, Int]),
+                ("igte", &[Int, Int]),
+                ("eq", &[Int, Int]),
+                ("eq", &[Set, Set]),
+                ("eq", &[SetOfSets, SetOfSets]),
+                ("neq", &[List, List]),
+                ("not", &[Bool]),
+                ("iff", &[Bool, Bool]),
+                ("implies", &[Bool, Bool]),
+                ("and", &[Bool, Bool, Bool]),
+                ("or", &[Bool, Bool]),
+                ("in", &[Int, Set]),
+                ("in", &[Set, SetOfSets]),
+                ("contains", &[Set, Int]),
+                ("subseteq", &[Set, Set]),
+                ("subseteq", &[SetOfSets, SetOfSets]),
+                ("isFinite", &[Set]),
+            ],
+            Set => &[
+                ("Set", &[Int, Int, Int]),
+                ("union", &[Set, Set]),
+                ("intersect", &[Set, Set]),
+                ("exclude", &[Set, Set]),
+                ("flatten", &[SetOfSets]),
+                ("indices", &[List]),
+                ("ite", &[Bool, Set, Set]),
+            ],
+            SetOfSets => &[
+                ("Set", &[Set, Set]),
+                ("union", &[SetOfSets, SetOfSets]),
+                ("intersect", &[SetOfSets, SetOfSets]),
+                ("exclude", &[SetOfSets, SetOfSets]),
+            ],
+            List => &[
+                ("List", &[Int, Int, Int]),
+                ("append", &[List, Int]),
+                ("concat", &[List, List]),
+                ("tail", &[List]),
+                ("slice", &[List, Int, Int]),
+                ("replaceAt", &[List, Int, Int]),
+            ],
+        };
+        let (opcode, args) = *u.choose(operators)?;
+
+        let args = args
+            .iter()
+            .map(|typ| self.expr(u, *typ, depth - 1))
+            .collect::<Result<_>>()?;
+        Ok(self.app(opcode, args))
+    }
+
+    /// An expression of the given type with no subexpressions other than
+    /// literals. Sets are built from literal bounds, so they stay small
+    /// enough to be enumerated, as do their powersets.
+    fn leaf(&mut self, u: &mut Unstructured<'_>, typ: ExprType) -> Result<QuintEx> {
+        Ok(match typ {
+            ExprType::Int => self.int(u)?,
+            ExprType::Bool => QuintEx::QuintBool {
+                id: self.id(),
+                value: u.arbitrary()?,
+            },
+            ExprType::Set => {
+                let bounds = vec![self.int(u)?, self.int(u)?];
+                self.app("to", bounds)
+            }
+            ExprType::SetOfSets => {
+                let set = self.leaf(u, ExprType::Set)?;
+                self.app("powerset", vec![set])
+            }
+            ExprType::List => {
+                let bounds = vec![self.int(u)?, self.int(u)?];
+                self.app("range", bounds)
+            }
+        })
+    }
+
+    fn int(&mut self, u: &mut Unstructured<'_>) -> Result<QuintEx> {
+        Ok(QuintEx::QuintInt {
+            id: self.id(),
+            value: u.int_in_range(-4..=4)?,
+        })
+    }
+
+    fn app(&mut self, opcode: &str, args: Vec<QuintEx>) -> QuintEx {
+        QuintEx::QuintApp {
+            id: self.id(),
+            opcode: opcode.into(),
+            args,
+        }
+    }
+}
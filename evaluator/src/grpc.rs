@@ -0,0 +1,200 @@
+//! A gRPC service to evaluate and simulate specs remotely (see
+//! `proto/evaluator.proto`), so heavy simulation campaigns can run on other
+//! machines. Progress and violations are streamed while simulating, and
+//! simulations can be cancelled.
+//!
+//! This is only available with the `grpc` feature, and served by the
+//! `quint_evaluator_grpc` binary.
+
+/// The messages and service generated from `proto/evaluator.proto`.
+pub mod proto {
+    tonic::include_proto!("quint.evaluator.v1");
+}
+
+use crate::server::to_outcome;
+use crate::simulator::{ParsedQuint, ProgressUpdate, SimulationHooks, SimulatorConfig};
+use crate::trace::Trace;
+use fxhash::FxHashMap;
+use proto::evaluator_server::{Evaluator, EvaluatorServer};
+use proto::simulate_event::Event;
+use proto::{
+    CancelRequest, CancelResponse, CompileRequest, CompileResponse, Outcome, Progress,
+    ReleaseRequest, ReleaseResponse, SimulateEvent, SimulateRequest, Started,
+};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{self, Sender};
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+/// How many events can be waiting to be sent, before the simulation waits for
+/// the client to receive them.
+const EVENTS_BUFFER: usize = 64;
+
+/// How often progress is reported.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(500);
+
+/// The state of the service: the specs compiled so far and the running
+/// simulations, by their handles.
+///
+/// Models can't be shared across threads, so specs are kept in their
+/// serialized form, and deserialized again for each simulation.
+#[derive(Default)]
+pub struct EvaluatorService {
+    specs: Mutex<FxHashMap<u64, Arc<str>>>,
+    simulations: Arc<Mutex<FxHashMap<u64, Arc<AtomicBool>>>>,
+    next_handle: AtomicU64,
+}
+
+impl EvaluatorService {
+    pub fn into_server(self) -> EvaluatorServer<Self> {
+        EvaluatorServer::new(self)
+    }
+
+    fn handle(&self) -> u64 {
+        self.next_handle.fetch_add(1, Ordering::Relaxed)
+    }
+}
+
+type EventSender = Sender<Result<SimulateEvent, Status>>;
+
+fn send(sender: &EventSender, event: Event) {
+    // The client may be gone, and then the simulation is cancelled
+    let _ = sender.blocking_send(Ok(SimulateEvent { event: Some(event) }));
+}
+
+/// Stream the violations as they are found.
+struct ViolationStream {
+    sender: EventSender,
+    source: String,
+}
+
+impl SimulationHooks for ViolationStream {
+    fn on_violation(&mut self, trace: &Trace, invariant: usize) {
+        let itf = trace.clone().to_itf(self.source.clone());
+        let trace = proto::Trace {
+            itf_json: itf.to_string(),
+            invariant: invariant as u64,
+        };
+        send(&self.sender, Event::Trace(trace));
+    }
+}
+
+#[tonic::async_trait]
+impl Evaluator for EvaluatorService {
+    async fn compile(
+        &self,
+        request: Request<CompileRequest>,
+    ) -> Result<Response<CompileResponse>, Status> {
+        let parsed_json = request.into_inner().parsed_json;
+        serde_json::from_str::<ParsedQuint>(&parsed_json)
+            .map_err(|e| Status::invalid_argument(format!("Invalid spec: {e}")))?;
+        let spec = self.handle();
+        self.specs.lock().unwrap().insert(spec, parsed_json.into());
+        Ok(Response::new(CompileResponse { spec }))
+    }
+
+    type SimulateStream = ReceiverStream<Result<SimulateEvent, Status>>;
+
+    async fn simulate(
+        &self,
+        request: Request<SimulateRequest>,
+    ) -> Result<Response<Self::SimulateStream>, Status> {
+        let request = request.into_inner();
+        let model = self
+            .specs
+            .lock()
+            .unwrap()
+            .get(&request.spec)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("Unknown spec {}", request.spec)))?;
+
+        let simulation = self.handle();
+        let cancel = Arc::new(AtomicBool::new(false));
+        let simulations = self.simulations.clone();
+        simulations
+            .lock()
+            .unwrap()
+            .insert(simulation, cancel.clone());
+
+        let (sender, receiver) = mpsc::channel(EVENTS_BUFFER);
+        // Simulation is CPU-bound, so it runs outside of the async runtime
+        tokio::task::spawn_blocking(move || {
+            let parsed: ParsedQuint =
+                serde_json::from_str(&model).expect("Compiled spec should be deserializable");
+            send(&sender, Event::Started(Started { simulation }));
+
+            let progress_sender = sender.clone();
+            let progress_cancel = cancel.clone();
+            let mut last_progress = Duration::ZERO;
+            let progress = Box::new(move |update: ProgressUpdate| {
+                if progress_sender.is_closed() {
+                    progress_cancel.store(true, Ordering::Relaxed);
+                }
+                if update.elapsed < last_progress + PROGRESS_INTERVAL {
+                    return;
+                }
+                last_progress = update.elapsed;
+                let progress = Progress {
+                    current: update.current as u64,
+                    total: update.total as u64,
+                    states: update.states as u64,
+                    violations: update.violations as u64,
+                    elapsed_ms: update.elapsed.as_millis() as u64,
+                };
+                send(&progress_sender, Event::Progress(progress));
+            });
+
+            let config = SimulatorConfig {
+                steps: request.steps as usize,
+                samples: request.samples as usize,
+                n_traces: request.traces as usize,
+                seed: request.seed,
+                cancel: Some(cancel.clone()),
+                ..SimulatorConfig::default()
+            };
+            let mut hooks = ViolationStream {
+                sender: sender.clone(),
+                source: request.source.clone(),
+            };
+            let result = parsed.simulate_with_hooks(&config, Some(progress), &mut hooks);
+            simulations.lock().unwrap().remove(&simulation);
+
+            let outcome = Outcome {
+                outcome_json: serde_json::json!(to_outcome(request.source, result)).to_string(),
+                cancelled: cancel.load(Ordering::Relaxed),
+            };
+            send(&sender, Event::Outcome(outcome));
+        });
+
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+
+    async fn cancel(
+        &self,
+        request: Request<CancelRequest>,
+    ) -> Result<Response<CancelResponse>, Status> {
+        let simulation = request.into_inner().simulation;
+        let simulations = self.simulations.lock().unwrap();
+        let cancelled = match simulations.get(&simulation) {
+            Some(cancel) => {
+                cancel.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        };
+        Ok(Response::new(CancelResponse { cancelled }))
+    }
+
+    async fn release(
+        &self,
+        request: Request<ReleaseRequest>,
+    ) -> Result<Response<ReleaseResponse>, Status> {
+        let spec = request.into_inner().spec;
+        match self.specs.lock().unwrap().remove(&spec) {
+            Some(_) => Ok(Response::new(ReleaseResponse {})),
+            None => Err(Status::not_found(format!("Unknown spec {spec}"))),
+        }
+    }
+}
@@ -0,0 +1,125 @@
+//! Artifacts to escalate from simulation to symbolic model checking with
+//! Apalache, when simulation didn't find any violation.
+//!
+//! A handoff is a directory with:
+//!  - `module.qnt.json`: the spec compiled into a single flattened module (see
+//!    [`crate::helpers::compile_flattened`]), which is what the Quint
+//!    typescript tool sends to Apalache;
+//!  - `apalache.json`: an Apalache configuration checking the same
+//!    initializer, step and invariants, up to the same number of steps as the
+//!    simulation. It can be given to `quint verify --apalache-config`, or to
+//!    Apalache itself;
+//!  - `handoff.json`: the configuration of the simulation, and the `quint
+//!    verify` command to check the same properties symbolically.
+
+use serde::Serialize;
+use serde_json::{json, Value as Json};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The file with the flattened module, in the handoff directory.
+pub const MODULE_FILE: &str = "module.qnt.json";
+/// The file with the Apalache configuration, in the handoff directory.
+pub const APALACHE_CONFIG_FILE: &str = "apalache.json";
+/// The file with the configuration of the simulation, in the handoff directory.
+pub const HANDOFF_FILE: &str = "handoff.json";
+
+/// The configuration of a simulation that didn't find violations, to check
+/// the same properties with Apalache.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Handoff {
+    /// The spec file that was simulated.
+    pub source: PathBuf,
+    /// The main module, if not computed from the file name.
+    pub main: Option<String>,
+    pub init: String,
+    pub step: String,
+    /// The names of the invariants that held, or an empty list if only the
+    /// default invariant (`true`) was checked.
+    pub invariants: Vec<String>,
+    /// The maximum number of steps per sample, which becomes the bound for
+    /// Apalache.
+    pub max_steps: usize,
+    /// The number of samples simulated.
+    pub samples: usize,
+    /// The master seed of the simulation.
+    pub seed: u64,
+}
+
+impl Handoff {
+    /// The invariant as a single expression, as given to `quint compile` and
+    /// `quint verify`.
+    pub fn invariant(&self) -> String {
+        if self.invariants.is_empty() {
+            "true".to_string()
+        } else {
+            self.invariants.join(" and ")
+        }
+    }
+
+    /// The Apalache configuration, reading the flattened module from
+    /// [`MODULE_FILE`]. The names of the initializer, step and invariant are
+    /// the ones added by `quint compile` and `quint verify`.
+    pub fn apalache_config(&self) -> Json {
+        json!({
+            "input": {
+                "source": {
+                    "type": "file",
+                    "format": "qnt",
+                    "file": MODULE_FILE,
+                },
+            },
+            "checker": {
+                "length": self.max_steps,
+                "init": "q::init",
+                "next": "q::step",
+                "inv": ["q::inv"],
+            },
+        })
+    }
+
+    /// The `quint verify` command checking the same properties with
+    /// Apalache, run from the handoff directory.
+    pub fn verify_command(&self) -> Vec<String> {
+        let mut command = vec![
+            "quint".to_string(),
+            "verify".to_string(),
+            self.source.display().to_string(),
+        ];
+        if let Some(main) = &self.main {
+            command.extend(["--main".to_string(), main.clone()]);
+        }
+        command.extend([
+            "--init".to_string(),
+            self.init.clone(),
+            "--step".to_string(),
+            self.step.clone(),
+            "--invariant".to_string(),
+            self.invariant(),
+            "--max-steps".to_string(),
+            self.max_steps.to_string(),
+            "--apalache-config".to_string(),
+            APALACHE_CONFIG_FILE.to_string(),
+        ]);
+        command
+    }
+
+    /// Write the handoff to a directory, creating it if needed, with the
+    /// flattened `module` given.
+    pub fn write(&self, dir: &Path, module: &Json) -> io::Result<()> {
+        fs::create_dir_all(dir)?;
+        let write_json = |name: &str, json: &Json| {
+            let mut file = File::create(dir.join(name))?;
+            file.write_all(serde_json::to_string_pretty(json)?.as_bytes())
+        };
+
+        write_json(MODULE_FILE, module)?;
+        write_json(APALACHE_CONFIG_FILE, &self.apalache_config())?;
+
+        let mut handoff = serde_json::to_value(self)?;
+        handoff["command"] = json!(self.verify_command());
+        write_json(HANDOFF_FILE, &handoff)
+    }
+}
@@ -0,0 +1,109 @@
+//! The hasher of the sets and maps of values (see [`crate::value`]).
+//!
+//! Sets and maps are hashed by hashing their elements in iteration order, so
+//! identical sets have to iterate in the same order. That only holds when all
+//! of them use the same hash function, built with the same keys every time,
+//! which is why the hashers here are never randomly seeded.
+//!
+//! fxhash is used by default, as it is the fastest on the small values most
+//! specs have. Other hashers can be picked with features:
+//!  - `ahash`: faster on large values, like long strings or nested records;
+//!  - `siphash`: SipHash 1-3, the hasher of the standard library, keyed with
+//!    [`set_siphash_key`]. For values from untrusted inputs, where collisions
+//!    could be forced to slow evaluation down.
+//!
+//! When both features are enabled, `siphash` is used. Run `cargo bench --bench
+//! hashing` with each of them to compare.
+//!
+//! # Determinism
+//!
+//! Picks from a set (i.e. `oneOf`) take its elements in iteration order, so
+//! seeded simulations only give the same traces where values hash the same.
+//! The fxhash hasher is `FxHasher64` on every platform, as the default
+//! `FxHasher` works on words of the pointer width and hashes differently on
+//! 32-bit targets (i.e. `wasm32`), and [`crate::value::Value`] hashes its kind
+//! with an explicit tag rather than the compiler's discriminant. Hashes are
+//! then the same on all little-endian platforms, which include x86_64, ARM and
+//! `wasm32`: fxhash reads strings in native-endian words. They are locked by
+//! `tests/determinism_tests.rs`, and together with the random number generator
+//! (see [`crate::rand`]) and the version of `imbl`, which lays sets out by
+//! hash, they fix the traces of a seed.
+
+#[cfg(any(feature = "ahash", feature = "siphash"))]
+use std::hash::BuildHasher;
+#[cfg(not(any(feature = "ahash", feature = "siphash")))]
+use std::hash::BuildHasherDefault;
+
+/// The hasher of [`crate::value::ImmutableSet`] and
+/// [`crate::value::ImmutableMap`].
+#[cfg(not(any(feature = "ahash", feature = "siphash")))]
+pub type ValueHasher = BuildHasherDefault<fxhash::FxHasher64>;
+
+/// The hasher of [`crate::value::ImmutableSet`] and
+/// [`crate::value::ImmutableMap`].
+#[cfg(all(feature = "ahash", not(feature = "siphash")))]
+pub type ValueHasher = AHashBuilder;
+
+/// The hasher of [`crate::value::ImmutableSet`] and
+/// [`crate::value::ImmutableMap`].
+#[cfg(feature = "siphash")]
+pub type ValueHasher = SipHashBuilder;
+
+/// The name of the hasher in use, for benchmarks and reports.
+pub const VALUE_HASHER: &str = if cfg!(feature = "siphash") {
+    "siphash"
+} else if cfg!(feature = "ahash") {
+    "ahash"
+} else {
+    "fxhash"
+};
+
+/// Builds ahash hashers with fixed seeds, as ahash's default ones are random.
+#[cfg(feature = "ahash")]
+#[derive(Clone, Copy, Default)]
+pub struct AHashBuilder;
+
+// Arbitrary seeds (digits of pi), which only need to be the same every time
+#[cfg(feature = "ahash")]
+static AHASH_STATE: ahash::RandomState = ahash::RandomState::with_seeds(
+    0x243f_6a88_85a3_08d3,
+    0x1319_8a2e_0370_7344,
+    0xa409_3822_299f_31d0,
+    0x082e_fa98_ec4e_6c89,
+);
+
+#[cfg(feature = "ahash")]
+impl BuildHasher for AHashBuilder {
+    type Hasher = ahash::AHasher;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        AHASH_STATE.build_hasher()
+    }
+}
+
+/// Builds SipHash 1-3 hashers with the key given to [`set_siphash_key`].
+#[cfg(feature = "siphash")]
+#[derive(Clone, Copy, Default)]
+pub struct SipHashBuilder;
+
+#[cfg(feature = "siphash")]
+static SIPHASH_KEY: std::sync::OnceLock<(u64, u64)> = std::sync::OnceLock::new();
+
+/// Set the key of the SipHash hasher, i.e. from a random source when values
+/// come from untrusted inputs. It can only be set before the first value is
+/// hashed, as sets and maps hashed with different keys can't be compared.
+/// Returns whether the key was set.
+#[cfg(feature = "siphash")]
+pub fn set_siphash_key(key: (u64, u64)) -> bool {
+    SIPHASH_KEY.set(key).is_ok()
+}
+
+#[cfg(feature = "siphash")]
+impl BuildHasher for SipHashBuilder {
+    type Hasher = siphasher::sip::SipHasher13;
+
+    fn build_hasher(&self) -> Self::Hasher {
+        let (k0, k1) = *SIPHASH_KEY.get_or_init(|| (0, 0));
+        siphasher::sip::SipHasher13::new_with_keys(k0, k1)
+    }
+}
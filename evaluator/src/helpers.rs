@@ -8,9 +8,12 @@
 //! Quint users should never need this as they use the Typescript tooling as an
 //! entrypoint, which calls Rust with all the pre-processing already done.
 
+use crate::diagnostics::{Diagnostic, SourceMap};
 use crate::ir::OpDef;
-use crate::ir::{QuintDeclaration, QuintOutput};
-use crate::simulator::ParsedQuint;
+use crate::ir::{LookupTable, OpQualifier, QuintDeclaration, QuintOutput};
+use crate::loader;
+use crate::simulator::{NamedProperty, ParsedQuint};
+use itertools::Itertools;
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
@@ -45,10 +48,7 @@ pub fn parse(
     }
 
     let serialized_quint = String::from_utf8(output.stdout)?;
-    let jd = &mut serde_json::Deserializer::from_str(serialized_quint.as_str());
-    let output: QuintOutput = serde_path_to_error::deserialize(jd).unwrap();
-
-    Ok(output)
+    Ok(loader::load_str(&serialized_quint)?)
 }
 
 pub fn parse_from_path(
@@ -58,6 +58,151 @@ pub fn parse_from_path(
     inv: Option<&str>,
     main: Option<&str>,
 ) -> Result<ParsedQuint, Box<dyn Error>> {
+    parse_from_path_with_properties(file_path, init, step, inv, &Properties::default(), main)
+}
+
+/// Names of definitions to be checked besides the invariant, see
+/// [`parse_from_path_with_properties`].
+#[derive(Default)]
+pub struct Properties<'a> {
+    /// Invariants to be checked and reported on individually (see
+    /// [`ParsedQuint::invariants`]), which replace the invariant.
+    pub invariants: &'a [&'a str],
+    /// Temporal properties (see [`ParsedQuint::temporal`]).
+    pub temporal: &'a [&'a str],
+    /// Witnesses (see [`ParsedQuint::witnesses`]).
+    pub witnesses: &'a [&'a str],
+    /// The label of the states in state graphs (see [`ParsedQuint::state_label`]).
+    pub state_label: Option<&'a str>,
+    /// Actions of composed components to be interleaved (see
+    /// [`ParsedQuint::components`]), which replace the step.
+    pub components: &'a [&'a str],
+}
+
+/// Like [`parse_from_path`], but also with the names of other properties to
+/// be checked.
+pub fn parse_from_path_with_properties(
+    file_path: &Path,
+    init: &str,
+    step: &str,
+    inv: Option<&str>,
+    properties: &Properties,
+    main: Option<&str>,
+) -> Result<ParsedQuint, Box<dyn Error>> {
+    let (step, inv) = step_and_invariant(step, inv, properties);
+    let output = compile_from_path(file_path, init, step, &inv, main)?;
+
+    let find_all = |names: &[&str]| {
+        names
+            .iter()
+            .map(|name| {
+                Ok(NamedProperty {
+                    name: name.to_string(),
+                    expr: output.find_definition_by_name(name)?.expr.clone(),
+                })
+            })
+            .collect::<Result<Vec<_>, Box<dyn Error>>>()
+    };
+    let invariants = match properties.invariants {
+        // A single invariant can be an expression, compiled as `q::inv`
+        [expr] if output.find_definition_by_name(expr).is_err() => vec![NamedProperty {
+            name: expr.to_string(),
+            expr: output.find_definition_by_name("q::inv")?.expr.clone(),
+        }],
+        names => find_all(names)?,
+    };
+    let temporal = find_all(properties.temporal)?;
+    let components = find_all(properties.components)?;
+    let witnesses = find_all(properties.witnesses)?
+        .into_iter()
+        .map(|witness| witness.expr)
+        .collect();
+    let state_label = properties
+        .state_label
+        .map(|name| Ok::<_, Box<dyn Error>>(output.find_definition_by_name(name)?.expr.clone()))
+        .transpose()?;
+
+    let parsed = ParsedQuint {
+        invariants,
+        temporal,
+        witnesses,
+        state_label,
+        components,
+        ..to_parsed_quint(output)
+    };
+    // Temporal operators are only found where they are used, i.e. in the
+    // invariants picked here
+    parsed
+        .validate()
+        .map_err(|errors| errors.iter().map(|e| e.to_string()).join("\n"))?;
+
+    Ok(parsed)
+}
+
+/// The step and the invariant to compile a spec with, given the properties
+/// to be checked.
+fn step_and_invariant<'a>(
+    step: &'a str,
+    inv: Option<&str>,
+    properties: &Properties,
+) -> (&'a str, String) {
+    let inv = if properties.invariants.is_empty() {
+        inv.unwrap_or("true").to_string()
+    } else {
+        properties.invariants.join(" and ")
+    };
+    // The step is not needed with components, and the spec may not have one
+    let step = if properties.components.is_empty() {
+        step
+    } else {
+        "true"
+    };
+    (step, inv)
+}
+
+/// Compile a Quint file without flattening it, as the evaluator reads it, so
+/// modules keep their imports, instances and exports (see
+/// [`crate::flattening`]).
+pub fn compile_from_path(
+    file_path: &Path,
+    init: &str,
+    step: &str,
+    inv: &str,
+    main: Option<&str>,
+) -> Result<QuintOutput, Box<dyn Error>> {
+    let serialized_quint = run_compile(file_path, init, step, inv, main, false)?;
+
+    let output = loader::load_str(&serialized_quint)?;
+    output
+        .validate()
+        .map_err(|errors| errors.iter().map(|e| e.to_string()).join("\n"))?;
+
+    Ok(output)
+}
+
+/// Compile a Quint file into a single flattened module, with instances
+/// instantiated and imports resolved, as the Quint typescript tool sends it to
+/// Apalache (see [`crate::handoff`]). The initializer, step and invariant are
+/// added as the `q::init`, `q::step` and `q::inv` definitions.
+pub fn compile_flattened(
+    file_path: &Path,
+    init: &str,
+    step: &str,
+    inv: &str,
+    main: Option<&str>,
+) -> Result<serde_json::Value, Box<dyn Error>> {
+    let serialized_quint = run_compile(file_path, init, step, inv, main, true)?;
+    Ok(serde_json::from_str(&serialized_quint)?)
+}
+
+fn run_compile(
+    file_path: &Path,
+    init: &str,
+    step: &str,
+    inv: &str,
+    main: Option<&str>,
+    flatten: bool,
+) -> Result<String, Box<dyn Error>> {
     let dir = tempfile::tempdir()?;
     let file_name = dir.path().join("tictactoe.json");
     let file = File::create(file_name.clone()).expect("failed to open file");
@@ -69,9 +214,9 @@ pub fn parse_from_path(
         .arg(file_path)
         .args(["--init", init])
         .args(["--step", step])
-        .args(["--invariant", inv.unwrap_or("true")])
+        .args(["--invariant", inv])
         .args(main.map(|m| vec!["--main", m]).unwrap_or_default())
-        .args(["--flatten", "false"])
+        .args(["--flatten", if flatten { "true" } else { "false" }])
         .stdout(file)
         .output()?;
 
@@ -87,10 +232,74 @@ pub fn parse_from_path(
     let mut file = File::open(file_name)?;
     file.read_to_string(&mut serialized_quint)?;
 
-    let jd = &mut serde_json::Deserializer::from_str(serialized_quint.as_str());
-    let output: QuintOutput = serde_path_to_error::deserialize(jd).unwrap();
+    Ok(serialized_quint)
+}
+
+/// The source map of a Quint file, to locate the errors found when
+/// evaluating it (see [`crate::diagnostics`]).
+pub fn source_map(file_path: &Path) -> Result<SourceMap, Box<dyn Error>> {
+    let dir = tempfile::tempdir()?;
+    let source_map = dir.path().join("source_map.json");
+    let output = Command::new("quint")
+        .arg("parse")
+        .arg(file_path)
+        .arg("--source-map")
+        .arg(&source_map)
+        .arg("--out")
+        .arg(dir.path().join("parsed.json"))
+        .output()?;
+
+    if !output.status.success() {
+        return Err(format!(
+            "Quint parsing failed: {}",
+            String::from_utf8_lossy(&output.stderr)
+        )
+        .into());
+    }
+    let json = serde_json::from_reader(File::open(source_map)?)?;
+    Ok(SourceMap::from_json(&json)?)
+}
+
+/// The diagnostics for the errors of compiling a Quint file like
+/// [`parse_from_path_with_properties`], which are empty if it compiles.
+pub fn compilation_diagnostics(
+    file_path: &Path,
+    init: &str,
+    step: &str,
+    inv: Option<&str>,
+    properties: &Properties,
+    main: Option<&str>,
+) -> Result<Vec<Diagnostic>, Box<dyn Error>> {
+    let (step, inv) = step_and_invariant(step, inv, properties);
+    let dir = tempfile::tempdir()?;
+    let out = dir.path().join("errors.json");
+    // With `--out`, errors are written there as JSON instead of to STDERR
+    let output = Command::new("quint")
+        .arg("compile")
+        .arg(file_path)
+        .args(["--init", init])
+        .args(["--step", step])
+        .args(["--invariant", &inv])
+        .args(main.map(|m| vec!["--main", m]).unwrap_or_default())
+        .args(["--flatten", "false"])
+        .arg("--out")
+        .arg(&out)
+        .output()?;
+
+    if output.status.success() {
+        return Ok(Vec::new());
+    }
+    let json: serde_json::Value = serde_json::from_reader(File::open(out)?)?;
+    Ok(json["errors"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(Diagnostic::from_error_message)
+        .collect())
+}
 
-    Ok(ParsedQuint {
+fn to_parsed_quint(output: QuintOutput) -> ParsedQuint {
+    ParsedQuint {
         init: output
             .find_definition_by_name("q::init")
             .unwrap()
@@ -106,11 +315,54 @@ pub fn parse_from_path(
             .unwrap()
             .expr
             .clone(),
+        invariants: Vec::new(),
+        temporal: Vec::new(),
+        witnesses: Vec::new(),
+        state_label: None,
+        components: Vec::new(),
         table: output.table,
-    })
+        types: output.types,
+    }
+}
+
+/// Parse a Quint file like [`parse_from_path`], returning its `run`
+/// definitions (i.e. tests) and the lookup table to evaluate them, see
+/// [`crate::tester`]. Only runs with names containing `filter` are returned,
+/// if given.
+pub fn parse_runs_from_path(
+    file_path: &Path,
+    filter: Option<&str>,
+    main: Option<&str>,
+) -> Result<(Vec<NamedProperty>, LookupTable), Box<dyn Error>> {
+    // Runs don't need an init, a step or an invariant
+    let output = compile_from_path(file_path, "true", "true", "true", main)?;
+    let runs = output
+        .find_runs()
+        .into_iter()
+        .filter(|run| filter.is_none_or(|filter| run.name.contains(filter)))
+        .map(|run| NamedProperty {
+            name: run.name.to_string(),
+            expr: run.expr.clone(),
+        })
+        .collect();
+
+    Ok((runs, output.table))
 }
 
 impl QuintOutput {
+    /// The `run` definitions of the main module, in the order they are declared.
+    pub fn find_runs(&self) -> Vec<&OpDef> {
+        self.modules
+            .iter()
+            .filter(|m| m.name == self.main)
+            .flat_map(|module| &module.declarations)
+            .filter_map(|d| match d {
+                QuintDeclaration::QuintOpDef(def) if def.qualifier == OpQualifier::Run => Some(def),
+                _ => None,
+            })
+            .collect()
+    }
+
     pub fn find_definition_by_name<'a>(&'a self, name: &str) -> Result<&'a OpDef, Box<dyn Error>> {
         self.modules
             .iter()
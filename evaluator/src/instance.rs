@@ -0,0 +1,59 @@
+//! Instances of a spec: its main module instantiated with values for its
+//! constants given by library users, so specs can be embedded in Rust
+//! programs and run for many values of their constants (i.e. parameter
+//! sweeps) without generating the IR again for each of them.
+//!
+//! An [`Instance`] keeps its interpreter, so the spec is compiled on the
+//! first evaluation or simulation only. Setting the constants again (see
+//! [`Instance::set_constants`]) re-instantiates the module without compiling
+//! it again.
+
+use crate::evaluator::{Env, Interpreter};
+use crate::ir::{QuintError, QuintEx};
+use crate::simulator::{ParsedQuint, SimulationResult, SimulatorConfig};
+use crate::value::Value;
+
+/// The main module of a spec, instantiated with values for its constants.
+pub struct Instance<'a> {
+    parsed: &'a ParsedQuint,
+    interpreter: Interpreter<'a>,
+}
+
+impl ParsedQuint {
+    /// Instantiate the main module with values for its constants, by name.
+    /// Fails with QNT516 if a constant is not used by the spec or a value
+    /// doesn't have the type of its constant.
+    pub fn instantiate(&self, constants: &[(String, Value)]) -> Result<Instance<'_>, QuintError> {
+        let mut interpreter = Interpreter::new(&self.table);
+        interpreter.set_constant_values(constants)?;
+        Ok(Instance {
+            parsed: self,
+            interpreter,
+        })
+    }
+}
+
+impl Instance<'_> {
+    /// Set the values of some constants again, keeping the values of the
+    /// others.
+    pub fn set_constants(&mut self, constants: &[(String, Value)]) -> Result<(), QuintError> {
+        self.interpreter.set_constant_values(constants)
+    }
+
+    /// Evaluate an expression of the spec, with no state.
+    pub fn evaluate(&mut self, expr: &QuintEx) -> Result<Value, QuintError> {
+        let mut env = Env::new(self.interpreter.var_storage.clone());
+        self.interpreter.compile(expr).execute(&mut env)
+    }
+
+    /// Simulate the instance like [`ParsedQuint::simulate_with_config`].
+    ///
+    /// Values given to constants in `config` are set on the instance too,
+    /// replacing the ones it had. Options that change how the spec is
+    /// compiled (i.e. action weights and fair actions) only apply to the first
+    /// simulation.
+    pub fn simulate(&mut self, config: &SimulatorConfig) -> Result<SimulationResult, QuintError> {
+        self.parsed
+            .simulate_with_interpreter(&mut self.interpreter, config, None, &mut ())
+    }
+}
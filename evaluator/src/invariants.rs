@@ -0,0 +1,117 @@
+//! Checks that the elements of sets and the keys of maps hash and compare
+//! consistently, to debug the representations of values (`invariants`
+//! feature).
+//!
+//! Sets and maps find their elements by hash, so two equal values that hash
+//! differently, like an interval and the equivalent enumerated set, end up as
+//! different elements, and lookups silently miss them. That's why elements
+//! and keys must be normalized (see [`crate::normalizer`]). With this feature,
+//! the sets and maps built by built-in operators are randomly sampled, and
+//! some of their elements are checked:
+//!  - each element hashes as its normal form does;
+//!  - distinct elements are not equal, nor ordered as equal;
+//!  - the order is antisymmetric and transitive on them.
+//!
+//! The first inconsistency found panics, with the values involved. Only one in
+//! [`SAMPLE_RATE`] collections is checked, on up to [`SAMPLE_SIZE`] of its
+//! elements, so that whole specs can still be run with the checks.
+
+use crate::hashing::ValueHasher;
+use crate::value::Value;
+use std::cell::Cell;
+use std::cmp::Ordering;
+use std::hash::BuildHasher;
+
+/// One in this many sets and maps built by operators is checked.
+pub const SAMPLE_RATE: u64 = 16;
+
+/// How many elements of a set or map are checked against each other.
+pub const SAMPLE_SIZE: usize = 8;
+
+thread_local! {
+    // The state of a xorshift generator, with a fixed seed so that the same
+    // collections are checked in every run
+    static RANDOM: Cell<u64> = const { Cell::new(0x9e37_79b9_7f4a_7c15) };
+}
+
+fn next_random() -> u64 {
+    RANDOM.with(|random| {
+        let mut x = random.get();
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        random.set(x);
+        x
+    })
+}
+
+/// Check a set or map built by an operator, if it's picked at random.
+/// Panics on an inconsistency.
+pub fn sample(value: &Value) {
+    if !matches!(value, Value::Set(_) | Value::Map(_)) || next_random() % SAMPLE_RATE != 0 {
+        return;
+    }
+    if let Err(inconsistency) = check(value) {
+        panic!("Inconsistent values in a set or map: {inconsistency}");
+    }
+}
+
+/// Check some elements of a set, or keys of a map, starting from a random
+/// one. Other values are not checked.
+pub fn check(value: &Value) -> Result<(), String> {
+    let start = next_random() as usize;
+    // Elements from the random one on, wrapping around to the first ones
+    let elems: Vec<&Value> = match value {
+        Value::Set(set) if !set.is_empty() => set
+            .iter()
+            .chain(set.iter())
+            .skip(start % set.len())
+            .take(SAMPLE_SIZE.min(set.len()))
+            .collect(),
+        Value::Map(map) if !map.is_empty() => map
+            .keys()
+            .chain(map.keys())
+            .skip(start % map.len())
+            .take(SAMPLE_SIZE.min(map.len()))
+            .collect(),
+        _ => return Ok(()),
+    };
+
+    for elem in &elems {
+        let normal = (*elem).clone().normalize();
+        if hash(elem) != hash(&normal) {
+            return Err(format!(
+                "{elem} is not normalized, so it can't be found as the equal {normal}"
+            ));
+        }
+    }
+
+    for (i, a) in elems.iter().enumerate() {
+        for b in &elems[i + 1..] {
+            if a == b {
+                return Err(format!("{a} and {b} are equal, but are different elements"));
+            }
+            let order = a.cmp(b);
+            if order == Ordering::Equal {
+                return Err(format!("{a} and {b} are different, but ordered as equal"));
+            }
+            if order != b.cmp(a).reverse() {
+                return Err(format!("{a} and {b} are not ordered antisymmetrically"));
+            }
+        }
+    }
+
+    for triple in elems.windows(3) {
+        let (a, b, c) = (triple[0], triple[1], triple[2]);
+        let (ab, bc) = (a.cmp(b), b.cmp(c));
+        if ab == bc && a.cmp(c) != ab {
+            return Err(format!("{a}, {b} and {c} are not ordered transitively"));
+        }
+    }
+
+    Ok(())
+}
+
+fn hash(value: &Value) -> u64 {
+    ValueHasher::default().hash_one(value)
+}
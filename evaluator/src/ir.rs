@@ -10,30 +10,148 @@ use thiserror::Error;
 pub type QuintId = u64;
 pub type QuintName = LocalHipStr<'static>;
 
+/// An error of loading, compiling or evaluating a spec. Errors are told apart
+/// by their kind, which gives their code (see [`ErrorKind`]), and evaluation
+/// errors reference the expression where they happened and, for some of them,
+/// the value that couldn't be handled.
+///
+/// This is the only error type of the evaluator, for evaluation failures too:
+/// a separate type for them would have the same fields, and would have to be
+/// converted back into this one for Quint, which reads errors by their code.
+/// Values of the wrong kind, which type-checked specs never give to an
+/// operator, are reported as malformed IR (see [`crate::value::Value::try_int`]).
 #[derive(Debug, Clone, Error, PartialEq, Serialize)]
-#[error("[{code}] {message}")]
+#[error("[{}] {message}", .kind.code())]
 pub struct QuintError {
-    pub code: String,
+    /// The kind of the error, serialized as its code.
+    #[serde(rename = "code", serialize_with = "serialize_code")]
+    pub kind: ErrorKind,
     pub message: String,
     pub reference: Option<QuintId>,
+    /// The value the error is about, as Quint shows it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
 }
 
 impl QuintError {
-    pub fn new(code: &str, message: &str) -> Self {
+    pub fn new(kind: ErrorKind, message: &str) -> Self {
         QuintError {
-            code: code.to_string(),
+            kind,
             message: message.to_string(),
             reference: None,
+            value: None,
         }
     }
 
     pub fn with_reference(self, reference: QuintId) -> Self {
         QuintError {
-            code: self.code,
-            message: self.message,
             reference: Some(reference),
+            ..self
         }
     }
+
+    pub fn with_value(self, value: &impl std::fmt::Display) -> Self {
+        QuintError {
+            value: Some(value.to_string()),
+            ..self
+        }
+    }
+
+    /// The code of the error, i.e. `QNT508`.
+    pub fn code(&self) -> &'static str {
+        self.kind.code()
+    }
+
+    /// Whether the error is recoverable, see [`ErrorKind::is_recoverable`].
+    pub fn is_recoverable(&self) -> bool {
+        self.kind.is_recoverable()
+    }
+}
+
+fn serialize_code<S: serde::Serializer>(
+    kind: &ErrorKind,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(kind.code())
+}
+
+/// The kinds of errors, one for each code, for library users to match on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum ErrorKind {
+    /// QNT500: a parameter or constant without a value.
+    Uninitialized,
+    /// QNT501: an operator or input the evaluator doesn't support, or an
+    /// internal error.
+    Unsupported,
+    /// QNT502: a variable read before it was set.
+    UnsetVariable,
+    /// QNT503: division by zero, undefined powers and integer overflows.
+    Arithmetic,
+    /// QNT504: an argument out of the domain of an operator.
+    InvalidArgument,
+    /// QNT505: no element to take, i.e. the head of an empty list, or no case
+    /// matching a variant.
+    NoElement,
+    /// QNT506: a slice out of the bounds of a list.
+    InvalidSlice,
+    /// QNT507: a key missing from a map.
+    MissingKey,
+    /// QNT508: a failed assertion or `expect`. Recoverable in one of several
+    /// alternatives (see
+    /// [`crate::evaluator::CompiledExpr::execute_alternative`]).
+    AssertionFailed,
+    /// QNT509: a pick from an empty set. Recoverable: actions are disabled by
+    /// them, instead of failing (see
+    /// [`crate::evaluator::CompiledExpr::execute_action`]).
+    EmptySet,
+    /// QNT510: an index out of the bounds of a list.
+    OutOfBounds,
+    /// QNT513: a run that could not continue.
+    RunFailed,
+    /// QNT514: an infinite set, or one too large, where a finite one is needed.
+    InfiniteSet,
+    /// QNT515: the evaluation budget was exceeded.
+    BudgetExceeded,
+    /// QNT516: a constant that is unknown or has an invalid value.
+    InvalidConstant,
+    /// QNT517: malformed IR, or IR from an unsupported version of Quint.
+    MalformedIr,
+    /// QNT518: a recursive definition that likely never terminates.
+    InfiniteRecursion,
+}
+
+impl ErrorKind {
+    /// The code of errors of this kind, as the Quint tool reports it.
+    pub fn code(self) -> &'static str {
+        match self {
+            ErrorKind::Uninitialized => "QNT500",
+            ErrorKind::Unsupported => "QNT501",
+            ErrorKind::UnsetVariable => "QNT502",
+            ErrorKind::Arithmetic => "QNT503",
+            ErrorKind::InvalidArgument => "QNT504",
+            ErrorKind::NoElement => "QNT505",
+            ErrorKind::InvalidSlice => "QNT506",
+            ErrorKind::MissingKey => "QNT507",
+            ErrorKind::AssertionFailed => "QNT508",
+            ErrorKind::EmptySet => "QNT509",
+            ErrorKind::OutOfBounds => "QNT510",
+            ErrorKind::RunFailed => "QNT513",
+            ErrorKind::InfiniteSet => "QNT514",
+            ErrorKind::BudgetExceeded => "QNT515",
+            ErrorKind::InvalidConstant => "QNT516",
+            ErrorKind::MalformedIr => "QNT517",
+            ErrorKind::InfiniteRecursion => "QNT518",
+        }
+    }
+
+    /// Whether errors of this kind only rule out the choice they happen in,
+    /// such as a pick from an empty set, or a failed assertion in one of the
+    /// actions of `any { ... }`, so evaluation can go on with another one.
+    /// Other errors are fatal: they come from a mistake in the spec (i.e. a
+    /// value of an unexpected type) or in the evaluator, and stop it.
+    pub fn is_recoverable(self) -> bool {
+        matches!(self, ErrorKind::EmptySet | ErrorKind::AssertionFailed)
+    }
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -41,10 +159,31 @@ pub struct QuintOutput {
     pub modules: Vec<QuintModule>,
     pub table: LookupTable,
     pub main: QuintName,
+    /// The stage of the Quint tool that produced the IR, i.e. `compiling`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stage: Option<String>,
+    /// The version of the Quint tool that produced the IR, if it says so (see
+    /// [`crate::loader`]).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+    /// The types inferred for expressions, by id, used to pick how values are
+    /// represented (see [`crate::representation`]).
+    #[serde(default, skip_serializing_if = "IndexMap::is_empty")]
+    pub types: TypeTable,
 }
 
 pub type LookupTable = IndexMap<QuintId, LookupDefinition, FxBuildHasher>;
 
+pub type TypeTable = IndexMap<QuintId, TypeScheme, FxBuildHasher>;
+
+/// The type inferred for an expression. Type and row variables are not
+/// needed by the evaluator.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TypeScheme {
+    #[serde(rename = "type")]
+    pub typ: QuintType,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
 pub enum LookupDefinition {
@@ -133,6 +272,45 @@ pub struct QuintAssume {
     pub namespaces: Option<Vec<QuintName>>,
 }
 
+/// An import of the definitions of another module, i.e. `import A.*`,
+/// `import A.x` or `import A as B`. Exports have the same shape.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuintImport {
+    pub id: QuintId,
+    /// The name of the module
+    #[serde(rename = "protoName")]
+    pub proto_name: QuintName,
+    /// The name of the imported definition, or `*` for all of them
+    #[serde(rename = "defName", default, skip_serializing_if = "Option::is_none")]
+    pub def_name: Option<QuintName>,
+    /// The qualifier of the imported definitions, i.e. `B` in `import A as B`
+    #[serde(
+        rename = "qualifiedName",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub qualified_name: Option<QuintName>,
+}
+
+/// An instance of another module, i.e. `import A(N = 3) as I`.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct QuintInstance {
+    pub id: QuintId,
+    /// The name of the instantiated module
+    #[serde(rename = "protoName")]
+    pub proto_name: QuintName,
+    #[serde(
+        rename = "qualifiedName",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub qualified_name: Option<QuintName>,
+    /// The expressions given to the constants of the module
+    pub overrides: Vec<(QuintLambdaParameter, QuintEx)>,
+    #[serde(rename = "identityOverride", default)]
+    pub identity_override: bool,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct QuintTypeDef {
     pub id: QuintId,
@@ -144,11 +322,83 @@ pub struct QuintTypeDef {
 pub struct QuintConst {
     pub id: QuintId,
     pub name: QuintName,
+    #[serde(rename = "typeAnnotation")]
+    pub type_annotation: Option<QuintType>,
     #[serde(rename = "importedFrom")]
     pub imported_from: Option<ImportedFrom>,
     pub namespaces: Option<Vec<QuintName>>,
 }
 
+/// A Quint type, as annotated in constant declarations or inferred for
+/// expressions. Only the types of values are represented, to check the values
+/// given to constants at run time (see [`crate::constants`]) and to represent
+/// values of some types more compactly (see [`crate::representation`]).
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind")]
+pub enum QuintType {
+    #[serde(rename = "bool")]
+    Bool,
+    #[serde(rename = "int")]
+    Int,
+    #[serde(rename = "str")]
+    Str,
+    #[serde(rename = "set")]
+    Set { elem: Box<QuintType> },
+    #[serde(rename = "list")]
+    List { elem: Box<QuintType> },
+    #[serde(rename = "fun")]
+    Fun {
+        arg: Box<QuintType>,
+        res: Box<QuintType>,
+    },
+    #[serde(rename = "tup")]
+    Tup { fields: Row },
+    #[serde(rename = "rec")]
+    Rec { fields: Row },
+    #[serde(rename = "sum")]
+    Sum { fields: Row },
+    /// Any other type, i.e. type aliases, type variables and operators
+    #[serde(other, rename = "other")]
+    Other,
+}
+
+/// The fields of tuple, record and sum types.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "kind")]
+pub enum Row {
+    #[serde(rename = "row")]
+    Row {
+        fields: Vec<RowField>,
+        other: Box<Row>,
+    },
+    #[serde(rename = "var")]
+    Var { name: QuintName },
+    #[serde(rename = "empty")]
+    Empty,
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct RowField {
+    #[serde(rename = "fieldName")]
+    pub field_name: QuintName,
+    #[serde(rename = "fieldType")]
+    pub field_type: QuintType,
+}
+
+impl Row {
+    /// The fields of the row, in order, and whether more fields are allowed.
+    pub fn fields(&self) -> (Vec<&RowField>, bool) {
+        match self {
+            Row::Row { fields, other } => {
+                let (rest, open) = other.fields();
+                (fields.iter().chain(rest).collect(), open)
+            }
+            Row::Var { .. } => (Vec::new(), true),
+            Row::Empty => (Vec::new(), false),
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(tag = "kind")]
 pub enum QuintDeclaration {
@@ -170,12 +420,14 @@ pub enum QuintDeclaration {
     #[serde(rename = "const")]
     QuintConst(QuintConst),
 
+    // Imports, instances and exports are only left in modules that are not
+    // flattened (see `crate::flattening`)
     #[serde(rename = "import")]
-    QuintImport {}, // Ignore
+    QuintImport(QuintImport),
     #[serde(rename = "instance")]
-    QuintInstance {}, // Ignore
+    QuintInstance(QuintInstance),
     #[serde(rename = "export")]
-    QuintExport {}, // Ignore
+    QuintExport(QuintImport),
 }
 
 impl QuintDeclaration {
@@ -186,7 +438,9 @@ impl QuintDeclaration {
             Self::QuintAssume(QuintAssume { id, .. }) => *id,
             Self::QuintTypeDef(QuintTypeDef { id }) => *id,
             Self::QuintConst(QuintConst { id, .. }) => *id,
-            _ => panic!("This import-like declaration doesn't have an id"),
+            Self::QuintImport(QuintImport { id, .. })
+            | Self::QuintExport(QuintImport { id, .. })
+            | Self::QuintInstance(QuintInstance { id, .. }) => *id,
         }
     }
 
@@ -0,0 +1,78 @@
+//! Iterating over the elements of sets without enumerating them beforehand.
+//!
+//! Calling [`Value::as_set`] on an intermediate value (such as an interval or
+//! a powerset) builds the whole set in memory. Operators that may stop early
+//! (i.e. `exists` and `forall`) or that only need to see each element once
+//! (i.e. `fold`) can instead use [`Value::iter_set`], which produces the
+//! elements one at a time.
+
+use crate::value::{powerset_at_index, ImmutableMap, ImmutableVec, Universe, Value};
+use itertools::Itertools;
+use std::iter;
+
+impl Value {
+    /// Iterate over the elements of a set, producing elements on demand for
+    /// intermediate values. Panics if the wrong type is given, which should
+    /// never happen as input expressions are type-checked.
+    ///
+    /// The order of the elements is stable: iterating over the same set twice
+    /// produces the elements in the same order.
+    pub fn iter_set(&self) -> Box<dyn Iterator<Item = Value> + '_> {
+        match self {
+            Value::Set(set) => Box::new(set.iter().cloned()),
+            Value::Interval(start, end) => Box::new((*start..=*end).map(Value::Int)),
+            Value::BitSet(labels, bits) => Box::new(Value::bitset_elements(labels, *bits)),
+            Value::CrossProduct(sets, _) => {
+                if self.cardinality() == 0 {
+                    // an empty set produces the empty product
+                    return Box::new(iter::empty());
+                }
+
+                // Each of the sets is enumerated, but not the product
+                Box::new(
+                    sets.iter()
+                        .map(|set| set.iter_set().collect::<Vec<_>>())
+                        .multi_cartesian_product()
+                        .map(|product| Value::Tuple(ImmutableVec::from(product))),
+                )
+            }
+            Value::PowerSet(value, _) => {
+                let base = value.as_set().into_owned();
+                // 2^n subsets for a set of size n
+                Box::new((0..self.cardinality()).map(move |i| powerset_at_index(&base, i)))
+            }
+            Value::MapSet(domain, range, _) => {
+                if domain.cardinality() == 0 {
+                    // To reflect the behaviour of TLC, an empty domain needs to give Set(Map())
+                    return Box::new(iter::once(Value::Map(ImmutableMap::default())));
+                }
+
+                if range.cardinality() == 0 {
+                    // To reflect the behaviour of TLC, an empty range needs to give Set()
+                    return Box::new(iter::empty());
+                }
+
+                let domain_vec = domain.iter_set().collect::<Vec<_>>();
+                let range_vec = range.iter_set().collect::<Vec<_>>();
+
+                let nvalues = range_vec.len();
+                let nmaps = self.cardinality();
+
+                Box::new((0..nmaps).map(move |i| {
+                    let mut index = i;
+                    let pairs = domain_vec.iter().map(|key| {
+                        let value = range_vec[index % nvalues].clone();
+                        index /= nvalues;
+                        (key.clone(), value)
+                    });
+                    Value::Map(pairs.collect())
+                }))
+            }
+            Value::Universe(Universe::Bool) => {
+                Box::new([Value::Bool(false), Value::Bool(true)].into_iter())
+            }
+            Value::Universe(universe) => panic!("Infinite set {universe} is non-enumerable"),
+            _ => panic!("Expected set"),
+        }
+    }
+}
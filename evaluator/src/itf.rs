@@ -3,108 +3,345 @@
 //! Read more about it [here](https://apalache-mc.org/docs/adr/015adr-trace.html).
 //!
 //! This format can be parsed by Quint's typescript tool and by the ITF trace
-//! viewer extension on VSCode.
+//! viewer extension on VSCode. We produce the JSON directly, using the same
+//! encoding as the typescript tool: integers are always written as
+//! `{ "#bigint": "<digits>" }` (as JSON numbers can't represent all of them
+//! precisely in Javascript), and sets, tuples and maps use the `#set`, `#tup`
+//! and `#map` objects.
+//!
+//! Traces can also be read back from ITF (i.e. to replay them), accepting
+//! integers written either as `#bigint` or as plain JSON numbers.
 
-use crate::value::Value;
+use crate::mbt::{ACTION_TAKEN, NONDET_PICKS};
+use crate::spans::span;
+use crate::trace::{Alternative, State, StepMeta, Trace};
+use crate::value::{ImmutableMap, Value};
 use chrono::{self};
-use itf;
-use std::collections::BTreeMap;
+use serde_json::{json, Map, Value as Json};
+use std::rc::Rc;
+use thiserror::Error;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
-pub struct Trace {
-    pub states: Vec<Value>,
-    pub violation: bool,
-}
+/// An error when reading a trace or a value from ITF.
+#[derive(Debug, Clone, Error, PartialEq)]
+#[error("Invalid ITF: {0}")]
+pub struct ItfError(String);
 
 impl Trace {
-    pub fn to_itf(self, source: String) -> itf::Trace<itf::Value> {
+    /// Convert the trace to ITF, with the metadata expected by Quint's
+    /// typescript tool. `source` is the name of the file with the spec.
+    ///
+    /// The recorded steps are written in the metadata of each state (its
+    /// `#meta` object, which other tools ignore), as `actionTaken`,
+    /// `nondetPicks` and `alternatives`.
+    pub fn to_itf(self, source: String) -> Json {
+        self.itf(source, false)
+    }
+
+    /// Convert the trace to ITF like [`Trace::to_itf`], also writing the
+    /// recorded steps as the [`ACTION_TAKEN`] and [`NONDET_PICKS`] variables of
+    /// each state, as the typescript tool does with `--mbt`.
+    pub fn to_mbt_itf(self, source: String) -> Json {
+        self.itf(source, true)
+    }
+
+    fn itf(self, source: String, mbt_vars: bool) -> Json {
+        let _span = span!(INFO, "export", states = self.states.len());
         let states = self
             .states
             .iter()
             .enumerate()
-            .map(|(i, v)| itf::State {
-                meta: itf::state::Meta {
-                    index: Some(i as u64),
-                    other: BTreeMap::default(),
-                },
-                value: v.to_itf(),
+            .map(|(i, state)| {
+                // States are records of the variables, so other values have none
+                let mut fields = match state.vars.to_itf() {
+                    Json::Object(fields) if matches!(state.vars, Value::Record(_)) => fields,
+                    _ => Map::new(),
+                };
+                let mut state_meta = json!({ "index": i });
+                if let Some(meta) = &state.meta {
+                    let action = meta.action_taken.clone().unwrap_or_default();
+                    let alternatives = meta
+                        .alternatives
+                        .iter()
+                        .map(|alternative| match &alternative.name {
+                            Some(name) => json!({ "index": alternative.index, "name": name }),
+                            None => json!({ "index": alternative.index }),
+                        })
+                        .collect::<Vec<_>>();
+                    state_meta["actionTaken"] = json!(action);
+                    state_meta["nondetPicks"] = meta.picks().to_itf();
+                    state_meta["alternatives"] = json!(alternatives);
+                    if mbt_vars {
+                        fields.insert(ACTION_TAKEN.to_string(), Json::String(action));
+                        fields.insert(NONDET_PICKS.to_string(), meta.picks().to_itf());
+                    }
+                }
+                fields.insert("#meta".to_string(), state_meta);
+                Json::Object(fields)
             })
-            .collect::<Vec<itf::State<itf::Value>>>();
-
-        // Find the variable names by taking the fields from the first state
-        // (which should be a record)
-        let vars = if let Some(Value::Record(map)) = self.states.first() {
-            map.keys().map(|v| v.to_string()).collect::<Vec<_>>()
-        } else {
-            panic!("Expected a record, got {}", self.states[0]);
+            .collect::<Vec<_>>();
+
+        // Find the variable names by taking the fields from the first state,
+        // if there is one
+        let mut vars = match self.states.first() {
+            Some(State {
+                vars: Value::Record(map),
+                meta,
+            }) => {
+                let mut vars = map.keys().map(|v| v.to_string()).collect::<Vec<_>>();
+                if mbt_vars && meta.is_some() {
+                    vars.extend([ACTION_TAKEN.to_string(), NONDET_PICKS.to_string()]);
+                }
+                vars
+            }
+            _ => Vec::new(),
         };
+        vars.sort();
 
-        let mut other = BTreeMap::new();
-        other.insert(
-            "status".to_string(),
-            if self.violation {
-                "violation".to_string()
-            } else {
-                "ok".to_string()
-            },
-        );
-
-        itf::Trace {
-            meta: itf::trace::Meta {
-                format: Some("ITF".to_string()),
-                format_description: Some(
-                    "https://apalache-mc.org/docs/adr/015adr-trace.html".to_string(),
-                ),
-                source: Some(source),
-                description: Some(format!(
-                    "Created by Quint (Rust version) on {}",
-                    chrono::offset::Local::now().to_rfc2822()
-                )),
-                var_types: BTreeMap::default(),
-                timestamp: Some(chrono::offset::Local::now().timestamp_millis() as u64),
-                other,
-            },
-            vars,
-            states,
-            params: vec![],
-            loop_index: None,
+        let mut meta = json!({
+            "format": "ITF",
+            "format-description": "https://apalache-mc.org/docs/adr/015adr-trace.html",
+            "source": source,
+            "status": if self.violation { "violation" } else { "ok" },
+            "description": format!(
+                "Created by Quint (Rust version) on {}",
+                chrono::offset::Local::now().to_rfc2822()
+            ),
+            "timestamp": chrono::offset::Local::now().timestamp_millis(),
+        });
+        if let Some(seed) = self.seed {
+            meta["seed"] = json!(format!("{seed:#x}"));
         }
+
+        json!({
+            "#meta": meta,
+            "vars": vars,
+            "states": states,
+        })
+    }
+
+    /// Read a trace from ITF, i.e. one written by [`Trace::to_itf`]. The
+    /// trace's status and seed are read from its metadata, if present, and
+    /// the steps from the metadata of the states or, for traces of other
+    /// tools, from the [`ACTION_TAKEN`] and [`NONDET_PICKS`] variables.
+    pub fn from_itf(itf: &Json) -> Result<Trace, ItfError> {
+        let states = itf["states"]
+            .as_array()
+            .ok_or_else(|| ItfError("expected a `states` array".to_string()))?
+            .iter()
+            .map(|state| {
+                let mut state = state.clone();
+                let Some(fields) = state.as_object_mut() else {
+                    return Err(ItfError("expected a state as an object".to_string()));
+                };
+                let state_meta = fields.remove("#meta").unwrap_or(Json::Null);
+                let action = fields.remove(ACTION_TAKEN);
+                let picks = fields.remove(NONDET_PICKS);
+                let meta = if state_meta.get("actionTaken").is_some() {
+                    let action = state_meta.get("actionTaken").cloned();
+                    let picks = state_meta.get("nondetPicks").cloned();
+                    let mut step = step_from_itf(action, picks)?;
+                    step.alternatives = alternatives_from_itf(&state_meta["alternatives"])?;
+                    Some(step)
+                } else if action.is_some() || picks.is_some() {
+                    Some(step_from_itf(action, picks)?)
+                } else {
+                    None
+                };
+                Ok(State {
+                    vars: Value::from_itf(&state)?,
+                    meta,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let seed = match &itf["#meta"]["seed"] {
+            Json::Null => None,
+            seed => Some(
+                seed.as_str()
+                    .and_then(|seed| seed.strip_prefix("0x"))
+                    .and_then(|seed| u64::from_str_radix(seed, 16).ok())
+                    .ok_or_else(|| ItfError(format!("invalid seed {seed}")))?,
+            ),
+        };
+
+        Ok(Trace {
+            states,
+            violation: itf["#meta"]["status"] == "violation",
+            seed,
+        })
     }
 }
 
+/// Read a step from the action taken and the nondet picks of a state, i.e.
+/// its [`ACTION_TAKEN`] and [`NONDET_PICKS`] variables. An empty action is an
+/// action that wasn't recorded.
+fn step_from_itf(action: Option<Json>, picks: Option<Json>) -> Result<StepMeta, ItfError> {
+    let action_taken = match action {
+        Some(Json::String(action)) if !action.is_empty() => Some(action),
+        Some(Json::String(_)) | None => None,
+        Some(action) => return Err(ItfError(format!("expected an action, got {action}"))),
+    };
+    let picks = match picks.map(|picks| Value::from_itf(&picks)).transpose()? {
+        Some(picks @ Value::Record(_)) => picks,
+        Some(picks) => return Err(ItfError(format!("expected nondet picks, got {picks}"))),
+        None => Value::Record(ImmutableMap::default()),
+    };
+    Ok(StepMeta::from_picks(action_taken, &picks))
+}
+
+/// Read the alternatives taken on a step from the metadata of a state.
+fn alternatives_from_itf(alternatives: &Json) -> Result<Vec<Alternative>, ItfError> {
+    let invalid = || ItfError(format!("expected alternatives, got {alternatives}"));
+    let Some(alternatives) = alternatives.as_array() else {
+        return match alternatives {
+            Json::Null => Ok(Vec::new()),
+            _ => Err(invalid()),
+        };
+    };
+    alternatives
+        .iter()
+        .map(|alternative| {
+            let index = alternative["index"].as_u64().ok_or_else(invalid)?;
+            let name = match &alternative["name"] {
+                Json::Null => None,
+                Json::String(name) => Some(name.clone()),
+                _ => return Err(invalid()),
+            };
+            Ok(Alternative {
+                index: index as usize,
+                name,
+            })
+        })
+        .collect()
+}
+
 impl Value {
-    pub fn to_itf(&self) -> itf::Value {
+    /// Read a value from ITF. Objects with exactly the `tag` and `value`
+    /// fields are read as variants, and any other objects as records.
+    pub fn from_itf(itf: &Json) -> Result<Value, ItfError> {
+        let invalid = || ItfError(format!("unexpected value {itf}"));
+
+        match itf {
+            Json::Bool(b) => Ok(Value::Bool(*b)),
+            Json::Number(n) => n.as_i64().map(Value::Int).ok_or_else(invalid),
+            Json::String(s) => Ok(Value::Str(s.as_str().into())),
+            Json::Array(elems) => Ok(Value::List(
+                elems
+                    .iter()
+                    .map(Value::from_itf)
+                    .collect::<Result<_, _>>()?,
+            )),
+            Json::Object(fields) => {
+                if let Some(n) = fields.get("#bigint") {
+                    return n
+                        .as_str()
+                        .and_then(|n| n.parse().ok())
+                        .map(Value::Int)
+                        .ok_or_else(|| ItfError(format!("integer out of 64-bit range: {n}")));
+                }
+
+                let elems = |key: &str| match &fields[key] {
+                    Json::Array(elems) => elems
+                        .iter()
+                        .map(Value::from_itf)
+                        .collect::<Result<Vec<_>, _>>(),
+                    _ => Err(invalid()),
+                };
+
+                if fields.contains_key("#set") {
+                    return Ok(Value::Set(
+                        elems("#set")?.into_iter().map(Value::normalize).collect(),
+                    ));
+                }
+                if fields.contains_key("#tup") {
+                    return Ok(Value::Tuple(elems("#tup")?.into_iter().collect()));
+                }
+                if fields.contains_key("#map") {
+                    return elems("#map")?
+                        .into_iter()
+                        .map(|entry| match entry {
+                            Value::List(pair) if pair.len() == 2 => {
+                                Ok((pair[0].clone().normalize(), pair[1].clone()))
+                            }
+                            _ => Err(invalid()),
+                        })
+                        .collect::<Result<_, _>>()
+                        .map(Value::Map);
+                }
+                if fields.contains_key("#unserializable") {
+                    return Err(ItfError(format!("unserializable value {itf}")));
+                }
+
+                if fields.len() == 2 {
+                    if let (Some(Json::String(tag)), Some(value)) =
+                        (fields.get("tag"), fields.get("value"))
+                    {
+                        return Ok(Value::Variant(
+                            tag.as_str().into(),
+                            Rc::new(Value::from_itf(value)?),
+                        ));
+                    }
+                }
+
+                fields
+                    .iter()
+                    .map(|(name, value)| Ok((name.as_str().into(), Value::from_itf(value)?)))
+                    .collect::<Result<ImmutableMap<_, _>, _>>()
+                    .map(Value::Record)
+            }
+            Json::Null => Err(invalid()),
+        }
+    }
+
+    /// Convert the value to ITF. The elements of sets and the entries of maps
+    /// are sorted, so the same value always produces the same JSON.
+    ///
+    /// Infinite sets and operators can't be written as ITF values, so they
+    /// are written as `{ "#unserializable": "<value>" }`, as the format
+    /// specifies, with the value as Quint shows it.
+    pub fn to_itf(&self) -> Json {
         match self {
-            Self::Int(i) => itf::Value::Number(*i),
-            Self::Bool(b) => itf::Value::Bool(*b),
-            Self::Str(s) => itf::Value::String(s.to_string()),
+            _ if self.is_infinite() => json!({ "#unserializable": self.to_string() }),
+            Self::Int(i) => json!({ "#bigint": i.to_string() }),
+            Self::Bool(b) => Json::Bool(*b),
+            Self::Str(s) => Json::String(s.to_string()),
             Self::Set(_)
             | Self::Interval(_, _)
-            | Self::CrossProduct(_)
-            | Self::PowerSet(_)
-            | Self::MapSet(_, _) => {
-                itf::Value::Set(self.as_set().iter().map(|v| v.to_itf()).collect())
+            | Self::CrossProduct(..)
+            | Self::PowerSet(..)
+            | Self::MapSet(..)
+            | Self::Universe(_)
+            | Self::BitSet(_, _) => {
+                let set = self.as_set();
+                let mut elems = set.iter().collect::<Vec<_>>();
+                elems.sort_unstable();
+                json!({ "#set": elems.into_iter().map(|v| v.to_itf()).collect::<Vec<_>>() })
             }
-            Self::Tuple(elems) => itf::Value::Tuple(elems.iter().map(|v| v.to_itf()).collect()),
-            Self::Record(fields) => itf::Value::Record(
+            Self::Tuple(elems) => {
+                json!({ "#tup": elems.iter().map(|v| v.to_itf()).collect::<Vec<_>>() })
+            }
+            Self::Record(fields) => Json::Object(
                 fields
                     .iter()
                     .map(|(k, v)| (k.to_string(), v.to_itf()))
-                    .collect(),
+                    .collect::<Map<_, _>>(),
             ),
             Self::Map(map) => {
-                itf::Value::Map(map.iter().map(|(k, v)| (k.to_itf(), v.to_itf())).collect())
+                let mut entries = map.iter().collect::<Vec<_>>();
+                entries.sort_unstable();
+                // Map entries are 2-element arrays, not tuples
+                let entries = entries
+                    .into_iter()
+                    .map(|(k, v)| json!([k.to_itf(), v.to_itf()]))
+                    .collect::<Vec<_>>();
+                json!({ "#map": entries })
             }
-            Self::List(elems) => itf::Value::List(elems.iter().map(|v| v.to_itf()).collect()),
-            Self::Variant(label, value) => itf::Value::Record(
-                vec![
-                    ("tag".to_string(), itf::Value::String(label.to_string())),
-                    ("value".to_string(), value.to_itf()),
-                ]
-                .into_iter()
-                .collect(),
-            ),
-            Self::Lambda(_, _) => panic!("Cannot convert Lambda to ITF"),
+            Self::List(elems) => Json::Array(elems.iter().map(|v| v.to_itf()).collect()),
+            Self::Variant(label, value) => json!({
+                "tag": label.to_string(),
+                "value": value.to_itf(),
+            }),
+            Self::Lambda(_, _) => json!({ "#unserializable": self.to_string() }),
         }
     }
 }
@@ -3,15 +3,66 @@
 //! Evaluation is done by compiling Quint expressions and definitions into Rust closures,
 //! which can be evaluated to yield the expression's result.
 
+pub mod browser;
 pub mod builtins;
+pub mod constants;
+pub mod counters;
+pub mod diagnostics;
+pub mod enumeration;
 pub mod evaluator;
+pub mod explorer;
+pub mod fairness;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod flattening;
+#[cfg(feature = "arbitrary")]
+pub mod fuzzing;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod handoff;
+pub mod hashing;
 pub mod helpers;
+pub mod instance;
+#[cfg(feature = "invariants")]
+pub mod invariants;
 pub mod ir;
+pub mod iterator;
 pub mod itf;
+pub mod loader;
 pub mod log;
+pub mod lowering;
+pub mod mbt;
+pub mod metrics;
+pub mod msgpack;
+pub mod ndjson;
+#[cfg(feature = "napi")]
+pub mod node;
 pub mod normalizer;
+pub mod output;
 pub mod picker;
+pub mod printer;
+#[cfg(feature = "protobuf")]
+pub mod protobuf;
+pub mod protocol;
 pub mod rand;
+pub mod reflection;
+pub mod repl;
+pub mod report;
+pub mod representation;
+pub mod server;
+pub mod sharing;
 pub mod simulator;
+mod spans;
 pub mod storage;
+#[cfg(feature = "sqlite")]
+pub mod store;
+pub mod symbol;
+pub mod temporal;
+pub mod tester;
+pub mod tlc;
+pub mod trace;
+pub mod validator;
 pub mod value;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+pub mod watch;
@@ -0,0 +1,122 @@
+//! Loading of the IR written by `quint compile`, in the formats of all the
+//! versions of Quint the evaluator reads, so fields written by other versions
+//! are never misread.
+//!
+//! The loader works on the JSON before deserializing it:
+//!  1. it detects the version of Quint that wrote the IR, from its `version`
+//!     field, or from its shape for IR written before Quint wrote versions
+//!     (see [`ir_version`]);
+//!  2. it rejects IR written by versions older than [`MIN_QUINT_VERSION`]
+//!     (when they say so), or by minor versions newer than
+//!     [`LATEST_QUINT_VERSION`], which may change the format in ways the
+//!     evaluator doesn't know about;
+//!  3. it upgrades IR written by older versions to the current format, one
+//!     format change at a time.
+//!
+//! Malformed IR is reported with QNT517 errors, with the path of the field
+//! that couldn't be read.
+
+use crate::ir::{ErrorKind, QuintError, QuintOutput};
+use crate::validator::parse_version;
+use crate::validator::MIN_QUINT_VERSION;
+use serde_json::Value as Json;
+
+/// The newest minor version of Quint whose IR the evaluator reads, with any
+/// patch version. Minor versions of Quint can change the IR format.
+pub const LATEST_QUINT_VERSION: (u64, u64) = (0, 25);
+
+/// A version of Quint, as `(major, minor, patch)`.
+pub type Version = (u64, u64, u64);
+
+/// A change of the IR format: the version of Quint that made it, and how to
+/// upgrade IR written by older versions.
+struct Upgrade {
+    version: Version,
+    apply: fn(&mut Json) -> Result<(), String>,
+}
+
+/// The changes of the IR format, oldest first.
+const UPGRADES: [Upgrade; 1] = [
+    // Quint 0.24.0 started writing the name of the main module (#1623)
+    Upgrade {
+        version: (0, 24, 0),
+        apply: add_main,
+    },
+];
+
+/// Load the IR written by `quint compile`, upgrading it to the current
+/// format if it was written by an older version of Quint.
+pub fn load(mut json: Json) -> Result<QuintOutput, QuintError> {
+    let version = ir_version(&json)?;
+    if json.get("version").is_some() {
+        check_version(version)?;
+    }
+    for upgrade in UPGRADES.iter().filter(|u| version < u.version) {
+        (upgrade.apply)(&mut json).map_err(malformed)?;
+    }
+    serde_path_to_error::deserialize(json)
+        .map_err(|e| malformed(format!("can't read {}: {}", e.path(), e.inner())))
+}
+
+/// Load the IR written by `quint compile`, as a JSON string.
+pub fn load_str(json: &str) -> Result<QuintOutput, QuintError> {
+    load(serde_json::from_str(json).map_err(|e| malformed(e.to_string()))?)
+}
+
+/// The version of Quint that wrote some IR. Quint doesn't write its version
+/// before 0.25.0, so the version of IR without one is taken from its shape:
+/// IR without a `main` field was written by a version older than 0.24.0
+/// (taken as 0.23.0), and IR with it by 0.24.0 or later (taken as 0.24.0).
+pub fn ir_version(json: &Json) -> Result<Version, QuintError> {
+    match json.get("version") {
+        Some(Json::String(version)) => parse_version(version)
+            .ok_or_else(|| malformed(format!("can't read the version {version}"))),
+        Some(version) => Err(malformed(format!("can't read the version {version}"))),
+        None if json.get("main").is_none() => Ok((0, 23, 0)),
+        None => Ok((0, 24, 0)),
+    }
+}
+
+/// Check that the evaluator reads the IR written by a version of Quint that
+/// says so.
+fn check_version(version: Version) -> Result<(), QuintError> {
+    let (major, minor, patch) = version;
+    let (min_major, min_minor, min_patch) = MIN_QUINT_VERSION;
+    let (latest_major, latest_minor) = LATEST_QUINT_VERSION;
+    if version < MIN_QUINT_VERSION {
+        Err(incompatible(format!(
+            "IR written by Quint {major}.{minor}.{patch}, which is older than the evaluator \
+             reads (from {min_major}.{min_minor}.{min_patch}). Use a newer version of Quint"
+        )))
+    } else if (major, minor) > LATEST_QUINT_VERSION {
+        Err(incompatible(format!(
+            "IR written by Quint {major}.{minor}.{patch}, which is newer than the evaluator \
+             reads (up to {latest_major}.{latest_minor}.x). Update the evaluator, or use an \
+             older version of Quint"
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+/// Upgrade IR written before Quint 0.24.0, which picked the last module as
+/// the main one.
+fn add_main(json: &mut Json) -> Result<(), String> {
+    let main = json
+        .get("modules")
+        .and_then(Json::as_array)
+        .and_then(|modules| modules.last())
+        .and_then(|module| module.get("name"))
+        .cloned()
+        .ok_or("can't find the main module")?;
+    json["main"] = main;
+    Ok(())
+}
+
+fn malformed(message: String) -> QuintError {
+    QuintError::new(ErrorKind::MalformedIr, &format!("Malformed IR: {message}"))
+}
+
+fn incompatible(message: String) -> QuintError {
+    QuintError::new(ErrorKind::MalformedIr, &message)
+}
@@ -1,9 +1,20 @@
 //! Logging module for the simulation, used by the CLI.
 
+use crate::output::Verbosity;
 use std::fmt;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicU8};
 
-static _HEADERS: &[&str] = &["Parsing", "Simulation", "Result", "Elapsed"];
+static _HEADERS: &[&str] = &[
+    "Parsing",
+    "Simulation",
+    "Explore",
+    "Replay",
+    "Result",
+    "Invariant",
+    "Temporal",
+    "Elapsed",
+    "Counters",
+];
 
 pub static JSON: AtomicBool = AtomicBool::new(false);
 
@@ -15,6 +26,17 @@ pub fn get_json() -> bool {
     JSON.load(std::sync::atomic::Ordering::Relaxed)
 }
 
+/// How much to log, as the level of a [`Verbosity`].
+pub static VERBOSITY: AtomicU8 = AtomicU8::new(2);
+
+pub fn set_verbosity(value: Verbosity) {
+    VERBOSITY.store(value.level(), std::sync::atomic::Ordering::Relaxed);
+}
+
+pub fn get_verbosity() -> Verbosity {
+    Verbosity::from_level(VERBOSITY.load(std::sync::atomic::Ordering::Relaxed))
+}
+
 const _: () = {
     let mut max = 0;
     let mut i = 0;
@@ -40,6 +62,9 @@ const _: () = {
 pub fn log(header: &str, message: &fmt::Arguments<'_>) {
     use colored::Colorize;
 
+    if !get_verbosity().logs() {
+        return;
+    }
     if get_json() {
         let json = serde_json::json!({
             "header": header,
@@ -47,7 +72,12 @@ pub fn log(header: &str, message: &fmt::Arguments<'_>) {
         });
         println!("{json}");
     } else {
-        println!("{:>12} {}", header.yellow(), message);
+        let header = if header == "Error" {
+            header.red().bold()
+        } else {
+            header.yellow()
+        };
+        println!("{:>12} {}", header, message);
     }
 }
 
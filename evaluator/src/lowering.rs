@@ -0,0 +1,331 @@
+//! Lowering of specs to the IR the evaluator works with, exported by the
+//! `compile` command of the CLI, so users can see their spec the way the
+//! evaluator sees it.
+//!
+//! A spec is lowered in stages:
+//!  1. parsing, by the Quint typescript tool (see
+//!     [`crate::helpers::compile_from_path`]), and flattening (see
+//!     [`crate::flattening`]), which leaves modules with imports resolved and
+//!     the constants of instances given their values;
+//!  2. instantiating the constants given values, replacing their uses with
+//!     the values, as [`crate::evaluator::Interpreter::set_constants`] does
+//!     at run time (see [`instantiate_constants`]);
+//!  3. simplifying expressions, folding the operators applied to literals
+//!     (see [`simplify`]).
+//!
+//! The result can be written as JSON, like the output of `quint compile`, or
+//! as Quint (see [`crate::printer`]).
+
+use crate::constants;
+use crate::ir::{
+    ErrorKind, LookupDefinition, QuintDeclaration, QuintError, QuintEx, QuintId, QuintOutput,
+};
+use crate::value::Value;
+use serde_json::Value as Json;
+
+/// Lower a compiled spec, instantiating the constants with `values` (by name,
+/// as JSON, see [`crate::constants`]) and simplifying all of its expressions.
+pub fn lower(output: &mut QuintOutput, values: &[(String, Json)]) -> Result<(), QuintError> {
+    instantiate_constants(output, values)?;
+    map_expressions(output, &mut simplify);
+    Ok(())
+}
+
+/// Replace the uses of the constants of the main module with their values,
+/// removing their declarations.
+pub fn instantiate_constants(
+    output: &mut QuintOutput,
+    values: &[(String, Json)],
+) -> Result<(), QuintError> {
+    let error = |message: String| QuintError::new(ErrorKind::InvalidConstant, &message);
+    let mut next_id = max_id(output) + 1;
+    let mut instantiated = Vec::new();
+    for (name, json) in values {
+        let constant = output
+            .table
+            .values()
+            .find_map(|def| match def {
+                LookupDefinition::Definition(QuintDeclaration::QuintConst(c))
+                    if c.name.as_str() == name && c.imported_from.is_none() =>
+                {
+                    Some(c)
+                }
+                _ => None,
+            })
+            .ok_or_else(|| error(format!("Unknown constant {name}, or not used by the spec")))?;
+        let value = constants::value_from_json(json, constant.type_annotation.as_ref())
+            .map_err(|e| error(format!("Invalid value for constant {name}: {e}")))?;
+        instantiated.push((constant.id, value));
+    }
+    // Later values of the same constant take precedence
+    instantiated.reverse();
+    instantiated.dedup_by_key(|(id, _)| *id);
+
+    let mut failure = None;
+    let table = output.table.clone();
+    map_expressions(output, &mut |expr| {
+        replace_names(expr, &mut |id, name| {
+            let def = table.get(&id)?;
+            let (_, value) = instantiated.iter().find(|(c, _)| *c == def.id())?;
+            match value_to_expr(value, &mut next_id) {
+                Ok(expr) => Some(expr),
+                Err(e) => {
+                    failure.get_or_insert(format!("Can't instantiate constant {name}: {e}"));
+                    None
+                }
+            }
+        })
+    });
+    if let Some(message) = failure {
+        return Err(error(message));
+    }
+
+    let is_instantiated = |id: QuintId| instantiated.iter().any(|(c, _)| *c == id);
+    for module in &mut output.modules {
+        module.declarations.retain(
+            |decl| !matches!(decl, QuintDeclaration::QuintConst(c) if is_instantiated(c.id)),
+        );
+    }
+    output.table.retain(|_, def| {
+        !matches!(def, LookupDefinition::Definition(QuintDeclaration::QuintConst(c)) if is_instantiated(c.id))
+    });
+    Ok(())
+}
+
+/// Simplify an expression, folding the arithmetic, comparisons and boolean
+/// operators applied to literals, and conditionals on literals.
+pub fn simplify(expr: QuintEx) -> QuintEx {
+    match expr {
+        QuintEx::QuintApp { id, opcode, args } => {
+            let args: Vec<_> = args.into_iter().map(simplify).collect();
+            fold(id, opcode.as_str(), &args).unwrap_or(QuintEx::QuintApp { id, opcode, args })
+        }
+        QuintEx::QuintLambda { id, params, expr } => QuintEx::QuintLambda {
+            id,
+            params,
+            expr: Box::new(simplify(*expr)),
+        },
+        QuintEx::QuintLet {
+            id,
+            mut opdef,
+            expr,
+        } => {
+            opdef.expr = simplify(opdef.expr);
+            QuintEx::QuintLet {
+                id,
+                opdef,
+                expr: Box::new(simplify(*expr)),
+            }
+        }
+        _ => expr,
+    }
+}
+
+/// Fold an operator applied to (simplified) arguments, if it can be.
+fn fold(id: QuintId, opcode: &str, args: &[QuintEx]) -> Option<QuintEx> {
+    let int = |value: i64| QuintEx::QuintInt { id, value };
+    let bool = |value: bool| QuintEx::QuintBool { id, value };
+
+    match (opcode, args) {
+        ("ite", [QuintEx::QuintBool { value, .. }, then, otherwise]) => {
+            Some(if *value { then } else { otherwise }.clone())
+        }
+        ("not", [QuintEx::QuintBool { value, .. }]) => Some(bool(!value)),
+        ("and" | "or", _) => {
+            // `true` doesn't change a conjunction, nor `false` a disjunction
+            let neutral = opcode == "and";
+            let mut rest = Vec::new();
+            for arg in args {
+                match arg {
+                    QuintEx::QuintBool { value, .. } if *value == neutral => {}
+                    QuintEx::QuintBool { .. } => return Some(bool(!neutral)),
+                    _ => rest.push(arg.clone()),
+                }
+            }
+            match rest.len() {
+                0 => Some(bool(neutral)),
+                1 => rest.pop(),
+                n if n < args.len() => Some(QuintEx::QuintApp {
+                    id,
+                    opcode: opcode.into(),
+                    args: rest,
+                }),
+                _ => None,
+            }
+        }
+        ("iuminus", [QuintEx::QuintInt { value, .. }]) => value.checked_neg().map(int),
+        (_, [QuintEx::QuintInt { value: a, .. }, QuintEx::QuintInt { value: b, .. }]) => {
+            match opcode {
+                "iadd" => a.checked_add(*b).map(int),
+                "isub" => a.checked_sub(*b).map(int),
+                "imul" => a.checked_mul(*b).map(int),
+                "ilt" => Some(bool(a < b)),
+                "ilte" => Some(bool(a <= b)),
+                "igt" => Some(bool(a > b)),
+                "igte" => Some(bool(a >= b)),
+                "eq" => Some(bool(a == b)),
+                "neq" => Some(bool(a != b)),
+                _ => None,
+            }
+        }
+        (_, [QuintEx::QuintBool { value: a, .. }, QuintEx::QuintBool { value: b, .. }]) => {
+            match opcode {
+                "eq" | "iff" => Some(bool(a == b)),
+                "neq" => Some(bool(a != b)),
+                "implies" => Some(bool(!a || *b)),
+                _ => None,
+            }
+        }
+        (_, [QuintEx::QuintStr { value: a, .. }, QuintEx::QuintStr { value: b, .. }]) => {
+            match opcode {
+                "eq" => Some(bool(a == b)),
+                "neq" => Some(bool(a != b)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Apply `f` to the expressions of all definitions of a spec, in its modules
+/// and in its lookup table.
+fn map_expressions(output: &mut QuintOutput, f: &mut dyn FnMut(QuintEx) -> QuintEx) {
+    let mut map_declaration = |decl: &mut QuintDeclaration| match decl {
+        QuintDeclaration::QuintOpDef(def) => def.expr = f(def.expr.clone()),
+        QuintDeclaration::QuintAssume(assume) => assume.assumption = f(assume.assumption.clone()),
+        _ => {}
+    };
+    for module in &mut output.modules {
+        module
+            .declarations
+            .iter_mut()
+            .for_each(&mut map_declaration);
+    }
+    for def in output.table.values_mut() {
+        if let LookupDefinition::Definition(decl) = def {
+            map_declaration(decl);
+        }
+    }
+}
+
+/// Replace the names in an expression for which `f` returns an expression.
+fn replace_names(expr: QuintEx, f: &mut dyn FnMut(QuintId, &str) -> Option<QuintEx>) -> QuintEx {
+    match expr {
+        QuintEx::QuintName { id, ref name } => f(id, name).unwrap_or(expr),
+        QuintEx::QuintApp { id, opcode, args } => QuintEx::QuintApp {
+            id,
+            opcode,
+            args: args.into_iter().map(|arg| replace_names(arg, f)).collect(),
+        },
+        QuintEx::QuintLambda { id, params, expr } => QuintEx::QuintLambda {
+            id,
+            params,
+            expr: Box::new(replace_names(*expr, f)),
+        },
+        QuintEx::QuintLet {
+            id,
+            mut opdef,
+            expr,
+        } => {
+            opdef.expr = replace_names(opdef.expr, f);
+            QuintEx::QuintLet {
+                id,
+                opdef,
+                expr: Box::new(replace_names(*expr, f)),
+            }
+        }
+        _ => expr,
+    }
+}
+
+/// An expression for a value, with new ids starting from `next_id`.
+fn value_to_expr(value: &Value, next_id: &mut QuintId) -> Result<QuintEx, String> {
+    let id = fresh(next_id);
+    let app = |opcode: &str, args: Vec<QuintEx>| QuintEx::QuintApp {
+        id,
+        opcode: opcode.into(),
+        args,
+    };
+    let all = |values: &mut dyn Iterator<Item = &Value>, next_id: &mut QuintId| {
+        values
+            .map(|v| value_to_expr(v, next_id))
+            .collect::<Result<Vec<_>, _>>()
+    };
+
+    Ok(match value {
+        Value::Int(value) => QuintEx::QuintInt { id, value: *value },
+        Value::Bool(value) => QuintEx::QuintBool { id, value: *value },
+        Value::Str(value) => QuintEx::QuintStr {
+            id,
+            value: value.clone(),
+        },
+        Value::Set(set) => app("Set", all(&mut set.iter(), next_id)?),
+        Value::List(list) => app("List", all(&mut list.iter(), next_id)?),
+        Value::Tuple(elems) => app("Tup", all(&mut elems.iter(), next_id)?),
+        Value::Record(fields) => {
+            let mut args = Vec::new();
+            for (name, v) in fields.iter() {
+                args.push(QuintEx::QuintStr {
+                    id: fresh(next_id),
+                    value: name.as_str().into(),
+                });
+                args.push(value_to_expr(v, next_id)?);
+            }
+            app("Rec", args)
+        }
+        Value::Map(map) => {
+            let mut pairs = Vec::new();
+            for (k, v) in map.iter() {
+                pairs.push(QuintEx::QuintApp {
+                    id: fresh(next_id),
+                    opcode: "Tup".into(),
+                    args: vec![value_to_expr(k, next_id)?, value_to_expr(v, next_id)?],
+                });
+            }
+            app("Map", pairs)
+        }
+        Value::Variant(label, payload) => {
+            let label = QuintEx::QuintStr {
+                id: fresh(next_id),
+                value: label.as_str().into(),
+            };
+            app("variant", vec![label, value_to_expr(payload, next_id)?])
+        }
+        _ => return Err(format!("{value} can't be written as an expression")),
+    })
+}
+
+fn fresh(next_id: &mut QuintId) -> QuintId {
+    *next_id += 1;
+    *next_id - 1
+}
+
+/// The largest id in a spec, to create new expressions with fresh ids.
+fn max_id(output: &QuintOutput) -> QuintId {
+    fn max_in(expr: &QuintEx) -> QuintId {
+        match expr {
+            QuintEx::QuintApp { id, args, .. } => args.iter().map(max_in).fold(*id, QuintId::max),
+            QuintEx::QuintLambda { id, params, expr } => params
+                .iter()
+                .map(|p| p.id)
+                .fold(max_in(expr).max(*id), QuintId::max),
+            QuintEx::QuintLet { id, opdef, expr } => (*id)
+                .max(opdef.id)
+                .max(max_in(&opdef.expr))
+                .max(max_in(expr)),
+            _ => expr.id(),
+        }
+    }
+
+    let table = output.table.keys().copied().max().unwrap_or_default();
+    output
+        .table
+        .values()
+        .filter_map(|def| match def {
+            LookupDefinition::Definition(QuintDeclaration::QuintOpDef(def)) => Some(&def.expr),
+            LookupDefinition::Definition(QuintDeclaration::QuintAssume(a)) => Some(&a.assumption),
+            _ => None,
+        })
+        .map(max_in)
+        .fold(table, QuintId::max)
+}
@@ -1,37 +1,101 @@
-//! The CLI has two main commands:
+//! The CLI has eleven main commands:
 //!  1. `run`: Runs the simulation on a file with specified parameters,
 //!     to be used for development and tests.
 //!  2. `simulate-from-stdin`: Reads input from standard input (STDIN) and
 //!     simulates based on that input, used in the integration with the `quint` typescript tool.
+//!  3. `replay`: Replays an ITF trace against a spec, i.e. to check that a
+//!     known counterexample is still reproducible (or not) after fixing the spec,
+//!     or that the evaluator agrees with a counterexample found by Apalache or TLC.
+//!  4. `test`: Executes the `run` definitions of a spec, reporting the ones that fail.
+//!  5. `server`: Serves requests to compile, evaluate, simulate and replay
+//!     specs over JSON-RPC, as a persistent backend (see [`quint_evaluator::server`]).
+//!  6. `backend`: Speaks the protocol the `quint` typescript tool uses to
+//!     delegate simulations to a subprocess (see [`quint_evaluator::protocol`]).
+//!  7. `repl`: Evaluates expressions and takes actions interactively, keeping
+//!     the state between inputs (see [`quint_evaluator::repl`]).
+//!  8. `browse`: Steps through the states of a trace interactively (see
+//!     [`quint_evaluator::browser`]).
+//!  9. `eval`: Evaluates a single expression over a spec, optionally in a
+//!     state of a trace, for scripts and quick checks.
+//! 10. `compile`: Writes a spec as the evaluator sees it, after flattening,
+//!     instantiating constants and simplifying (see
+//!     [`quint_evaluator::lowering`]), as Quint or JSON.
+//! 11. `builtins`: Lists the built-in operators the evaluator supports, with
+//!     their types and documentation (see [`quint_evaluator::reflection`]).
 
+use std::cell::RefCell;
+use std::fmt::Display;
 use std::fs::{self, File};
-use std::io::{self, Read, Write};
-use std::path::PathBuf;
-use std::time::Instant;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::TcpListener;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
 
 use argh::FromArgs;
-use eyre::bail;
-use quint_evaluator::ir::{QuintError, QuintEx};
-use quint_evaluator::simulator::{ParsedQuint, ProgressUpdate, SimulationResult};
-use quint_evaluator::{helpers, log};
-use serde::{Deserialize, Serialize};
+use colored::Colorize;
+use eyre::{bail, eyre};
+use quint_evaluator::browser::{Browser, Evaluate};
+use quint_evaluator::counters::Counters;
+use quint_evaluator::diagnostics::Diagnostic;
+use quint_evaluator::explorer::ExplorerConfig;
+use quint_evaluator::handoff::Handoff;
+use quint_evaluator::helpers::{self, Properties};
+use quint_evaluator::ir::QuintError;
+use quint_evaluator::metrics::{self, Metrics};
+use quint_evaluator::ndjson::NdjsonWriter;
+use quint_evaluator::output::{DebugOutput, Output, Verbosity};
+use quint_evaluator::protocol::{self, SimulateInput};
+use quint_evaluator::repl::Repl;
+use quint_evaluator::report::{Outcome, Report};
+use quint_evaluator::server::{error_outcome, to_outcome, Server};
+use quint_evaluator::simulator::{
+    Agreement, InitStrategy, ParsedQuint, ProbabilityEstimate, ProgressUpdate, ReplayOutcome,
+    SimulationHooks, SimulationResult, SimulatorConfig, Strategy, TraceQuality, WitnessCount,
+};
+#[cfg(feature = "sqlite")]
+use quint_evaluator::store::TraceStore;
+use quint_evaluator::tester::{self, TestOutcome};
+use quint_evaluator::trace::Trace;
+use quint_evaluator::value::Value;
+use quint_evaluator::watch::Watcher;
+use quint_evaluator::{counters, log, lowering, mbt, printer, reflection, tlc};
+use serde_json::json;
 
 #[derive(FromArgs)]
 #[argh(description = "Quint simulator")]
 struct TopLevel {
+    /// never use colors in the output, which is colored by default when
+    /// writing to a terminal (and `NO_COLOR` is not set)
+    #[argh(switch)]
+    no_color: bool,
+
     #[argh(subcommand)]
     command: Command,
 }
 
+// The command is parsed once, so the size of its largest variant doesn't matter
+#[allow(clippy::large_enum_variant)]
 #[derive(FromArgs)]
 #[argh(subcommand)]
 enum Command {
     Run(RunArgs),
     SimulateFromStdin(SimulateQuintArgs),
+    Replay(ReplayArgs),
+    Test(TestArgs),
+    Server(ServerArgs),
+    Backend(BackendArgs),
+    Repl(ReplArgs),
+    Browse(BrowseArgs),
+    Eval(EvalArgs),
+    Compile(CompileArgs),
+    Builtins(BuiltinsArgs),
 }
 
 /// Run simulation with command-line arguments
-#[derive(FromArgs)]
+#[derive(FromArgs, Clone)]
 #[argh(subcommand, name = "run")]
 struct RunArgs {
     /// the file to read
@@ -50,6 +114,50 @@ struct RunArgs {
     #[argh(option)]
     inv: Option<String>,
 
+    /// name of an invariant to check and report on individually, can be
+    /// repeated (cannot be combined with --inv). A single one can also be an
+    /// expression, as in `quint run --invariant`
+    #[argh(option)]
+    invariant: Vec<String>,
+
+    /// name of a temporal property to check over the generated traces, can
+    /// be repeated
+    #[argh(option)]
+    temporal: Vec<String>,
+
+    /// name of a witness to count the samples and states satisfying it, can
+    /// be repeated
+    #[argh(option)]
+    witness: Vec<String>,
+
+    /// same as --witness, as named in `quint run`
+    #[argh(option)]
+    witnesses: Vec<String>,
+
+    /// name of a variable to hide from the printed traces (not from the
+    /// files), can be repeated
+    #[argh(option)]
+    hide: Vec<String>,
+
+    /// how much to print, from 0 (nothing but errors) to 5, where 3 and
+    /// above also print the traces, as in `quint run` (default: 2)
+    #[argh(option, default = "2")]
+    verbosity: u8,
+
+    /// the format of the output: text, for the log, or json, for a single
+    /// JSON document with the result (default: text)
+    #[argh(
+        option,
+        default = "OutputFormat::Text",
+        from_str_fn(parse_output_format)
+    )]
+    out_format: OutputFormat,
+
+    /// with --out-format json, include the traces in the document, besides
+    /// writing them to files
+    #[argh(switch)]
+    inline_traces: bool,
+
     /// name of the main module to check (default: computed from filename)
     #[argh(option)]
     main: Option<String>,
@@ -65,6 +173,431 @@ struct RunArgs {
     /// how many traces to generate (only affects output to out-itf) (default: 1)
     #[argh(option, default = "1")]
     n_traces: usize,
+
+    /// output the traces in the Informal Trace Format to files, e.g.,
+    /// out_{seq}.itf.json where {seq} is the trace sequence number
+    /// (default: out_{seq}.itf.json)
+    #[argh(option, default = "\"out_{seq}.itf.json\".to_string()")]
+    out_itf: String,
+
+    /// run again whenever the spec, or another Quint file in its directory,
+    /// changes, until interrupted
+    #[argh(switch)]
+    watch: bool,
+
+    /// collect evaluation counters and report them at the end
+    #[argh(switch)]
+    counters: bool,
+
+    /// measure how much of each state is shared with the previous one, and
+    /// how many values are cloned, reporting it with the counters (implies
+    /// --counters)
+    #[argh(switch)]
+    sharing: bool,
+
+    /// the number of threads to split the samples across (default: 1)
+    #[argh(option, default = "1")]
+    threads: usize,
+
+    /// explore all states up to --max-steps steps, breadth-first, instead of
+    /// sampling random traces
+    #[argh(switch)]
+    exhaustive: bool,
+
+    /// run many short walks with restarts, with lengths growing up to
+    /// --max-steps, instead of samples of the same length
+    #[argh(switch)]
+    restarts: bool,
+
+    /// stop starting new samples after this many seconds, reporting the
+    /// results so far
+    #[argh(option)]
+    max_time: Option<u64>,
+
+    /// weight for picking an action of `any`, as <action>=<weight> (e.g.
+    /// crash=10), where actions have weight 1 by default. Can be repeated.
+    #[argh(option, from_str_fn(parse_action_weight))]
+    action_weight: Vec<(String, u32)>,
+
+    /// run all samples, estimating the probability of violating each
+    /// invariant in a sample, instead of stopping at the first violation
+    #[argh(switch)]
+    estimate: bool,
+
+    /// keep simulating after violations, reporting each distinct violating
+    /// state with a representative trace
+    #[argh(switch)]
+    all_violations: bool,
+
+    /// evaluate init again while it gives an initial state that was already
+    /// used, to cover more distinct initial states
+    #[argh(switch)]
+    distinct_init: bool,
+
+    /// enumerate up to this many initial states, trying all choices in init,
+    /// and start the samples from each of them in turn
+    #[argh(option)]
+    enumerate_init: Option<usize>,
+
+    /// write the action taken and the nondeterministic picks on each step as
+    /// the mbt::actionTaken and mbt::nondetPicks variables of the states
+    #[argh(switch)]
+    mbt: bool,
+
+    /// output the traces as test vectors for model-based testing to files,
+    /// e.g., test_{seq}.json where {seq} is the trace sequence number
+    /// (implies --mbt)
+    #[argh(option)]
+    out_tests: Option<String>,
+
+    /// name of a variable to include in the expected states of the test
+    /// vectors, can be repeated (default: all variables)
+    #[argh(option)]
+    expected_var: Vec<String>,
+
+    /// the master seed for the random number generator, as a decimal or
+    /// hexadecimal (0x...) number (default: random)
+    #[argh(option, from_str_fn(parse_seed))]
+    seed: Option<u64>,
+
+    /// only run the sample with this number, as reported with violations,
+    /// to reproduce it in isolation with the same --seed
+    #[argh(option)]
+    sample_index: Option<usize>,
+
+    /// print the traces written to files, showing only the variables that
+    /// changed on each state
+    #[argh(switch)]
+    print_traces: bool,
+
+    /// print every variable on each state with --print-traces
+    #[argh(switch)]
+    full_states: bool,
+
+    /// print the traces with --print-traces in TLA+ syntax, as TLC does,
+    /// with every variable on each state
+    #[argh(switch)]
+    tla: bool,
+
+    /// with --exhaustive, write the graph of the explored states to this
+    /// file, in the DOT language of Graphviz
+    #[argh(option)]
+    out_dot: Option<String>,
+
+    /// name of a definition to label the states with in --out-dot (default:
+    /// the whole state)
+    #[argh(option)]
+    state_label: Option<String>,
+
+    /// how to measure the quality of traces without violations, to keep the
+    /// best ones: length, states (distinct states) or actions (distinct
+    /// actions taken) (default: length)
+    #[argh(
+        option,
+        default = "TraceQuality::Length",
+        from_str_fn(parse_trace_quality)
+    )]
+    trace_quality: TraceQuality,
+
+    /// when no violation is found, write the artifacts to check the same
+    /// properties with Apalache (with `quint verify`) to this directory
+    #[argh(option)]
+    out_handoff: Option<PathBuf>,
+
+    /// write the states of every sample to this SQLite database, to query
+    /// them afterwards (requires the `sqlite` feature, cannot be combined
+    /// with --threads)
+    #[argh(option)]
+    out_sqlite: Option<PathBuf>,
+
+    /// write the errors found when compiling or evaluating the spec to this
+    /// file, as a JSON array of diagnostics in the shape of LSP diagnostics,
+    /// with the file of each one
+    #[argh(option)]
+    out_diagnostics: Option<PathBuf>,
+
+    /// stream every state to this file as newline-delimited JSON, as the
+    /// simulation progresses, or to STDOUT with `-` (cannot be combined with
+    /// --threads)
+    #[argh(option)]
+    out_ndjson: Option<String>,
+
+    /// name of an action of `any` to take with weak fairness, i.e. it is
+    /// taken when enabled after --fairness-period steps without it, can be
+    /// repeated
+    #[argh(option)]
+    fair: Vec<String>,
+
+    /// the number of steps without taking a fair action before it is forced
+    /// (default: 10)
+    #[argh(option, default = "10")]
+    fairness_period: usize,
+
+    /// name of the action of a composed component (e.g. an instance of a
+    /// module) to interleave with the others instead of taking the step, can
+    /// be repeated. Variables not assigned by the action keep their values
+    #[argh(option)]
+    component: Vec<String>,
+
+    /// value of a constant of the main module, as <name>=<value>, with the
+    /// value in JSON (e.g. N=5 or Nodes=[1,2,3]), or as a string if it isn't
+    /// valid JSON. Can be repeated, and overrides the values of --constants
+    #[argh(option, long = "const", from_str_fn(parse_constant))]
+    constant: Vec<(String, serde_json::Value)>,
+
+    /// a JSON file with the values of constants of the main module, as an
+    /// object by their names (e.g. {"N": 5, "Nodes": [1, 2, 3]})
+    #[argh(option)]
+    constants: Option<PathBuf>,
+
+    /// fail when an operator enumerates a set with more elements than this,
+    /// e.g. mapping over a large interval, to find accidental blowups while
+    /// developing a spec
+    #[argh(option)]
+    max_enumeration: Option<usize>,
+}
+
+/// Parse an action weight given as `<action>=<weight>`
+fn parse_action_weight(value: &str) -> Result<(String, u32), String> {
+    let (name, weight) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Expected <action>=<weight>, got {value}"))?;
+    let weight = weight
+        .parse()
+        .map_err(|_| format!("Invalid weight for action {name}: {weight}"))?;
+    Ok((name.to_string(), weight))
+}
+
+/// Parse the value of a constant given as `<name>=<value>`
+fn parse_constant(value: &str) -> Result<(String, serde_json::Value), String> {
+    let (name, json) = value
+        .split_once('=')
+        .ok_or_else(|| format!("Expected <name>=<value>, got {value}"))?;
+    let json =
+        serde_json::from_str(json).unwrap_or_else(|_| serde_json::Value::String(json.to_string()));
+    Ok((name.to_string(), json))
+}
+
+/// The values of constants given with --constants and --const, in that order,
+/// so values from --const take precedence
+fn read_constants(
+    file: Option<&Path>,
+    values: &[(String, serde_json::Value)],
+) -> eyre::Result<Vec<(String, serde_json::Value)>> {
+    let mut constants = Vec::new();
+    if let Some(path) = file {
+        let file: serde_json::Map<String, serde_json::Value> =
+            serde_json::from_reader(BufReader::new(File::open(path)?))
+                .map_err(|e| eyre!("Invalid constants in {}: {e}", path.display()))?;
+        constants.extend(file);
+    }
+    constants.extend(values.iter().cloned());
+    Ok(constants)
+}
+
+/// The format of the output of a command, see `--out-format`
+#[derive(Clone, Copy, PartialEq)]
+enum OutputFormat {
+    /// The log, for humans
+    Text,
+    /// A single JSON document with the result, see [`Report`]
+    Json,
+}
+
+fn parse_output_format(value: &str) -> Result<OutputFormat, String> {
+    match value {
+        "text" => Ok(OutputFormat::Text),
+        "json" => Ok(OutputFormat::Json),
+        _ => Err(format!(
+            "Invalid output format: {value}, expected text or json"
+        )),
+    }
+}
+
+/// The fields of a JSON object, for the details of a report
+fn json_map(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+    match value {
+        serde_json::Value::Object(map) => map,
+        _ => serde_json::Map::new(),
+    }
+}
+
+/// The output of the spec for a `--verbosity` and `--out-format`, where the
+/// values given to `q::debug` are dropped when printing a report.
+fn output(verbosity: u8, format: OutputFormat) -> Output {
+    Output {
+        verbosity: Verbosity::from_level(verbosity),
+        debug: match format {
+            OutputFormat::Text => DebugOutput::Print,
+            OutputFormat::Json => DebugOutput::Silent,
+        },
+    }
+}
+
+/// Print the report of a command, with `--out-format json`. Nothing else is
+/// printed with it, as the log is disabled.
+fn print_report(format: OutputFormat, report: &Report) -> eyre::Result<()> {
+    if format == OutputFormat::Json {
+        println!("{}", serde_json::to_string_pretty(report)?);
+    }
+    Ok(())
+}
+
+/// Parse a trace quality metric, by the names given in `--trace-quality`
+fn parse_trace_quality(value: &str) -> Result<TraceQuality, String> {
+    match value {
+        "length" => Ok(TraceQuality::Length),
+        "states" => Ok(TraceQuality::DistinctStates),
+        "actions" => Ok(TraceQuality::DistinctActions),
+        _ => Err(format!(
+            "Invalid trace quality: {value}, expected length, states or actions"
+        )),
+    }
+}
+
+/// Parse a seed given as a decimal or hexadecimal (`0x...`) number
+fn parse_seed(value: &str) -> Result<u64, String> {
+    match value.strip_prefix("0x") {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.parse(),
+    }
+    .map_err(|_| format!("Invalid seed: {value}"))
+}
+
+/// Replay an ITF trace against a spec
+#[derive(FromArgs)]
+#[argh(subcommand, name = "replay")]
+struct ReplayArgs {
+    /// the file with the spec
+    #[argh(positional)]
+    file: PathBuf,
+
+    /// the file with the trace to replay, in ITF (`.json`) or as printed by
+    /// TLC (any other extension)
+    #[argh(positional)]
+    trace: PathBuf,
+
+    /// name of the initializer action (default: "init")
+    #[argh(option, default = "\"init\".to_string()")]
+    init: String,
+
+    /// name of the step action (default: "step")
+    #[argh(option, default = "\"step\".to_string()")]
+    step: String,
+
+    /// name of the invariant to check
+    #[argh(option)]
+    inv: Option<String>,
+
+    /// name of the main module to check (default: computed from filename)
+    #[argh(option)]
+    main: Option<String>,
+
+    /// how many times to evaluate each action, trying to produce the next
+    /// state of the trace, for states without recorded picks (default: 1000)
+    #[argh(option, default = "1000")]
+    attempts: usize,
+
+    /// check that the evaluator agrees with the trace, as found by another
+    /// tool like Apalache or TLC: "violation" for a counterexample, whose
+    /// last state violates the invariant, or "ok" for a trace without
+    /// violations
+    #[argh(option, from_str_fn(parse_expectation))]
+    expect: Option<bool>,
+
+    /// the format of the output: text, for the log, or json, for a single
+    /// JSON document with the result (default: text)
+    #[argh(
+        option,
+        default = "OutputFormat::Text",
+        from_str_fn(parse_output_format)
+    )]
+    out_format: OutputFormat,
+}
+
+/// Parse the expected result of a trace, i.e. whether it is a violation
+fn parse_expectation(value: &str) -> Result<bool, String> {
+    match value {
+        "violation" => Ok(true),
+        "ok" => Ok(false),
+        _ => Err(format!(
+            "Invalid expectation: {value} (expected \"violation\" or \"ok\")"
+        )),
+    }
+}
+
+/// Execute the `run` definitions of a spec
+#[derive(FromArgs, Clone)]
+#[argh(subcommand, name = "test")]
+struct TestArgs {
+    /// the file with the spec
+    #[argh(positional)]
+    file: PathBuf,
+
+    /// name of the main module to test (default: computed from filename)
+    #[argh(option)]
+    main: Option<String>,
+
+    /// only execute the runs with names containing this text
+    #[argh(option, long = "match")]
+    filter: Option<String>,
+
+    /// the master seed for the random number generator, as a decimal or
+    /// hexadecimal (0x...) number (default: random)
+    #[argh(option, from_str_fn(parse_seed))]
+    seed: Option<u64>,
+
+    /// the maximum number of times to execute each run with
+    /// nondeterministic choices, stopping at the first failure (default:
+    /// 10000)
+    #[argh(option, default = "10000")]
+    max_samples: usize,
+
+    /// how much to print, from 0 (nothing but errors) to 5, where 3 and
+    /// above also print the traces of the runs that passed, as in `quint
+    /// test` (default: 2)
+    #[argh(option, default = "2")]
+    verbosity: u8,
+
+    /// the format of the output: text, for the log, or json, for a single
+    /// JSON document with the result (default: text)
+    #[argh(
+        option,
+        default = "OutputFormat::Text",
+        from_str_fn(parse_output_format)
+    )]
+    out_format: OutputFormat,
+
+    /// with --out-format json, include the traces in the document, besides
+    /// writing them to files
+    #[argh(switch)]
+    inline_traces: bool,
+
+    /// output the traces of failing runs in the Informal Trace Format to
+    /// files, e.g., out_{seq}.itf.json where {seq} is the name of the run
+    /// (default: out_{seq}.itf.json)
+    #[argh(option, default = "\"out_{seq}.itf.json\".to_string()")]
+    out_itf: String,
+
+    /// run again whenever the spec, or another Quint file in its directory,
+    /// changes, until interrupted
+    #[argh(switch)]
+    watch: bool,
+}
+
+/// Serve JSON-RPC requests on STDIN, or on a socket
+#[derive(FromArgs)]
+#[argh(subcommand, name = "server")]
+struct ServerArgs {
+    /// the address to listen on, e.g. 127.0.0.1:4242, serving one
+    /// connection at a time (default: serve on STDIN and STDOUT)
+    #[argh(option)]
+    listen: Option<String>,
+
+    /// the address to serve the metrics of the simulations on, for
+    /// Prometheus, e.g. 127.0.0.1:9184 (at /metrics)
+    #[argh(option)]
+    metrics: Option<String>,
 }
 
 /// Run simulation with input from STDIN
@@ -72,43 +605,138 @@ struct RunArgs {
 #[argh(subcommand, name = "simulate-from-stdin")]
 struct SimulateQuintArgs {}
 
-/// Data expected on STDIN for simulation
-#[derive(Serialize, Deserialize)]
-struct SimulateInput {
-    parsed: ParsedQuint,
-    source: String,
-    witnesses: Vec<QuintEx>,
-    nruns: usize,
-    nsteps: usize,
-    ntraces: usize,
-}
-
-#[derive(Serialize)]
-#[serde(rename_all = "lowercase")]
-enum SimulationStatus {
-    #[serde(rename = "ok")]
-    Success,
-    Violation,
-    Error,
-}
-
-/// Data to be written to STDOUT after simulation
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct Outcome {
-    status: SimulationStatus,
-    errors: Vec<QuintError>,
-    best_traces: Vec<SimulationTrace>,
-    witnessing_traces: Vec<usize>,
-    samples: usize,
+/// Simulate with the protocol of the `quint` CLI on STDIN and STDOUT
+#[derive(FromArgs)]
+#[argh(subcommand, name = "backend")]
+struct BackendArgs {}
+
+/// Evaluate expressions and take actions interactively, keeping the state
+#[derive(FromArgs)]
+#[argh(subcommand, name = "repl")]
+struct ReplArgs {
+    /// the file with the spec to load
+    #[argh(positional)]
+    file: Option<PathBuf>,
+
+    /// name of the module to load (default: computed from filename)
+    #[argh(option)]
+    main: Option<String>,
+}
+
+/// Evaluate an expression once, optionally in a state of a trace
+#[derive(FromArgs)]
+#[argh(subcommand, name = "eval")]
+struct EvalArgs {
+    /// the file with the spec
+    #[argh(positional)]
+    file: PathBuf,
+
+    /// the expression to evaluate, which can use the definitions of the spec
+    #[argh(positional)]
+    expr: String,
+
+    /// name of the module of the spec (default: computed from filename)
+    #[argh(option)]
+    main: Option<String>,
+
+    /// a trace (in ITF, or as printed by TLC) with the state to evaluate the
+    /// expression in, which is the last one unless --state is given
+    #[argh(option)]
+    trace: Option<PathBuf>,
+
+    /// the index of the state of --trace to evaluate the expression in,
+    /// starting from 0
+    #[argh(option)]
+    state: Option<usize>,
+
+    /// the format of the value: text, as Quint shows it, or json, in ITF
+    /// (default: text)
+    #[argh(
+        option,
+        default = "OutputFormat::Text",
+        from_str_fn(parse_output_format)
+    )]
+    out_format: OutputFormat,
+}
+
+/// Lower a spec to the IR the evaluator works with, for inspection
+#[derive(FromArgs)]
+#[argh(subcommand, name = "compile")]
+struct CompileArgs {
+    /// the file with the spec
+    #[argh(positional)]
+    file: PathBuf,
+
+    /// name of the module of the spec (default: computed from filename)
+    #[argh(option)]
+    main: Option<String>,
+
+    /// name of the initializer action (default: "init")
+    #[argh(option, default = "\"init\".to_string()")]
+    init: String,
+
+    /// name of the step action (default: "step")
+    #[argh(option, default = "\"step\".to_string()")]
+    step: String,
+
+    /// the invariant, as a definition name or an expression (default: "true")
+    #[argh(option, default = "\"true\".to_string()")]
+    invariant: String,
+
+    /// value of a constant of the main module, as <name>=<value>, like in
+    /// `run`. Can be repeated
+    #[argh(option, long = "const", from_str_fn(parse_constant))]
+    constant: Vec<(String, serde_json::Value)>,
+
+    /// a JSON file with the values of constants of the main module, like in
+    /// `run`
+    #[argh(option)]
+    constants: Option<PathBuf>,
+
+    /// the format of the lowered spec: text, as Quint, or json, in the IR
+    /// (default: text)
+    #[argh(
+        option,
+        default = "OutputFormat::Text",
+        from_str_fn(parse_output_format)
+    )]
+    out_format: OutputFormat,
+
+    /// the file to write the lowered spec to (default: STDOUT)
+    #[argh(option)]
+    out: Option<PathBuf>,
 }
 
-#[derive(Serialize)]
-#[serde(rename_all = "camelCase")]
-struct SimulationTrace {
-    seed: usize,
-    states: itf::Trace<itf::Value>,
-    result: bool,
+/// List the built-in operators the evaluator supports
+#[derive(FromArgs)]
+#[argh(subcommand, name = "builtins")]
+struct BuiltinsArgs {
+    /// the format of the list: text, with a built-in per line, or json, for
+    /// editors and documentation generators (default: text)
+    #[argh(
+        option,
+        default = "OutputFormat::Text",
+        from_str_fn(parse_output_format)
+    )]
+    out_format: OutputFormat,
+}
+
+/// Browse the states of a trace interactively
+#[derive(FromArgs)]
+#[argh(subcommand, name = "browse")]
+struct BrowseArgs {
+    /// the trace to browse, in ITF (.itf.json) or as printed by TLC
+    #[argh(positional)]
+    trace: PathBuf,
+
+    /// the file with the spec of the trace, to go to the states where an
+    /// expression holds
+    #[argh(option)]
+    spec: Option<PathBuf>,
+
+    /// name of the module of the spec (default: computed from filename)
+    #[argh(option)]
+    main: Option<String>,
 }
 
 /// The CLI has two main commands: 1. `run`: Runs the simulation on a file with
@@ -117,59 +745,1120 @@ struct SimulationTrace {
 /// based on that input, used in the integration with the `quint` typescript tool.
 fn main() -> eyre::Result<()> {
     let top_level: TopLevel = argh::from_env();
+    if top_level.no_color {
+        colored::control::set_override(false);
+    }
 
     match top_level.command {
+        Command::Run(args) if args.watch => watch(&args.file, || run_simulation(args.clone())),
         Command::Run(args) => run_simulation(args),
         Command::SimulateFromStdin(_) => simulate_from_stdin(),
+        Command::Replay(args) => replay(args),
+        Command::Test(args) if args.watch => watch(&args.file, || test(args.clone())),
+        Command::Test(args) => test(args),
+        Command::Server(args) => serve(args),
+        Command::Backend(_) => Ok(protocol::serve(io::stdin().lock(), io::stdout().lock())?),
+        Command::Repl(args) => repl(args),
+        Command::Browse(args) => browse(args),
+        Command::Eval(args) => eval(args),
+        Command::Compile(args) => compile(args),
+        Command::Builtins(args) => list_builtins(args),
     }
 }
 
+/// Run a command, and then again whenever the files of the spec change, for
+/// the `--watch` option. Errors are reported without stopping.
+fn watch(file: &Path, mut command: impl FnMut() -> eyre::Result<()>) -> eyre::Result<()> {
+    loop {
+        // The imports may change, so their files are listed again every time,
+        // before running the command so changes while it runs are noticed
+        let mut watcher = match helpers::source_map(file) {
+            Ok(source_map) => Watcher::for_sources(file, &source_map),
+            Err(_) => Watcher::for_spec(file)?,
+        };
+        if let Err(e) = command() {
+            log!("Error", "{e}");
+        }
+        log!(
+            "Watch",
+            "Waiting for changes to {} files",
+            watcher.files().count()
+        );
+        for path in watcher.wait() {
+            log!("Watch", "{} changed", path.display());
+        }
+    }
+}
+
+/// Run a simulation, writing its samples to an SQLite database, besides
+/// running the other `hooks`.
+#[cfg(feature = "sqlite")]
+fn simulate_to_store(
+    parsed: &ParsedQuint,
+    config: &SimulatorConfig,
+    path: &Path,
+    source: &str,
+    hooks: &mut dyn SimulationHooks,
+) -> eyre::Result<Result<SimulationResult, QuintError>> {
+    let mut store = TraceStore::open(path, source)?;
+    let run = store.run();
+    let result = parsed.simulate_with_hooks(config, None, &mut (&mut store, hooks));
+    store.finish()?;
+    log!("Store", "{} (run {run})", path.display());
+    Ok(result)
+}
+
+#[cfg(not(feature = "sqlite"))]
+fn simulate_to_store(
+    _parsed: &ParsedQuint,
+    _config: &SimulatorConfig,
+    _path: &Path,
+    _source: &str,
+    _hooks: &mut dyn SimulationHooks,
+) -> eyre::Result<Result<SimulationResult, QuintError>> {
+    bail!("Option --out-sqlite requires the `sqlite` feature")
+}
+
 /// Utility to run the simulation with command-line arguments. Not meant to be
 /// user-facing, but quite useful for development and testing. This calls the
 /// `quint` typescript binary to parse the provided file (expects `quint` to be
 /// installed and in the PATH).
-fn run_simulation(args: RunArgs) -> eyre::Result<()> {
+fn run_simulation(mut args: RunArgs) -> eyre::Result<()> {
     log::set_json(false);
+    log::set_verbosity(Verbosity::from_level(args.verbosity));
+    if Verbosity::from_level(args.verbosity).traces() {
+        args.print_traces = true;
+    }
+    let witnesses = std::mem::take(&mut args.witnesses);
+    args.witness.extend(witnesses);
+    // The report is the only output
+    if args.out_format == OutputFormat::Json {
+        log::set_verbosity(Verbosity::Quiet);
+        args.print_traces = false;
+    }
 
     if !fs::exists(&args.file)? {
         bail!("File not found: {}", args.file.display());
     }
 
+    if args.inv.is_some() && !args.invariant.is_empty() {
+        bail!("Options --inv and --invariant cannot be combined");
+    }
+
+    if args.out_sqlite.is_some() && args.threads > 1 {
+        bail!("Options --out-sqlite and --threads cannot be combined");
+    }
+
+    if args.out_ndjson.is_some() && args.threads > 1 {
+        bail!("Options --out-ndjson and --threads cannot be combined");
+    }
+
+    if args.out_ndjson.as_deref() == Some("-") && args.out_format == OutputFormat::Json {
+        bail!("Options --out-ndjson - and --out-format json cannot be combined");
+    }
+
+    // Everything written to STDOUT is then JSON, one object per line
+    if args.out_ndjson.as_deref() == Some("-") {
+        log::set_json(true);
+    }
+
     log!("Parsing", "Parsing file: {}", args.file.display());
-    let parsed = helpers::parse_from_path(
+    let invariants = args
+        .invariant
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let temporal = args.temporal.iter().map(String::as_str).collect::<Vec<_>>();
+    let witnesses = args.witness.iter().map(String::as_str).collect::<Vec<_>>();
+    let components = args
+        .component
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    let properties = Properties {
+        invariants: &invariants,
+        temporal: &temporal,
+        witnesses: &witnesses,
+        state_label: args.state_label.as_deref(),
+        components: &components,
+    };
+    let parsed = match helpers::parse_from_path_with_properties(
         &args.file,
         args.init.as_str(),
         args.step.as_str(),
         args.inv.as_deref(),
+        &properties,
         args.main.as_deref(),
-    )
-    .unwrap();
+    ) {
+        Ok(parsed) => parsed,
+        Err(e) => {
+            if args.out_diagnostics.is_some() {
+                let diagnostics = helpers::compilation_diagnostics(
+                    &args.file,
+                    args.init.as_str(),
+                    args.step.as_str(),
+                    args.inv.as_deref(),
+                    &properties,
+                    args.main.as_deref(),
+                )
+                .map_err(|e| eyre!("{e}"))?;
+                write_diagnostics(&args, &diagnostics)?;
+            }
+            let mut report = Report::new("run");
+            report.add_error(&e);
+            print_report(args.out_format, &report)?;
+            bail!("{e}");
+        }
+    };
+
+    counters::set_enabled(args.counters || args.sharing);
+    counters::set_sharing_enabled(args.sharing);
+
+    if args.exhaustive {
+        return explore(&args, &parsed);
+    }
 
     let start = Instant::now();
     log!("Simulation", "Starting simulation");
-    let result = parsed.simulate(args.max_steps, args.max_samples, args.n_traces, None);
+    let source = args.file.display().to_string();
+    let config = SimulatorConfig {
+        steps: args.max_steps,
+        samples: args.max_samples,
+        n_traces: args.n_traces,
+        strategy: if args.restarts {
+            Strategy::Restarts
+        } else {
+            Strategy::FixedLength
+        },
+        time_budget: args.max_time.map(Duration::from_secs),
+        action_weights: args.action_weight.clone(),
+        estimate: args.estimate,
+        collect_violations: args.all_violations,
+        init_strategy: match (args.enumerate_init, args.distinct_init) {
+            (Some(bound), _) => InitStrategy::Enumerate(bound),
+            (None, true) => InitStrategy::Distinct,
+            (None, false) => InitStrategy::Random,
+        },
+        mbt: args.mbt || args.out_tests.is_some(),
+        seed: Some(args.seed.unwrap_or_else(rand::random)),
+        sample: args.sample_index,
+        trace_quality: args.trace_quality,
+        fair_actions: args.fair.clone(),
+        fairness_period: args.fairness_period,
+        constants: read_constants(args.constants.as_deref(), &args.constant)?,
+        cancel: None,
+        output: output(args.verbosity, args.out_format),
+        max_enumeration: args.max_enumeration,
+    };
+    let result = if args.threads > 1 {
+        parsed
+            .simulate_parallel_with_config(&config, args.threads, &source, None)
+            .map(|result| {
+                let trace_scores = result.best_traces.iter().map(|t| t.score).collect();
+                let traces = result.best_traces.into_iter().map(|t| t.trace).collect();
+                let invariants = result
+                    .invariants
+                    .into_iter()
+                    .map(|i| {
+                        let violation = i
+                            .violation
+                            .map(|v| (v.sample, v.seed, v.step, v.trace.trace));
+                        (i.name, violation)
+                    })
+                    .collect::<Vec<_>>();
+                let stats = RunStats {
+                    samples: result.samples,
+                    seed: result.seed,
+                    trace_scores,
+                    timed_out: result.timed_out,
+                    action_weights: result.action_weights,
+                    estimates: result.estimates,
+                    witnesses: result.witnesses,
+                    initial_states: result.initial_states.len(),
+                    enumerated_initial_states: result.enumerated_initial_states,
+                    distinct_violations: result
+                        .distinct_violations
+                        .into_iter()
+                        .map(|v| (v.invariant, v.count, v.trace.trace))
+                        .collect(),
+                };
+                (
+                    result.result,
+                    traces,
+                    invariants,
+                    stats,
+                    Some(result.counters),
+                )
+            })
+    } else {
+        let mut stream = match args.out_ndjson.as_deref() {
+            Some("-") => Some(NdjsonWriter::new(Box::new(io::stdout()) as Box<dyn Write>)),
+            Some(path) => Some(NdjsonWriter::new(
+                Box::new(BufWriter::new(File::create(path)?)) as Box<dyn Write>,
+            )),
+            None => None,
+        };
+        let result = match &args.out_sqlite {
+            Some(path) => simulate_to_store(&parsed, &config, path, &source, &mut stream)?,
+            None => parsed.simulate_with_hooks(&config, None, &mut stream),
+        };
+        if let Some(stream) = stream {
+            stream.finish()?;
+        }
+        result.map(|result| {
+            let to_itf = |trace: Trace| {
+                if config.mbt {
+                    trace.to_mbt_itf(source.clone())
+                } else {
+                    trace.to_itf(source.clone())
+                }
+            };
+            let traces = result
+                .best_traces
+                .into_iter()
+                .map(to_itf)
+                .collect::<Vec<_>>();
+            let invariants = result
+                .invariants
+                .into_iter()
+                .map(|i| {
+                    let violation = i
+                        .violation
+                        .map(|v| (v.sample, v.seed, v.step, to_itf(v.trace)));
+                    (i.name, violation)
+                })
+                .collect::<Vec<_>>();
+            let stats = RunStats {
+                samples: result.samples,
+                seed: result.seed,
+                trace_scores: result.best_trace_scores,
+                timed_out: result.timed_out,
+                action_weights: result.action_weights,
+                estimates: result.estimates,
+                witnesses: result.witnesses,
+                initial_states: result.initial_states.len(),
+                enumerated_initial_states: result.enumerated_initial_states,
+                distinct_violations: result
+                    .distinct_violations
+                    .into_iter()
+                    .map(|v| (v.invariant, v.count, to_itf(v.trace)))
+                    .collect(),
+            };
+            (result.result, traces, invariants, stats, None)
+        })
+    };
+
+    let elapsed = start.elapsed();
+    let mut diagnostics = Vec::new();
+    let mut report = Report::new("run");
+    let inline = args.inline_traces;
+
+    // Counters are per thread, so parallel simulations report the merged ones
+    let mut merged_counters = None;
+
+    match result {
+        Ok((result, traces, invariants, stats, counters)) => {
+            merged_counters = counters;
+            log!("Result", "{}", result);
+            log!("Seed", "{:#x}", stats.seed);
+            let samples = stats.samples;
+            report.set_seed(stats.seed);
+            report.set_statistic("samples", samples);
+            report.set_statistic("timedOut", stats.timed_out);
+            report.set_statistic("initialStates", stats.initial_states);
+            if stats.timed_out {
+                log!("Samples", "{samples} (stopped after --max-time)");
+            } else {
+                log!("Samples", "{samples}");
+            }
+            match stats.enumerated_initial_states {
+                Some(total) => log!(
+                    "Initial",
+                    "{} of {total} enumerated initial states ({:.2}%)",
+                    stats.initial_states,
+                    percentage(stats.initial_states, total)
+                ),
+                None => log!(
+                    "Initial",
+                    "{} distinct initial states",
+                    stats.initial_states
+                ),
+            }
+            // Report the weights actually used, so the simulation can be reproduced
+            for (name, weight) in &stats.action_weights {
+                log!("Weight", "{name}={weight}");
+            }
+            for (name, _) in &args.action_weight {
+                if !stats.action_weights.iter().any(|(n, _)| n == name) {
+                    log!(
+                        "Warning",
+                        "No `any` has an action named {name}, ignoring its weight"
+                    );
+                }
+            }
+            let names = if args.invariant.is_empty() {
+                vec![args.inv.clone().unwrap_or_else(|| "invariant".to_string())]
+            } else {
+                args.invariant.clone()
+            };
+            for (name, count) in args.witness.iter().zip(&stats.witnesses) {
+                report.add_property(
+                    name,
+                    "witness",
+                    true,
+                    None,
+                    json_map(json!({ "samples": count.samples, "states": count.states })),
+                );
+                log!(
+                    "Witness",
+                    "{name} held in {}/{samples} samples ({:.2}%), {} states",
+                    count.samples,
+                    percentage(count.samples, samples),
+                    count.states
+                );
+            }
+            for (name, estimate) in names.iter().zip(&stats.estimates) {
+                log!(
+                    "Estimate",
+                    "{name} violated in {}/{} samples, p = {:.4} ± {:.4}",
+                    estimate.hits,
+                    estimate.samples,
+                    estimate.probability(),
+                    estimate.margin()
+                );
+            }
+            if args.all_violations {
+                log!(
+                    "Violations",
+                    "{} distinct violations",
+                    stats.distinct_violations.len()
+                );
+            }
+            for (i, (invariant, count, itf_trace)) in stats.distinct_violations.iter().enumerate() {
+                let filename = expand_output_template(&args.out_itf, format!("violation{i}"), true);
+                let mut file = File::create(filename.clone())?;
+                file.write_all(serde_json::to_string(itf_trace)?.as_bytes())?;
+                report.add_trace(Some(filename.clone()), itf_trace, inline);
+                log!(
+                    "Violations",
+                    "{} violated {count} times: {filename}",
+                    names[*invariant]
+                )
+            }
+            for (i, (itf_trace, score)) in traces.iter().zip(&stats.trace_scores).enumerate() {
+                let json_data = serde_json::to_string(itf_trace)?;
+                let filename = expand_output_template(&args.out_itf, i, args.n_traces > 1);
+                let mut file = File::create(filename.clone())?;
+                file.write_all(json_data.as_bytes())?;
+                report.add_trace(Some(filename.clone()), itf_trace, inline);
+                // Violations are measured by their length
+                let violation = itf_trace["#meta"]["status"] == "violation";
+                match args.trace_quality {
+                    TraceQuality::DistinctStates if !violation => {
+                        log!("Trace", "{filename} ({score} distinct states)")
+                    }
+                    TraceQuality::DistinctActions if !violation => {
+                        log!("Trace", "{filename} ({score} distinct actions)")
+                    }
+                    _ => log!("Trace", "{filename}"),
+                }
+                print_trace(&args, itf_trace)?;
+            }
+            if let Some(template) = &args.out_tests {
+                for (i, itf_trace) in traces.iter().enumerate() {
+                    let test_case =
+                        mbt::test_case(&Trace::from_itf(itf_trace)?, &args.expected_var);
+                    let filename = expand_output_template(template, i, args.n_traces > 1);
+                    let mut file = File::create(filename.clone())?;
+                    file.write_all(serde_json::to_string(&test_case)?.as_bytes())?;
+                    log!("Test", "{filename}")
+                }
+            }
+            for (name, violation) in invariants {
+                match violation {
+                    None => {
+                        report.add_property(&name, "invariant", true, None, Default::default());
+                        log!("Invariant", "{name} held")
+                    }
+                    Some((sample, sample_seed, step, itf_trace)) => {
+                        // Each violation gets its own file, named after the invariant
+                        let filename = expand_output_template(&args.out_itf, &name, true);
+                        let mut file = File::create(filename.clone())?;
+                        file.write_all(serde_json::to_string(&itf_trace)?.as_bytes())?;
+                        let trace = report.add_trace(Some(filename.clone()), &itf_trace, inline);
+                        report.add_property(
+                            &name,
+                            "invariant",
+                            false,
+                            Some(trace),
+                            json_map(json!({
+                                "sample": sample,
+                                "seed": format!("{sample_seed:#x}"),
+                                "step": step,
+                            })),
+                        );
+                        log!(
+                            "Invariant",
+                            "{name} violated in sample {sample} (seed {sample_seed:#x}), step {step}: {filename}"
+                        );
+                        log!(
+                            "Invariant",
+                            "Reproduce with --seed {:#x} --sample-index {sample}",
+                            stats.seed
+                        );
+                        print_trace(&args, &itf_trace)?;
+                    }
+                }
+            }
+            if !parsed.temporal.is_empty() {
+                let traces = traces
+                    .iter()
+                    .map(Trace::from_itf)
+                    .collect::<Result<Vec<_>, _>>()?;
+                match parsed.check_temporal(&traces) {
+                    Ok(results) => {
+                        for result in results {
+                            for (i, verdict) in result.verdicts.iter().enumerate() {
+                                log!("Temporal", "{} on trace {i}: {verdict}", result.name)
+                            }
+                        }
+                    }
+                    Err(e) => log!("Error", "Temporal property checking failed: {e}"),
+                }
+            }
+            if !result {
+                report.outcome = Outcome::Failed;
+            }
+            if result && args.out_handoff.is_some() && !args.component.is_empty() {
+                log!(
+                    "Warning",
+                    "Handoffs are not supported with --component, skipping it"
+                );
+            } else if let (true, Some(dir)) = (result, &args.out_handoff) {
+                let handoff = Handoff {
+                    source: fs::canonicalize(&args.file)?,
+                    main: args.main.clone(),
+                    init: args.init.clone(),
+                    step: args.step.clone(),
+                    invariants: if args.invariant.is_empty() {
+                        args.inv.iter().cloned().collect()
+                    } else {
+                        args.invariant.clone()
+                    },
+                    max_steps: args.max_steps,
+                    samples: stats.samples,
+                    seed: stats.seed,
+                };
+                match helpers::compile_flattened(
+                    &args.file,
+                    &handoff.init,
+                    &handoff.step,
+                    &handoff.invariant(),
+                    handoff.main.as_deref(),
+                ) {
+                    Ok(module) => {
+                        handoff.write(dir, &module)?;
+                        log!("Handoff", "{}", dir.display());
+                        log!(
+                            "Handoff",
+                            "Verify with: {}",
+                            handoff.verify_command().join(" ")
+                        );
+                    }
+                    Err(e) => log!("Error", "Flattening the spec failed: {e}"),
+                }
+            }
+        }
+        Err(e) => {
+            let diagnostic = evaluation_diagnostic(&args, &e)?;
+            match diagnostic.location() {
+                Some(location) => log!("Error", "Simulation failed at {location}: {e}"),
+                None => log!("Error", "Simulation failed: {e}"),
+            }
+            report.add_error(&e);
+            diagnostics.push(diagnostic);
+        }
+    }
+    write_diagnostics(&args, &diagnostics)?;
+
+    log!("Elapsed", "{elapsed:.2?}");
+    if args.counters || args.sharing {
+        let counters = merged_counters.unwrap_or_else(counters::get);
+        log_counters(&args, &counters, &mut report)?;
+    }
+    report.elapsed_ms = elapsed.as_millis();
+    print_report(args.out_format, &report)
+}
+
+/// The diagnostic for an error found when evaluating the spec, located with
+/// the source map of the spec.
+fn evaluation_diagnostic(args: &RunArgs, error: &QuintError) -> eyre::Result<Diagnostic> {
+    let source_map = helpers::source_map(&args.file).map_err(|e| eyre!("{e}"))?;
+    Ok(Diagnostic::from_error(error, &source_map))
+}
+
+/// Log the counters and add them to the report, with a summary of the sharing
+/// between states if it was measured (see `--sharing`).
+fn log_counters(args: &RunArgs, counters: &Counters, report: &mut Report) -> eyre::Result<()> {
+    log!("Counters", "{}", serde_json::to_string(counters)?);
+    report.set_statistic("counters", serde_json::to_value(counters)?);
+    if args.sharing {
+        log!(
+            "Sharing",
+            "{:.1}% of the state shared with the previous one, {:.0} bytes copied and {:.1} values cloned per step",
+            counters.shared_fraction() * 100.0,
+            counters.bytes_copied_per_step(),
+            counters.clones_per_step()
+        );
+    }
+    Ok(())
+}
+
+/// Write the diagnostics to --out-diagnostics, if given.
+fn write_diagnostics(args: &RunArgs, diagnostics: &[Diagnostic]) -> eyre::Result<()> {
+    if let Some(path) = &args.out_diagnostics {
+        fs::write(path, serde_json::to_string_pretty(diagnostics)?)?;
+        log!("Diagnostics", "{}", path.display());
+    }
+    Ok(())
+}
+
+/// Print a trace read from ITF, for the `--print-traces` option of `run`.
+fn print_trace(args: &RunArgs, itf_trace: &serde_json::Value) -> eyre::Result<()> {
+    if args.print_traces {
+        let mut itf_trace = itf_trace.clone();
+        for state in itf_trace["states"].as_array_mut().into_iter().flatten() {
+            if let Some(fields) = state.as_object_mut() {
+                fields.retain(|name, _| !args.hide.contains(name));
+            }
+        }
+        let trace = Trace::from_itf(&itf_trace)?;
+        if args.tla {
+            print!("{}", trace.to_tla()?);
+        } else {
+            print!("{}", trace.render_colored(args.full_states));
+        }
+    }
+    Ok(())
+}
 
+/// Explore the states of the spec exhaustively, for the `--exhaustive` option
+/// of `run`.
+fn explore(args: &RunArgs, parsed: &ParsedQuint) -> eyre::Result<()> {
+    let start = Instant::now();
+    log!("Explore", "Exploring up to depth {}", args.max_steps);
+    let result = parsed.explore_with_config(&ExplorerConfig {
+        max_depth: args.max_steps,
+        graph: args.out_dot.is_some(),
+        constants: read_constants(args.constants.as_deref(), &args.constant)?,
+        output: output(args.verbosity, args.out_format),
+    });
     let elapsed = start.elapsed();
+    let mut diagnostics = Vec::new();
+    let mut report = Report::new("run");
 
     match result {
         Ok(result) => {
             log!("Result", "{}", result.result);
-            for (i, trace) in result.best_traces.into_iter().enumerate() {
+            if !result.result {
+                report.outcome = Outcome::Failed;
+            }
+            report.set_statistic("states", result.states);
+            report.set_statistic("depth", result.depth);
+            report.set_statistic("complete", result.complete);
+            log!(
+                "Explore",
+                "{} states up to depth {}{}",
+                result.states,
+                result.depth,
+                if result.complete {
+                    " (all reachable states)"
+                } else {
+                    ""
+                }
+            );
+            if let Some(trace) = result.counterexample {
                 let itf_trace = trace.to_itf(args.file.display().to_string());
-                let json_data = serde_json::to_string(&itf_trace)?;
-                let filename = format!("out_{i}.itf.json");
+                let filename = expand_output_template(&args.out_itf, 0, false);
                 let mut file = File::create(filename.clone())?;
-                file.write_all(json_data.as_bytes())?;
-                log!("Trace", "{filename}")
+                file.write_all(serde_json::to_string(&itf_trace)?.as_bytes())?;
+                report.add_trace(Some(filename.clone()), &itf_trace, args.inline_traces);
+                log!("Trace", "{filename}");
+                print_trace(args, &itf_trace)?;
+            }
+            if let (Some(filename), Some(graph)) = (&args.out_dot, result.graph) {
+                fs::write(filename, graph.to_dot())?;
+                log!(
+                    "Graph",
+                    "{} states, {} transitions: {filename}",
+                    graph.nodes.len(),
+                    graph.edges.len()
+                );
+            }
+        }
+        Err(e) => {
+            let diagnostic = evaluation_diagnostic(args, &e)?;
+            match diagnostic.location() {
+                Some(location) => log!("Error", "Exploration failed at {location}: {e}"),
+                None => log!("Error", "Exploration failed: {e}"),
             }
+            report.add_error(&e);
+            diagnostics.push(diagnostic);
         }
-        Err(e) => log!("Error", "Simulation failed: {e}"),
     }
+    write_diagnostics(args, &diagnostics)?;
 
     log!("Elapsed", "{elapsed:.2?}");
+    if args.counters || args.sharing {
+        log_counters(args, &counters::get(), &mut report)?;
+    }
+    report.elapsed_ms = elapsed.as_millis();
+    print_report(args.out_format, &report)
+}
+
+/// Replay an ITF trace against a spec, reporting the first state where the
+/// trace diverges from the spec or violates the invariant. Like `run`, this
+/// expects `quint` to be installed and in the PATH.
+fn replay(args: ReplayArgs) -> eyre::Result<()> {
+    log::set_json(false);
+    if args.out_format == OutputFormat::Json {
+        log::set_verbosity(Verbosity::Quiet);
+    }
+    let start = Instant::now();
+    let mut report = Report::new("replay");
+
+    for file in [&args.file, &args.trace] {
+        if !fs::exists(file)? {
+            bail!("File not found: {}", file.display());
+        }
+    }
+
+    log!("Parsing", "Parsing file: {}", args.file.display());
+    let parsed = helpers::parse_from_path(
+        &args.file,
+        args.init.as_str(),
+        args.step.as_str(),
+        args.inv.as_deref(),
+        args.main.as_deref(),
+    )
+    .unwrap();
+
+    let trace = read_trace(&args.trace)?;
+
+    log!(
+        "Replay",
+        "Replaying {} states from {}",
+        trace.states.len(),
+        args.trace.display()
+    );
+    report.set_statistic("states", trace.states.len());
+    // Where the evaluator and the trace part ways, if they do
+    let failure = |report: &mut Report, reason: &str, state: Option<usize>| {
+        report.outcome = Outcome::Failed;
+        report.set_statistic("reason", reason);
+        if let Some(state) = state {
+            report.set_statistic("state", state);
+        }
+    };
+    if let Some(expect_violation) = args.expect {
+        match parsed.cross_check(&trace, expect_violation, args.attempts) {
+            Ok(Agreement::Agrees) => log!("Result", "The evaluator agrees with the trace"),
+            Ok(Agreement::Divergence { state }) => {
+                failure(&mut report, "divergence", Some(state));
+                log!(
+                    "Result",
+                    "The evaluator disagrees: state {state} does not follow from the previous one"
+                )
+            }
+            Ok(Agreement::UnexpectedViolation { state }) => {
+                failure(&mut report, "unexpectedViolation", Some(state));
+                log!(
+                    "Result",
+                    "The evaluator disagrees: state {state} violates the invariant"
+                )
+            }
+            Ok(Agreement::MissingViolation) => {
+                failure(&mut report, "missingViolation", None);
+                log!(
+                    "Result",
+                    "The evaluator disagrees: the last state satisfies the invariant"
+                )
+            }
+            Err(e) => {
+                report.add_error(&e);
+                log!("Error", "Replay failed: {e}")
+            }
+        }
+    } else {
+        match parsed.replay(&trace, args.attempts) {
+            Ok(ReplayOutcome::Ok) => log!("Result", "All states follow the spec"),
+            Ok(ReplayOutcome::Divergence { state }) => {
+                failure(&mut report, "divergence", Some(state));
+                log!(
+                    "Result",
+                    "State {state} does not follow from the previous one"
+                )
+            }
+            Ok(ReplayOutcome::Violation { state }) => {
+                failure(&mut report, "violation", Some(state));
+                log!("Result", "State {state} violates the invariant")
+            }
+            Err(e) => {
+                report.add_error(&e);
+                log!("Error", "Replay failed: {e}")
+            }
+        }
+    }
+
+    report.elapsed_ms = start.elapsed().as_millis();
+    print_report(args.out_format, &report)
+}
+
+/// Execute the `run` definitions of a spec, for the `test` command.
+fn test(mut args: TestArgs) -> eyre::Result<()> {
+    log::set_json(false);
+    // The report is the only output
+    if args.out_format == OutputFormat::Json {
+        args.verbosity = 0;
+    }
+    let verbosity = Verbosity::from_level(args.verbosity);
+    log::set_verbosity(verbosity);
+    let start = Instant::now();
+    let mut report = Report::new("test");
+
+    if !fs::exists(&args.file)? {
+        bail!("File not found: {}", args.file.display());
+    }
+
+    log!("Parsing", "Parsing file: {}", args.file.display());
+    let (runs, table) =
+        helpers::parse_runs_from_path(&args.file, args.filter.as_deref(), args.main.as_deref())
+            .unwrap();
+
+    let seed = args.seed.unwrap_or_else(rand::random);
+    log!("Seed", "{seed:#x}");
+    report.set_seed(seed);
+
+    let mut failed = Vec::new();
+    for run in &runs {
+        // Each run has its own seed, derived from its name, so it can be
+        // executed alone with --match
+        let run_seed = quint_evaluator::rand::derive_state(seed, fxhash::hash64(&run.name));
+        let result = tester::run_test_samples(&table, run, run_seed, args.max_samples);
+        let samples = match result.samples {
+            1 => String::new(),
+            n => format!(" ({n} samples)"),
+        };
+        match &result.outcome {
+            TestOutcome::Passed => {
+                report.add_property(
+                    &result.name,
+                    "test",
+                    true,
+                    None,
+                    json_map(json!({ "samples": result.samples })),
+                );
+                log!("Test", "{} passed{samples}", result.name);
+                if verbosity.traces() {
+                    print!("{}", result.trace.render_colored(false));
+                }
+            }
+            TestOutcome::Failed { step, error } => {
+                failed.push(result.name.clone());
+                match error {
+                    Some(error) => log!(
+                        "Test",
+                        "{} failed at step {step}{samples}: {error}",
+                        result.name
+                    ),
+                    None => log!(
+                        "Test",
+                        "{} failed at step {step}{samples}: false",
+                        result.name
+                    ),
+                }
+                if verbosity.logs() {
+                    print!("{}", result.trace.render_colored(false));
+                }
+                let filename = expand_output_template(&args.out_itf, &result.name, true);
+                let itf_trace = result.trace.to_itf(args.file.display().to_string());
+                fs::write(&filename, serde_json::to_string(&itf_trace)?)?;
+                log!("Trace", "{filename}");
+                let trace = report.add_trace(Some(filename), &itf_trace, args.inline_traces);
+                report.add_property(
+                    &result.name,
+                    "test",
+                    false,
+                    Some(trace),
+                    json_map(json!({
+                        "samples": result.samples,
+                        "step": step,
+                        "error": error.as_ref().map(ToString::to_string),
+                    })),
+                );
+            }
+        }
+    }
+
+    log!(
+        "Result",
+        "{} passed, {} failed",
+        runs.len() - failed.len(),
+        failed.len()
+    );
+    for name in &failed {
+        log!(
+            "Result",
+            "Reproduce {name} with --seed {seed:#x} --match {name}"
+        );
+    }
+    report.elapsed_ms = start.elapsed().as_millis();
+    print_report(args.out_format, &report)?;
+    if !failed.is_empty() {
+        bail!("{} of {} runs failed", failed.len(), runs.len());
+    }
+    Ok(())
+}
+
+/// Statistics of a simulation, from either a sequential or a parallel run
+struct RunStats {
+    samples: usize,
+    seed: u64,
+    /// The scores of the best traces, see `SimulationResult::best_trace_scores`
+    trace_scores: Vec<usize>,
+    timed_out: bool,
+    action_weights: Vec<(String, u32)>,
+    estimates: Vec<ProbabilityEstimate>,
+    witnesses: Vec<WitnessCount>,
+    initial_states: usize,
+    enumerated_initial_states: Option<usize>,
+    /// Distinct violations, by invariant index, with their counts and traces
+    distinct_violations: Vec<(usize, usize, serde_json::Value)>,
+}
+
+/// The percentage of `part` in `total`, or 0 if `total` is 0
+fn percentage(part: usize, total: usize) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+    part as f64 / total as f64 * 100.0
+}
+
+/// Expand the output template with the index of the trace (or another
+/// suffix), replacing the `{seq}` placeholder. If there is no placeholder and
+/// `auto_append` is true, the index is appended to the filename, before the
+/// extension. This mirrors the behavior of `quint run --out-itf`.
+fn expand_output_template(template: &str, index: impl Display, auto_append: bool) -> String {
+    if template.contains("{seq}") {
+        return template.replace("{seq}", &index.to_string());
+    }
+
+    if auto_append {
+        return match template.split_once('.') {
+            Some((name, extension)) => format!("{name}{index}.{extension}"),
+            None => format!("{template}{index}"),
+        };
+    }
+
+    template.to_string()
+}
+
+/// Serve JSON-RPC requests until the input ends, on STDIN or on each
+/// connection to the address given with --listen. Specs compiled in a
+/// connection can be used in the following ones.
+fn serve(args: ServerArgs) -> eyre::Result<()> {
+    let mut server = Server::default();
+    if let Some(address) = &args.metrics {
+        let listener = TcpListener::bind(address)?;
+        let metrics = Arc::new(Metrics::default());
+        server = server.with_metrics(metrics.clone());
+        thread::spawn(move || metrics::serve(&metrics, listener));
+    }
+    let Some(address) = args.listen else {
+        // Nothing else can be written to STDOUT, so nothing is logged
+        return Ok(server.serve(io::stdin().lock(), io::stdout().lock())?);
+    };
+
+    log::set_json(false);
+    let listener = TcpListener::bind(&address)?;
+    log!("Server", "Listening on {}", listener.local_addr()?);
+    for stream in listener.incoming() {
+        let stream = stream?;
+        let peer = stream.peer_addr()?;
+        log!("Server", "Serving {peer}");
+        if let Err(e) = server.serve(BufReader::new(stream.try_clone()?), stream) {
+            log!("Error", "Connection to {peer} failed: {e}");
+        }
+    }
+    Ok(())
+}
+
+/// Run the REPL on STDIN and STDOUT, compiling the inputs with the `quint`
+/// typescript tool, until `.exit` or the end of the input.
+fn repl(args: ReplArgs) -> eyre::Result<()> {
+    let mut repl = Repl::new(Box::new(|module: &str| {
+        helpers::parse(module, "true", "true", None).map_err(|e| e.to_string())
+    }))
+    .with_colors(colored::control::SHOULD_COLORIZE.should_colorize());
+    if let Some(file) = &args.file {
+        let load = format!(".load {} {}", file.display(), args.main.unwrap_or_default());
+        repl.handle(&load).map_err(|e| eyre!(e))?;
+    }
+
+    let mut stdout = io::stdout().lock();
+    let mut line = String::new();
+    loop {
+        write!(stdout, ">>> ")?;
+        stdout.flush()?;
+        line.clear();
+        if io::stdin().read_line(&mut line)? == 0 || line.trim() == ".exit" {
+            return Ok(());
+        }
+        match repl.handle(&line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => writeln!(stdout, "{output}")?,
+            Err(error) => writeln!(stdout, "{} {error}", "error:".red().bold())?,
+        }
+    }
+}
+
+/// Evaluate an expression with the definitions of a spec, in the empty state
+/// or in a state of a trace, printing its value, for the `eval` command.
+fn eval(args: EvalArgs) -> eyre::Result<()> {
+    if !fs::exists(&args.file)? {
+        bail!("File not found: {}", args.file.display());
+    }
+    if args.state.is_some() && args.trace.is_none() {
+        bail!("Option --state requires --trace");
+    }
+
+    let state = match &args.trace {
+        Some(path) => {
+            let trace = read_trace(path)?;
+            let index = args.state.unwrap_or(trace.states.len().saturating_sub(1));
+            match trace.states.get(index) {
+                Some(state) => state.vars.clone(),
+                None => bail!("The trace has no state {index}"),
+            }
+        }
+        None => Value::Record(Default::default()),
+    };
+
+    let mut repl = Repl::new(Box::new(|module: &str| {
+        helpers::parse(module, "true", "true", None).map_err(|e| e.to_string())
+    }));
+    let load = format!(
+        ".load {} {}",
+        args.file.display(),
+        args.main.unwrap_or_default()
+    );
+    repl.handle(&load).map_err(|e| eyre!(e))?;
+    let value = repl.evaluate_in(&args.expr, &state).map_err(|e| eyre!(e))?;
+
+    match args.out_format {
+        OutputFormat::Text => println!("{}", value.colored()),
+        OutputFormat::Json => println!("{}", serde_json::to_string(&value.to_itf())?),
+    }
+    Ok(())
+}
+
+/// Lower a spec like the evaluator does before evaluating it (see
+/// [`quint_evaluator::lowering`]), and write the result. Like `run`, this
+/// expects `quint` to be installed and in the PATH.
+fn compile(args: CompileArgs) -> eyre::Result<()> {
+    if !fs::exists(&args.file)? {
+        bail!("File not found: {}", args.file.display());
+    }
+
+    let mut output = helpers::compile_from_path(
+        &args.file,
+        &args.init,
+        &args.step,
+        &args.invariant,
+        args.main.as_deref(),
+    )
+    .map_err(|e| eyre!("{e}"))?;
+    output.flatten();
+    let constants = read_constants(args.constants.as_deref(), &args.constant)?;
+    lowering::lower(&mut output, &constants)?;
+
+    let lowered = match args.out_format {
+        OutputFormat::Text => printer::to_quint(&output),
+        OutputFormat::Json => serde_json::to_string_pretty(&output)?,
+    };
+    match &args.out {
+        Some(path) => fs::write(path, lowered + "\n")?,
+        None => println!("{lowered}"),
+    }
+    Ok(())
+}
+
+/// Write the built-ins the evaluator supports, with their types and
+/// documentation.
+fn list_builtins(args: BuiltinsArgs) -> eyre::Result<()> {
+    match args.out_format {
+        OutputFormat::Text => {
+            for builtin in reflection::builtins() {
+                println!("{}: {}", builtin.name.bold(), builtin.signature);
+                println!("  {}", builtin.doc);
+            }
+        }
+        OutputFormat::Json => {
+            println!("{}", serde_json::to_string_pretty(reflection::builtins())?)
+        }
+    }
     Ok(())
 }
 
+/// Read a trace, in ITF if it's a JSON file, or as printed by TLC otherwise.
+fn read_trace(path: &Path) -> eyre::Result<Trace> {
+    let contents = fs::read_to_string(path)?;
+    if path.extension().is_some_and(|ext| ext == "json") {
+        Ok(Trace::from_itf(&serde_json::from_str(&contents)?)?)
+    } else {
+        Ok(tlc::parse_trace(&contents)?)
+    }
+}
+
+/// Browse a trace on STDIN and STDOUT, until `quit` or the end of the input.
+/// Expressions are evaluated with a REPL that loads the spec, if given.
+fn browse(args: BrowseArgs) -> eyre::Result<()> {
+    let mut browser = Browser::new(read_trace(&args.trace)?);
+    if let Some(spec) = &args.spec {
+        let mut repl = Repl::new(Box::new(|module: &str| {
+            helpers::parse(module, "true", "true", None).map_err(|e| e.to_string())
+        }));
+        let load = format!(".load {} {}", spec.display(), args.main.unwrap_or_default());
+        repl.handle(&load).map_err(|e| eyre!(e))?;
+        // The REPL keeps the expression compiled for the states it's evaluated in
+        let repl = Rc::new(RefCell::new(repl));
+        browser = browser.with_compiler(Box::new(move |expr: &str| {
+            repl.borrow_mut().prepare(expr)?;
+            let (repl, expr) = (repl.clone(), expr.to_string());
+            let evaluate: Evaluate =
+                Box::new(move |state: &Value| repl.borrow_mut().evaluate_in(&expr, state));
+            Ok(evaluate)
+        }));
+    }
+
+    let mut stdout = io::stdout().lock();
+    writeln!(stdout, "{}", browser.show())?;
+    let mut line = String::new();
+    loop {
+        write!(stdout, "> ")?;
+        stdout.flush()?;
+        line.clear();
+        if io::stdin().read_line(&mut line)? == 0 || matches!(line.trim(), "q" | "quit") {
+            return Ok(());
+        }
+        match browser.handle(&line) {
+            Ok(output) if output.is_empty() => {}
+            Ok(output) => writeln!(stdout, "{output}")?,
+            Err(error) => writeln!(stdout, "{} {error}", "error:".red().bold())?,
+        }
+    }
+}
+
 /// Reads input from standard input (STDIN), parses it, and performs a simulation based on the parsed input.
 /// The result of the simulation is then printed in JSON format to standard output (STDOUT).
 fn simulate_from_stdin() -> eyre::Result<()> {
@@ -180,7 +1869,14 @@ fn simulate_from_stdin() -> eyre::Result<()> {
     io::stdin().read_to_string(&mut input)?;
 
     let input: SimulateInput = serde_json::from_str(&input)?;
-    let parsed = input.parsed;
+    let parsed = ParsedQuint {
+        witnesses: input.witnesses,
+        ..input.parsed
+    };
+    if let Err(errors) = parsed.validate() {
+        println!("{}", serde_json::to_string(&error_outcome(errors))?);
+        return Ok(());
+    }
 
     // Create a progress callback that writes progress to stderr in JSON format
     let progress_callback = Box::new(|update: ProgressUpdate| {
@@ -188,17 +1884,22 @@ fn simulate_from_stdin() -> eyre::Result<()> {
             "type": "progress",
             "current": update.current,
             "total": update.total,
-            "percentage": update.percentage()
+            "percentage": update.percentage(),
+            "states": update.states,
+            "violations": update.violations,
+            "elapsedMs": update.elapsed.as_millis()
         });
         eprintln!("{progress}");
     });
 
-    let result = parsed.simulate(
-        input.nsteps,
-        input.nruns,
-        input.ntraces,
-        Some(progress_callback),
-    );
+    let config = SimulatorConfig {
+        steps: input.nsteps,
+        samples: input.nruns,
+        n_traces: input.ntraces,
+        seed: input.seed,
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, Some(progress_callback));
 
     // Transform the SimulationResult into the Outcome format expected by Quint
     let outcome = to_outcome(input.source, result);
@@ -208,43 +1909,3 @@ fn simulate_from_stdin() -> eyre::Result<()> {
 
     Ok(())
 }
-
-/// Converts the result of a simulation into an `Outcome` struct.
-///
-/// The status is determined based on whether the simulation result indicates success, violation, or error.
-/// Errors are collected into a vector if any are present.
-/// Best traces are converted to the intermediate trace format (ITF).
-fn to_outcome(source: String, result: Result<SimulationResult, QuintError>) -> Outcome {
-    let status = match &result {
-        Ok(r) if r.result => SimulationStatus::Success,
-        Ok(_) => SimulationStatus::Violation,
-        Err(_) => SimulationStatus::Error,
-    };
-
-    let errors = result
-        .as_ref()
-        .err()
-        .map_or_else(Vec::new, |e| vec![e.clone()]);
-
-    let best_traces = result.as_ref().ok().map_or_else(Vec::new, |r| {
-        r.best_traces
-            .iter()
-            .cloned()
-            .map(|t| SimulationTrace {
-                // TODO: Fetch seed from the random generator state
-                seed: 0,
-                states: t.clone().to_itf(source.clone()),
-                result: !t.violation,
-            })
-            .collect()
-    });
-
-    Outcome {
-        status,
-        errors,
-        best_traces,
-        // TODO: This simulator is not tracking witnesses yet
-        witnessing_traces: vec![],
-        samples: 0,
-    }
-}
@@ -0,0 +1,60 @@
+//! Model-based testing (MBT) support: recording which action was taken on each
+//! step and which values were picked by `nondet` definitions, and exporting
+//! traces as test vectors for conformance test harnesses.
+//!
+//! The recorded information is kept with each state of a trace (see
+//! [`crate::trace::StepMeta`]). As in Quint's typescript tool (with `--mbt`),
+//! it can be written to ITF traces as the `mbt::actionTaken` and
+//! `mbt::nondetPicks` variables of each state (see
+//! [`crate::trace::Trace::to_mbt_itf`]).
+
+use crate::trace::Trace;
+use serde_json::{json, Map, Value as Json};
+
+/// The name of the ITF variable with the action taken to reach a state.
+pub const ACTION_TAKEN: &str = "mbt::actionTaken";
+/// The name of the ITF variable with the values picked by `nondet` definitions.
+pub const NONDET_PICKS: &str = "mbt::nondetPicks";
+
+/// Convert a trace recorded for MBT into a test case, with one entry per
+/// state: the action taken to reach it, the values picked by `nondet`
+/// definitions on the way, and the expected state. The expected state is
+/// projected to the `variables` given, or has all variables if none are given.
+///
+/// Values are written in the same encoding as ITF (see [`crate::itf`]).
+pub fn test_case(trace: &Trace, variables: &[String]) -> Json {
+    let steps = trace
+        .states
+        .iter()
+        .map(|state| {
+            let action = state
+                .meta
+                .as_ref()
+                .map(|meta| meta.action_taken.clone().unwrap_or_default());
+            let picks = state
+                .meta
+                .as_ref()
+                .map_or_else(|| json!({}), |meta| meta.picks().to_itf());
+            let expected = state
+                .vars
+                .as_record_map()
+                .iter()
+                .filter(|(name, _)| {
+                    variables.is_empty() || variables.iter().any(|v| v == name.as_str())
+                })
+                .map(|(name, value)| (name.to_string(), value.to_itf()))
+                .collect::<Map<_, _>>();
+
+            json!({
+                "action": action,
+                "nondetPicks": picks,
+                "expected": expected,
+            })
+        })
+        .collect::<Vec<_>>();
+
+    json!({
+        "status": if trace.violation { "violation" } else { "ok" },
+        "steps": steps,
+    })
+}
@@ -0,0 +1,254 @@
+//! Metrics of the simulations run by a long-running process (e.g. the
+//! [`crate::server`]), exposed to Prometheus, so campaigns running in CI or
+//! on shared infrastructure can be monitored with existing dashboards.
+//!
+//! The metrics are written in the Prometheus text format by
+//! [`Metrics::render`], and served over HTTP by [`serve`]. They are:
+//!  - `quint_simulations_total` and `quint_simulations_running`;
+//!  - `quint_samples_total`, `quint_states_total` and
+//!    `quint_violations_total`, over all simulations;
+//!  - `quint_samples_per_second` and `quint_states_per_second`, in the
+//!    current (or last) simulation;
+//!  - `quint_interned_names` and `quint_interned_name_bytes`, the names
+//!    interned by all simulations, which are never freed (see
+//!    [`crate::symbol`]);
+//!  - `process_resident_memory_bytes`, where it is known (i.e. on Linux).
+
+use crate::simulator::SimulationHooks;
+use crate::symbol;
+use crate::trace::{State, Trace};
+use std::fmt::Write as _;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// The metrics of all simulations, which can be shared between threads.
+#[derive(Default)]
+pub struct Metrics {
+    simulations: AtomicU64,
+    running: AtomicU64,
+    samples: AtomicU64,
+    states: AtomicU64,
+    violations: AtomicU64,
+    /// The rates of the current simulation, as the bits of an `f64`
+    samples_per_second: AtomicU64,
+    states_per_second: AtomicU64,
+}
+
+impl Metrics {
+    /// Start recording a simulation, returning the hooks to run it with (see
+    /// [`crate::simulator::ParsedQuint::simulate_with_hooks`]). The
+    /// simulation is no longer running when the hooks are dropped.
+    pub fn simulation(&self) -> SimulationMetrics<'_> {
+        self.simulations.fetch_add(1, Ordering::Relaxed);
+        self.running.fetch_add(1, Ordering::Relaxed);
+        SimulationMetrics {
+            metrics: self,
+            start: Instant::now(),
+            samples: 0,
+            states: 0,
+        }
+    }
+
+    /// The metrics in the Prometheus text format.
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, value: f64| {
+            // Writing to a string can't fail
+            let _ = write!(
+                out,
+                "# HELP {name} {help}\n# TYPE {name} {kind}\n{name} {value}\n"
+            );
+        };
+        let count = |counter: &AtomicU64| counter.load(Ordering::Relaxed) as f64;
+        let rate = |rate: &AtomicU64| f64::from_bits(rate.load(Ordering::Relaxed));
+
+        metric(
+            "quint_simulations_total",
+            "counter",
+            "Simulations started.",
+            count(&self.simulations),
+        );
+        metric(
+            "quint_simulations_running",
+            "gauge",
+            "Simulations running.",
+            count(&self.running),
+        );
+        metric(
+            "quint_samples_total",
+            "counter",
+            "Samples run, over all simulations.",
+            count(&self.samples),
+        );
+        metric(
+            "quint_states_total",
+            "counter",
+            "States reached, over all simulations.",
+            count(&self.states),
+        );
+        metric(
+            "quint_violations_total",
+            "counter",
+            "Invariant violations found, over all simulations.",
+            count(&self.violations),
+        );
+        metric(
+            "quint_samples_per_second",
+            "gauge",
+            "Samples per second in the current simulation.",
+            rate(&self.samples_per_second),
+        );
+        metric(
+            "quint_states_per_second",
+            "gauge",
+            "States per second in the current simulation.",
+            rate(&self.states_per_second),
+        );
+        let (names, bytes) = symbol::interned();
+        metric(
+            "quint_interned_names",
+            "gauge",
+            "Field names and labels interned and in use.",
+            names as f64,
+        );
+        metric(
+            "quint_interned_name_bytes",
+            "gauge",
+            "Total length in bytes of the interned field names and labels.",
+            bytes as f64,
+        );
+        if let Some(memory) = resident_memory() {
+            metric(
+                "process_resident_memory_bytes",
+                "gauge",
+                "Resident memory size in bytes.",
+                memory as f64,
+            );
+        }
+        out
+    }
+}
+
+/// The hooks recording the metrics of a simulation, see
+/// [`Metrics::simulation`].
+pub struct SimulationMetrics<'a> {
+    metrics: &'a Metrics,
+    start: Instant,
+    samples: u64,
+    states: u64,
+}
+
+impl SimulationMetrics<'_> {
+    fn update_rates(&self) {
+        let elapsed = self.start.elapsed().as_secs_f64();
+        if elapsed > 0.0 {
+            let rate = |count: u64| (count as f64 / elapsed).to_bits();
+            let metrics = self.metrics;
+            metrics
+                .samples_per_second
+                .store(rate(self.samples), Ordering::Relaxed);
+            metrics
+                .states_per_second
+                .store(rate(self.states), Ordering::Relaxed);
+        }
+    }
+}
+
+impl SimulationHooks for SimulationMetrics<'_> {
+    fn on_step(&mut self, _state: &State) {
+        self.states += 1;
+        self.metrics.states.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn on_sample_end(&mut self, _trace: &Trace) {
+        self.samples += 1;
+        self.metrics.samples.fetch_add(1, Ordering::Relaxed);
+        self.update_rates();
+    }
+
+    fn on_violation(&mut self, _trace: &Trace, _invariant: usize) {
+        self.metrics.violations.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for SimulationMetrics<'_> {
+    fn drop(&mut self) {
+        self.update_rates();
+        self.metrics.running.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// How long a connection can take to send its request, so that clients that
+/// never do don't keep a thread for long.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The most bytes read from a request, as only its first line is used.
+const MAX_REQUEST_SIZE: u64 = 8 * 1024;
+
+/// How many connections are served at the same time.
+const THREADS: usize = 4;
+
+/// Serve the metrics over HTTP on `listener`, at `/metrics`, until accepting
+/// connections fails. Connections are served by a few threads taking turns
+/// to accept them, so a slow client doesn't hold up the others, and many
+/// clients at once don't start as many threads.
+pub fn serve(metrics: &Metrics, listener: TcpListener) -> io::Result<()> {
+    let listener = &listener;
+    thread::scope(|scope| {
+        let workers = (0..THREADS)
+            .map(|_| {
+                scope.spawn(move || -> io::Result<()> {
+                    loop {
+                        let (mut stream, _) = listener.accept()?;
+                        // A failed connection doesn't stop the others
+                        let _ = respond(metrics, &mut stream);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        // Each thread stops when accepting fails, with the first error
+        // reported once all of them have
+        workers
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|_| Err(io::Error::other("Metrics thread panicked")))
+            })
+            .fold(Ok(()), io::Result::and)
+    })
+}
+
+fn respond(metrics: &Metrics, stream: &mut TcpStream) -> io::Result<()> {
+    stream.set_read_timeout(Some(READ_TIMEOUT))?;
+    let mut reader = BufReader::new(stream.try_clone()?.take(MAX_REQUEST_SIZE));
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    // Skip the headers, up to the empty line
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+
+    let (status, content_type, body) = match request.split_whitespace().nth(1) {
+        Some("/metrics") => ("200 OK", "text/plain; version=0.0.4", metrics.render()),
+        _ => ("404 Not Found", "text/plain", "Not found\n".to_string()),
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    stream.flush()
+}
+
+/// The resident memory of the process, from `/proc` on Linux.
+fn resident_memory() -> Option<u64> {
+    let status = std::fs::read_to_string("/proc/self/status").ok()?;
+    let line = status.lines().find(|line| line.starts_with("VmRSS:"))?;
+    let kilobytes: u64 = line.split_whitespace().nth(1)?.parse().ok()?;
+    Some(kilobytes * 1024)
+}
@@ -0,0 +1,237 @@
+//! MessagePack encoding of JSON documents, a faster alternative to JSON text
+//! for the messages of the server (see [`crate::server`]).
+//!
+//! Documents are encoded with the smallest representation of each value, and
+//! decoded from any representation, except for the types that have no JSON
+//! equivalent (binary data, extensions, and maps with keys that are not
+//! strings), which are rejected. Messages are self-delimiting, so a stream of
+//! them needs no framing.
+
+use serde_json::{Map, Number, Value as Json};
+use std::io::{self, Read, Write};
+
+/// Encode a JSON document.
+pub fn encode(value: &Json) -> Vec<u8> {
+    let mut out = Vec::new();
+    // Writing to a vector can't fail
+    write(&mut out, value).expect("writing to a vector should not fail");
+    out
+}
+
+/// Decode a JSON document, failing if there are bytes left.
+pub fn decode(mut bytes: &[u8]) -> io::Result<Json> {
+    let value = read(&mut bytes)?;
+    if !bytes.is_empty() {
+        return Err(invalid(format!("{} trailing bytes", bytes.len())));
+    }
+    Ok(value)
+}
+
+/// Write a JSON document to `out`.
+pub fn write(out: &mut impl Write, value: &Json) -> io::Result<()> {
+    match value {
+        Json::Null => out.write_all(&[0xc0]),
+        Json::Bool(false) => out.write_all(&[0xc2]),
+        Json::Bool(true) => out.write_all(&[0xc3]),
+        Json::Number(n) => {
+            if let Some(n) = n.as_u64() {
+                write_uint(out, n)
+            } else if let Some(n) = n.as_i64() {
+                write_int(out, n)
+            } else {
+                let n = n.as_f64().expect("JSON numbers should be u64, i64 or f64");
+                out.write_all(&[0xcb])?;
+                out.write_all(&n.to_be_bytes())
+            }
+        }
+        Json::String(s) => {
+            let len = s.len();
+            match len {
+                0..=31 => out.write_all(&[0xa0 | len as u8])?,
+                _ => write_len(out, len, [0xd9, 0xda, 0xdb])?,
+            }
+            out.write_all(s.as_bytes())
+        }
+        Json::Array(elems) => {
+            match elems.len() {
+                len @ 0..=15 => out.write_all(&[0x90 | len as u8])?,
+                len => write_len(out, len, [0, 0xdc, 0xdd])?,
+            }
+            elems.iter().try_for_each(|elem| write(out, elem))
+        }
+        Json::Object(fields) => {
+            match fields.len() {
+                len @ 0..=15 => out.write_all(&[0x80 | len as u8])?,
+                len => write_len(out, len, [0, 0xde, 0xdf])?,
+            }
+            fields.iter().try_for_each(|(key, value)| {
+                write(out, &Json::String(key.clone()))?;
+                write(out, value)
+            })
+        }
+    }
+}
+
+fn write_uint(out: &mut impl Write, n: u64) -> io::Result<()> {
+    match n {
+        0..=0x7f => out.write_all(&[n as u8]),
+        0x80..=0xff => out.write_all(&[0xcc, n as u8]),
+        0x100..=0xffff => {
+            out.write_all(&[0xcd])?;
+            out.write_all(&(n as u16).to_be_bytes())
+        }
+        0x1_0000..=0xffff_ffff => {
+            out.write_all(&[0xce])?;
+            out.write_all(&(n as u32).to_be_bytes())
+        }
+        _ => {
+            out.write_all(&[0xcf])?;
+            out.write_all(&n.to_be_bytes())
+        }
+    }
+}
+
+/// Write a negative integer (non-negative ones are written as unsigned).
+fn write_int(out: &mut impl Write, n: i64) -> io::Result<()> {
+    if n >= -32 {
+        out.write_all(&[n as u8])
+    } else if n >= i8::MIN.into() {
+        out.write_all(&[0xd0, n as u8])
+    } else if n >= i16::MIN.into() {
+        out.write_all(&[0xd1])?;
+        out.write_all(&(n as i16).to_be_bytes())
+    } else if n >= i32::MIN.into() {
+        out.write_all(&[0xd2])?;
+        out.write_all(&(n as i32).to_be_bytes())
+    } else {
+        out.write_all(&[0xd3])?;
+        out.write_all(&n.to_be_bytes())
+    }
+}
+
+/// Write the marker and length of a string, array or map, given the markers
+/// for 8, 16 and 32-bit lengths.
+fn write_len(out: &mut impl Write, len: usize, markers: [u8; 3]) -> io::Result<()> {
+    if len <= 0xff && markers[0] != 0 {
+        out.write_all(&[markers[0], len as u8])
+    } else if len <= 0xffff {
+        out.write_all(&[markers[1]])?;
+        out.write_all(&(len as u16).to_be_bytes())
+    } else {
+        let len = u32::try_from(len).map_err(|_| invalid("collection too large".to_string()))?;
+        out.write_all(&[markers[2]])?;
+        out.write_all(&len.to_be_bytes())
+    }
+}
+
+/// Read a JSON document from `input`.
+pub fn read(input: &mut impl Read) -> io::Result<Json> {
+    let marker = read_bytes::<1>(input)?[0];
+    match marker {
+        0x00..=0x7f => Ok(Json::from(marker)),
+        0x80..=0x8f => read_map(input, usize::from(marker & 0x0f)),
+        0x90..=0x9f => read_array(input, usize::from(marker & 0x0f)),
+        0xa0..=0xbf => read_str(input, usize::from(marker & 0x1f)),
+        0xc0 => Ok(Json::Null),
+        0xc2 => Ok(Json::Bool(false)),
+        0xc3 => Ok(Json::Bool(true)),
+        0xca => float(f32::from_be_bytes(read_bytes(input)?).into()),
+        0xcb => float(f64::from_be_bytes(read_bytes(input)?)),
+        0xcc => Ok(Json::from(u8::from_be_bytes(read_bytes(input)?))),
+        0xcd => Ok(Json::from(u16::from_be_bytes(read_bytes(input)?))),
+        0xce => Ok(Json::from(u32::from_be_bytes(read_bytes(input)?))),
+        0xcf => Ok(Json::from(u64::from_be_bytes(read_bytes(input)?))),
+        0xd0 => Ok(Json::from(i8::from_be_bytes(read_bytes(input)?))),
+        0xd1 => Ok(Json::from(i16::from_be_bytes(read_bytes(input)?))),
+        0xd2 => Ok(Json::from(i32::from_be_bytes(read_bytes(input)?))),
+        0xd3 => Ok(Json::from(i64::from_be_bytes(read_bytes(input)?))),
+        0xd9 => {
+            let len = read_len::<1>(input)?;
+            read_str(input, len)
+        }
+        0xda => {
+            let len = read_len::<2>(input)?;
+            read_str(input, len)
+        }
+        0xdb => {
+            let len = read_len::<4>(input)?;
+            read_str(input, len)
+        }
+        0xdc => {
+            let len = read_len::<2>(input)?;
+            read_array(input, len)
+        }
+        0xdd => {
+            let len = read_len::<4>(input)?;
+            read_array(input, len)
+        }
+        0xde => {
+            let len = read_len::<2>(input)?;
+            read_map(input, len)
+        }
+        0xdf => {
+            let len = read_len::<4>(input)?;
+            read_map(input, len)
+        }
+        0xe0..=0xff => Ok(Json::from(marker as i8)),
+        0xc4..=0xc6 => Err(invalid("binary data is not supported".to_string())),
+        0xc7..=0xc9 | 0xd4..=0xd8 => Err(invalid("extensions are not supported".to_string())),
+        0xc1 => Err(invalid("invalid marker 0xc1".to_string())),
+    }
+}
+
+fn read_bytes<const N: usize>(input: &mut impl Read) -> io::Result<[u8; N]> {
+    let mut bytes = [0; N];
+    input.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+/// Read a big-endian length of `N` bytes.
+fn read_len<const N: usize>(input: &mut impl Read) -> io::Result<usize> {
+    Ok(read_bytes::<N>(input)?
+        .iter()
+        .fold(0, |len, &byte| len << 8 | usize::from(byte)))
+}
+
+fn read_str(input: &mut impl Read, len: usize) -> io::Result<Json> {
+    let mut bytes = Vec::new();
+    input.take(len as u64).read_to_end(&mut bytes)?;
+    if bytes.len() < len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    String::from_utf8(bytes)
+        .map(Json::String)
+        .map_err(|e| invalid(e.to_string()))
+}
+
+fn read_array(input: &mut impl Read, len: usize) -> io::Result<Json> {
+    // The length is not trusted to preallocate, as it may be corrupted
+    (0..len)
+        .map(|_| read(input))
+        .collect::<io::Result<_>>()
+        .map(Json::Array)
+}
+
+fn read_map(input: &mut impl Read, len: usize) -> io::Result<Json> {
+    let mut fields = Map::new();
+    for _ in 0..len {
+        let Json::String(key) = read(input)? else {
+            return Err(invalid("map keys must be strings".to_string()));
+        };
+        fields.insert(key, read(input)?);
+    }
+    Ok(Json::Object(fields))
+}
+
+fn float(n: f64) -> io::Result<Json> {
+    Number::from_f64(n)
+        .map(Json::Number)
+        .ok_or_else(|| invalid(format!("{n} is not a valid JSON number")))
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("Invalid MessagePack: {message}"),
+    )
+}
@@ -0,0 +1,92 @@
+//! Streaming of the states of a simulation as newline-delimited JSON, written
+//! as the simulation progresses instead of buffering whole traces, so
+//! consumers can process arbitrarily long runs with bounded memory.
+//!
+//! Each line is a JSON object with a `kind`:
+//!  - `{"kind": "state", "sample", "step", "state"}` for every state reached,
+//!    with the state in ITF;
+//!  - `{"kind": "violation", "sample", "step", "invariant"}` when the last
+//!    state violates an invariant, by its index (see
+//!    [`SimulationHooks::on_violation`]);
+//!  - `{"kind": "sample", "sample", "length", "violation"}` at the end of
+//!    every sample.
+//!
+//! Samples are numbered from 1, in the order they are run.
+
+use crate::simulator::SimulationHooks;
+use crate::trace::{State, Trace};
+use serde_json::{json, Value as Json};
+use std::io::{self, Write};
+
+/// Writes the states of a simulation to `W`, one JSON object per line.
+pub struct NdjsonWriter<W: Write> {
+    out: W,
+    sample: usize,
+    step: usize,
+    /// The first error when writing, reported by [`NdjsonWriter::finish`], as
+    /// hooks can't fail
+    error: Option<io::Error>,
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(out: W) -> Self {
+        Self {
+            out,
+            sample: 1,
+            step: 0,
+            error: None,
+        }
+    }
+
+    /// Finish writing, returning the first error that happened, if any.
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.error {
+            Some(e) => Err(e),
+            None => self.out.flush(),
+        }
+    }
+
+    fn write(&mut self, line: Json) {
+        if self.error.is_none() {
+            if let Err(e) = writeln!(self.out, "{line}") {
+                self.error = Some(e);
+            }
+        }
+    }
+}
+
+impl<W: Write> SimulationHooks for NdjsonWriter<W> {
+    fn on_step(&mut self, state: &State) {
+        self.write(json!({
+            "kind": "state",
+            "sample": self.sample,
+            "step": self.step,
+            "state": state.vars.to_itf(),
+        }));
+        self.step += 1;
+    }
+
+    fn on_sample_end(&mut self, trace: &Trace) {
+        self.write(json!({
+            "kind": "sample",
+            "sample": self.sample,
+            "length": trace.states.len(),
+            "violation": trace.violation,
+        }));
+        // Consumers see each sample as soon as it ends
+        if let Err(e) = self.out.flush() {
+            self.error.get_or_insert(e);
+        }
+        self.sample += 1;
+        self.step = 0;
+    }
+
+    fn on_violation(&mut self, trace: &Trace, invariant: usize) {
+        self.write(json!({
+            "kind": "violation",
+            "sample": self.sample,
+            "step": trace.states.len() - 1,
+            "invariant": invariant,
+        }));
+    }
+}
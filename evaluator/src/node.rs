@@ -0,0 +1,173 @@
+//! A Node addon exposing the evaluator to the Quint typescript tool
+//! in-process, built with napi-rs, to avoid starting a process and
+//! serializing specs to JSON for each query. The addon is the crate built as
+//! a dynamic library (`cargo rustc --lib --release --features napi
+//! --crate-type cdylib`), renamed to `quint_evaluator.node`.
+//!
+//! These mirror the [`crate::server`] methods: a [`Spec`] is compiled once from
+//! the IR produced by the typescript tool, and then expressions can be
+//! evaluated and simulations run on it. Values and outcomes are plain JS
+//! objects, in the same formats as the JSON outputs (ITF for values).
+//!
+//! Simulations run on the libuv thread pool, resolving a promise with their
+//! outcome, so the CLI can keep reporting progress from the callback it
+//! passes, as it does for its own simulator.
+
+use crate::evaluator::{Env as EvalEnv, Interpreter};
+use crate::ir::{LookupTable, QuintError};
+use crate::server::{to_outcome, Outcome};
+use crate::simulator::{ParsedQuint, ProgressUpdate, SimulatorConfig};
+use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{Env, JsUnknown};
+use napi_derive::napi;
+
+/// The options of [`Spec::simulate`], named as in the `simulate` server method.
+#[napi(object)]
+pub struct SimulateOptions {
+    pub source: Option<String>,
+    pub nruns: u32,
+    pub nsteps: u32,
+    pub ntraces: Option<u32>,
+    pub seed: Option<BigInt>,
+}
+
+/// The progress of a simulation, passed to the callback of [`Spec::simulate`]
+/// after each sample.
+#[napi(object)]
+pub struct Progress {
+    pub current: u32,
+    pub total: u32,
+    pub states: i64,
+    pub violations: u32,
+}
+
+type ProgressFunction = ThreadsafeFunction<Progress, ErrorStrategy::Fatal>;
+
+/// A compiled spec.
+#[napi]
+pub struct Spec {
+    parsed: ParsedQuint,
+}
+
+#[napi]
+impl Spec {
+    /// Load a spec, given as the `parsed` field of the input of
+    /// `simulate-from-stdin`.
+    #[napi(constructor)]
+    pub fn new(env: Env, parsed: JsUnknown) -> Result<Self> {
+        Ok(Spec {
+            parsed: env.from_js_value(parsed)?,
+        })
+    }
+
+    /// Evaluate an expression, with the definitions it refers to added to the
+    /// spec's table (if not already there), returning its value in ITF.
+    #[napi]
+    pub fn evaluate(
+        &mut self,
+        env: Env,
+        expr: JsUnknown,
+        table: Option<JsUnknown>,
+    ) -> Result<JsUnknown> {
+        let expr = env.from_js_value(expr)?;
+        let table: LookupTable = match table {
+            Some(table) => env.from_js_value(table)?,
+            None => LookupTable::default(),
+        };
+        for (id, def) in table {
+            self.parsed.table.entry(id).or_insert(def);
+        }
+
+        let mut interpreter = Interpreter::new(&self.parsed.table);
+        let mut eval_env = EvalEnv::new(interpreter.var_storage.clone());
+        let value = interpreter
+            .compile(&expr)
+            .execute(&mut eval_env)
+            .map_err(quint_error)?;
+        env.to_js_value(&value.to_itf())
+    }
+
+    /// Simulate the spec with the given options, resolving to the same outcome
+    /// as `simulate-from-stdin`. The optional `progress` function is called
+    /// after each sample.
+    #[napi(ts_return_type = "Promise<unknown>")]
+    pub fn simulate(
+        &self,
+        options: SimulateOptions,
+        progress: Option<ProgressFunction>,
+    ) -> Result<AsyncTask<Simulation>> {
+        let seed = match options.seed {
+            Some(seed) => match seed.get_u64() {
+                (false, seed, true) => Some(seed),
+                _ => {
+                    return Err(Error::new(
+                        Status::InvalidArg,
+                        "The seed should be a 64-bit unsigned integer",
+                    ))
+                }
+            },
+            None => None,
+        };
+        let config = SimulatorConfig {
+            steps: options.nsteps as usize,
+            samples: options.nruns as usize,
+            n_traces: options.ntraces.unwrap_or(1) as usize,
+            seed,
+            ..SimulatorConfig::default()
+        };
+        // The model can't be shared across threads, so we share its serialized
+        // form instead
+        let model = serde_json::to_string(&self.parsed).map_err(|e| {
+            Error::new(
+                Status::GenericFailure,
+                format!("Failed to serialize model: {e}"),
+            )
+        })?;
+        Ok(AsyncTask::new(Simulation {
+            model,
+            source: options.source.unwrap_or_default(),
+            config,
+            progress,
+        }))
+    }
+}
+
+/// A simulation running on the libuv thread pool.
+pub struct Simulation {
+    model: String,
+    source: String,
+    config: SimulatorConfig,
+    progress: Option<ProgressFunction>,
+}
+
+impl Task for Simulation {
+    type Output = Outcome;
+    type JsValue = JsUnknown;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let parsed: ParsedQuint = serde_json::from_str(&self.model)
+            .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+        let progress = self.progress.clone().map(|progress| {
+            Box::new(move |update: ProgressUpdate| {
+                let update = Progress {
+                    current: update.current as u32,
+                    total: update.total as u32,
+                    states: update.states as i64,
+                    violations: update.violations as u32,
+                };
+                progress.call(update, ThreadsafeFunctionCallMode::NonBlocking);
+            }) as Box<dyn FnMut(ProgressUpdate)>
+        });
+        let result = parsed.simulate_with_config(&self.config, progress);
+        Ok(to_outcome(self.source.clone(), result))
+    }
+
+    fn resolve(&mut self, env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        env.to_js_value(&output)
+    }
+}
+
+fn quint_error(error: QuintError) -> Error {
+    Error::new(Status::GenericFailure, error.to_string())
+}
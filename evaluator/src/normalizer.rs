@@ -10,11 +10,15 @@ impl Value {
     pub fn normalize(self) -> Value {
         match self {
             Value::Int(_) | Value::Bool(_) | Value::Str(_) => self,
+            // Infinite sets can't be enumerated, so they are kept symbolic
+            _ if self.is_infinite() => self,
             Value::Set(_)
             | Value::Interval(_, _)
-            | Value::CrossProduct(_)
-            | Value::PowerSet(_)
-            | Value::MapSet(_, _) => Value::Set(
+            | Value::CrossProduct(..)
+            | Value::PowerSet(..)
+            | Value::MapSet(..)
+            | Value::Universe(_)
+            | Value::BitSet(_, _) => Value::Set(
                 self.as_set()
                     .into_owned()
                     .into_iter()
@@ -37,7 +41,8 @@ impl Value {
             Value::Variant(label, value) => {
                 Value::Variant(label, Rc::new(<Value as Clone>::clone(&value).normalize()))
             }
-            Value::Lambda(_, _) => panic!("Cannot normalize lambda"),
+            // Lambdas are compared by identity, so they are already normal
+            Value::Lambda(_, _) => self,
         }
     }
 }
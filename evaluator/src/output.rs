@@ -0,0 +1,126 @@
+//! What evaluations and simulations output, configured in one place, so
+//! library users can silence or capture all of it.
+//!
+//! The library itself only prints the values given to `q::debug` in specs,
+//! which go to STDOUT by default, as in the Quint tool. An [`Output`] says
+//! where they go instead (see [`DebugOutput`]), and how much the CLI prints
+//! about the commands it runs (see [`Verbosity`]). Evaluations take it from
+//! their [`crate::evaluator::Env`], and simulations and explorations from
+//! their configurations.
+
+use crate::value::Value;
+use serde_json::Value as Json;
+use std::sync::{Arc, Mutex};
+
+/// How much to print, by the levels of the `--verbosity` option of the Quint
+/// tool, from 0 to 5.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verbosity {
+    /// Nothing but errors.
+    Quiet,
+    /// The results, and the values given to `q::debug`.
+    Minimal,
+    /// The progress of commands too.
+    #[default]
+    Normal,
+    /// The traces too, even without violations or failures.
+    Detailed,
+    Debug,
+    All,
+}
+
+impl Verbosity {
+    /// The verbosity of a level, where levels above 5 are the same as 5.
+    pub fn from_level(level: u8) -> Verbosity {
+        match level {
+            0 => Verbosity::Quiet,
+            1 => Verbosity::Minimal,
+            2 => Verbosity::Normal,
+            3 => Verbosity::Detailed,
+            4 => Verbosity::Debug,
+            _ => Verbosity::All,
+        }
+    }
+
+    pub fn level(self) -> u8 {
+        self as u8
+    }
+
+    /// Whether to print the progress and results of commands.
+    pub fn logs(self) -> bool {
+        self >= Verbosity::Minimal
+    }
+
+    /// Whether to print the values given to `q::debug`.
+    pub fn debug_values(self) -> bool {
+        self >= Verbosity::Minimal
+    }
+
+    /// Whether to print every trace, and not only the ones showing violations
+    /// or failures.
+    pub fn traces(self) -> bool {
+        self >= Verbosity::Detailed
+    }
+}
+
+/// A value given to `q::debug`, with its label.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DebugEntry {
+    pub label: String,
+    /// The value, in ITF, so entries can be collected across threads.
+    pub value: Json,
+}
+
+/// Where the values given to `q::debug` go.
+#[derive(Debug, Clone, Default)]
+pub enum DebugOutput {
+    /// Printed to STDOUT.
+    #[default]
+    Print,
+    /// Collected, in the order they are given, i.e. to be shown by the caller.
+    Collect(Arc<Mutex<Vec<DebugEntry>>>),
+    /// Dropped.
+    Silent,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Output {
+    pub verbosity: Verbosity,
+    pub debug: DebugOutput,
+}
+
+impl Output {
+    /// An output where nothing is printed or collected.
+    pub fn silent() -> Output {
+        Output {
+            verbosity: Verbosity::Quiet,
+            debug: DebugOutput::Silent,
+        }
+    }
+
+    /// An output collecting the values given to `q::debug` instead of
+    /// printing them, returning where they are collected.
+    pub fn collecting() -> (Output, Arc<Mutex<Vec<DebugEntry>>>) {
+        let entries = Arc::new(Mutex::new(Vec::new()));
+        let output = Output {
+            verbosity: Verbosity::default(),
+            debug: DebugOutput::Collect(entries.clone()),
+        };
+        (output, entries)
+    }
+
+    /// Output a value given to `q::debug`.
+    pub fn debug(&self, label: &str, value: &Value) {
+        match &self.debug {
+            DebugOutput::Print if self.verbosity.debug_values() => println!("> {label} {value}"),
+            DebugOutput::Print | DebugOutput::Silent => {}
+            DebugOutput::Collect(entries) => entries
+                .lock()
+                .expect("The debug entries should not be poisoned")
+                .push(DebugEntry {
+                    label: label.to_string(),
+                    value: value.to_itf(),
+                }),
+        }
+    }
+}
@@ -1,9 +1,219 @@
 //! Picking values out of sets without enumerating the elements.
 
-use crate::value::{powerset_at_index, ImmutableMap, Value};
+use crate::evaluator::EvalResult;
+use crate::ir::{ErrorKind, QuintError};
+use crate::rand::Rand;
+use crate::value::{powerset_at_index, ImmutableMap, ImmutableSet, Universe, Value};
 use std::convert::TryInto;
 
+/// How nondeterministic choices (i.e. `oneOf`) pick elements from sets.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PickMode {
+    /// Pick a random element, using the environment's random number generator.
+    #[default]
+    Random,
+    /// Always pick the canonical element of the set (its minimum under the
+    /// total order of values), regardless of the random seed. Useful for
+    /// reproducible evaluation, i.e. in documentation examples and golden tests.
+    Deterministic,
+    /// Follow the environment's [`Choices`], so that repeating an evaluation
+    /// eventually goes over all of its possible outcomes. Used for exhaustive
+    /// exploration, where infinite sets can't be picked from.
+    Exhaustive,
+}
+
+/// The choices made by nondeterministic evaluation in [`PickMode::Exhaustive`].
+///
+/// Evaluation is deterministic given the choices, so the possible outcomes
+/// form a tree, where each choice is a node. After each evaluation,
+/// [`Choices::advance`] moves to the next path in that tree (depth-first),
+/// replaying the choices up to the last one that still has alternatives.
+#[derive(Debug, Default, Clone)]
+pub struct Choices {
+    // The choices made so far, with the number of alternatives for each
+    made: Vec<(usize, usize)>,
+    // How many choices were made in the current evaluation
+    position: usize,
+}
+
+impl Choices {
+    /// Choose one of `bound` alternatives, replaying the previous choice at
+    /// this point, or taking the first alternative for a new choice.
+    pub fn next(&mut self, bound: usize) -> usize {
+        let choice = match self.made.get(self.position) {
+            Some((choice, _)) => *choice,
+            None => {
+                self.made.push((0, bound));
+                0
+            }
+        };
+        self.position += 1;
+        choice
+    }
+
+    /// Move to the next combination of choices, to be made by the next
+    /// evaluation. Returns false once all combinations were made, leaving
+    /// the choices ready for a new evaluation tree.
+    pub fn advance(&mut self) -> bool {
+        self.position = 0;
+        while let Some((choice, bound)) = self.made.pop() {
+            if choice + 1 < bound {
+                self.made.push((choice + 1, bound));
+                return true;
+            }
+        }
+        false
+    }
+}
+
+/// How integers are sampled out of the infinite sets `Int` and `Nat` by
+/// nondeterministic choices, as those can't be picked from uniformly.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Distribution {
+    /// Uniformly in the range `[-bound, bound]`, or `[0, bound]` for `Nat`.
+    Range { bound: u32 },
+    /// The absolute value follows a geometric distribution with the given
+    /// mean, so small integers are the most likely, but there is no bound
+    /// other than the 64-bit one. For `Int`, the sign is picked uniformly.
+    Geometric { mean: u32 },
+}
+
+impl Default for Distribution {
+    /// By default, integers are picked uniformly from `[-2^31, 2^31]`, so that
+    /// the product of any two picked integers still fits in 64 bits.
+    fn default() -> Self {
+        Distribution::Range { bound: 1 << 31 }
+    }
+}
+
+impl Distribution {
+    /// Sample an integer from `Int`.
+    pub fn sample_int(&self, rand: &mut Rand) -> i64 {
+        match self {
+            Distribution::Range { bound } => {
+                // Computed in 64 bits, as `2 * bound + 1` may not fit in a `usize`
+                let bound = u64::from(*bound);
+                let offset = rand.next_u64(2 * bound + 1);
+                offset as i64 - bound as i64
+            }
+            Distribution::Geometric { .. } => {
+                let magnitude = self.sample_nat(rand);
+                if magnitude != 0 && rand.next(2) == 0 {
+                    -magnitude
+                } else {
+                    magnitude
+                }
+            }
+        }
+    }
+
+    /// Sample an integer from `Nat`.
+    pub fn sample_nat(&self, rand: &mut Rand) -> i64 {
+        match self {
+            Distribution::Range { bound } => rand.next_u64(u64::from(*bound) + 1) as i64,
+            Distribution::Geometric { mean } => {
+                // Inverse transform sampling, with success probability
+                // p = 1 / (mean + 1). Float to integer casts saturate, so
+                // huge samples are capped at the maximum 64-bit integer.
+                let p = 1.0 / (f64::from(*mean) + 1.0);
+                let uniform = rand.next_float();
+                ((1.0 - uniform).ln() / (1.0 - p).ln()).floor() as i64
+            }
+        }
+    }
+}
+
+/// Sample an integer from `[1, n]` following the Zipf distribution with the
+/// given exponent, where `k` has a probability proportional to
+/// `1 / k^exponent`, so 1 is the most likely. Samples are taken by
+/// rejection-inversion, in constant time on average, however large `n` is.
+pub fn sample_zipf(rand: &mut Rand, n: u64, exponent: f64) -> u64 {
+    let (size, s) = (n as f64, exponent);
+    // The inverse of the integral of the (continuous) density is scaled by
+    // `t`, and `q` is its exponent
+    let (q, t) = if s != 1.0 {
+        let q = 1.0 / (1.0 - s);
+        (q, (size.powf(1.0 - s) - s) * q)
+    } else {
+        (0.0, 1.0 + size.ln())
+    };
+
+    loop {
+        let pt = rand.next_float() * t;
+        let inverse = if pt <= 1.0 {
+            pt
+        } else if s != 1.0 {
+            (pt * (1.0 - s) + s).powf(q)
+        } else {
+            (pt - 1.0).exp()
+        };
+        let k = (inverse + 1.0).floor();
+        let mut ratio = k.powf(-s);
+        if k > 1.0 {
+            ratio *= inverse.powf(s);
+        }
+        if rand.next_float() < ratio {
+            // In case of rounding at the ends of the range
+            return (k as u64).clamp(1, n);
+        }
+    }
+}
+
 impl Value {
+    /// Pick a random element from the set. Finite sets are picked from without
+    /// being enumerated, while integers from the infinite sets `Int` and `Nat`
+    /// are sampled from the given distribution.
+    pub fn sample(&self, rand: &mut Rand, distribution: &Distribution) -> EvalResult {
+        if !self.is_infinite() {
+            // Some sets require multiple random numbers in order to pick an element efficiently.
+            // For example, a cross product will require one random number per set, and return a tuple like
+            // (set1.pick(r1), set2.pick(r2), ..., setn.pick(rn))
+
+            return self.pick_by(|bound| rand.next(bound));
+        }
+
+        match self {
+            Value::Universe(Universe::Int) => Ok(Value::Int(distribution.sample_int(rand))),
+            Value::Universe(Universe::Nat) => Ok(Value::Int(distribution.sample_nat(rand))),
+            Value::CrossProduct(sets, _) => sets
+                .iter()
+                .map(|set| set.sample(rand, distribution))
+                .collect::<Result<_, _>>()
+                .map(Value::Tuple),
+            Value::MapSet(domain, range, _) if !domain.is_infinite() => domain
+                .iter_set()
+                .map(|key| Ok((key, range.sample(rand, distribution)?)))
+                .collect::<Result<_, _>>()
+                .map(Value::Map),
+            _ => Err(QuintError::new(
+                ErrorKind::InfiniteSet,
+                format!("Cannot pick an element from the infinite set {self}").as_str(),
+            )),
+        }
+    }
+
+    /// Pick an element from a finite set, choosing a position within each of
+    /// its [`bounds`](Value::bounds) with `choose`.
+    pub fn pick_by(&self, mut choose: impl FnMut(usize) -> usize) -> EvalResult {
+        // The ranges in which to generate which position
+        let bounds = self.bounds();
+        // The generated position for each bound
+        let mut positions = Vec::with_capacity(bounds.len());
+
+        for bound in bounds {
+            if bound == 0 {
+                return Err(QuintError::new(
+                    ErrorKind::EmptySet,
+                    "Applied oneOf on an empty set",
+                ));
+            }
+
+            positions.push(choose(bound))
+        }
+
+        Ok(self.pick(&mut positions.into_iter()))
+    }
+
     /// Pick a value from the set, using the given indexes, without enumerating
     /// the elements (and thus avoiding expensive computations).
     ///
@@ -25,16 +235,24 @@ impl Value {
                 assert!(idx <= end - start);
                 Value::Int(start + idx)
             }
-            Value::CrossProduct(sets) => {
+            Value::BitSet(labels, bits) => {
+                let index = indexes
+                    .next()
+                    .expect("Internal error: too few positions. Report a bug");
+                Value::bitset_elements(labels, *bits)
+                    .nth(index)
+                    .expect("Index should be within the bitset")
+            }
+            Value::CrossProduct(sets, _) => {
                 Value::Tuple(sets.iter().map(|value| value.pick(indexes)).collect())
             }
-            Value::PowerSet(base_set) => {
+            Value::PowerSet(base_set, _) => {
                 let index = indexes
                     .next()
                     .expect("Internal error: too few positions. Report a bug");
                 powerset_at_index(&base_set.as_set(), index)
             }
-            Value::MapSet(domain, range) => {
+            Value::MapSet(domain, range, _) => {
                 let domain_size = domain.cardinality();
                 let range_size = range.cardinality();
 
@@ -54,6 +272,54 @@ impl Value {
 
                 Value::Map(ImmutableMap::from_iter(key_values))
             }
+            Value::Universe(Universe::Bool) => {
+                let index = indexes
+                    .next()
+                    .expect("Internal error: too few positions. Report a bug");
+                Value::Bool(index == 1)
+            }
+            _ => panic!("Not a set"),
+        }
+    }
+
+    /// The minimum element of a set under the total order of values, or `None`
+    /// if the set is empty. This is the canonical element of the set, used for
+    /// deterministic picks.
+    ///
+    /// Intermediate values are not enumerated: the minimum of a powerset is
+    /// always the empty set, the minimum of a cross product is the tuple of
+    /// minimums, and the minimum of a set of maps maps every key to the
+    /// minimum of the range.
+    ///
+    /// `Int` has no minimum, so its canonical element is zero.
+    pub fn min_element(&self) -> Option<Value> {
+        match self {
+            Value::Set(set) => set.iter().min().cloned(),
+            Value::Interval(start, _) => Some(Value::Int(*start)),
+            Value::BitSet(labels, bits) => Value::bitset_elements(labels, *bits).next(),
+            Value::CrossProduct(sets, _) => sets
+                .iter()
+                .map(|set| set.min_element())
+                .collect::<Option<_>>()
+                .map(Value::Tuple),
+            Value::PowerSet(..) => Some(Value::Set(ImmutableSet::default())),
+            Value::MapSet(domain, range, _) => {
+                if domain.cardinality() == 0 {
+                    // To reflect the behaviour of TLC, an empty domain needs to give Set(Map())
+                    return Some(Value::Map(ImmutableMap::default()));
+                }
+
+                let min_value = range.min_element()?;
+                Some(Value::Map(
+                    domain
+                        .as_set()
+                        .iter()
+                        .map(|key| (key.clone(), min_value.clone()))
+                        .collect(),
+                ))
+            }
+            Value::Universe(Universe::Bool) => Some(Value::Bool(false)),
+            Value::Universe(Universe::Int | Universe::Nat) => Some(Value::Int(0)),
             _ => panic!("Not a set"),
         }
     }
@@ -65,13 +331,14 @@ impl Value {
     pub fn bounds(&self) -> Vec<usize> {
         match self {
             Value::Set(set) => vec![set.len()],
-            Value::Interval(_, _) => vec![self.cardinality()],
-            Value::CrossProduct(sets) => sets.iter().map(|set| set.cardinality()).collect(),
-            Value::PowerSet(base_set) => vec![base_set.cardinality()],
-            Value::MapSet(domain, range) => {
+            Value::Interval(_, _) | Value::BitSet(_, _) => vec![self.cardinality()],
+            Value::CrossProduct(sets, _) => sets.iter().map(|set| set.cardinality()).collect(),
+            Value::PowerSet(base_set, _) => vec![base_set.cardinality()],
+            Value::MapSet(domain, range, _) => {
                 // Cardinality of range repeated domain times
                 vec![range.cardinality(); domain.cardinality()]
             }
+            Value::Universe(Universe::Bool) => vec![2],
             _ => panic!("Not a set"),
         }
     }
@@ -0,0 +1,424 @@
+//! Printing of the IR as Quint, so users can read specs the way the evaluator
+//! sees them (i.e. after flattening and lowering, see [`crate::lowering`]),
+//! and errors can show the expressions they are about.
+//!
+//! Built-in operators are written in the syntax of Quint: infix for
+//! arithmetic, comparisons and boolean connectives, with parentheses only
+//! where precedence requires them; blocks for `all { ... }` and
+//! `any { ... }`; literals for records, tuples, lists and maps; and method
+//! calls (i.e. `S.map(x => x + 1)`) for the other built-in operators.
+//! Operators applied to unexpected arguments are written in their normal
+//! form, i.e. `iadd(x)`, as in the IR. Declarations don't have types, except
+//! for constants, as the evaluator doesn't read them.
+
+use crate::ir::{
+    OpDef, OpQualifier, QuintConst, QuintDeclaration, QuintEx, QuintLambdaParameter, QuintModule,
+    QuintOutput, QuintType, Row,
+};
+use crate::reflection::builtin;
+use itertools::Itertools;
+
+/// The longest line a block (i.e. `all { ... }`) is written in before its
+/// expressions are written on lines of their own.
+const MAX_LINE: usize = 80;
+
+/// Built-in operators written as functions, i.e. `not(p)`, instead of as
+/// methods of their first argument. This includes the operators with syntax
+/// of their own, for the arguments that the syntax can't write (i.e.
+/// assignments to something other than a name).
+const FUNCTIONS: [&str; 19] = [
+    "assign",
+    "next",
+    "Tup",
+    "Rec",
+    "Map",
+    "field",
+    "item",
+    "variant",
+    "matchVariant",
+    "not",
+    "always",
+    "eventually",
+    "enabled",
+    "weakFair",
+    "strongFair",
+    "fail",
+    "assert",
+    "withProbability",
+    "q::debug",
+];
+
+/// The precedence of expressions, from the loosest to the tightest binding,
+/// as in the grammar of Quint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum Precedence {
+    /// Lambdas, conditionals and other expressions that extend to the right
+    /// as far as possible
+    Open,
+    Implies,
+    Iff,
+    Or,
+    And,
+    Assign,
+    Comparison,
+    Sum,
+    Product,
+    Negation,
+    Power,
+    /// Names, literals, calls and anything else that never needs parentheses
+    Atom,
+}
+
+/// A spec as Quint, with all of its modules.
+pub fn to_quint(output: &QuintOutput) -> String {
+    output.modules.iter().map(module_to_quint).join("\n\n")
+}
+
+pub fn module_to_quint(module: &QuintModule) -> String {
+    let declarations = module
+        .declarations
+        .iter()
+        .filter_map(declaration_to_quint)
+        .map(|decl| format!("{}\n", indent(&decl)))
+        .join("");
+    format!("module {} {{\n{declarations}}}", module.name)
+}
+
+/// A declaration as Quint, or `None` for the declarations the evaluator
+/// ignores (types, imports, instances and exports).
+pub fn declaration_to_quint(decl: &QuintDeclaration) -> Option<String> {
+    match decl {
+        QuintDeclaration::QuintOpDef(def) => Some(opdef_to_quint(def)),
+        QuintDeclaration::QuintVar(var) => Some(format!("var {}", var.name)),
+        QuintDeclaration::QuintAssume(assume) => Some(format!(
+            "assume {} = {}",
+            assume.name,
+            expr_to_quint(&assume.assumption)
+        )),
+        QuintDeclaration::QuintConst(QuintConst {
+            name,
+            type_annotation,
+            ..
+        }) => Some(match type_annotation.as_ref().and_then(type_to_quint) {
+            Some(typ) => format!("const {name}: {typ}"),
+            None => format!("const {name}"),
+        }),
+        _ => None,
+    }
+}
+
+/// A definition as Quint, with the parameters of operators next to their
+/// name, i.e. `pure def double(x) = x + x`.
+pub fn opdef_to_quint(def: &OpDef) -> String {
+    let qualifier = match def.qualifier {
+        OpQualifier::PureDef => "pure def",
+        OpQualifier::PureVal => "pure val",
+        OpQualifier::Def => "def",
+        OpQualifier::Val => "val",
+        OpQualifier::Nondet => "nondet",
+        OpQualifier::Action => "action",
+        OpQualifier::Run => "run",
+        OpQualifier::Temporal => "temporal",
+    };
+    match &def.expr {
+        QuintEx::QuintLambda { params, expr, .. } if def.qualifier != OpQualifier::Nondet => {
+            let params = params.iter().map(|p| &p.name).join(", ");
+            format!(
+                "{qualifier} {}({params}) = {}",
+                def.name,
+                expr_to_quint(expr)
+            )
+        }
+        expr => format!("{qualifier} {} = {}", def.name, expr_to_quint(expr)),
+    }
+}
+
+/// An expression as Quint.
+pub fn expr_to_quint(expr: &QuintEx) -> String {
+    print(expr).0
+}
+
+/// An expression as Quint, with parentheses if it binds looser than
+/// `precedence`.
+fn print_at(expr: &QuintEx, precedence: Precedence) -> String {
+    let (text, own) = print(expr);
+    if own < precedence {
+        format!("({text})")
+    } else {
+        text
+    }
+}
+
+/// An expression as Quint, with its precedence.
+fn print(expr: &QuintEx) -> (String, Precedence) {
+    match expr {
+        QuintEx::QuintName { name, .. } => (name.to_string(), Precedence::Atom),
+        QuintEx::QuintBool { value, .. } => (value.to_string(), Precedence::Atom),
+        QuintEx::QuintInt { value, .. } if *value < 0 => (value.to_string(), Precedence::Negation),
+        QuintEx::QuintInt { value, .. } => (value.to_string(), Precedence::Atom),
+        QuintEx::QuintStr { value, .. } => (format!("{:?}", value.as_str()), Precedence::Atom),
+        QuintEx::QuintApp { opcode, args, .. } => print_app(opcode, args),
+        QuintEx::QuintLambda { params, expr, .. } => (lambda(params, expr), Precedence::Open),
+        QuintEx::QuintLet { .. } => {
+            // Nested definitions are written one per line, as in a block
+            let mut lines = Vec::new();
+            let mut expr = expr;
+            while let QuintEx::QuintLet {
+                opdef, expr: body, ..
+            } = expr
+            {
+                lines.push(opdef_to_quint(opdef));
+                expr = body;
+            }
+            lines.push(expr_to_quint(expr));
+            let body = lines.iter().map(|line| indent(line)).join("\n");
+            (format!("{{\n{body}\n}}"), Precedence::Atom)
+        }
+    }
+}
+
+fn print_app(opcode: &str, args: &[QuintEx]) -> (String, Precedence) {
+    use Precedence::*;
+
+    let binary = |symbol: &str, precedence: Precedence| {
+        let (left, right) = match precedence {
+            // Comparisons and implications don't chain
+            Comparison | Implies => (next(precedence), next(precedence)),
+            Power => (Atom, Power),
+            _ => (precedence, next(precedence)),
+        };
+        let text = format!(
+            "{} {symbol} {}",
+            print_at(&args[0], left),
+            print_at(&args[1], right)
+        );
+        (text, precedence)
+    };
+    let label = |expr: &QuintEx| match expr {
+        QuintEx::QuintStr { value, .. } => Some(value.to_string()),
+        _ => None,
+    };
+
+    match (opcode, args) {
+        ("iadd", [_, _]) => binary("+", Sum),
+        ("isub", [_, _]) => binary("-", Sum),
+        ("imul", [_, _]) => binary("*", Product),
+        ("idiv", [_, _]) => binary("/", Product),
+        ("imod", [_, _]) => binary("%", Product),
+        ("ipow", [_, _]) => binary("^", Power),
+        ("ilt", [_, _]) => binary("<", Comparison),
+        ("ilte", [_, _]) => binary("<=", Comparison),
+        ("igt", [_, _]) => binary(">", Comparison),
+        ("igte", [_, _]) => binary(">=", Comparison),
+        ("eq", [_, _]) => binary("==", Comparison),
+        ("neq", [_, _]) => binary("!=", Comparison),
+        ("iff", [_, _]) => binary("iff", Iff),
+        ("implies", [_, _]) => binary("implies", Implies),
+        ("iuminus", [arg]) => (format!("-{}", print_at(arg, Power)), Negation),
+        ("and" | "or", [_, _, ..]) => {
+            let precedence = if opcode == "and" { And } else { Or };
+            let text = args
+                .iter()
+                .map(|arg| print_at(arg, next(precedence)))
+                .join(&format!(" {opcode} "));
+            (text, precedence)
+        }
+        ("and" | "or", _) => (block(opcode, args), Atom),
+        ("actionAll", _) => (block("all", args), Atom),
+        ("actionAny", _) => (block("any", args), Atom),
+        ("ite", [cond, then, otherwise]) => (
+            format!(
+                "if ({}) {} else {}",
+                expr_to_quint(cond),
+                print_at(then, Implies),
+                expr_to_quint(otherwise)
+            ),
+            Open,
+        ),
+        ("assign", [QuintEx::QuintName { name, .. }, value]) => {
+            (format!("{name}' = {}", print_at(value, Comparison)), Assign)
+        }
+        ("next", [QuintEx::QuintName { name, .. }]) => (format!("{name}'"), Atom),
+        ("Set", _) => (format!("Set({})", list(args)), Atom),
+        ("List", _) => (format!("[{}]", list(args)), Atom),
+        ("Tup", [_, _, ..]) => (format!("({})", list(args)), Atom),
+        ("Rec", [_, ..]) if args.len().is_multiple_of(2) => {
+            let fields = args
+                .iter()
+                .tuples()
+                .map(|(name, value)| Some(format!("{}: {}", label(name)?, expr_to_quint(value))))
+                .collect::<Option<Vec<_>>>();
+            match fields {
+                Some(fields) => (format!("{{ {} }}", fields.join(", ")), Atom),
+                None => call(opcode, args),
+            }
+        }
+        ("Map", _) => {
+            let pairs = args
+                .iter()
+                .map(|pair| match pair {
+                    QuintEx::QuintApp { opcode, args, .. }
+                        if opcode == "Tup" && args.len() == 2 =>
+                    {
+                        Some(format!(
+                            "{} -> {}",
+                            expr_to_quint(&args[0]),
+                            expr_to_quint(&args[1])
+                        ))
+                    }
+                    _ => None,
+                })
+                .collect::<Option<Vec<_>>>();
+            match pairs {
+                Some(pairs) => (format!("Map({})", pairs.join(", ")), Atom),
+                None => call(opcode, args),
+            }
+        }
+        ("field", [record, name]) if label(name).is_some() => (
+            format!("{}.{}", print_at(record, Atom), label(name).unwrap()),
+            Atom,
+        ),
+        ("item", [tuple, QuintEx::QuintInt { value, .. }]) => {
+            (format!("{}._{value}", print_at(tuple, Atom)), Atom)
+        }
+        ("variant", [name, value]) if label(name).is_some() => {
+            let label = label(name).unwrap();
+            match value {
+                QuintEx::QuintApp { opcode, args, .. } if opcode == "Tup" && args.is_empty() => {
+                    (label, Atom)
+                }
+                _ => (format!("{label}({})", expr_to_quint(value)), Atom),
+            }
+        }
+        ("matchVariant", [expr, cases @ ..]) if cases.len().is_multiple_of(2) => {
+            let cases = cases
+                .iter()
+                .tuples()
+                .map(|(name, handler)| {
+                    let label = label(name)?;
+                    let QuintEx::QuintLambda { params, expr, .. } = handler else {
+                        return None;
+                    };
+                    let pattern = match (label.as_str(), params.as_slice()) {
+                        ("_", _) => "_".to_string(),
+                        (label, [param]) if param.name == "_" => label.to_string(),
+                        (label, [param]) => format!("{label}({})", param.name),
+                        _ => return None,
+                    };
+                    Some(format!("| {pattern} => {}", expr_to_quint(expr)))
+                })
+                .collect::<Option<Vec<_>>>();
+            match cases {
+                Some(cases) => (
+                    format!("match {} {{ {} }}", print_at(expr, Atom), cases.join(" ")),
+                    Atom,
+                ),
+                None => call(opcode, args),
+            }
+        }
+        (_, [receiver, rest @ ..])
+            if builtin(opcode)
+                .and_then(|b| b.arity)
+                .is_some_and(|arity| arity.contains(args.len()))
+                && !FUNCTIONS.contains(&opcode) =>
+        {
+            let text = format!("{}.{opcode}({})", print_at(receiver, Atom), list(rest));
+            (text, Atom)
+        }
+        _ => call(opcode, args),
+    }
+}
+
+/// The precedence of the right operand of a left-associative operator.
+fn next(precedence: Precedence) -> Precedence {
+    use Precedence::*;
+    match precedence {
+        Open => Implies,
+        Implies => Iff,
+        Iff => Or,
+        Or => And,
+        And => Assign,
+        Assign => Comparison,
+        Comparison => Sum,
+        Sum => Product,
+        Product => Negation,
+        Negation => Power,
+        Power | Atom => Atom,
+    }
+}
+
+/// An operator applied to its arguments in normal form, i.e. `f(x, y)`.
+fn call(opcode: &str, args: &[QuintEx]) -> (String, Precedence) {
+    (format!("{opcode}({})", list(args)), Precedence::Atom)
+}
+
+fn list(args: &[QuintEx]) -> String {
+    args.iter().map(expr_to_quint).join(", ")
+}
+
+fn lambda(params: &[QuintLambdaParameter], expr: &QuintEx) -> String {
+    let body = expr_to_quint(expr);
+    match params {
+        [param] => format!("{} => {body}", param.name),
+        _ => format!("({}) => {body}", params.iter().map(|p| &p.name).join(", ")),
+    }
+}
+
+/// A block, i.e. `all { a, b }`, with its expressions on lines of their own
+/// if they don't fit in a line.
+fn block(keyword: &str, args: &[QuintEx]) -> String {
+    let args = args.iter().map(expr_to_quint).collect::<Vec<_>>();
+    let line = format!("{keyword} {{ {} }}", args.join(", "));
+    if line.len() <= MAX_LINE && !line.contains('\n') {
+        return line;
+    }
+    let lines = args
+        .iter()
+        .map(|arg| format!("{},", indent(arg)))
+        .join("\n");
+    format!("{keyword} {{\n{lines}\n}}")
+}
+
+/// Indent all the lines of some text.
+fn indent(text: &str) -> String {
+    text.lines().map(|line| format!("  {line}")).join("\n")
+}
+
+/// A type as Quint, if it can be written (i.e. not an alias).
+pub fn type_to_quint(typ: &QuintType) -> Option<String> {
+    let fields = |row: &Row| -> Option<Vec<(String, String)>> {
+        let (fields, _) = row.fields();
+        fields
+            .into_iter()
+            .map(|f| Some((f.field_name.to_string(), type_to_quint(&f.field_type)?)))
+            .collect()
+    };
+    Some(match typ {
+        QuintType::Bool => "bool".to_string(),
+        QuintType::Int => "int".to_string(),
+        QuintType::Str => "str".to_string(),
+        QuintType::Set { elem } => format!("Set[{}]", type_to_quint(elem)?),
+        QuintType::List { elem } => format!("List[{}]", type_to_quint(elem)?),
+        QuintType::Fun { arg, res } => {
+            format!("{} -> {}", type_to_quint(arg)?, type_to_quint(res)?)
+        }
+        QuintType::Tup { fields: row } => {
+            format!("({})", fields(row)?.into_iter().map(|(_, t)| t).join(", "))
+        }
+        QuintType::Rec { fields: row } => format!(
+            "{{ {} }}",
+            fields(row)?
+                .into_iter()
+                .map(|(name, t)| format!("{name}: {t}"))
+                .join(", ")
+        ),
+        QuintType::Sum { fields: row } => fields(row)?
+            .into_iter()
+            .map(|(name, t)| match t.as_str() {
+                "()" => name,
+                _ => format!("{name}({t})"),
+            })
+            .join(" | "),
+        QuintType::Other => return None,
+    })
+}
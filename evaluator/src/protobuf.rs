@@ -0,0 +1,254 @@
+//! Encoding of values and traces with protobuf (see `proto/values.proto`),
+//! for compact binary interchange with other services and languages.
+//!
+//! As in ITF (see [`crate::itf`]), the elements of sets and the entries of
+//! maps are sorted, and so are the fields of records, so the same value is
+//! always encoded the same way. This is only available with the `protobuf`
+//! feature.
+
+/// The messages generated from `proto/values.proto`.
+pub mod proto {
+    include!(concat!(env!("OUT_DIR"), "/quint.values.v1.rs"));
+}
+
+use crate::ir::{ErrorKind, QuintError};
+use crate::spans::span;
+use crate::trace::{Alternative, State, StepMeta, Trace};
+use crate::value::{ImmutableMap, ImmutableVec, Value};
+use itertools::Itertools;
+use prost::Message;
+use proto::value::Kind;
+use std::rc::Rc;
+use thiserror::Error;
+
+/// An error when decoding a trace or a value from protobuf.
+#[derive(Debug, Clone, Error, PartialEq)]
+#[error("Invalid protobuf: {0}")]
+pub struct ProtobufError(String);
+
+impl Trace {
+    /// The trace as a protobuf message, or an error if it has values that
+    /// can't be encoded (see [`Value::to_proto`]).
+    pub fn to_proto(&self) -> Result<proto::Trace, QuintError> {
+        Ok(proto::Trace {
+            states: self
+                .states
+                .iter()
+                .map(|state| record_to_proto(&state.vars))
+                .collect::<Result<_, _>>()?,
+            violation: self.violation,
+            // Only traces with all of their steps recorded have them
+            steps: self
+                .states
+                .iter()
+                .map(|state| state.meta.as_ref())
+                .collect::<Option<Vec<_>>>()
+                .unwrap_or_default()
+                .into_iter()
+                .map(|meta| {
+                    Ok(proto::Step {
+                        action_taken: meta.action_taken.clone().unwrap_or_default(),
+                        nondet_picks: Some(record_to_proto(&meta.picks())?),
+                        alternatives: meta
+                            .alternatives
+                            .iter()
+                            .map(|alternative| proto::Alternative {
+                                index: alternative.index as u64,
+                                name: alternative.name.clone(),
+                            })
+                            .collect(),
+                    })
+                })
+                .collect::<Result<_, QuintError>>()?,
+            seed: self.seed,
+        })
+    }
+
+    pub fn from_proto(trace: &proto::Trace) -> Result<Trace, ProtobufError> {
+        if !trace.steps.is_empty() && trace.steps.len() != trace.states.len() {
+            return Err(ProtobufError(format!(
+                "{} steps for {} states",
+                trace.steps.len(),
+                trace.states.len()
+            )));
+        }
+        let mut steps = trace.steps.iter().map(step_from_proto);
+        let states = trace
+            .states
+            .iter()
+            .map(|state| {
+                Ok(State {
+                    vars: record_from_proto(state)?,
+                    meta: steps.next().transpose()?,
+                })
+            })
+            .collect::<Result<Vec<_>, ProtobufError>>()?;
+
+        Ok(Trace {
+            states,
+            violation: trace.violation,
+            seed: trace.seed,
+        })
+    }
+
+    /// Encode the trace in the protobuf wire format.
+    pub fn encode_proto(&self) -> Result<Vec<u8>, QuintError> {
+        let _span = span!(INFO, "export", states = self.states.len());
+        Ok(self.to_proto()?.encode_to_vec())
+    }
+
+    /// Decode a trace from the protobuf wire format.
+    pub fn decode_proto(bytes: &[u8]) -> Result<Trace, ProtobufError> {
+        let trace = proto::Trace::decode(bytes).map_err(|e| ProtobufError(e.to_string()))?;
+        Trace::from_proto(&trace)
+    }
+}
+
+impl Value {
+    /// The value as a protobuf message. Infinite sets and operators have no
+    /// encoding, so they give an error.
+    pub fn to_proto(&self) -> Result<proto::Value, QuintError> {
+        let elements = |elems: Vec<&Value>| {
+            Ok::<_, QuintError>(proto::Elements {
+                elements: elems
+                    .into_iter()
+                    .map(Value::to_proto)
+                    .collect::<Result<_, _>>()?,
+            })
+        };
+
+        let kind = match self {
+            Self::Int(i) => Kind::Int(*i),
+            Self::Bool(b) => Kind::Bool(*b),
+            Self::Str(s) => Kind::Str(s.to_string()),
+            Self::Set(_)
+            | Self::Interval(_, _)
+            | Self::CrossProduct(..)
+            | Self::PowerSet(..)
+            | Self::MapSet(..)
+            | Self::Universe(_)
+            | Self::BitSet(_, _) => {
+                let set = self.try_set()?;
+                Kind::Set(elements(set.iter().sorted_unstable().collect())?)
+            }
+            Self::Tuple(elems) => Kind::Tuple(elements(elems.iter().collect())?),
+            Self::List(elems) => Kind::List(elements(elems.iter().collect())?),
+            Self::Record(_) => Kind::Record(record_to_proto(self)?),
+            Self::Map(map) => Kind::Map(proto::Map {
+                entries: map
+                    .iter()
+                    .sorted_unstable()
+                    .map(|(key, value)| {
+                        Ok(proto::Entry {
+                            key: Some(key.to_proto()?),
+                            value: Some(value.to_proto()?),
+                        })
+                    })
+                    .collect::<Result<_, QuintError>>()?,
+            }),
+            Self::Variant(label, value) => Kind::Variant(Box::new(proto::Variant {
+                label: label.to_string(),
+                value: Some(Box::new(value.to_proto()?)),
+            })),
+            Self::Lambda(_, _) => {
+                return Err(QuintError::new(
+                    ErrorKind::Unsupported,
+                    "Cannot encode an operator in protobuf",
+                ))
+            }
+        };
+
+        Ok(proto::Value { kind: Some(kind) })
+    }
+
+    pub fn from_proto(value: &proto::Value) -> Result<Value, ProtobufError> {
+        let elements = |elems: &proto::Elements| {
+            elems
+                .elements
+                .iter()
+                .map(Value::from_proto)
+                .collect::<Result<Vec<_>, _>>()
+        };
+
+        match value.kind.as_ref() {
+            Some(Kind::Int(i)) => Ok(Value::Int(*i)),
+            Some(Kind::Bool(b)) => Ok(Value::Bool(*b)),
+            Some(Kind::Str(s)) => Ok(Value::Str(s.as_str().into())),
+            Some(Kind::Set(elems)) => Ok(Value::Set(
+                elements(elems)?.into_iter().map(Value::normalize).collect(),
+            )),
+            Some(Kind::Tuple(elems)) => Ok(Value::Tuple(ImmutableVec::from(elements(elems)?))),
+            Some(Kind::List(elems)) => Ok(Value::List(ImmutableVec::from(elements(elems)?))),
+            Some(Kind::Record(record)) => record_from_proto(record),
+            Some(Kind::Map(map)) => map
+                .entries
+                .iter()
+                .map(|entry| {
+                    let key = Value::from_proto(entry.key.as_ref().ok_or_else(missing)?)?;
+                    Ok((
+                        key.normalize(),
+                        Value::from_proto(entry.value.as_ref().ok_or_else(missing)?)?,
+                    ))
+                })
+                .collect::<Result<ImmutableMap<_, _>, _>>()
+                .map(Value::Map),
+            Some(Kind::Variant(variant)) => Ok(Value::Variant(
+                variant.label.as_str().into(),
+                Rc::new(Value::from_proto(
+                    variant.value.as_deref().ok_or_else(missing)?,
+                )?),
+            )),
+            None => Err(ProtobufError("value without a kind".to_string())),
+        }
+    }
+}
+
+fn record_to_proto(record: &Value) -> Result<proto::Record, QuintError> {
+    Ok(proto::Record {
+        fields: record
+            .try_record_map()?
+            .iter()
+            .sorted_unstable_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(name, value)| {
+                Ok(proto::Field {
+                    name: name.to_string(),
+                    value: Some(value.to_proto()?),
+                })
+            })
+            .collect::<Result<_, QuintError>>()?,
+    })
+}
+
+fn record_from_proto(record: &proto::Record) -> Result<Value, ProtobufError> {
+    record
+        .fields
+        .iter()
+        .map(|field| {
+            let value = Value::from_proto(field.value.as_ref().ok_or_else(missing)?)?;
+            Ok((field.name.as_str().into(), value))
+        })
+        .collect::<Result<ImmutableMap<_, _>, _>>()
+        .map(Value::Record)
+}
+
+fn step_from_proto(step: &proto::Step) -> Result<StepMeta, ProtobufError> {
+    let picks = record_from_proto(step.nondet_picks.as_ref().ok_or_else(missing)?)?;
+    let action = Some(step.action_taken.clone()).filter(|action| !action.is_empty());
+    let alternatives = step
+        .alternatives
+        .iter()
+        .map(|alternative| Alternative {
+            index: alternative.index as usize,
+            name: alternative.name.clone(),
+        })
+        .collect();
+    Ok(StepMeta {
+        alternatives,
+        ..StepMeta::from_picks(action, &picks)
+    })
+}
+
+/// Message fields are optional in protobuf, but values always have them.
+fn missing() -> ProtobufError {
+    ProtobufError("missing value".to_string())
+}
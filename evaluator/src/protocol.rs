@@ -0,0 +1,206 @@
+//! The protocol the `quint` CLI uses to delegate simulations to a native
+//! backend, running as a subprocess (see the `backend` command), so this
+//! evaluator can replace the existing simulator backend.
+//!
+//! Messages are JSON objects, one per line, on the STDIN and STDOUT of the
+//! backend, each with a `type`:
+//!  1. The backend starts with a handshake, `{"type": "hello", "protocol",
+//!     "version"}`, with the version of the protocol ([`PROTOCOL`]) and of
+//!     the evaluator. The frontend answers with `{"type": "hello",
+//!     "protocol"}`, and the backend stops with an `error` message if it
+//!     doesn't speak that version.
+//!  2. The frontend sends a config message, `{"type": "simulate", "parsed",
+//!     "source", "witnesses", "nruns", "nsteps", "ntraces", "seed"}`, with the
+//!     same fields as the input of `simulate-from-stdin` (see
+//!     [`SimulateInput`]).
+//!  3. The backend streams `{"type": "progress", "current", "total",
+//!     "states", "violations", "elapsedMs"}` messages while simulating (at
+//!     most every [`PROGRESS_INTERVAL`]), and ends with `{"type": "outcome",
+//!     "outcome"}`, with the same [`Outcome`] as `simulate-from-stdin`.
+//!
+//! Steps 2 and 3 repeat until the frontend closes STDIN. Invalid messages are
+//! answered with `{"type": "error", "message"}`.
+
+use crate::ir::QuintEx;
+use crate::server::{error_outcome, to_outcome, Outcome};
+use crate::simulator::{ParsedQuint, SimulationHooks, SimulatorConfig};
+use crate::trace::{State, Trace};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as Json};
+use std::io::{self, BufRead, Write};
+use std::time::{Duration, Instant};
+
+/// The version of the protocol.
+pub const PROTOCOL: u64 = 1;
+
+/// The minimum time between two progress messages.
+pub const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
+/// A simulation requested by the frontend, as the input of
+/// `simulate-from-stdin` and the config messages of the protocol.
+#[derive(Serialize, Deserialize)]
+pub struct SimulateInput {
+    pub parsed: ParsedQuint,
+    pub source: String,
+    pub witnesses: Vec<QuintEx>,
+    pub nruns: usize,
+    pub nsteps: usize,
+    pub ntraces: usize,
+    /// The master seed, random if not given
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl SimulateInput {
+    /// Run the simulation, reporting to `hooks`.
+    pub fn simulate(self, hooks: &mut dyn SimulationHooks) -> Outcome {
+        let parsed = ParsedQuint {
+            witnesses: self.witnesses,
+            ..self.parsed
+        };
+        if let Err(errors) = parsed.validate() {
+            return error_outcome(errors);
+        }
+        let config = SimulatorConfig {
+            steps: self.nsteps,
+            samples: self.nruns,
+            n_traces: self.ntraces,
+            seed: self.seed,
+            ..SimulatorConfig::default()
+        };
+        let result = parsed.simulate_with_hooks(&config, None, hooks);
+        to_outcome(self.source, result)
+    }
+}
+
+enum Message {
+    Hello { protocol: u64 },
+    Simulate(Box<SimulateInput>),
+}
+
+impl Message {
+    fn parse(line: &str) -> Result<Message, serde_json::Error> {
+        // Specs can't be deserialized from the buffered content of a tagged
+        // enum, so the message is parsed first and then dispatched on its type
+        let mut message: Json = serde_json::from_str(line)?;
+        let kind = message["type"].take();
+        match kind.as_str() {
+            Some("hello") => Ok(Message::Hello {
+                protocol: serde_json::from_value(message["protocol"].take())?,
+            }),
+            Some("simulate") => serde_json::from_value(message).map(Message::Simulate),
+            _ => Err(serde::de::Error::custom(format!(
+                "unknown message type {kind}"
+            ))),
+        }
+    }
+}
+
+/// Speak the protocol as the backend, reading the messages of the frontend
+/// from `input` and writing to `output`, until the input ends.
+pub fn serve(mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    send(
+        &mut output,
+        json!({ "type": "hello", "protocol": PROTOCOL, "version": env!("CARGO_PKG_VERSION") }),
+    )?;
+
+    let mut greeted = false;
+    let mut line = String::new();
+    loop {
+        line.clear();
+        if input.read_line(&mut line)? == 0 {
+            return Ok(());
+        }
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        match Message::parse(&line) {
+            Ok(Message::Hello { protocol }) if protocol == PROTOCOL => greeted = true,
+            Ok(Message::Hello { protocol }) => {
+                let message = format!("unsupported protocol {protocol}, expected {PROTOCOL}");
+                return send(&mut output, json!({ "type": "error", "message": message }));
+            }
+            Ok(Message::Simulate(_)) if !greeted => {
+                let message = "expected a `hello` message first";
+                send(&mut output, json!({ "type": "error", "message": message }))?;
+            }
+            Ok(Message::Simulate(simulation)) => {
+                let mut progress = Progress::new(&mut output, simulation.nruns);
+                let outcome = simulation.simulate(&mut progress);
+                progress.finish()?;
+                send(
+                    &mut output,
+                    json!({ "type": "outcome", "outcome": outcome }),
+                )?;
+            }
+            Err(e) => {
+                let message = e.to_string();
+                send(&mut output, json!({ "type": "error", "message": message }))?;
+            }
+        }
+    }
+}
+
+fn send(output: &mut impl Write, message: Json) -> io::Result<()> {
+    writeln!(output, "{message}")?;
+    output.flush()
+}
+
+/// The hooks writing the progress messages of a simulation.
+struct Progress<W: Write> {
+    output: W,
+    total: usize,
+    samples: usize,
+    states: usize,
+    violations: usize,
+    start: Instant,
+    last: Option<Instant>,
+    /// The first error when writing, as hooks can't fail
+    error: Option<io::Error>,
+}
+
+impl<W: Write> Progress<W> {
+    fn new(output: W, total: usize) -> Self {
+        Self {
+            output,
+            total,
+            samples: 0,
+            states: 0,
+            violations: 0,
+            start: Instant::now(),
+            last: None,
+            error: None,
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        self.error.map_or(Ok(()), Err)
+    }
+}
+
+impl<W: Write> SimulationHooks for Progress<W> {
+    fn on_step(&mut self, _state: &State) {
+        self.states += 1;
+    }
+
+    fn on_sample_end(&mut self, trace: &Trace) {
+        self.samples += 1;
+        self.violations += usize::from(trace.violation);
+        if self.error.is_some() || self.last.is_some_and(|t| t.elapsed() < PROGRESS_INTERVAL) {
+            return;
+        }
+        self.last = Some(Instant::now());
+        let progress = json!({
+            "type": "progress",
+            "current": self.samples,
+            "total": self.total,
+            "states": self.states,
+            "violations": self.violations,
+            "elapsedMs": self.start.elapsed().as_millis(),
+        });
+        if let Err(e) = send(&mut self.output, progress) {
+            self.error = Some(e);
+        }
+    }
+}
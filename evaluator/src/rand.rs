@@ -39,13 +39,48 @@ impl Rand {
 
     pub fn next(&mut self, bound: usize) -> usize {
         let bound64: u64 = bound.try_into().unwrap();
+        self.next_u64(bound64).try_into().unwrap()
+    }
+
+    /// Generate a number uniformly in the range `[0, bound)`, for bounds that
+    /// may not fit in a `usize` (i.e. on 32-bit targets). Advances the state
+    /// like [`Rand::next`], and gives the same numbers for the same bounds.
+    pub fn next_u64(&mut self, bound: u64) -> u64 {
+        let number = rand64(self.key, self.counter);
+        self.counter = self.counter.saturating_add(1);
+
+        number % bound
+    }
+
+    /// Generate a number uniformly in the range `[0, 1)`.
+    pub fn next_float(&mut self) -> f64 {
         let number = rand64(self.key, self.counter);
         self.counter = self.counter.saturating_add(1);
 
-        (number % bound64).try_into().unwrap()
+        // Use the 53 most significant bits, as many as an f64 can represent exactly
+        (number >> 11) as f64 / (1u64 << 53) as f64
     }
 
     pub fn get_state(&self) -> u64 {
         self.counter
     }
+
+    pub fn set_state(&mut self, state: u64) {
+        self.counter = state;
+    }
+}
+
+/// A random seed, for simulations where none is given.
+pub fn random_seed() -> u64 {
+    rand::rng().random()
+}
+
+/// Derive the state for an independent random stream (i.e. for one of many
+/// parallel workers) from a master seed. The same seed and stream always give
+/// the same state.
+pub fn derive_state(seed: u64, stream: u64) -> u64 {
+    rand64(
+        squares_rnd::KEY,
+        rand64(squares_rnd::KEY, seed).wrapping_add(stream),
+    )
 }
@@ -0,0 +1,259 @@
+//! Descriptions of the built-in operators and values the evaluator supports:
+//! their names, numbers of arguments, types, whether their arguments are
+//! evaluated lazily, and documentation.
+//!
+//! This is the one list of built-ins of the evaluator. The validator checks
+//! applications against it (see [`crate::validator`]), the interpreter picks
+//! how to compile operators from it (see [`crate::builtins`]), and the REPL
+//! and the `builtins` command show it, so editors and documentation
+//! generators can read what the evaluator actually implements.
+//!
+//! Types are written as in Quint's `builtin.qnt`, i.e. `(Set[a], (a) => b) =>
+//! Set[b]`, with operators that take any number of arguments written with
+//! `*`, i.e. `(a*) => Set[a]`.
+
+use serde::Serialize;
+use std::fmt;
+
+/// A built-in operator or value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Builtin {
+    pub name: &'static str,
+    /// The numbers of arguments the operator takes, or `None` for values
+    /// (i.e. `Nat`).
+    pub arity: Option<Arity>,
+    pub signature: &'static str,
+    /// Whether the arguments are evaluated by the operator, when needed,
+    /// instead of before it is applied (see [`crate::builtins`]).
+    pub lazy: bool,
+    pub doc: &'static str,
+}
+
+/// The numbers of arguments an operator takes, from `min` to `max`, or any
+/// number from `min` if there is no `max`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Arity {
+    pub min: usize,
+    pub max: Option<usize>,
+}
+
+impl Builtin {
+    /// The same operator, evaluating its arguments lazily.
+    const fn lazy(self) -> Builtin {
+        Builtin { lazy: true, ..self }
+    }
+}
+
+impl Arity {
+    pub fn contains(&self, args: usize) -> bool {
+        args >= self.min && self.max.is_none_or(|max| args <= max)
+    }
+}
+
+impl fmt::Display for Arity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max {
+            Some(max) if max == self.min => write!(f, "{max}"),
+            Some(max) => write!(f, "{} to {max}", self.min),
+            None => write!(f, "at least {}", self.min),
+        }
+    }
+}
+
+/// The built-in with the given name, if any.
+pub fn builtin(name: &str) -> Option<&'static Builtin> {
+    BUILTINS.iter().find(|builtin| builtin.name == name)
+}
+
+/// All the built-ins, grouped by the values they work on.
+pub fn builtins() -> &'static [Builtin] {
+    &BUILTINS
+}
+
+/// Whether the operator with the given name is a built-in evaluating its
+/// arguments lazily.
+pub fn is_lazy(name: &str) -> bool {
+    builtin(name).is_some_and(|builtin| builtin.lazy)
+}
+
+const fn value(name: &'static str, signature: &'static str, doc: &'static str) -> Builtin {
+    Builtin {
+        name,
+        arity: None,
+        signature,
+        lazy: false,
+        doc,
+    }
+}
+
+const fn op(
+    name: &'static str,
+    args: usize,
+    signature: &'static str,
+    doc: &'static str,
+) -> Builtin {
+    Builtin {
+        name,
+        arity: Some(Arity {
+            min: args,
+            max: Some(args),
+        }),
+        signature,
+        lazy: false,
+        doc,
+    }
+}
+
+/// An operator taking any number of arguments from `min`.
+const fn variadic(
+    name: &'static str,
+    min: usize,
+    signature: &'static str,
+    doc: &'static str,
+) -> Builtin {
+    Builtin {
+        arity: Some(Arity { min, max: None }),
+        ..op(name, min, signature, doc)
+    }
+}
+
+const BUILTINS: [Builtin; 122] = [
+    // Values
+    value("Bool", "Set[bool]", "The set of all booleans, `Set(false, true)`."),
+    value("Int", "Set[int]", "The infinite set of all integers."),
+    value("Nat", "Set[int]", "The infinite set of all natural numbers."),
+    value("true", "bool", "The boolean true."),
+    value("false", "bool", "The boolean false."),
+    // Booleans
+    op("eq", 2, "(t, t) => bool", "`a == b` is true when `a` and `b` are equal values."),
+    op("neq", 2, "(t, t) => bool", "`a != b` is true when `a` and `b` are not equal values."),
+    op("hashOf", 1, "(t) => int", "`hashOf(v)` is a digest of `v`, the same for equal values on every run."),
+    op("not", 1, "(bool) => bool", "`not(p)` is true when `p` is false."),
+    op("iff", 2, "(bool, bool) => bool", "`p iff q` is true when `p` and `q` are equal booleans."),
+    op("implies", 2, "(bool, bool) => bool", "`p implies q` is true when `not(p) or q` is true.").lazy(),
+    variadic("and", 0, "(bool*) => bool", "`p and q` is true when all of its arguments are true, evaluated in order until one is false.").lazy(),
+    variadic("or", 0, "(bool*) => bool", "`p or q` is true when some of its arguments is true, evaluated in order until one is true.").lazy(),
+    // Integers
+    op("iadd", 2, "(int, int) => int", "`a + b` is the sum of `a` and `b`."),
+    op("isub", 2, "(int, int) => int", "`a - b` is the difference of `a` and `b`."),
+    op("imul", 2, "(int, int) => int", "`a * b` is the product of `a` and `b`."),
+    op("idiv", 2, "(int, int) => int", "`a / b` is the integer division of `a` by `b`."),
+    op("imod", 2, "(int, int) => int", "`a % b` is the remainder of the division of `a` by `b`."),
+    op("ipow", 2, "(int, int) => int", "`a ^ b` is `a` to the power of `b`."),
+    op("iuminus", 1, "(int) => int", "`-a` is `a` with the opposite sign."),
+    op("bitAnd", 2, "(int, int) => int", "`a.bitAnd(b)` is the bitwise and of `a` and `b`, in two's complement."),
+    op("bitOr", 2, "(int, int) => int", "`a.bitOr(b)` is the bitwise or of `a` and `b`, in two's complement."),
+    op("bitXor", 2, "(int, int) => int", "`a.bitXor(b)` is the bitwise exclusive or of `a` and `b`, in two's complement."),
+    op("shiftLeft", 2, "(int, int) => int", "`a.shiftLeft(n)` is `a * 2^n`."),
+    op("shiftRight", 2, "(int, int) => int", "`a.shiftRight(n)` is `a / 2^n`, rounded towards negative infinity."),
+    op("popCount", 1, "(int) => int", "`a.popCount()` is the number of ones in the binary representation of `a`, which can't be negative."),
+    op("ilt", 2, "(int, int) => bool", "`a < b` is true when `a` is less than `b`."),
+    op("ilte", 2, "(int, int) => bool", "`a <= b` is true when `a` is less than or equal to `b`."),
+    op("igt", 2, "(int, int) => bool", "`a > b` is true when `a` is greater than `b`."),
+    op("igte", 2, "(int, int) => bool", "`a >= b` is true when `a` is greater than or equal to `b`."),
+    // Sets
+    variadic("Set", 0, "(a*) => Set[a]", "`Set(a, b)` is the set of its arguments."),
+    op("to", 2, "(int, int) => Set[int]", "`i.to(j)` is the set of integers from `i` to `j`."),
+    op("in", 2, "(a, Set[a]) => bool", "`e.in(s)` is true when `e` is in the set `s`."),
+    op("contains", 2, "(Set[a], a) => bool", "`s.contains(e)` is true when `e` is in the set `s`."),
+    op("union", 2, "(Set[a], Set[a]) => Set[a]", "`s1.union(s2)` is the set of elements in `s1` or in `s2`."),
+    op("intersect", 2, "(Set[a], Set[a]) => Set[a]", "`s1.intersect(s2)` is the set of elements in both `s1` and `s2`."),
+    op("exclude", 2, "(Set[a], Set[a]) => Set[a]", "`s1.exclude(s2)` is the set of elements in `s1` that are not in `s2`."),
+    op("subseteq", 2, "(Set[a], Set[a]) => bool", "`s1.subseteq(s2)` is true when all elements of `s1` are in `s2`."),
+    op("exists", 2, "(Set[a], (a) => bool) => bool", "`s.exists(p)` is true when some element of `s` satisfies `p`."),
+    op("forall", 2, "(Set[a], (a) => bool) => bool", "`s.forall(p)` is true when all elements of `s` satisfy `p`."),
+    op("filter", 2, "(Set[a], (a) => bool) => Set[a]", "`s.filter(p)` is the set of elements of `s` that satisfy `p`."),
+    op("map", 2, "(Set[a], (a) => b) => Set[b]", "`s.map(f)` is the set of the results of applying `f` to the elements of `s`."),
+    op("fold", 3, "(Set[a], b, (b, a) => b) => b", "`s.fold(z, f)` reduces the elements of `s` with `f`, starting with `z`."),
+    op("size", 1, "(Set[a]) => int", "`s.size()` is the number of elements of `s`."),
+    op("isFinite", 1, "(Set[a]) => bool", "`s.isFinite()` is true when `s` is a finite set."),
+    op("powerset", 1, "(Set[a]) => Set[Set[a]]", "`s.powerset()` is the set of all subsets of `s`."),
+    op("flatten", 1, "(Set[Set[a]]) => Set[a]", "`s.flatten()` is the set of the elements of the sets in `s`."),
+    op("allLists", 1, "(Set[a]) => Set[List[a]]", "`s.allLists()` is the set of all lists of elements of `s`."),
+    op("allListsUpTo", 2, "(Set[a], int) => Set[List[a]]", "`s.allListsUpTo(l)` is the set of all lists of elements of `s` with length up to `l`."),
+    op("getOnlyElement", 1, "(Set[a]) => a", "`s.getOnlyElement()` is the only element of `s`."),
+    op("chooseSome", 1, "(Set[a]) => a", "`s.chooseSome()` is, deterministically, one element of `s`."),
+    op("min", 1, "(Set[a]) => a", "`s.min()` is the least element of `s`."),
+    op("max", 1, "(Set[a]) => a", "`s.max()` is the greatest element of `s`."),
+    op("sum", 1, "(Set[int]) => int", "`s.sum()` is the sum of the integers in `s`."),
+    op("oneOf", 1, "(Set[a]) => a", "`s.oneOf()` is, non-deterministically, one element of `s`.").lazy(),
+    variadic("tuples", 1, "(Set[a], Set[b]*) => Set[(a, b)]", "`tuples(s1, s2)` is the set of tuples with elements of `s1`, `s2`, and so on."),
+    // Maps
+    variadic("Map", 0, "((a, b)*) => a -> b", "`Map(k -> v)` is the map with the given pairs of keys and values."),
+    op("get", 2, "(a -> b, a) => b", "`m.get(k)` is the value of the key `k` in `m`."),
+    op("keys", 1, "(a -> b) => Set[a]", "`m.keys()` is the set of keys of `m`."),
+    op("set", 3, "(a -> b, a, b) => a -> b", "`m.set(k, v)` is `m` with the existing key `k` mapped to `v`."),
+    op("setBy", 3, "(a -> b, a, (b) => b) => a -> b", "`m.setBy(k, f)` is `m` with the key `k` mapped to `f(m.get(k))`."),
+    op("put", 3, "(a -> b, a, b) => a -> b", "`m.put(k, v)` is `m` with the key `k` mapped to `v`, adding it if needed."),
+    op("mapBy", 2, "(Set[a], (a) => b) => a -> b", "`s.mapBy(f)` is the map from each element `x` of `s` to `f(x)`."),
+    op("setToMap", 1, "(Set[(a, b)]) => a -> b", "`s.setToMap()` is the map with the pairs of keys and values in `s`."),
+    op("setOfMaps", 2, "(Set[a], Set[b]) => Set[a -> b]", "`keys.setOfMaps(values)` is the set of all maps from `keys` to `values`."),
+    op("mapValues", 2, "(a -> b, (b) => c) => a -> c", "`m.mapValues(f)` is the map from each key `k` of `m` to `f(m.get(k))`."),
+    op("filterEntries", 2, "(a -> b, (a, b) => bool) => a -> b", "`m.filterEntries(p)` is `m` with only the keys `k` for which `p(k, m.get(k))` is true."),
+    op("mergeWith", 3, "(a -> b, a -> b, (b, b) => b) => a -> b", "`m1.mergeWith(m2, f)` has the keys of `m1` and `m2`, mapped to `f(m1.get(k), m2.get(k))` when in both."),
+    op("mapKeys", 2, "(a -> b, (a) => c) => c -> b", "`m.mapKeys(f)` is the map from `f(k)` to `m.get(k)` for each key `k` of `m`, when `f` gives different keys."),
+    // Lists
+    variadic("List", 0, "(a*) => List[a]", "`[a, b]` is the list of its arguments."),
+    op("range", 2, "(int, int) => List[int]", "`range(i, j)` is the list of integers from `i` to `j - 1`."),
+    op("nth", 2, "(List[a], int) => a", "`l.nth(i)` is the element of `l` at index `i`."),
+    op("head", 1, "(List[a]) => a", "`l.head()` is the first element of `l`."),
+    op("tail", 1, "(List[a]) => List[a]", "`l.tail()` is `l` without its first element."),
+    op("length", 1, "(List[a]) => int", "`l.length()` is the number of elements of `l`."),
+    op("indices", 1, "(List[a]) => Set[int]", "`l.indices()` is the set of indices of `l`."),
+    op("append", 2, "(List[a], a) => List[a]", "`l.append(e)` is `l` with `e` added at the end."),
+    op("concat", 2, "(List[a], List[a]) => List[a]", "`l1.concat(l2)` is `l1` followed by `l2`."),
+    op("replaceAt", 3, "(List[a], int, a) => List[a]", "`l.replaceAt(i, e)` is `l` with the element at index `i` replaced by `e`."),
+    op("slice", 3, "(List[a], int, int) => List[a]", "`l.slice(i, j)` is the list of elements of `l` from index `i` to `j - 1`."),
+    op("select", 2, "(List[a], (a) => bool) => List[a]", "`l.select(p)` is the list of elements of `l` that satisfy `p`."),
+    op("foldl", 3, "(List[a], b, (b, a) => b) => b", "`l.foldl(z, f)` reduces the elements of `l` with `f`, from the first one, starting with `z`."),
+    op("foldr", 3, "(List[a], b, (a, b) => b) => b", "`l.foldr(z, f)` reduces the elements of `l` with `f`, from the last one, starting with `z`."),
+    op("setToSortedList", 1, "(Set[a]) => List[a]", "`s.setToSortedList()` is the list of the elements of `s` in ascending order, the same on every run."),
+    op("zip", 2, "(List[a], List[b]) => List[(a, b)]", "`l1.zip(l2)` is the list of pairs of the elements of `l1` and `l2` at the same index, as long as the shorter one."),
+    op("enumerate", 1, "(List[a]) => List[(int, a)]", "`l.enumerate()` is the list of pairs of the indices and elements of `l`."),
+    op("sortBy", 2, "(List[a], (a) => b) => List[a]", "`l.sortBy(f)` is `l` sorted by the values of `f` on its elements, keeping the order of elements with equal values."),
+    op("takeWhile", 2, "(List[a], (a) => bool) => List[a]", "`l.takeWhile(p)` is the longest prefix of `l` whose elements satisfy `p`."),
+    op("dropWhile", 2, "(List[a], (a) => bool) => List[a]", "`l.dropWhile(p)` is `l` without the longest prefix whose elements satisfy `p`."),
+    op("chunks", 2, "(List[a], int) => List[List[a]]", "`l.chunks(n)` is `l` split into lists of `n` elements, the last one possibly shorter."),
+    // Tuples, records and sum types
+    variadic("Tup", 0, "(a, b, ...) => (a, b, ...)", "`(a, b)` is the tuple of its arguments."),
+    op("item", 2, "((a, b, ...), int) => a", "`t._1` is the first element of the tuple `t`, and so on."),
+    variadic("Rec", 0, "(str, a, ...) => { ... }", "`{ f: a }` is the record with the given names and values of fields."),
+    op("field", 2, "({ f: a, ... }, str) => a", "`r.f` is the value of the field `f` of the record `r`."),
+    op("fieldNames", 1, "({ ... }) => Set[str]", "`r.fieldNames()` is the set of the names of the fields of `r`."),
+    op("with", 3, "({ f: a, ... }, str, a) => { f: a, ... }", "`r.with(\"f\", v)` is `r` with the field `f` set to `v`."),
+    op("variant", 2, "(str, a) => L(a) | ...", "`L(v)` is the variant `L` of a sum type, with the value `v`."),
+    variadic("matchVariant", 1, "(L(a) | ..., str, (a) => b, ...) => b", "`match v { | L(x) => e }` is the result of the case for the variant of `v`.").lazy(),
+    // Control flow
+    op("ite", 3, "(bool, a, a) => a", "`if (c) t else e` is `t` when `c` is true, and `e` otherwise.").lazy(),
+    // Actions
+    op("assign", 2, "(a, a) => bool", "`x' = v` sets the state variable `x` to `v` in the next state.").lazy(),
+    op("next", 1, "(a) => a", "`x'` is the value of the state variable `x` in the next state.").lazy(),
+    variadic("actionAll", 0, "(bool*) => bool", "`all { A, B }` takes all of the actions, and is false if any of them is.").lazy(),
+    variadic("actionAny", 0, "(bool*) => bool", "`any { A, B }` takes one of the enabled actions, picked at random.").lazy(),
+    op("withProbability", 3, "(int, int, bool) => bool", "`withProbability(n, d, A)` takes `A` with probability `n/d`.").lazy(),
+    op("uniform", 2, "(int, int) => int", "`uniform(i, j)` is, at random, an integer from `i` to `j`, all of them equally likely."),
+    op("bernoulli", 2, "(int, int) => bool", "`bernoulli(n, d)` is, at random, true with probability `n/d`."),
+    op("geometric", 1, "(int) => int", "`geometric(m)` is, at random, a natural number following a geometric distribution with mean `m`."),
+    op("zipf", 2, "(int, int) => int", "`zipf(n, s)` is, at random, an integer `k` from 1 to `n`, with probability proportional to `1/k^s`."),
+    op("fresh", 0, "() => int", "`fresh()` is a new integer, distinct from all the others given by `fresh` in the same run."),
+    op("then", 2, "(bool, bool) => bool", "`A.then(B)` takes `A` and then `B`, in two steps.").lazy(),
+    op("reps", 2, "(int, (int) => bool) => bool", "`n.reps(i => A(i))` takes `A` `n` times.").lazy(),
+    op("expect", 2, "(bool, bool) => bool", "`A.expect(p)` takes `A`, failing the run if `p` doesn't hold after it.").lazy(),
+    op("fail", 1, "(bool) => bool", "`A.fail()` is true when the action `A` is false."),
+    op("assert", 1, "(bool) => bool", "`assert(p)` is true when `p` is true, failing the run otherwise."),
+    Builtin {
+        arity: Some(Arity {
+            min: 1,
+            max: Some(2),
+        }),
+        ..op("q::debug", 2, "(str, a) => a", "`q::debug(msg, v)` is `v`, printing `msg` and `v`.")
+    },
+    // Temporal operators, which are only checked over traces (see `crate::temporal`)
+    op("always", 1, "(bool) => bool", "`always(p)` is true when `p` holds on every state."),
+    op("eventually", 1, "(bool) => bool", "`eventually(p)` is true when `p` holds on some state."),
+    op("enabled", 1, "(bool) => bool", "`enabled(A)` is true when the action `A` can be taken."),
+    op("orKeep", 2, "(bool, a) => bool", "`orKeep(A, v)` is true when `A` is taken or `v` doesn't change."),
+    op("mustChange", 2, "(bool, a) => bool", "`mustChange(A, v)` is true when `A` is taken and `v` changes."),
+    op("weakFair", 2, "(bool, a) => bool", "`weakFair(A, v)` is true when `A` is taken whenever it is always enabled."),
+    op("strongFair", 2, "(bool, a) => bool", "`strongFair(A, v)` is true when `A` is taken whenever it is enabled infinitely often."),
+];
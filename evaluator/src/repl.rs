@@ -0,0 +1,400 @@
+//! A REPL (read-eval-print loop) evaluating Quint expressions and actions
+//! against a spec, keeping the state of its variables between inputs, so specs
+//! can be explored interactively (see the `repl` command).
+//!
+//! Quint can't be parsed here, so each input is compiled by the Quint
+//! typescript tool, in a module that imports the loaded spec and has the
+//! definitions entered so far (see [`Compile`]), and then evaluated natively.
+//! Inputs are compiled once, as actions, which the Quint tool accepts for any
+//! expression that isn't temporal, and stay compiled until the definitions
+//! change, so repeated inputs (i.e. `step`) are not compiled again. Actions
+//! that hold move to the state they produce, where variables not assigned by
+//! the action keep their values, and can be undone.
+//!
+//! All inputs are evaluated in the same environment, so random picks and
+//! fresh identifiers carry on from one input to the next, and undoing an
+//! action also restores the environment from before it. Each compilation has
+//! its own ids, so each input has its own variable storage, which the
+//! environment switches to when evaluating it.
+//!
+//! Inputs starting with a qualifier (e.g. `val` or `action`) are definitions,
+//! kept for the next inputs, and inputs starting with a dot are commands (see
+//! [`HELP`]). Sessions can be saved and restored as JSON (see [`Session`]).
+
+use crate::evaluator::{CompiledExpr, Env, EnvSnapshot, Interpreter};
+use crate::ir::QuintOutput;
+use crate::reflection::{builtin, builtins};
+use crate::storage::Storage;
+use crate::value::{ImmutableMap, Value};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::rc::Rc;
+
+pub const HELP: &str = "\
+Enter an expression to evaluate it, or an action to take it, i.e. `init`.
+Enter a definition (i.e. `val n = 3`) to use it in the next inputs.
+
+.load <file> [<module>]  load a module (by default, the one named after the file)
+.vars                    show the variables in the current state
+.undo                    go back to the state before the last action
+.clear                   forget the definitions and go back to the empty state
+.save <file>             save the session to a JSON file
+.restore <file>          restore a session saved with .save
+.doc [<builtin>]         show the type and documentation of a built-in, or list them
+.help                    show this help
+.exit                    exit the REPL";
+
+/// The name of the definition with the input, in the module compiled for it.
+const INPUT: &str = "__input";
+
+/// The qualifiers starting definitions.
+const QUALIFIERS: [&str; 7] = ["val", "def", "pure", "action", "temporal", "run", "type"];
+
+/// Compiles the text of a Quint module with a single module, written to a file
+/// in the temporary directory, as [`crate::helpers::parse`] does: the loaded
+/// spec is imported by its path relative to that directory.
+pub type Compile = Box<dyn FnMut(&str) -> Result<QuintOutput, String>>;
+
+/// A spec loaded in the REPL.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Spec {
+    pub path: PathBuf,
+    /// The name of the module to import from the file.
+    pub module: String,
+}
+
+/// A REPL session, to be saved and restored.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub spec: Option<Spec>,
+    pub definitions: Vec<String>,
+    /// The states reached, in ITF, starting from the empty state. The last one
+    /// is the current state.
+    pub states: Vec<Json>,
+}
+
+/// An input compiled by the Quint tool, with the storage of the variables it
+/// reads and assigns.
+#[derive(Clone)]
+struct CompiledInput {
+    expr: CompiledExpr,
+    var_storage: Rc<RefCell<Storage>>,
+}
+
+pub struct Repl {
+    compile: Compile,
+    spec: Option<Spec>,
+    definitions: Vec<String>,
+    /// The inputs compiled with the current spec and definitions, by their
+    /// text.
+    compiled: HashMap<String, CompiledInput>,
+    /// The environment all inputs are evaluated in.
+    env: Env,
+    /// The states reached, starting from the empty state, to undo actions.
+    /// The last one is the current state.
+    states: Vec<Value>,
+    /// The environments before each action taken, to restore them when
+    /// undoing it. Sessions restored with `.restore` don't have the ones of
+    /// the actions taken before saving them.
+    snapshots: Vec<EnvSnapshot>,
+    /// Whether to show values with colors (see [`Value::colored`]).
+    colors: bool,
+}
+
+impl Repl {
+    pub fn new(compile: Compile) -> Self {
+        Self {
+            compile,
+            spec: None,
+            definitions: Vec::new(),
+            compiled: HashMap::new(),
+            env: Env::new(Rc::default()),
+            states: vec![Value::Record(ImmutableMap::default())],
+            snapshots: Vec::new(),
+            colors: false,
+        }
+    }
+
+    /// Show values with colors, for terminals.
+    pub fn with_colors(mut self, colors: bool) -> Self {
+        self.colors = colors;
+        self
+    }
+
+    /// Handle an input, returning the text to print or an error.
+    pub fn handle(&mut self, input: &str) -> Result<String, String> {
+        let input = input.trim();
+        if let Some(command) = input.strip_prefix('.') {
+            let (command, arg) = command
+                .split_once(char::is_whitespace)
+                .map_or((command, ""), |(command, arg)| (command, arg.trim()));
+            return match command {
+                "load" => self.load(arg),
+                "vars" => Ok(self.vars()),
+                "undo" => self.undo(),
+                "clear" => {
+                    self.definitions.clear();
+                    self.reset();
+                    Ok(String::new())
+                }
+                "save" => self.save(Path::new(arg)),
+                "restore" => self.restore(Path::new(arg)),
+                "doc" => doc(arg),
+                "help" => Ok(HELP.to_string()),
+                _ => Err(format!("Unknown command .{command}, see .help")),
+            };
+        }
+
+        let first_word = input.split_whitespace().next().unwrap_or_default();
+        if input.is_empty() {
+            Ok(String::new())
+        } else if QUALIFIERS.contains(&first_word) {
+            self.define(input)
+        } else {
+            self.evaluate(input)
+        }
+    }
+
+    /// The current state.
+    pub fn state(&self) -> &Value {
+        self.states.last().expect("There should always be a state")
+    }
+
+    /// Load the module `<module>` from `<file>`, given as `<file> [<module>]`,
+    /// starting a new session.
+    fn load(&mut self, arg: &str) -> Result<String, String> {
+        let mut args = arg.split_whitespace();
+        let path = args.next().ok_or("Expected .load <file> [<module>]")?;
+        let path = std::path::absolute(path).map_err(|e| e.to_string())?;
+        if !path.exists() {
+            return Err(format!("File not found: {}", path.display()));
+        }
+        let module = match args.next() {
+            Some(module) => module.to_string(),
+            None => path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+
+        let previous = self.spec.replace(Spec { path, module });
+        // Check that the module can be imported
+        if let Err(e) = self.compile_input("val", "true", &[]) {
+            self.spec = previous;
+            return Err(e);
+        }
+        self.definitions.clear();
+        self.reset();
+        let spec = self.spec.as_ref().expect("The spec was just set");
+        Ok(format!(
+            "Loaded module {} from {}",
+            spec.module,
+            spec.path.display()
+        ))
+    }
+
+    fn define(&mut self, definition: &str) -> Result<String, String> {
+        let mut definitions = self.definitions.clone();
+        definitions.push(definition.to_string());
+        self.compile_input("val", "true", &definitions)?;
+        self.definitions = definitions;
+        // Inputs may refer to the new definition instead of a spec's one
+        self.compiled.clear();
+        Ok(String::new())
+    }
+
+    /// Go back to the empty state, in a new environment, forgetting the
+    /// compiled inputs.
+    fn reset(&mut self) {
+        self.compiled.clear();
+        self.env = Env::new(Rc::default());
+        self.states.truncate(1);
+        self.snapshots.clear();
+    }
+
+    fn evaluate(&mut self, input: &str) -> Result<String, String> {
+        let state = self.state().clone();
+        let snapshot = self.env.snapshot();
+        let (value, next) = self.execute(input, &state)?;
+        if let Some(next) = next {
+            self.states.push(next);
+            self.snapshots.push(snapshot);
+        }
+        Ok(self.show(&value))
+    }
+
+    /// Evaluate an expression (or action) in the given state, with the loaded
+    /// spec and definitions, without changing the current state.
+    pub fn evaluate_in(&mut self, input: &str, state: &Value) -> Result<Value, String> {
+        self.execute(input, state).map(|(value, _)| value)
+    }
+
+    /// Compile an input ahead of evaluating it with [`Repl::evaluate_in`], i.e.
+    /// to report its errors before evaluating it in many states.
+    pub fn prepare(&mut self, input: &str) -> Result<(), String> {
+        self.compiled_input(input).map(|_| ())
+    }
+
+    /// Execute the input in a state, returning its value and, for actions that
+    /// hold and assign variables, the state they move to.
+    fn execute(&mut self, input: &str, state: &Value) -> Result<(Value, Option<Value>), String> {
+        let compiled = self.compiled_input(input)?;
+        self.env.var_storage = compiled.var_storage;
+        // Variables are registered when compiling, so the state is loaded after
+        let empty = Value::Record(ImmutableMap::default());
+        self.env.var_storage.borrow_mut().load(state, &empty);
+
+        let value = compiled
+            .expr
+            .execute(&mut self.env)
+            .map_err(|e| e.to_string())?;
+        let assigned = self.env.var_storage.borrow().primed_as_record();
+        let assigned = assigned.as_record_map();
+        if matches!(value, Value::Bool(true)) && !assigned.is_empty() {
+            let mut next = state.as_record_map().clone();
+            next.extend(assigned.clone());
+            return Ok((value, Some(Value::Record(next))));
+        }
+        Ok((value, None))
+    }
+
+    /// The compiled input, compiling it if it wasn't yet with the current spec
+    /// and definitions.
+    fn compiled_input(&mut self, input: &str) -> Result<CompiledInput, String> {
+        if let Some(compiled) = self.compiled.get(input) {
+            return Ok(compiled.clone());
+        }
+
+        let output = self.compile_input("action", input, &self.definitions.clone())?;
+        let def = output
+            .find_definition_by_name(INPUT)
+            .map_err(|e| e.to_string())?;
+        let mut interpreter = Interpreter::new(&output.table);
+        let compiled = CompiledInput {
+            expr: interpreter.compile(&def.expr),
+            var_storage: interpreter.var_storage.clone(),
+        };
+        self.compiled.insert(input.to_string(), compiled.clone());
+        Ok(compiled)
+    }
+
+    fn show(&self, value: &Value) -> String {
+        if self.colors {
+            value.colored().to_string()
+        } else {
+            value.to_string()
+        }
+    }
+
+    fn vars(&self) -> String {
+        let mut vars = self
+            .state()
+            .as_record_map()
+            .iter()
+            .map(|(name, value)| format!("{name}: {}", self.show(value)))
+            .collect::<Vec<_>>();
+        vars.sort();
+        vars.join("\n")
+    }
+
+    fn undo(&mut self) -> Result<String, String> {
+        if self.states.len() == 1 {
+            return Err("No action to undo".to_string());
+        }
+        self.states.pop();
+        if let Some(snapshot) = self.snapshots.pop() {
+            self.env.restore(&snapshot);
+        }
+        Ok(self.vars())
+    }
+
+    fn save(&self, path: &Path) -> Result<String, String> {
+        let session = Session {
+            spec: self.spec.clone(),
+            definitions: self.definitions.clone(),
+            states: self.states.iter().map(Value::to_itf).collect(),
+        };
+        let json = serde_json::to_string_pretty(&session).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| e.to_string())?;
+        Ok(format!("Saved session to {}", path.display()))
+    }
+
+    fn restore(&mut self, path: &Path) -> Result<String, String> {
+        let json = fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let session: Session = serde_json::from_str(&json).map_err(|e| e.to_string())?;
+        let states = session
+            .states
+            .iter()
+            .map(Value::from_itf)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| e.to_string())?;
+        if states.is_empty() {
+            return Err("Invalid session: no states".to_string());
+        }
+
+        self.spec = session.spec;
+        self.definitions = session.definitions;
+        self.reset();
+        self.states = states;
+        Ok(format!("Restored session from {}", path.display()))
+    }
+
+    /// Compile the input as a definition with the given qualifier, after the
+    /// given definitions.
+    fn compile_input(
+        &mut self,
+        qualifier: &str,
+        input: &str,
+        definitions: &[String],
+    ) -> Result<QuintOutput, String> {
+        let mut module = String::from("module __repl {\n");
+        if let Some(spec) = &self.spec {
+            let path = relative_to(&spec.path.with_extension(""), &std::env::temp_dir());
+            module.push_str(&format!(
+                "  import {}.* from \"{}\"\n",
+                spec.module,
+                path.display()
+            ));
+        }
+        for definition in definitions {
+            module.push_str(&format!("  {definition}\n"));
+        }
+        module.push_str(&format!("  {qualifier} {INPUT} = {input}\n}}\n"));
+        (self.compile)(&module)
+    }
+}
+
+/// The path of `path` relative to the directory `base`, both absolute.
+fn relative_to(path: &Path, base: &Path) -> PathBuf {
+    let common = path
+        .components()
+        .zip(base.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    base.components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .chain(path.components().skip(common))
+        .collect()
+}
+
+/// The type and documentation of a built-in, or the names of all of them
+/// without a name.
+fn doc(name: &str) -> Result<String, String> {
+    if name.is_empty() {
+        return Ok(builtins()
+            .iter()
+            .map(|b| b.name)
+            .collect::<Vec<_>>()
+            .join(" "));
+    }
+    let builtin = builtin(name).ok_or_else(|| format!("Unknown built-in {name}, see .doc"))?;
+    Ok(format!(
+        "{}: {}\n{}",
+        builtin.name, builtin.signature, builtin.doc
+    ))
+}
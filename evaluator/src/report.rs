@@ -0,0 +1,130 @@
+//! The result of a CLI command as a single JSON document, written with
+//! `--out-format json` instead of the log, so CI pipelines can read results
+//! without scraping the output.
+//!
+//! A report has the outcome of the command, the seed it used, statistics
+//! (which depend on the command, e.g. the number of samples), the properties
+//! checked (invariants, or the runs of `test`), and the traces it produced.
+//! Properties refer to their traces by their index in `traces`. Traces are
+//! referenced by the files they were written to, and also included inline
+//! when the command is asked to.
+
+use serde::Serialize;
+use serde_json::{Map, Value as Json};
+
+/// The result document of a command.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Report {
+    /// The command, e.g. `run`.
+    pub command: String,
+    pub outcome: Outcome,
+    /// The master seed, as a hexadecimal number, to reproduce the result.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<String>,
+    pub statistics: Map<String, Json>,
+    pub properties: Vec<PropertyReport>,
+    pub traces: Vec<TraceReport>,
+    /// The errors that stopped the command, if the outcome is an error.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+    pub elapsed_ms: u128,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    /// Every property held, every test passed, or the trace was replayed.
+    Ok,
+    /// A property was violated, a test failed, or the trace was not replayed.
+    Failed,
+    /// The command couldn't finish, i.e. the spec didn't compile.
+    Error,
+}
+
+/// The result of checking a property, or executing a test.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PropertyReport {
+    pub name: String,
+    /// What the property is, e.g. `invariant` or `test`.
+    pub kind: String,
+    pub passed: bool,
+    /// The index of the trace showing the property, in [`Report::traces`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trace: Option<usize>,
+    /// Details that depend on the kind, e.g. the step that failed.
+    #[serde(flatten)]
+    pub details: Map<String, Json>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TraceReport {
+    /// The file the trace was written to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// The trace in ITF, if traces are included inline.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub itf: Option<Json>,
+}
+
+impl Report {
+    /// An empty report of a command, with the `ok` outcome.
+    pub fn new(command: &str) -> Report {
+        Report {
+            command: command.to_string(),
+            outcome: Outcome::Ok,
+            seed: None,
+            statistics: Map::new(),
+            properties: Vec::new(),
+            traces: Vec::new(),
+            errors: Vec::new(),
+            elapsed_ms: 0,
+        }
+    }
+
+    pub fn set_seed(&mut self, seed: u64) {
+        self.seed = Some(format!("{seed:#x}"));
+    }
+
+    pub fn set_statistic(&mut self, name: &str, value: impl Into<Json>) {
+        self.statistics.insert(name.to_string(), value.into());
+    }
+
+    /// Add a trace, written to `file` if given, and included inline if
+    /// `inline`. Returns its index, for [`PropertyReport::trace`].
+    pub fn add_trace(&mut self, file: Option<String>, itf: &Json, inline: bool) -> usize {
+        self.traces.push(TraceReport {
+            file,
+            itf: inline.then(|| itf.clone()),
+        });
+        self.traces.len() - 1
+    }
+
+    /// Add the result of a property, failing the report if it didn't pass.
+    pub fn add_property(
+        &mut self,
+        name: &str,
+        kind: &str,
+        passed: bool,
+        trace: Option<usize>,
+        details: Map<String, Json>,
+    ) {
+        if !passed && self.outcome == Outcome::Ok {
+            self.outcome = Outcome::Failed;
+        }
+        self.properties.push(PropertyReport {
+            name: name.to_string(),
+            kind: kind.to_string(),
+            passed,
+            trace,
+            details,
+        });
+    }
+
+    /// Record an error that stopped the command.
+    pub fn add_error(&mut self, error: impl ToString) {
+        self.outcome = Outcome::Error;
+        self.errors.push(error.to_string());
+    }
+}
@@ -0,0 +1,145 @@
+//! Specialized representations of values, picked from the types inferred for
+//! expressions in the IR (see [`crate::ir::TypeTable`]).
+//!
+//! Without types, the evaluator represents every set, map, tuple and record
+//! generically, as persistent collections of values. When the type of an
+//! expression says that its values fit a more compact representation, the
+//! interpreter converts the values it produces to that representation (see
+//! [`crate::evaluator::Interpreter::set_types`]), and operators working on
+//! values in that representation avoid the generic collections altogether.
+//!
+//! Only sets of enumerations are specialized for now: sets of a sum type
+//! whose variants carry no values (i.e. `type Color = Red | Green | Blue`),
+//! with at most [`MAX_ENUMERATION_SIZE`] variants, are represented as
+//! bitsets ([`Value::BitSet`]). Maps, tuples and records keep their generic
+//! representations.
+//!
+//! Representations are an optimization only: values in different
+//! representations are equal if they have the same elements, and are
+//! normalized to the generic ones for fingerprints and serialization.
+
+use crate::ir::{QuintId, QuintType, Row, TypeTable};
+use crate::symbol::Symbol;
+use crate::value::{ImmutableVec, Value};
+use std::rc::Rc;
+
+/// The most variants an enumeration can have to have its sets represented as
+/// bitsets.
+pub const MAX_ENUMERATION_SIZE: usize = u64::BITS as usize;
+
+/// A specialized representation for the values of a type.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Representation {
+    /// Sets of the variants of an enumeration, as bitsets over its labels,
+    /// which are sorted so bits are in the order of values.
+    EnumerationSet(Rc<[Symbol]>),
+}
+
+impl Representation {
+    /// The representation for values of the given type, or `None` if they
+    /// are represented generically.
+    pub fn for_type(typ: &QuintType) -> Option<Representation> {
+        match typ {
+            QuintType::Set { elem } => {
+                enumeration_labels(elem).map(|labels| Representation::EnumerationSet(labels.into()))
+            }
+            _ => None,
+        }
+    }
+
+    /// The representations for the expressions of a type table, by id, for
+    /// the expressions that have one.
+    pub fn for_types(types: &TypeTable) -> impl Iterator<Item = (QuintId, Representation)> + '_ {
+        types
+            .iter()
+            .filter_map(|(id, scheme)| Some((*id, Representation::for_type(&scheme.typ)?)))
+    }
+
+    /// Convert a value to this representation. Values that are already in it,
+    /// or that can't be converted (i.e. infinite sets), are kept as they are.
+    pub fn apply(&self, value: Value) -> Value {
+        match self {
+            Representation::EnumerationSet(labels) => match value {
+                Value::BitSet(ref value_labels, _) if value_labels == labels => value,
+                _ if !value.is_set() || value.is_infinite() => value,
+                _ => {
+                    let bits = value.iter_set().try_fold(0, |bits, elem| match elem {
+                        Value::Variant(label, _) => {
+                            let index = labels.binary_search(&label).ok()?;
+                            Some(bits | 1 << index)
+                        }
+                        _ => None,
+                    });
+                    match bits {
+                        Some(bits) => Value::BitSet(labels.clone(), bits),
+                        None => value,
+                    }
+                }
+            },
+        }
+    }
+}
+
+/// The sorted labels of an enumeration type, if it is one: a sum type whose
+/// variants carry no values, with at most [`MAX_ENUMERATION_SIZE`] of them.
+fn enumeration_labels(typ: &QuintType) -> Option<Vec<Symbol>> {
+    let QuintType::Sum { fields } = typ else {
+        return None;
+    };
+    let (fields, open) = fields.fields();
+    let is_unit = |typ: &QuintType| matches!(typ, QuintType::Tup { fields: Row::Empty });
+    if open || fields.len() > MAX_ENUMERATION_SIZE || !fields.iter().all(|f| is_unit(&f.field_type))
+    {
+        return None;
+    }
+
+    let mut labels = fields
+        .iter()
+        .map(|f| Symbol::from(&f.field_name))
+        .collect::<Vec<_>>();
+    labels.sort_unstable();
+    labels.dedup();
+    Some(labels)
+}
+
+impl Value {
+    /// The elements of a bitset, in order.
+    pub(crate) fn bitset_elements(
+        labels: &[Symbol],
+        bits: u64,
+    ) -> impl Iterator<Item = Value> + '_ {
+        labels
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| bits & (1 << index) != 0)
+            .map(|(_, label)| {
+                Value::Variant(
+                    label.clone(),
+                    Rc::new(Value::Tuple(ImmutableVec::default())),
+                )
+            })
+    }
+
+    /// Whether a bitset contains a variant.
+    pub(crate) fn bitset_contains(labels: &[Symbol], bits: u64, elem: &Value) -> bool {
+        match elem {
+            Value::Variant(label, _) => labels
+                .binary_search(label)
+                .is_ok_and(|index| bits & (1 << index) != 0),
+            _ => false,
+        }
+    }
+
+    /// Combine two bitsets over the same labels bit by bit, i.e. with `|` for
+    /// their union, or `None` if the values are not such bitsets.
+    pub fn combine_bitsets(&self, other: &Value, op: fn(u64, u64) -> u64) -> Option<Value> {
+        match (self, other) {
+            (Value::BitSet(labels, a), Value::BitSet(other_labels, b))
+                if labels == other_labels =>
+            {
+                Some(Value::BitSet(labels.clone(), op(*a, *b)))
+            }
+            _ => None,
+        }
+    }
+}
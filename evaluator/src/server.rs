@@ -0,0 +1,522 @@
+//! A long-running server exposing the evaluator over JSON-RPC 2.0, so the
+//! Quint typescript tool and editor tooling can drive it as a persistent
+//! backend, instead of starting a process for each query.
+//!
+//! Messages are JSON objects, one per line, read from stdin or from a socket
+//! (see [`Server::serve`]), or MessagePack objects (see [`crate::msgpack`]),
+//! once negotiated for the connection with the `codec` method. Specs are
+//! compiled once and referred to by a handle in the following requests. The
+//! methods are:
+//!  - `codec`: switch the encoding of the following messages on the
+//!    connection (`{"codec": "json" | "msgpack"}`), after the response;
+//!  - `compile`: load a spec (`{"parsed": ParsedQuint}`), returning its handle
+//!    as `{"spec": n}`, or the QNT517 errors of malformed specs as the `data`
+//!    of the error (see [`crate::validator`]);
+//!  - `evaluate`: evaluate an expression in a spec (`{"spec", "expr"}`), with
+//!    the definitions it refers to added to the spec's table (`"table"`, if
+//!    not already there), returning its value in ITF;
+//!  - `simulate`: simulate a spec (`{"spec", "source", "nruns", "nsteps",
+//!    "ntraces", "seed"}`), returning the same [`Outcome`] as the
+//!    `simulate-from-stdin` command;
+//!  - `replay`: replay an ITF trace against a spec (`{"spec", "trace",
+//!    "attempts"}`), returning `{"status": "ok" | "divergence" | "violation"}`
+//!    and the index of the offending `"state"`;
+//!  - `inspect`: evaluate an expression like `evaluate`, in a state given as
+//!    an ITF record of the variables (`"state"`, if any), within a time budget
+//!    (`"timeout"`, in milliseconds) and a bound on the size of collections
+//!    (`"maxElements"`), for editors showing live values. The result is
+//!    `{"status": "ok", "value", "text"}` with the value in ITF and in Quint
+//!    syntax, or `{"status": "error", "error"}` with the [`QuintError`] (e.g.
+//!    QNT515 when the budget is exceeded), and the `"elapsed"` milliseconds;
+//!  - `release`: drop a spec that is no longer needed (`{"spec"}`).
+
+use crate::evaluator::{Budget, Env, Interpreter};
+use crate::ir::{LookupTable, QuintError, QuintEx};
+use crate::metrics::Metrics;
+use crate::msgpack;
+use crate::simulator::{ParsedQuint, ReplayOutcome, SimulationResult, SimulatorConfig};
+use crate::trace::Trace;
+use crate::value::{ImmutableMap, Value};
+use fxhash::FxHashMap;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{json, Value as Json};
+use std::io::{self, BufRead, Write};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+// Error codes defined by JSON-RPC
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+/// The error code for evaluation errors, with the [`QuintError`] as data.
+pub const EVALUATION_ERROR: i64 = -32000;
+
+#[derive(Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SimulationStatus {
+    #[serde(rename = "ok")]
+    Success,
+    Violation,
+    Error,
+}
+
+/// The outcome of a simulation, as expected by the Quint typescript tool
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Outcome {
+    status: SimulationStatus,
+    errors: Vec<QuintError>,
+    best_traces: Vec<SimulationTrace>,
+    witnessing_traces: Vec<usize>,
+    samples: usize,
+    invariants: Vec<InvariantOutcome>,
+}
+
+/// The outcome for one of the invariants reported individually
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvariantOutcome {
+    name: String,
+    violation: Option<InvariantViolationOutcome>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InvariantViolationOutcome {
+    sample: usize,
+    seed: u64,
+    step: usize,
+    states: serde_json::Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimulationTrace {
+    seed: u64,
+    states: serde_json::Value,
+    result: bool,
+}
+
+/// Converts the result of a simulation into an `Outcome` struct.
+///
+/// The status is determined based on whether the simulation result indicates success, violation, or error.
+/// Errors are collected into a vector if any are present.
+/// Best traces are converted to the intermediate trace format (ITF).
+pub fn to_outcome(source: String, result: Result<SimulationResult, QuintError>) -> Outcome {
+    let status = match &result {
+        Ok(r) if r.result => SimulationStatus::Success,
+        Ok(_) => SimulationStatus::Violation,
+        Err(_) => SimulationStatus::Error,
+    };
+
+    let errors = result
+        .as_ref()
+        .err()
+        .map_or_else(Vec::new, |e| vec![e.clone()]);
+
+    let best_traces = result.as_ref().ok().map_or_else(Vec::new, |r| {
+        r.best_traces
+            .iter()
+            .map(|t| SimulationTrace {
+                // TODO: Report the seed of the sample that produced the trace
+                seed: r.seed,
+                states: t.clone().to_itf(source.clone()),
+                result: !t.violation,
+            })
+            .collect()
+    });
+
+    let invariants = result.as_ref().ok().map_or_else(Vec::new, |r| {
+        r.invariants
+            .iter()
+            .map(|i| InvariantOutcome {
+                name: i.name.clone(),
+                violation: i.violation.as_ref().map(|v| InvariantViolationOutcome {
+                    sample: v.sample,
+                    seed: v.seed,
+                    step: v.step,
+                    states: v.trace.clone().to_itf(source.clone()),
+                }),
+            })
+            .collect()
+    });
+
+    Outcome {
+        status,
+        errors,
+        best_traces,
+        witnessing_traces: result.as_ref().map_or_else(
+            |_| vec![],
+            |r| r.witnesses.iter().map(|w| w.samples).collect(),
+        ),
+        samples: result.as_ref().map_or(0, |r| r.samples),
+        invariants,
+    }
+}
+
+/// The outcome of a simulation that couldn't start, i.e. because the spec is
+/// malformed (see [`crate::validator`]).
+pub fn error_outcome(errors: Vec<QuintError>) -> Outcome {
+    Outcome {
+        status: SimulationStatus::Error,
+        errors,
+        best_traces: Vec::new(),
+        witnessing_traces: Vec::new(),
+        samples: 0,
+        invariants: Vec::new(),
+    }
+}
+
+/// An error response, see the JSON-RPC specification.
+#[derive(Debug, Serialize)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Json>,
+}
+
+impl RpcError {
+    fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+}
+
+impl From<QuintError> for RpcError {
+    fn from(error: QuintError) -> Self {
+        Self {
+            code: EVALUATION_ERROR,
+            message: error.to_string(),
+            data: serde_json::to_value(error).ok(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct CompileParams {
+    parsed: ParsedQuint,
+}
+
+#[derive(Deserialize)]
+struct SpecParams {
+    spec: usize,
+}
+
+#[derive(Deserialize)]
+struct EvaluateParams {
+    spec: usize,
+    expr: QuintEx,
+    #[serde(default)]
+    table: LookupTable,
+}
+
+/// The options of a simulation, as given to the `simulate` method and to the
+/// bindings for other languages.
+#[derive(Deserialize)]
+pub struct SimulateOptions {
+    /// The name of the spec file, for the ITF traces
+    #[serde(default)]
+    pub source: String,
+    pub nruns: usize,
+    pub nsteps: usize,
+    #[serde(default = "one")]
+    pub ntraces: usize,
+    #[serde(default)]
+    pub seed: Option<u64>,
+}
+
+impl SimulateOptions {
+    pub fn config(&self) -> SimulatorConfig {
+        SimulatorConfig {
+            steps: self.nsteps,
+            samples: self.nruns,
+            n_traces: self.ntraces,
+            seed: self.seed,
+            ..SimulatorConfig::default()
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InspectParams {
+    spec: usize,
+    expr: QuintEx,
+    #[serde(default)]
+    table: LookupTable,
+    #[serde(default)]
+    state: Option<Json>,
+    #[serde(default = "default_timeout")]
+    timeout: u64,
+    #[serde(default = "default_max_elements")]
+    max_elements: usize,
+}
+
+#[derive(Deserialize)]
+struct SimulateParams {
+    spec: usize,
+    #[serde(flatten)]
+    options: SimulateOptions,
+}
+
+#[derive(Deserialize)]
+struct ReplayParams {
+    spec: usize,
+    trace: Json,
+    #[serde(default = "default_attempts")]
+    attempts: usize,
+}
+
+fn one() -> usize {
+    1
+}
+
+fn default_attempts() -> usize {
+    1000
+}
+
+fn default_timeout() -> u64 {
+    1000
+}
+
+fn default_max_elements() -> usize {
+    100_000
+}
+
+/// The encoding of the messages on a connection.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Codec {
+    #[default]
+    Json,
+    #[serde(rename = "msgpack")]
+    MessagePack,
+}
+
+#[derive(Deserialize)]
+struct CodecParams {
+    codec: Codec,
+}
+
+/// The state of the server: the specs compiled so far, by their handles.
+#[derive(Default)]
+pub struct Server {
+    specs: FxHashMap<usize, ParsedQuint>,
+    next_spec: usize,
+    metrics: Option<Arc<Metrics>>,
+    /// The encoding of the messages on the current connection
+    codec: Codec,
+}
+
+impl Server {
+    /// Record the metrics of the simulations in `metrics`, e.g. to serve
+    /// them to Prometheus (see [`crate::metrics`]).
+    pub fn with_metrics(mut self, metrics: Arc<Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Serve the requests read from `input`, writing the responses to
+    /// `output`, until the input ends. Connections start with JSON messages,
+    /// one per line, until another codec is negotiated.
+    pub fn serve(&mut self, mut input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+        self.codec = Codec::Json;
+        loop {
+            // The response to a `codec` request is still in the previous codec
+            let codec = self.codec;
+            let response = match codec {
+                Codec::Json => {
+                    let mut line = String::new();
+                    if input.read_line(&mut line)? == 0 {
+                        break;
+                    }
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+                    self.handle(&line)
+                }
+                Codec::MessagePack => {
+                    if input.fill_buf()?.is_empty() {
+                        break;
+                    }
+                    // Invalid messages can't be skipped, so they end the
+                    // connection
+                    let request = msgpack::read(&mut input)?;
+                    self.handle_request(request)
+                }
+            };
+            if let Some(response) = response {
+                match codec {
+                    Codec::Json => writeln!(output, "{response}")?,
+                    Codec::MessagePack => msgpack::write(&mut output, &response)?,
+                }
+                output.flush()?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Handle a request, returning the response, or nothing for
+    /// notifications (i.e. requests without an id).
+    pub fn handle(&mut self, message: &str) -> Option<Json> {
+        match serde_json::from_str(message) {
+            Ok(request) => self.handle_request(request),
+            Err(e) => Some(response(
+                Json::Null,
+                Err(RpcError::new(PARSE_ERROR, e.to_string())),
+            )),
+        }
+    }
+
+    fn handle_request(&mut self, request: Json) -> Option<Json> {
+        let id = request.get("id").cloned();
+        let result = match request["method"].as_str() {
+            Some(method) => {
+                self.dispatch(method, request.get("params").cloned().unwrap_or(Json::Null))
+            }
+            None => Err(RpcError::new(INVALID_REQUEST, "expected a `method`")),
+        };
+        id.map(|id| response(id, result))
+    }
+
+    fn dispatch(&mut self, method: &str, params: Json) -> Result<Json, RpcError> {
+        match method {
+            "codec" => {
+                let params: CodecParams = parse_params(params)?;
+                self.codec = params.codec;
+                Ok(json!({ "codec": params.codec }))
+            }
+            "compile" => {
+                let params: CompileParams = parse_params(params)?;
+                if let Err(errors) = params.parsed.validate() {
+                    return Err(RpcError {
+                        code: INVALID_PARAMS,
+                        message: errors[0].to_string(),
+                        data: serde_json::to_value(errors).ok(),
+                    });
+                }
+                let spec = self.next_spec;
+                self.next_spec += 1;
+                self.specs.insert(spec, params.parsed);
+                Ok(json!({ "spec": spec }))
+            }
+            "evaluate" => {
+                let params: EvaluateParams = parse_params(params)?;
+                let parsed = self.spec_mut(params.spec)?;
+                for (id, def) in params.table {
+                    parsed.table.entry(id).or_insert(def);
+                }
+                let mut interpreter = Interpreter::new(&parsed.table);
+                let mut env = Env::new(interpreter.var_storage.clone());
+                let value = interpreter.compile(&params.expr).execute(&mut env)?;
+                Ok(value.to_itf())
+            }
+            "inspect" => {
+                let params: InspectParams = parse_params(params)?;
+                let state = params
+                    .state
+                    .as_ref()
+                    .map(Value::from_itf)
+                    .transpose()
+                    .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+                let parsed = self.spec_mut(params.spec)?;
+                for (id, def) in params.table {
+                    parsed.table.entry(id).or_insert(def);
+                }
+
+                let start = Instant::now();
+                let mut interpreter = Interpreter::new(&parsed.table);
+                let mut env = Env::new(interpreter.var_storage.clone());
+                let expr = interpreter.compile(&params.expr);
+                // Variables are registered when compiling, so the state is
+                // loaded after
+                if let Some(state) = state {
+                    let empty = Value::Record(ImmutableMap::default());
+                    env.var_storage.borrow_mut().load(&state, &empty);
+                }
+                env.budget = Some(Budget {
+                    deadline: start + Duration::from_millis(params.timeout),
+                    max_elements: params.max_elements,
+                });
+                let result = expr.execute(&mut env);
+                let elapsed = start.elapsed().as_millis() as u64;
+                Ok(match result {
+                    Ok(value) => json!({
+                        "status": "ok",
+                        "value": value.to_itf(),
+                        "text": value.to_string(),
+                        "elapsed": elapsed,
+                    }),
+                    Err(error) => json!({ "status": "error", "error": error, "elapsed": elapsed }),
+                })
+            }
+            "simulate" => {
+                let SimulateParams { spec, options } = parse_params(params)?;
+                let parsed = self.spec(spec)?;
+                let result = match &self.metrics {
+                    Some(metrics) => parsed.simulate_with_hooks(
+                        &options.config(),
+                        None,
+                        &mut metrics.simulation(),
+                    ),
+                    None => parsed.simulate_with_config(&options.config(), None),
+                };
+                Ok(json!(to_outcome(options.source, result)))
+            }
+            "replay" => {
+                let params: ReplayParams = parse_params(params)?;
+                let trace = Trace::from_itf(&params.trace)
+                    .map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))?;
+                Ok(
+                    match self.spec(params.spec)?.replay(&trace, params.attempts)? {
+                        ReplayOutcome::Ok => json!({ "status": "ok" }),
+                        ReplayOutcome::Divergence { state } => {
+                            json!({ "status": "divergence", "state": state })
+                        }
+                        ReplayOutcome::Violation { state } => {
+                            json!({ "status": "violation", "state": state })
+                        }
+                    },
+                )
+            }
+            "release" => {
+                let params: SpecParams = parse_params(params)?;
+                self.spec(params.spec)?;
+                self.specs.remove(&params.spec);
+                Ok(Json::Null)
+            }
+            _ => Err(RpcError::new(
+                METHOD_NOT_FOUND,
+                format!("unknown method `{method}`"),
+            )),
+        }
+    }
+
+    fn spec(&self, spec: usize) -> Result<&ParsedQuint, RpcError> {
+        self.specs
+            .get(&spec)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("unknown spec {spec}")))
+    }
+
+    fn spec_mut(&mut self, spec: usize) -> Result<&mut ParsedQuint, RpcError> {
+        self.specs
+            .get_mut(&spec)
+            .ok_or_else(|| RpcError::new(INVALID_PARAMS, format!("unknown spec {spec}")))
+    }
+}
+
+fn parse_params<T: DeserializeOwned>(params: Json) -> Result<T, RpcError> {
+    serde_json::from_value(params).map_err(|e| RpcError::new(INVALID_PARAMS, e.to_string()))
+}
+
+fn response(id: Json, result: Result<Json, RpcError>) -> Json {
+    match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+        Err(error) => json!({ "jsonrpc": "2.0", "id": id, "error": error }),
+    }
+}
@@ -0,0 +1,134 @@
+//! Estimates of how much of a state is shared with the previous state, to
+//! find where copy-on-write breaks down (see [`crate::counters`]).
+//!
+//! Values are compared with the values of the previous state: collections that
+//! are the same as before (i.e. the same pointer) are shared as a whole, while
+//! collections that changed are copied at the top and compared element by
+//! element, by key for records and maps, by index for lists and tuples, and by
+//! membership for sets. Scalars are always copied.
+//!
+//! Sizes are estimates: each value counts as the size of [`Value`], plus the
+//! bytes of strings and the sizes of the values it holds. The nodes of the
+//! persistent collections are not counted.
+
+use crate::value::Value;
+use std::mem::size_of;
+use std::rc::Rc;
+
+/// Bytes of a value shared with a previous value, and bytes copied.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Sharing {
+    pub shared: u64,
+    pub copied: u64,
+}
+
+impl Sharing {
+    fn shared(bytes: u64) -> Self {
+        Sharing {
+            shared: bytes,
+            copied: 0,
+        }
+    }
+
+    fn copied(bytes: u64) -> Self {
+        Sharing {
+            shared: 0,
+            copied: bytes,
+        }
+    }
+}
+
+impl std::ops::Add for Sharing {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Sharing {
+            shared: self.shared + other.shared,
+            copied: self.copied + other.copied,
+        }
+    }
+}
+
+impl std::iter::Sum for Sharing {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.fold(Sharing::default(), |a, b| a + b)
+    }
+}
+
+/// The estimated size of a value, in bytes.
+pub fn footprint(value: &Value) -> u64 {
+    let held: u64 = match value {
+        Value::Str(s) => s.len() as u64,
+        Value::Set(set) => set.iter().map(footprint).sum(),
+        Value::Tuple(elems) | Value::List(elems) => elems.iter().map(footprint).sum(),
+        Value::Record(fields) => fields.values().map(footprint).sum(),
+        Value::Map(map) => map.iter().map(|(k, v)| footprint(k) + footprint(v)).sum(),
+        Value::Variant(_, value) | Value::PowerSet(value, _) => footprint(value),
+        Value::CrossProduct(sets, _) => sets.iter().map(footprint).sum(),
+        Value::MapSet(domain, range, _) => footprint(domain) + footprint(range),
+        _ => 0,
+    };
+    size_of::<Value>() as u64 + held
+}
+
+/// How much of a value is shared with its value in the previous state, if it
+/// had one.
+pub fn measure(value: &Value, previous: Option<&Value>) -> Sharing {
+    let Some(previous) = previous else {
+        return Sharing::copied(footprint(value));
+    };
+    let top = Sharing::copied(size_of::<Value>() as u64);
+
+    match (value, previous) {
+        (Value::Str(a), Value::Str(b)) if !a.is_empty() && a.as_ptr() == b.as_ptr() => {
+            top + Sharing::shared(a.len() as u64)
+        }
+        (Value::Set(a), Value::Set(b)) if a.ptr_eq(b) => Sharing::shared(footprint(value)),
+        (Value::Set(a), Value::Set(b)) => {
+            top + a
+                .iter()
+                .map(|elem| {
+                    if b.contains(elem) {
+                        Sharing::shared(footprint(elem))
+                    } else {
+                        Sharing::copied(footprint(elem))
+                    }
+                })
+                .sum::<Sharing>()
+        }
+        (Value::Tuple(a), Value::Tuple(b)) | (Value::List(a), Value::List(b)) if a.ptr_eq(b) => {
+            Sharing::shared(footprint(value))
+        }
+        (Value::Tuple(a), Value::Tuple(b)) | (Value::List(a), Value::List(b)) => {
+            top + a
+                .iter()
+                .enumerate()
+                .map(|(i, elem)| measure(elem, b.get(i)))
+                .sum::<Sharing>()
+        }
+        (Value::Record(a), Value::Record(b)) if a.ptr_eq(b) => Sharing::shared(footprint(value)),
+        (Value::Record(a), Value::Record(b)) => {
+            top + a
+                .iter()
+                .map(|(name, value)| measure(value, b.get(name)))
+                .sum::<Sharing>()
+        }
+        (Value::Map(a), Value::Map(b)) if a.ptr_eq(b) => Sharing::shared(footprint(value)),
+        (Value::Map(a), Value::Map(b)) => {
+            top + a
+                .iter()
+                .map(|(key, value)| match b.get(key) {
+                    Some(previous) => {
+                        Sharing::shared(footprint(key)) + measure(value, Some(previous))
+                    }
+                    None => Sharing::copied(footprint(key) + footprint(value)),
+                })
+                .sum::<Sharing>()
+        }
+        (Value::Variant(_, a), Value::Variant(_, b)) if Rc::ptr_eq(a, b) => {
+            top + Sharing::shared(footprint(a))
+        }
+        (Value::Variant(_, a), Value::Variant(_, b)) => top + measure(a, Some(&**b)),
+        _ => Sharing::copied(footprint(value)),
+    }
+}
@@ -1,11 +1,30 @@
 //! Simulation for Quint models.
 
 use crate::{
-    evaluator::{Env, Interpreter},
-    ir::{LookupTable, QuintError, QuintEx},
-    itf::Trace,
+    counters::{self, Counters},
+    enumeration,
+    evaluator::{CompiledExpr, Env, Interpreter},
+    fairness::Fairness,
+    ir::{ErrorKind, LookupTable, QuintError, QuintEx, TypeTable},
+    output::Output,
+    picker::{Choices, PickMode},
+    rand,
+    spans::span,
+    temporal::{TemporalChecker, TemporalResult},
+    trace::{DeltaTrace, State, Trace},
+    value::{ImmutableMap, Value},
 };
+use fxhash::{FxHashMap, FxHashSet};
 use serde::{Deserialize, Serialize};
+use serde_json::Value as Json;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use web_time::Instant;
 
 /// Simulation input that depends on the typescript Quint tool.
 #[derive(Serialize, Deserialize)]
@@ -13,24 +32,387 @@ pub struct ParsedQuint {
     pub init: QuintEx,
     pub step: QuintEx,
     pub invariant: QuintEx,
+    /// Invariants to be checked and reported on individually. If empty, only
+    /// `invariant` is checked. Otherwise, these are checked instead of it.
+    #[serde(default)]
+    pub invariants: Vec<NamedProperty>,
+    /// Temporal properties to be checked over the traces, see
+    /// [`ParsedQuint::check_temporal`].
+    #[serde(default)]
+    pub temporal: Vec<NamedProperty>,
+    /// Predicates on states to be counted, to confirm that simulations reach
+    /// interesting states, see [`SimulationResult::witnesses`].
+    #[serde(default)]
+    pub witnesses: Vec<QuintEx>,
+    /// An expression to label the states with in state graphs, see
+    /// [`crate::explorer::StateGraph`].
+    #[serde(default)]
+    pub state_label: Option<QuintEx>,
+    /// The actions of composed components, e.g. instances of a node module
+    /// and a network module, to be interleaved as the step instead of `step`
+    /// (see [`Interpreter::compile_interleaving`]).
+    #[serde(default)]
+    pub components: Vec<NamedProperty>,
     pub table: LookupTable,
+    /// The types inferred for expressions, to represent values of some types
+    /// more compactly (see [`crate::representation`]). Optional, as values
+    /// of all types have a generic representation.
+    #[serde(default)]
+    pub types: TypeTable,
+}
+
+/// An invariant, temporal property or run with the name of its definition,
+/// for reporting.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct NamedProperty {
+    pub name: String,
+    pub expr: QuintEx,
 }
 
 /// Simulation output.
 pub struct SimulationResult {
     pub result: bool,
+    /// The traces of the greatest quality, from the best one: the shortest
+    /// violations first, and then the traces with the greatest score (see
+    /// [`SimulatorConfig::trace_quality`]).
     pub best_traces: Vec<Trace>,
-    // TODO
-    // witnessing_traces
-    // samples
+    /// The scores of the best traces, in the same order. The score of a
+    /// violation is its length.
+    pub best_trace_scores: Vec<usize>,
+    pub samples: usize,
+    /// The master seed of the simulation, from which the seed of each sample
+    /// is derived (see [`SimulatorConfig::seed`]).
+    pub seed: u64,
+    /// The outcome for each of the [`ParsedQuint::invariants`], in the same order.
+    pub invariants: Vec<InvariantResult>,
+    /// Whether the simulation stopped early because it ran out of
+    /// [`SimulatorConfig::time_budget`].
+    pub timed_out: bool,
+    /// The [`SimulatorConfig::action_weights`] that were applied to some
+    /// `any` expression, to reproduce the simulation.
+    pub action_weights: Vec<(String, u32)>,
+    /// With [`SimulatorConfig::estimate`], the estimated probability of
+    /// violating `invariant`, or each of the [`ParsedQuint::invariants`].
+    pub estimates: Vec<ProbabilityEstimate>,
+    /// How often each of the [`ParsedQuint::witnesses`] held, in the same order.
+    pub witnesses: Vec<WitnessCount>,
+    /// With [`SimulatorConfig::collect_violations`], the distinct violations
+    /// found, in the order they were first found.
+    pub distinct_violations: Vec<DistinctViolation>,
+    /// Fingerprints of the distinct initial states of the samples, for the
+    /// initial state coverage.
+    pub initial_states: FxHashSet<u64>,
+    /// With [`InitStrategy::Enumerate`], the number of initial states that
+    /// were enumerated.
+    pub enumerated_initial_states: Option<usize>,
 }
 
+/// A violation of an invariant in a distinct state, found with
+/// [`SimulatorConfig::collect_violations`]. Different states violating the
+/// same invariant are taken as different failure modes.
+pub struct DistinctViolation<T = Trace> {
+    /// The index of the violated invariant in [`ParsedQuint::invariants`] (or
+    /// 0 for [`ParsedQuint::invariant`]).
+    pub invariant: usize,
+    /// The fingerprint of the violating state.
+    pub fingerprint: u64,
+    /// The number of times the state violated the invariant.
+    pub count: usize,
+    /// The shortest trace found to the violating state.
+    pub trace: T,
+}
+
+/// How often a witness held during a simulation.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WitnessCount {
+    /// The number of samples with at least one state satisfying the witness.
+    pub samples: usize,
+    /// The number of states satisfying the witness, over all samples.
+    pub states: usize,
+}
+
+/// Outcome of checking one of the [`ParsedQuint::invariants`].
+pub struct InvariantResult<T = Trace> {
+    pub name: String,
+    /// Where the invariant was first violated, if it was.
+    pub violation: Option<InvariantViolation<T>>,
+}
+
+/// The first violation of an invariant.
+pub struct InvariantViolation<T = Trace> {
+    /// The sample where the violation was found, starting from 1.
+    pub sample: usize,
+    /// The seed of that sample, i.e. the state of the random number generator
+    /// at its start.
+    pub seed: u64,
+    /// The index of the violating state in the trace, where 0 is the initial state.
+    pub step: usize,
+    /// The trace leading to the violation.
+    pub trace: T,
+}
+
+/// Output of a parallel simulation, see [`ParsedQuint::simulate_parallel`].
+pub struct ParallelSimulationResult {
+    pub result: bool,
+    pub best_traces: Vec<ItfTrace>,
+    pub samples: usize,
+    /// The master seed of the simulation, see [`SimulationResult::seed`].
+    pub seed: u64,
+    /// The outcome for each of the [`ParsedQuint::invariants`]. Samples are
+    /// numbered as if the workers ran theirs one after the other.
+    pub invariants: Vec<InvariantResult<ItfTrace>>,
+    /// Whether any of the workers ran out of [`SimulatorConfig::time_budget`].
+    pub timed_out: bool,
+    /// The [`SimulatorConfig::action_weights`] that were applied.
+    pub action_weights: Vec<(String, u32)>,
+    /// The estimates, over the samples of all workers.
+    pub estimates: Vec<ProbabilityEstimate>,
+    /// The witness counts, summed over all workers.
+    pub witnesses: Vec<WitnessCount>,
+    /// The distinct violations found by all workers.
+    pub distinct_violations: Vec<DistinctViolation<ItfTrace>>,
+    /// Fingerprints of the distinct initial states of all workers.
+    pub initial_states: FxHashSet<u64>,
+    /// With [`InitStrategy::Enumerate`], the number of initial states that
+    /// were enumerated (by each worker).
+    pub enumerated_initial_states: Option<usize>,
+    /// The evaluation counters, summed over all workers.
+    pub counters: Counters,
+}
+
+/// A Monte Carlo estimate of the probability of an event (i.e. violating an
+/// invariant) in a sample.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProbabilityEstimate {
+    /// The number of samples where the event happened.
+    pub hits: usize,
+    /// The number of samples run.
+    pub samples: usize,
+}
+
+impl ProbabilityEstimate {
+    /// The estimated probability, i.e. the fraction of samples with the event.
+    pub fn probability(&self) -> f64 {
+        if self.samples == 0 {
+            return 0.0;
+        }
+        self.hits as f64 / self.samples as f64
+    }
+
+    /// The half-width of the 95% confidence interval of the estimate, using
+    /// the normal approximation to the binomial distribution.
+    pub fn margin(&self) -> f64 {
+        if self.samples == 0 {
+            return 1.0;
+        }
+        let p = self.probability();
+        1.96 * (p * (1.0 - p) / self.samples as f64).sqrt()
+    }
+}
+
+/// A trace converted to ITF. Unlike [`Trace`], this can be sent across
+/// threads, as it doesn't hold any [`crate::value::Value`].
+pub struct ItfTrace {
+    pub trace: serde_json::Value,
+    pub violation: bool,
+    /// The number of states in the trace.
+    pub len: usize,
+    /// The score of the trace, for the best traces (see
+    /// [`SimulationResult::best_trace_scores`]), or its length otherwise.
+    pub score: usize,
+}
+
+/// Outcome of replaying a trace, see [`ParsedQuint::replay`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReplayOutcome {
+    /// Every state follows from the previous one and satisfies the invariant.
+    Ok,
+    /// The state at this index doesn't follow from the previous one (or, for
+    /// the first state, from `init`).
+    Divergence { state: usize },
+    /// The state at this index violates the invariant.
+    Violation { state: usize },
+}
+
+/// Whether the evaluator agrees with a trace found by another tool, such as an
+/// Apalache or TLC counterexample, see [`ParsedQuint::cross_check`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Agreement {
+    /// Every state follows from the previous one, and the invariant is
+    /// violated on the last state if and only if a violation was expected.
+    Agrees,
+    /// The state at this index doesn't follow from the previous one (or, for
+    /// the first state, from `init`).
+    Divergence { state: usize },
+    /// The state at this index violates the invariant, although it is not the
+    /// last state of a counterexample.
+    UnexpectedViolation { state: usize },
+    /// The last state of the counterexample satisfies the invariant.
+    MissingViolation,
+}
+
+/// Parameters of a simulation campaign, see [`ParsedQuint::simulate_with_config`].
+#[derive(Debug, Clone)]
+pub struct SimulatorConfig {
+    /// The maximum number of steps in each sample.
+    pub steps: usize,
+    /// The number of samples (runs) to attempt.
+    pub samples: usize,
+    /// How many of the best traces to keep.
+    pub n_traces: usize,
+    /// How the samples are run.
+    pub strategy: Strategy,
+    /// How long to keep simulating, if limited. Once it is over, the sample
+    /// being run is finished and no more samples are started, so the
+    /// simulation can take slightly longer.
+    pub time_budget: Option<Duration>,
+    /// Weights for picking the actions of `any { ... }` expressions, by the
+    /// name of the action definitions, so rare actions can be taken more
+    /// often. Actions without a weight have weight 1, and actions with weight
+    /// 0 are never taken.
+    pub action_weights: Vec<(String, u32)>,
+    /// Estimate the probability of violating each invariant in a sample (see
+    /// [`ProbabilityEstimate`]). Instead of stopping at the first violation,
+    /// all samples are run, and every invariant is checked until it is
+    /// violated in the sample. Nondeterminism is resolved with the
+    /// probabilities given by `withProbability`, and uniformly otherwise.
+    pub estimate: bool,
+    /// Keep simulating after violations, collecting the distinct violations
+    /// (see [`DistinctViolation`]) instead of stopping at the first one. As
+    /// when estimating, all samples are run, and every invariant is checked
+    /// until it is violated in the sample.
+    pub collect_violations: bool,
+    /// How the initial state of each sample is picked.
+    pub init_strategy: InitStrategy,
+    /// Write the steps of the traces in ITF as variables of their states, as
+    /// Quint's typescript tool does for model-based testing (see
+    /// [`crate::mbt`]). The steps are recorded either way, with the action of
+    /// the initial state as `init`.
+    pub mbt: bool,
+    /// The master seed. Each sample starts with its own seed, derived from
+    /// the master seed and the sample number, so any sample can be run again
+    /// in isolation (see `sample`), regardless of how the samples were split
+    /// across threads. A random seed is used if not given.
+    pub seed: Option<u64>,
+    /// Only run the sample with this number (starting from 1), i.e. to
+    /// reproduce a violation found in it with the same `seed`.
+    pub sample: Option<usize>,
+    /// How the quality of traces without violations is measured, to keep the
+    /// best ones.
+    pub trace_quality: TraceQuality,
+    /// The names of the actions of `any { ... }` expressions to be taken
+    /// with weak fairness (see [`crate::fairness`]): when they are not taken
+    /// for `fairness_period` steps, they are tried first, regardless of
+    /// their weights.
+    pub fair_actions: Vec<String>,
+    pub fairness_period: usize,
+    /// Values for the constants of the main module, by name, as JSON (see
+    /// [`crate::constants`]).
+    pub constants: Vec<(String, Json)>,
+    /// Set to stop the simulation at the next sample, i.e. when it is
+    /// cancelled from another thread. The result has the samples run so far.
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// Where the values given to `q::debug` in the spec go (see
+    /// [`crate::output`]).
+    pub output: Output,
+    /// Fail when an operator enumerates an intermediate set, like an
+    /// interval, with more elements than this (see [`crate::enumeration`]).
+    pub max_enumeration: Option<usize>,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            steps: 10,
+            samples: 10_000,
+            n_traces: 1,
+            strategy: Strategy::default(),
+            time_budget: None,
+            action_weights: Vec::new(),
+            estimate: false,
+            collect_violations: false,
+            init_strategy: InitStrategy::default(),
+            mbt: false,
+            seed: None,
+            sample: None,
+            trace_quality: TraceQuality::default(),
+            fair_actions: Vec::new(),
+            fairness_period: 10,
+            constants: Vec::new(),
+            cancel: None,
+            output: Output::default(),
+            max_enumeration: None,
+        }
+    }
+}
+
+/// How the samples of a simulation are run.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Strategy {
+    /// Every sample runs for the maximum number of steps (unless it gets
+    /// stuck or finds a violation).
+    #[default]
+    FixedLength,
+    /// Many short walks with restarts: the number of steps grows
+    /// geometrically over consecutive samples (1, 2, 4, ...) up to the
+    /// maximum, and then starts over. Each step also tries to avoid states
+    /// that were already visited by earlier walks. This finds shallow bugs
+    /// much faster when there is a lot of branching.
+    Restarts,
+}
+
+/// How the initial states of the samples of a simulation are picked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum InitStrategy {
+    /// Evaluate `init`, with random choices, for each sample.
+    #[default]
+    Random,
+    /// Evaluate `init` again (a few times) while it gives an initial state
+    /// that an earlier sample started from, to cover more distinct initial
+    /// states.
+    Distinct,
+    /// Enumerate up to the given number of distinct initial states, going
+    /// over all combinations of the choices in `init`, and go over them in
+    /// order across samples. The choices must be over finite sets.
+    Enumerate(usize),
+}
+
+/// How the quality of a trace without violations is measured, to keep the
+/// best traces of a simulation. Greater scores are better, and violations are
+/// always better than traces without them (with shorter violations first).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TraceQuality {
+    /// The number of states in the trace.
+    #[default]
+    Length,
+    /// The number of distinct states in the trace, so traces that go around
+    /// in circles (or stutter) are not taken as good.
+    DistinctStates,
+    /// The number of distinct actions taken in the trace, by the names of
+    /// the actions in `any { ... }` expressions.
+    DistinctActions,
+}
+
+/// How many times a step is evaluated with the [`Strategy::Restarts`]
+/// strategy (or `init` with [`InitStrategy::Distinct`]), looking for a state
+/// that wasn't visited yet.
+const REVISIT_ATTEMPTS: usize = 3;
+
+/// How often progress is reported while simulating in parallel.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+
 /// Simulation progress update.
 pub struct ProgressUpdate {
     /// Current sample
     pub current: usize,
     /// Total number of samples
     pub total: usize,
+    /// Number of states visited so far, over all samples
+    pub states: usize,
+    /// Number of samples that found a violation so far
+    pub violations: usize,
+    /// Time since the simulation started
+    pub elapsed: Duration,
 }
 
 impl ProgressUpdate {
@@ -42,7 +424,95 @@ impl ProgressUpdate {
 /// Callback type for reporting simulation progress
 pub type ProgressCallback = Box<dyn FnMut(ProgressUpdate)>;
 
+/// Callbacks on simulation events, for embedders to stream states into their
+/// own storage, trigger alerts or compute custom metrics during simulation,
+/// see [`ParsedQuint::simulate_with_hooks`]. All callbacks do nothing by
+/// default, and `()` implements this trait for when no hooks are needed.
+pub trait SimulationHooks {
+    /// Called on every state reached, including the initial state of each
+    /// sample.
+    fn on_step(&mut self, _state: &State) {}
+
+    /// Called at the end of every sample, with its trace.
+    fn on_sample_end(&mut self, _trace: &Trace) {}
+
+    /// Called when a state violates an invariant, with the trace up to that
+    /// state and the index of the invariant in [`ParsedQuint::invariants`]
+    /// (or 0 for [`ParsedQuint::invariant`]).
+    fn on_violation(&mut self, _trace: &Trace, _invariant: usize) {}
+}
+
+impl SimulationHooks for () {}
+
+impl<H: SimulationHooks + ?Sized> SimulationHooks for &mut H {
+    fn on_step(&mut self, state: &State) {
+        (**self).on_step(state)
+    }
+
+    fn on_sample_end(&mut self, trace: &Trace) {
+        (**self).on_sample_end(trace)
+    }
+
+    fn on_violation(&mut self, trace: &Trace, invariant: usize) {
+        (**self).on_violation(trace, invariant)
+    }
+}
+
+/// Optional hooks, e.g. for outputs enabled by options.
+impl<H: SimulationHooks> SimulationHooks for Option<H> {
+    fn on_step(&mut self, state: &State) {
+        if let Some(hooks) = self {
+            hooks.on_step(state)
+        }
+    }
+
+    fn on_sample_end(&mut self, trace: &Trace) {
+        if let Some(hooks) = self {
+            hooks.on_sample_end(trace)
+        }
+    }
+
+    fn on_violation(&mut self, trace: &Trace, invariant: usize) {
+        if let Some(hooks) = self {
+            hooks.on_violation(trace, invariant)
+        }
+    }
+}
+
+/// Two hooks, called in order, e.g. to stream states and store them at once.
+impl<A: SimulationHooks, B: SimulationHooks> SimulationHooks for (A, B) {
+    fn on_step(&mut self, state: &State) {
+        self.0.on_step(state);
+        self.1.on_step(state);
+    }
+
+    fn on_sample_end(&mut self, trace: &Trace) {
+        self.0.on_sample_end(trace);
+        self.1.on_sample_end(trace);
+    }
+
+    fn on_violation(&mut self, trace: &Trace, invariant: usize) {
+        self.0.on_violation(trace, invariant);
+        self.1.on_violation(trace, invariant);
+    }
+}
+
 impl ParsedQuint {
+    /// Compile the step, i.e. `step` or the interleaving of the
+    /// [`ParsedQuint::components`], if any.
+    pub fn compile_step(&self, interpreter: &mut Interpreter) -> CompiledExpr {
+        if self.components.is_empty() {
+            return interpreter.compile(&self.step);
+        }
+
+        let components = self
+            .components
+            .iter()
+            .map(|component| (component.name.as_str().into(), component.expr.clone()))
+            .collect::<Vec<_>>();
+        interpreter.compile_interleaving(&components)
+    }
+
     /// Simulate a Quint model for a given number of steps and samples, storing
     /// up to `n_traces` traces of the greatest quality.
     ///
@@ -54,62 +524,746 @@ impl ParsedQuint {
     /// If `init` or `invariant` return false at any given point, simulation stops.
     /// If `step` returns false, we continue, as that just means we failed to progress
     /// in a specific setting.
+    ///
+    /// If there are [`ParsedQuint::invariants`], a violation only ends the
+    /// current sample, and simulation stops once all of them are violated.
+    /// Violated invariants are not checked in the following samples.
     pub fn simulate(
         &self,
         steps: usize,
         samples: usize,
         n_traces: usize,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Result<SimulationResult, QuintError> {
+        let config = SimulatorConfig {
+            steps,
+            samples,
+            n_traces,
+            ..SimulatorConfig::default()
+        };
+        self.simulate_with_config(&config, progress_callback)
+    }
+
+    /// Simulate a Quint model like [`ParsedQuint::simulate`], with all the
+    /// parameters given by `config`.
+    pub fn simulate_with_config(
+        &self,
+        config: &SimulatorConfig,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Result<SimulationResult, QuintError> {
+        self.simulate_with_hooks(config, progress_callback, &mut ())
+    }
+
+    /// Simulate a Quint model like [`ParsedQuint::simulate_with_config`],
+    /// calling `hooks` on every simulation event. Hooks are not available for
+    /// parallel simulations, as values can't be sent across threads.
+    pub fn simulate_with_hooks(
+        &self,
+        config: &SimulatorConfig,
+        progress_callback: Option<ProgressCallback>,
+        hooks: &mut dyn SimulationHooks,
+    ) -> Result<SimulationResult, QuintError> {
+        let mut interpreter = Interpreter::new(&self.table);
+        self.simulate_with_interpreter(&mut interpreter, config, progress_callback, hooks)
+    }
+
+    /// Simulate a Quint model like [`ParsedQuint::simulate_with_hooks`], with
+    /// an interpreter that may have compiled the model already (see
+    /// [`crate::instance::Instance`]).
+    pub(crate) fn simulate_with_interpreter(
+        &self,
+        interpreter: &mut Interpreter,
+        config: &SimulatorConfig,
         mut progress_callback: Option<ProgressCallback>,
+        hooks: &mut dyn SimulationHooks,
     ) -> Result<SimulationResult, QuintError> {
+        // Counters (if enabled) are reported per run, so start from zero
+        counters::reset();
+
+        let mut env = Env::new(interpreter.var_storage.clone());
+        env.output = config.output.clone();
+
+        // Running a single sample is like running the samples before it in
+        // another worker
+        let (config, first_sample) = match config.sample {
+            Some(sample) => (
+                &SimulatorConfig {
+                    samples: 1,
+                    ..config.clone()
+                },
+                sample.saturating_sub(1),
+            ),
+            None => (config, 0),
+        };
+
+        self.run(
+            interpreter,
+            &mut env,
+            config,
+            progress_callback
+                .as_mut()
+                .map(|callback| callback.as_mut() as &mut dyn FnMut(ProgressUpdate)),
+            hooks,
+            &Worker {
+                last_sample: &AtomicUsize::new(usize::MAX),
+                first_sample,
+                seed: config.seed.unwrap_or_else(rand::random_seed),
+            },
+        )
+    }
+
+    /// Simulate a Quint model like [`ParsedQuint::simulate`], splitting the
+    /// samples across `threads` worker threads.
+    ///
+    /// Each sample has its own seed, derived from the master `seed` (see
+    /// [`SimulatorConfig::seed`]), so the same seed reproduces the same samples
+    /// with any number of threads. Once a worker finds a violation (of all the
+    /// [`ParsedQuint::invariants`], if any), the samples after the one where it
+    /// did are skipped, while earlier ones still run. So the violations
+    /// reported are the ones of the lowest samples, as a sequential
+    /// simulation reports them, regardless of the number of threads and of
+    /// which worker finds a violation first.
+    ///
+    /// Values and the IR are not thread-safe, by design: they use reference
+    /// counting that is not atomic (i.e. `Rc` and `LocalHipStr`), which keeps
+    /// the sequential evaluator fast, so there is no thread-safe variant of
+    /// them. Instead, each worker parses its own copy of the model from JSON
+    /// and compiles it, once per simulation, and traces are converted to ITF
+    /// (with the given `source`) before being merged.
+    pub fn simulate_parallel(
+        &self,
+        steps: usize,
+        samples: usize,
+        n_traces: usize,
+        threads: usize,
+        seed: u64,
+        source: &str,
+    ) -> Result<ParallelSimulationResult, QuintError> {
+        let config = SimulatorConfig {
+            steps,
+            samples,
+            n_traces,
+            seed: Some(seed),
+            ..SimulatorConfig::default()
+        };
+        self.simulate_parallel_with_config(&config, threads, source, None)
+    }
+
+    /// Simulate a Quint model in parallel like [`ParsedQuint::simulate_parallel`],
+    /// with the parameters given by `config`.
+    ///
+    /// Progress is aggregated over all workers and reported from the calling
+    /// thread, every [`PROGRESS_INTERVAL`].
+    pub fn simulate_parallel_with_config(
+        &self,
+        config: &SimulatorConfig,
+        threads: usize,
+        source: &str,
+        progress_callback: Option<ProgressCallback>,
+    ) -> Result<ParallelSimulationResult, QuintError> {
+        let SimulatorConfig {
+            samples, n_traces, ..
+        } = *config;
+        let seed = config.seed.unwrap_or_else(rand::random_seed);
+
+        // Values hold `Rc`s, so they can't be shared across threads, and
+        // neither can the model (with its values, i.e. of constants). Each
+        // worker parses its own copy from the serialized form instead, which
+        // is cheap next to simulating
+        let model = serde_json::to_string(self).map_err(|e| {
+            QuintError::new(
+                ErrorKind::Unsupported,
+                format!("Failed to serialize model: {e}").as_str(),
+            )
+        })?;
+
+        let threads = threads.clamp(1, samples.max(1));
+        // The last sample to run, lowered as workers find violations
+        let last_sample = AtomicUsize::new(usize::MAX);
+        let progress: Vec<WorkerProgress> =
+            (0..threads).map(|_| WorkerProgress::default()).collect();
+        let start = Instant::now();
+        // Counting is enabled per thread, so workers take it from this one
+        let counting = counters::is_enabled();
+        let measuring_sharing = counters::is_sharing_enabled();
+
+        let outcomes = thread::scope(|scope| {
+            let workers = (0..threads)
+                .map(|index| {
+                    // Distribute the remainder over the first workers
+                    let worker_samples = samples / threads + usize::from(index < samples % threads);
+                    let first_sample = index * (samples / threads) + index.min(samples % threads);
+                    let model = &model;
+                    let last_sample = &last_sample;
+                    let progress = &progress[index];
+
+                    scope.spawn(move || {
+                        let parsed: ParsedQuint = serde_json::from_str(model).map_err(|e| {
+                            QuintError::new(
+                                ErrorKind::Unsupported,
+                                format!("Failed to deserialize model: {e}").as_str(),
+                            )
+                        })?;
+
+                        counters::set_enabled(counting);
+                        counters::set_sharing_enabled(measuring_sharing);
+                        counters::reset();
+
+                        let mut interpreter = Interpreter::new(&parsed.table);
+                        let mut env = Env::new(interpreter.var_storage.clone());
+                        env.output = config.output.clone();
+
+                        let config = SimulatorConfig {
+                            samples: worker_samples,
+                            ..config.clone()
+                        };
+                        let mut report = |update: ProgressUpdate| progress.store(&update);
+                        let result = parsed.run(
+                            &mut interpreter,
+                            &mut env,
+                            &config,
+                            Some(&mut report),
+                            &mut (),
+                            &Worker {
+                                last_sample,
+                                first_sample,
+                                seed,
+                            },
+                        );
+
+                        // Errors stop all workers
+                        if result.is_err() {
+                            last_sample.store(0, Ordering::Relaxed);
+                        }
+
+                        result.map(|result| ParallelSimulationResult {
+                            result: result.result,
+                            best_traces: result
+                                .best_traces
+                                .into_iter()
+                                .zip(result.best_trace_scores)
+                                .map(|(trace, score)| ItfTrace {
+                                    score,
+                                    ..ItfTrace::new(trace, source, config.mbt)
+                                })
+                                .collect(),
+                            samples: result.samples,
+                            seed,
+                            invariants: result
+                                .invariants
+                                .into_iter()
+                                .map(|i| InvariantResult {
+                                    name: i.name,
+                                    violation: i.violation.map(|v| InvariantViolation {
+                                        sample: v.sample,
+                                        seed: v.seed,
+                                        step: v.step,
+                                        trace: ItfTrace::new(v.trace, source, config.mbt),
+                                    }),
+                                })
+                                .collect(),
+                            timed_out: result.timed_out,
+                            action_weights: result.action_weights,
+                            estimates: result.estimates,
+                            witnesses: result.witnesses,
+                            distinct_violations: result
+                                .distinct_violations
+                                .into_iter()
+                                .map(|v| DistinctViolation {
+                                    invariant: v.invariant,
+                                    fingerprint: v.fingerprint,
+                                    count: v.count,
+                                    trace: ItfTrace::new(v.trace, source, config.mbt),
+                                })
+                                .collect(),
+                            initial_states: result.initial_states,
+                            enumerated_initial_states: result.enumerated_initial_states,
+                            counters: counters::get(),
+                        })
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            if let Some(mut callback) = progress_callback {
+                while !workers.iter().all(|worker| worker.is_finished()) {
+                    callback(ProgressUpdate {
+                        current: progress
+                            .iter()
+                            .map(|p| p.current.load(Ordering::Relaxed))
+                            .sum(),
+                        total: samples,
+                        states: progress
+                            .iter()
+                            .map(|p| p.states.load(Ordering::Relaxed))
+                            .sum(),
+                        violations: progress
+                            .iter()
+                            .map(|p| p.violations.load(Ordering::Relaxed))
+                            .sum(),
+                        elapsed: start.elapsed(),
+                    });
+                    thread::sleep(PROGRESS_INTERVAL);
+                }
+            }
+
+            workers
+                .into_iter()
+                .map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|panic| Err(worker_panicked(panic)))
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let mut merged = ParallelSimulationResult {
+            result: true,
+            best_traces: Vec::with_capacity(n_traces + 1),
+            samples: 0,
+            seed,
+            invariants: self
+                .invariants
+                .iter()
+                .map(|i| InvariantResult {
+                    name: i.name.clone(),
+                    violation: None,
+                })
+                .collect(),
+            timed_out: false,
+            action_weights: Vec::new(),
+            estimates: Vec::new(),
+            witnesses: vec![WitnessCount::default(); self.witnesses.len()],
+            distinct_violations: Vec::new(),
+            initial_states: FxHashSet::default(),
+            enumerated_initial_states: None,
+            counters: Counters::default(),
+        };
+
+        for outcome in outcomes {
+            let outcome = outcome?;
+            merged.result &= outcome.result;
+            merged.samples += outcome.samples;
+            merged.timed_out |= outcome.timed_out;
+            // All workers compile the same model, so they apply the same weights
+            merged.action_weights = outcome.action_weights;
+            merged.initial_states.extend(outcome.initial_states);
+            merged.enumerated_initial_states = outcome.enumerated_initial_states;
+            for violation in outcome.distinct_violations {
+                let existing = merged.distinct_violations.iter_mut().find(|v| {
+                    (v.invariant, v.fingerprint) == (violation.invariant, violation.fingerprint)
+                });
+                match existing {
+                    Some(existing) => {
+                        existing.count += violation.count;
+                        if violation.trace.len < existing.trace.len {
+                            existing.trace = violation.trace;
+                        }
+                    }
+                    None => merged.distinct_violations.push(violation),
+                }
+            }
+            for (merged, outcome) in merged.witnesses.iter_mut().zip(outcome.witnesses) {
+                merged.samples += outcome.samples;
+                merged.states += outcome.states;
+            }
+            if merged.estimates.is_empty() {
+                merged.estimates = outcome.estimates;
+            } else {
+                for (merged, outcome) in merged.estimates.iter_mut().zip(outcome.estimates) {
+                    merged.hits += outcome.hits;
+                    merged.samples += outcome.samples;
+                }
+            }
+            merged.counters += outcome.counters;
+            // Keep the violation from the earliest sample
+            for (merged, outcome) in merged.invariants.iter_mut().zip(outcome.invariants) {
+                match (&merged.violation, outcome.violation) {
+                    (Some(m), Some(o)) if o.sample < m.sample => merged.violation = Some(o),
+                    (None, o) => merged.violation = o,
+                    _ => {}
+                }
+            }
+            for trace in outcome.best_traces {
+                let index = merged.best_traces.binary_search_by(|t| {
+                    compare_by_quality((t.violation, t.score), (trace.violation, trace.score))
+                });
+                merged
+                    .best_traces
+                    .insert(index.unwrap_or_else(|i| i), trace);
+                merged.best_traces.truncate(n_traces);
+            }
+        }
+
+        Ok(merged)
+    }
+
+    /// Replay a trace against the model, checking that the first state is
+    /// produced by `init`, that each state is produced by `step` from the
+    /// previous one, and that every state satisfies the invariant. Stops at
+    /// the first state where any of these doesn't hold.
+    ///
+    /// States with a recorded step (see [`crate::trace::StepMeta`]) are
+    /// replayed deterministically, taking the recorded alternatives in `any`s
+    /// and the recorded values of `nondet` definitions. For other states, i.e.
+    /// of traces found by other tools, nondeterministic choices are random, so
+    /// each action is evaluated up to `attempts` times, going back to the
+    /// previous state in between, until it produces the expected state.
+    /// Actions with many possible outcomes may need more attempts to be
+    /// matched.
+    pub fn replay(&self, trace: &Trace, attempts: usize) -> Result<ReplayOutcome, QuintError> {
         let mut interpreter = Interpreter::new(&self.table);
+        interpreter.set_types(&self.types);
         let mut env = Env::new(interpreter.var_storage.clone());
+        // Traces of samples start from their seed, so the first attempts make
+        // the same choices as the sample did, when the spec hasn't changed
+        if let Some(seed) = trace.seed {
+            env.rand.set_state(seed);
+        }
 
         let init = interpreter.compile(&self.init);
-        let step = interpreter.compile(&self.step);
+        let step = self.compile_step(&mut interpreter);
         let invariant = interpreter.compile(&self.invariant);
 
-        // Have one extra space as we insert first and then pop if we have too many traces
+        for (index, expected) in trace.states.iter().enumerate() {
+            let action = if index == 0 { &init } else { &step };
+
+            if !self.produces(&interpreter, &mut env, action, expected, attempts)? {
+                return Ok(ReplayOutcome::Divergence { state: index });
+            }
+
+            interpreter.shift();
+
+            if !invariant.execute(&mut env)?.try_bool()? {
+                return Ok(ReplayOutcome::Violation { state: index });
+            }
+        }
+
+        Ok(ReplayOutcome::Ok)
+    }
+
+    /// Replay a trace found by another tool, like [`ParsedQuint::replay`], to
+    /// check that the evaluator agrees with it: counterexamples (with
+    /// `expect_violation`) should violate the invariant on their last state
+    /// only, and other traces never.
+    pub fn cross_check(
+        &self,
+        trace: &Trace,
+        expect_violation: bool,
+        attempts: usize,
+    ) -> Result<Agreement, QuintError> {
+        let last = trace.states.len().saturating_sub(1);
+        Ok(match self.replay(trace, attempts)? {
+            ReplayOutcome::Ok if expect_violation => Agreement::MissingViolation,
+            ReplayOutcome::Ok => Agreement::Agrees,
+            ReplayOutcome::Violation { state } if expect_violation && state == last => {
+                Agreement::Agrees
+            }
+            ReplayOutcome::Violation { state } => Agreement::UnexpectedViolation { state },
+            ReplayOutcome::Divergence { state } => Agreement::Divergence { state },
+        })
+    }
+
+    /// Check each of the [`ParsedQuint::temporal`] properties over the given
+    /// traces, with one verdict per trace. See [`crate::temporal`] for how
+    /// finite traces are interpreted.
+    pub fn check_temporal(&self, traces: &[Trace]) -> Result<Vec<TemporalResult>, QuintError> {
+        self.temporal
+            .iter()
+            .map(|property| {
+                let mut checker = TemporalChecker::new(&self.table, &property.expr)?;
+                let verdicts = traces
+                    .iter()
+                    .map(|trace| checker.check(&trace.vars().cloned().collect::<Vec<_>>()))
+                    .collect::<Result<_, _>>()?;
+
+                Ok(TemporalResult {
+                    name: property.name.clone(),
+                    verdicts,
+                })
+            })
+            .collect()
+    }
+
+    /// Whether the action can produce the expected next state, with the
+    /// choices recorded for it, or else trying up to `attempts` times. On
+    /// success, the expected state is left primed.
+    fn produces(
+        &self,
+        interpreter: &Interpreter,
+        env: &mut Env,
+        action: &CompiledExpr,
+        expected: &State,
+        attempts: usize,
+    ) -> Result<bool, QuintError> {
+        let produced = |env: &mut Env| -> Result<bool, QuintError> {
+            Ok(action.execute(env)?.try_bool()?
+                && has_state(
+                    &interpreter.var_storage.borrow().primed_as_record(),
+                    &expected.vars,
+                ))
+        };
+
+        if let Some(meta) = expected.meta.as_ref().filter(|meta| meta.has_choices()) {
+            env.replay = Some(meta.clone());
+            let result = produced(env);
+            env.replay = None;
+            return result;
+        }
+
+        // Only restore the variables, so each attempt makes different choices
+        let snapshot = env.var_storage.borrow().take_state_snapshot();
+
+        for _ in 0..attempts {
+            env.var_storage.borrow_mut().restore_state(&snapshot);
+
+            if produced(env)? {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+
+    /// Run the simulation samples with the given interpreter and environment,
+    /// as the given `worker` of a (possibly parallel) simulation.
+    fn run(
+        &self,
+        interpreter: &mut Interpreter,
+        env: &mut Env,
+        config: &SimulatorConfig,
+        mut progress_callback: Option<&mut dyn FnMut(ProgressUpdate)>,
+        hooks: &mut dyn SimulationHooks,
+        worker: &Worker,
+    ) -> Result<SimulationResult, QuintError> {
+        let SimulatorConfig {
+            samples,
+            n_traces,
+            strategy,
+            ..
+        } = *config;
+
+        interpreter.set_action_weights(&config.action_weights);
+        interpreter.set_fair_actions(&config.fair_actions);
+        interpreter.set_constants(&config.constants)?;
+        interpreter.set_types(&self.types);
+        env.fairness = Fairness::new(&config.fair_actions, config.fairness_period);
+        enumeration::set_limit(config.max_enumeration);
+        // The steps of the traces are always recorded, to show what happened
+        // in counterexamples
+        interpreter.set_record_mbt(true);
+        let (init, step, invariants, witnesses) = {
+            let _span = span!(INFO, "compile");
+            let init = interpreter.compile(&self.init);
+            let step = self.compile_step(interpreter);
+            let invariants = if self.invariants.is_empty() {
+                vec![interpreter.compile(&self.invariant)]
+            } else {
+                self.invariants
+                    .iter()
+                    .map(|i| interpreter.compile(&i.expr))
+                    .collect()
+            };
+            let witnesses = self
+                .witnesses
+                .iter()
+                .map(|w| interpreter.compile(w))
+                .collect::<Vec<_>>();
+            (init, step, invariants, witnesses)
+        };
+        let mut witness_counts = vec![WitnessCount::default(); witnesses.len()];
+
+        // Have one extra space as we insert first and then pop if we have too
+        // many traces. Traces kept through the run only store the changes on
+        // each step (see `DeltaTrace`).
         let mut best_traces = Vec::with_capacity(n_traces + 1);
 
+        // The first violation of each invariant, by their index in `invariants`
+        let mut violations: Vec<Option<(usize, u64, usize, DeltaTrace)>> =
+            vec![None; invariants.len()];
+        // The number of samples where each invariant was violated
+        let mut hits = vec![0; invariants.len()];
+        let keep_going = config.estimate || config.collect_violations;
+
+        // Distinct violations, and their indices by invariant and fingerprint
+        // of the violating state
+        let mut distinct = Vec::new();
+        let mut distinct_index = FxHashMap::default();
+
+        // Fingerprints of the states visited so far, to avoid revisiting them
+        // with the restarts strategy
+        let mut visited = FxHashSet::default();
+        // Fingerprints of the initial states of the samples so far
+        let mut initial_states = FxHashSet::default();
+        let enumerated = match config.init_strategy {
+            InitStrategy::Enumerate(bound) => Some(enumerate_initial_states(env, &init, bound)?),
+            _ => None,
+        };
+        // Every sample starts with the same fresh identifiers, after the ones
+        // taken by the enumerated initial states
+        let first_fresh = env.next_fresh;
+
+        let mut samples_run = 0;
+        let mut states = 0;
+        let mut violated_samples = 0;
+        let mut timed_out = false;
+        let start = Instant::now();
+
         for sample_number in 1..=samples {
+            let cancelled = config
+                .cancel
+                .as_ref()
+                .is_some_and(|cancel| cancel.load(Ordering::Relaxed));
+            // Samples are numbered from 1, across workers
+            let sample = worker.first_sample + sample_number;
+            if sample > worker.last_sample.load(Ordering::Relaxed) || cancelled {
+                break;
+            }
+            if config
+                .time_budget
+                .is_some_and(|budget| start.elapsed() >= budget)
+            {
+                timed_out = true;
+                break;
+            }
+            samples_run = sample_number;
+
+            // Each sample starts from its own seed, so it can be reproduced
+            // without running the previous ones
+            let sample_seed = rand::derive_state(worker.seed, sample as u64);
+            let _span = span!(INFO, "sample", sample, seed = sample_seed);
+            env.rand.set_state(sample_seed);
+            env.fairness.reset();
+            env.next_fresh = first_fresh;
+
             if let Some(callback) = &mut progress_callback {
                 callback(ProgressUpdate {
                     current: sample_number,
                     total: samples,
+                    states,
+                    violations: violated_samples,
+                    elapsed: start.elapsed(),
                 });
             }
 
+            let steps = match strategy {
+                Strategy::FixedLength => config.steps,
+                Strategy::Restarts => walk_length(sample_number, config.steps),
+            };
             let mut trace = Vec::with_capacity(steps + 1);
+            // The distinct actions taken in this sample
+            let mut actions = FxHashSet::default();
 
-            if !init.execute(&mut env)?.as_bool() {
+            let initialized = match &enumerated {
+                Some(states) if !states.is_empty() => {
+                    // Go over the enumerated states in order, continuing from
+                    // where the previous workers stopped
+                    let index = (sample - 1) % states.len();
+                    let empty = Value::Record(ImmutableMap::default());
+                    env.var_storage.borrow_mut().load(&empty, &states[index]);
+                    env.mbt.clear();
+                    true
+                }
+                _ => {
+                    let distinct = config.init_strategy == InitStrategy::Distinct;
+                    take_step(env, &init, distinct, &initial_states)?
+                }
+            };
+            if !initialized {
+                let (best_traces, best_trace_scores) = full_traces(best_traces);
                 return Ok(SimulationResult {
                     result: false,
                     best_traces,
+                    best_trace_scores,
+                    samples: samples_run,
+                    seed: worker.seed,
+                    invariants: self.invariant_results(violations),
+                    timed_out,
+                    action_weights: interpreter.applied_action_weights().to_vec(),
+                    estimates: estimates(config, &hits, samples_run),
+                    witnesses: witness_counts,
+                    distinct_violations: distinct.into_iter().map(Into::into).collect(),
+                    initial_states,
+                    enumerated_initial_states: enumerated.map(|states| states.len()),
                 });
             }
 
+            let mut violated = false;
+            // Which invariants were violated in this sample, for the estimates
+            let mut hit = vec![false; invariants.len()];
+            // Which witnesses held in this sample
+            let mut witnessed = vec![false; witnesses.len()];
+
             for step_number in 1..=(steps + 1) {
                 interpreter.shift();
+                env.fairness.end_step();
 
-                trace.push(interpreter.var_storage.borrow().as_record());
-
-                if !invariant.execute(&mut env)?.as_bool() {
-                    // Found a counterexample
-                    collect_trace(
-                        &mut best_traces,
-                        n_traces,
-                        Trace {
-                            states: trace,
-                            violation: false,
-                        },
-                    );
-                    return Ok(SimulationResult {
-                        result: false,
-                        best_traces,
-                    });
+                let state = interpreter.var_storage.borrow().as_record();
+                if step_number == 1 {
+                    initial_states.insert(state.fingerprint());
+                }
+                if strategy == Strategy::Restarts {
+                    visited.insert(state.fingerprint());
+                }
+                if step_number == 1 {
+                    env.mbt.action_taken = Some("init".to_string());
+                }
+                trace.push(State {
+                    vars: state,
+                    meta: Some(env.mbt.clone()),
+                });
+                states += 1;
+                hooks.on_step(trace.last().unwrap());
+
+                for ((witness, witnessed), count) in witnesses
+                    .iter()
+                    .zip(&mut witnessed)
+                    .zip(&mut witness_counts)
+                {
+                    if witness.execute(env)?.try_bool()? {
+                        *witnessed = true;
+                        count.states += 1;
+                    }
+                }
+
+                for (i, invariant) in invariants.iter().enumerate() {
+                    // When estimating or collecting violations, every
+                    // invariant is checked on every sample, until it is violated
+                    let check = if keep_going {
+                        !hit[i]
+                    } else {
+                        violations[i].is_none()
+                    };
+                    if !check {
+                        continue;
+                    }
+                    let _span = span!(DEBUG, "invariant", invariant = i);
+                    if !invariant.execute(env)?.try_bool()? {
+                        let trace = Trace {
+                            states: trace.clone(),
+                            violation: true,
+                            seed: Some(sample_seed),
+                        };
+                        hooks.on_violation(&trace, i);
+                        if config.collect_violations {
+                            collect_violation(&mut distinct, &mut distinct_index, i, &trace);
+                        }
+                        if violations[i].is_none() {
+                            let trace = DeltaTrace::from(&trace);
+                            violations[i] = Some((sample, sample_seed, step_number - 1, trace));
+                        }
+                        hit[i] = true;
+                        violated = true;
+                    }
                 }
 
-                if step_number != steps + 1 && !step.execute(&mut env)?.as_bool() {
+                if violated && (!keep_going || hit.iter().all(|&h| h)) {
+                    // Found a counterexample (for every invariant, if estimating
+                    // or collecting violations)
+                    break;
+                }
+
+                let avoid_visited = strategy == Strategy::Restarts;
+                if step_number != steps + 1 && !take_step(env, &step, avoid_visited, &visited)? {
                     // The run cannot be extended. In some cases, this may indicate a deadlock.
                     // Since we are doing random simulation, it is very likely
                     // that we have not generated good values for extending
@@ -118,53 +1272,343 @@ impl ParsedQuint {
                     // positives, which look like deadlocks but they are not.
                     break;
                 }
+                if let Some(action) = &env.mbt.action_taken {
+                    actions.insert(action.clone());
+                }
+            }
+
+            violated_samples += usize::from(violated);
+            let score = match config.trace_quality {
+                _ if violated => trace.len(),
+                TraceQuality::Length => trace.len(),
+                TraceQuality::DistinctStates => trace
+                    .iter()
+                    .map(|state| state.vars.fingerprint())
+                    .collect::<FxHashSet<_>>()
+                    .len(),
+                TraceQuality::DistinctActions => actions.len(),
+            };
+            let trace = Trace {
+                states: trace,
+                violation: violated,
+                seed: Some(sample_seed),
+            };
+            hooks.on_sample_end(&trace);
+            for (hits, hit) in hits.iter_mut().zip(hit) {
+                *hits += usize::from(hit);
+            }
+            for (count, witnessed) in witness_counts.iter_mut().zip(witnessed) {
+                count.samples += usize::from(witnessed);
+            }
+            collect_trace(&mut best_traces, n_traces, &trace, score);
+
+            if !keep_going && violations.iter().all(Option::is_some) {
+                // Samples after this one have nothing left to find
+                worker.last_sample.fetch_min(sample, Ordering::Relaxed);
+                break;
             }
-            collect_trace(
-                &mut best_traces,
-                n_traces,
-                Trace {
-                    states: trace,
-                    violation: false,
-                },
-            );
         }
+        let (best_traces, best_trace_scores) = full_traces(best_traces);
         Ok(SimulationResult {
-            result: true,
+            result: violations.iter().all(Option::is_none),
             best_traces,
+            best_trace_scores,
+            samples: samples_run,
+            seed: worker.seed,
+            invariants: self.invariant_results(violations),
+            timed_out,
+            action_weights: interpreter.applied_action_weights().to_vec(),
+            estimates: estimates(config, &hits, samples_run),
+            witnesses: witness_counts,
+            distinct_violations: distinct.into_iter().map(Into::into).collect(),
+            initial_states,
+            enumerated_initial_states: enumerated.map(|states| states.len()),
         })
     }
+
+    /// Pair the violations found by [`ParsedQuint::run`] with the names of
+    /// the invariants. If there are no named invariants, there is nothing to
+    /// report.
+    fn invariant_results(
+        &self,
+        violations: Vec<Option<(usize, u64, usize, DeltaTrace)>>,
+    ) -> Vec<InvariantResult> {
+        self.invariants
+            .iter()
+            .zip(violations)
+            .map(|(invariant, violation)| InvariantResult {
+                name: invariant.name.clone(),
+                violation: violation.map(|(sample, seed, step, trace)| InvariantViolation {
+                    sample,
+                    seed,
+                    step,
+                    trace: trace.into(),
+                }),
+            })
+            .collect()
+    }
+}
+
+/// How a run fits in a simulation, which can be split across workers.
+struct Worker<'a> {
+    /// The last sample to run, lowered by other workers as they find
+    /// violations. Later samples are skipped.
+    last_sample: &'a AtomicUsize,
+    /// The number of samples run by previous workers.
+    first_sample: usize,
+    /// The master seed of the simulation.
+    seed: u64,
+}
+
+/// The latest progress of a parallel simulation worker, read by the thread
+/// reporting the overall progress.
+#[derive(Default)]
+struct WorkerProgress {
+    current: AtomicUsize,
+    states: AtomicUsize,
+    violations: AtomicUsize,
+}
+
+impl WorkerProgress {
+    fn store(&self, update: &ProgressUpdate) {
+        self.current.store(update.current, Ordering::Relaxed);
+        self.states.store(update.states, Ordering::Relaxed);
+        self.violations.store(update.violations, Ordering::Relaxed);
+    }
+}
+
+/// Add a violation of the invariant at index `invariant` to the distinct
+/// ones, if its last state is new, or count it otherwise, keeping the
+/// shortest trace.
+fn collect_violation(
+    distinct: &mut Vec<DistinctViolation<DeltaTrace>>,
+    index: &mut FxHashMap<(usize, u64), usize>,
+    invariant: usize,
+    trace: &Trace,
+) {
+    let fingerprint = trace.states.last().unwrap().vars.fingerprint();
+    match index.get(&(invariant, fingerprint)) {
+        Some(&i) => {
+            distinct[i].count += 1;
+            if trace.states.len() < distinct[i].trace.len() {
+                distinct[i].trace = DeltaTrace::from(trace);
+            }
+        }
+        None => {
+            index.insert((invariant, fingerprint), distinct.len());
+            distinct.push(DistinctViolation {
+                invariant,
+                fingerprint,
+                count: 1,
+                trace: DeltaTrace::from(trace),
+            });
+        }
+    }
+}
+
+impl From<DistinctViolation<DeltaTrace>> for DistinctViolation {
+    fn from(violation: DistinctViolation<DeltaTrace>) -> Self {
+        DistinctViolation {
+            invariant: violation.invariant,
+            fingerprint: violation.fingerprint,
+            count: violation.count,
+            trace: violation.trace.into(),
+        }
+    }
+}
+
+/// The estimates from the number of samples violating each invariant, if
+/// they were requested.
+fn estimates(config: &SimulatorConfig, hits: &[usize], samples: usize) -> Vec<ProbabilityEstimate> {
+    if !config.estimate {
+        return Vec::new();
+    }
+    hits.iter()
+        .map(|&hits| ProbabilityEstimate { hits, samples })
+        .collect()
+}
+
+impl ItfTrace {
+    /// The trace in ITF, with the steps as variables with `mbt` (see
+    /// [`Trace::to_mbt_itf`]).
+    fn new(trace: Trace, source: &str, mbt: bool) -> Self {
+        let len = trace.states.len();
+        ItfTrace {
+            violation: trace.violation,
+            len,
+            score: len,
+            trace: if mbt {
+                trace.to_mbt_itf(source.to_string())
+            } else {
+                trace.to_itf(source.to_string())
+            },
+        }
+    }
+}
+
+/// Evaluate the step (or `init`), returning whether it was enabled. With
+/// `avoid_visited`, the step is evaluated again (up to [`REVISIT_ATTEMPTS`]
+/// times) while it leads to an already visited state.
+fn take_step(
+    env: &mut Env,
+    step: &CompiledExpr,
+    avoid_visited: bool,
+    visited: &FxHashSet<u64>,
+) -> Result<bool, QuintError> {
+    if !avoid_visited {
+        env.mbt.clear();
+        env.fairness.clear();
+        return step.execute_action(env)?.try_bool();
+    }
+
+    // Only restore the variables, so each attempt makes different choices
+    let snapshot = env.var_storage.borrow().take_state_snapshot();
+    let mut enabled = false;
+    for attempt in 0..REVISIT_ATTEMPTS {
+        if attempt > 0 {
+            env.var_storage.borrow_mut().restore_state(&snapshot);
+        }
+
+        env.mbt.clear();
+        env.fairness.clear();
+        // Failed assertions are only recovered from inside of alternatives,
+        // so they are reported here as they would be without retries
+        enabled = step.execute_action(env)?.try_bool()?;
+        let next_state = env.var_storage.borrow().primed_as_record();
+        if enabled && !visited.contains(&next_state.fingerprint()) {
+            break;
+        }
+    }
+
+    Ok(enabled)
+}
+
+/// The error for a simulation worker that panicked, with the message of the
+/// panic when it has one.
+fn worker_panicked(panic: Box<dyn std::any::Any + Send>) -> QuintError {
+    let message = panic
+        .downcast_ref::<&str>()
+        .map(|message| message.to_string())
+        .or_else(|| panic.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown cause".to_string());
+    QuintError::new(
+        ErrorKind::Unsupported,
+        format!("Simulation worker panicked: {message}").as_str(),
+    )
+}
+
+/// Whether a state produced by the spec has the values of the expected state,
+/// for all of its variables. Other fields of the expected state are ignored,
+/// such as the ones recorded for MBT (see [`crate::mbt`]) or by other tools.
+fn has_state(produced: &Value, expected: &Value) -> bool {
+    match (produced, expected) {
+        (Value::Record(produced), Value::Record(expected)) => produced
+            .iter()
+            .all(|(name, value)| expected.get(name) == Some(value)),
+        _ => produced == expected,
+    }
 }
 
-/// Collect a trace of the simulation, up to a maximum of `n_traces`.
+/// Enumerate up to `bound` distinct states produced by `init`, going over the
+/// combinations of its nondeterministic choices.
+fn enumerate_initial_states(
+    env: &mut Env,
+    init: &CompiledExpr,
+    bound: usize,
+) -> Result<Vec<Value>, QuintError> {
+    let empty = Value::Record(ImmutableMap::default());
+    let pick_mode = std::mem::replace(&mut env.pick_mode, PickMode::Exhaustive);
+    let mut fingerprints = FxHashSet::default();
+    let mut states = Vec::new();
+
+    let mut discarded = None;
+
+    let mut enumerate = || loop {
+        env.var_storage.borrow_mut().load(&empty, &empty);
+        if init.execute_alternative(env, &mut discarded)? {
+            let state = env.var_storage.borrow().primed_as_record();
+            if fingerprints.insert(state.fingerprint()) {
+                states.push(state);
+            }
+        }
+
+        if states.len() >= bound || !env.choices.advance() {
+            return Ok(());
+        }
+    };
+    let result = enumerate();
+
+    // Leave the environment as it was, even if we stopped in the middle
+    env.choices = Choices::default();
+    env.pick_mode = pick_mode;
+    result?;
+    match discarded {
+        Some(error) if states.is_empty() => Err(error),
+        _ => Ok(states),
+    }
+}
+
+/// The number of steps for a sample with the restarts strategy. Lengths double
+/// on each sample, from 1 up to `max_steps`, and then start over.
+fn walk_length(sample_number: usize, max_steps: usize) -> usize {
+    // The number of samples until the maximum length is reached, inclusive
+    let cycle = (max_steps.max(1).next_power_of_two().trailing_zeros() + 1) as usize;
+    let position = (sample_number - 1) % cycle;
+    (1 << position).min(max_steps)
+}
+
+/// Collect a trace of the simulation with its score (see [`TraceQuality`]),
+/// up to a maximum of `n_traces`. Traces are only stored (as a [`DeltaTrace`])
+/// if they are kept.
 ///
 /// Assumes `best_traces` is sorted by quality.
-fn collect_trace(best_traces: &mut Vec<Trace>, n_traces: usize, trace: Trace) {
-    insert_trace_sorted_by_quality(best_traces, trace);
+fn collect_trace(
+    best_traces: &mut Vec<(DeltaTrace, usize)>,
+    n_traces: usize,
+    trace: &Trace,
+    score: usize,
+) {
+    let index = quality_index(best_traces, trace.violation, score);
+    if index >= n_traces {
+        return;
+    }
+    best_traces.insert(index, (DeltaTrace::from(trace), score));
     if best_traces.len() > n_traces {
         best_traces.pop();
     }
 }
 
-/// Compare two traces by quality.
+/// The best traces with their scores, with their full states.
+fn full_traces(best_traces: Vec<(DeltaTrace, usize)>) -> (Vec<Trace>, Vec<usize>) {
+    best_traces
+        .into_iter()
+        .map(|(trace, score)| (trace.into(), score))
+        .unzip()
+}
+
+/// Compare two traces by quality, given whether they have a violation and
+/// their score, which is the length for violations.
 ///
-/// Prefer short traces for error, and longer traces for non error.
-/// Therefore, trace a is better than trace b iff
+/// Prefer short traces for error, and traces with greater scores for non
+/// error. Therefore, trace a is better than trace b iff
 ///  - when a has an error: a is shorter or b has no error;
-///  - when a has no error: a is longer and b has no error.
-fn compare_traces_by_quality(a: &Trace, b: &Trace) -> std::cmp::Ordering {
-    match (a.violation, b.violation) {
-        (true, true) => a.states.len().cmp(&b.states.len()),
+///  - when a has no error: a has a greater score and b has no error.
+fn compare_by_quality(
+    (a_violation, a_score): (bool, usize),
+    (b_violation, b_score): (bool, usize),
+) -> std::cmp::Ordering {
+    match (a_violation, b_violation) {
+        (true, true) => a_score.cmp(&b_score),
         (true, false) => std::cmp::Ordering::Less,
         (false, true) => std::cmp::Ordering::Greater,
-        (false, false) => b.states.len().cmp(&a.states.len()),
+        (false, false) => b_score.cmp(&a_score),
     }
 }
 
-/// Insert a trace into a sorted vector of traces, maintaining the order by quality.
-fn insert_trace_sorted_by_quality(best_traces: &mut Vec<Trace>, trace: Trace) {
-    let index = best_traces.binary_search_by(|t| compare_traces_by_quality(t, &trace));
-    match index {
-        Ok(index) => best_traces.insert(index, trace),
-        Err(index) => best_traces.insert(index, trace),
-    }
+/// Where to insert a trace into a sorted vector of traces, maintaining the
+/// order by quality.
+fn quality_index(best_traces: &[(DeltaTrace, usize)], violation: bool, score: usize) -> usize {
+    best_traces
+        .binary_search_by(|(t, s)| compare_by_quality((t.violation, *s), (violation, score)))
+        .unwrap_or_else(|index| index)
 }
@@ -0,0 +1,31 @@
+//! Spans for the phases of evaluation (compiling a spec, running each sample
+//! of a simulation, evaluating invariants, and exporting traces), with the
+//! `tracing` crate, so embedders can see where wall-clock time goes when the
+//! evaluator is one component of a larger pipeline. The spans are recorded by
+//! the subscriber installed by the embedder, and can be exported to
+//! OpenTelemetry with `tracing-opentelemetry`.
+//!
+//! This is only available with the `tracing` feature. Without it, the spans
+//! are not even created.
+
+/// Enter a span at the given level (e.g. `INFO`), with the same name and
+/// fields as `tracing::span!`, until the returned guard is dropped. Without
+/// the `tracing` feature, this does nothing and the fields are not evaluated.
+macro_rules! span {
+    ($level:ident, $name:literal $(, $($fields:tt)*)?) => {{
+        #[cfg(feature = "tracing")]
+        {
+            ::tracing::span!(::tracing::Level::$level, $name $(, $($fields)*)?).entered()
+        }
+        #[cfg(not(feature = "tracing"))]
+        {
+            $crate::spans::NoSpan
+        }
+    }};
+}
+
+pub(crate) use span;
+
+/// The guard of a span without the `tracing` feature.
+#[cfg(not(feature = "tracing"))]
+pub(crate) struct NoSpan;
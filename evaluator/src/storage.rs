@@ -1,7 +1,10 @@
 //! Storage for state variables with extra functionality that depends on the
 //! state machines' state.
 
+use crate::counters;
 use crate::ir::QuintName;
+use crate::sharing;
+use crate::symbol::Symbol;
 use crate::value::{ImmutableMap, Value};
 use std::{cell::RefCell, rc::Rc};
 
@@ -10,12 +13,12 @@ use std::{cell::RefCell, rc::Rc};
 /// the trace.
 #[derive(Clone)]
 pub struct VariableRegister {
-    pub name: QuintName,
+    pub name: Symbol,
     pub value: Option<Value>,
 }
 
-/// While `vars` values are immutable during evaluation and are only updated in `shift_vars()`, the remaining
-/// stored values can be changed, and sometimes we need to revert those changes. For example, in:
+/// While the current state is immutable during evaluation and is only updated in `shift_vars()`, the
+/// primed values can be changed, and sometimes we need to revert those changes. For example, in:
 /// ```quint
 /// all {
 ///  x' = x + 1,
@@ -25,21 +28,210 @@ pub struct VariableRegister {
 /// The second expression makes it so the `all` is false, and we need to revert back to the state before
 /// we evaluated `x' = x + 1`.
 ///
-/// That's where snapshots are used
+/// That's where snapshots are used. As primed values are kept in a persistent
+/// map, taking and restoring a snapshot is O(1).
 #[derive(Clone)]
 pub struct Snapshot {
-    pub next_vars: ImmutableMap<QuintName, VariableRegister>,
+    pub primed: ImmutableMap<QuintName, Value>,
     // TODO:
     // nondet_picks
     // action_taken
 }
 
+/// A snapshot of both the current and the next state. While [`Snapshot`] is
+/// used to revert assignments made during the evaluation of a single step, this
+/// is used to go back to a previous point of the execution altogether (i.e. for
+/// backtracking).
+#[derive(Clone)]
+pub struct StateSnapshot {
+    pub state: ImmutableMap<Symbol, Value>,
+    pub primed: ImmutableMap<QuintName, Value>,
+}
+
+/// The values of state variables, with prime/commit semantics.
+///
+/// Assignments (`x' = e`) prime a value for the next state, and [`commit`]
+/// makes the primed values current. The current state is a persistent map
+/// from variable names to values, so consecutive states share the values of
+/// the variables that were not changed, and committing a step only costs as
+/// much as the number of variables that were assigned.
+///
+/// Compiled expressions read variables through registers, which are resolved
+/// at compile time, and kept in sync with the current state on each commit.
+///
+/// [`commit`]: StateStorage::commit
+#[derive(Default, Clone)]
+pub struct StateStorage {
+    // Registers for the values in the current state, to be read during
+    // evaluation. Keyed by the variable key (id and namespaces).
+    registers: ImmutableMap<QuintName, Rc<RefCell<VariableRegister>>>,
+    // The current state, keyed by the variable name (as shown in traces).
+    state: ImmutableMap<Symbol, Value>,
+    // Values for the next state, keyed by the variable key. Only variables
+    // assigned in the current step are present.
+    primed: ImmutableMap<QuintName, Value>,
+}
+
+impl StateStorage {
+    /// Whether a variable with the given key was registered.
+    pub fn contains(&self, key: &QuintName) -> bool {
+        self.registers.contains_key(key)
+    }
+
+    /// Get the register for reading the variable with the given key, creating
+    /// it if it doesn't exist yet.
+    pub fn register(&mut self, key: QuintName, name: QuintName) -> Rc<RefCell<VariableRegister>> {
+        self.registers
+            .entry(key)
+            .or_insert_with(|| {
+                Rc::new(RefCell::new(VariableRegister {
+                    name: name.into(),
+                    value: None,
+                }))
+            })
+            .clone()
+    }
+
+    /// Set the value of the variable with the given key for the next state.
+    pub fn prime(&mut self, key: QuintName, value: Value) {
+        self.primed.insert(key, value);
+    }
+
+    /// Get the value primed for the next state for the variable with the given
+    /// name, if any.
+    pub fn primed_value(&self, name: &str) -> Option<&Value> {
+        self.registers
+            .iter()
+            .find(|(_, register)| register.borrow().name == name)
+            .and_then(|(key, _)| self.primed.get(key))
+    }
+
+    /// Keep the values of the variables that were not assigned in the current
+    /// step, priming them with their current values.
+    pub fn keep_unassigned(&mut self) {
+        if self.primed.len() == self.registers.len() {
+            return;
+        }
+        for (key, register) in self.registers.iter() {
+            if self.primed.contains_key(key) {
+                continue;
+            }
+            if let Some(value) = &register.borrow().value {
+                self.primed.insert(key.clone(), value.clone());
+            }
+        }
+    }
+
+    /// Make the primed values the current state. Variables that were not
+    /// assigned become unset. If enabled, counts how much of the new values is
+    /// shared with the current ones (see [`crate::sharing`]).
+    pub fn commit(&mut self) {
+        let primed = std::mem::take(&mut self.primed);
+
+        if primed.len() < self.registers.len() {
+            // Some variables were not assigned in this step. This is not
+            // expected for well-formed steps, so we don't mind going over all
+            // of the variables here.
+            for (key, register) in self.registers.iter() {
+                if !primed.contains_key(key) {
+                    let mut register = register.borrow_mut();
+                    register.value = None;
+                    self.state.remove(&register.name);
+                }
+            }
+        }
+
+        let measure_sharing = counters::is_sharing_enabled();
+        for (key, value) in primed {
+            if let Some(register) = self.registers.get(&key) {
+                let mut register = register.borrow_mut();
+                if measure_sharing {
+                    let sharing = sharing::measure(&value, self.state.get(&register.name));
+                    counters::record(|c| {
+                        c.bytes_shared += sharing.shared;
+                        c.bytes_copied += sharing.copied;
+                    });
+                }
+                self.state.insert(register.name.clone(), value.clone());
+                register.value = Some(value);
+            }
+        }
+    }
+
+    /// Build a record with the current state variables' values, to be used in traces.
+    pub fn as_record(&self) -> Value {
+        // TODO: add nondet picks and action taken
+        Value::Record(self.state.clone())
+    }
+
+    /// Build a record with the primed values, i.e. the next state as it would
+    /// be after a commit.
+    pub fn primed_as_record(&self) -> Value {
+        let fields = self.primed.iter().filter_map(|(key, value)| {
+            let register = self.registers.get(key)?;
+            let name = register.borrow().name.clone();
+            Some((name, value.clone()))
+        });
+
+        Value::Record(fields.collect())
+    }
+
+    pub fn take_snapshot(&self) -> Snapshot {
+        Snapshot {
+            primed: self.primed.clone(),
+        }
+    }
+
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.primed = snapshot.primed.clone();
+    }
+
+    /// Take a snapshot of both the current state and the primed values.
+    pub fn take_state_snapshot(&self) -> StateSnapshot {
+        StateSnapshot {
+            state: self.state.clone(),
+            primed: self.primed.clone(),
+        }
+    }
+
+    /// Restore both the current state and the primed values from a snapshot,
+    /// updating the registers accordingly.
+    pub fn restore_state(&mut self, snapshot: &StateSnapshot) {
+        self.state = snapshot.state.clone();
+        self.primed = snapshot.primed.clone();
+        self.sync_registers();
+    }
+
+    /// Set the current state and the primed values from records, i.e. from
+    /// two consecutive states of a trace. Fields of `next` that don't
+    /// correspond to a registered variable are ignored.
+    pub fn load(&mut self, state: &Value, next: &Value) {
+        let next = next.as_record_map();
+
+        self.state = state.as_record_map().clone();
+        self.primed = self
+            .registers
+            .iter()
+            .filter_map(|(key, register)| {
+                let value = next.get(&register.borrow().name)?;
+                Some((key.clone(), value.clone()))
+            })
+            .collect();
+        self.sync_registers();
+    }
+
+    fn sync_registers(&self) {
+        for register in self.registers.values() {
+            let mut register = register.borrow_mut();
+            register.value = self.state.get(&register.name).cloned();
+        }
+    }
+}
+
 #[derive(Default, Clone)]
 pub struct Storage {
-    // Registers for the values in the current state, to be read during evaluation
-    pub vars: ImmutableMap<QuintName, Rc<RefCell<VariableRegister>>>,
-    // Registers for values in the next state, to be written to during evaluation
-    pub next_vars: ImmutableMap<QuintName, Rc<RefCell<VariableRegister>>>,
+    // The values of state variables, on the current and next states
+    pub state: StateStorage,
     // A list of caches to clear after every step, used to cache values during a single state only.
     pub caches_to_clear: Vec<Rc<RefCell<Option<Value>>>>,
     // TODO:
@@ -48,49 +240,54 @@ pub struct Storage {
 }
 
 impl Storage {
-    /// Move the values in `next_vars` registries to `vars` registries, and
-    /// clear the caches.
+    /// Commit the primed values to the current state, and clear the caches.
     pub fn shift_vars(&mut self) {
-        for (key, register_for_current) in self.vars.iter() {
-            if let Some(register_for_next) = self.next_vars.get(key) {
-                let mut current = register_for_current.borrow_mut();
-                let mut next = register_for_next.borrow_mut();
-                current.value = next.value.take();
-            }
-        }
+        self.state.commit();
         self.clear_caches();
+        counters::record(|c| c.steps += 1);
     }
 
     /// Build a record with the current state variables' values, to be used in traces.
     pub fn as_record(&self) -> Value {
-        let map = self.vars.values().filter_map(|register| {
-            let reg = register.borrow().clone();
-            reg.value.map(|v| (reg.name, v))
-        });
+        self.state.as_record()
+    }
 
-        // TODO: add nondet picks and action taken
-        Value::Record(ImmutableMap::from_iter(map))
+    /// Build a record with the next state variables' values, see [`StateStorage::primed_as_record`].
+    pub fn primed_as_record(&self) -> Value {
+        self.state.primed_as_record()
     }
 
     pub fn take_snapshot(&self) -> Snapshot {
-        Snapshot {
-            next_vars: self
-                .next_vars
-                .iter()
-                .map(|(k, v)| (k.clone(), v.borrow().clone()))
-                .collect(),
-        }
+        self.state.take_snapshot()
     }
 
     pub fn restore(&mut self, snapshot: &Snapshot) {
-        self.next_vars.iter().for_each(|(k, v)| {
-            if let Some(next) = snapshot.next_vars.get(k) {
-                v.borrow_mut().value = next.value.clone();
-            }
-        });
+        self.state.restore(snapshot)
+    }
+
+    /// Take a snapshot of the values in both the current and next states.
+    pub fn take_state_snapshot(&self) -> StateSnapshot {
+        self.state.take_state_snapshot()
+    }
+
+    /// Restore the values in both the current and next states from a
+    /// snapshot, and clear the caches, as they may refer to a different state.
+    ///
+    /// Variables created after the snapshot was taken become unset.
+    pub fn restore_state(&mut self, snapshot: &StateSnapshot) {
+        self.state.restore_state(snapshot);
+        self.clear_caches();
+    }
+
+    /// Set the current and next states from records, see
+    /// [`StateStorage::load`], and clear the caches.
+    pub fn load(&mut self, state: &Value, next: &Value) {
+        self.state.load(state, next);
+        self.clear_caches();
     }
 
-    fn clear_caches(&mut self) {
+    /// Clear the cached values of definitions that depend on the state.
+    pub fn clear_caches(&mut self) {
         for cache in self.caches_to_clear.iter() {
             *cache.borrow_mut() = None;
         }
@@ -0,0 +1,272 @@
+//! A store of the traces of a simulation in an SQLite database, to query them
+//! after the fact (e.g. "all states where x > 10") over large campaigns,
+//! without running them again. This is only available with the `sqlite`
+//! feature.
+//!
+//! The store is a [`SimulationHooks`], so every sample is written as it ends.
+//! Several simulations can be written to the same database, each as a run.
+//! The tables are:
+//!  - `runs(id, source, created)`;
+//!  - `samples(run, sample, length, violation)`, where `violation` is the
+//!    index of the violated invariant, if any (see
+//!    [`SimulationHooks::on_violation`]);
+//!  - `states(run, sample, step, fingerprint, action, changes)`, with the
+//!    variables that changed on the step in ITF (all of them for the initial
+//!    state), and the action taken if it was recorded (see [`crate::mbt`]);
+//!  - `variables(run, sample, step, name, value, number)`, with the value of
+//!    each variable that changed on the step in ITF, and as a `number` too
+//!    when it is an integer.
+//!
+//! States of long runs are mostly the same from one step to the next, so only
+//! their changes are stored (see [`crate::trace::Delta`]). The
+//! `state_variables` view has the value of every variable on every state,
+//! i.e. the states where `x > 10` are found with:
+//!
+//! ```sql
+//! SELECT states.* FROM state_variables JOIN states USING (run, sample, step)
+//! WHERE name = 'x' AND number > 10
+//! ```
+//!
+//! The version of the schema is kept in the `user_version` of the database
+//! (see [`SCHEMA_VERSION`]). Databases from before states were stored as
+//! deltas are migrated when opened, and the ones written by a newer version of
+//! the evaluator are refused.
+
+use crate::simulator::SimulationHooks;
+use crate::trace::{Delta, Trace};
+use crate::value::{ImmutableMap, Value};
+use rusqlite::{ffi, params, Connection};
+use std::path::Path;
+
+/// The version of the schema. Version 1 stored every variable of every state,
+/// with states in full in a `state` column, and didn't record its version.
+pub const SCHEMA_VERSION: i64 = 2;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS runs (
+    id INTEGER PRIMARY KEY,
+    source TEXT NOT NULL,
+    created TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS samples (
+    run INTEGER NOT NULL REFERENCES runs (id),
+    sample INTEGER NOT NULL,
+    length INTEGER NOT NULL,
+    violation INTEGER,
+    PRIMARY KEY (run, sample)
+);
+CREATE TABLE IF NOT EXISTS states (
+    run INTEGER NOT NULL,
+    sample INTEGER NOT NULL,
+    step INTEGER NOT NULL,
+    fingerprint INTEGER NOT NULL,
+    action TEXT,
+    changes TEXT NOT NULL,
+    PRIMARY KEY (run, sample, step),
+    FOREIGN KEY (run, sample) REFERENCES samples (run, sample)
+);
+CREATE INDEX IF NOT EXISTS states_fingerprint ON states (fingerprint);
+CREATE TABLE IF NOT EXISTS variables (
+    run INTEGER NOT NULL,
+    sample INTEGER NOT NULL,
+    step INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    value TEXT NOT NULL,
+    number INTEGER,
+    FOREIGN KEY (run, sample, step) REFERENCES states (run, sample, step)
+);
+CREATE INDEX IF NOT EXISTS variables_number ON variables (name, number);
+CREATE INDEX IF NOT EXISTS variables_step ON variables (run, sample, name, step);
+CREATE VIEW IF NOT EXISTS state_variables AS
+SELECT states.run, states.sample, states.step, variables.name, variables.value, variables.number
+FROM states JOIN variables ON variables.run = states.run AND variables.sample = states.sample
+WHERE variables.step = (
+    SELECT MAX(latest.step) FROM variables AS latest
+    WHERE latest.run = states.run AND latest.sample = states.sample
+        AND latest.name = variables.name AND latest.step <= states.step
+);
+";
+
+/// Migrate from version 1, keeping only the variables that changed on each
+/// step, and the changes of each state instead of the whole state.
+const MIGRATE_TO_DELTAS: &str = "
+CREATE TEMP TABLE unchanged AS
+SELECT variables.rowid AS id FROM variables JOIN variables AS previous
+    ON previous.run = variables.run AND previous.sample = variables.sample
+    AND previous.name = variables.name AND previous.step = variables.step - 1
+WHERE previous.value = variables.value;
+DELETE FROM variables WHERE rowid IN (SELECT id FROM unchanged);
+DROP TABLE unchanged;
+ALTER TABLE states RENAME COLUMN state TO changes;
+UPDATE states SET changes = (
+    SELECT json_group_object(variables.name, json(variables.value)) FROM variables
+    WHERE variables.run = states.run AND variables.sample = states.sample
+        AND variables.step = states.step
+);
+";
+
+/// Writes the samples of a simulation to an SQLite database, as a new run.
+pub struct TraceStore {
+    connection: Connection,
+    run: i64,
+    /// The number of the current sample, counting from 1 in the order they
+    /// are run
+    sample: usize,
+    /// The invariant violated in the current sample, if any
+    violation: Option<usize>,
+    /// The first error when writing, reported by [`TraceStore::finish`], as
+    /// hooks can't fail
+    error: Option<rusqlite::Error>,
+}
+
+impl TraceStore {
+    /// Open the database at `path`, creating it if needed, and start a new
+    /// run for the spec in the `source` file.
+    pub fn open(path: &Path, source: &str) -> rusqlite::Result<Self> {
+        let mut connection = Connection::open(path)?;
+        migrate(&mut connection)?;
+        connection.execute(
+            "INSERT INTO runs (source, created) VALUES (?1, ?2)",
+            params![source, chrono::offset::Local::now().to_rfc3339()],
+        )?;
+        let run = connection.last_insert_rowid();
+
+        Ok(Self {
+            connection,
+            run,
+            sample: 0,
+            violation: None,
+            error: None,
+        })
+    }
+
+    /// The id of the run in the `runs` table.
+    pub fn run(&self) -> i64 {
+        self.run
+    }
+
+    /// Finish writing, returning the first error that happened, if any.
+    pub fn finish(self) -> rusqlite::Result<()> {
+        match self.error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn write_sample(&mut self, trace: &Trace) -> rusqlite::Result<()> {
+        let transaction = self.connection.transaction()?;
+        let sample = self.sample as i64;
+        transaction.execute(
+            "INSERT INTO samples (run, sample, length, violation) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                self.run,
+                sample,
+                trace.states.len() as i64,
+                self.violation.map(|i| i as i64)
+            ],
+        )?;
+
+        {
+            let mut insert_state = transaction.prepare(
+                "INSERT INTO states (run, sample, step, fingerprint, action, changes)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+            let mut insert_variable = transaction.prepare(
+                "INSERT INTO variables (run, sample, step, name, value, number)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+            )?;
+
+            let mut previous = None;
+            for (step, state) in trace.states.iter().enumerate() {
+                let delta = Delta::new(&state.vars, previous);
+                previous = Some(&state.vars);
+                let action = state
+                    .meta
+                    .as_ref()
+                    .map(|meta| meta.action_taken.clone().unwrap_or_default());
+                let fields = delta.fields();
+                let changes = fields
+                    .iter()
+                    .map(|&(name, value)| (name.clone(), value.clone()))
+                    .collect::<ImmutableMap<_, _>>();
+                insert_state.execute(params![
+                    self.run,
+                    sample,
+                    step as i64,
+                    // SQLite integers are signed
+                    state.vars.fingerprint() as i64,
+                    action,
+                    Value::Record(changes).to_itf().to_string()
+                ])?;
+
+                for (name, value) in fields {
+                    let number = match value {
+                        Value::Int(n) => Some(*n),
+                        _ => None,
+                    };
+                    insert_variable.execute(params![
+                        self.run,
+                        sample,
+                        step as i64,
+                        name.as_str(),
+                        value.to_itf().to_string(),
+                        number
+                    ])?;
+                }
+            }
+        }
+
+        transaction.commit()
+    }
+}
+
+/// Create the tables, or migrate the existing ones to the current version of
+/// the schema.
+fn migrate(connection: &mut Connection) -> rusqlite::Result<()> {
+    let transaction = connection.transaction()?;
+    let mut version: i64 =
+        transaction.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    if version == 0 {
+        // Either a new database, or one of version 1, which had no version
+        let tables: i64 = transaction.query_row(
+            "SELECT COUNT(*) FROM sqlite_master WHERE type = 'table' AND name = 'states'",
+            [],
+            |row| row.get(0),
+        )?;
+        version = if tables > 0 { 1 } else { SCHEMA_VERSION };
+    }
+    if version > SCHEMA_VERSION {
+        return Err(rusqlite::Error::SqliteFailure(
+            ffi::Error::new(ffi::SQLITE_ERROR),
+            Some(format!(
+                "The trace store has version {version} of the schema, written by a newer \
+                 version of the evaluator, which supports up to version {SCHEMA_VERSION}"
+            )),
+        ));
+    }
+
+    // Before migrating, for the migration to use the new indices
+    transaction.execute_batch(SCHEMA)?;
+    if version == 1 {
+        transaction.execute_batch(MIGRATE_TO_DELTAS)?;
+    }
+    transaction.pragma_update(None, "user_version", SCHEMA_VERSION)?;
+    transaction.commit()
+}
+
+impl SimulationHooks for TraceStore {
+    fn on_sample_end(&mut self, trace: &Trace) {
+        self.sample += 1;
+        if self.error.is_none() {
+            if let Err(e) = self.write_sample(trace) {
+                self.error = Some(e);
+            }
+        }
+        self.violation = None;
+    }
+
+    fn on_violation(&mut self, _trace: &Trace, invariant: usize) {
+        // Only the first violation of each sample is recorded
+        self.violation.get_or_insert(invariant);
+    }
+}
@@ -0,0 +1,231 @@
+//! Interned names, for the field names of records and the labels of variants
+//! in values.
+//!
+//! Records are hashed and compared all the time (i.e. to put states in sets
+//! of visited states), and hashing or comparing their field names as strings
+//! is a good part of that. A [`Symbol`] is a pointer to the one copy of its
+//! name, so symbols are compared by address, and hashed by writing a hash of
+//! the name computed once, when it is interned.
+//!
+//! The hash only depends on the name, so values hash the same way in every
+//! run and on every thread, and symbols are ordered by their names, so the
+//! order of values (see [`crate::value::Value`]) is the same as with strings.
+//!
+//! Names are freed when no symbol refers to them anymore, so a long-running
+//! process (i.e. the server, the gRPC service or the REPL) only keeps the
+//! names of the values it still holds, and not those of every spec, input or
+//! ITF trace it has loaded. [`interned`] tells how much memory names take,
+//! which [`crate::metrics`] reports.
+
+use crate::ir::QuintName;
+use fxhash::FxHashMap;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, Weak};
+
+/// An interned name, see the module documentation.
+#[derive(Clone)]
+pub struct Symbol(Arc<Interned>);
+
+struct Interned {
+    name: Box<str>,
+    hash: u64,
+}
+
+/// All the interned names, by their hashes, shared by all threads so that a
+/// name is always the same symbol while it's in use.
+static SYMBOLS: Mutex<Option<Symbols>> = Mutex::new(None);
+
+#[derive(Default)]
+struct Symbols {
+    by_hash: FxHashMap<u64, Vec<Weak<Interned>>>,
+    // How many names there are, including the freed ones not removed yet
+    len: usize,
+    // How many names there can be before the freed ones are removed
+    capacity: usize,
+}
+
+/// The names interned before freed ones are removed for the first time.
+const INITIAL_CAPACITY: usize = 1024;
+
+/// The symbols looked up on a thread that it keeps, at most.
+const CACHE_SIZE: usize = 4096;
+
+thread_local! {
+    // The symbols already looked up on this thread, by the hashes of their
+    // names, so that most lookups don't need the lock
+    static CACHE: RefCell<FxHashMap<u64, Weak<Interned>>> = RefCell::default();
+}
+
+impl Symbols {
+    fn get(&self, name: &str, hash: u64) -> Option<Symbol> {
+        self.by_hash
+            .get(&hash)?
+            .iter()
+            .filter_map(Weak::upgrade)
+            .find(|interned| *interned.name == *name)
+            .map(Symbol)
+    }
+
+    fn insert(&mut self, symbol: &Symbol) {
+        if self.len >= self.capacity {
+            // Remove the names freed since the last time, and wait until
+            // there are twice as many names as are left before doing it again
+            self.by_hash.retain(|_, interned| {
+                interned.retain(|i| i.strong_count() > 0);
+                !interned.is_empty()
+            });
+            self.len = self.by_hash.values().map(Vec::len).sum();
+            self.capacity = (2 * self.len).max(INITIAL_CAPACITY);
+        }
+        self.len += 1;
+        self.by_hash
+            .entry(symbol.0.hash)
+            .or_default()
+            .push(Arc::downgrade(&symbol.0));
+    }
+}
+
+impl Symbol {
+    /// The symbol of a name, interning it if it's new.
+    pub fn intern(name: &str) -> Symbol {
+        let hash = fxhash::hash64(name);
+        let cached = CACHE.with(|cache| cache.borrow().get(&hash).and_then(Weak::upgrade));
+        if let Some(interned) = cached.filter(|interned| *interned.name == *name) {
+            return Symbol(interned);
+        }
+
+        let mut symbols = SYMBOLS.lock().unwrap();
+        let symbols = symbols.get_or_insert_with(Symbols::default);
+        let symbol = symbols.get(name, hash).unwrap_or_else(|| {
+            let symbol = Symbol(Arc::new(Interned {
+                name: name.into(),
+                hash,
+            }));
+            symbols.insert(&symbol);
+            symbol
+        });
+        CACHE.with(|cache| {
+            let mut cache = cache.borrow_mut();
+            if cache.len() >= CACHE_SIZE {
+                cache.clear();
+            }
+            cache.insert(hash, Arc::downgrade(&symbol.0));
+        });
+        symbol
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0.name
+    }
+}
+
+/// The number of interned names in use, and their total length in bytes.
+pub fn interned() -> (usize, usize) {
+    let symbols = SYMBOLS.lock().unwrap();
+    symbols.as_ref().map_or((0, 0), |symbols| {
+        symbols
+            .by_hash
+            .values()
+            .flatten()
+            .filter_map(Weak::upgrade)
+            .fold((0, 0), |(names, bytes), interned| {
+                (names + 1, bytes + interned.name.len())
+            })
+    })
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0.name
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Symbol {}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        *self.0.name == *other
+    }
+}
+
+impl PartialEq<&str> for Symbol {
+    fn eq(&self, other: &&str) -> bool {
+        *self.0.name == **other
+    }
+}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        state.write_u64(self.0.hash)
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        if self == other {
+            Ordering::Equal
+        } else {
+            self.0.name.cmp(&other.0.name)
+        }
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0.name)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.0.name, f)
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Symbol::intern(&name)
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(name: &String) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<&QuintName> for Symbol {
+    fn from(name: &QuintName) -> Self {
+        Symbol::intern(name)
+    }
+}
+
+impl From<QuintName> for Symbol {
+    fn from(name: QuintName) -> Self {
+        Symbol::intern(&name)
+    }
+}
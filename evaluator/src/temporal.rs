@@ -0,0 +1,405 @@
+//! Checking of temporal properties over finite traces.
+//!
+//! Temporal properties are about infinite executions, while simulation
+//! produces finite traces. We follow the usual approach from runtime
+//! verification and give one of four verdicts: a trace can definitely satisfy
+//! or violate a property (i.e. when `always(p)` fails on some state, no
+//! extension of the trace can fix it), or only presumably do so, when an
+//! extension of the trace could still change the verdict (i.e. `always(p)`
+//! holding on every state of the trace).
+//!
+//! Past the end of the trace, the last state is assumed to repeat forever
+//! (stuttering), and anything that depends on that is only presumable. This
+//! way, `always(x' >= x)` presumably holds on a trace where `x` never
+//! decreases.
+//!
+//! Properties are made of state (or action) predicates, combined with
+//! `always`, `eventually`, `next` and the boolean operators. User-defined
+//! temporal operators are supported as long as their parameters are
+//! formulas, so leads-to patterns like
+//!
+//! ```quint
+//! temporal leadsTo(p, q) = always(p implies eventually(q))
+//! ```
+//!
+//! can be checked. Fairness conditions and `enabled` are not supported, as they
+//! don't have a meaning over a single finite trace.
+
+use crate::evaluator::{CompiledExpr, Env, Interpreter};
+use crate::ir::{
+    ErrorKind, LookupDefinition, LookupTable, OpQualifier, QuintDeclaration, QuintError, QuintEx,
+    QuintId,
+};
+use crate::value::Value;
+use fxhash::{FxHashMap, FxHashSet};
+use std::fmt;
+
+/// The verdict of a temporal property on a finite trace, from worst to best.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Verdict {
+    /// The trace violates the property, no matter how it is extended.
+    Violated,
+    /// The trace violates the property, but an extension of it might not.
+    PresumablyViolated,
+    /// The trace satisfies the property, but an extension of it might not.
+    PresumablySatisfied,
+    /// The trace satisfies the property, no matter how it is extended.
+    Satisfied,
+}
+
+impl Verdict {
+    fn from_bool(b: bool) -> Self {
+        if b {
+            Verdict::Satisfied
+        } else {
+            Verdict::Violated
+        }
+    }
+
+    fn not(self) -> Self {
+        match self {
+            Verdict::Violated => Verdict::Satisfied,
+            Verdict::PresumablyViolated => Verdict::PresumablySatisfied,
+            Verdict::PresumablySatisfied => Verdict::PresumablyViolated,
+            Verdict::Satisfied => Verdict::Violated,
+        }
+    }
+
+    /// Weaken a definite verdict, for when it depends on how the trace ends.
+    fn presumably(self) -> Self {
+        match self {
+            Verdict::Violated => Verdict::PresumablyViolated,
+            Verdict::Satisfied => Verdict::PresumablySatisfied,
+            _ => self,
+        }
+    }
+
+    /// Whether the trace satisfies the property, definitely or presumably.
+    pub fn holds(&self) -> bool {
+        *self >= Verdict::PresumablySatisfied
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Verdict::Violated => write!(f, "violated"),
+            Verdict::PresumablyViolated => write!(f, "presumably violated"),
+            Verdict::PresumablySatisfied => write!(f, "presumably satisfied"),
+            Verdict::Satisfied => write!(f, "satisfied"),
+        }
+    }
+}
+
+/// The verdicts of a temporal property, one for each of the checked traces.
+pub struct TemporalResult {
+    pub name: String,
+    pub verdicts: Vec<Verdict>,
+}
+
+/// A temporal property, with its predicates compiled.
+#[derive(Clone)]
+enum Formula {
+    Predicate {
+        expr: CompiledExpr,
+        // Whether the predicate reads the next state, so its value on the
+        // last state of a trace depends on the stuttering assumption
+        uses_next: bool,
+    },
+    Not(Box<Formula>),
+    And(Vec<Formula>),
+    Or(Vec<Formula>),
+    Implies(Box<Formula>, Box<Formula>),
+    Iff(Box<Formula>, Box<Formula>),
+    Next(Box<Formula>),
+    Always(Box<Formula>),
+    Eventually(Box<Formula>),
+}
+
+/// Operators that only make sense in temporal properties.
+pub(crate) const TEMPORAL_OPS: [&str; 7] = [
+    "always",
+    "eventually",
+    "weakFair",
+    "strongFair",
+    "enabled",
+    "orKeep",
+    "mustChange",
+];
+
+/// Temporal operators that can't be checked over a finite trace, as they are
+/// about the actions that could be taken rather than the ones that were.
+pub(crate) const UNSUPPORTED_OPS: [&str; 5] =
+    ["weakFair", "strongFair", "enabled", "orKeep", "mustChange"];
+
+/// A temporal property compiled for checking over traces.
+pub struct TemporalChecker {
+    env: Env,
+    formula: Formula,
+}
+
+impl TemporalChecker {
+    /// Compile a temporal property for checking. Returns an error if it uses
+    /// operators that are not supported over finite traces.
+    pub fn new(table: &LookupTable, property: &QuintEx) -> Result<Self, QuintError> {
+        let mut interpreter = Interpreter::new(table);
+        let env = Env::new(interpreter.var_storage.clone());
+
+        let formula = FormulaBuilder {
+            interpreter: &mut interpreter,
+            table,
+            params: FxHashMap::default(),
+        }
+        .build(property)?;
+
+        Ok(Self { env, formula })
+    }
+
+    /// Check the property over a trace, given by its states.
+    pub fn check(&mut self, states: &[Value]) -> Result<Verdict, QuintError> {
+        if states.is_empty() {
+            return Err(QuintError::new(
+                ErrorKind::Unsupported,
+                "Cannot check a temporal property on an empty trace",
+            ));
+        }
+
+        eval(&self.formula, &mut self.env, states, 0)
+    }
+}
+
+/// Evaluate a formula on the state at index `i` of a non-empty trace.
+fn eval(
+    formula: &Formula,
+    env: &mut Env,
+    states: &[Value],
+    i: usize,
+) -> Result<Verdict, QuintError> {
+    let last = states.len() - 1;
+
+    let verdict = match formula {
+        Formula::Predicate { expr, uses_next } => {
+            // Stutter on the last state
+            let next = &states[(i + 1).min(last)];
+            env.var_storage.borrow_mut().load(&states[i], next);
+
+            let verdict = Verdict::from_bool(expr.execute(env)?.try_bool()?);
+            if i == last && *uses_next {
+                verdict.presumably()
+            } else {
+                verdict
+            }
+        }
+        Formula::Not(f) => eval(f, env, states, i)?.not(),
+        Formula::And(fs) => {
+            let mut verdict = Verdict::Satisfied;
+            for f in fs {
+                verdict = verdict.min(eval(f, env, states, i)?);
+                if verdict == Verdict::Violated {
+                    break;
+                }
+            }
+            verdict
+        }
+        Formula::Or(fs) => {
+            let mut verdict = Verdict::Violated;
+            for f in fs {
+                verdict = verdict.max(eval(f, env, states, i)?);
+                if verdict == Verdict::Satisfied {
+                    break;
+                }
+            }
+            verdict
+        }
+        Formula::Implies(lhs, rhs) => {
+            let lhs = eval(lhs, env, states, i)?.not();
+            if lhs == Verdict::Satisfied {
+                lhs
+            } else {
+                lhs.max(eval(rhs, env, states, i)?)
+            }
+        }
+        Formula::Iff(lhs, rhs) => {
+            let lhs = eval(lhs, env, states, i)?;
+            let rhs = eval(rhs, env, states, i)?;
+            lhs.not().max(rhs).min(rhs.not().max(lhs))
+        }
+        Formula::Next(f) => {
+            if i == last {
+                eval(f, env, states, i)?.presumably()
+            } else {
+                eval(f, env, states, i + 1)?
+            }
+        }
+        Formula::Always(f) => {
+            let mut verdict = Verdict::Satisfied;
+            for j in i..=last {
+                verdict = verdict.min(eval(f, env, states, j)?);
+                if verdict == Verdict::Violated {
+                    break;
+                }
+            }
+            // The trace could still be extended with a violating state
+            if verdict == Verdict::Satisfied {
+                Verdict::PresumablySatisfied
+            } else {
+                verdict
+            }
+        }
+        Formula::Eventually(f) => {
+            let mut verdict = Verdict::Violated;
+            for j in i..=last {
+                verdict = verdict.max(eval(f, env, states, j)?);
+                if verdict == Verdict::Satisfied {
+                    break;
+                }
+            }
+            // The trace could still be extended with a satisfying state
+            if verdict == Verdict::Violated {
+                Verdict::PresumablyViolated
+            } else {
+                verdict
+            }
+        }
+    };
+
+    Ok(verdict)
+}
+
+/// Builds a [`Formula`] from a Quint expression, inlining temporal
+/// definitions and the arguments of user-defined temporal operators.
+struct FormulaBuilder<'a, 'b> {
+    interpreter: &'b mut Interpreter<'a>,
+    table: &'a LookupTable,
+    // Formulas given as arguments to the temporal operator being inlined,
+    // by parameter id
+    params: FxHashMap<QuintId, Formula>,
+}
+
+impl FormulaBuilder<'_, '_> {
+    fn build(&mut self, expr: &QuintEx) -> Result<Formula, QuintError> {
+        if !self.is_temporal(expr) {
+            return Ok(Formula::Predicate {
+                expr: self.interpreter.compile(expr),
+                uses_next: self.uses_next(expr, &mut FxHashSet::default()),
+            });
+        }
+
+        match expr {
+            QuintEx::QuintName { id, .. } => match self.table.get(id) {
+                Some(LookupDefinition::Param(param)) => Ok(self.params[&param.id].clone()),
+                Some(LookupDefinition::Definition(QuintDeclaration::QuintOpDef(def))) => {
+                    self.build(&def.expr)
+                }
+                _ => unreachable!("Only parameters and definitions can be temporal"),
+            },
+            QuintEx::QuintApp { id, opcode, args } => {
+                if let Some(LookupDefinition::Definition(QuintDeclaration::QuintOpDef(def))) =
+                    self.table.get(id)
+                {
+                    return self.build_application(&def.expr, args);
+                }
+
+                let arg = |builder: &mut Self, i: usize| builder.build(&args[i]).map(Box::new);
+                match opcode.as_str() {
+                    "always" => Ok(Formula::Always(arg(self, 0)?)),
+                    "eventually" => Ok(Formula::Eventually(arg(self, 0)?)),
+                    "next" => Ok(Formula::Next(arg(self, 0)?)),
+                    "not" => Ok(Formula::Not(arg(self, 0)?)),
+                    "implies" => Ok(Formula::Implies(arg(self, 0)?, arg(self, 1)?)),
+                    "iff" => Ok(Formula::Iff(arg(self, 0)?, arg(self, 1)?)),
+                    "and" => Ok(Formula::And(self.build_all(args)?)),
+                    "or" => Ok(Formula::Or(self.build_all(args)?)),
+                    _ => Err(unsupported(opcode).with_reference(*id)),
+                }
+            }
+            QuintEx::QuintLet { id, .. } => Err(unsupported("let").with_reference(*id)),
+            _ => unreachable!("Literals and lambdas are not temporal"),
+        }
+    }
+
+    fn build_all(&mut self, exprs: &[QuintEx]) -> Result<Vec<Formula>, QuintError> {
+        exprs.iter().map(|e| self.build(e)).collect()
+    }
+
+    /// Inline the application of a user-defined temporal operator, binding
+    /// its parameters to the formulas given as arguments.
+    fn build_application(&mut self, op: &QuintEx, args: &[QuintEx]) -> Result<Formula, QuintError> {
+        let QuintEx::QuintLambda { params, expr, .. } = op else {
+            return Err(unsupported("higher-order temporal operators"));
+        };
+
+        let args = self.build_all(args)?;
+        let outer = self.params.clone();
+        self.params.extend(params.iter().map(|p| p.id).zip(args));
+        let formula = self.build(expr);
+        self.params = outer;
+
+        formula
+    }
+
+    /// Whether the expression needs to be handled as a temporal formula,
+    /// instead of being evaluated as a predicate on a state.
+    fn is_temporal(&self, expr: &QuintEx) -> bool {
+        match expr {
+            QuintEx::QuintName { id, .. } => match self.table.get(id) {
+                Some(LookupDefinition::Param(param)) => self.params.contains_key(&param.id),
+                Some(LookupDefinition::Definition(QuintDeclaration::QuintOpDef(def))) => {
+                    def.qualifier == OpQualifier::Temporal
+                }
+                _ => false,
+            },
+            QuintEx::QuintApp { id, opcode, args } => {
+                TEMPORAL_OPS.contains(&opcode.as_str())
+                    || matches!(
+                        self.table.get(id),
+                        Some(LookupDefinition::Definition(QuintDeclaration::QuintOpDef(def)))
+                            if def.qualifier == OpQualifier::Temporal
+                    )
+                    || args.iter().any(|arg| self.is_temporal(arg))
+            }
+            QuintEx::QuintLambda { expr, .. } => self.is_temporal(expr),
+            QuintEx::QuintLet { opdef, expr, .. } => {
+                self.is_temporal(&opdef.expr) || self.is_temporal(expr)
+            }
+            QuintEx::QuintBool { .. } | QuintEx::QuintInt { .. } | QuintEx::QuintStr { .. } => {
+                false
+            }
+        }
+    }
+
+    /// Whether evaluating the expression may read the next state, looking
+    /// into the definitions it refers to.
+    fn uses_next(&self, expr: &QuintEx, visited: &mut FxHashSet<QuintId>) -> bool {
+        let in_definition =
+            |id: &QuintId, visited: &mut FxHashSet<QuintId>| match self.table.get(id) {
+                Some(LookupDefinition::Definition(QuintDeclaration::QuintOpDef(def))) => {
+                    visited.insert(def.id) && self.uses_next(&def.expr, visited)
+                }
+                _ => false,
+            };
+
+        match expr {
+            QuintEx::QuintName { id, .. } => in_definition(id, visited),
+            QuintEx::QuintApp { id, opcode, args } => {
+                opcode == "next"
+                    || in_definition(id, visited)
+                    || args.iter().any(|arg| self.uses_next(arg, visited))
+            }
+            QuintEx::QuintLambda { expr, .. } => self.uses_next(expr, visited),
+            QuintEx::QuintLet { opdef, expr, .. } => {
+                self.uses_next(&opdef.expr, visited) || self.uses_next(expr, visited)
+            }
+            QuintEx::QuintBool { .. } | QuintEx::QuintInt { .. } | QuintEx::QuintStr { .. } => {
+                false
+            }
+        }
+    }
+}
+
+fn unsupported(what: &str) -> QuintError {
+    QuintError::new(
+        ErrorKind::Unsupported,
+        format!("Temporal properties with {what} are not supported over finite traces").as_str(),
+    )
+}
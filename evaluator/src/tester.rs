@@ -0,0 +1,114 @@
+//! Execution of Quint `run` definitions, i.e. tests written as sequences of
+//! actions and expectations, like `init.then(A).then(B).expect(inv)`.
+//!
+//! A run passes when it evaluates to `true`. It fails when it evaluates to
+//! `false` or gives an error, i.e. when an action in `A.then(B)` is disabled
+//! or an `expect` doesn't hold. The states reached by the run are collected
+//! on each step (see [`Env::shift_step`]), so failures report the step that
+//! failed and the trace leading to it.
+//!
+//! Runs with nondeterministic choices can be executed many times, with
+//! different choices, until one fails (see [`run_test_samples`]).
+
+use crate::evaluator::{Env, Interpreter};
+use crate::ir::{LookupTable, QuintError};
+use crate::rand::derive_state;
+use crate::simulator::NamedProperty;
+use crate::trace::Trace;
+
+/// The result of executing a run.
+pub struct TestResult {
+    pub name: String,
+    pub outcome: TestOutcome,
+    /// The states reached by the run, up to the failing step if it failed.
+    pub trace: Trace,
+    /// The number of times the run was executed.
+    pub samples: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TestOutcome {
+    Passed,
+    Failed {
+        /// The index of the step that failed, starting from 0, i.e. the
+        /// number of steps completed before it.
+        step: usize,
+        /// The error given by the run, if it didn't just evaluate to `false`.
+        error: Option<QuintError>,
+    },
+}
+
+impl TestResult {
+    pub fn passed(&self) -> bool {
+        self.outcome == TestOutcome::Passed
+    }
+}
+
+/// Execute a run, making nondeterministic choices with a random number
+/// generator with the given state, so the same seed reproduces the same
+/// execution.
+pub fn run_test(table: &LookupTable, run: &NamedProperty, seed: u64) -> TestResult {
+    execute(table, run, seed).0
+}
+
+/// Execute a run up to `max_samples` times, until it fails, each time with a
+/// random number generator state derived from `seed`. Runs that make no
+/// nondeterministic choices are executed once, as they would always give the
+/// same result.
+pub fn run_test_samples(
+    table: &LookupTable,
+    run: &NamedProperty,
+    seed: u64,
+    max_samples: usize,
+) -> TestResult {
+    let mut sample = 0;
+    loop {
+        let (mut result, nondeterministic) = execute(table, run, derive_state(seed, sample as u64));
+        sample += 1;
+        if !result.passed() || !nondeterministic || sample >= max_samples {
+            result.samples = sample;
+            return result;
+        }
+    }
+}
+
+/// Execute a run once, also returning whether it made nondeterministic
+/// choices, i.e. whether it used the random number generator.
+fn execute(table: &LookupTable, run: &NamedProperty, seed: u64) -> (TestResult, bool) {
+    let mut interpreter = Interpreter::new(table);
+    let mut env = Env::with_rand_state(interpreter.var_storage.clone(), seed);
+    env.trace = Some(Vec::new());
+
+    let result = interpreter
+        .compile(&run.expr)
+        .execute(&mut env)
+        .and_then(|value| value.try_bool());
+    let nondeterministic = env.rand.get_state() != seed;
+    let mut states = env.trace.take().unwrap_or_default();
+    let step = states.len();
+
+    let outcome = match result {
+        Ok(true) => {
+            // The last step isn't shifted by the run itself
+            interpreter.shift();
+            states.push(interpreter.var_storage.borrow().as_record());
+            TestOutcome::Passed
+        }
+        Ok(false) => TestOutcome::Failed { step, error: None },
+        Err(error) => TestOutcome::Failed {
+            step,
+            error: Some(error),
+        },
+    };
+
+    let result = TestResult {
+        name: run.name.clone(),
+        trace: Trace {
+            seed: Some(seed),
+            ..Trace::new(states, outcome != TestOutcome::Passed)
+        },
+        outcome,
+        samples: 1,
+    };
+    (result, nondeterministic)
+}
@@ -0,0 +1,435 @@
+//! Reading traces printed by TLC, the explicit-state model checker for TLA+,
+//! so legacy counterexamples can be replayed and re-analyzed against the
+//! equivalent Quint spec (see [`crate::simulator::ParsedQuint::replay`]), and
+//! writing values and traces the same way, so states can be pasted into TLC
+//! configs or TLAPS proofs when comparing Quint and TLA+ models.
+//!
+//! TLC prints each state as a conjunction of the values of the variables:
+//!
+//! ```text
+//! State 2: <Next line 10, col 5 to line 12, col 20 of module M>
+//! /\ x = 1
+//! /\ f = (1 :> "a" @@ 2 :> "b")
+//! ```
+//!
+//! TLA+ values are read as the corresponding Quint values: functions as maps,
+//! sequences as lists, and records as records, except the ones with exactly
+//! the `tag` and `value` fields, which are read as variants (as Quint encodes
+//! variants in TLA+). Tuples can't be told apart from sequences, so they are
+//! read as lists too, and model values are read as strings. Values are
+//! written with the inverse conversion.
+
+use crate::ir::{ErrorKind, QuintError};
+use crate::spans::span;
+use crate::symbol::Symbol;
+use crate::trace::Trace;
+use crate::value::{ImmutableMap, ImmutableVec, Universe, Value};
+use itertools::Itertools;
+use std::rc::Rc;
+use thiserror::Error;
+
+/// An error when reading a trace or a value printed by TLC.
+#[derive(Debug, Clone, Error, PartialEq)]
+#[error("Invalid TLC trace: {0}")]
+pub struct TlcError(String);
+
+/// Read the states of an error trace from TLC's output. Other lines of the
+/// output are ignored, and the trace is a violation if TLC reported one.
+/// Stuttering states repeat the previous state, and a lasso's loop back to an
+/// earlier state ends the trace.
+pub fn parse_trace(output: &str) -> Result<Trace, TlcError> {
+    let mut states = Vec::new();
+    // The lines of the state being read, until the next blank line
+    let mut block: Option<Vec<&str>> = None;
+
+    for line in output.lines() {
+        let line = line.trim_end();
+        if let Some(header) = line.strip_prefix("State ") {
+            if let Some(lines) = block.take() {
+                states.push(parse_state(&lines)?);
+            }
+            if header.ends_with("Stuttering") {
+                let previous = states
+                    .last()
+                    .cloned()
+                    .ok_or_else(|| TlcError("stuttering on the first state".to_string()))?;
+                states.push(previous);
+            } else {
+                block = Some(Vec::new());
+            }
+        } else if line.starts_with("Back to state") {
+            break;
+        } else if let Some(lines) = block.as_mut() {
+            if line.trim().is_empty() {
+                states.push(parse_state(&block.take().unwrap())?);
+            } else {
+                lines.push(line);
+            }
+        }
+    }
+    if let Some(lines) = block {
+        states.push(parse_state(&lines)?);
+    }
+
+    if states.is_empty() {
+        return Err(TlcError("no states found".to_string()));
+    }
+
+    Ok(Trace::new(states, output.contains("is violated")))
+}
+
+/// Read a state from its lines, as a record of the values of the variables.
+/// Values can span several lines, and the conjunction is omitted when there
+/// is a single variable.
+fn parse_state(lines: &[&str]) -> Result<Value, TlcError> {
+    let mut conjuncts: Vec<String> = Vec::new();
+    for line in lines {
+        match line.trim_start().strip_prefix("/\\") {
+            Some(conjunct) => conjuncts.push(conjunct.to_string()),
+            None => match conjuncts.last_mut() {
+                Some(conjunct) => conjunct.push_str(line),
+                None => conjuncts.push(line.to_string()),
+            },
+        }
+    }
+
+    conjuncts
+        .iter()
+        .map(|conjunct| {
+            let (name, value) = conjunct
+                .split_once('=')
+                .ok_or_else(|| TlcError(format!("expected `name = value`, got {conjunct}")))?;
+            Ok((name.trim().into(), parse_value(value)?))
+        })
+        .collect::<Result<_, _>>()
+        .map(Value::Record)
+}
+
+/// Read a TLA+ value, as printed by TLC.
+pub fn parse_value(input: &str) -> Result<Value, TlcError> {
+    let mut parser = Parser { input, pos: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.pos < input.len() {
+        return Err(parser.error("end of value"));
+    }
+    Ok(value)
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+
+    fn skip_whitespace(&mut self) {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+    }
+
+    /// Consume `token` if it comes next, ignoring whitespace before it.
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_whitespace();
+        if self.rest().starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<(), TlcError> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("`{token}`")))
+        }
+    }
+
+    fn error(&self, expected: &str) -> TlcError {
+        TlcError(format!(
+            "expected {expected} at position {} of {}",
+            self.pos, self.input
+        ))
+    }
+
+    fn value(&mut self) -> Result<Value, TlcError> {
+        if self.eat("{") {
+            let elems = self.elements("}", Self::value)?;
+            return Ok(Value::Set(
+                elems.into_iter().map(Value::normalize).collect(),
+            ));
+        }
+        if self.eat("<<") {
+            let elems = self.elements(">>", Self::value)?;
+            return Ok(Value::List(ImmutableVec::from(elems)));
+        }
+        if self.eat("[") {
+            let fields = self.elements("]", Self::field)?;
+            return Ok(record_or_variant(fields));
+        }
+        if self.eat("(") {
+            return self.function_or_parenthesized();
+        }
+        if self.eat("\"") {
+            return self.string();
+        }
+
+        let start = self.pos;
+        if self.rest().starts_with('-') || self.rest().starts_with(|c: char| c.is_ascii_digit()) {
+            let low = self.integer()?;
+            if self.eat("..") {
+                let high = self.integer()?;
+                return Ok(Value::Set((low..=high).map(Value::Int).collect()));
+            }
+            return Ok(Value::Int(low));
+        }
+
+        match self.identifier() {
+            "TRUE" => Ok(Value::Bool(true)),
+            "FALSE" => Ok(Value::Bool(false)),
+            "" => {
+                self.pos = start;
+                Err(self.error("a value"))
+            }
+            // Model values are plain identifiers
+            name => Ok(Value::Str(name.into())),
+        }
+    }
+
+    /// Read the elements separated by commas, up to the `close` delimiter.
+    fn elements<T>(
+        &mut self,
+        close: &str,
+        mut element: impl FnMut(&mut Self) -> Result<T, TlcError>,
+    ) -> Result<Vec<T>, TlcError> {
+        let mut elems = Vec::new();
+        if self.eat(close) {
+            return Ok(elems);
+        }
+        loop {
+            elems.push(element(self)?);
+            if self.eat(close) {
+                return Ok(elems);
+            }
+            self.expect(",")?;
+        }
+    }
+
+    /// A record field, `name |-> value`.
+    fn field(&mut self) -> Result<(String, Value), TlcError> {
+        self.skip_whitespace();
+        let name = self.identifier().to_string();
+        if name.is_empty() {
+            return Err(self.error("a field name"));
+        }
+        self.expect("|->")?;
+        Ok((name, self.value()?))
+    }
+
+    /// A function, `(k1 :> v1 @@ k2 :> v2 @@ ...)`, or a value in parentheses.
+    fn function_or_parenthesized(&mut self) -> Result<Value, TlcError> {
+        let first = self.value()?;
+        if !self.eat(":>") {
+            self.expect(")")?;
+            return Ok(first);
+        }
+
+        let mut entries = vec![(first.normalize(), self.value()?)];
+        while self.eat("@@") {
+            let key = self.value()?;
+            self.expect(":>")?;
+            entries.push((key.normalize(), self.value()?));
+        }
+        self.expect(")")?;
+        Ok(Value::Map(ImmutableMap::from_iter(entries)))
+    }
+
+    /// The rest of a string, after its opening quote.
+    fn string(&mut self) -> Result<Value, TlcError> {
+        let mut string = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(Value::Str(string.into()));
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => string.push('\n'),
+                    Some((_, 't')) => string.push('\t'),
+                    Some((_, c)) => string.push(c),
+                    None => break,
+                },
+                c => string.push(c),
+            }
+        }
+        Err(self.error("the end of the string"))
+    }
+
+    fn integer(&mut self) -> Result<i64, TlcError> {
+        self.skip_whitespace();
+        let rest = self.rest();
+        let sign = usize::from(rest.starts_with('-'));
+        let len = sign
+            + rest[sign..]
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(rest.len() - sign);
+        let integer = rest[..len]
+            .parse()
+            .map_err(|_| self.error("a 64-bit integer"))?;
+        self.pos += len;
+        Ok(integer)
+    }
+
+    fn identifier(&mut self) -> &'a str {
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+}
+
+impl Trace {
+    /// Write the trace as TLC prints it, with each state as a conjunction of
+    /// the values of the variables (see [`Value::to_tla_state`]).
+    pub fn to_tla(&self) -> Result<String, QuintError> {
+        let _span = span!(INFO, "export", states = self.states.len());
+        let states = self
+            .states
+            .iter()
+            .enumerate()
+            .map(|(i, state)| {
+                Ok(format!(
+                    "State {}:\n{}\n",
+                    i + 1,
+                    state.vars.to_tla_state()?
+                ))
+            })
+            .collect::<Result<Vec<_>, QuintError>>()?;
+        Ok(states.join("\n"))
+    }
+}
+
+impl Value {
+    /// Write the value as a TLA+ expression. The elements of sets, the
+    /// entries of functions and the fields of records are sorted, so the same
+    /// value is always written the same way. Infinite sets are written as
+    /// they were built, i.e. `Int \X BOOLEAN`, and operators can't be written.
+    pub fn to_tla(&self) -> Result<String, QuintError> {
+        let written = match self {
+            Self::Int(i) => i.to_string(),
+            Self::Bool(true) => "TRUE".to_string(),
+            Self::Bool(false) => "FALSE".to_string(),
+            Self::Str(s) => format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\"")),
+            Self::Universe(Universe::Bool) => "BOOLEAN".to_string(),
+            Self::Universe(Universe::Int) => "Int".to_string(),
+            Self::Universe(Universe::Nat) => "Nat".to_string(),
+            Self::CrossProduct(sets, _) if self.is_infinite() => sets
+                .iter()
+                .map(Value::to_tla_operand)
+                .collect::<Result<Vec<_>, _>>()?
+                .join(" \\X "),
+            Self::PowerSet(base, _) if self.is_infinite() => {
+                format!("SUBSET {}", base.to_tla_operand()?)
+            }
+            Self::MapSet(domain, range, _) if self.is_infinite() => {
+                format!("[{} -> {}]", domain.to_tla()?, range.to_tla()?)
+            }
+            Self::Set(_)
+            | Self::Interval(_, _)
+            | Self::CrossProduct(..)
+            | Self::PowerSet(..)
+            | Self::MapSet(..)
+            | Self::BitSet(_, _) => {
+                let set = self.as_set();
+                let elems = set.iter().sorted_unstable().map(Value::to_tla);
+                format!("{{{}}}", elems.collect::<Result<Vec<_>, _>>()?.join(", "))
+            }
+            Self::Tuple(elems) | Self::List(elems) => {
+                let elems = elems.iter().map(Value::to_tla);
+                format!("<<{}>>", elems.collect::<Result<Vec<_>, _>>()?.join(", "))
+            }
+            Self::Record(fields) => {
+                let fields = fields
+                    .iter()
+                    .sorted_unstable_by(|(a, _), (b, _)| a.cmp(b))
+                    .map(|(name, value)| Ok(format!("{name} |-> {}", value.to_tla()?)));
+                format!(
+                    "[{}]",
+                    fields.collect::<Result<Vec<_>, QuintError>>()?.join(", ")
+                )
+            }
+            // The empty function is the empty sequence
+            Self::Map(map) if map.is_empty() => "<<>>".to_string(),
+            Self::Map(map) => {
+                let entries = map
+                    .iter()
+                    .sorted_unstable()
+                    .map(|(key, value)| Ok(format!("{} :> {}", key.to_tla()?, value.to_tla()?)));
+                format!(
+                    "({})",
+                    entries
+                        .collect::<Result<Vec<_>, QuintError>>()?
+                        .join(" @@ ")
+                )
+            }
+            Self::Variant(label, value) => {
+                format!("[tag |-> \"{label}\", value |-> {}]", value.to_tla()?)
+            }
+            Self::Lambda(_, _) => {
+                return Err(QuintError::new(
+                    ErrorKind::Unsupported,
+                    "Cannot write an operator in TLA+",
+                ))
+            }
+        };
+        Ok(written)
+    }
+
+    /// Write the value as an operand of `\X` or `SUBSET`, in parentheses if
+    /// it is itself written with one of them.
+    fn to_tla_operand(&self) -> Result<String, QuintError> {
+        let written = self.to_tla()?;
+        if self.is_infinite() && matches!(self, Self::CrossProduct(..) | Self::PowerSet(..)) {
+            return Ok(format!("({written})"));
+        }
+        Ok(written)
+    }
+
+    /// Write a state, i.e. a record of the values of the variables, as the
+    /// conjunction of their values, one per line.
+    pub fn to_tla_state(&self) -> Result<String, QuintError> {
+        let conjuncts = self
+            .try_record_map()?
+            .iter()
+            .sorted_unstable_by(|(a, _), (b, _)| a.cmp(b))
+            .map(|(name, value)| Ok(format!("/\\ {name} = {}", value.to_tla()?)));
+        Ok(conjuncts
+            .collect::<Result<Vec<_>, QuintError>>()?
+            .join("\n"))
+    }
+}
+
+/// Records with exactly the `tag` and `value` fields are Quint variants.
+fn record_or_variant(fields: Vec<(String, Value)>) -> Value {
+    let fields: ImmutableMap<Symbol, Value> = fields
+        .into_iter()
+        .map(|(name, value)| (name.into(), value))
+        .collect();
+    if fields.len() == 2 {
+        if let (Some(Value::Str(tag)), Some(value)) = (
+            fields.get(&Symbol::from("tag")),
+            fields.get(&Symbol::from("value")),
+        ) {
+            return Value::Variant(tag.as_str().into(), Rc::new(value.clone()));
+        }
+    }
+    Value::Record(fields)
+}
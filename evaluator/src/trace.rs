@@ -0,0 +1,443 @@
+//! Traces: the states reached by a run, with what happened on the step to
+//! each of them when it was recorded (see [`crate::mbt`]).
+//!
+//! Traces are produced by the simulator, the tester and the explorer, and
+//! read from other tools (see [`crate::itf`] and [`crate::tlc`]) to be
+//! replayed. The same [`Trace`] goes to all the exporters, i.e. ITF, TLA+,
+//! protobuf and the trace store, so the variables of the spec are never mixed
+//! with the information about the steps, which each format writes its own
+//! way.
+//!
+//! Traces kept through long runs (i.e. the best traces and the violations of
+//! a simulation) are stored as a [`DeltaTrace`], with only the variables that
+//! changed on each step, as states of large specs are mostly the same from
+//! one step to the next.
+
+use crate::ir::QuintId;
+use crate::symbol::Symbol;
+use crate::value::{ImmutableMap, Value};
+use itertools::Itertools;
+use std::rc::Rc;
+
+/// An alternative taken in an `any`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Alternative {
+    /// The position of the alternative among the ones given to the `any`,
+    /// from 0.
+    pub index: usize,
+    /// The name of the action, for alternatives that are calls to actions.
+    pub name: Option<String>,
+}
+
+impl Alternative {
+    /// The name of the alternative, or its position for anonymous ones (i.e.
+    /// `#1` for the second one).
+    pub fn label(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => format!("#{}", self.index),
+        }
+    }
+}
+
+/// What happened on the step reaching a state, recorded during evaluation
+/// when the interpreter is set to record it (see
+/// `Interpreter::set_record_mbt`).
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StepMeta {
+    /// The name of the action taken in an `any { ... }`. With nested `any`s,
+    /// the innermost action is recorded.
+    pub action_taken: Option<String>,
+    /// The id of the action taken, as given to the `any`, to locate it in the
+    /// spec (see [`crate::diagnostics::SourceMap`]).
+    pub action_id: Option<QuintId>,
+    /// The alternatives taken in every `any` evaluated on the step, in the
+    /// order they were evaluated, with each one before the alternatives
+    /// taken in it (i.e. `Player1` and then `MoveA`, when `MoveA` is taken in
+    /// the `any` of `Player1`).
+    pub alternatives: Vec<Alternative>,
+    /// The values picked by `nondet` definitions, in the order they were
+    /// evaluated.
+    pub nondet_picks: Vec<(String, Value)>,
+}
+
+impl StepMeta {
+    pub fn clear(&mut self) {
+        self.action_taken = None;
+        self.action_id = None;
+        self.alternatives.clear();
+        self.nondet_picks.clear();
+    }
+
+    /// The step with the given action, and the values picked by `nondet`
+    /// definitions given as a record by their names (see [`StepMeta::picks`]),
+    /// i.e. when reading traces. The order they were picked in is lost, so
+    /// they are ordered by name. Values other than records have no picks.
+    pub fn from_picks(action_taken: Option<String>, picks: &Value) -> Self {
+        let mut nondet_picks = picks
+            .try_record_map()
+            .into_iter()
+            .flatten()
+            .map(|(name, value)| (name.to_string(), value.clone()))
+            .collect::<Vec<_>>();
+        nondet_picks.sort_by(|(a, _), (b, _)| a.cmp(b));
+        Self {
+            action_taken,
+            action_id: None,
+            alternatives: Vec::new(),
+            nondet_picks,
+        }
+    }
+
+    /// Whether the alternatives or picks of the step were recorded, so that
+    /// it can be replayed from them (see `ParsedQuint::replay`).
+    pub fn has_choices(&self) -> bool {
+        !self.alternatives.is_empty() || !self.nondet_picks.is_empty()
+    }
+
+    /// Take the next alternative to replay in an `any`, by its position.
+    pub fn take_alternative(&mut self) -> Option<usize> {
+        (!self.alternatives.is_empty()).then(|| self.alternatives.remove(0).index)
+    }
+
+    /// Take the value to replay for the `nondet` definition with the given
+    /// name, the first one recorded for it.
+    pub fn take_pick(&mut self, name: &str) -> Option<Value> {
+        let position = self.nondet_picks.iter().position(|(n, _)| n == name)?;
+        Some(self.nondet_picks.remove(position).1)
+    }
+
+    /// The values picked by `nondet` definitions, as a record by their names.
+    pub fn picks(&self) -> Value {
+        Value::Record(
+            self.nondet_picks
+                .iter()
+                .map(|(name, value)| (Symbol::from(name), value.clone()))
+                .collect::<ImmutableMap<_, _>>(),
+        )
+    }
+}
+
+/// A state of a trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct State {
+    /// The values of the state variables, as a record.
+    pub vars: Value,
+    /// What happened on the step reaching the state, if it was recorded. The
+    /// action of initial states is `init`.
+    pub meta: Option<StepMeta>,
+}
+
+impl State {
+    pub fn new(vars: Value) -> Self {
+        Self { vars, meta: None }
+    }
+
+    /// The action taken to reach the state, if it was recorded.
+    pub fn action_taken(&self) -> Option<&str> {
+        self.meta.as_ref()?.action_taken.as_deref()
+    }
+
+    /// A description of the step reaching the state, if it was recorded: the
+    /// alternatives taken (or the action, if no `any` was evaluated), i.e.
+    /// `Player1 > MoveA`.
+    pub fn step_label(&self) -> Option<String> {
+        let meta = self.meta.as_ref()?;
+        if meta.alternatives.is_empty() {
+            return meta.action_taken.clone();
+        }
+        let labels = meta
+            .alternatives
+            .iter()
+            .map(Alternative::label)
+            .collect::<Vec<_>>();
+        Some(labels.join(" > "))
+    }
+}
+
+impl From<Value> for State {
+    fn from(vars: Value) -> Self {
+        Self::new(vars)
+    }
+}
+
+/// The states reached by a run, from an initial state.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Trace {
+    pub states: Vec<State>,
+    /// Whether the last state is a violation, i.e. of an invariant or of the
+    /// expectations of a test.
+    pub violation: bool,
+    /// The seed that reproduces the trace, for traces of runs making random
+    /// choices (i.e. the seed of a sample of a simulation, or of a test).
+    pub seed: Option<u64>,
+}
+
+impl Trace {
+    /// The trace with states with the given values of the variables, without
+    /// any recorded steps or seed.
+    pub fn new(states: impl IntoIterator<Item = Value>, violation: bool) -> Self {
+        Self {
+            states: states.into_iter().map(State::new).collect(),
+            violation,
+            seed: None,
+        }
+    }
+
+    /// The values of the variables on each state.
+    pub fn vars(&self) -> impl Iterator<Item = &Value> {
+        self.states.iter().map(|state| &state.vars)
+    }
+
+    /// Render the trace for reading, with one state per block. With
+    /// `full_states`, every variable is shown on every state. Otherwise, only
+    /// the variables that changed since the previous state are shown, which
+    /// keeps traces of specs with many variables readable.
+    ///
+    /// Recorded steps are shown with the header of each state, followed by
+    /// the values picked by `nondet` definitions on the step.
+    pub fn render(&self, full_states: bool) -> String {
+        self.render_with_colors(full_states, false)
+    }
+
+    /// Render the trace like [`Trace::render`], with colored values and
+    /// headers (see [`Value::colored`]), for terminals.
+    pub fn render_colored(&self, full_states: bool) -> String {
+        self.render_with_colors(full_states, true)
+    }
+
+    fn render_with_colors(&self, full_states: bool, colors: bool) -> String {
+        use colored::Colorize;
+
+        let empty = Value::Record(ImmutableMap::default());
+        let mut output = String::new();
+
+        for (i, state) in self.states.iter().enumerate() {
+            let previous = if i == 0 || full_states {
+                &empty
+            } else {
+                &self.states[i - 1].vars
+            };
+            let fields = state.vars.changed_fields(previous);
+            let header = match state.step_label() {
+                Some(step) => format!("[State {i}] {step}"),
+                None => format!("[State {i}]"),
+            };
+            if fields.is_empty() {
+                if colors {
+                    output.push_str(&format!("{} {}\n", header.bold(), "(no changes)".dimmed()));
+                } else {
+                    output.push_str(&format!("{header} (no changes)\n"));
+                }
+            } else if colors {
+                output.push_str(&format!("{}\n", header.bold()));
+            } else {
+                output.push_str(&format!("{header}\n"));
+            }
+            let picks = state.meta.iter().flat_map(|meta| &meta.nondet_picks);
+            for (name, value) in picks {
+                if colors {
+                    let pick = format!("nondet {name} = {value}");
+                    output.push_str(&format!("  {}\n", pick.dimmed()));
+                } else {
+                    output.push_str(&format!("  nondet {name} = {value}\n"));
+                }
+            }
+            for (name, value) in fields {
+                if colors {
+                    output.push_str(&format!("  {}: {}\n", name.blue(), value.colored()));
+                } else {
+                    output.push_str(&format!("  {name}: {value}\n"));
+                }
+            }
+        }
+
+        output
+    }
+}
+
+/// The variables of a state of a [`DeltaTrace`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Delta {
+    /// All the variables, as a record. This is the case of the initial state,
+    /// and of states without some of the variables of the previous one.
+    Full(Value),
+    /// The variables with a different value than in the previous state,
+    /// sorted by name.
+    Changes(Vec<(Symbol, Value)>),
+}
+
+impl Delta {
+    /// The delta from the `previous` state (if any) to the given variables.
+    /// States that are not records are always stored in full.
+    pub fn new(vars: &Value, previous: Option<&Value>) -> Self {
+        match (vars, previous) {
+            (Value::Record(fields), Some(Value::Record(previous)))
+                if previous.keys().all(|name| fields.contains_key(name)) =>
+            {
+                let changes = fields
+                    .iter()
+                    .filter(|(name, value)| {
+                        !previous.get(*name).is_some_and(|p| unchanged(value, p))
+                    })
+                    .map(|(name, value)| (name.clone(), value.clone()))
+                    .sorted_by(|(a, _), (b, _)| a.cmp(b))
+                    .collect();
+                Delta::Changes(changes)
+            }
+            _ => Delta::Full(vars.clone()),
+        }
+    }
+
+    /// The variables of the state, given the variables of the previous one.
+    /// Without a previous record, the state only has the changed variables.
+    pub fn apply(&self, previous: Option<&Value>) -> Value {
+        match self {
+            Delta::Full(vars) => vars.clone(),
+            Delta::Changes(changes) => {
+                let mut fields = previous
+                    .and_then(|previous| previous.try_record_map().ok())
+                    .cloned()
+                    .unwrap_or_default();
+                for (name, value) in changes {
+                    fields.insert(name.clone(), value.clone());
+                }
+                Value::Record(fields)
+            }
+        }
+    }
+
+    /// The variables stored, i.e. all of them or only the changed ones.
+    pub fn fields(&self) -> Vec<(&Symbol, &Value)> {
+        match self {
+            Delta::Full(vars) => vars
+                .try_record_map()
+                .into_iter()
+                .flatten()
+                .sorted_by(|(a, _), (b, _)| a.cmp(b))
+                .collect(),
+            Delta::Changes(changes) => changes.iter().map(|(name, value)| (name, value)).collect(),
+        }
+    }
+}
+
+/// Whether a variable has the same value as in the previous state. Variables
+/// kept by a step hold the very same collections, which is checked first to
+/// not compare large values that didn't change element by element.
+fn unchanged(value: &Value, previous: &Value) -> bool {
+    let same = match (value, previous) {
+        (Value::Set(a), Value::Set(b)) => a.ptr_eq(b),
+        (Value::Record(a), Value::Record(b)) => a.ptr_eq(b),
+        (Value::Map(a), Value::Map(b)) => a.ptr_eq(b),
+        (Value::Tuple(a), Value::Tuple(b)) | (Value::List(a), Value::List(b)) => a.ptr_eq(b),
+        (Value::Variant(a_label, a), Value::Variant(b_label, b)) => {
+            a_label == b_label && Rc::ptr_eq(a, b)
+        }
+        _ => false,
+    };
+    same || value == previous
+}
+
+/// A trace stored as its initial state and the variables that changed on
+/// each step (see [`Delta`]). Full states are reconstructed on demand, by
+/// applying the changes in order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeltaTrace {
+    deltas: Vec<(Delta, Option<StepMeta>)>,
+    /// The variables of the last state, to find the changes of the next one.
+    last: Option<Value>,
+    pub violation: bool,
+    pub seed: Option<u64>,
+}
+
+impl DeltaTrace {
+    pub fn new(violation: bool, seed: Option<u64>) -> Self {
+        Self {
+            deltas: Vec::new(),
+            last: None,
+            violation,
+            seed,
+        }
+    }
+
+    /// Add a state at the end of the trace.
+    pub fn push(&mut self, state: State) {
+        let delta = Delta::new(&state.vars, self.last.as_ref());
+        self.deltas.push((delta, state.meta));
+        self.last = Some(state.vars);
+    }
+
+    pub fn len(&self) -> usize {
+        self.deltas.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.deltas.is_empty()
+    }
+
+    /// The variables stored for each state.
+    pub fn deltas(&self) -> impl Iterator<Item = &Delta> {
+        self.deltas.iter().map(|(delta, _)| delta)
+    }
+
+    /// The state at `index`, reconstructed from the ones before it.
+    pub fn state(&self, index: usize) -> State {
+        let (_, meta) = &self.deltas[index];
+        // Only the changes since the last full state are applied. The initial
+        // state is always full (see `Delta::new`)
+        let start = self.deltas[..=index]
+            .iter()
+            .rposition(|(delta, _)| matches!(delta, Delta::Full(_)))
+            .unwrap_or(0);
+        let vars = self.deltas[start..=index]
+            .iter()
+            .fold(None, |previous, (delta, _)| {
+                Some(delta.apply(previous.as_ref()))
+            })
+            .unwrap_or_else(|| Value::Record(ImmutableMap::default()));
+        State {
+            vars,
+            meta: meta.clone(),
+        }
+    }
+
+    /// All the states, reconstructed in order.
+    pub fn states(&self) -> impl Iterator<Item = State> + '_ {
+        self.deltas
+            .iter()
+            .scan(None, |previous: &mut Option<Value>, (delta, meta)| {
+                let vars = delta.apply(previous.as_ref());
+                *previous = Some(vars.clone());
+                Some(State {
+                    vars,
+                    meta: meta.clone(),
+                })
+            })
+    }
+}
+
+impl From<&Trace> for DeltaTrace {
+    fn from(trace: &Trace) -> Self {
+        let mut deltas = DeltaTrace::new(trace.violation, trace.seed);
+        for state in &trace.states {
+            deltas.push(state.clone());
+        }
+        deltas
+    }
+}
+
+impl From<&DeltaTrace> for Trace {
+    fn from(trace: &DeltaTrace) -> Self {
+        Trace {
+            states: trace.states().collect(),
+            violation: trace.violation,
+            seed: trace.seed,
+        }
+    }
+}
+
+impl From<DeltaTrace> for Trace {
+    fn from(trace: DeltaTrace) -> Self {
+        Trace::from(&trace)
+    }
+}
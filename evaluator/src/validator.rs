@@ -0,0 +1,294 @@
+//! Validation of the IR read from the Quint tool (or from any other
+//! frontend), before it is compiled, so malformed IR is reported with QNT517
+//! errors instead of panics deep inside compilation.
+//!
+//! The validator checks:
+//!  - that the IR was produced by a supported version of Quint, at the stage
+//!    the evaluator expects (see [`MIN_QUINT_VERSION`]), when the IR says so;
+//!  - referential integrity: names refer to definitions in the lookup table
+//!    (or to built-in values), assignments to variables, and instances
+//!    override constants that are in the table;
+//!  - that operators are known to the evaluator, and applied to as many
+//!    arguments as they take (see [`crate::reflection`]);
+//!  - for parsed specs, that temporal operators are only used in temporal
+//!    properties, and only the ones that can be checked over finite traces
+//!    (see [`crate::temporal`]), so they are reported before running instead
+//!    of failing mid-run.
+//!
+//! Errors reference the expressions they are about, so they can be located
+//! in the spec (see [`crate::diagnostics`]), and show malformed expressions as
+//! the evaluator sees them (see [`crate::printer`]).
+
+use crate::ir::{
+    ErrorKind, ImportedFrom, LookupDefinition, LookupTable, QuintDeclaration, QuintError, QuintEx,
+    QuintId, QuintOutput,
+};
+use crate::printer::expr_to_quint;
+use crate::reflection::builtin;
+use crate::simulator::ParsedQuint;
+use crate::temporal::{TEMPORAL_OPS, UNSUPPORTED_OPS};
+use fxhash::FxHashSet;
+
+/// The oldest version of Quint whose IR the evaluator reads.
+pub const MIN_QUINT_VERSION: (u64, u64, u64) = (0, 25, 0);
+
+impl QuintOutput {
+    /// Check that the IR can be compiled, returning all the problems found.
+    pub fn validate(&self) -> Result<(), Vec<QuintError>> {
+        let mut validator = Validator::new(&self.table);
+        if let Some(stage) = self.stage.as_ref().filter(|s| *s != "compiling") {
+            let message = format!("Expected the IR of `quint compile`, got the {stage} stage");
+            validator.error(None, message);
+        }
+        let supported =
+            |version: &String| parse_version(version).is_some_and(|v| v >= MIN_QUINT_VERSION);
+        if let Some(version) = self.version.as_ref().filter(|v| !supported(v)) {
+            let (major, minor, patch) = MIN_QUINT_VERSION;
+            let message = format!(
+                "Unsupported IR version {version}, expected {major}.{minor}.{patch} or later"
+            );
+            validator.error(None, message);
+        }
+        if !self.modules.iter().any(|m| m.name == self.main) {
+            validator.error(None, format!("Main module {} not found", self.main));
+        }
+        for module in &self.modules {
+            module
+                .declarations
+                .iter()
+                .for_each(|decl| validator.declaration(decl));
+        }
+        validator.finish()
+    }
+}
+
+impl ParsedQuint {
+    /// Check that the expressions of a parsed spec can be compiled, returning
+    /// all the problems found.
+    pub fn validate(&self) -> Result<(), Vec<QuintError>> {
+        let mut validator = Validator::new(&self.table);
+        let properties = self.invariants.iter().chain(&self.components);
+        let exprs = [&self.init, &self.step, &self.invariant]
+            .into_iter()
+            .chain(properties.map(|p| &p.expr))
+            .chain(&self.witnesses)
+            .chain(&self.state_label)
+            .collect::<Vec<_>>();
+        let temporal = self.temporal.iter().map(|p| &p.expr).collect::<Vec<_>>();
+        exprs
+            .iter()
+            .chain(&temporal)
+            .for_each(|expr| validator.expr(expr));
+
+        // States are evaluated one at a time, so temporal operators can only
+        // be used in temporal properties, which are checked over traces
+        let mut visited = FxHashSet::default();
+        for expr in exprs {
+            validator.temporal_operators(expr, false, &mut visited);
+        }
+        let mut visited = FxHashSet::default();
+        for expr in temporal {
+            validator.temporal_operators(expr, true, &mut visited);
+        }
+        validator.finish()
+    }
+}
+
+struct Validator<'a> {
+    table: &'a LookupTable,
+    errors: Vec<QuintError>,
+    /// The definitions already checked, by id, as they are referenced from
+    /// many places.
+    checked: FxHashSet<QuintId>,
+}
+
+impl<'a> Validator<'a> {
+    fn new(table: &'a LookupTable) -> Self {
+        Self {
+            table,
+            errors: Vec::new(),
+            checked: FxHashSet::default(),
+        }
+    }
+
+    fn finish(self) -> Result<(), Vec<QuintError>> {
+        if self.errors.is_empty() {
+            Ok(())
+        } else {
+            Err(self.errors)
+        }
+    }
+
+    fn error(&mut self, reference: Option<QuintId>, message: String) {
+        let error = QuintError::new(ErrorKind::MalformedIr, &message);
+        self.errors.push(match reference {
+            Some(id) => error.with_reference(id),
+            None => error,
+        });
+    }
+
+    fn declaration(&mut self, decl: &QuintDeclaration) {
+        match decl {
+            QuintDeclaration::QuintOpDef(def) => {
+                if !self.checked.insert(def.id) {
+                    return;
+                }
+                if let Some(ImportedFrom::Instance { overrides, .. }) = &def.imported_from {
+                    for (param, expr) in overrides {
+                        if !self.table.contains_key(&param.id) {
+                            let message = format!("Overridden constant {} not found", param.name);
+                            self.error(Some(param.id), message);
+                        }
+                        self.expr(expr);
+                    }
+                }
+                self.expr(&def.expr);
+            }
+            QuintDeclaration::QuintAssume(assume) => self.expr(&assume.assumption),
+            _ => {}
+        }
+    }
+
+    fn expr(&mut self, expr: &QuintEx) {
+        let table = self.table;
+        match expr {
+            QuintEx::QuintName { id, name } => match table.get(id) {
+                Some(LookupDefinition::Definition(def @ QuintDeclaration::QuintOpDef(_))) => {
+                    self.declaration(def)
+                }
+                Some(LookupDefinition::Definition(
+                    QuintDeclaration::QuintTypeDef(_) | QuintDeclaration::QuintAssume(_),
+                )) => {
+                    self.error(Some(*id), format!("{name} doesn't refer to a value"));
+                }
+                Some(_) => {}
+                None if builtin(name).is_some_and(|b| b.arity.is_none()) => {}
+                None => self.error(Some(*id), format!("Name {name} not found")),
+            },
+            QuintEx::QuintBool { .. } | QuintEx::QuintInt { .. } | QuintEx::QuintStr { .. } => {}
+            QuintEx::QuintApp { id, opcode, args } => {
+                self.app(*id, opcode, args, expr);
+                args.iter().for_each(|arg| self.expr(arg));
+            }
+            QuintEx::QuintLambda { expr, .. } => self.expr(expr),
+            QuintEx::QuintLet { opdef, expr, .. } => {
+                self.expr(&opdef.expr);
+                self.expr(expr);
+            }
+        }
+    }
+
+    fn app(&mut self, id: QuintId, opcode: &str, args: &[QuintEx], app: &QuintEx) {
+        let table = self.table;
+        match table.get(&id) {
+            // A user-defined operator, or a parameter or constant that is an
+            // operator
+            Some(LookupDefinition::Definition(def @ QuintDeclaration::QuintOpDef(_))) => {
+                self.declaration(def)
+            }
+            Some(
+                LookupDefinition::Param(_)
+                | LookupDefinition::Definition(QuintDeclaration::QuintConst(_)),
+            ) => {}
+            Some(_) => self.error(Some(id), format!("{opcode} is not an operator")),
+            None => match builtin(opcode).map(|b| b.arity) {
+                None => self.error(Some(id), format!("Unsupported operator {opcode}")),
+                Some(None) => self.error(Some(id), format!("{opcode} is not an operator")),
+                Some(Some(arity)) if !arity.contains(args.len()) => {
+                    let message = format!(
+                        "{opcode} applied to {} arguments, expected {arity}, in {}",
+                        args.len(),
+                        expr_to_quint(app)
+                    );
+                    self.error(Some(id), message);
+                }
+                Some(_) if opcode == "Rec" && !args.len().is_multiple_of(2) => {
+                    self.error(Some(id), "Rec applied to an odd number of arguments".into());
+                }
+                Some(_) if opcode == "matchVariant" && args.len().is_multiple_of(2) => {
+                    self.error(
+                        Some(id),
+                        "matchVariant applied to an even number of arguments".into(),
+                    );
+                }
+                Some(_) if opcode == "assign" => self.assignment(id, &args[0], app),
+                Some(_) => {}
+            },
+        }
+    }
+
+    /// Report each application of a temporal operator that can't be
+    /// evaluated, in an expression or in the definitions it refers to. In
+    /// temporal properties, these are the ones that can't be checked over
+    /// finite traces, and elsewhere all of them.
+    fn temporal_operators(
+        &mut self,
+        expr: &QuintEx,
+        in_property: bool,
+        visited: &mut FxHashSet<QuintId>,
+    ) {
+        let table = self.table;
+        let mut definition = |validator: &mut Self, id: &QuintId| {
+            if let Some(LookupDefinition::Definition(QuintDeclaration::QuintOpDef(def))) =
+                table.get(id)
+            {
+                if visited.insert(def.id) {
+                    validator.temporal_operators(&def.expr, in_property, visited);
+                }
+            }
+        };
+        match expr {
+            QuintEx::QuintName { id, .. } => definition(self, id),
+            QuintEx::QuintApp { id, opcode, args } => {
+                definition(self, id);
+                let opcode = opcode.as_str();
+                if table.contains_key(id) {
+                    // A user-defined operator, checked above
+                } else if in_property && UNSUPPORTED_OPS.contains(&opcode) {
+                    let message = format!(
+                        "Temporal operator {opcode} cannot be checked over finite traces, in {}",
+                        expr_to_quint(expr)
+                    );
+                    self.error(Some(*id), message);
+                } else if !in_property && TEMPORAL_OPS.contains(&opcode) {
+                    let message = format!(
+                        "Temporal operator {opcode} can only be used in temporal properties, in {}",
+                        expr_to_quint(expr)
+                    );
+                    self.error(Some(*id), message);
+                }
+                args.iter()
+                    .for_each(|arg| self.temporal_operators(arg, in_property, visited));
+            }
+            QuintEx::QuintLambda { expr, .. } => {
+                self.temporal_operators(expr, in_property, visited)
+            }
+            QuintEx::QuintLet { opdef, expr, .. } => {
+                self.temporal_operators(&opdef.expr, in_property, visited);
+                self.temporal_operators(expr, in_property, visited);
+            }
+            QuintEx::QuintBool { .. } | QuintEx::QuintInt { .. } | QuintEx::QuintStr { .. } => {}
+        }
+    }
+
+    fn assignment(&mut self, id: QuintId, target: &QuintEx, app: &QuintEx) {
+        let is_var = matches!(
+            self.table.get(&target.id()),
+            Some(LookupDefinition::Definition(QuintDeclaration::QuintVar(_)))
+        );
+        if !is_var {
+            let message = format!(
+                "Assignment to something other than a variable, in {}",
+                expr_to_quint(app)
+            );
+            self.error(Some(id), message);
+        }
+    }
+}
+
+/// A version as `(major, minor, patch)`, i.e. `0.25.0` or `v0.25.0`.
+pub(crate) fn parse_version(version: &str) -> Option<(u64, u64, u64)> {
+    let mut parts = version.trim_start_matches('v').split('.');
+    let mut next = || parts.next()?.parse().ok();
+    Some((next()?, next()?, next()?))
+}
@@ -11,31 +11,36 @@
 //! structures are used to represent those values and properly optimize
 //! operations for immutability. This has significant performance impact.
 //!
-//! We use `fxhash::FxBuildHasher` for the hash maps and sets, as it guarantees
-//! that iterators over identical sets/maps will always return the same order,
-//! which is important for the `Hash` implementation (as identical sets/maps
-//! should have the same hash).
+//! The hash maps and sets use a deterministic hasher (fxhash by default, see
+//! [`crate::hashing`]), as it guarantees that iterators over identical
+//! sets/maps will always return the same order, which is important for the
+//! `Hash` implementation (as identical sets/maps should have the same hash).
 
+use crate::counters;
+use crate::enumeration;
 use crate::evaluator::{CompiledExpr, Env, EvalResult};
-use crate::ir::QuintName;
+use crate::hashing::ValueHasher;
+use crate::ir::{ErrorKind, QuintError};
+use crate::symbol::Symbol;
 use imbl::shared_ptr::RcK;
 use imbl::{GenericHashMap, GenericHashSet, GenericVector};
 use itertools::Itertools;
 use std::borrow::Cow;
-use std::cell::RefCell;
+use std::cell::{OnceCell, RefCell};
+use std::cmp::Ordering;
 use std::fmt;
 use std::hash::{Hash, Hasher};
 use std::rc::Rc;
 
 /// Quint values that hold sets are immutable, use `GenericHashSet` immutable
 /// structure to hold them
-pub type ImmutableSet<T> = GenericHashSet<T, fxhash::FxBuildHasher, RcK>;
+pub type ImmutableSet<T> = GenericHashSet<T, ValueHasher, RcK>;
 /// Quint values that hold vectors are immutable, use `GenericVector` immutable
 /// structure to hold them
 pub type ImmutableVec<T> = GenericVector<T, RcK>;
 /// Quint values that hold maps are immutable, use `GenericHashMap` immutable
 /// structure to hold them
-pub type ImmutableMap<K, V> = GenericHashMap<K, V, fxhash::FxBuildHasher, RcK>;
+pub type ImmutableMap<K, V> = GenericHashMap<K, V, ValueHasher, RcK>;
 
 /// Quint strings are immutable, use hipstr's LocalHipStr type, which provides
 /// inlined (stack allocated) strings of length up to 23 bytes, and cheap clones
@@ -46,31 +51,170 @@ pub type Str = hipstr::LocalHipStr<'static>;
 ///
 /// Can be seen as a normal form of the expression, except for the intermediate
 /// values that enable lazy evaluation of some potentially expensive expressions.
-#[derive(Clone, Debug)]
+#[derive(Debug)]
 pub enum Value {
     Int(i64),
     Bool(bool),
     Str(Str),
     Set(ImmutableSet<Value>),
     Tuple(ImmutableVec<Value>),
-    Record(ImmutableMap<QuintName, Value>),
+    Record(ImmutableMap<Symbol, Value>),
     Map(ImmutableMap<Value, Value>),
     List(ImmutableVec<Value>),
     Lambda(Vec<Rc<RefCell<EvalResult>>>, CompiledExpr),
-    Variant(QuintName, Rc<Value>),
+    Variant(Symbol, Rc<Value>),
     // "Intermediate" values using during evaluation to avoid expensive computations
     Interval(i64, i64),
-    CrossProduct(Vec<Value>),
-    PowerSet(Rc<Value>),
-    MapSet(Rc<Value>, Rc<Value>),
+    CrossProduct(Vec<Value>, CachedCardinality),
+    PowerSet(Rc<Value>, CachedCardinality),
+    MapSet(Rc<Value>, Rc<Value>, CachedCardinality),
+    Universe(Universe),
+    /// A set of variants of an enumeration, as a bitset over its sorted labels
+    /// (see [`crate::representation`]).
+    BitSet(Rc<[Symbol]>, u64),
+}
+
+/// The cardinality of an intermediate set, computed the first time it's needed
+/// (see [`Value::cardinality`]). Values are immutable, so it never changes.
+#[derive(Clone, Debug, Default)]
+pub struct CachedCardinality(OnceCell<usize>);
+
+impl CachedCardinality {
+    /// The cached cardinality, computing it with `init` if it's not cached
+    /// yet. Cardinalities too large to be counted are cached as `usize::MAX`.
+    fn get_or_init(&self, init: impl FnOnce() -> Option<usize>) -> Option<usize> {
+        let cardinality = *self.0.get_or_init(|| init().unwrap_or(usize::MAX));
+        (cardinality != usize::MAX).then_some(cardinality)
+    }
+}
+
+/// The cardinality of a set, kept symbolic when it's too large to be counted,
+/// so that it can still be compared with integers (i.e. in `size(S) > 10`),
+/// see [`Value::symbolic_cardinality`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Cardinality {
+    Finite(usize),
+    /// Finite, but greater than `usize::MAX`.
+    TooLarge,
+    Infinite,
+}
+
+impl PartialEq<i64> for Cardinality {
+    fn eq(&self, n: &i64) -> bool {
+        self.partial_cmp(n) == Some(Ordering::Equal)
+    }
+}
+
+impl PartialOrd<i64> for Cardinality {
+    /// `None` only when the cardinality is too large to be counted and the
+    /// integer doesn't fit in a `usize` (on 32-bit targets), so that it's
+    /// unknown which one is greater.
+    fn partial_cmp(&self, n: &i64) -> Option<Ordering> {
+        match self {
+            Cardinality::Finite(size) => Some((*size as i128).cmp(&i128::from(*n))),
+            Cardinality::TooLarge if i128::from(*n) <= usize::MAX as i128 => {
+                Some(Ordering::Greater)
+            }
+            Cardinality::TooLarge => None,
+            Cardinality::Infinite => Some(Ordering::Greater),
+        }
+    }
+}
+
+/// The built-in sets `Bool`, `Int` and `Nat`. These are kept symbolic, as
+/// `Int` and `Nat` are infinite: they support membership checks and picks, but
+/// can't be enumerated.
+// Universes are hashed by their position here, so keep the order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Universe {
+    Bool,
+    Int,
+    Nat,
+}
+
+impl Universe {
+    pub fn is_infinite(&self) -> bool {
+        !matches!(self, Universe::Bool)
+    }
+}
+
+impl fmt::Display for Universe {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Universe::Bool => write!(f, "Bool"),
+            Universe::Int => write!(f, "Int"),
+            Universe::Nat => write!(f, "Nat"),
+        }
+    }
+}
+
+impl Clone for Value {
+    /// Clone the value, counting the clone (see [`counters`]). Collections are
+    /// shared, so this is cheap, but it shows where values are copied around.
+    fn clone(&self) -> Self {
+        counters::record(|c| c.values_cloned += 1);
+
+        match self {
+            Value::Int(i) => Value::Int(*i),
+            Value::Bool(b) => Value::Bool(*b),
+            Value::Str(s) => Value::Str(s.clone()),
+            Value::Set(set) => Value::Set(set.clone()),
+            Value::Tuple(elems) => Value::Tuple(elems.clone()),
+            Value::Record(fields) => Value::Record(fields.clone()),
+            Value::Map(map) => Value::Map(map.clone()),
+            Value::List(elems) => Value::List(elems.clone()),
+            Value::Lambda(registers, body) => Value::Lambda(registers.clone(), body.clone()),
+            Value::Variant(label, value) => Value::Variant(label.clone(), value.clone()),
+            Value::Interval(start, end) => Value::Interval(*start, *end),
+            Value::CrossProduct(sets, cardinality) => {
+                Value::CrossProduct(sets.clone(), cardinality.clone())
+            }
+            Value::PowerSet(value, cardinality) => {
+                Value::PowerSet(value.clone(), cardinality.clone())
+            }
+            Value::MapSet(domain, range, cardinality) => {
+                Value::MapSet(domain.clone(), range.clone(), cardinality.clone())
+            }
+            Value::Universe(universe) => Value::Universe(*universe),
+            Value::BitSet(labels, bits) => Value::BitSet(labels.clone(), *bits),
+        }
+    }
+}
+
+impl Value {
+    /// The tag of the kind of value hashed by the `Hash` implementation, which
+    /// must never change, as set iteration orders, and with them the traces
+    /// of seeded simulations, depend on it (see [`crate::hashing`]). Unlike
+    /// `core::mem::discriminant`, its hash is the same on every platform and
+    /// compiler version.
+    fn hash_tag(&self) -> u64 {
+        match self {
+            Value::Int(_) => 0,
+            Value::Bool(_) => 1,
+            Value::Str(_) => 2,
+            Value::Set(_) => 3,
+            Value::Tuple(_) => 4,
+            Value::Record(_) => 5,
+            Value::Map(_) => 6,
+            Value::List(_) => 7,
+            Value::Lambda(_, _) => 8,
+            Value::Variant(_, _) => 9,
+            Value::Interval(_, _) => 10,
+            Value::CrossProduct(_, _) => 11,
+            Value::PowerSet(_, _) => 12,
+            Value::MapSet(_, _, _) => 13,
+            Value::Universe(_) => 14,
+            // Hashed like the equal generic sets
+            Value::BitSet(_, _) => 3,
+        }
+    }
 }
 
 impl Hash for Value {
     fn hash<H: Hasher>(&self, state: &mut H) {
-        // First, hash the discriminant, as we want hashes of Set(1, 2, 3) and
-        // List(1, 2, 3) to be different.
-        let discr = core::mem::discriminant(self);
-        discr.hash(state);
+        // First, hash the kind, as we want hashes of Set(1, 2, 3) and List(1,
+        // 2, 3) to be different.
+        state.write_u64(self.hash_tag());
 
         match self {
             Value::Int(n) => n.hash(state),
@@ -103,9 +247,9 @@ impl Hash for Value {
                     elem.hash(state);
                 }
             }
-            Value::Lambda(_, _) => {
-                panic!("Cannot hash lambda");
-            }
+            // Lambdas are compared by identity (see `PartialEq`), which
+            // can't be hashed in a way that is stable across runs
+            Value::Lambda(_, _) => {}
             Value::Variant(label, value) => {
                 label.hash(state);
                 value.hash(state);
@@ -114,18 +258,25 @@ impl Hash for Value {
                 start.hash(state);
                 end.hash(state);
             }
-            Value::CrossProduct(sets) => {
+            Value::CrossProduct(sets, _) => {
                 for value in sets {
                     value.hash(state);
                 }
             }
-            Value::PowerSet(value) => {
+            Value::PowerSet(value, _) => {
                 value.hash(state);
             }
-            Value::MapSet(a, b) => {
+            Value::MapSet(a, b, _) => {
                 a.hash(state);
                 b.hash(state);
             }
+            Value::Universe(universe) => state.write_u64(*universe as u64),
+            Value::BitSet(labels, bits) => {
+                // Labels are sorted, so elements come in the order of sets
+                for elem in Value::bitset_elements(labels, *bits) {
+                    elem.hash(state);
+                }
+            }
         }
     }
 }
@@ -141,18 +292,46 @@ impl PartialEq for Value {
             (Value::Record(a), Value::Record(b)) => *a == *b,
             (Value::Map(a), Value::Map(b)) => *a == *b,
             (Value::List(a), Value::List(b)) => *a == *b,
-            (Value::Lambda(_, _), Value::Lambda(_, _)) => panic!("Cannot compare lambdas"),
+            (Value::Lambda(a_registers, a_body), Value::Lambda(b_registers, b_body)) => {
+                lambda_identity(a_registers, a_body) == lambda_identity(b_registers, b_body)
+            }
             (Value::Variant(a_label, a_value), Value::Variant(b_label, b_value)) => {
                 a_label == b_label && a_value == b_value
             }
             (Value::Interval(a_start, a_end), Value::Interval(b_start, b_end)) => {
                 a_start == b_start && a_end == b_end
             }
-            (Value::CrossProduct(a), Value::CrossProduct(b)) => *a == *b,
-            (Value::PowerSet(a), Value::PowerSet(b)) => *a == *b,
-            (Value::MapSet(a1, b1), Value::MapSet(a2, b2)) => a1 == a2 && b1 == b2,
-            // To compare two sets represented in different ways, we need to enumarate them both
-            (a, b) if a.is_set() && b.is_set() => a.as_set() == b.as_set(),
+            (Value::Universe(a), Value::Universe(b)) => a == b,
+            (Value::BitSet(a_labels, a), Value::BitSet(b_labels, b)) if a_labels == b_labels => {
+                a == b
+            }
+            // Empty intermediate sets can have any components, i.e. `tuples(S, Set())`
+            (a, b) if a.is_set() && b.is_set() && (a.is_empty_set() || b.is_empty_set()) => {
+                a.is_empty_set() && b.is_empty_set()
+            }
+            // Non-empty intermediate sets of the same kind are equal when their
+            // components are, and maps from an empty domain are all the same
+            (Value::CrossProduct(a, _), Value::CrossProduct(b, _)) => *a == *b,
+            (Value::PowerSet(a, _), Value::PowerSet(b, _)) => *a == *b,
+            (Value::MapSet(a_domain, a_range, _), Value::MapSet(b_domain, b_range, _)) => {
+                a_domain == b_domain && (a_domain.is_empty_set() || a_range == b_range)
+            }
+            // An infinite set can't be equal to a finite one, and we can't
+            // enumerate it to compare with other representations
+            (a, b) if a.is_infinite() || b.is_infinite() => false,
+            // Sets represented in different ways are equal when they have the
+            // same size and the elements of one are in the other. Membership
+            // checks don't enumerate sets, so only one of them is iterated
+            // over, preferably one that is already enumerated
+            (a, b) if a.is_set() && b.is_set() => {
+                let (iterated, other) = if matches!(b, Value::Set(_)) {
+                    (b, a)
+                } else {
+                    (a, b)
+                };
+                a.cardinality() == b.cardinality()
+                    && iterated.iter_set().all(|elem| other.contains(&elem))
+            }
             _ => false,
         }
     }
@@ -160,105 +339,376 @@ impl PartialEq for Value {
 
 impl Eq for Value {}
 
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// A total order on values, used whenever we need a canonical order that
+/// doesn't depend on hashing (i.e. to deterministically choose an element of a
+/// set).
+///
+/// Values of different kinds are ordered by kind. Sets are compared as their
+/// sorted sequences of elements, so the order doesn't depend on how the set is
+/// represented, which keeps it consistent with `PartialEq`. Same for records and
+/// maps, which are compared as their sorted sequences of entries.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::Str(a), Value::Str(b)) => a.cmp(b),
+            // Comparing the bounds is the same as comparing the sorted elements
+            (Value::Interval(a_start, a_end), Value::Interval(b_start, b_end)) => {
+                (a_start, a_end).cmp(&(b_start, b_end))
+            }
+            (Value::Universe(a), Value::Universe(b)) => a.cmp(b),
+            // Infinite sets can't be enumerated, so they come after all finite sets
+            (a, b) if a.is_set() && b.is_set() && (a.is_infinite() || b.is_infinite()) => {
+                match (a.is_infinite(), b.is_infinite()) {
+                    // Infinite sets are kept symbolic, so they are ordered
+                    // by how they are written
+                    (true, true) => a.to_string().cmp(&b.to_string()),
+                    (a_infinite, b_infinite) => a_infinite.cmp(&b_infinite),
+                }
+            }
+            // Values share structure, so collections are often the same one,
+            // which can be told without sorting them
+            (Value::Set(a), Value::Set(b)) if a.ptr_eq(b) => Ordering::Equal,
+            (Value::Record(a), Value::Record(b)) if a.ptr_eq(b) => Ordering::Equal,
+            (Value::Map(a), Value::Map(b)) if a.ptr_eq(b) => Ordering::Equal,
+            (a, b) if a.is_set() && b.is_set() => cmp_sorted(a.as_set().iter(), b.as_set().iter()),
+            (Value::Tuple(a), Value::Tuple(b)) => a.iter().cmp(b.iter()),
+            (Value::List(a), Value::List(b)) => a.iter().cmp(b.iter()),
+            (Value::Record(a), Value::Record(b)) => cmp_sorted(a.iter(), b.iter()),
+            (Value::Map(a), Value::Map(b)) => cmp_sorted(a.iter(), b.iter()),
+            (Value::Variant(a_label, a_value), Value::Variant(b_label, b_value)) => {
+                a_label.cmp(b_label).then_with(|| a_value.cmp(b_value))
+            }
+            (Value::Lambda(a_registers, a_body), Value::Lambda(b_registers, b_body)) => {
+                lambda_identity(a_registers, a_body).cmp(&lambda_identity(b_registers, b_body))
+            }
+            (a, b) => a.kind_rank().cmp(&b.kind_rank()),
+        }
+    }
+}
+
+/// What identifies a lambda: the addresses of its body and of the registers
+/// of its parameters. Operators can't be compared by what they compute, so
+/// only a lambda and its clones are equal.
+fn lambda_identity(registers: &[Rc<RefCell<EvalResult>>], body: &CompiledExpr) -> Vec<usize> {
+    std::iter::once(body.address())
+        .chain(
+            registers
+                .iter()
+                .map(|register| Rc::as_ptr(register) as usize),
+        )
+        .collect()
+}
+
+/// Compare two collections as their sorted sequences of items. Only
+/// references are sorted, so no value is cloned.
+fn cmp_sorted<T: Ord>(a: impl Iterator<Item = T>, b: impl Iterator<Item = T>) -> Ordering {
+    let mut a = a.collect::<Vec<_>>();
+    let mut b = b.collect::<Vec<_>>();
+    a.sort_unstable();
+    b.sort_unstable();
+    a.cmp(&b)
+}
+
+/// The finalizer of SplitMix64, spreading the bits of a hash.
+fn mix(hash: u64) -> u64 {
+    let hash = (hash ^ (hash >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    let hash = (hash ^ (hash >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    hash ^ (hash >> 31)
+}
+
 impl Value {
+    /// A hash of the value that is the same for equal values with different
+    /// representations (i.e. an interval and the equivalent enumerated set),
+    /// to identify states.
+    ///
+    /// Sets, records and maps are hashed by combining the hashes of their
+    /// elements in a way that doesn't depend on their order, so they don't
+    /// have to be normalized (which rebuilds every collection in the value).
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = fxhash::FxHasher64::default();
+        self.hash_canonical(&mut hasher);
+        hasher.finish()
+    }
+
+    fn hash_canonical<H: Hasher>(&self, state: &mut H) {
+        // Order-independent combination of the hashes of some items, mixing
+        // each one first so that i.e. `Set(1, 4)` and `Set(2, 3)` differ
+        fn combine(items: impl Iterator<Item = u64>, state: &mut impl Hasher) {
+            let (count, sum) = items.fold((0_usize, 0_u64), |(count, sum), hash| {
+                (count + 1, sum.wrapping_add(mix(hash)))
+            });
+            count.hash(state);
+            sum.hash(state);
+        }
+
+        self.kind_rank().hash(state);
+        match self {
+            Value::Int(n) => n.hash(state),
+            Value::Bool(b) => b.hash(state),
+            Value::Str(s) => s.hash(state),
+            // Infinite sets can't be enumerated, so they are kept symbolic
+            _ if self.is_infinite() => self.hash(state),
+            _ if self.is_set() => combine(self.as_set().iter().map(Value::fingerprint), state),
+            Value::Tuple(elems) | Value::List(elems) => {
+                elems.iter().for_each(|elem| elem.hash_canonical(state))
+            }
+            Value::Record(fields) => combine(
+                fields.iter().map(|(name, value)| {
+                    let mut hasher = fxhash::FxHasher64::default();
+                    name.hash(&mut hasher);
+                    value.hash_canonical(&mut hasher);
+                    hasher.finish()
+                }),
+                state,
+            ),
+            Value::Map(map) => combine(
+                map.iter().map(|(key, value)| {
+                    let mut hasher = fxhash::FxHasher64::default();
+                    key.hash_canonical(&mut hasher);
+                    value.hash_canonical(&mut hasher);
+                    hasher.finish()
+                }),
+                state,
+            ),
+            Value::Variant(label, value) => {
+                label.hash(state);
+                value.hash_canonical(state);
+            }
+            // Lambdas are only equal to their clones, so the kind is enough
+            _ => {}
+        }
+    }
+
     /// Calculate the cardinality of the value without having to enumerate it
-    /// (i.e. without calling `as_set`).
+    /// (i.e. without calling `as_set`). Infinite sets, and sets too large to
+    /// be counted (i.e. powersets of large sets), have a cardinality of
+    /// `usize::MAX`, see [`Value::checked_cardinality`].
     pub fn cardinality(&self) -> usize {
+        self.checked_cardinality().unwrap_or(usize::MAX)
+    }
+
+    /// The cardinality of the value, or `None` if the value is an infinite set
+    /// or a set too large for its cardinality to fit in a `usize`, or if it is
+    /// not a collection.
+    pub fn checked_cardinality(&self) -> Option<usize> {
         match self {
-            Value::Set(set) => set.len(),
-            Value::Tuple(elems) => elems.len(),
-            Value::Record(fields) => fields.len(),
-            Value::Map(map) => map.len(),
-            Value::List(elems) => elems.len(),
-            Value::Interval(start, end) => (end - start + 1).try_into().unwrap(),
-            Value::CrossProduct(sets) => sets.iter().fold(1, |acc, set| acc * set.cardinality()),
-            Value::PowerSet(value) => {
+            Value::Set(set) => Some(set.len()),
+            Value::Tuple(elems) => Some(elems.len()),
+            Value::Record(fields) => Some(fields.len()),
+            Value::Map(map) => Some(map.len()),
+            Value::List(elems) => Some(elems.len()),
+            Value::Interval(start, end) => end
+                .checked_sub(*start)
+                .and_then(|n| n.checked_add(1))
+                .and_then(|n| n.try_into().ok()),
+            // Intermediate sets are often nested, so their cardinalities are
+            // cached instead of recomputed from their components every time.
+            // Sets too large to be counted are cached as `usize::MAX`
+            Value::CrossProduct(sets, cached) => cached.get_or_init(|| {
+                sets.iter().try_fold(1_usize, |acc, set| {
+                    acc.checked_mul(set.checked_cardinality()?)
+                })
+            }),
+            Value::PowerSet(value, cached) => cached.get_or_init(|| {
                 // 2^(cardinality of value)
-                2_usize.pow(value.cardinality().try_into().unwrap())
-            }
-            Value::MapSet(domain, range) => {
-                // (cardinality of range)^(cardinality of domain()
-                range
-                    .cardinality()
-                    .pow(domain.cardinality().try_into().unwrap())
-            }
-            _ => panic!("Cardinality not implemented for {:?}", self),
+                let exponent = value.checked_cardinality()?.try_into().ok()?;
+                2_usize.checked_pow(exponent)
+            }),
+            Value::MapSet(domain, range, cached) => cached.get_or_init(|| {
+                // (cardinality of range)^(cardinality of domain)
+                let exponent = domain.checked_cardinality()?.try_into().ok()?;
+                range.checked_cardinality()?.checked_pow(exponent)
+            }),
+            Value::Universe(Universe::Bool) => Some(2),
+            Value::BitSet(_, bits) => Some(bits.count_ones() as usize),
+            Value::Universe(_) => None,
+            Value::Int(_)
+            | Value::Bool(_)
+            | Value::Str(_)
+            | Value::Lambda(..)
+            | Value::Variant(..) => None,
         }
     }
 
+    /// The cardinality of the value, like [`Value::checked_cardinality`], but
+    /// telling infinite sets from sets too large to be counted.
+    pub fn symbolic_cardinality(&self) -> Cardinality {
+        if self.is_infinite() {
+            return Cardinality::Infinite;
+        }
+        self.checked_cardinality()
+            .map_or(Cardinality::TooLarge, Cardinality::Finite)
+    }
+
     /// Check for membership of a value in a set, without having to enumerate
-    /// the set.
+    /// the set. Values that are not sets contain nothing.
     pub fn contains(&self, elem: &Value) -> bool {
         match (self, elem) {
             (Value::Set(elems), _) => elems.contains(elem),
             (Value::Interval(start, end), Value::Int(n)) => start <= n && n <= end,
-            (Value::CrossProduct(sets), Value::Tuple(elems)) => {
+            (Value::CrossProduct(sets, _), Value::Tuple(elems)) => {
                 sets.len() == elems.len()
                     && sets.iter().zip(elems).all(|(set, elem)| set.contains(elem))
             }
-            (Value::PowerSet(base), Value::Set(elems)) => {
-                let base_elems = base.as_set();
-                elems.len() <= base_elems.len()
-                    && elems.iter().all(|elem| base_elems.contains(elem))
-            }
-            (Value::MapSet(domain, range), Value::Map(map)) => {
-                let map_domain = Value::Set(map.keys().cloned().collect::<ImmutableSet<_>>());
-                // Check if domains are equal and all map values are in the range set
-                map_domain == **domain && map.values().all(|v| range.contains(v))
+            // Check membership in the base set directly, as it may be infinite
+            (Value::PowerSet(base, _), elem) if elem.is_set() => elem.subseteq(base),
+            (Value::MapSet(domain, range, _), Value::Map(map)) => {
+                // Check if domains are equal, comparing sizes and checking
+                // membership so the domain isn't enumerated, and if all map
+                // values are in the range set
+                !domain.is_infinite()
+                    && map.len() == domain.cardinality()
+                    && map.keys().all(|k| domain.contains(k))
+                    && map.values().all(|v| range.contains(v))
             }
-            _ => panic!("contains not implemented for {:?}", self),
+            (Value::Universe(Universe::Bool), Value::Bool(_)) => true,
+            (Value::Universe(Universe::Int), Value::Int(_)) => true,
+            (Value::Universe(Universe::Nat), Value::Int(n)) => *n >= 0,
+            (Value::Universe(_), _) => false,
+            (Value::BitSet(labels, bits), _) => Value::bitset_contains(labels, *bits, elem),
+            _ => false,
         }
     }
 
-    /// Check if a set is a subset of another set, avoiding enumeration when possible
+    /// Check if a set is a subset of another set, avoiding enumeration when
+    /// possible. Values that are not sets are not subsets of anything.
     pub fn subseteq(&self, superset: &Value) -> bool {
         match (self, superset) {
+            (subset, superset) if !subset.is_set() || !superset.is_set() => false,
             (Value::Set(subset), Value::Set(superset)) => subset.is_subset(superset),
             (
                 Value::Interval(subset_start, subset_end),
                 Value::Interval(superset_start, superset_end),
             ) => subset_start >= superset_start && subset_end <= superset_end,
-            (Value::CrossProduct(subsets), Value::CrossProduct(supersets)) => {
+            (Value::CrossProduct(subsets, _), Value::CrossProduct(supersets, _)) => {
                 subsets.len() == supersets.len()
                     && subsets
                         .iter()
                         .zip(supersets)
                         .all(|(subset, superset)| subset.subseteq(superset))
             }
-            (Value::PowerSet(subset), Value::PowerSet(superset)) => subset.subseteq(superset),
+            (Value::PowerSet(subset, _), Value::PowerSet(superset, _)) => subset.subseteq(superset),
             (
-                Value::MapSet(subset_domain, subset_range),
-                Value::MapSet(superset_domain, superset_range),
+                Value::MapSet(subset_domain, subset_range, _),
+                Value::MapSet(superset_domain, superset_range, _),
             ) => subset_domain == superset_domain && subset_range.subseteq(superset_range),
+            (Value::Universe(subset), Value::Universe(superset)) => {
+                subset == superset || (*subset == Universe::Nat && *superset == Universe::Int)
+            }
+            (Value::BitSet(subset_labels, subset), Value::BitSet(superset_labels, superset))
+                if subset_labels == superset_labels =>
+            {
+                subset & !superset == 0
+            }
+            // An infinite set is never a subset of a finite one, nor of an
+            // infinite one of another kind (the same kinds are handled above)
+            (subset, _) if subset.is_infinite() => false,
+            // For infinite supersets, check membership of each element, which
+            // doesn't require enumerating the superset
+            (subset, superset) if superset.is_infinite() && !subset.is_infinite() => {
+                subset.iter_set().all(|elem| superset.contains(&elem))
+            }
             // Fall back to the native implementation (`is_subset`) if no optimization is possible
             (subset, superset) => subset.as_set().is_subset(superset.as_set().as_ref()),
         }
     }
 
-    /// Convert an integer value to `i64`. Panics if the wrong type is given,
-    /// which should never happen as input expressions are type-checked.
-    pub fn as_int(&self) -> i64 {
+    /// Convert an integer value to `i64`, or fail if the wrong type is given,
+    /// which type-checked specs never do, but malformed IR can.
+    pub fn try_int(&self) -> Result<i64, QuintError> {
         match self {
-            Value::Int(n) => *n,
-            _ => panic!("Expected integer"),
+            Value::Int(n) => Ok(*n),
+            _ => Err(unexpected_kind("an integer", self)),
         }
     }
 
-    /// Convert a boolean value to `bool`. Panics if the wrong type is given,
-    /// which should never happen as input expressions are type-checked.
-    pub fn as_bool(&self) -> bool {
+    /// Convert a boolean value to `bool`, or fail if the wrong type is given
+    /// (see [`Value::try_int`]).
+    pub fn try_bool(&self) -> Result<bool, QuintError> {
         match self {
-            Value::Bool(b) => *b,
-            _ => panic!("Expected boolean"),
+            Value::Bool(b) => Ok(*b),
+            _ => Err(unexpected_kind("a boolean", self)),
         }
     }
 
+    /// Convert a string value to `Str`, or fail if the wrong type is given
+    /// (see [`Value::try_int`]).
+    pub fn try_str(&self) -> Result<Str, QuintError> {
+        match self {
+            Value::Str(s) => Ok(s.clone()),
+            _ => Err(unexpected_kind("a string", self)),
+        }
+    }
+
+    /// Convert an integer value to `i64`. Panics if the wrong type is given,
+    /// so values that come from evaluation use [`Value::try_int`].
+    pub fn as_int(&self) -> i64 {
+        self.try_int().unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Convert a boolean value to `bool`. Panics if the wrong type is given,
+    /// so values that come from evaluation use [`Value::try_bool`].
+    pub fn as_bool(&self) -> bool {
+        self.try_bool().unwrap_or_else(|error| panic!("{error}"))
+    }
+
     /// Convert a string value to `Str`. Panics if the wrong type is given,
-    /// which should never happen as input expressions are type-checked.
+    /// so values that come from evaluation use [`Value::try_str`].
     pub fn as_str(&self) -> Str {
+        self.try_str().unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// The name of the value's kind, as errors about values of the wrong
+    /// kind show it.
+    pub fn kind_name(&self) -> &'static str {
         match self {
-            Value::Str(s) => s.clone(),
-            _ => panic!("Expected string"),
+            Value::Int(_) => "integer",
+            Value::Bool(_) => "boolean",
+            Value::Str(_) => "string",
+            Value::Set(_)
+            | Value::Interval(_, _)
+            | Value::CrossProduct(..)
+            | Value::PowerSet(..)
+            | Value::MapSet(..)
+            | Value::Universe(_)
+            | Value::BitSet(_, _) => "set",
+            Value::Tuple(_) => "tuple",
+            Value::Record(_) => "record",
+            Value::Map(_) => "map",
+            Value::List(_) => "list",
+            Value::Lambda(_, _) => "operator",
+            Value::Variant(_, _) => "variant",
+        }
+    }
+
+    /// The position of the value's kind in the total order of values. Sets
+    /// share the same position regardless of their representation.
+    fn kind_rank(&self) -> u8 {
+        match self {
+            Value::Int(_) => 0,
+            Value::Bool(_) => 1,
+            Value::Str(_) => 2,
+            Value::Set(_)
+            | Value::Interval(_, _)
+            | Value::CrossProduct(..)
+            | Value::PowerSet(..)
+            | Value::MapSet(..)
+            | Value::Universe(_)
+            | Value::BitSet(_, _) => 3,
+            Value::Tuple(_) => 4,
+            Value::Record(_) => 5,
+            Value::Map(_) => 6,
+            Value::List(_) => 7,
+            Value::Variant(_, _) => 8,
+            Value::Lambda(_, _) => 9,
         }
     }
 
@@ -269,24 +719,85 @@ impl Value {
             self,
             Value::Set(_)
                 | Value::Interval(_, _)
-                | Value::CrossProduct(_)
-                | Value::PowerSet(_)
-                | Value::MapSet(_, _)
+                | Value::CrossProduct(..)
+                | Value::PowerSet(..)
+                | Value::MapSet(..)
+                | Value::Universe(_)
+                | Value::BitSet(_, _)
         )
     }
 
-    /// Enumerate the value as a set. Panics if the wrong type is given,
-    /// which should never happen as input expressions are type-checked.
+    /// Checks whether a value is an infinite set, i.e. one that can't be
+    /// enumerated. This is the case for `Int` and `Nat`, and for intermediate
+    /// values built from them (such as `tuples(Int, Bool)`), unless they are
+    /// empty as a whole.
+    pub fn is_infinite(&self) -> bool {
+        match self {
+            Value::Universe(universe) => universe.is_infinite(),
+            Value::CrossProduct(sets, _) => {
+                sets.iter().any(|set| set.is_infinite())
+                    && !sets.iter().any(|set| set.is_empty_set())
+            }
+            Value::PowerSet(base, _) => base.is_infinite(),
+            Value::MapSet(domain, range, _) => {
+                (domain.is_infinite() && !range.is_empty_set())
+                    || (range.is_infinite() && !domain.is_empty_set())
+            }
+            _ => false,
+        }
+    }
+
+    /// Checks whether a value is an empty set, without enumerating it.
+    fn is_empty_set(&self) -> bool {
+        match self {
+            // Without computing cardinalities, which can be huge
+            Value::CrossProduct(sets, _) => sets.iter().any(|set| set.is_empty_set()),
+            Value::PowerSet(..) => false,
+            Value::MapSet(domain, range, _) => !domain.is_empty_set() && range.is_empty_set(),
+            _ => !self.is_infinite() && self.cardinality() == 0,
+        }
+    }
+
+    /// Enumerate the value as a set, or fail if the wrong type is given (see
+    /// [`Value::try_int`]) or the set is infinite (i.e. `Int`).
     ///
     /// Sometimes, we need to create a value from scratch, and other times, we
     /// operate over the borroweed value (&self). So this returns a
     /// clone-on-write (Cow) pointer, avoiding unnecessary clones that would be
     /// required if we always wanted to return Owned data.
+    pub fn try_set(&self) -> Result<Cow<'_, ImmutableSet<Value>>, QuintError> {
+        if !self.is_set() {
+            return Err(unexpected_kind("a set", self));
+        }
+        if self.is_infinite() {
+            return Err(QuintError::new(
+                ErrorKind::InfiniteSet,
+                format!("Infinite set {self} is non-enumerable").as_str(),
+            )
+            .with_value(self));
+        }
+        Ok(self.as_set())
+    }
+
+    /// Enumerate the value as a set. Panics if the wrong type is given, and
+    /// for infinite sets, so values that come from evaluation use
+    /// [`Value::try_set`]. Intermediate sets are only built from sets (see
+    /// [`crate::builtins`]), so their components can be enumerated.
     pub fn as_set(&self) -> Cow<'_, ImmutableSet<Value>> {
+        counters::record(|c| {
+            if self.is_set() && !matches!(self, Value::Set(_)) {
+                c.set_enumerations += 1;
+                c.elements_enumerated += self.cardinality() as u64;
+            }
+        });
+        if enumeration::limit().is_some() && self.is_set() && !matches!(self, Value::Set(_)) {
+            enumeration::record(self.cardinality());
+        }
+
         match self {
             Value::Set(set) => Cow::Borrowed(set),
             Value::Interval(start, end) => Cow::Owned((*start..=*end).map(Value::Int).collect()),
-            Value::CrossProduct(sets) => {
+            Value::CrossProduct(sets, _) => {
                 let size = self.cardinality();
                 if size == 0 {
                     // an empty set produces the empty product
@@ -304,17 +815,17 @@ impl Value {
                 Cow::Owned(product_sets)
             }
 
-            Value::PowerSet(value) => {
+            Value::PowerSet(value, _) => {
                 let base = value.as_set();
-                let size = 1 << base.len(); // 2^n subsets for a set of size n
+                // 2^n subsets for a set of size n
                 Cow::Owned(
-                    (0..size)
+                    (0..self.cardinality())
                         .map(|i| powerset_at_index(base.as_ref(), i))
                         .collect(),
                 )
             }
 
-            Value::MapSet(domain, range) => {
+            Value::MapSet(domain, range, _) => {
                 if domain.cardinality() == 0 {
                     // To reflect the behaviour of TLC, an empty domain needs to give Set(Map())
                     return Cow::Owned(
@@ -326,98 +837,172 @@ impl Value {
                     // To reflect the behaviour of TLC, an empty range needs to give Set()
                     return Cow::Owned(ImmutableSet::default());
                 }
-                let domain_vec = domain.as_set().iter().cloned().collect::<Vec<_>>();
-                let range_vec = range.as_set().iter().cloned().collect::<Vec<_>>();
+                // Only the maps are put in a set, not their keys and values
+                let domain_vec = domain.iter_set().collect::<Vec<_>>();
+                let range_vec = range.iter_set().collect::<Vec<_>>();
 
-                let nindices = domain_vec.len();
                 let nvalues = range_vec.len();
 
-                let nmaps = nvalues.pow(nindices.try_into().unwrap());
+                let nmaps = self.cardinality();
 
-                let mut result_set = ImmutableSet::new();
-
-                for i in 0..nmaps {
-                    let mut pairs = Vec::with_capacity(nindices);
+                let maps = (0..nmaps).map(|i| {
                     let mut index = i;
-                    for key in domain_vec.iter() {
-                        pairs.push((key.clone(), range_vec[index % nvalues].clone()));
+                    let pairs = domain_vec.iter().map(|key| {
+                        let value = range_vec[index % nvalues].clone();
                         index /= nvalues;
-                    }
-                    result_set.insert(Value::Map(ImmutableMap::from_iter(pairs)));
-                }
+                        (key.clone(), value)
+                    });
+                    Value::Map(pairs.collect())
+                });
 
-                Cow::Owned(result_set)
+                Cow::Owned(maps.collect())
+            }
+            Value::Universe(Universe::Bool) => Cow::Owned(
+                [Value::Bool(false), Value::Bool(true)]
+                    .into_iter()
+                    .collect(),
+            ),
+            Value::Universe(universe) => panic!("Infinite set {universe} is non-enumerable"),
+            Value::BitSet(labels, bits) => {
+                Cow::Owned(Value::bitset_elements(labels, *bits).collect())
             }
             _ => panic!("Expected set"),
         }
     }
 
-    /// Convert a map value to a map. Panics if the wrong type is given, which
-    /// should never happen as input expressions are type-checked.
-    pub fn as_map(&self) -> &ImmutableMap<Value, Value> {
+    /// Convert a map value to a map, or fail if the wrong type is given (see
+    /// [`Value::try_int`]).
+    pub fn try_map(&self) -> Result<&ImmutableMap<Value, Value>, QuintError> {
         match self {
-            Value::Map(map) => map,
-            _ => panic!("Expected map"),
+            Value::Map(map) => Ok(map),
+            _ => Err(unexpected_kind("a map", self)),
         }
     }
 
-    /// Convert a list or a tuple value to a vector. Panics if the wrong type is
-    /// given, which should never happen as input expressions are type-checked.
-    pub fn as_list(&self) -> &ImmutableVec<Value> {
+    /// Convert a list or a tuple value to a vector, or fail if the wrong type
+    /// is given (see [`Value::try_int`]).
+    pub fn try_list(&self) -> Result<&ImmutableVec<Value>, QuintError> {
         match self {
-            Value::Tuple(elems) => elems,
-            Value::List(elems) => elems,
-            _ => panic!("Expected list, got {:?}", self),
+            Value::Tuple(elems) => Ok(elems),
+            Value::List(elems) => Ok(elems),
+            _ => Err(unexpected_kind("a list", self)),
         }
     }
 
-    /// Convert a record value to a map. Panics if the wrong type is given,
-    /// which should never happen as input expressions are type-checked.
-    pub fn as_record_map(&self) -> &ImmutableMap<QuintName, Value> {
+    /// Convert a record value to a map, or fail if the wrong type is given
+    /// (see [`Value::try_int`]).
+    pub fn try_record_map(&self) -> Result<&ImmutableMap<Symbol, Value>, QuintError> {
         match self {
-            Value::Record(fields) => fields,
-            _ => panic!("Expected record"),
+            Value::Record(fields) => Ok(fields),
+            _ => Err(unexpected_kind("a record", self)),
         }
     }
 
-    /// Convert a lambda value to a closure. Panics if the wrong type is given,
-    /// which should never happen as input expressions are type-checked.
-    pub fn as_closure(&self) -> impl Fn(&mut Env, Vec<Value>) -> EvalResult + '_ {
+    /// Convert a map value to a map. Panics if the wrong type is given, so
+    /// values that come from evaluation use [`Value::try_map`].
+    pub fn as_map(&self) -> &ImmutableMap<Value, Value> {
+        self.try_map().unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Convert a list or a tuple value to a vector. Panics if the wrong type
+    /// is given, so values that come from evaluation use [`Value::try_list`].
+    pub fn as_list(&self) -> &ImmutableVec<Value> {
+        self.try_list().unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// Convert a record value to a map. Panics if the wrong type is given,
+    /// which never happens for states, as they are built by the evaluator.
+    /// Other values that come from evaluation use [`Value::try_record_map`].
+    pub fn as_record_map(&self) -> &ImmutableMap<Symbol, Value> {
+        self.try_record_map()
+            .unwrap_or_else(|error| panic!("{error}"))
+    }
+
+    /// The fields of a record that have a different value in the `previous`
+    /// record (or are missing from it), sorted by name. Panics if the values
+    /// are not records.
+    pub fn changed_fields<'a>(&'a self, previous: &Value) -> Vec<(&'a Symbol, &'a Value)> {
+        let previous = previous.as_record_map();
+        self.as_record_map()
+            .iter()
+            .filter(|(name, value)| previous.get(*name) != Some(*value))
+            .sorted_by(|(a, _), (b, _)| a.cmp(b))
+            .collect()
+    }
+
+    /// Convert a lambda value to a closure, or fail if the wrong type is given
+    /// (see [`Value::try_int`]).
+    pub fn try_closure(
+        &self,
+    ) -> Result<impl Fn(&mut Env, Vec<Value>) -> EvalResult + '_, QuintError> {
         match self {
-            Value::Lambda(registers, body) => move |env: &mut Env, args: Vec<Value>| {
-                args.into_iter().enumerate().for_each(|(i, arg)| {
-                    *registers[i].borrow_mut() = Ok(arg);
-                });
+            Value::Lambda(registers, body) => Ok(move |env: &mut Env, args: Vec<Value>| {
+                // The same lambda can be applied while its body is being
+                // evaluated (i.e. when it is passed to a higher-order operator
+                // that calls it again), so we save the values currently in the
+                // registers and restore them after the application. Otherwise,
+                // the outer application would read the inner one's arguments.
+                let previous_values = registers
+                    .iter()
+                    .zip(args)
+                    .map(|(register, arg)| register.replace(Ok(arg)))
+                    .collect::<Vec<_>>();
+
+                let result = body.execute(env);
 
-                body.execute(env)
-                // FIXME: restore previous values (#1560)
-            },
-            _ => panic!("Expected lambda"),
+                registers
+                    .iter()
+                    .zip(previous_values)
+                    .for_each(|(register, value)| {
+                        *register.borrow_mut() = value;
+                    });
+
+                result
+            }),
+            _ => Err(unexpected_kind("an operator", self)),
         }
     }
 
-    /// Convert a variant value to a tuple like (label, value). Panics if the
-    /// wrong type is given, which should never happen as input expressions are
-    /// type-checked.
-    pub fn as_variant(&self) -> (&QuintName, &Value) {
+    /// Convert a variant value to a tuple like (label, value), or fail if the
+    /// wrong type is given (see [`Value::try_int`]).
+    pub fn try_variant(&self) -> Result<(&Symbol, &Value), QuintError> {
         match self {
-            Value::Variant(label, value) => (label, value),
-            _ => panic!("Expected variant"),
+            Value::Variant(label, value) => Ok((label, value)),
+            _ => Err(unexpected_kind("a variant", self)),
         }
     }
 
-    /// Convert a tuple value to a 2-element tuple. Panics if the wrong type is given,
-    /// which should never happen as input expressions are type-checked.
+    /// Convert a tuple value to a 2-element tuple, or fail if the wrong type
+    /// is given (see [`Value::try_int`]).
     ///
-    /// Useful as some builtins expect tuples of 2 elements, so we have type
-    /// guarantees that this conversion will work and can avoid having to handle
-    /// other scenarios.
-    pub fn as_tuple2(&self) -> (Value, Value) {
-        let mut elems = self.as_list().iter();
-        (elems.next().unwrap().clone(), elems.next().unwrap().clone())
+    /// Useful as some builtins expect tuples of 2 elements, i.e. the entries
+    /// of maps.
+    pub fn try_tuple2(&self) -> Result<(Value, Value), QuintError> {
+        match self.try_list()?.iter().collect_tuple() {
+            Some((a, b)) => Ok((a.clone(), b.clone())),
+            None => Err(QuintError::new(
+                ErrorKind::MalformedIr,
+                format!(
+                    "Expected a pair, got a {} of {} elements",
+                    self.kind_name(),
+                    self.cardinality()
+                )
+                .as_str(),
+            )),
+        }
     }
 }
 
+/// The error for a value of the wrong kind, which type-checked specs never
+/// give to an operator, but malformed IR can. The value itself is not shown,
+/// as it may be a set too large to be enumerated.
+pub(crate) fn unexpected_kind(expected: &str, value: &Value) -> QuintError {
+    QuintError::new(
+        ErrorKind::MalformedIr,
+        format!("Expected {expected}, got a {}", value.kind_name()).as_str(),
+    )
+}
+
 /// Get the corresponding element of a powerset of a set at a given index
 /// following a stable algorithm and avoiding enumeration. Calling this with the
 /// same index for the same set should yield the same result.
@@ -431,8 +1016,9 @@ impl Value {
 pub fn powerset_at_index(base: &ImmutableSet<Value>, i: usize) -> Value {
     let mut elems = ImmutableSet::default();
     for (j, elem) in base.iter().enumerate() {
-        // membership condition, numerical over the indexes i and j
-        if (i & (1 << j)) != 0 {
+        // membership condition, numerical over the indexes i and j. Indexes
+        // only have bits for the first elements of larger sets
+        if j < usize::BITS as usize && (i & (1 << j)) != 0 {
             elems.insert(elem.clone());
         }
     }
@@ -442,73 +1028,125 @@ pub fn powerset_at_index(base: &ImmutableSet<Value>, i: usize) -> Value {
 /// Display implementation, used for debugging only. Users should not need to see a [`Value`].
 impl fmt::Display for Value {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Value::Int(n) => write!(f, "{}", n),
-            Value::Bool(b) => write!(f, "{}", b),
-            Value::Str(s) => write!(f, "{:?}", s),
-            Value::Set(_)
-            | Value::Interval(_, _)
-            | Value::CrossProduct(_)
-            | Value::PowerSet(_)
-            | Value::MapSet(_, _) => {
-                write!(f, "Set(")?;
-                for (i, set) in self.as_set().iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{:#}", set)?;
-                }
-                write!(f, ")")
-            }
-            Value::Tuple(elems) => {
-                write!(f, "(")?;
-                for (i, elem) in elems.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{:#}", elem)?;
-                }
-                write!(f, ")")
-            }
-            Value::Record(fields) => {
-                write!(f, "{{ ")?;
-                for (i, (name, value)) in fields.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{}: {:#}", name, value)?;
-                }
-                write!(f, " }}")
+        write_value(f, self, false)
+    }
+}
+
+/// A value displayed with ANSI colors for its kind of literals (see
+/// [`Value::colored`]), when the terminal supports them.
+pub struct Colored<'a>(&'a Value);
+
+impl fmt::Display for Colored<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> std::fmt::Result {
+        write_value(f, self.0, true)
+    }
+}
+
+impl Value {
+    /// Display the value with colors, as [`fmt::Display`] does without them.
+    /// Colors are only used when the `colored` crate enables them, i.e. when
+    /// STDOUT is a terminal and `NO_COLOR` is not set.
+    pub fn colored(&self) -> Colored<'_> {
+        Colored(self)
+    }
+}
+
+/// Write a value, with colors if `colors` is set: literals are colored by
+/// their type, and names (of record fields and variants) are highlighted.
+fn write_value(f: &mut fmt::Formatter<'_>, value: &Value, colors: bool) -> fmt::Result {
+    use colored::Colorize;
+
+    let paint = |text: String, style: fn(&str) -> colored::ColoredString| {
+        if colors {
+            style(&text).to_string()
+        } else {
+            text
+        }
+    };
+    let write_all = |f: &mut fmt::Formatter<'_>, values: &mut dyn Iterator<Item = &Value>| {
+        for (i, value) in values.enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
             }
-            Value::Map(map) => {
-                write!(f, "Map(")?;
-                for (i, (key, value)) in map.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "Tup({:#}, {:#})", key, value)?;
+            write_value(f, value, colors)?;
+        }
+        Ok(())
+    };
+
+    match value {
+        Value::Int(n) => write!(f, "{}", paint(n.to_string(), |s| s.cyan())),
+        Value::Bool(b) => write!(f, "{}", paint(b.to_string(), |s| s.yellow())),
+        Value::Str(s) => write!(f, "{}", paint(format!("{:?}", s), |s| s.green())),
+        // Infinite sets can't be enumerated, so show how they were built
+        Value::Universe(universe) if universe.is_infinite() => write!(f, "{}", universe),
+        Value::CrossProduct(sets, _) if value.is_infinite() => {
+            write!(
+                f,
+                "tuples({})",
+                sets.iter().map(|set| format!("{:#}", set)).join(", ")
+            )
+        }
+        Value::PowerSet(base, _) if value.is_infinite() => write!(f, "powerset({:#})", base),
+        Value::MapSet(domain, range, _) if value.is_infinite() => {
+            write!(f, "setOfMaps({:#}, {:#})", domain, range)
+        }
+        Value::Set(_)
+        | Value::Interval(_, _)
+        | Value::CrossProduct(..)
+        | Value::PowerSet(..)
+        | Value::MapSet(..)
+        | Value::Universe(_)
+        | Value::BitSet(_, _) => {
+            write!(f, "Set(")?;
+            write_all(f, &mut value.as_set().iter())?;
+            write!(f, ")")
+        }
+        Value::Tuple(elems) => {
+            write!(f, "(")?;
+            write_all(f, &mut elems.iter())?;
+            write!(f, ")")
+        }
+        Value::Record(fields) => {
+            write!(f, "{{ ")?;
+            for (i, (name, value)) in fields.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
                 }
-                write!(f, ")")
+                write!(f, "{}: ", paint(name.to_string(), |s| s.blue()))?;
+                write_value(f, value, colors)?;
             }
-            Value::List(elems) => {
-                write!(f, "List(")?;
-                for (i, elem) in elems.iter().enumerate() {
-                    if i > 0 {
-                        write!(f, ", ")?;
-                    }
-                    write!(f, "{:#}", elem)?;
+            write!(f, " }}")
+        }
+        Value::Map(map) => {
+            write!(f, "Map(")?;
+            for (i, (key, value)) in map.iter().enumerate() {
+                if i > 0 {
+                    write!(f, ", ")?;
                 }
-                write!(f, ")")
+                write!(f, "Tup(")?;
+                write_value(f, key, colors)?;
+                write!(f, ", ")?;
+                write_value(f, value, colors)?;
+                write!(f, ")")?;
             }
-            Value::Lambda(_, _) => write!(f, "<lambda>"),
-            Value::Variant(label, value) => {
-                if let Value::Tuple(elems) = &**value {
-                    if elems.is_empty() {
-                        return write!(f, "{}", label);
-                    }
+            write!(f, ")")
+        }
+        Value::List(elems) => {
+            write!(f, "List(")?;
+            write_all(f, &mut elems.iter())?;
+            write!(f, ")")
+        }
+        Value::Lambda(_, _) => write!(f, "<lambda>"),
+        Value::Variant(label, value) => {
+            let label = paint(label.to_string(), |s| s.magenta());
+            if let Value::Tuple(elems) = &**value {
+                if elems.is_empty() {
+                    return write!(f, "{}", label);
                 }
-                write!(f, "{}({:#})", label, value)
             }
+            write!(f, "{}(", label)?;
+            write_value(f, value, colors)?;
+            write!(f, ")")
         }
     }
 }
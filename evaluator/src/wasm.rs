@@ -0,0 +1,112 @@
+//! JavaScript bindings for running the evaluator in the browser, compiled to
+//! WebAssembly so the Quint playground and the VSCode web extension don't
+//! need a backend. The crate is only a Rust library by default, so the module
+//! is built as a dynamic library, and then bound with `wasm-bindgen`:
+//!
+//! ```sh
+//! cargo rustc --lib --release --target wasm32-unknown-unknown --features wasm --crate-type cdylib
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/release/quint_evaluator.wasm
+//! ```
+//!
+//! These mirror the [`crate::server`] methods: a [`Spec`] is compiled once from
+//! the IR produced by the Quint typescript tool, and then expressions can be
+//! evaluated and small simulations run on it. Values and outcomes are plain JS
+//! objects, in the same formats as the JSON outputs (ITF for values). Errors
+//! are thrown as the serialized [`QuintError`], with its `code` and `message`.
+//!
+//! Simulation runs on the calling thread, so it should be kept small or moved
+//! to a web worker, to avoid blocking the page.
+
+use crate::evaluator::{Env, Interpreter};
+use crate::ir::{LookupTable, QuintError, QuintEx};
+use crate::server::{to_outcome, SimulateOptions};
+use crate::simulator::{ParsedQuint, ProgressUpdate};
+use serde::{de::DeserializeOwned, Serialize};
+use serde_wasm_bindgen::Serializer;
+use wasm_bindgen::prelude::*;
+
+/// A compiled spec.
+#[wasm_bindgen]
+pub struct Spec {
+    parsed: ParsedQuint,
+}
+
+#[wasm_bindgen]
+impl Spec {
+    /// Load a spec, given as the `parsed` field of the input of
+    /// `simulate-from-stdin`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(parsed: JsValue) -> Result<Spec, JsError> {
+        Ok(Spec {
+            parsed: from_js(parsed)?,
+        })
+    }
+
+    /// Evaluate an expression, with the definitions it refers to added to the
+    /// spec's table (if not already there), returning its value in ITF.
+    pub fn evaluate(&mut self, expr: JsValue, table: JsValue) -> Result<JsValue, JsValue> {
+        let expr: QuintEx = from_js(expr)?;
+        let table: LookupTable = if table.is_undefined() || table.is_null() {
+            LookupTable::default()
+        } else {
+            from_js(table)?
+        };
+        for (id, def) in table {
+            self.parsed.table.entry(id).or_insert(def);
+        }
+
+        let mut interpreter = Interpreter::new(&self.parsed.table);
+        let mut env = Env::new(interpreter.var_storage.clone());
+        let value = interpreter
+            .compile(&expr)
+            .execute(&mut env)
+            .map_err(quint_error)?;
+        to_js(&value.to_itf())
+    }
+
+    /// Simulate the spec with the given options (`{source, nruns, nsteps,
+    /// ntraces, seed}`), returning the same outcome as `simulate-from-stdin`.
+    /// The optional `progress` function is called with `{current, total,
+    /// states, violations}` after each sample.
+    pub fn simulate(
+        &self,
+        options: JsValue,
+        progress: Option<js_sys::Function>,
+    ) -> Result<JsValue, JsValue> {
+        let options: SimulateOptions = from_js(options)?;
+        let progress = progress.map(|progress| {
+            Box::new(move |update: ProgressUpdate| {
+                let update = serde_json::json!({
+                    "current": update.current,
+                    "total": update.total,
+                    "states": update.states,
+                    "violations": update.violations,
+                });
+                if let Ok(update) = to_js(&update) {
+                    // Exceptions in the callback don't stop the simulation
+                    let _ = progress.call1(&JsValue::NULL, &update);
+                }
+            }) as Box<dyn FnMut(ProgressUpdate)>
+        });
+
+        let result = self
+            .parsed
+            .simulate_with_config(&options.config(), progress);
+        to_js(&to_outcome(options.source, result))
+    }
+}
+
+fn from_js<T: DeserializeOwned>(value: JsValue) -> Result<T, JsError> {
+    serde_wasm_bindgen::from_value(value).map_err(|e| JsError::new(&e.to_string()))
+}
+
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, JsValue> {
+    // Maps are serialized as plain objects, as in JSON, rather than JS `Map`s
+    value
+        .serialize(&Serializer::json_compatible())
+        .map_err(JsValue::from)
+}
+
+fn quint_error(error: QuintError) -> JsValue {
+    to_js(&error).unwrap_or_else(|e| e)
+}
@@ -0,0 +1,111 @@
+//! Watching the files of a spec, to run a command again whenever they change
+//! (see the `--watch` option of the CLI).
+//!
+//! Files are polled for their modification times, which needs no support from
+//! the platform. A spec is watched with the files of the modules it imports,
+//! as listed by its source map (see [`crate::helpers::source_map`]). While
+//! the spec doesn't parse, its imports are unknown, so it is watched with the
+//! other Quint files in its directory instead, as they are usually next to it.
+//!
+//! Changes are not compiled incrementally: the command runs again, compiling
+//! the whole spec, as the Quint tool compiles specs as a whole.
+
+use crate::diagnostics::SourceMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// How often files are polled for changes by [`Watcher::wait`].
+pub const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+pub struct Watcher {
+    /// The files being watched, with their last modification times (`None`
+    /// for files that don't exist).
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl Watcher {
+    /// Watch the given files, as they are now.
+    pub fn new(paths: impl IntoIterator<Item = PathBuf>) -> Watcher {
+        let files = paths
+            .into_iter()
+            .map(|path| {
+                let modified = modified(&path);
+                (path, modified)
+            })
+            .collect();
+        Watcher { files }
+    }
+
+    /// Watch a spec and the files of the modules it imports, from the source
+    /// map of the spec.
+    pub fn for_sources(path: &Path, source_map: &SourceMap) -> Watcher {
+        let mut paths = vec![path.to_path_buf()];
+        for source in source_map.files().into_iter().map(PathBuf::from) {
+            if !paths.iter().any(|p| same_file(p, &source)) {
+                paths.push(source);
+            }
+        }
+        Watcher::new(paths)
+    }
+
+    /// Watch a spec and the other Quint files (i.e. `.qnt`) in its directory.
+    pub fn for_spec(path: &Path) -> io::Result<Watcher> {
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let mut paths = vec![path.to_path_buf()];
+        for entry in fs::read_dir(dir)? {
+            let sibling = entry?.path();
+            if sibling.extension().is_some_and(|ext| ext == "qnt")
+                && !paths.iter().any(|p| same_file(p, &sibling))
+            {
+                paths.push(sibling);
+            }
+        }
+        Ok(Watcher::new(paths))
+    }
+
+    pub fn files(&self) -> impl Iterator<Item = &Path> {
+        self.files.iter().map(|(path, _)| path.as_path())
+    }
+
+    /// The files that changed (or were created or removed) since they were
+    /// last checked.
+    pub fn changes(&mut self) -> Vec<PathBuf> {
+        let mut changes = Vec::new();
+        for (path, last) in &mut self.files {
+            let modified = modified(path);
+            if modified != *last {
+                *last = modified;
+                changes.push(path.clone());
+            }
+        }
+        changes
+    }
+
+    /// Block until some of the files change, returning them.
+    pub fn wait(&mut self) -> Vec<PathBuf> {
+        loop {
+            let changes = self.changes();
+            if !changes.is_empty() {
+                return changes;
+            }
+            thread::sleep(POLL_INTERVAL);
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+fn same_file(a: &Path, b: &Path) -> bool {
+    match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
@@ -0,0 +1,63 @@
+//! The aggregates of sets aren't built-ins of Quint, where `min` and `max`
+//! are defined by `basicSpells` and `sum` is a common name of parameters, so
+//! their IR is built by hand.
+
+mod common;
+
+use common::{app, int, ints};
+use quint_evaluator::evaluator::run;
+use quint_evaluator::ir::{ErrorKind, LookupTable, QuintEx};
+use quint_evaluator::value::Value;
+
+/// `opcode(Set(values))`
+fn of_set(opcode: &str, values: &[i64]) -> QuintEx {
+    app(1, opcode, vec![ints(10, "Set", values)])
+}
+
+/// `opcode(start.to(end))`
+fn of_interval(opcode: &str, start: i64, end: i64) -> QuintEx {
+    app(
+        1,
+        opcode,
+        vec![app(10, "to", vec![int(11, start), int(12, end)])],
+    )
+}
+
+fn eval(expr: &QuintEx) -> Result<Value, ErrorKind> {
+    run(&LookupTable::default(), expr).map_err(|error| error.kind)
+}
+
+#[test]
+fn min_and_max() {
+    assert_eq!(eval(&of_set("min", &[3, -2, 7])), Ok(Value::Int(-2)));
+    assert_eq!(eval(&of_set("max", &[3, -2, 7])), Ok(Value::Int(7)));
+    assert_eq!(eval(&of_set("min", &[])), Err(ErrorKind::NoElement));
+    assert_eq!(eval(&of_interval("max", 5, 4)), Err(ErrorKind::NoElement));
+
+    // Taken from the bounds, without iterating over the interval
+    let (min, max) = (i64::MIN, i64::MAX);
+    assert_eq!(eval(&of_interval("min", min, max)), Ok(Value::Int(min)));
+    assert_eq!(eval(&of_interval("max", min, max)), Ok(Value::Int(max)));
+}
+
+#[test]
+fn sum() {
+    assert_eq!(eval(&of_set("sum", &[3, -2, 7])), Ok(Value::Int(8)));
+    assert_eq!(eval(&of_set("sum", &[])), Ok(Value::Int(0)));
+    assert_eq!(eval(&of_interval("sum", 5, 4)), Ok(Value::Int(0)));
+    assert_eq!(eval(&of_interval("sum", -3, 4)), Ok(Value::Int(4)));
+
+    // In closed form, without iterating over the interval
+    assert_eq!(
+        eval(&of_interval("sum", 1, 1_000_000_000)),
+        Ok(Value::Int(500_000_000_500_000_000))
+    );
+    assert_eq!(
+        eval(&of_interval("sum", 1, i64::MAX)),
+        Err(ErrorKind::Arithmetic)
+    );
+    assert_eq!(
+        eval(&of_set("sum", &[i64::MAX, 1])),
+        Err(ErrorKind::Arithmetic)
+    );
+}
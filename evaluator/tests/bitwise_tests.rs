@@ -0,0 +1,58 @@
+//! Bitwise operators, which aren't built-ins of Quint, as user definitions
+//! with their names would clash with them, but IR from other frontends can
+//! use, so the IR here is built by hand.
+
+mod common;
+
+use common::ints;
+use quint_evaluator::evaluator::run;
+use quint_evaluator::ir::{ErrorKind, LookupTable};
+use quint_evaluator::value::Value;
+
+fn eval(opcode: &str, args: &[i64]) -> Result<i64, ErrorKind> {
+    match run(&LookupTable::default(), &ints(1, opcode, args)) {
+        Ok(Value::Int(result)) => Ok(result),
+        Ok(value) => panic!("Expected an integer, got {value}"),
+        Err(error) => Err(error.kind),
+    }
+}
+
+#[test]
+fn and_or_xor() {
+    assert_eq!(eval("bitAnd", &[0b1100, 0b1010]), Ok(0b1000));
+    assert_eq!(eval("bitOr", &[0b1100, 0b1010]), Ok(0b1110));
+    assert_eq!(eval("bitXor", &[0b1100, 0b1010]), Ok(0b0110));
+
+    // Negative integers have infinitely many leading ones
+    assert_eq!(eval("bitAnd", &[-1, 0b1010]), Ok(0b1010));
+    assert_eq!(eval("bitAnd", &[-4, 0b1111]), Ok(0b1100));
+    assert_eq!(eval("bitOr", &[-8, 0b0011]), Ok(-5));
+    assert_eq!(eval("bitXor", &[-1, 5]), Ok(-6));
+}
+
+#[test]
+fn shifts() {
+    assert_eq!(eval("shiftLeft", &[3, 4]), Ok(48));
+    assert_eq!(eval("shiftLeft", &[-3, 4]), Ok(-48));
+    assert_eq!(eval("shiftLeft", &[-1, 63]), Ok(i64::MIN));
+    assert_eq!(eval("shiftLeft", &[0, 1000]), Ok(0));
+    assert_eq!(eval("shiftLeft", &[1, 63]), Err(ErrorKind::Arithmetic));
+    assert_eq!(eval("shiftLeft", &[1, 64]), Err(ErrorKind::Arithmetic));
+    assert_eq!(eval("shiftLeft", &[1, -1]), Err(ErrorKind::Arithmetic));
+
+    assert_eq!(eval("shiftRight", &[48, 4]), Ok(3));
+    assert_eq!(eval("shiftRight", &[7, 1]), Ok(3));
+    // Rounded towards negative infinity
+    assert_eq!(eval("shiftRight", &[-7, 1]), Ok(-4));
+    assert_eq!(eval("shiftRight", &[5, 1000]), Ok(0));
+    assert_eq!(eval("shiftRight", &[-5, 1000]), Ok(-1));
+    assert_eq!(eval("shiftRight", &[1, -1]), Err(ErrorKind::Arithmetic));
+}
+
+#[test]
+fn pop_count() {
+    assert_eq!(eval("popCount", &[0]), Ok(0));
+    assert_eq!(eval("popCount", &[0b1011]), Ok(3));
+    assert_eq!(eval("popCount", &[i64::MAX]), Ok(63));
+    assert_eq!(eval("popCount", &[-1]), Err(ErrorKind::Arithmetic));
+}
@@ -0,0 +1,110 @@
+use std::cell::Cell;
+use std::rc::Rc;
+
+use quint_evaluator::browser::{Browser, Evaluate};
+use quint_evaluator::symbol::Symbol;
+use quint_evaluator::trace::Trace;
+use quint_evaluator::value::{ImmutableMap, Value};
+
+fn trace() -> Trace {
+    let state = |x: i64, s: &[i64]| {
+        Value::Record(ImmutableMap::from_iter(vec![
+            ("x".into(), Value::Int(x)),
+            (
+                "s".into(),
+                Value::List(s.iter().copied().map(Value::Int).collect()),
+            ),
+        ]))
+    };
+    Trace::new(vec![state(0, &[]), state(1, &[1]), state(2, &[1, 2])], true)
+}
+
+#[test]
+fn states_are_shown_with_their_changes() {
+    let mut browser = Browser::new(trace());
+    assert_eq!(browser.show(), "[State 0/2]\n  s: List()\n  x: 0");
+
+    assert_eq!(
+        browser.handle("next"),
+        Ok("[State 1/2]\n* s: List(1)\n* x: 1".to_string())
+    );
+    assert_eq!(
+        browser.handle("goto last"),
+        Ok("[State 2/2] (violation)\n* s: List(1, 2)\n* x: 2".to_string())
+    );
+    assert!(browser.handle("n").is_err());
+    assert_eq!(browser.step(), 2);
+
+    browser.handle("p 2").unwrap();
+    assert_eq!(browser.step(), 0);
+    assert!(browser.handle("goto 3").is_err());
+    assert!(browser.handle("frobnicate").is_err());
+}
+
+#[test]
+fn variables_are_filtered() {
+    let mut browser = Browser::new(trace());
+    assert_eq!(
+        browser.handle("filter x"),
+        Ok("[State 0/2]\n  x: 0".to_string())
+    );
+
+    // Searches only look at the variables shown
+    assert!(browser.handle("search List(1)").is_err());
+    browser.handle("filter").unwrap();
+    browser.handle("search List(1)").unwrap();
+    assert_eq!(browser.step(), 1);
+}
+
+#[test]
+fn nested_values_are_collapsed() {
+    let nested = Value::Record(ImmutableMap::from_iter(vec![(
+        "r".into(),
+        Value::Record(ImmutableMap::from_iter(vec![(
+            "s".into(),
+            Value::List([Value::Int(1), Value::Int(2)].into_iter().collect()),
+        )])),
+    )]));
+    let mut browser = Browser::new(Trace::new(vec![nested], false));
+
+    assert_eq!(
+        browser.show(),
+        "[State 0/0]\n  r: { s: List(<2 elements>) }"
+    );
+    assert_eq!(
+        browser.handle("expand r"),
+        Ok("[State 0/0]\n  r: { s: List(1, 2) }".to_string())
+    );
+    assert_eq!(
+        browser.handle("collapse"),
+        Ok("[State 0/0]\n  r: { s: List(<2 elements>) }".to_string())
+    );
+}
+
+#[test]
+fn expressions_are_evaluated_to_go_to_states() {
+    let mut browser = Browser::new(trace());
+    assert!(browser.handle("until x > 1").is_err());
+
+    // Compile `x > <n>`, counting the compilations
+    let compilations = Rc::new(Cell::new(0));
+    let counter = compilations.clone();
+    let mut browser = browser.with_compiler(Box::new(move |expr: &str| {
+        counter.set(counter.get() + 1);
+        let bound: i64 = expr
+            .trim_start_matches("x > ")
+            .parse()
+            .map_err(|_| "Invalid")?;
+        let evaluate: Evaluate = Box::new(move |state: &Value| {
+            let x = state.as_record_map()[&Symbol::from("x")].clone();
+            Ok(Value::Bool(x > Value::Int(bound)))
+        });
+        Ok(evaluate)
+    }));
+    browser.handle("until x > 1").unwrap();
+    assert_eq!(browser.step(), 2);
+    assert!(browser.handle("until x > 2").is_err());
+    assert_eq!(browser.step(), 2);
+    // Once for each command, not for each state
+    assert_eq!(compilations.get(), 2);
+}
@@ -0,0 +1,64 @@
+//! Helpers shared by the tests for building IR by hand, for what the Quint
+//! tool doesn't write (e.g. applications of `min`, or recursive definitions).
+
+#![allow(dead_code)]
+
+use quint_evaluator::ir::{LookupDefinition, LookupTable, QuintEx, QuintId, QuintLambdaParameter};
+
+pub fn int(id: QuintId, value: i64) -> QuintEx {
+    QuintEx::QuintInt { id, value }
+}
+
+pub fn app(id: QuintId, opcode: &str, args: Vec<QuintEx>) -> QuintEx {
+    QuintEx::QuintApp {
+        id,
+        opcode: opcode.into(),
+        args,
+    }
+}
+
+/// `opcode(values)`, i.e. the list or set of the given integers, with ids
+/// from `id`.
+pub fn ints(id: QuintId, opcode: &str, values: &[i64]) -> QuintEx {
+    let elems = values
+        .iter()
+        .zip(id + 1..)
+        .map(|(value, id)| int(id, *value))
+        .collect();
+    app(id, opcode, elems)
+}
+
+/// The lambda over the parameters with the given names (with ids from `id`),
+/// with a body built from references to them, and the lookup table entries
+/// of those references.
+pub fn lambda(
+    id: QuintId,
+    names: &[&str],
+    body: impl FnOnce(Vec<QuintEx>) -> QuintEx,
+) -> (QuintEx, LookupTable) {
+    let params = names
+        .iter()
+        .zip(id + 1..)
+        .map(|(name, id)| QuintLambdaParameter {
+            id,
+            name: (*name).into(),
+        })
+        .collect::<Vec<_>>();
+    let refs = (id + 1 + names.len() as QuintId..).zip(&params);
+    let table = refs
+        .clone()
+        .map(|(id, param)| (id, LookupDefinition::Param(param.clone())))
+        .collect();
+    let names = refs
+        .map(|(id, param)| QuintEx::QuintName {
+            id,
+            name: param.name.clone(),
+        })
+        .collect();
+    let lambda = QuintEx::QuintLambda {
+        id,
+        params,
+        expr: Box::new(body(names)),
+    };
+    (lambda, table)
+}
@@ -0,0 +1,189 @@
+use std::fs::File;
+
+use quint_evaluator::{
+    constants::value_from_json,
+    evaluator::{Env, Interpreter},
+    ir::{QuintEx, QuintOutput, QuintType, Row, RowField},
+    simulator::{ParsedQuint, SimulatorConfig},
+    value::Value,
+};
+use serde_json::json;
+
+fn ewd840() -> ParsedQuint {
+    let file = File::open("fixtures/ewd840.json").unwrap();
+    let output: QuintOutput = serde_json::from_reader(file).unwrap();
+    let def = |name: &str| output.find_definition_by_name(name).unwrap().expr.clone();
+    ParsedQuint {
+        init: def("init"),
+        step: def("step"),
+        invariant: def("Inv"),
+        invariants: Vec::new(),
+        temporal: Vec::new(),
+        witnesses: Vec::new(),
+        state_label: None,
+        components: Vec::new(),
+        table: output.table.clone(),
+        types: output.types.clone(),
+    }
+}
+
+fn simulate(constants: Vec<(String, serde_json::Value)>) -> Result<usize, String> {
+    let config = SimulatorConfig {
+        steps: 5,
+        samples: 10,
+        seed: Some(1),
+        constants,
+        ..SimulatorConfig::default()
+    };
+    ewd840()
+        .simulate_with_config(&config, None)
+        .map(|result| result.samples)
+        .map_err(|e| e.to_string())
+}
+
+#[test]
+fn constants_are_set_by_name() {
+    assert_eq!(simulate(vec![("N".to_string(), json!(3))]), Ok(10));
+    // Integers can be given as strings too, i.e. if they are too large
+    assert_eq!(simulate(vec![("N".to_string(), json!("4"))]), Ok(10));
+}
+
+#[test]
+fn constants_are_type_checked() {
+    let error = simulate(vec![("N".to_string(), json!(true))]).unwrap_err();
+    assert!(error.starts_with("[QNT516] Invalid value for constant N"));
+
+    let error = simulate(vec![("M".to_string(), json!(3))]).unwrap_err();
+    assert!(error.starts_with("[QNT516] Unknown constant M"));
+
+    let error = simulate(Vec::new()).unwrap_err();
+    assert!(error.starts_with("[QNT500] Uninitialized const N"));
+}
+
+#[test]
+fn instances_are_reinstantiated_without_compiling_again() {
+    let parsed = ewd840();
+    let nodes = parsed
+        .table
+        .iter()
+        .find(|(_, def)| def.name() == "Nodes")
+        .map(|(id, _)| QuintEx::QuintName {
+            id: *id,
+            name: "Nodes".into(),
+        })
+        .unwrap();
+    let n = |value: i64| vec![("N".to_string(), Value::Int(value))];
+
+    let mut instance = parsed.instantiate(&n(3)).unwrap();
+    assert_eq!(instance.evaluate(&nodes).unwrap().cardinality(), 3);
+    // Values computed from the previous constants are not reused
+    instance.set_constants(&n(5)).unwrap();
+    assert_eq!(instance.evaluate(&nodes).unwrap().cardinality(), 5);
+
+    let config = SimulatorConfig {
+        steps: 5,
+        samples: 10,
+        seed: Some(1),
+        ..SimulatorConfig::default()
+    };
+    assert_eq!(instance.simulate(&config).unwrap().samples, 10);
+    instance.set_constants(&n(4)).unwrap();
+    assert_eq!(instance.simulate(&config).unwrap().samples, 10);
+    assert_eq!(instance.evaluate(&nodes).unwrap().cardinality(), 4);
+
+    let error = instance
+        .set_constants(&[("N".to_string(), Value::Bool(true))])
+        .unwrap_err();
+    assert!(error
+        .to_string()
+        .starts_with("[QNT516] Invalid value for constant N"));
+    assert!(parsed
+        .instantiate(&[("M".to_string(), Value::Int(3))])
+        .is_err());
+}
+
+#[test]
+fn constants_are_restored_with_snapshots() {
+    let parsed = ewd840();
+    let nodes = parsed
+        .table
+        .iter()
+        .find(|(_, def)| def.name() == "Nodes")
+        .map(|(id, _)| QuintEx::QuintName {
+            id: *id,
+            name: "Nodes".into(),
+        })
+        .unwrap();
+    let n = |value: i64| vec![("N".to_string(), Value::Int(value))];
+
+    let mut interpreter = Interpreter::new(&parsed.table);
+    let mut env = Env::new(interpreter.var_storage.clone());
+    interpreter.set_constant_values(&n(3)).unwrap();
+    let snapshot = interpreter.snapshot(&env);
+
+    interpreter.set_constant_values(&n(5)).unwrap();
+    let size = |interpreter: &mut Interpreter, env: &mut Env| {
+        interpreter.eval(env, nodes.clone()).unwrap().cardinality()
+    };
+    assert_eq!(size(&mut interpreter, &mut env), 5);
+
+    interpreter.restore(&mut env, &snapshot);
+    assert_eq!(size(&mut interpreter, &mut env), 3);
+}
+
+#[test]
+fn values_are_read_by_type() {
+    let int = || QuintType::Int;
+    let row = |names: &[&str], typ: fn() -> QuintType| Row::Row {
+        fields: names
+            .iter()
+            .map(|name| RowField {
+                field_name: (*name).into(),
+                field_type: typ(),
+            })
+            .collect(),
+        other: Box::new(Row::Empty),
+    };
+
+    let set = QuintType::Set {
+        elem: Box::new(int()),
+    };
+    assert_eq!(
+        value_from_json(&json!([3, 1, 2, 1]), Some(&set)),
+        Ok(Value::Set([1, 2, 3].into_iter().map(Value::Int).collect()))
+    );
+    assert_eq!(
+        value_from_json(&json!({ "#set": [1] }), Some(&set)),
+        Ok(Value::Set([Value::Int(1)].into_iter().collect()))
+    );
+    assert!(value_from_json(&json!(["a"]), Some(&set)).is_err());
+
+    let record = QuintType::Rec {
+        fields: row(&["a", "b"], int),
+    };
+    assert!(value_from_json(&json!({ "a": 1, "b": 2 }), Some(&record)).is_ok());
+    assert!(value_from_json(&json!({ "a": 1 }), Some(&record)).is_err());
+    assert!(value_from_json(&json!({ "a": 1, "b": 2, "c": 3 }), Some(&record)).is_err());
+
+    let map = QuintType::Fun {
+        arg: Box::new(QuintType::Str),
+        res: Box::new(int()),
+    };
+    assert_eq!(
+        value_from_json(&json!({ "a": 1 }), Some(&map)),
+        value_from_json(&json!([["a", 1]]), Some(&map))
+    );
+
+    let unit = || QuintType::Tup { fields: Row::Empty };
+    let option = QuintType::Sum {
+        fields: row(&["None"], unit),
+    };
+    assert_eq!(
+        value_from_json(&json!("None"), Some(&option)),
+        Ok(Value::Variant(
+            "None".into(),
+            std::rc::Rc::new(Value::Tuple(Default::default()))
+        ))
+    );
+    assert!(value_from_json(&json!("Some"), Some(&option)).is_err());
+}
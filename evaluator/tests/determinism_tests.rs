@@ -0,0 +1,159 @@
+//! Seeded simulations must give the same traces on every platform. The hashes
+//! here were computed on x86_64, and must not change (see
+//! `quint_evaluator::hashing`).
+
+use std::path::Path;
+use std::thread;
+
+use quint_evaluator::helpers;
+use quint_evaluator::simulator::SimulatorConfig;
+use quint_evaluator::symbol::Symbol;
+use quint_evaluator::value::{ImmutableMap, Universe, Value};
+
+fn variant(label: &str, value: Value) -> Value {
+    Value::Variant(Symbol::from(label), value.into())
+}
+
+#[cfg(not(any(feature = "ahash", feature = "siphash")))]
+#[test]
+fn value_hashes_are_locked() {
+    use quint_evaluator::hashing::ValueHasher;
+    use std::hash::BuildHasher;
+
+    let hash = |value: Value| ValueHasher::default().hash_one(value);
+
+    assert_eq!(hash(Value::Int(42)), 0x5e77_c80c_6b95_bc72);
+    assert_eq!(hash(Value::Int(-1)), 0xae83_3e48_d8dd_f56b);
+    assert_eq!(hash(Value::Bool(true)), 0x5ec2_2ba5_6ef5_cb87);
+    assert_eq!(hash(Value::Str("quint".into())), 0xf09a_256b_f57f_a691);
+    assert_eq!(
+        hash(Value::Tuple(vec![Value::Int(1), Value::Bool(true)].into())),
+        0x4e2d_856f_89c9_337d
+    );
+    assert_eq!(
+        hash(Value::List((1..=3).map(Value::Int).collect())),
+        0x5172_842a_f386_09af
+    );
+    assert_eq!(hash(variant("Some", Value::Int(1))), 0x0658_5c56_3483_0342);
+    assert_eq!(hash(Value::Interval(1, 10)), 0xb138_587d_46d9_3824);
+    assert_eq!(hash(Value::Universe(Universe::Nat)), 0xfb6a_ef74_5c8f_42bc);
+}
+
+#[test]
+fn fingerprints_are_locked() {
+    assert_eq!(Value::Int(42).fingerprint(), 0x5e77_c80c_6b95_bc72);
+    assert_eq!(
+        Value::Str("quint".into()).fingerprint(),
+        0xf09a_256b_f57f_a691
+    );
+    assert_eq!(
+        Value::Set((1..=3).map(Value::Int).collect()).fingerprint(),
+        0x7809_9172_3572_b8b6
+    );
+    assert_eq!(Value::Interval(1, 3).fingerprint(), 0x7809_9172_3572_b8b6);
+    assert_eq!(
+        Value::Record(ImmutableMap::from_iter([
+            (Symbol::from("x"), Value::Int(1)),
+            (Symbol::from("y"), Value::Str("a".into())),
+        ]))
+        .fingerprint(),
+        0x8c7a_765c_0bb0_8636
+    );
+    assert_eq!(
+        Value::Map(ImmutableMap::from_iter([(
+            Value::Int(1),
+            Value::Bool(true)
+        )]))
+        .fingerprint(),
+        0x3157_ab53_eca2_0d07
+    );
+    assert_eq!(
+        variant(
+            "Some",
+            Value::List(vec![Value::Int(1), Value::Int(2)].into())
+        )
+        .fingerprint(),
+        0x680d_e094_36ed_0369
+    );
+}
+
+/// The fingerprints of the states of the best traces of a seeded simulation
+/// of tictactoe.
+fn simulate(seed: u64) -> Vec<Vec<u64>> {
+    let parsed = helpers::parse_from_path(
+        Path::new("fixtures/tictactoe.qnt"),
+        "init",
+        "step",
+        Some("inv"),
+        None,
+    )
+    .unwrap();
+    let config = SimulatorConfig {
+        steps: 10,
+        samples: 50,
+        n_traces: 3,
+        seed: Some(seed),
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    result
+        .best_traces
+        .iter()
+        .map(|trace| trace.vars().map(Value::fingerprint).collect())
+        .collect()
+}
+
+#[test]
+fn seeded_simulations_give_the_same_traces() {
+    let traces = simulate(0x42);
+    assert_eq!(traces.len(), 3);
+
+    // From a fresh interpreter, and on another thread, which has its own
+    // symbol cache
+    assert_eq!(simulate(0x42), traces);
+    assert_eq!(thread::spawn(|| simulate(0x42)).join().unwrap(), traces);
+}
+
+/// The states of the trace of a single sample of a seeded simulation.
+fn simulate_sample(seed: u64, sample: usize) -> Vec<Value> {
+    let parsed = helpers::parse_from_path(
+        Path::new("fixtures/seededPicks.qnt"),
+        "init",
+        "step",
+        Some("inv"),
+        None,
+    )
+    .unwrap();
+    let config = SimulatorConfig {
+        steps: 10,
+        samples: 3,
+        seed: Some(seed),
+        sample: Some(sample),
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    result.best_traces[0].vars().cloned().collect()
+}
+
+#[test]
+fn seeded_traces_are_locked() {
+    let xs = |states: &[Value]| {
+        states
+            .iter()
+            .map(|state| {
+                state
+                    .as_record_map()
+                    .get(&Symbol::from("x"))
+                    .unwrap()
+                    .as_int()
+            })
+            .collect::<Vec<_>>()
+    };
+    let fingerprint = |states: Vec<Value>| Value::List(states.into()).fingerprint();
+
+    let states = simulate_sample(0x42, 1);
+    assert_eq!(xs(&states), [0, 6, 8, 5, 6, 3, 8, 6, 2, 3, 2]);
+    assert_eq!(fingerprint(states), 0xf4e0_dd05_428f_540b);
+    assert_eq!(fingerprint(simulate_sample(0x42, 2)), 0x77c5_8c78_7f85_cc72);
+    assert_eq!(fingerprint(simulate_sample(0x42, 3)), 0x7088_2dd5_90e0_fe97);
+}
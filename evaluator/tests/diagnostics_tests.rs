@@ -0,0 +1,123 @@
+use quint_evaluator::diagnostics::{Diagnostic, SourceMap};
+use quint_evaluator::evaluator::{Env, Interpreter};
+use quint_evaluator::ir::{ErrorKind, LookupTable, QuintError, QuintEx};
+use serde_json::json;
+
+fn source_map() -> SourceMap {
+    SourceMap::from_json(&json!({
+        "sourceIndex": { "0": "spec.qnt" },
+        "map": {
+            "12": [0, { "line": 3, "col": 4, "index": 40 }, { "line": 3, "col": 10, "index": 46 }],
+            "13": [0, { "line": 5, "col": 2, "index": 60 }, {}],
+        },
+    }))
+    .unwrap()
+}
+
+#[test]
+fn evaluation_errors_are_located_with_the_source_map() {
+    let error = QuintError::new(ErrorKind::Arithmetic, "Division by zero").with_reference(12);
+    let diagnostic = Diagnostic::from_error(&error, &source_map());
+
+    assert_eq!(
+        serde_json::to_value(&diagnostic).unwrap(),
+        json!({
+            "file": "spec.qnt",
+            "range": {
+                "start": { "line": 3, "character": 4 },
+                "end": { "line": 3, "character": 11 },
+            },
+            "severity": 1,
+            "code": "QNT503",
+            "source": "quint",
+            "message": "Division by zero",
+        })
+    );
+
+    // Locations without an end are empty ranges
+    let error = QuintError::new(ErrorKind::Arithmetic, "Division by zero").with_reference(13);
+    let range = Diagnostic::from_error(&error, &source_map()).range;
+    assert_eq!(range.start, range.end);
+
+    // Errors without a location are at the start of an unknown file
+    let error = QuintError::new(ErrorKind::Unsupported, "Internal error");
+    let diagnostic = Diagnostic::from_error(&error, &source_map());
+    assert_eq!(diagnostic.file, None);
+    assert_eq!(diagnostic.range, Default::default());
+}
+
+#[test]
+fn locations_are_shown_with_1_based_positions() {
+    let error = QuintError::new(ErrorKind::Arithmetic, "Division by zero").with_reference(12);
+    let location = Diagnostic::from_error(&error, &source_map()).location();
+    assert_eq!(location.unwrap().to_string(), "spec.qnt:4:5");
+
+    let error = QuintError::new(ErrorKind::Unsupported, "Internal error");
+    assert_eq!(
+        Diagnostic::from_error(&error, &source_map()).location(),
+        None
+    );
+}
+
+#[test]
+fn compiled_expressions_keep_their_ids() {
+    let int = |id, value| QuintEx::QuintInt { id, value };
+    // 1 / 0, where the division is expression 12
+    let expr = QuintEx::QuintApp {
+        id: 12,
+        opcode: "idiv".into(),
+        args: vec![int(10, 1), int(11, 0)],
+    };
+    let table = LookupTable::default();
+    let mut interpreter = Interpreter::new(&table);
+    let compiled = interpreter.compile(&expr);
+    assert_eq!(compiled.id(), Some(12));
+
+    let mut env = Env::new(interpreter.var_storage.clone());
+    let error = compiled.execute(&mut env).unwrap_err();
+    let diagnostic = Diagnostic::from_error(&error, &source_map());
+    assert_eq!(diagnostic.location().unwrap().to_string(), "spec.qnt:4:5");
+}
+
+#[test]
+fn compilation_errors_are_read_from_the_cli_output() {
+    let loc = |line: u64| {
+        json!({
+            "source": "spec.qnt",
+            "start": { "line": line, "col": 0, "index": 0 },
+            "end": { "line": line, "col": 2, "index": 2 },
+        })
+    };
+    let diagnostic = Diagnostic::from_error_message(&json!({
+        "explanation": "[QNT404] Name 'x' not found",
+        "locs": [loc(1), loc(7)],
+    }));
+
+    assert_eq!(diagnostic.code, "QNT404");
+    assert_eq!(diagnostic.message, "Name 'x' not found");
+    assert_eq!(diagnostic.file.as_deref(), Some("spec.qnt"));
+    assert_eq!(diagnostic.range.start.line, 1);
+    assert_eq!(
+        serde_json::to_value(&diagnostic).unwrap()["relatedInformation"],
+        json!([{
+            "location": {
+                "uri": "spec.qnt",
+                "range": {
+                    "start": { "line": 7, "character": 0 },
+                    "end": { "line": 7, "character": 3 },
+                },
+            },
+            "message": "Name 'x' not found",
+        }])
+    );
+}
+
+#[test]
+fn invalid_source_maps_are_rejected() {
+    assert!(SourceMap::from_json(&json!({ "map": {} })).is_err());
+    assert!(SourceMap::from_json(&json!({
+        "sourceIndex": { "0": "spec.qnt" },
+        "map": { "12": [1, { "line": 3, "col": 4, "index": 40 }, {}] },
+    }))
+    .is_err());
+}
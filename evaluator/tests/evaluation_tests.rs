@@ -1,8 +1,11 @@
 use std::rc::Rc;
 
 use quint_evaluator::{
+    counters, enumeration,
     evaluator::{run, Env, EvalResult, Interpreter},
     helpers,
+    ir::ErrorKind,
+    output::{DebugEntry, Output},
     value::Value,
 };
 
@@ -87,14 +90,14 @@ fn assert_var_after_run(
 
     assert_eq!(run_result, Ok(Value::Bool(true)));
 
-    let storage = env.var_storage.borrow();
-    let var_value = storage
-        .next_vars
-        .values()
-        .find(|v| v.borrow().name == var_name)
+    let var_value = env
+        .var_storage
+        .borrow()
+        .state
+        .primed_value(var_name)
         .unwrap()
-        .borrow();
-    let var_value = var_value.clone().value.unwrap().normalize();
+        .clone()
+        .normalize();
 
     let parsed_expected = helpers::parse(&quint_content, "init", "step", None)?;
     let expected_def = parsed_expected.find_definition_by_name("expected")?;
@@ -146,12 +149,14 @@ fn multiplication() -> Result<(), Box<dyn std::error::Error>> {
 
 #[test]
 fn division() -> Result<(), Box<dyn std::error::Error>> {
-    assert_from_string("7 / 2", "3")
+    assert_from_string("7 / 2", "3")?;
+    assert_from_string("7 / 0", "undefined")
 }
 
 #[test]
 fn remainder() -> Result<(), Box<dyn std::error::Error>> {
-    assert_from_string("7 % 2", "1")
+    assert_from_string("7 % 2", "1")?;
+    assert_from_string("7 % 0", "undefined")
 }
 
 #[test]
@@ -165,6 +170,23 @@ fn power() -> Result<(), Box<dyn std::error::Error>> {
     assert_from_string("0^0", "undefined")
 }
 
+#[test]
+fn integer_overflow() -> Result<(), Box<dyn std::error::Error>> {
+    // i64::MAX and i64::MIN, built without literals that don't fit in a double
+    let max = "((2^62 - 1) * 2 + 1)";
+    let min = "(-(2^62) * 2)";
+    assert_from_string(&format!("{max} + 1"), "undefined")?;
+    assert_from_string(&format!("{min} - 1"), "undefined")?;
+    assert_from_string("4294967296 * 4294967296", "undefined")?;
+    assert_from_string("2^63", "undefined")?;
+    assert_from_string("2^62", "4611686018427387904")?;
+    assert_from_string("1^4294967296", "1")?;
+    assert_from_string("(-1)^4294967297", "-1")?;
+    assert_from_string(&format!("{min} / (-1)"), "undefined")?;
+    assert_from_string(&format!("-{min}"), "undefined")?;
+    assert_from_string(&format!("{min} % (-1)"), "0")
+}
+
 #[test]
 fn greater_than() -> Result<(), Box<dyn std::error::Error>> {
     assert_from_string("5 > 3", "true")?;
@@ -332,6 +354,29 @@ fn higher_order_operators_in_folds() -> Result<(), Box<dyn std::error::Error>> {
     assert_from_string(input, "20")
 }
 
+#[test]
+fn reentrant_operator_application() -> Result<(), Box<dyn std::error::Error>> {
+    // `apply` is called again while evaluating its own body, and `x` must
+    // still hold the outer argument after the inner call returns
+    let input = "def apply(f, x) = f(x) + x
+       apply(y => apply(z => z, y + 1), 5)";
+    assert_from_string(input, "17")
+}
+
+#[test]
+fn nested_higher_order_applications() -> Result<(), Box<dyn std::error::Error>> {
+    let input = "def twice(f, x) = f(f(x)) + x
+       twice(y => twice(z => z + 1, y), 0)";
+    assert_from_string(input, "6")
+}
+
+#[test]
+fn reentrant_lambda_in_folds() -> Result<(), Box<dyn std::error::Error>> {
+    let input = "def sumWith(s, f) = s.fold(0, (acc, i) => acc + f(i))
+       sumWith(1.to(3), n => sumWith(1.to(n), m => m))";
+    assert_from_string(input, "10")
+}
+
 // TODO: variables
 // #[test]
 // fn compile_variables() -> Result<(), Box<dyn std::error::Error>> {
@@ -570,6 +615,23 @@ fn set_forall_over_intervals() -> Result<(), Box<dyn std::error::Error>> {
     assert_from_string("1.to(3).forall(x => x >= 0)", "true")
 }
 
+#[test]
+fn quantifiers_stop_early_over_lazy_sets() -> Result<(), Box<dyn std::error::Error>> {
+    // These sets are too big to be enumerated, so these only finish if the
+    // quantifiers stop at the first witness/counterexample
+    assert_from_string("1.to(1000000000000).exists(x => x > 5)", "true")?;
+    assert_from_string("1.to(1000000000000).forall(x => x < 5)", "false")?;
+    assert_from_string("1.to(40).powerset().exists(s => s.size() > 0)", "true")?;
+    assert_from_string(
+        "tuples(1.to(100000), 1.to(100000)).forall(t => t._1 != t._2)",
+        "false",
+    )?;
+    assert_from_string(
+        "setOfMaps(1.to(10), 1.to(10)).exists(m => m.keys().size() == 10)",
+        "true",
+    )
+}
+
 #[test]
 fn set_map() -> Result<(), Box<dyn std::error::Error>> {
     // a bijection
@@ -640,6 +702,124 @@ fn set_fold() -> Result<(), Box<dyn std::error::Error>> {
     assert_from_string(input, "24")
 }
 
+#[test]
+fn set_fold_streams_over_lazy_sets() -> Result<(), Box<dyn std::error::Error>> {
+    let quint_content = "module main {
+          val input = 1.to(10_000_000).fold(0, (acc, i) => acc + i)
+          val tuples_input = tuples(1.to(1000), 1.to(1000)).fold(0, (acc, t) => acc + t._1 * t._2)
+          val init = true
+          val step = true
+        }";
+
+    let parsed = helpers::parse(quint_content, "init", "step", None)?;
+    let input_def = parsed.find_definition_by_name("input")?;
+    let tuples_def = parsed.find_definition_by_name("tuples_input")?;
+
+    counters::set_enabled(true);
+    counters::reset();
+    let value = run(&parsed.table, &input_def.expr);
+    let tuples_value = run(&parsed.table, &tuples_def.expr);
+    let enumerations = counters::get().set_enumerations;
+    counters::set_enabled(false);
+
+    assert_eq!(value, Ok(Value::Int(50_000_005_000_000)));
+    assert_eq!(tuples_value, Ok(Value::Int(250_500_250_000)));
+    // Materializing the sets would be a memory regression
+    assert_eq!(enumerations, 0);
+
+    Ok(())
+}
+
+#[test]
+fn set_builtins_iterate_over_lazy_sets() -> Result<(), Box<dyn std::error::Error>> {
+    let quint_content = "module main {
+          val input = 1.to(1000).map(i => i % 10).filter(i => i > 4).size()
+          val pairs = tuples(1.to(10), 1.to(10)).filter(t => t._1 == t._2).setToMap().keys().size()
+          val squares = 1.to(5).mapBy(i => i * i).get(4)
+          val init = true
+          val step = true
+        }";
+
+    let parsed = helpers::parse(quint_content, "init", "step", None)?;
+    let defs = ["input", "pairs", "squares"].map(|name| parsed.find_definition_by_name(name));
+
+    counters::set_enabled(true);
+    counters::reset();
+    let values = defs
+        .into_iter()
+        .map(|def| Ok(run(&parsed.table, &def?.expr)?.to_string()))
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+    let enumerations = counters::get().set_enumerations;
+    counters::set_enabled(false);
+
+    assert_eq!(values, ["5", "10", "16"].map(String::from));
+    assert_eq!(enumerations, 0);
+
+    Ok(())
+}
+
+#[test]
+fn enumerations_over_the_limit_fail() -> Result<(), Box<dyn std::error::Error>> {
+    let quint_content = "module main {
+          val large = 1.to(1000).union(Set(0)).size()
+          val small = 1.to(10).union(Set(0)).size()
+          val iterated = 1.to(1000).map(i => i % 10).size()
+          val init = true
+          val step = true
+        }";
+
+    let parsed = helpers::parse(quint_content, "init", "step", None)?;
+    let large = parsed.find_definition_by_name("large")?;
+    let small = parsed.find_definition_by_name("small")?;
+    let iterated = parsed.find_definition_by_name("iterated")?;
+
+    enumeration::set_limit(Some(100));
+    counters::set_enabled(true);
+    counters::reset();
+    let large_value = run(&parsed.table, &large.expr);
+    // The limit is checked before building the set
+    let enumerations = counters::get().set_enumerations;
+    counters::set_enabled(false);
+    let small_value = run(&parsed.table, &small.expr);
+    let iterated_value = run(&parsed.table, &iterated.expr);
+    enumeration::set_limit(None);
+    let unlimited_value = run(&parsed.table, &large.expr);
+
+    let error = large_value.unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InfiniteSet);
+    assert_eq!(
+        error.message,
+        "Enumerating a set of 1000 elements, over the limit of 100"
+    );
+    assert!(error.reference.is_some());
+    assert_eq!(enumerations, 0);
+    assert_eq!(small_value, Ok(Value::Int(11)));
+    assert_eq!(iterated_value, Ok(Value::Int(10)));
+    assert_eq!(unlimited_value, Ok(Value::Int(1001)));
+
+    Ok(())
+}
+
+#[test]
+fn sets_that_cant_be_enumerated_fail() -> Result<(), Box<dyn std::error::Error>> {
+    let quint_content = "module main {
+          val flattened = Set(Set(1), Int).flatten()
+          val excluded = Set(Set(1)).exclude(1.to(100).powerset())
+          val intersected = Set(Set(1)).intersect(1.to(100).powerset())
+          val init = true
+          val step = true
+        }";
+
+    let parsed = helpers::parse(quint_content, "init", "step", None)?;
+    for name in ["flattened", "excluded", "intersected"] {
+        let def = parsed.find_definition_by_name(name)?;
+        let error = run(&parsed.table, &def.expr).unwrap_err();
+        assert_eq!(error.kind, ErrorKind::InfiniteSet, "{name}");
+    }
+
+    Ok(())
+}
+
 #[test]
 fn set_powerset() -> Result<(), Box<dyn std::error::Error>> {
     assert_from_string(
@@ -676,6 +856,19 @@ fn set_powerset_cardinality() -> Result<(), Box<dyn std::error::Error>> {
     assert_from_string("2.to(5).powerset().size()", "16")
 }
 
+#[test]
+fn set_size_too_large_to_count() -> Result<(), Box<dyn std::error::Error>> {
+    // 2^(2^100) elements, more than a 64-bit integer can hold
+    let huge = "1.to(100).powerset().powerset()";
+    assert_from_string(&format!("{huge}.size()"), "undefined")?;
+    assert_from_string(&format!("{huge}.size() > 10"), "true")?;
+    assert_from_string(&format!("{huge}.size() <= 10"), "false")?;
+    assert_from_string(&format!("{huge}.size() == 10"), "false")?;
+    assert_from_string(&format!("10 < {huge}.size()"), "true")?;
+    assert_from_string("2.to(5).powerset().size() >= 16", "true")?;
+    assert_from_string("Nat.size() > 10", "undefined")
+}
+
 // TODO builtin values tests
 
 #[test]
@@ -867,6 +1060,35 @@ fn all_lists_up_to() -> Result<(), Box<dyn std::error::Error>> {
     assert_from_string("Set(1).allListsUpTo(0)", "Set(List())")
 }
 
+#[test]
+fn set_choose_some() -> Result<(), Box<dyn std::error::Error>> {
+    assert_from_string("Set(1, 2, 3).chooseSome()", "1")?;
+    assert_from_string("Set(1, 2, 3).filter(x => x > 2).chooseSome()", "3")?;
+    assert_from_string("3.to(7).chooseSome()", "3")?;
+    assert_from_string("Set(Set(2, 3), Set(1, 5)).chooseSome()", "Set(1, 5)")?;
+    assert_from_string("Set(1, 2).powerset().chooseSome()", "Set()")?;
+    assert_from_string("Set().chooseSome()", "undefined")
+}
+
+#[test]
+fn infinite_sets() -> Result<(), Box<dyn std::error::Error>> {
+    assert_from_string("5.in(Nat)", "true")?;
+    assert_from_string("(-5).in(Nat)", "false")?;
+    assert_from_string("(-5).in(Int)", "true")?;
+    assert_from_string("Nat.subseteq(Int)", "true")?;
+    assert_from_string("Int.subseteq(Nat)", "false")?;
+    assert_from_string("0.to(10).subseteq(Nat)", "true")?;
+    assert_from_string("Nat.subseteq(0.to(10))", "false")?;
+    assert_from_string("Set(-1, 1).intersect(Nat)", "Set(1)")?;
+    assert_from_string("(1, true).in(tuples(Nat, Bool))", "true")?;
+    assert_from_string("Set(1, 2).powerset().subseteq(Nat.powerset())", "true")?;
+    assert_from_string("Int.isFinite()", "false")?;
+    assert_from_string("Bool.isFinite()", "true")?;
+    assert_from_string("Bool.size()", "2")?;
+    assert_from_string("Nat.size()", "undefined")?;
+    assert_from_string("Int.exists(x => x > 0)", "undefined")
+}
+
 #[test]
 fn set_get_only_element() -> Result<(), Box<dyn std::error::Error>> {
     assert_from_string("Set(5).getOnlyElement()", "5")?;
@@ -1214,3 +1436,34 @@ fn run_q_debug_single_arg() -> Result<(), Box<dyn std::error::Error>> {
          run run1 = (n' = 1).then(n' = q::debug(n + 1))",
     )
 }
+
+#[test]
+fn q_debug_values_are_collected() -> Result<(), Box<dyn std::error::Error>> {
+    let quint_content = "module main {
+      var n: int
+      val init = true
+      val step = true
+      run run1 = (n' = 1).then(n' = q::debug(\"n plus one\", n + 1))
+    }";
+
+    let parsed = helpers::parse(quint_content, "init", "step", None)?;
+    let run_def = parsed.find_definition_by_name("run1")?;
+    let mut interpreter = Interpreter::new(&parsed.table);
+    let mut env = Env::new(Rc::clone(&interpreter.var_storage));
+    let (output, entries) = Output::collecting();
+    env.output = output;
+
+    assert_eq!(
+        interpreter.eval(&mut env, run_def.expr.clone()),
+        Ok(Value::Bool(true))
+    );
+    assert_eq!(
+        *entries.lock().unwrap(),
+        [DebugEntry {
+            label: "n plus one".to_string(),
+            value: serde_json::json!({ "#bigint": "2" }),
+        }]
+    );
+
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use quint_evaluator::explorer::ExplorerConfig;
+use quint_evaluator::helpers::{self, Properties};
+
+#[test]
+fn tictactoe_explored_completely() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let result = parsed.explore(20).unwrap();
+    // A game has at most 9 moves, after which the state doesn't change
+    assert!(result.result);
+    assert!(result.complete);
+    assert!(result.depth <= 9);
+}
+
+#[test]
+fn tictactoe_shortest_violation() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    let result = parsed.explore(20).unwrap();
+    assert!(!result.result);
+    // X can win after its third move, which is the fifth move in the game
+    let trace = result.counterexample.unwrap();
+    assert_eq!(trace.states.len(), 6);
+    assert!(trace.violation);
+}
+
+#[test]
+fn tictactoe_bounded_exploration() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    // X can't win in 2 moves, so the invariant holds up to that depth
+    let result = parsed.explore(2).unwrap();
+    assert!(result.result);
+    assert!(!result.complete);
+    assert_eq!(result.depth, 2);
+}
+
+#[test]
+fn tictactoe_state_graph() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path_with_properties(
+        file_path,
+        "init",
+        "step",
+        Some("inv"),
+        &Properties {
+            state_label: Some("boardEmpty"),
+            ..Properties::default()
+        },
+        None,
+    )
+    .unwrap();
+    let result = parsed
+        .explore_with_config(&ExplorerConfig {
+            max_depth: 1,
+            graph: true,
+            ..ExplorerConfig::default()
+        })
+        .unwrap();
+    let graph = result.graph.unwrap();
+
+    // X starts in one of the 4 corners
+    assert_eq!(graph.nodes.len(), 5);
+    assert_eq!(graph.initial, [0]);
+    assert_eq!(graph.nodes[0], "true");
+    assert!(graph.nodes[1..].iter().all(|label| label == "false"));
+    assert_eq!(graph.edges.len(), 4);
+    assert!(graph
+        .edges
+        .iter()
+        .all(|(from, _, action)| *from == 0 && action.as_deref() == Some("MoveX")));
+    assert!(graph.to_dot().contains("s0 -> s1 [label=\"MoveX\"];"));
+}
@@ -0,0 +1,105 @@
+#![cfg(feature = "ffi")]
+
+use std::ffi::{c_char, CStr, CString};
+use std::fs::File;
+use std::ptr;
+
+use quint_evaluator::{
+    ffi::*,
+    ir::QuintOutput,
+    simulator::{NamedProperty, ParsedQuint},
+};
+use serde_json::{json, Value as Json};
+
+fn tictactoe() -> ParsedQuint {
+    let file = File::open("fixtures/tictactoe.json").unwrap();
+    let output: QuintOutput = serde_json::from_reader(file).unwrap();
+    let def = |name: &str| output.find_definition_by_name(name).unwrap().expr.clone();
+    ParsedQuint {
+        init: def("init"),
+        step: def("step"),
+        invariant: def("XHasNotWon"),
+        invariants: vec![NamedProperty {
+            name: "XHasNotWon".to_string(),
+            expr: def("XHasNotWon"),
+        }],
+        temporal: Vec::new(),
+        witnesses: Vec::new(),
+        state_label: None,
+        components: Vec::new(),
+        table: output.table.clone(),
+        types: output.types.clone(),
+    }
+}
+
+fn c_string(json: Json) -> CString {
+    CString::new(json.to_string()).unwrap()
+}
+
+/// Take a string returned by the C interface, freeing it.
+fn take(string: *mut c_char) -> Json {
+    assert!(!string.is_null());
+    let json = serde_json::from_str(unsafe { CStr::from_ptr(string) }.to_str().unwrap()).unwrap();
+    unsafe { quint_string_free(string) };
+    json
+}
+
+#[test]
+fn ffi_evaluates_and_simulates_specs() {
+    let mut error = ptr::null_mut();
+    let parsed = c_string(json!(tictactoe()));
+    let spec = unsafe { quint_spec_new(parsed.as_ptr(), &mut error) };
+    assert!(!spec.is_null() && error.is_null());
+
+    let expr = c_string(json!({
+        "kind": "app", "id": 1, "opcode": "iadd",
+        "args": [{ "kind": "int", "id": 2, "value": 1 }, { "kind": "int", "id": 3, "value": 2 }]
+    }));
+    let value = unsafe { quint_spec_evaluate(spec, expr.as_ptr(), ptr::null(), &mut error) };
+    assert_eq!(take(value), json!({ "#bigint": "3" }));
+
+    let options =
+        c_string(json!({ "source": "tictactoe.qnt", "nruns": 100, "nsteps": 10, "seed": 42 }));
+    let simulator = unsafe { quint_simulator_new(spec, options.as_ptr(), &mut error) };
+    // The simulator has its own copy of the spec
+    unsafe { quint_spec_free(spec) };
+    let outcome = take(unsafe { quint_simulator_run(simulator, &mut error) });
+    assert_eq!(outcome["status"], "violation");
+    unsafe { quint_simulator_free(simulator) };
+}
+
+#[test]
+fn ffi_cancels_simulations_before_they_start() {
+    let mut error = ptr::null_mut();
+    let parsed = c_string(json!(tictactoe()));
+    let spec = unsafe { quint_spec_new(parsed.as_ptr(), &mut error) };
+    let options =
+        c_string(json!({ "source": "tictactoe.qnt", "nruns": 100, "nsteps": 10, "seed": 42 }));
+    let simulator = unsafe { quint_simulator_new(spec, options.as_ptr(), &mut error) };
+    unsafe { quint_spec_free(spec) };
+
+    unsafe { quint_simulator_cancel(simulator) };
+    let outcome = take(unsafe { quint_simulator_run(simulator, &mut error) });
+    assert_eq!(outcome["samples"], 0);
+    // The cancellation only applies to one run
+    let outcome = take(unsafe { quint_simulator_run(simulator, &mut error) });
+    assert_eq!(outcome["status"], "violation");
+    unsafe { quint_simulator_free(simulator) };
+}
+
+#[test]
+fn ffi_reports_errors() {
+    let mut error = ptr::null_mut();
+    let parsed = CString::new("{").unwrap();
+    let spec = unsafe { quint_spec_new(parsed.as_ptr(), &mut error) };
+    assert!(spec.is_null());
+    assert_eq!(take(error)["code"], "QNT501");
+
+    let parsed = c_string(json!(tictactoe()));
+    let spec = unsafe { quint_spec_new(parsed.as_ptr(), ptr::null_mut()) };
+    let expr = c_string(json!({ "kind": "name", "id": 1, "name": "undefined" }));
+    let value = unsafe { quint_spec_evaluate(spec, expr.as_ptr(), ptr::null(), &mut error) };
+    assert!(value.is_null());
+    assert!(take(error)["message"].is_string());
+    unsafe { quint_spec_free(spec) };
+}
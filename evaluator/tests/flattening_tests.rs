@@ -0,0 +1,131 @@
+use std::fs::File;
+
+use quint_evaluator::{
+    evaluator::{Env, Interpreter},
+    ir::QuintOutput,
+    printer::to_quint,
+    value::Value,
+};
+use serde_json::{json, Value as Json};
+
+fn name(id: u64, name: &str) -> Json {
+    json!({ "kind": "name", "id": id, "name": name })
+}
+
+fn iadd(id: u64, args: Vec<Json>) -> Json {
+    json!({ "kind": "app", "id": id, "opcode": "iadd", "args": args })
+}
+
+/// A definition as the lookup table has it for the modules importing it.
+fn imported(def: &Json, imported_from: Json, namespace: &str) -> Json {
+    let mut def = def.clone();
+    def["importedFrom"] = imported_from;
+    def["namespaces"] = json!([namespace]);
+    def
+}
+
+/// A spec with a module `main` importing `B` under a qualifier and
+/// instantiating `A`:
+///
+/// ```quint
+/// module A { const N: int; val x = N + 1 }
+/// module B { pure def double(a) = a + a }
+/// module main {
+///   import B as Q
+///   import A(N = 2) as I
+///   val y = Q::double(I::x)
+/// }
+/// ```
+fn spec() -> QuintOutput {
+    let n = json!({ "kind": "const", "id": 1, "name": "N", "typeAnnotation": { "kind": "int" } });
+    let x = json!({
+        "kind": "def", "id": 2, "name": "x", "qualifier": "val",
+        "expr": iadd(5, vec![name(3, "N"), json!({ "kind": "int", "id": 4, "value": 1 })]),
+    });
+    let double = json!({
+        "kind": "def", "id": 20, "name": "double", "qualifier": "puredef",
+        "expr": {
+            "kind": "lambda", "id": 21,
+            "params": [{ "id": 22, "name": "a" }],
+            "expr": iadd(23, vec![name(24, "a"), name(25, "a")]),
+        },
+    });
+    let param = json!({ "id": 22, "name": "a" });
+    let overrides = json!([[
+        { "id": 32, "name": "N" },
+        { "kind": "int", "id": 33, "value": 2 },
+    ]]);
+    serde_json::from_value(json!({
+        "modules": [
+            { "name": "A", "declarations": [n, x] },
+            { "name": "B", "declarations": [double] },
+            { "name": "main", "declarations": [
+                { "kind": "import", "id": 30, "protoName": "B", "qualifiedName": "Q" },
+                {
+                    "kind": "instance", "id": 31, "protoName": "A", "qualifiedName": "I",
+                    "overrides": overrides,
+                },
+                {
+                    "kind": "def", "id": 40, "name": "y", "qualifier": "val",
+                    "expr": {
+                        "kind": "app", "id": 41, "opcode": "Q::double",
+                        "args": [name(42, "I::x")],
+                    },
+                },
+            ] },
+        ],
+        "table": {
+            "3": n,
+            "24": param,
+            "25": param,
+            "32": n,
+            "41": imported(&double, json!({ "kind": "import", "id": 30 }), "Q"),
+            "42": imported(&x, json!({ "kind": "instance", "id": 31, "overrides": overrides }), "I"),
+        },
+        "main": "main",
+    }))
+    .unwrap()
+}
+
+const FLATTENED_MAIN: &str = "module main {
+  pure def Q::double(Q::a) = Q::a + Q::a
+  pure val I::N = 2
+  val I::x = I::N + 1
+  val y = Q::double(I::x)
+}";
+
+#[test]
+fn imports_and_instances_are_flattened() {
+    let mut output = spec();
+    assert!(output.needs_flattening());
+    output.flatten();
+    assert!(!output.needs_flattening());
+
+    let quint = to_quint(&output);
+    assert!(quint.ends_with(FLATTENED_MAIN), "{quint}");
+
+    // The flattened module is evaluated like the original one
+    let y = output.find_definition_by_name("y").unwrap().expr.clone();
+    let mut interpreter = Interpreter::new(&output.table);
+    let mut env = Env::new(interpreter.var_storage.clone());
+    assert_eq!(
+        interpreter.compile(&y).execute(&mut env).unwrap(),
+        Value::Int(6)
+    );
+}
+
+#[test]
+fn imported_modules_are_found_in_the_lookup_table() {
+    // `quint compile` only writes the main module
+    let mut output = spec();
+    output.modules.retain(|module| module.name == "main");
+    output.flatten();
+    assert_eq!(to_quint(&output), FLATTENED_MAIN);
+
+    let file = File::open("fixtures/jmt.json").unwrap();
+    let mut output: QuintOutput = serde_json::from_reader(file).unwrap();
+    assert!(output.needs_flattening());
+    output.flatten();
+    assert!(!output.needs_flattening());
+    output.validate().unwrap();
+}
@@ -0,0 +1,39 @@
+//! Fresh identifiers, which aren't a built-in of Quint, as user definitions
+//! named `fresh` would clash with them, but IR from other frontends can use,
+//! so the IR here is built by hand.
+
+mod common;
+
+use common::app;
+use quint_evaluator::evaluator::{run, Env, Interpreter};
+use quint_evaluator::ir::LookupTable;
+use quint_evaluator::value::Value;
+
+#[test]
+fn fresh_identifiers_are_distinct() {
+    // (fresh(), fresh(), fresh())
+    let fresh = |id| app(id, "fresh", vec![]);
+    let expr = app(1, "Tup", vec![fresh(2), fresh(3), fresh(4)]);
+    assert_eq!(
+        run(&LookupTable::default(), &expr),
+        Ok(Value::Tuple(
+            vec![Value::Int(0), Value::Int(1), Value::Int(2)].into()
+        ))
+    );
+}
+
+#[test]
+fn restored_environments_give_the_same_identifiers() {
+    let table = LookupTable::default();
+    let mut interpreter = Interpreter::new(&table);
+    let mut env = Env::new(interpreter.var_storage.clone());
+    let fresh = interpreter.compile(&app(1, "fresh", vec![]));
+
+    assert_eq!(fresh.execute(&mut env), Ok(Value::Int(0)));
+    let snapshot = env.snapshot();
+    assert_eq!(fresh.execute(&mut env), Ok(Value::Int(1)));
+    assert_eq!(fresh.execute(&mut env), Ok(Value::Int(2)));
+
+    env.restore(&snapshot);
+    assert_eq!(fresh.execute(&mut env), Ok(Value::Int(1)));
+}
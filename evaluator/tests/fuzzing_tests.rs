@@ -0,0 +1,48 @@
+#![cfg(feature = "arbitrary")]
+
+use std::rc::Rc;
+
+use arbitrary::{Arbitrary, Unstructured};
+use quint_evaluator::fuzzing::{check_as_set, check_consistency, check_evaluation};
+use quint_evaluator::ir::QuintEx;
+use quint_evaluator::value::{CachedCardinality, ImmutableSet, Value};
+use rand::rngs::StdRng;
+use rand::{RngCore, SeedableRng};
+
+/// Run a check on inputs generated from random bytes, as the fuzz targets do
+/// with the bytes given by the fuzzer.
+fn check_random_inputs<T: for<'a> Arbitrary<'a>>(check: impl Fn(T)) {
+    let mut rng = StdRng::seed_from_u64(0x42);
+    let mut bytes = vec![0; 1024];
+    for _ in 0..500 {
+        rng.fill_bytes(&mut bytes);
+        if let Ok(input) = T::arbitrary(&mut Unstructured::new(&bytes)) {
+            check(input);
+        }
+    }
+}
+
+#[test]
+fn random_values_are_consistent() {
+    check_random_inputs(|(a, b, c): (Value, Value, Value)| check_consistency(&a, &b, &c));
+}
+
+#[test]
+fn random_sets_enumerate_to_equal_sets() {
+    check_random_inputs(|value: Value| check_as_set(&value));
+}
+
+#[test]
+fn random_expressions_evaluate() {
+    check_random_inputs(|expr: QuintEx| check_evaluation(&expr));
+}
+
+#[test]
+fn representations_of_the_same_set_are_consistent() {
+    let interval = Value::Interval(1, 3);
+    let enumerated = Value::Set((1..=3).map(Value::Int).collect::<ImmutableSet<_>>());
+    let powerset = Value::PowerSet(Rc::new(Value::Interval(1, 2)), CachedCardinality::default());
+    check_consistency(&interval, &enumerated, &powerset);
+    check_as_set(&interval);
+    check_as_set(&powerset);
+}
@@ -0,0 +1,118 @@
+#![cfg(feature = "grpc")]
+
+use std::fs::File;
+
+use quint_evaluator::{
+    grpc::{
+        proto::{
+            evaluator_server::Evaluator, simulate_event::Event, CancelRequest, CompileRequest,
+            ReleaseRequest, SimulateRequest,
+        },
+        EvaluatorService,
+    },
+    ir::QuintOutput,
+    simulator::{NamedProperty, ParsedQuint},
+};
+use serde_json::Value as Json;
+use tokio_stream::StreamExt;
+use tonic::{Code, Request};
+
+fn tictactoe() -> ParsedQuint {
+    let file = File::open("fixtures/tictactoe.json").unwrap();
+    let output: QuintOutput = serde_json::from_reader(file).unwrap();
+    let def = |name: &str| output.find_definition_by_name(name).unwrap().expr.clone();
+    ParsedQuint {
+        init: def("init"),
+        step: def("step"),
+        invariant: def("XHasNotWon"),
+        invariants: vec![NamedProperty {
+            name: "XHasNotWon".to_string(),
+            expr: def("XHasNotWon"),
+        }],
+        temporal: Vec::new(),
+        witnesses: Vec::new(),
+        state_label: None,
+        components: Vec::new(),
+        table: output.table.clone(),
+        types: output.types.clone(),
+    }
+}
+
+#[tokio::test]
+async fn grpc_streams_violations_and_outcome() {
+    let service = EvaluatorService::default();
+    let parsed_json = serde_json::to_string(&tictactoe()).unwrap();
+    let spec = service
+        .compile(Request::new(CompileRequest { parsed_json }))
+        .await
+        .unwrap()
+        .into_inner()
+        .spec;
+
+    let request = SimulateRequest {
+        spec,
+        source: "tictactoe.qnt".to_string(),
+        samples: 100,
+        steps: 10,
+        traces: 1,
+        seed: Some(42),
+    };
+    let mut events = service
+        .simulate(Request::new(request))
+        .await
+        .unwrap()
+        .into_inner();
+
+    let mut kinds = Vec::new();
+    let mut outcome = None;
+    while let Some(event) = events.next().await {
+        match event.unwrap().event.unwrap() {
+            Event::Started(_) => kinds.push("started"),
+            Event::Progress(_) => {}
+            Event::Trace(trace) => {
+                assert_eq!(trace.invariant, 0);
+                kinds.push("trace");
+            }
+            Event::Outcome(o) => {
+                kinds.push("outcome");
+                outcome = Some(o);
+            }
+        }
+    }
+    assert_eq!(kinds, vec!["started", "trace", "outcome"]);
+
+    let outcome = outcome.unwrap();
+    assert!(!outcome.cancelled);
+    let outcome: Json = serde_json::from_str(&outcome.outcome_json).unwrap();
+    assert_eq!(outcome["status"], "violation");
+
+    service
+        .release(Request::new(ReleaseRequest { spec }))
+        .await
+        .unwrap();
+    let released = service.release(Request::new(ReleaseRequest { spec })).await;
+    assert_eq!(released.unwrap_err().code(), Code::NotFound);
+}
+
+#[tokio::test]
+async fn grpc_cancels_only_running_simulations() {
+    let service = EvaluatorService::default();
+    let cancelled = service
+        .cancel(Request::new(CancelRequest { simulation: 42 }))
+        .await
+        .unwrap()
+        .into_inner()
+        .cancelled;
+    assert!(!cancelled);
+
+    let request = SimulateRequest {
+        spec: 42,
+        source: "tictactoe.qnt".to_string(),
+        samples: 1,
+        steps: 1,
+        traces: 1,
+        seed: None,
+    };
+    let unknown = service.simulate(Request::new(request)).await;
+    assert_eq!(unknown.unwrap_err().code(), Code::NotFound);
+}
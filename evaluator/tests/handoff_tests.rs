@@ -0,0 +1,50 @@
+use std::path::PathBuf;
+
+use quint_evaluator::handoff::{Handoff, APALACHE_CONFIG_FILE, HANDOFF_FILE, MODULE_FILE};
+use serde_json::json;
+
+fn handoff(invariants: &[&str]) -> Handoff {
+    Handoff {
+        source: PathBuf::from("/specs/tictactoe.qnt"),
+        main: None,
+        init: "init".to_string(),
+        step: "step".to_string(),
+        invariants: invariants.iter().map(|i| i.to_string()).collect(),
+        max_steps: 20,
+        samples: 10000,
+        seed: 0x42,
+    }
+}
+
+#[test]
+fn handoff_checks_the_simulated_properties() {
+    let handoff = handoff(&["inv", "NotStalemate"]);
+
+    assert_eq!(handoff.invariant(), "inv and NotStalemate");
+    assert_eq!(handoff.apalache_config()["checker"]["length"], 20);
+    assert_eq!(
+        handoff.verify_command().join(" "),
+        "quint verify /specs/tictactoe.qnt --init init --step step \
+         --invariant inv and NotStalemate --max-steps 20 --apalache-config apalache.json"
+    );
+}
+
+#[test]
+fn handoff_is_written_to_a_directory() {
+    let dir = tempfile::tempdir().unwrap();
+    let module = json!({ "modules": [], "table": {}, "main": "tictactoe" });
+    handoff(&[]).write(dir.path(), &module).unwrap();
+
+    let read = |name: &str| -> serde_json::Value {
+        serde_json::from_str(&std::fs::read_to_string(dir.path().join(name)).unwrap()).unwrap()
+    };
+    assert_eq!(read(MODULE_FILE), module);
+    assert_eq!(
+        read(APALACHE_CONFIG_FILE)["checker"]["inv"],
+        json!(["q::inv"])
+    );
+    let written = read(HANDOFF_FILE);
+    assert_eq!(written["maxSteps"], 20);
+    assert_eq!(written["command"][7], "--invariant");
+    assert_eq!(written["command"][8], "true");
+}
@@ -0,0 +1,81 @@
+mod common;
+
+use std::hash::BuildHasher;
+
+use common::{app, int, ints};
+use quint_evaluator::evaluator::run;
+use quint_evaluator::hashing::ValueHasher;
+use quint_evaluator::ir::{ErrorKind, LookupTable, QuintEx};
+use quint_evaluator::value::{ImmutableMap, ImmutableSet, Value};
+
+fn set(elems: impl IntoIterator<Item = i64>) -> ImmutableSet<Value> {
+    elems.into_iter().map(Value::Int).collect()
+}
+
+#[test]
+fn identical_sets_have_the_same_hash() {
+    let forward = set(0..1000);
+    let backward = set((0..1000).rev());
+    let mut shrunk = set(0..2000);
+    (1000..2000).for_each(|i| {
+        shrunk.remove(&Value::Int(i));
+    });
+
+    for other in [backward, shrunk] {
+        assert!(forward.iter().eq(other.iter()));
+        let hash = |s: &ImmutableSet<Value>| ValueHasher::default().hash_one(Value::Set(s.clone()));
+        assert_eq!(hash(&forward), hash(&other));
+    }
+}
+
+#[test]
+fn fingerprints_dont_depend_on_representations() {
+    let interval = Value::Interval(1, 3);
+    let enumerated = Value::Set(set(1..=3));
+    assert_eq!(interval.fingerprint(), enumerated.fingerprint());
+    assert_ne!(
+        Value::Set(set([1, 4])).fingerprint(),
+        Value::Set(set([2, 3])).fingerprint()
+    );
+
+    let map = |key: Value| Value::Map(ImmutableMap::from_iter([(key, Value::Bool(true))]));
+    assert_eq!(map(interval).fingerprint(), map(enumerated).fingerprint());
+    assert_ne!(
+        map(Value::Int(1)).fingerprint(),
+        map(Value::Set(set([1]))).fingerprint()
+    );
+}
+
+/// `hashOf`, which isn't a built-in of Quint, as user definitions named
+/// `hashOf` would clash with it, but IR from other frontends can use, so the
+/// IR here is built by hand.
+#[test]
+fn specs_hash_values_by_their_fingerprints() {
+    let table = LookupTable::default();
+    let hash_of = |arg| run(&table, &app(1, "hashOf", vec![arg])).map_err(|error| error.kind);
+
+    // hashOf(1.to(3)) and hashOf(Set(3, 1, 2))
+    let interval = app(10, "to", vec![int(11, 1), int(12, 3)]);
+    let enumerated = ints(10, "Set", &[3, 1, 2]);
+    let fingerprint = Value::Set(set(1..=3)).fingerprint() as i64;
+    assert_eq!(hash_of(interval), Ok(Value::Int(fingerprint)));
+    assert_eq!(hash_of(enumerated), Ok(Value::Int(fingerprint)));
+
+    // hashOf((1, () => 2))
+    let operator = QuintEx::QuintLambda {
+        id: 12,
+        params: vec![],
+        expr: Box::new(int(13, 2)),
+    };
+    let tuple = app(10, "Tup", vec![int(11, 1), operator]);
+    assert_eq!(hash_of(tuple), Err(ErrorKind::InvalidArgument));
+}
+
+#[cfg(feature = "siphash")]
+#[test]
+fn siphash_key_cannot_change_after_hashing() {
+    use quint_evaluator::hashing::set_siphash_key;
+
+    ValueHasher::default().hash_one(Value::Int(1));
+    assert!(!set_siphash_key((1, 2)));
+}
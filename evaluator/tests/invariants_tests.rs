@@ -0,0 +1,52 @@
+#![cfg(feature = "invariants")]
+
+use quint_evaluator::invariants::check;
+use quint_evaluator::symbol::Symbol;
+use quint_evaluator::value::{ImmutableMap, ImmutableSet, Value};
+
+fn set(elems: impl IntoIterator<Item = Value>) -> Value {
+    Value::Set(elems.into_iter().collect::<ImmutableSet<_>>())
+}
+
+fn ints(start: i64, end: i64) -> Value {
+    set((start..=end).map(Value::Int))
+}
+
+#[test]
+fn normalized_elements_are_consistent() {
+    assert_eq!(check(&ints(1, 20)), Ok(()));
+    assert_eq!(check(&set([ints(1, 3), ints(2, 4), ints(1, 2)])), Ok(()));
+
+    let records = (1..=10).map(|i| {
+        Value::Record(ImmutableMap::from_iter([(
+            Symbol::from("elems"),
+            ints(1, i),
+        )]))
+    });
+    assert_eq!(check(&set(records)), Ok(()));
+}
+
+#[test]
+fn intervals_in_sets_are_reported() {
+    let result = check(&set([Value::Interval(1, 3)]));
+    assert!(result.unwrap_err().contains("is not normalized"));
+}
+
+#[test]
+fn equal_elements_with_different_representations_are_reported() {
+    // Both are normalized on their own, but a record holding an interval is
+    // not, so these two equal records are different keys of the map
+    let record =
+        |elems: Value| Value::Record(ImmutableMap::from_iter([(Symbol::from("elems"), elems)]));
+    let map = Value::Map(ImmutableMap::from_iter([
+        (record(Value::Interval(1, 3)), Value::Int(1)),
+        (record(ints(1, 3)), Value::Int(2)),
+    ]));
+    assert!(check(&map).is_err());
+}
+
+#[test]
+fn other_values_are_not_checked() {
+    assert_eq!(check(&Value::Interval(1, 3)), Ok(()));
+    assert_eq!(check(&Value::Int(1)), Ok(()));
+}
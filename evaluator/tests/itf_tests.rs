@@ -0,0 +1,227 @@
+use quint_evaluator::trace::{Alternative, State, StepMeta, Trace};
+use quint_evaluator::value::{ImmutableMap, ImmutableVec, Value};
+use serde_json::json;
+use std::rc::Rc;
+
+#[test]
+fn value_to_itf() {
+    let value = Value::Record(ImmutableMap::from_iter(vec![
+        ("n".into(), Value::Int(9_007_199_254_740_993)),
+        ("s".into(), Value::Interval(1, 3)),
+        (
+            "t".into(),
+            Value::Tuple(ImmutableVec::from(vec![
+                Value::Bool(true),
+                Value::Str("a".into()),
+            ])),
+        ),
+        (
+            "m".into(),
+            Value::Map(ImmutableMap::from_iter(vec![
+                (Value::Int(2), Value::List(ImmutableVec::new())),
+                (
+                    Value::Int(1),
+                    Value::List(ImmutableVec::from(vec![Value::Int(0)])),
+                ),
+            ])),
+        ),
+        (
+            "v".into(),
+            Value::Variant("None".into(), Rc::new(Value::Tuple(ImmutableVec::new()))),
+        ),
+    ]));
+
+    assert_eq!(
+        value.to_itf(),
+        json!({
+            "n": { "#bigint": "9007199254740993" },
+            "s": { "#set": [{ "#bigint": "1" }, { "#bigint": "2" }, { "#bigint": "3" }] },
+            "t": { "#tup": [true, "a"] },
+            "m": { "#map": [
+                [{ "#bigint": "1" }, [{ "#bigint": "0" }]],
+                [{ "#bigint": "2" }, []],
+            ] },
+            "v": { "tag": "None", "value": { "#tup": [] } },
+        })
+    );
+}
+
+#[test]
+fn trace_to_itf() {
+    let state = |x| Value::Record(ImmutableMap::from_iter(vec![("x".into(), Value::Int(x))]));
+    let trace = Trace::new(vec![state(0), state(1)], true);
+
+    let itf = trace.to_itf("spec.qnt".to_string());
+
+    assert_eq!(itf["#meta"]["format"], "ITF");
+    assert_eq!(itf["#meta"]["source"], "spec.qnt");
+    assert_eq!(itf["#meta"]["status"], "violation");
+    assert_eq!(itf["vars"], json!(["x"]));
+    assert_eq!(
+        itf["states"],
+        json!([
+            { "#meta": { "index": 0 }, "x": { "#bigint": "0" } },
+            { "#meta": { "index": 1 }, "x": { "#bigint": "1" } },
+        ])
+    );
+}
+
+#[test]
+fn value_from_itf() {
+    let value = Value::Record(ImmutableMap::from_iter(vec![
+        ("n".into(), Value::Int(-9_007_199_254_740_993)),
+        ("s".into(), Value::Interval(1, 3)),
+        (
+            "m".into(),
+            Value::Map(ImmutableMap::from_iter(vec![(
+                Value::Tuple(ImmutableVec::from(vec![Value::Bool(false)])),
+                Value::List(ImmutableVec::from(vec![Value::Str("a".into())])),
+            )])),
+        ),
+        (
+            "v".into(),
+            Value::Variant("Some".into(), Rc::new(Value::Int(1))),
+        ),
+    ]));
+
+    assert_eq!(Value::from_itf(&value.to_itf()), Ok(value));
+
+    // Plain JSON numbers are also accepted as integers
+    assert_eq!(Value::from_itf(&json!(42)), Ok(Value::Int(42)));
+    assert!(Value::from_itf(&json!({ "#bigint": "1267650600228229401496703205376" })).is_err());
+}
+
+#[test]
+fn trace_from_itf() {
+    let state = |x| Value::Record(ImmutableMap::from_iter(vec![("x".into(), Value::Int(x))]));
+    let trace = Trace::new(vec![state(0), state(1)], true);
+
+    let itf = trace.clone().to_itf("spec.qnt".to_string());
+
+    assert_eq!(Trace::from_itf(&itf), Ok(trace));
+}
+
+#[test]
+fn trace_steps_and_seed_in_itf() {
+    let state = |x, action: &str| State {
+        vars: Value::Record(ImmutableMap::from_iter(vec![("x".into(), Value::Int(x))])),
+        meta: Some(StepMeta {
+            action_taken: Some(action.to_string()),
+            action_id: None,
+            alternatives: vec![Alternative {
+                index: 1,
+                name: Some(action.to_string()),
+            }],
+            nondet_picks: vec![("y".to_string(), Value::Int(x))],
+        }),
+    };
+    let trace = Trace {
+        states: vec![state(0, "init"), state(5, "add")],
+        violation: false,
+        seed: Some(0x2a),
+    };
+
+    let itf = trace.clone().to_itf("spec.qnt".to_string());
+
+    assert_eq!(itf["#meta"]["seed"], "0x2a");
+    assert_eq!(itf["vars"], json!(["x"]));
+    assert_eq!(
+        itf["states"][1]["#meta"],
+        json!({
+            "index": 1,
+            "actionTaken": "add",
+            "nondetPicks": { "y": { "#bigint": "5" } },
+            "alternatives": [{ "index": 1, "name": "add" }],
+        })
+    );
+    assert_eq!(Trace::from_itf(&itf), Ok(trace.clone()));
+
+    // Written as in the typescript tool with `--mbt`
+    let itf = trace.clone().to_mbt_itf("spec.qnt".to_string());
+    assert_eq!(
+        itf["vars"],
+        json!(["mbt::actionTaken", "mbt::nondetPicks", "x"])
+    );
+    assert_eq!(itf["states"][1]["mbt::actionTaken"], "add");
+    assert_eq!(
+        itf["states"][1]["mbt::nondetPicks"],
+        json!({ "y": { "#bigint": "5" } })
+    );
+    assert_eq!(Trace::from_itf(&itf), Ok(trace));
+}
+
+#[test]
+fn trace_render_shows_changes() {
+    let state = |x, y| {
+        Value::Record(ImmutableMap::from_iter(vec![
+            ("y".into(), Value::Int(y)),
+            ("x".into(), Value::Int(x)),
+        ]))
+    };
+    let trace = Trace::new(vec![state(0, 0), state(1, 0), state(1, 0)], false);
+
+    assert_eq!(
+        trace.render(false),
+        "[State 0]\n  x: 0\n  y: 0\n[State 1]\n  x: 1\n[State 2] (no changes)\n"
+    );
+    assert_eq!(
+        trace.render(true),
+        "[State 0]\n  x: 0\n  y: 0\n[State 1]\n  x: 1\n  y: 0\n[State 2]\n  x: 1\n  y: 0\n"
+    );
+}
+
+#[test]
+fn trace_render_shows_steps() {
+    let vars = |x| Value::Record(ImmutableMap::from_iter(vec![("x".into(), Value::Int(x))]));
+    let mut trace = Trace::new(vec![vars(0), vars(2), vars(2)], false);
+    trace.states[0].meta = Some(StepMeta {
+        action_taken: Some("init".to_string()),
+        ..StepMeta::default()
+    });
+    let alternatives = vec![
+        Alternative {
+            index: 0,
+            name: Some("move".to_string()),
+        },
+        Alternative {
+            index: 1,
+            name: None,
+        },
+    ];
+    trace.states[1].meta = Some(StepMeta {
+        action_taken: Some("move".to_string()),
+        alternatives: alternatives.clone(),
+        nondet_picks: vec![("d".to_string(), Value::Int(2))],
+        ..StepMeta::default()
+    });
+    trace.states[2].meta = Some(StepMeta {
+        action_taken: Some("move".to_string()),
+        alternatives,
+        nondet_picks: vec![("d".to_string(), Value::Int(0))],
+        ..StepMeta::default()
+    });
+
+    assert_eq!(
+        trace.render(false),
+        "[State 0] init\n  x: 0\n[State 1] move > #1\n  nondet d = 2\n  x: 2\n\
+         [State 2] move > #1 (no changes)\n  nondet d = 0\n"
+    );
+}
+
+#[test]
+fn colored_values_only_differ_in_colors() {
+    let value = Value::Record(ImmutableMap::from_iter(vec![
+        ("n".into(), Value::Int(1)),
+        (
+            "v".into(),
+            Value::Variant("Some".into(), Rc::new(Value::Str("a".into()))),
+        ),
+    ]));
+
+    colored::control::set_override(true);
+    let colored = value.colored().to_string();
+    colored::control::set_override(false);
+
+    assert!(colored.contains("\u{1b}["), "{colored:?}");
+    assert_eq!(value.colored().to_string(), value.to_string());
+}
@@ -0,0 +1,110 @@
+//! List operators and `setToSortedList`, which aren't built-ins of Quint, as
+//! user definitions with their names would clash with them (i.e. `takeWhile`
+//! from `basicSpells`), but IR from other frontends can use, so the IR here is
+//! built by hand.
+
+mod common;
+
+use common::{app, int, ints, lambda};
+use quint_evaluator::evaluator::run;
+use quint_evaluator::ir::{ErrorKind, LookupTable};
+use quint_evaluator::value::Value;
+
+fn int_list(values: &[i64]) -> Value {
+    Value::List(values.iter().copied().map(Value::Int).collect())
+}
+
+fn pair(a: i64, b: i64) -> Value {
+    Value::Tuple(vec![Value::Int(a), Value::Int(b)].into())
+}
+
+#[test]
+fn zip() {
+    // List(1, 2, 3).zip(List(4, 5))
+    let expr = app(
+        1,
+        "zip",
+        vec![ints(10, "List", &[1, 2, 3]), ints(20, "List", &[4, 5])],
+    );
+    assert_eq!(
+        run(&LookupTable::default(), &expr),
+        Ok(Value::List(vec![pair(1, 4), pair(2, 5)].into()))
+    );
+}
+
+#[test]
+fn enumerate() {
+    // List(7, 8).enumerate()
+    let expr = app(1, "enumerate", vec![ints(10, "List", &[7, 8])]);
+    assert_eq!(
+        run(&LookupTable::default(), &expr),
+        Ok(Value::List(vec![pair(0, 7), pair(1, 8)].into()))
+    );
+}
+
+#[test]
+fn sort_by_keeps_the_order_of_equal_keys() {
+    // List(5, 3, 4, 6, 1).sortBy(x => x % 3)
+    let (key, table) = lambda(20, &["x"], |x| {
+        app(30, "imod", vec![x[0].clone(), int(31, 3)])
+    });
+    let expr = app(1, "sortBy", vec![ints(10, "List", &[5, 3, 4, 6, 1]), key]);
+    assert_eq!(run(&table, &expr), Ok(int_list(&[3, 6, 4, 1, 5])));
+}
+
+#[test]
+fn take_and_drop_while() {
+    // x => x < 3
+    let (predicate, table) = lambda(20, &["x"], |x| {
+        app(30, "ilt", vec![x[0].clone(), int(31, 3)])
+    });
+    let apply = |opcode, values: &[i64]| {
+        let list = ints(10, "List", values);
+        run(&table, &app(1, opcode, vec![list, predicate.clone()]))
+    };
+
+    assert_eq!(apply("takeWhile", &[1, 2, 3, 1]), Ok(int_list(&[1, 2])));
+    assert_eq!(apply("takeWhile", &[1, 2]), Ok(int_list(&[1, 2])));
+    assert_eq!(apply("dropWhile", &[1, 2, 3, 1]), Ok(int_list(&[3, 1])));
+}
+
+#[test]
+fn chunks() {
+    let table = LookupTable::default();
+    let chunks = |values: &[i64], size| {
+        let list = ints(10, "List", values);
+        run(&table, &app(1, "chunks", vec![list, int(2, size)])).map_err(|error| error.kind)
+    };
+
+    assert_eq!(
+        chunks(&[1, 2, 3, 4, 5], 2),
+        Ok(Value::List(
+            vec![int_list(&[1, 2]), int_list(&[3, 4]), int_list(&[5])].into()
+        ))
+    );
+    assert_eq!(
+        chunks(&[1, 2, 3, 4, 5], 5),
+        Ok(Value::List(vec![int_list(&[1, 2, 3, 4, 5])].into()))
+    );
+    assert_eq!(chunks(&[], 3), Ok(Value::List(vec![].into())));
+    assert_eq!(chunks(&[1, 2, 3, 4, 5], 0), Err(ErrorKind::InvalidArgument));
+}
+
+#[test]
+fn sets_to_sorted_lists() {
+    let table = LookupTable::default();
+    let sorted = |set| run(&table, &app(1, "setToSortedList", vec![set]));
+
+    assert_eq!(
+        sorted(ints(10, "Set", &[3, -1, 2])),
+        Ok(int_list(&[-1, 2, 3]))
+    );
+    assert_eq!(
+        sorted(ints(10, "Set", &[2, 3, -1])),
+        Ok(int_list(&[-1, 2, 3]))
+    );
+    assert_eq!(sorted(ints(10, "Set", &[])), Ok(int_list(&[])));
+
+    let interval = app(10, "to", vec![int(11, 1), int(12, 4)]);
+    assert_eq!(sorted(interval), Ok(int_list(&[1, 2, 3, 4])));
+}
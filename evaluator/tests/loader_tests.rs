@@ -0,0 +1,55 @@
+use std::fs::File;
+
+use quint_evaluator::loader::{ir_version, load};
+use serde_json::{json, Value as Json};
+
+fn tictactoe() -> Json {
+    let file = File::open("fixtures/tictactoe.json").unwrap();
+    serde_json::from_reader(file).unwrap()
+}
+
+fn error(json: Json) -> String {
+    let error = load(json).unwrap_err();
+    assert_eq!(error.code(), "QNT517");
+    error.message
+}
+
+#[test]
+fn versions_are_detected() {
+    let mut json = tictactoe();
+    assert_eq!(ir_version(&json).unwrap(), (0, 24, 0));
+
+    json["version"] = json!("v0.25.2");
+    assert_eq!(ir_version(&json).unwrap(), (0, 25, 2));
+    assert_eq!(load(json.clone()).unwrap().main, "tictactoe");
+
+    // IR written before Quint wrote the main module
+    let mut json = tictactoe();
+    json.as_object_mut().unwrap().remove("main");
+    assert_eq!(ir_version(&json).unwrap(), (0, 23, 0));
+    assert_eq!(load(json).unwrap().main, "tictactoe");
+}
+
+#[test]
+fn incompatible_versions_are_rejected() {
+    let mut json = tictactoe();
+    json["version"] = json!("0.26.0");
+    assert!(error(json.clone()).starts_with("IR written by Quint 0.26.0, which is newer"));
+
+    json["version"] = json!("0.18.0");
+    assert!(error(json.clone()).starts_with("IR written by Quint 0.18.0, which is older"));
+
+    json["version"] = json!("latest");
+    assert_eq!(error(json), "Malformed IR: can't read the version latest");
+}
+
+#[test]
+fn malformed_fields_are_reported_with_their_path() {
+    let mut json = tictactoe();
+    json["modules"][0]["declarations"][0]["id"] = json!("one");
+    let message = error(json);
+    assert!(
+        message.starts_with("Malformed IR: can't read modules[0].declarations[0]"),
+        "{message}"
+    );
+}
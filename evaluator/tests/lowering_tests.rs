@@ -0,0 +1,91 @@
+use std::fs::File;
+
+use quint_evaluator::{
+    ir::{QuintEx, QuintOutput},
+    lowering::{lower, simplify},
+    printer::{expr_to_quint, to_quint},
+    simulator::{ParsedQuint, SimulatorConfig},
+};
+use serde_json::json;
+
+fn ewd840() -> QuintOutput {
+    let file = File::open("fixtures/ewd840.json").unwrap();
+    serde_json::from_reader(file).unwrap()
+}
+
+fn app(opcode: &str, args: Vec<QuintEx>) -> QuintEx {
+    QuintEx::QuintApp {
+        id: 0,
+        opcode: opcode.into(),
+        args,
+    }
+}
+
+fn int(value: i64) -> QuintEx {
+    QuintEx::QuintInt { id: 0, value }
+}
+
+fn name(name: &str) -> QuintEx {
+    QuintEx::QuintName {
+        id: 0,
+        name: name.into(),
+    }
+}
+
+#[test]
+fn constants_are_replaced_by_their_values() {
+    let mut output = ewd840();
+    lower(&mut output, &[("N".to_string(), json!(3))]).unwrap();
+
+    let quint = to_quint(&output);
+    assert!(!quint.contains("const N"), "{quint}");
+    assert!(quint.contains("val Nodes = 0.to(2)"), "{quint}");
+
+    // The lowered spec runs without values for its constants
+    let def = |name: &str| output.find_definition_by_name(name).unwrap().expr.clone();
+    let parsed = ParsedQuint {
+        init: def("init"),
+        step: def("step"),
+        invariant: def("Inv"),
+        invariants: Vec::new(),
+        temporal: Vec::new(),
+        witnesses: Vec::new(),
+        state_label: None,
+        components: Vec::new(),
+        table: output.table.clone(),
+        types: output.types.clone(),
+    };
+    let config = SimulatorConfig {
+        steps: 5,
+        samples: 10,
+        seed: Some(1),
+        ..SimulatorConfig::default()
+    };
+    assert!(parsed.simulate_with_config(&config, None).is_ok());
+}
+
+#[test]
+fn unknown_constants_are_rejected() {
+    let error = lower(&mut ewd840(), &[("M".to_string(), json!(3))]).unwrap_err();
+    assert_eq!(error.code(), "QNT516");
+}
+
+#[test]
+fn operators_on_literals_are_folded() {
+    let expr = app(
+        "ite",
+        vec![
+            app("ilt", vec![int(1), app("iadd", vec![int(1), int(2)])]),
+            app(
+                "and",
+                vec![name("x"), QuintEx::QuintBool { id: 0, value: true }],
+            ),
+            name("y"),
+        ],
+    );
+    assert_eq!(expr_to_quint(&simplify(expr)), "x");
+
+    // Overflows are left to the evaluator
+    let expr = app("imul", vec![int(i64::MAX), int(2)]);
+    assert_eq!(expr_to_quint(&simplify(expr)), format!("{} * 2", i64::MAX));
+}
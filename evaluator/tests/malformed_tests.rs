@@ -0,0 +1,82 @@
+//! Type-checked specs never give values of the wrong kind to an operator, but
+//! malformed IR (i.e. from other frontends) can, so its IR is built by hand.
+//! These are reported as errors, instead of panicking.
+
+mod common;
+
+use common::{app, int, ints};
+use quint_evaluator::evaluator::run;
+use quint_evaluator::ir::{ErrorKind, LookupTable, QuintEx};
+use quint_evaluator::value::Value;
+
+fn eval(expr: &QuintEx) -> Result<Value, ErrorKind> {
+    run(&LookupTable::default(), expr).map_err(|error| error.kind)
+}
+
+fn bool(id: u64, value: bool) -> QuintEx {
+    QuintEx::QuintBool { id, value }
+}
+
+#[test]
+fn operands_of_the_wrong_kind_are_malformed() {
+    let sum = app(1, "iadd", vec![int(2, 1), bool(3, true)]);
+    assert_eq!(eval(&sum), Err(ErrorKind::MalformedIr));
+
+    let negation = app(1, "not", vec![int(2, 1)]);
+    assert_eq!(eval(&negation), Err(ErrorKind::MalformedIr));
+
+    let head = app(1, "head", vec![ints(2, "Set", &[1])]);
+    assert_eq!(eval(&head), Err(ErrorKind::MalformedIr));
+
+    let condition = app(1, "ite", vec![int(2, 1), int(3, 2), int(4, 3)]);
+    assert_eq!(eval(&condition), Err(ErrorKind::MalformedIr));
+
+    let size = app(2, "size", vec![ints(3, "Set", &[1])]);
+    let comparison = app(1, "igt", vec![size, bool(4, true)]);
+    assert_eq!(eval(&comparison), Err(ErrorKind::MalformedIr));
+}
+
+#[test]
+fn sets_are_checked_before_enumerating_them() {
+    let product = app(1, "tuples", vec![int(2, 1), ints(3, "Set", &[1])]);
+    assert_eq!(eval(&product), Err(ErrorKind::MalformedIr));
+
+    let powerset = app(1, "powerset", vec![int(2, 1)]);
+    assert_eq!(eval(&powerset), Err(ErrorKind::MalformedIr));
+
+    let flattened = app(1, "flatten", vec![ints(2, "Set", &[1, 2])]);
+    assert_eq!(eval(&flattened), Err(ErrorKind::MalformedIr));
+
+    let size = app(1, "size", vec![ints(2, "List", &[1, 2])]);
+    assert_eq!(eval(&size), Err(ErrorKind::MalformedIr));
+}
+
+#[test]
+fn map_entries_must_be_pairs() {
+    let map = app(1, "Map", vec![ints(2, "Tup", &[1, 2, 3])]);
+    assert_eq!(eval(&map), Err(ErrorKind::MalformedIr));
+
+    let map = app(1, "Map", vec![ints(2, "Tup", &[1, 2])]);
+    assert!(eval(&map).is_ok());
+}
+
+#[test]
+fn unknown_names_fail_when_evaluated() {
+    let name = QuintEx::QuintName {
+        id: 1,
+        name: "frobnicate".into(),
+    };
+    assert_eq!(eval(&name), Err(ErrorKind::MalformedIr));
+}
+
+#[test]
+fn indices_out_of_bounds_are_reported() {
+    let list = || ints(2, "List", &[1, 2]);
+    let nth = |index| app(1, "nth", vec![list(), int(10, index)]);
+    assert_eq!(eval(&nth(1)), Ok(Value::Int(2)));
+    assert_eq!(eval(&nth(2)), Err(ErrorKind::OutOfBounds));
+    assert_eq!(eval(&nth(-1)), Err(ErrorKind::OutOfBounds));
+
+    let replaced = app(1, "replaceAt", vec![list(), int(10, 5), int(11, 0)]);
+    assert_eq!(eval(&replaced), Err(ErrorKind::OutOfBounds));
+}
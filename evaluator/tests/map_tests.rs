@@ -0,0 +1,87 @@
+//! Map operators, which aren't built-ins of Quint, as user definitions with
+//! their names would clash with them, but IR from other frontends can use, so
+//! the IR here is built by hand.
+
+mod common;
+
+use common::{app, int, lambda};
+use quint_evaluator::evaluator::run;
+use quint_evaluator::ir::{ErrorKind, QuintEx, QuintId};
+use quint_evaluator::value::{ImmutableMap, Value};
+
+/// The map with the given pairs of integers, with ids from `id`.
+fn int_map(id: QuintId, pairs: &[(i64, i64)]) -> QuintEx {
+    let entries = pairs
+        .iter()
+        .zip((id + 1..).step_by(3))
+        .map(|((key, value), id)| app(id, "Tup", vec![int(id + 1, *key), int(id + 2, *value)]))
+        .collect();
+    app(id, "Map", entries)
+}
+
+fn map(pairs: &[(i64, i64)]) -> Value {
+    Value::Map(ImmutableMap::from_iter(
+        pairs.iter().map(|(k, v)| (Value::Int(*k), Value::Int(*v))),
+    ))
+}
+
+#[test]
+fn map_values() {
+    // Map(1 -> 10, 2 -> 20).mapValues(x => x + 1)
+    let (f, table) = lambda(50, &["x"], |x| {
+        app(60, "iadd", vec![x[0].clone(), int(61, 1)])
+    });
+    let expr = app(1, "mapValues", vec![int_map(10, &[(1, 10), (2, 20)]), f]);
+    assert_eq!(run(&table, &expr), Ok(map(&[(1, 11), (2, 21)])));
+}
+
+#[test]
+fn filter_entries() {
+    // Map(1 -> 10, 2 -> 10, 3 -> 40).filterEntries((x, y) => x * 10 == y)
+    let (p, table) = lambda(50, &["x", "y"], |xy| {
+        let product = app(61, "imul", vec![xy[0].clone(), int(62, 10)]);
+        app(60, "eq", vec![product, xy[1].clone()])
+    });
+    let expr = app(
+        1,
+        "filterEntries",
+        vec![int_map(10, &[(1, 10), (2, 10), (3, 40)]), p],
+    );
+    assert_eq!(run(&table, &expr), Ok(map(&[(1, 10)])));
+}
+
+#[test]
+fn merge_with() {
+    // Map(1 -> 10, 2 -> 20).mergeWith(Map(2 -> 2, 3 -> 3), (x, y) => x - y)
+    let (f, table) = lambda(50, &["x", "y"], |xy| app(60, "isub", xy));
+    let expr = app(
+        1,
+        "mergeWith",
+        vec![
+            int_map(10, &[(1, 10), (2, 20)]),
+            int_map(30, &[(2, 2), (3, 3)]),
+            f,
+        ],
+    );
+    assert_eq!(run(&table, &expr), Ok(map(&[(1, 10), (2, 18), (3, 3)])));
+}
+
+#[test]
+fn map_keys() {
+    // Map(1 -> 10, 2 -> 20).mapKeys(x => x * 2)
+    let (f, table) = lambda(50, &["x"], |x| {
+        app(60, "imul", vec![x[0].clone(), int(61, 2)])
+    });
+    let expr = app(1, "mapKeys", vec![int_map(10, &[(1, 10), (2, 20)]), f]);
+    assert_eq!(run(&table, &expr), Ok(map(&[(2, 10), (4, 20)])));
+
+    // Map(2 -> 10, 3 -> 20).mapKeys(x => x / 2), which gives 1 for both keys
+    let (f, table) = lambda(50, &["x"], |x| {
+        app(60, "idiv", vec![x[0].clone(), int(61, 2)])
+    });
+    let expr = app(1, "mapKeys", vec![int_map(10, &[(2, 10), (3, 20)]), f]);
+    assert_eq!(
+        run(&table, &expr).map_err(|error| error.kind),
+        Err(ErrorKind::InvalidArgument)
+    );
+}
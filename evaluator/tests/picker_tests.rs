@@ -1,7 +1,13 @@
+mod common;
+
+use common::ints;
 use quint_evaluator::{
     evaluator::{Env, Interpreter},
     helpers,
-    value::Value,
+    ir::{ErrorKind, LookupTable},
+    picker::{Distribution, PickMode},
+    rand::Rand,
+    value::{ImmutableMap, ImmutableVec, Value},
 };
 
 macro_rules! run_test {
@@ -55,3 +61,216 @@ fn interval_pick_test() -> Result<(), Box<dyn std::error::Error>> {
 
     run_test!(quint_content, Value::Int(5))
 }
+
+#[test]
+fn deterministic_pick_test() -> Result<(), Box<dyn std::error::Error>> {
+    let quint_content = "module main {
+          var x: int
+          var y: (int, str)
+          var z: int -> bool
+          val input = (x, y, z)
+          action init = {
+            nondet v = Set(3, 1, 2).oneOf()
+            nondet t = tuples(5.to(8), Set(\"b\", \"a\")).oneOf()
+            nondet m = setOfMaps(Set(1, 2), Bool).oneOf()
+            all { x' = v, y' = t, z' = m }
+          }
+          action step = x' = x
+        }";
+
+    let parsed = helpers::parse(quint_content, "init", "step", None)?;
+    let init_def = parsed.find_definition_by_name("init")?;
+    let input_def = parsed.find_definition_by_name("input")?;
+
+    let expected = Value::Tuple(ImmutableVec::from(vec![
+        Value::Int(1),
+        Value::Tuple(ImmutableVec::from(vec![
+            Value::Int(5),
+            Value::Str("a".into()),
+        ])),
+        Value::Map(ImmutableMap::from_iter(vec![
+            (Value::Int(1), Value::Bool(false)),
+            (Value::Int(2), Value::Bool(false)),
+        ])),
+    ]));
+
+    // Different seeds should give the same result
+    for seed in [0x42, 123_456] {
+        let mut interpreter = Interpreter::new(&parsed.table);
+        let mut env = Env::with_rand_state(interpreter.var_storage.clone(), seed);
+        env.pick_mode = PickMode::Deterministic;
+
+        let init = interpreter.eval(&mut env, init_def.expr.clone());
+        assert_eq!(init.unwrap(), Value::Bool(true));
+
+        interpreter.shift();
+
+        let input = interpreter.eval(&mut env, input_def.expr.clone());
+        assert_eq!(input.unwrap(), expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn infinite_set_pick_test() -> Result<(), Box<dyn std::error::Error>> {
+    let quint_content = "module main {
+          var x: int
+          var y: int
+          val input = (x, y)
+          action init = {
+            nondet i = Int.oneOf()
+            nondet n = Nat.oneOf()
+            all { x' = i, y' = n }
+          }
+          action step = init
+        }";
+
+    let parsed = helpers::parse(quint_content, "init", "step", None)?;
+    let init_def = parsed.find_definition_by_name("init")?;
+    let step_def = parsed.find_definition_by_name("step")?;
+    let input_def = parsed.find_definition_by_name("input")?;
+
+    let mut interpreter = Interpreter::new(&parsed.table);
+    let mut env = Env::with_rand_state(interpreter.var_storage.clone(), 0x42);
+    env.int_distribution = Distribution::Range { bound: 3 };
+
+    let init = interpreter.eval(&mut env, init_def.expr.clone());
+    assert_eq!(init.unwrap(), Value::Bool(true));
+
+    for _ in 0..20 {
+        interpreter.shift();
+
+        let (x, y) = interpreter
+            .eval(&mut env, input_def.expr.clone())?
+            .try_tuple2()?;
+        assert!((-3..=3).contains(&x.as_int()));
+        assert!((0..=3).contains(&y.as_int()));
+
+        let step = interpreter.eval(&mut env, step_def.expr.clone());
+        assert_eq!(step.unwrap(), Value::Bool(true));
+    }
+
+    Ok(())
+}
+
+/// Draw `n` values from a distribution, with the given integer parameters,
+/// from the same environment. The IR is built by hand, as the draws aren't
+/// built-ins of Quint, where user definitions with their names would clash
+/// with them.
+fn draws(
+    opcode: &str,
+    params: &[i64],
+    pick_mode: PickMode,
+    n: usize,
+) -> Result<Vec<Value>, ErrorKind> {
+    let expr = ints(1, opcode, params);
+    let table = LookupTable::default();
+    let mut interpreter = Interpreter::new(&table);
+    let mut env = Env::with_rand_state(interpreter.var_storage.clone(), 0x42);
+    env.pick_mode = pick_mode;
+    let draw = interpreter.compile(&expr);
+    (0..n)
+        .map(|_| draw.execute(&mut env).map_err(|error| error.kind))
+        .collect()
+}
+
+fn int_draws(opcode: &str, params: &[i64], n: usize) -> Vec<i64> {
+    draws(opcode, params, PickMode::Random, n)
+        .unwrap()
+        .iter()
+        .map(Value::as_int)
+        .collect()
+}
+
+#[test]
+fn distribution_draws_test() {
+    let dice = int_draws("uniform", &[1, 6], 6000);
+    for face in 1..=6 {
+        let count = dice.iter().filter(|&&d| d == face).count();
+        assert!((800..1200).contains(&count), "{face} drawn {count} times");
+    }
+
+    let coins = draws("bernoulli", &[1, 4], PickMode::Random, 4000).unwrap();
+    let heads = coins.iter().filter(|coin| coin.as_bool()).count();
+    assert!((850..1150).contains(&heads), "{heads} heads");
+
+    let waits = int_draws("geometric", &[3], 10_000);
+    let mean = waits.iter().sum::<i64>() as f64 / waits.len() as f64;
+    assert!(waits.iter().all(|&w| w >= 0));
+    assert!((2.8..3.2).contains(&mean), "mean {mean}");
+
+    // P(1) = 1 / (1 + 1/2 + 1/3 + 1/4 + 1/5), about 0.44
+    let ranks = int_draws("zipf", &[5, 1], 10_000);
+    let firsts = ranks.iter().filter(|&&r| r == 1).count();
+    assert!(ranks.iter().all(|r| (1..=5).contains(r)));
+    assert!((4100..4700).contains(&firsts), "1 drawn {firsts} times");
+}
+
+#[test]
+fn distribution_picks_test() {
+    let pick = |opcode, params: &[i64], pick_mode| {
+        draws(opcode, params, pick_mode, 1).map(|values| values[0].clone())
+    };
+
+    // The most likely values
+    let deterministic = PickMode::Deterministic;
+    assert_eq!(pick("uniform", &[3, 9], deterministic), Ok(Value::Int(3)));
+    assert_eq!(
+        pick("bernoulli", &[1, 4], deterministic),
+        Ok(Value::Bool(true))
+    );
+    assert_eq!(pick("geometric", &[5], deterministic), Ok(Value::Int(0)));
+    assert_eq!(pick("zipf", &[5, 2], deterministic), Ok(Value::Int(1)));
+
+    assert_eq!(
+        pick("geometric", &[5], PickMode::Exhaustive),
+        Err(ErrorKind::InfiniteSet)
+    );
+
+    let random = PickMode::Random;
+    assert_eq!(
+        pick("uniform", &[9, 3], random),
+        Err(ErrorKind::InvalidArgument)
+    );
+    assert_eq!(
+        pick("bernoulli", &[5, 4], random),
+        Err(ErrorKind::InvalidArgument)
+    );
+    assert_eq!(
+        pick("geometric", &[-1], random),
+        Err(ErrorKind::InvalidArgument)
+    );
+    assert_eq!(
+        pick("zipf", &[0, 1], random),
+        Err(ErrorKind::InvalidArgument)
+    );
+}
+
+#[test]
+fn wide_ranges_are_sampled_in_64_bits_test() {
+    // Bounds beyond 32 bits, which would be truncated if computed as `usize`
+    // on 32-bit targets
+    let mut rand = Rand::with_state(0x42);
+    let bound = 1 << 40;
+    let numbers: Vec<u64> = (0..100).map(|_| rand.next_u64(bound)).collect();
+    assert!(numbers.iter().all(|&n| n < bound));
+    assert!(numbers.iter().any(|&n| n > u64::from(u32::MAX)));
+
+    // `next` gives the same numbers for the same bounds
+    let (mut a, mut b) = (Rand::with_state(7), Rand::with_state(7));
+    for bound in [1, 2, 6, 1000] {
+        assert_eq!(a.next(bound) as u64, b.next_u64(bound as u64));
+    }
+
+    // Both signs and magnitudes beyond 31 bits, from `[-(2^32 - 1), 2^32 - 1]`
+    let distribution = Distribution::Range { bound: u32::MAX };
+    let mut rand = Rand::with_state(0x42);
+    let ints: Vec<i64> = (0..100)
+        .map(|_| distribution.sample_int(&mut rand))
+        .collect();
+    let limit = i64::from(u32::MAX);
+    assert!(ints.iter().all(|n| (-limit..=limit).contains(n)));
+    assert!(ints.iter().any(|&n| n < -(1 << 31)));
+    assert!(ints.iter().any(|&n| n > 1 << 31));
+}
@@ -0,0 +1,179 @@
+use std::fs::File;
+
+use quint_evaluator::{
+    ir::{QuintEx, QuintOutput},
+    printer::{expr_to_quint, to_quint},
+};
+use serde_json::{json, Value as Json};
+
+fn app(opcode: &str, args: Vec<Json>) -> Json {
+    json!({ "kind": "app", "id": 0, "opcode": opcode, "args": args })
+}
+
+fn name(name: &str) -> Json {
+    json!({ "kind": "name", "id": 0, "name": name })
+}
+
+fn int(value: i64) -> Json {
+    json!({ "kind": "int", "id": 0, "value": value })
+}
+
+fn str(value: &str) -> Json {
+    json!({ "kind": "str", "id": 0, "value": value })
+}
+
+fn lambda(params: &[&str], expr: Json) -> Json {
+    let params = params
+        .iter()
+        .map(|p| json!({ "id": 0, "name": p }))
+        .collect::<Vec<_>>();
+    json!({ "kind": "lambda", "id": 0, "params": params, "expr": expr })
+}
+
+fn print(expr: Json) -> String {
+    let expr: QuintEx = serde_json::from_value(expr).unwrap();
+    expr_to_quint(&expr)
+}
+
+#[test]
+fn operators_are_written_with_parentheses_only_where_needed() {
+    let sum = app("iadd", vec![name("x"), int(1)]);
+    assert_eq!(print(app("imul", vec![sum.clone(), int(2)])), "(x + 1) * 2");
+    assert_eq!(
+        print(app(
+            "iadd",
+            vec![int(2), app("imul", vec![name("x"), int(1)])]
+        )),
+        "2 + x * 1"
+    );
+    assert_eq!(
+        print(app("isub", vec![int(1), app("isub", vec![int(2), int(3)])])),
+        "1 - (2 - 3)"
+    );
+    assert_eq!(
+        print(app("isub", vec![app("isub", vec![int(1), int(2)]), int(3)])),
+        "1 - 2 - 3"
+    );
+    assert_eq!(print(app("ipow", vec![int(-2), int(2)])), "(-2) ^ 2");
+    assert_eq!(print(app("iuminus", vec![sum.clone()])), "-(x + 1)");
+
+    let compare = app("ilt", vec![name("x"), sum.clone()]);
+    let assign = app("assign", vec![name("x"), sum]);
+    assert_eq!(
+        print(app("and", vec![compare, assign.clone()])),
+        "x < x + 1 and x' = x + 1"
+    );
+    assert_eq!(
+        print(app(
+            "implies",
+            vec![app("or", vec![name("p"), name("q")]), name("r")]
+        )),
+        "p or q implies r"
+    );
+    assert_eq!(
+        print(app(
+            "in",
+            vec![app("iadd", vec![name("x"), int(1)]), name("S")]
+        )),
+        "(x + 1).in(S)"
+    );
+    assert_eq!(
+        print(app("not", vec![app("and", vec![name("p"), name("q")])])),
+        "not(p and q)"
+    );
+}
+
+#[test]
+fn builtins_are_written_in_their_syntax() {
+    let map = app(
+        "map",
+        vec![
+            name("S"),
+            lambda(&["x"], app("iadd", vec![name("x"), int(1)])),
+        ],
+    );
+    assert_eq!(print(map), "S.map(x => x + 1)");
+    assert_eq!(print(app("Set", vec![int(1), int(2)])), "Set(1, 2)");
+    assert_eq!(print(app("List", vec![int(1), int(2)])), "[1, 2]");
+    assert_eq!(print(app("Tup", vec![int(1), str("a")])), "(1, \"a\")");
+    assert_eq!(
+        print(app("Rec", vec![str("a"), int(1), str("b"), int(2)])),
+        "{ a: 1, b: 2 }"
+    );
+    assert_eq!(
+        print(app("Map", vec![app("Tup", vec![int(1), int(2)])])),
+        "Map(1 -> 2)"
+    );
+    assert_eq!(print(app("field", vec![name("r"), str("a")])), "r.a");
+    assert_eq!(print(app("item", vec![name("t"), int(2)])), "t._2");
+    assert_eq!(print(app("next", vec![name("x")])), "x'");
+    assert_eq!(
+        print(app("ite", vec![name("p"), int(1), int(2)])),
+        "if (p) 1 else 2"
+    );
+    assert_eq!(
+        print(app("variant", vec![str("None"), app("Tup", vec![])])),
+        "None"
+    );
+    assert_eq!(
+        print(app(
+            "matchVariant",
+            vec![
+                name("v"),
+                str("Some"),
+                lambda(&["x"], name("x")),
+                str("None"),
+                lambda(&["_"], int(0)),
+            ]
+        )),
+        "match v { | Some(x) => x | None => 0 }"
+    );
+    assert_eq!(print(app("double", vec![int(2)])), "double(2)");
+
+    // Operators applied to unexpected arguments are written as in the IR
+    assert_eq!(print(app("iadd", vec![int(1)])), "iadd(1)");
+    assert_eq!(print(app("assign", vec![int(1), int(1)])), "assign(1, 1)");
+}
+
+#[test]
+fn long_blocks_are_written_on_multiple_lines() {
+    assert_eq!(
+        print(app("actionAll", vec![name("a"), name("b")])),
+        "all { a, b }"
+    );
+    let long = (0..5)
+        .map(|i| app("assign", vec![name(&format!("variable{i}")), int(i)]))
+        .collect();
+    assert_eq!(
+        print(app("actionAny", long)),
+        "any {
+  variable0' = 0,
+  variable1' = 1,
+  variable2' = 2,
+  variable3' = 3,
+  variable4' = 4,
+}"
+    );
+}
+
+#[test]
+fn nested_definitions_are_written_as_blocks() {
+    let opdef = json!({
+        "kind": "def", "id": 0, "name": "y", "qualifier": "val",
+        "expr": app("iadd", vec![name("x"), int(1)]),
+    });
+    let expr = json!({ "kind": "let", "id": 0, "opdef": opdef, "expr": app("imul", vec![name("y"), int(2)]) });
+    assert_eq!(print(expr), "{\n  val y = x + 1\n  y * 2\n}");
+}
+
+#[test]
+fn specs_are_written_as_modules() {
+    let file = File::open("fixtures/ewd840.json").unwrap();
+    let output: QuintOutput = serde_json::from_reader(file).unwrap();
+    let quint = to_quint(&output);
+    assert!(
+        quint.starts_with("module ewd840 {\n  const N: int\n"),
+        "{quint}"
+    );
+    assert!(quint.contains("  val Nodes = 0.to(N - 1)\n"), "{quint}");
+}
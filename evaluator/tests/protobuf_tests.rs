@@ -0,0 +1,64 @@
+#![cfg(feature = "protobuf")]
+
+use quint_evaluator::trace::{Alternative, StepMeta, Trace};
+use quint_evaluator::value::{ImmutableMap, ImmutableVec, Value};
+use std::rc::Rc;
+
+#[test]
+fn traces_are_encoded_and_decoded() {
+    let state = |n: i64| {
+        Value::Record(ImmutableMap::from_iter(vec![
+            ("n".into(), Value::Int(n)),
+            ("s".into(), Value::Interval(1, n)),
+            (
+                "t".into(),
+                Value::Tuple(ImmutableVec::from(vec![
+                    Value::Bool(true),
+                    Value::Str("a".into()),
+                ])),
+            ),
+            (
+                "m".into(),
+                Value::Map(ImmutableMap::from_iter(vec![(
+                    Value::Int(n),
+                    Value::List(ImmutableVec::from(vec![Value::Int(0)])),
+                )])),
+            ),
+            (
+                "v".into(),
+                Value::Variant("None".into(), Rc::new(Value::Tuple(ImmutableVec::new()))),
+            ),
+        ]))
+    };
+    let trace = Trace::new(vec![state(1), state(3)], true);
+
+    let bytes = trace.encode_proto().unwrap();
+    assert_eq!(Trace::decode_proto(&bytes).unwrap(), trace);
+    // The same trace is always encoded the same way
+    assert_eq!(trace.clone().encode_proto().unwrap(), bytes);
+
+    assert!(Trace::decode_proto(&[0xff]).is_err());
+}
+
+#[test]
+fn trace_steps_and_seeds_are_encoded() {
+    let state = |n: i64| Value::Record(ImmutableMap::from_iter(vec![("n".into(), Value::Int(n))]));
+    let mut trace = Trace::new(vec![state(1), state(2)], false);
+    trace.seed = Some(7);
+    for (i, state) in trace.states.iter_mut().enumerate() {
+        state.meta = Some(StepMeta {
+            action_taken: Some(if i == 0 { "init" } else { "step" }.to_string()),
+            action_id: None,
+            alternatives: vec![Alternative {
+                index: i,
+                name: (i == 1).then(|| "step".to_string()),
+            }],
+            nondet_picks: vec![("k".to_string(), Value::Int(i as i64))],
+        });
+    }
+
+    assert_eq!(
+        Trace::decode_proto(&trace.encode_proto().unwrap()).unwrap(),
+        trace
+    );
+}
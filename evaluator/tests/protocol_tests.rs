@@ -0,0 +1,105 @@
+use std::fs::File;
+
+use quint_evaluator::{
+    ir::QuintOutput,
+    protocol::{self, PROTOCOL},
+    simulator::{NamedProperty, ParsedQuint},
+};
+use serde_json::{json, Value as Json};
+
+fn tictactoe() -> ParsedQuint {
+    let file = File::open("fixtures/tictactoe.json").unwrap();
+    let output: QuintOutput = serde_json::from_reader(file).unwrap();
+    let def = |name: &str| output.find_definition_by_name(name).unwrap().expr.clone();
+    ParsedQuint {
+        init: def("init"),
+        step: def("step"),
+        invariant: def("XHasNotWon"),
+        invariants: vec![NamedProperty {
+            name: "XHasNotWon".to_string(),
+            expr: def("XHasNotWon"),
+        }],
+        temporal: Vec::new(),
+        witnesses: Vec::new(),
+        state_label: None,
+        components: Vec::new(),
+        table: output.table.clone(),
+        types: output.types.clone(),
+    }
+}
+
+/// Run the backend on the given messages, returning the ones it sends.
+fn exchange(messages: &[Json]) -> Vec<Json> {
+    let input = messages
+        .iter()
+        .map(|m| format!("{m}\n"))
+        .collect::<String>();
+    let mut output = Vec::new();
+    protocol::serve(input.as_bytes(), &mut output).unwrap();
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[test]
+fn backend_simulates_after_the_handshake() {
+    let simulate = json!({
+        "type": "simulate", "parsed": tictactoe(), "source": "tictactoe.qnt",
+        "witnesses": [], "nruns": 100, "nsteps": 10, "ntraces": 1, "seed": 42,
+    });
+    let messages = exchange(&[
+        json!({ "type": "hello", "protocol": PROTOCOL }),
+        simulate.clone(),
+        simulate,
+    ]);
+
+    assert_eq!(messages[0]["type"], "hello");
+    assert_eq!(messages[0]["protocol"], PROTOCOL);
+    // The first sample is always reported
+    assert_eq!(messages[1]["type"], "progress");
+    assert_eq!(messages[1]["current"], 1);
+    assert_eq!(messages[1]["total"], 100);
+
+    let outcomes = messages
+        .iter()
+        .filter(|m| m["type"] == "outcome")
+        .collect::<Vec<_>>();
+    assert_eq!(outcomes.len(), 2);
+    assert_eq!(outcomes[0]["outcome"]["status"], "violation");
+    // The same seed gives the same outcome
+    let violation = |outcome: &Json| outcome["outcome"]["invariants"][0]["violation"].clone();
+    assert_eq!(
+        violation(outcomes[0])["sample"],
+        violation(outcomes[1])["sample"]
+    );
+    assert_eq!(
+        violation(outcomes[0])["step"],
+        violation(outcomes[1])["step"]
+    );
+    assert_eq!(messages.last().unwrap()["type"], "outcome");
+}
+
+#[test]
+fn backend_requires_the_handshake() {
+    let messages = exchange(&[
+        json!({ "type": "simulate", "parsed": tictactoe(), "source": "", "witnesses": [],
+                "nruns": 1, "nsteps": 1, "ntraces": 1 }),
+        json!({ "type": "frobnicate" }),
+        json!({ "type": "hello", "protocol": PROTOCOL + 1 }),
+        json!({ "type": "hello", "protocol": PROTOCOL }),
+    ]);
+
+    let types = messages
+        .iter()
+        .map(|m| m["type"].clone())
+        .collect::<Vec<_>>();
+    assert_eq!(types, ["hello", "error", "error", "error"]);
+    assert_eq!(messages[1]["message"], "expected a `hello` message first");
+    // The backend stops on an unsupported protocol
+    assert_eq!(
+        messages[3]["message"],
+        format!("unsupported protocol {}, expected {PROTOCOL}", PROTOCOL + 1)
+    );
+}
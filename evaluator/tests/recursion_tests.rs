@@ -0,0 +1,130 @@
+//! Recursive definitions are rejected by the Quint tool (QNT099), but can be
+//! found in IR from other frontends, so the IR here is built by hand.
+
+mod common;
+
+use common::{app, int};
+use quint_evaluator::evaluator::{run, Env, Interpreter};
+use quint_evaluator::ir::{
+    ErrorKind, LookupDefinition, LookupTable, OpDef, OpQualifier, QuintDeclaration, QuintEx,
+    QuintId, QuintLambdaParameter,
+};
+use quint_evaluator::value::Value;
+
+fn name(id: QuintId, name: &str) -> QuintEx {
+    QuintEx::QuintName {
+        id,
+        name: name.into(),
+    }
+}
+
+fn def(id: QuintId, name: &str, qualifier: OpQualifier, expr: QuintEx) -> LookupDefinition {
+    LookupDefinition::Definition(QuintDeclaration::QuintOpDef(OpDef {
+        id,
+        name: name.into(),
+        qualifier,
+        expr,
+        imported_from: None,
+        namespaces: None,
+        depth: None,
+    }))
+}
+
+/// An operator with a single parameter `x` (with id `id + 1`), and the
+/// lookup table entries of the references to `x` in its body.
+fn operator(
+    id: QuintId,
+    op: &str,
+    body: QuintEx,
+    refs: &[QuintId],
+) -> (LookupDefinition, LookupTable) {
+    let param = QuintLambdaParameter {
+        id: id + 1,
+        name: "x".into(),
+    };
+    let lambda = QuintEx::QuintLambda {
+        id: id + 2,
+        params: vec![param.clone()],
+        expr: Box::new(body),
+    };
+    let table = refs
+        .iter()
+        .map(|id| (*id, LookupDefinition::Param(param.clone())))
+        .collect();
+    (def(id, op, OpQualifier::Def, lambda), table)
+}
+
+/// `def loop(x) = loop(x)`, applied to 1.
+fn unproductive() -> (LookupTable, QuintEx) {
+    let body = app(10, "loop", vec![name(11, "x")]);
+    let (def, mut table) = operator(1, "loop", body, &[11]);
+    table.insert(10, def.clone());
+    table.insert(20, def);
+    (table, app(20, "loop", vec![int(21, 1)]))
+}
+
+#[test]
+fn unproductive_recursion_is_reported() {
+    let (table, expr) = unproductive();
+    let error = run(&table, &expr).unwrap_err();
+
+    assert_eq!(error.kind, ErrorKind::InfiniteRecursion);
+    assert_eq!(
+        error.message,
+        "Likely infinite recursion in def loop, called again with the same arguments (1)"
+    );
+}
+
+#[test]
+fn the_number_of_repeated_calls_is_configurable() {
+    let (table, expr) = unproductive();
+    let mut interpreter = Interpreter::new(&table);
+    interpreter.set_max_repeated_calls(100);
+    let mut env = Env::new(interpreter.var_storage.clone());
+
+    let error = interpreter.eval(&mut env, expr).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InfiniteRecursion);
+}
+
+#[test]
+fn productive_recursion_is_evaluated() {
+    // def countdown(x) = if (x <= 0) 0 else countdown(x - 1)
+    let body = app(
+        10,
+        "ite",
+        vec![
+            app(11, "ilte", vec![name(12, "x"), int(13, 0)]),
+            int(14, 0),
+            app(
+                15,
+                "countdown",
+                vec![app(16, "isub", vec![name(17, "x"), int(18, 1)])],
+            ),
+        ],
+    );
+    let (def, mut table) = operator(1, "countdown", body, &[12, 17]);
+    table.insert(15, def.clone());
+    table.insert(20, def);
+
+    let result = run(&table, &app(20, "countdown", vec![int(21, 100)]));
+    assert_eq!(result, Ok(Value::Int(0)));
+}
+
+#[test]
+fn values_depending_on_themselves_are_reported() {
+    // val v = v + 1
+    let v = def(
+        1,
+        "v",
+        OpQualifier::Val,
+        app(2, "iadd", vec![name(3, "v"), int(4, 1)]),
+    );
+    let table: LookupTable = [(3, v.clone()), (10, v)].into_iter().collect();
+
+    let error = run(&table, &name(10, "v")).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::InfiniteRecursion);
+    assert_eq!(
+        error.message,
+        "Likely infinite recursion in def v, whose value depends on itself"
+    );
+}
@@ -0,0 +1,35 @@
+use quint_evaluator::builtins::{compile_eager_op, compile_lazy_op};
+use quint_evaluator::reflection::{builtin, builtins, is_lazy};
+
+#[test]
+fn every_builtin_operator_is_implemented() {
+    for builtin in builtins().iter().filter(|b| b.arity.is_some()) {
+        // Assignments are compiled by the interpreter, which knows the variables
+        if builtin.name == "assign" {
+            continue;
+        }
+        if builtin.lazy {
+            compile_lazy_op(builtin.name);
+        } else {
+            compile_eager_op(builtin.name);
+        }
+    }
+}
+
+#[test]
+fn builtins_are_described() {
+    let iadd = builtin("iadd").unwrap();
+    assert_eq!(iadd.signature, "(int, int) => int");
+    assert_eq!(iadd.arity.map(|a| a.to_string()), Some("2".to_string()));
+    assert!(!iadd.lazy);
+
+    let and = builtin("and").unwrap().arity.unwrap();
+    assert_eq!(and.to_string(), "at least 0");
+    assert!(and.contains(0) && and.contains(5));
+
+    assert!(builtin("Int").unwrap().arity.is_none());
+    assert!(builtin("double").is_none());
+
+    assert!(is_lazy("ite") && is_lazy("oneOf"));
+    assert!(!is_lazy("iadd") && !is_lazy("double"));
+}
@@ -0,0 +1,193 @@
+use std::cell::Cell;
+use std::fs::File;
+use std::rc::Rc;
+
+use quint_evaluator::ir::{
+    LookupDefinition, OpDef, OpQualifier, QuintDeclaration, QuintEx, QuintModule, QuintOutput,
+};
+use quint_evaluator::repl::{Compile, Repl};
+use quint_evaluator::value::Value;
+
+/// Compile inputs that are names of tictactoe's definitions, like the Quint
+/// tool would, failing for actions defined with `def`.
+fn compile_tictactoe() -> Compile {
+    Box::new(|module: &str| {
+        let file = File::open("fixtures/tictactoe.json").unwrap();
+        let mut output: QuintOutput = serde_json::from_reader(file).unwrap();
+
+        let line = module.lines().find(|l| l.contains("__input = ")).unwrap();
+        let (qualifier, name) = line.trim().split_once(" __input = ").unwrap();
+        // Definitions are checked with `val __input = true`
+        let (expr, def) = if name == "true" {
+            (
+                QuintEx::QuintBool {
+                    id: 1_000_001,
+                    value: true,
+                },
+                None,
+            )
+        } else {
+            let def = output
+                .table
+                .values()
+                .find(|def| def.name() == name)
+                .cloned()
+                .ok_or_else(|| format!("Name {name} not found"))?;
+            match &def {
+                LookupDefinition::Definition(QuintDeclaration::QuintOpDef(op))
+                    if op.qualifier == OpQualifier::Action && qualifier != "action" =>
+                {
+                    return Err(format!("{name} updates variables"));
+                }
+                _ => {}
+            }
+            let expr = QuintEx::QuintName {
+                id: 1_000_001,
+                name: name.into(),
+            };
+            (expr, Some(def))
+        };
+
+        if let Some(def) = def {
+            output.table.insert(1_000_001, def);
+        }
+        output.modules.push(QuintModule {
+            name: "__repl".into(),
+            declarations: vec![QuintDeclaration::QuintOpDef(OpDef {
+                id: 1_000_000,
+                name: "__input".into(),
+                qualifier: match qualifier {
+                    "action" => OpQualifier::Action,
+                    _ => OpQualifier::Def,
+                },
+                expr,
+                imported_from: None,
+                namespaces: None,
+                depth: None,
+            })],
+        });
+        output.main = "__repl".into();
+        Ok(output)
+    })
+}
+
+#[test]
+fn actions_move_to_new_states() {
+    let mut repl = Repl::new(compile_tictactoe());
+
+    let error = repl.handle("nextTurn").unwrap_err();
+    assert!(error.starts_with("[QNT502]"), "{error}");
+
+    assert_eq!(repl.handle("init"), Ok("true".to_string()));
+    assert_eq!(repl.handle("nextTurn"), Ok("X".to_string()));
+    assert_eq!(repl.handle("boardEmpty"), Ok("true".to_string()));
+
+    assert_eq!(repl.handle("MoveX"), Ok("true".to_string()));
+    assert_eq!(repl.handle("nextTurn"), Ok("O".to_string()));
+    assert_eq!(repl.handle("boardEmpty"), Ok("false".to_string()));
+}
+
+#[test]
+fn actions_can_be_undone() {
+    let mut repl = Repl::new(compile_tictactoe());
+    repl.handle("init").unwrap();
+    let initial = repl.handle(".vars").unwrap();
+    assert!(initial.contains("nextTurn: X"), "{initial}");
+
+    repl.handle("MoveX").unwrap();
+    assert_ne!(repl.handle(".vars").unwrap(), initial);
+    assert_eq!(repl.handle(".undo"), Ok(initial));
+
+    repl.handle(".undo").unwrap();
+    assert_eq!(repl.handle(".vars"), Ok(String::new()));
+    assert!(repl.handle(".undo").is_err());
+}
+
+#[test]
+fn sessions_can_be_saved_and_restored() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("session.json");
+
+    let mut repl = Repl::new(compile_tictactoe());
+    repl.handle("init").unwrap();
+    repl.handle("MoveX").unwrap();
+    repl.handle("val corner = (1, 1)").unwrap();
+    repl.handle(&format!(".save {}", path.display())).unwrap();
+
+    let mut restored = Repl::new(compile_tictactoe());
+    restored
+        .handle(&format!(".restore {}", path.display()))
+        .unwrap();
+    assert_eq!(restored.state(), repl.state());
+    assert_eq!(restored.handle(".vars"), repl.handle(".vars"));
+
+    let session: serde_json::Value = serde_json::from_reader(File::open(&path).unwrap()).unwrap();
+    assert_eq!(
+        session["definitions"],
+        serde_json::json!(["val corner = (1, 1)"])
+    );
+    assert_eq!(session["states"].as_array().unwrap().len(), 3);
+
+    // Restored sessions can be undone as well
+    restored.handle(".undo").unwrap();
+    assert_eq!(restored.handle("nextTurn"), Ok("X".to_string()));
+}
+
+#[test]
+fn inputs_are_compiled_once() {
+    let compilations = Rc::new(Cell::new(0));
+    let mut compile = compile_tictactoe();
+    let counter = compilations.clone();
+    let mut repl = Repl::new(Box::new(move |module: &str| {
+        counter.set(counter.get() + 1);
+        compile(module)
+    }));
+
+    // Actions are compiled as such right away
+    repl.handle("init").unwrap();
+    assert_eq!(compilations.get(), 1);
+    repl.handle("MoveX").unwrap();
+    repl.handle("nextTurn").unwrap();
+    repl.handle(".undo").unwrap();
+    repl.handle("MoveX").unwrap();
+    assert_eq!(repl.handle("nextTurn"), Ok("O".to_string()));
+    assert_eq!(compilations.get(), 3);
+
+    // New definitions may change what inputs refer to
+    repl.handle("val corner = (1, 1)").unwrap();
+    repl.handle("nextTurn").unwrap();
+    assert_eq!(compilations.get(), 5);
+}
+
+#[test]
+fn unknown_commands_are_rejected() {
+    let mut repl = Repl::new(compile_tictactoe());
+    assert!(repl.handle(".frobnicate").is_err());
+    assert!(repl.handle(".load").is_err());
+    assert_eq!(repl.handle("   "), Ok(String::new()));
+}
+
+#[test]
+fn expressions_are_evaluated_in_given_states() {
+    let mut repl = Repl::new(compile_tictactoe());
+    repl.handle("init").unwrap();
+    let state = repl.state().clone();
+
+    let mut other = Repl::new(compile_tictactoe());
+    assert_eq!(
+        other.evaluate_in("nextTurn", &state).map(|v| v.to_string()),
+        Ok("X".to_string())
+    );
+    // Actions don't move to a new state
+    assert_eq!(other.evaluate_in("MoveX", &state), Ok(Value::Bool(true)));
+    assert_eq!(other.handle(".vars"), Ok(String::new()));
+}
+
+#[test]
+fn builtins_are_documented() {
+    let mut repl = Repl::new(compile_tictactoe());
+    let doc = repl.handle(".doc iadd").unwrap();
+    assert!(doc.starts_with("iadd: (int, int) => int\n"), "{doc}");
+    assert!(repl.handle(".doc").unwrap().contains("iadd"));
+    assert!(repl.handle(".doc double").is_err());
+}
@@ -0,0 +1,47 @@
+use quint_evaluator::report::{Outcome, Report};
+use serde_json::json;
+
+#[test]
+fn reports_fail_with_the_first_failed_property() {
+    let mut report = Report::new("run");
+    report.set_seed(0x42);
+    report.set_statistic("samples", 10);
+    report.add_property("inv1", "invariant", true, None, Default::default());
+    assert_eq!(report.outcome, Outcome::Ok);
+
+    let trace = report.add_trace(Some("out_inv2.itf.json".into()), &json!({}), false);
+    let details = json!({ "step": 3 }).as_object().unwrap().clone();
+    report.add_property("inv2", "invariant", false, Some(trace), details);
+    assert_eq!(report.outcome, Outcome::Failed);
+
+    assert_eq!(
+        serde_json::to_value(&report).unwrap(),
+        json!({
+            "command": "run",
+            "outcome": "failed",
+            "seed": "0x42",
+            "statistics": { "samples": 10 },
+            "properties": [
+                { "name": "inv1", "kind": "invariant", "passed": true },
+                { "name": "inv2", "kind": "invariant", "passed": false, "trace": 0, "step": 3 },
+            ],
+            "traces": [{ "file": "out_inv2.itf.json" }],
+            "elapsedMs": 0,
+        })
+    );
+}
+
+#[test]
+fn errors_override_the_outcome() {
+    let mut report = Report::new("test");
+    report.add_property("run1", "test", false, None, Default::default());
+    report.add_error("Name 'x' not found");
+    assert_eq!(report.outcome, Outcome::Error);
+
+    // Traces are included inline when asked to
+    report.add_trace(None, &json!({ "states": [] }), true);
+    assert_eq!(
+        serde_json::to_value(&report.traces).unwrap(),
+        json!([{ "itf": { "states": [] } }])
+    );
+}
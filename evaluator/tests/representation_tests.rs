@@ -0,0 +1,107 @@
+mod common;
+
+use common::app;
+use quint_evaluator::{
+    evaluator::{Env, Interpreter},
+    ir::{LookupTable, QuintEx, QuintType, TypeTable},
+    representation::Representation,
+    value::Value,
+};
+use serde_json::json;
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+fn color_set_type() -> QuintType {
+    let unit = json!({ "kind": "tup", "fields": { "kind": "empty" } });
+    serde_json::from_value(json!({
+        "kind": "set",
+        "elem": {
+            "kind": "sum",
+            "fields": {
+                "kind": "row",
+                "fields": [
+                    { "fieldName": "Red", "fieldType": unit },
+                    { "fieldName": "Green", "fieldType": unit },
+                    { "fieldName": "Blue", "fieldType": unit },
+                ],
+                "other": { "kind": "empty" },
+            },
+        },
+    }))
+    .unwrap()
+}
+
+/// A color, with ids from `id` to `id + 2`, as compiled expressions are
+/// memoized by id.
+fn color(id: u64, label: &str) -> QuintEx {
+    let label = QuintEx::QuintStr {
+        id,
+        value: label.into(),
+    };
+    app(id + 1, "variant", vec![label, app(id + 2, "Tup", vec![])])
+}
+
+/// Evaluate an expression, with the given ids typed as sets of colors.
+fn eval_with_color_sets(expr: &QuintEx, ids: &[u64]) -> Value {
+    let types: TypeTable = ids
+        .iter()
+        .map(|id| {
+            (
+                *id,
+                serde_json::from_value(json!({ "type": color_set_type() })).unwrap(),
+            )
+        })
+        .collect();
+    let table = LookupTable::default();
+    let mut interpreter = Interpreter::new(&table);
+    interpreter.set_types(&types);
+    let mut env = Env::new(interpreter.var_storage.clone());
+    interpreter.compile(expr).execute(&mut env).unwrap()
+}
+
+fn hash(value: &Value) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn sets_of_enumerations_are_bitsets() {
+    let Some(Representation::EnumerationSet(labels)) = Representation::for_type(&color_set_type())
+    else {
+        panic!("Expected a bitset representation");
+    };
+    assert_eq!(
+        labels.iter().map(|l| l.as_str()).collect::<Vec<_>>(),
+        ["Blue", "Green", "Red"]
+    );
+
+    // Variants with values are not enumerations
+    let mut typ = serde_json::to_value(color_set_type()).unwrap();
+    typ["elem"]["fields"]["fields"][0]["fieldType"] = json!({ "kind": "int" });
+    let typ: QuintType = serde_json::from_value(typ).unwrap();
+    assert_eq!(Representation::for_type(&typ), None);
+}
+
+#[test]
+fn bitsets_behave_like_sets() {
+    let reds = app(1, "Set", vec![color(10, "Red"), color(20, "Red")]);
+    let others = app(2, "Set", vec![color(30, "Green"), color(40, "Blue")]);
+    let union = app(3, "union", vec![reds.clone(), others.clone()]);
+
+    let generic = eval_with_color_sets(&union, &[]);
+    let bitset = eval_with_color_sets(&union, &[1, 2, 3]);
+    assert!(matches!(generic, Value::Set(_)));
+    assert!(matches!(bitset, Value::BitSet(_, 0b111)));
+    assert_eq!(bitset, generic);
+    assert_eq!(hash(&bitset), hash(&generic));
+    assert_eq!(bitset.fingerprint(), generic.fingerprint());
+    assert_eq!(bitset.to_itf(), generic.to_itf());
+    assert_eq!(bitset.to_string(), generic.to_string());
+
+    let difference = app(4, "exclude", vec![union, reds]);
+    let bitset = eval_with_color_sets(&difference, &[1, 2, 3, 4]);
+    assert!(matches!(bitset, Value::BitSet(_, 0b011)));
+    assert_eq!(bitset.cardinality(), 2);
+    assert!(bitset.subseteq(&eval_with_color_sets(&others, &[])));
+    assert!(!bitset.contains(&eval_with_color_sets(&color(10, "Red"), &[])));
+}
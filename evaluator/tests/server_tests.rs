@@ -0,0 +1,237 @@
+use std::fs::File;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::Arc;
+use std::thread;
+
+use quint_evaluator::{
+    ir::QuintOutput,
+    metrics::{self, Metrics},
+    msgpack,
+    server::{Server, EVALUATION_ERROR},
+    simulator::{NamedProperty, ParsedQuint},
+};
+use serde_json::{json, Value as Json};
+
+fn tictactoe() -> ParsedQuint {
+    let file = File::open("fixtures/tictactoe.json").unwrap();
+    let output: QuintOutput = serde_json::from_reader(file).unwrap();
+    let def = |name: &str| output.find_definition_by_name(name).unwrap().expr.clone();
+    ParsedQuint {
+        init: def("init"),
+        step: def("step"),
+        invariant: def("XHasNotWon"),
+        invariants: vec![NamedProperty {
+            name: "XHasNotWon".to_string(),
+            expr: def("XHasNotWon"),
+        }],
+        temporal: Vec::new(),
+        witnesses: Vec::new(),
+        state_label: None,
+        components: Vec::new(),
+        table: output.table.clone(),
+        types: output.types.clone(),
+    }
+}
+
+fn request(server: &mut Server, id: usize, method: &str, params: Json) -> Json {
+    let request = json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params });
+    let response = server.handle(&request.to_string()).unwrap();
+    assert_eq!(response["id"], id);
+    response
+}
+
+#[test]
+fn server_simulates_and_replays_compiled_specs() {
+    let mut server = Server::default();
+
+    let compiled = request(&mut server, 1, "compile", json!({ "parsed": tictactoe() }));
+    let spec = compiled["result"]["spec"].clone();
+
+    let params =
+        json!({ "spec": spec, "source": "tictactoe.qnt", "nruns": 100, "nsteps": 10, "seed": 42 });
+    let simulated = request(&mut server, 2, "simulate", params);
+    let outcome = &simulated["result"];
+    assert_eq!(outcome["status"], "violation");
+    let violation = &outcome["invariants"][0]["violation"];
+
+    let params = json!({ "spec": spec, "trace": violation["states"] });
+    let replayed = request(&mut server, 3, "replay", params);
+    assert_eq!(
+        replayed["result"],
+        json!({ "status": "violation", "state": violation["step"] })
+    );
+
+    // Specs stay compiled until they are released
+    let params = json!({ "spec": spec, "nruns": 1, "nsteps": 1 });
+    assert!(request(&mut server, 4, "simulate", params.clone())["result"].is_object());
+    request(&mut server, 5, "release", json!({ "spec": spec }));
+    let response = request(&mut server, 6, "simulate", params);
+    assert_eq!(response["error"]["message"], "unknown spec 0");
+}
+
+#[test]
+fn server_evaluates_expressions() {
+    let mut server = Server::default();
+    request(&mut server, 1, "compile", json!({ "parsed": tictactoe() }));
+
+    let division = |divisor: i64| {
+        json!({
+            "spec": 0,
+            "expr": {
+                "kind": "app", "id": 1_000_000, "opcode": "idiv",
+                "args": [
+                    { "kind": "int", "id": 1_000_001, "value": 6 },
+                    { "kind": "int", "id": 1_000_002, "value": divisor },
+                ],
+            },
+        })
+    };
+    let response = request(&mut server, 2, "evaluate", division(2));
+    assert_eq!(response["result"], json!({ "#bigint": "3" }));
+
+    let response = request(&mut server, 3, "evaluate", division(0));
+    assert_eq!(response["error"]["code"], EVALUATION_ERROR);
+    assert_eq!(response["error"]["data"]["code"], "QNT503");
+}
+
+#[test]
+fn server_reports_invalid_requests() {
+    let mut server = Server::default();
+
+    let response = server.handle("{").unwrap();
+    assert_eq!(response["error"]["code"], -32700);
+
+    let response = request(&mut server, 1, "frobnicate", json!({}));
+    assert_eq!(response["error"]["code"], -32601);
+
+    let response = request(&mut server, 2, "simulate", json!({ "spec": 0 }));
+    assert_eq!(response["error"]["code"], -32602);
+
+    // Malformed specs are rejected with the errors found in them
+    let mut parsed = tictactoe();
+    parsed.table.clear();
+    let response = request(&mut server, 3, "compile", json!({ "parsed": parsed }));
+    assert_eq!(response["error"]["code"], -32602);
+    assert_eq!(response["error"]["data"][0]["code"], "QNT517");
+
+    // Notifications have no response
+    let notification = json!({ "jsonrpc": "2.0", "method": "release", "params": { "spec": 0 } });
+    assert_eq!(server.handle(&notification.to_string()), None);
+}
+
+#[test]
+fn server_serves_simulation_metrics() {
+    let metrics = Arc::new(Metrics::default());
+    let mut server = Server::default().with_metrics(metrics.clone());
+    request(&mut server, 1, "compile", json!({ "parsed": tictactoe() }));
+    let params = json!({ "spec": 0, "nruns": 100, "nsteps": 10, "seed": 42 });
+    let samples = request(&mut server, 2, "simulate", params)["result"]["samples"].clone();
+
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let address = listener.local_addr().unwrap();
+    let served = metrics.clone();
+    thread::spawn(move || metrics::serve(&served, listener));
+
+    // A client that never sends its request doesn't hold up the others
+    let _stalled = TcpStream::connect(address).unwrap();
+    let mut stream = TcpStream::connect(address).unwrap();
+    write!(stream, "GET /metrics HTTP/1.1\r\nHost: {address}\r\n\r\n").unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains("\nquint_simulations_total 1\n"));
+    assert!(response.contains("\nquint_simulations_running 0\n"));
+    assert!(response.contains(&format!("\nquint_samples_total {samples}\n")));
+    // The simulation stops at the first violation
+    assert!(response.contains("\nquint_violations_total 1\n"));
+    assert!(response.contains("\nquint_interned_names "));
+}
+
+#[test]
+fn server_inspects_expressions_in_a_state_within_a_budget() {
+    let mut server = Server::default();
+    request(&mut server, 1, "compile", json!({ "parsed": tictactoe() }));
+
+    let state = json!({
+        "board": { "#map": [] },
+        "nextTurn": { "tag": "O", "value": { "#tup": [] } },
+    });
+    let expr = json!({ "kind": "name", "id": 415, "name": "nextTurn" });
+    let params = json!({ "spec": 0, "expr": expr, "state": state });
+    let inspected = &request(&mut server, 2, "inspect", params)["result"];
+    assert_eq!(inspected["status"], "ok");
+    assert_eq!(inspected["value"], state["nextTurn"]);
+    assert_eq!(inspected["text"], "O");
+
+    let range = |id: i64, end: i64| {
+        json!({
+            "kind": "app", "id": id, "opcode": "to",
+            "args": [
+                { "kind": "int", "id": id + 1, "value": 1 },
+                { "kind": "int", "id": id + 2, "value": end },
+            ],
+        })
+    };
+    let union = json!({
+        "kind": "app", "id": 1_000_000, "opcode": "union",
+        "args": [range(1_000_001, 10_000), range(1_000_004, 1)],
+    });
+    let params = json!({ "spec": 0, "expr": union, "maxElements": 1000 });
+    let inspected = &request(&mut server, 3, "inspect", params)["result"];
+    assert_eq!(inspected["status"], "error");
+    assert_eq!(inspected["error"]["code"], "QNT515");
+}
+
+#[test]
+fn server_negotiates_messagepack() {
+    let mut server = Server::default();
+    let negotiate =
+        json!({ "jsonrpc": "2.0", "id": 1, "method": "codec", "params": { "codec": "msgpack" } });
+    let mut input = format!("{negotiate}\n").into_bytes();
+    let compile = json!({ "jsonrpc": "2.0", "id": 2, "method": "compile", "params": { "parsed": tictactoe() } });
+    msgpack::write(&mut input, &compile).unwrap();
+    let evaluate = json!({
+        "jsonrpc": "2.0", "id": 3, "method": "evaluate",
+        "params": { "spec": 0, "expr": { "kind": "int", "id": 1_000_000, "value": -100_000 } },
+    });
+    msgpack::write(&mut input, &evaluate).unwrap();
+
+    let mut output = Vec::new();
+    server.serve(input.as_slice(), &mut output).unwrap();
+
+    let newline = output.iter().position(|&b| b == b'\n').unwrap();
+    let negotiated: Json = serde_json::from_slice(&output[..newline]).unwrap();
+    assert_eq!(negotiated["result"], json!({ "codec": "msgpack" }));
+    let mut rest = &output[newline + 1..];
+    assert_eq!(
+        msgpack::read(&mut rest).unwrap()["result"],
+        json!({ "spec": 0 })
+    );
+    assert_eq!(
+        msgpack::read(&mut rest).unwrap()["result"],
+        json!({ "#bigint": "-100000" })
+    );
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn messagepack_encodes_json_documents() {
+    let document = json!({
+        "null": null, "bools": [true, false], "string": "x".repeat(300),
+        "ints": [0, 127, 128, 65_536, u64::MAX, -1, -32, -33, -200, -40_000, i64::MIN],
+        "float": 1.5, "nested": { "empty": {}, "array": (0..20).collect::<Vec<_>>() },
+    });
+    assert_eq!(
+        msgpack::decode(&msgpack::encode(&document)).unwrap(),
+        document
+    );
+
+    // Examples from the specification
+    assert_eq!(
+        msgpack::encode(&json!({ "compact": true, "schema": 0 })),
+        b"\x82\xa7compact\xc3\xa6schema\x00"
+    );
+    assert!(msgpack::decode(b"\x81\x01\x02").is_err());
+    assert!(msgpack::decode(b"\xc4\x01\x00").is_err());
+}
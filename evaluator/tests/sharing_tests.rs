@@ -0,0 +1,61 @@
+use quint_evaluator::sharing::{footprint, measure, Sharing};
+use quint_evaluator::symbol::Symbol;
+use quint_evaluator::value::{ImmutableMap, Value};
+
+fn state(elems: Value, count: i64) -> Value {
+    Value::Record(ImmutableMap::from_iter(vec![
+        (Symbol::from("elems"), elems),
+        (Symbol::from("count"), Value::Int(count)),
+    ]))
+}
+
+#[test]
+fn new_values_are_copied() {
+    let value = state(Value::Set((1..=10).map(Value::Int).collect()), 0);
+    assert_eq!(
+        measure(&value, None),
+        Sharing {
+            shared: 0,
+            copied: footprint(&value),
+        }
+    );
+}
+
+#[test]
+fn unchanged_values_are_shared() {
+    let elems = Value::Set((1..=100).map(Value::Int).collect());
+    let previous = state(elems.clone(), 0);
+
+    // The same value is shared as a whole
+    assert_eq!(
+        measure(&previous, Some(&previous.clone())),
+        Sharing {
+            shared: footprint(&previous),
+            copied: 0,
+        }
+    );
+
+    // Only the changed field and the record are copied
+    let mut fields = previous.as_record_map().clone();
+    fields.insert(Symbol::from("count"), Value::Int(1));
+    let next = Value::Record(fields);
+    let sharing = measure(&next, Some(&previous));
+    assert_eq!(sharing.shared, footprint(&elems));
+    assert_eq!(sharing.shared + sharing.copied, footprint(&next));
+}
+
+#[test]
+fn updated_maps_share_their_other_entries() {
+    let previous: ImmutableMap<Value, Value> = (1..=10)
+        .map(|i| (Value::Int(i), Value::Set((0..i).map(Value::Int).collect())))
+        .collect();
+    let mut next = previous.clone();
+    next.insert(Value::Int(11), Value::Int(0));
+
+    let sharing = measure(&Value::Map(next.clone()), Some(&Value::Map(previous)));
+    assert!(sharing.shared > sharing.copied);
+    assert_eq!(
+        sharing.shared + sharing.copied,
+        footprint(&Value::Map(next))
+    );
+}
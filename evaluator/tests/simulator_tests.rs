@@ -1,6 +1,21 @@
-use std::path::Path;
+use std::{cell::RefCell, path::Path, rc::Rc, thread, time::Duration};
 
-use quint_evaluator::helpers;
+use fxhash::FxHashSet;
+
+use quint_evaluator::{
+    counters::{self, Counters},
+    helpers::{self, Properties},
+    ir::{ErrorKind, QuintError},
+    mbt,
+    ndjson::NdjsonWriter,
+    simulator::{
+        Agreement, InitStrategy, InvariantResult, ProgressUpdate, ReplayOutcome, SimulationHooks,
+        SimulatorConfig, Strategy, TraceQuality,
+    },
+    symbol::Symbol,
+    trace::{Alternative, State, Trace},
+    value::Value,
+};
 
 #[test]
 fn tictactoe_ok() {
@@ -56,3 +71,773 @@ fn instance_overrides_ok() {
     // Should not find violation
     assert!(result.unwrap().result);
 }
+
+#[test]
+fn counters_are_collected_when_enabled() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+
+    counters::set_enabled(true);
+    let result = parsed.simulate(10, 10, 0, None);
+    counters::set_enabled(false);
+    assert!(result.is_ok());
+
+    let counters = counters::get();
+    assert!(counters.operator_applications > 0);
+    assert!(counters.steps > 0);
+    assert!(counters.cache_hits + counters.cache_misses > 0);
+}
+
+#[test]
+fn counters_are_collected_from_parallel_workers() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let config = SimulatorConfig {
+        steps: 10,
+        samples: 10,
+        seed: Some(0x42),
+        ..SimulatorConfig::default()
+    };
+    let simulate = || {
+        parsed
+            .simulate_parallel_with_config(&config, 2, "tictactoe.qnt", None)
+            .unwrap()
+            .counters
+    };
+
+    assert_eq!(simulate(), Counters::default());
+    // Counting is enabled on this thread only, and passed on to the workers
+    counters::set_enabled(true);
+    let counters = simulate();
+    let enabled_elsewhere = thread::spawn(counters::is_enabled).join().unwrap();
+    counters::set_enabled(false);
+    assert!(counters.steps > 0);
+    assert!(!enabled_elsewhere);
+}
+
+#[test]
+fn sharing_is_measured_when_enabled() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+
+    counters::set_enabled(true);
+    counters::set_sharing_enabled(true);
+    let result = parsed.simulate(10, 10, 0, None);
+    counters::set_sharing_enabled(false);
+    counters::set_enabled(false);
+    assert!(result.is_ok());
+
+    // The board is updated one square at a time, so most of it is shared
+    let counters = counters::get();
+    assert!(counters.bytes_shared > 0);
+    assert!(counters.bytes_copied > 0);
+    assert!(counters.values_cloned > 0);
+}
+
+#[test]
+fn parallel_tictactoe_ok() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let result = parsed
+        .simulate_parallel(10, 100, 2, 4, 0x42, "tictactoe.qnt")
+        .unwrap();
+    // Should not find violation, and all samples should be run
+    assert!(result.result);
+    assert_eq!(result.samples, 100);
+    assert_eq!(result.best_traces.len(), 2);
+}
+
+#[test]
+fn parallel_tictactoe_violation() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    let result = parsed
+        .simulate_parallel(10, 100, 1, 4, 0x42, "tictactoe.qnt")
+        .unwrap();
+    // Should find violation
+    assert!(!result.result);
+    assert_eq!(result.best_traces.len(), 1);
+}
+
+#[test]
+fn replay_tictactoe_violation() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    let result = parsed.simulate(10, 100, 1, None).unwrap();
+    let trace = result.best_traces[0].clone();
+    let len = trace.states.len();
+
+    // Replaying the counterexample should reach the violation on its last state
+    let outcome = parsed.replay(&trace, 1000).unwrap();
+    assert_eq!(outcome, ReplayOutcome::Violation { state: len - 1 });
+
+    // The recorded moves are replayed, so a single attempt is enough, with
+    // no seed to start from either
+    let unseeded = Trace {
+        seed: None,
+        ..trace.clone()
+    };
+    let outcome = parsed.replay(&unseeded, 1).unwrap();
+    assert_eq!(outcome, ReplayOutcome::Violation { state: len - 1 });
+
+    // A trace repeating the initial state doesn't follow from `step`
+    let stuck = Trace {
+        states: vec![trace.states[0].clone(), trace.states[0].clone()],
+        violation: false,
+        seed: None,
+    };
+    let outcome = parsed.replay(&stuck, 100).unwrap();
+    assert_eq!(outcome, ReplayOutcome::Divergence { state: 1 });
+}
+
+#[test]
+fn cross_check_tictactoe_counterexample() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    let result = parsed.simulate(10, 100, 1, None).unwrap();
+    let mut trace = result.best_traces[0].clone();
+    let len = trace.states.len();
+
+    // Fields that are not variables of the spec are ignored, as other tools
+    // may add their own
+    for state in trace.states.iter_mut() {
+        if let Value::Record(fields) = &mut state.vars {
+            fields.insert("extra".into(), Value::Bool(true));
+        }
+    }
+    let outcome = parsed.cross_check(&trace, true, 1000).unwrap();
+    assert_eq!(outcome, Agreement::Agrees);
+
+    // The evaluator finds the violation, which is not expected
+    let outcome = parsed.cross_check(&trace, false, 1000).unwrap();
+    assert_eq!(outcome, Agreement::UnexpectedViolation { state: len - 1 });
+
+    // A prefix of the counterexample doesn't reach the violation
+    trace.states.pop();
+    let outcome = parsed.cross_check(&trace, true, 1000).unwrap();
+    assert_eq!(outcome, Agreement::MissingViolation);
+}
+
+#[test]
+fn multiple_invariants_are_reported_individually() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path_with_properties(
+        file_path,
+        "init",
+        "step",
+        None,
+        &Properties {
+            invariants: &["inv", "XHasNotWon", "NotStalemate"],
+            ..Properties::default()
+        },
+        None,
+    )
+    .unwrap();
+    let result = parsed.simulate(10, 100, 1, None).unwrap();
+    assert!(!result.result);
+
+    let names: Vec<_> = result.invariants.iter().map(|i| i.name.as_str()).collect();
+    assert_eq!(names, ["inv", "XHasNotWon", "NotStalemate"]);
+
+    // O never wins, as X always plays first and follows a winning strategy
+    assert!(result.invariants[0].violation.is_none());
+
+    let violation = result.invariants[1].violation.as_ref().unwrap();
+    assert!(violation.sample >= 1);
+    assert_eq!(violation.trace.states.len(), violation.step + 1);
+    assert!(violation.trace.violation);
+}
+
+#[test]
+fn restarts_tictactoe_violation() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    let config = SimulatorConfig {
+        steps: 10,
+        samples: 100,
+        n_traces: 1,
+        strategy: Strategy::Restarts,
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    // Should find violation, even though most walks are too short for X to win
+    assert!(!result.result);
+    assert!(result.best_traces[0].violation);
+}
+
+#[test]
+fn progress_reports_states_and_violations() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let updates = Rc::new(RefCell::new(Vec::new()));
+    let reported = Rc::clone(&updates);
+    let callback = Box::new(move |update: ProgressUpdate| {
+        reported
+            .borrow_mut()
+            .push((update.current, update.states, update.violations));
+    });
+
+    let result = parsed.simulate(10, 5, 0, Some(callback)).unwrap();
+    assert!(result.result);
+
+    let updates = updates.borrow();
+    assert_eq!(updates.len(), 5);
+    assert_eq!(updates[0], (1, 0, 0));
+    // Every sample visits at least its initial state
+    assert!(updates.windows(2).all(|w| w[1].1 > w[0].1));
+    assert!(updates.iter().all(|u| u.2 == 0));
+}
+
+#[test]
+fn time_budget_stops_simulation() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let config = SimulatorConfig {
+        samples: usize::MAX,
+        time_budget: Some(Duration::from_millis(100)),
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    // Should stop after the budget, with the samples run so far
+    assert!(result.result);
+    assert!(result.timed_out);
+    assert!(result.samples > 0);
+}
+
+#[test]
+fn action_weights_are_applied_and_reported() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    let config = SimulatorConfig {
+        samples: 100,
+        action_weights: vec![("MoveX".to_string(), 0)],
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    // X never moves, so it can't win
+    assert!(result.result);
+    assert_eq!(result.action_weights, vec![("MoveX".to_string(), 0)]);
+}
+
+#[test]
+fn estimate_violation_probability() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    let config = SimulatorConfig {
+        samples: 200,
+        estimate: true,
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    // Should run all samples, even after finding a violation
+    assert!(!result.result);
+    assert_eq!(result.samples, 200);
+
+    let estimate = result.estimates[0];
+    assert_eq!(estimate.samples, 200);
+    assert!(estimate.hits > 0 && estimate.hits <= 200);
+    assert!(estimate.margin() < 0.1);
+}
+
+#[test]
+fn hooks_are_called_on_simulation_events() {
+    #[derive(Default)]
+    struct Recorder {
+        steps: usize,
+        samples: usize,
+        violations: Vec<(usize, usize)>,
+    }
+
+    impl SimulationHooks for Recorder {
+        fn on_step(&mut self, _state: &State) {
+            self.steps += 1;
+        }
+
+        fn on_sample_end(&mut self, trace: &Trace) {
+            self.samples += 1;
+            assert!(!trace.states.is_empty());
+        }
+
+        fn on_violation(&mut self, trace: &Trace, invariant: usize) {
+            self.violations.push((invariant, trace.states.len()));
+        }
+    }
+
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    let mut recorder = Recorder::default();
+    let result = parsed
+        .simulate_with_hooks(&SimulatorConfig::default(), None, &mut recorder)
+        .unwrap();
+
+    assert!(!result.result);
+    assert_eq!(recorder.samples, result.samples);
+    assert!(recorder.steps >= recorder.samples);
+    // The simulation stops at the first violation
+    assert_eq!(recorder.violations.len(), 1);
+    assert_eq!(recorder.violations[0].0, 0);
+    assert_eq!(recorder.violations[0].1, result.best_traces[0].states.len());
+}
+
+#[test]
+fn states_are_streamed_as_ndjson() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    let mut out = Vec::new();
+    let mut stream = NdjsonWriter::new(&mut out);
+    let result = parsed
+        .simulate_with_hooks(&SimulatorConfig::default(), None, &mut stream)
+        .unwrap();
+    stream.finish().unwrap();
+
+    let lines = String::from_utf8(out)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    let count = |kind: &str| lines.iter().filter(|line| line["kind"] == kind).count();
+    assert_eq!(count("sample"), result.samples);
+    // The last sample ends with the violation
+    let violation = &result.best_traces[0];
+    let last = &lines[lines.len() - violation.states.len() - 2..];
+    for (step, (line, state)) in last.iter().zip(&violation.states).enumerate() {
+        assert_eq!(line["kind"], "state");
+        assert_eq!(line["sample"], result.samples);
+        assert_eq!(line["step"], step);
+        assert_eq!(line["state"], state.vars.to_itf());
+    }
+    assert_eq!(count("violation"), 1);
+    assert_eq!(lines[lines.len() - 2]["kind"], "violation");
+    assert_eq!(lines[lines.len() - 1]["violation"], true);
+}
+
+#[test]
+fn witnesses_are_counted() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path_with_properties(
+        file_path,
+        "init",
+        "step",
+        Some("inv"),
+        &Properties {
+            witnesses: &["boardEmpty", "gameOver"],
+            ..Properties::default()
+        },
+        None,
+    )
+    .unwrap();
+    let result = parsed.simulate(10, 100, 1, None).unwrap();
+
+    // Every sample starts with an empty board, and only the initial state has it
+    assert_eq!(result.witnesses[0].samples, result.samples);
+    assert_eq!(result.witnesses[0].states, result.samples);
+    // Some games should end within 10 steps
+    assert!(result.witnesses[1].samples > 0);
+    assert!(result.witnesses[1].states >= result.witnesses[1].samples);
+}
+
+#[test]
+fn distinct_violations_are_collected() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    let config = SimulatorConfig {
+        samples: 200,
+        collect_violations: true,
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    // Should run all samples, even after finding a violation
+    assert!(!result.result);
+    assert_eq!(result.samples, 200);
+
+    // X can win in many different ways
+    let violations = &result.distinct_violations;
+    assert!(violations.len() > 1);
+    assert!(violations.iter().map(|v| v.count).sum::<usize>() <= 200);
+    let mut fingerprints: Vec<_> = violations.iter().map(|v| v.fingerprint).collect();
+    fingerprints.sort();
+    fingerprints.dedup();
+    assert_eq!(fingerprints.len(), violations.len());
+    assert!(violations.iter().all(|v| v.trace.violation));
+}
+
+#[test]
+fn initial_states_are_enumerated() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let config = SimulatorConfig {
+        samples: 10,
+        init_strategy: InitStrategy::Enumerate(100),
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    // The game always starts with an empty board
+    assert!(result.result);
+    assert_eq!(result.enumerated_initial_states, Some(1));
+    assert_eq!(result.initial_states.len(), 1);
+}
+
+#[test]
+fn mbt_records_actions_and_nondet_picks() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let config = SimulatorConfig {
+        samples: 1,
+        mbt: true,
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    let trace = &result.best_traces[0];
+    let actions = trace
+        .states
+        .iter()
+        .map(|state| state.action_taken().unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(actions[..3], ["init", "MoveX", "MoveO"]);
+
+    // X starts in a corner, and O moves to an empty coordinate
+    let picks = |i: usize| trace.states[i].meta.as_ref().unwrap().picks();
+    assert!(picks(0).as_record_map().is_empty());
+    assert!(picks(1)
+        .as_record_map()
+        .contains_key(&Symbol::from("corner")));
+    assert!(picks(2)
+        .as_record_map()
+        .contains_key(&Symbol::from("coordinate")));
+
+    let test_case = mbt::test_case(trace, &["nextTurn".to_string()]);
+    assert_eq!(test_case["steps"][1]["action"], "MoveX");
+    let expected = test_case["steps"][1]["expected"].as_object().unwrap();
+    assert_eq!(expected.len(), 1);
+    assert_eq!(expected["nextTurn"]["tag"], "O");
+}
+
+#[test]
+fn violating_sample_is_reproduced_from_its_seed() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path_with_properties(
+        file_path,
+        "init",
+        "step",
+        None,
+        &Properties {
+            invariants: &["NotStalemate"],
+            ..Properties::default()
+        },
+        None,
+    )
+    .unwrap();
+    let result = parsed
+        .simulate_parallel(10, 200, 1, 4, 0x42, "tictactoe.qnt")
+        .unwrap();
+    assert_eq!(result.seed, 0x42);
+    let violation = result.invariants[0].violation.as_ref().unwrap();
+
+    // Running only the violating sample, sequentially, gives the same trace
+    let config = SimulatorConfig {
+        samples: 200,
+        seed: Some(0x42),
+        sample: Some(violation.sample),
+        ..SimulatorConfig::default()
+    };
+    let reproduced = parsed.simulate_with_config(&config, None).unwrap();
+    assert_eq!(reproduced.samples, 1);
+    let reproduced = reproduced.invariants[0].violation.as_ref().unwrap();
+    assert_eq!(reproduced.sample, violation.sample);
+    assert_eq!(reproduced.seed, violation.seed);
+    assert_eq!(reproduced.step, violation.step);
+    let trace = Trace::from_itf(&violation.trace.trace).unwrap();
+    assert!(reproduced.trace.vars().eq(trace.vars()));
+    assert!(reproduced
+        .trace
+        .states
+        .iter()
+        .zip(&trace.states)
+        .all(|(a, b)| a.step_label() == b.step_label()));
+}
+
+#[test]
+fn parallel_violations_do_not_depend_on_threads() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path_with_properties(
+        file_path,
+        "init",
+        "step",
+        None,
+        &Properties {
+            invariants: &["NotStalemate", "XHasNotWon"],
+            ..Properties::default()
+        },
+        None,
+    )
+    .unwrap();
+    fn violations<T>(invariants: Vec<InvariantResult<T>>) -> Vec<Option<(usize, u64, usize)>> {
+        invariants
+            .into_iter()
+            .map(|i| i.violation.map(|v| (v.sample, v.seed, v.step)))
+            .collect()
+    }
+
+    // The violations of the lowest samples are reported, as they are when
+    // simulating sequentially, whichever worker finds one first
+    let config = SimulatorConfig {
+        steps: 10,
+        samples: 200,
+        seed: Some(0x42),
+        ..SimulatorConfig::default()
+    };
+    let sequential = violations(
+        parsed
+            .simulate_with_config(&config, None)
+            .unwrap()
+            .invariants,
+    );
+    assert!(sequential.iter().all(Option::is_some));
+    for threads in [1, 2, 3, 8] {
+        let result = parsed
+            .simulate_parallel_with_config(&config, threads, "tictactoe.qnt", None)
+            .unwrap();
+        assert_eq!(
+            violations(result.invariants),
+            sequential,
+            "{threads} threads"
+        );
+    }
+}
+
+#[test]
+fn steps_are_recorded_without_mbt() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let result = parsed.simulate(10, 1, 1, None).unwrap();
+    let trace = &result.best_traces[0];
+    let alternatives = |i: usize| trace.states[i].meta.as_ref().unwrap().alternatives.clone();
+
+    assert_eq!(trace.states[0].step_label().as_deref(), Some("init"));
+    assert_eq!(
+        alternatives(1),
+        [Alternative {
+            index: 0,
+            name: Some("MoveX".to_string()),
+        }]
+    );
+    // Games end after at most 9 moves, and then the anonymous third
+    // alternative keeps the state
+    assert_eq!(
+        alternatives(10),
+        [Alternative {
+            index: 2,
+            name: None,
+        }]
+    );
+    assert_eq!(trace.states[10].step_label().as_deref(), Some("#2"));
+
+    // They are only written as variables in ITF for model-based testing
+    let itf = trace.clone().to_itf("tictactoe.qnt".to_string());
+    assert_eq!(itf["vars"], serde_json::json!(["board", "nextTurn"]));
+    assert_eq!(itf["states"][1]["#meta"]["actionTaken"], "MoveX");
+}
+
+#[test]
+fn best_traces_by_distinct_states() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let config = SimulatorConfig {
+        steps: 20,
+        samples: 20,
+        n_traces: 3,
+        trace_quality: TraceQuality::DistinctStates,
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    assert_eq!(result.best_trace_scores.len(), 3);
+    // Games end after at most 9 moves, and then the state doesn't change, so
+    // traces are not better for being longer
+    assert!(result.best_trace_scores.iter().all(|&score| score <= 10));
+    assert!(result.best_trace_scores.is_sorted_by(|a, b| a >= b));
+    for (trace, score) in result.best_traces.iter().zip(&result.best_trace_scores) {
+        assert_eq!(trace.states.len(), 21);
+        let distinct: FxHashSet<_> = trace.vars().map(Value::fingerprint).collect();
+        assert_eq!(distinct.len(), *score);
+    }
+}
+
+#[test]
+fn best_traces_by_distinct_actions() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let config = SimulatorConfig {
+        samples: 10,
+        trace_quality: TraceQuality::DistinctActions,
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    // Only `MoveX` and `MoveO` are named actions
+    assert_eq!(result.best_trace_scores, [2]);
+}
+
+#[test]
+fn fair_actions_are_taken_when_starving() {
+    let file_path: &Path = Path::new("fixtures/tictactoe.qnt");
+
+    let parsed =
+        helpers::parse_from_path(file_path, "init", "step", Some("XHasNotWon"), None).unwrap();
+    let config = SimulatorConfig {
+        samples: 100,
+        steps: 20,
+        action_weights: vec![("MoveX".to_string(), 0)],
+        fair_actions: vec!["MoveX".to_string()],
+        fairness_period: 1,
+        ..SimulatorConfig::default()
+    };
+    let result = parsed.simulate_with_config(&config, None).unwrap();
+    // X never moves on its own, but it is forced to when it is its turn, and
+    // then it can win
+    assert!(!result.result);
+}
+
+#[test]
+fn composed_components_are_interleaved() {
+    let file_path: &Path = Path::new("fixtures/composed.qnt");
+
+    let parse = |invariant: &str| {
+        helpers::parse_from_path_with_properties(
+            file_path,
+            "init",
+            "step",
+            None,
+            &Properties {
+                invariants: &[invariant],
+                components: &["node1", "node2", "network"],
+                ..Properties::default()
+            },
+            Some("composed"),
+        )
+        .unwrap()
+    };
+    let config = SimulatorConfig {
+        samples: 100,
+        n_traces: 10,
+        ..SimulatorConfig::default()
+    };
+
+    let result = parse("inv").simulate_with_config(&config, None).unwrap();
+    assert!(result.result);
+    // Only one component takes its action on each step, and the variables of
+    // the other node are kept
+    for trace in &result.best_traces {
+        for (previous, state) in trace.states.iter().zip(&trace.states[1..]) {
+            let changed = state
+                .vars
+                .changed_fields(&previous.vars)
+                .into_iter()
+                .map(|(name, _)| name.to_string())
+                .collect::<Vec<_>>();
+            assert!(
+                [
+                    vec!["messages"],
+                    vec!["messages", "n1::sent"],
+                    vec!["messages", "n2::sent"]
+                ]
+                .contains(&changed.iter().map(String::as_str).collect()),
+                "unexpected changes: {changed:?}"
+            );
+        }
+    }
+
+    // Nodes send messages faster than the network delivers them, at times
+    let result = parse("fewMessages")
+        .simulate_with_config(&config, None)
+        .unwrap();
+    assert!(!result.result);
+}
+
+#[test]
+fn empty_picks_disable_actions() {
+    let file_path: &Path = Path::new("fixtures/emptyPicks.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let result = parsed.simulate(10, 20, 1, None).unwrap();
+    assert!(result.result);
+    // The other action is taken instead
+    assert_eq!(result.best_traces[0].states.len(), 11);
+
+    // Samples where no action is enabled end, without an error
+    let parsed = helpers::parse_from_path(file_path, "init", "stuck", Some("inv"), None).unwrap();
+    let result = parsed.simulate(10, 20, 1, None).unwrap();
+    assert!(result.result);
+    assert_eq!(result.best_traces[0].states.len(), 1);
+}
+
+#[test]
+fn failed_assertions_discard_alternatives() {
+    let file_path: &Path = Path::new("fixtures/failedAssertions.qnt");
+
+    let parsed = helpers::parse_from_path(file_path, "init", "step", Some("inv"), None).unwrap();
+    let result = parsed.simulate(10, 20, 1, None).unwrap();
+    assert!(result.result);
+    assert_eq!(result.best_traces[0].states.len(), 11);
+
+    // Without another action to take, the assertion fails the simulation
+    let parsed = helpers::parse_from_path(file_path, "init", "failing", Some("inv"), None).unwrap();
+    let error = parsed.simulate(10, 20, 1, None).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::AssertionFailed);
+    assert!(error.is_recoverable());
+
+    // Steps retried to avoid visited states don't recover from assertions
+    let parsed = helpers::parse_from_path(file_path, "init", "picked", Some("inv"), None).unwrap();
+    let config = SimulatorConfig {
+        steps: 20,
+        samples: 10,
+        n_traces: 1,
+        strategy: Strategy::Restarts,
+        ..SimulatorConfig::default()
+    };
+    let error = parsed.simulate_with_config(&config, None).unwrap_err();
+    assert_eq!(error.kind, ErrorKind::AssertionFailed);
+}
+
+#[test]
+fn errors_are_classified() {
+    assert!(ErrorKind::EmptySet.is_recoverable());
+    assert!(ErrorKind::AssertionFailed.is_recoverable());
+    assert!(!ErrorKind::Unsupported.is_recoverable());
+    assert!(!ErrorKind::InvalidArgument.is_recoverable());
+    let error = QuintError::new(ErrorKind::Unsupported, "Missing field x");
+    assert!(!error.is_recoverable());
+    assert_eq!(error.code(), "QNT501");
+    assert_eq!(error.to_string(), "[QNT501] Missing field x");
+}
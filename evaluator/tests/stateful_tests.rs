@@ -82,3 +82,51 @@ fn action_any_test() -> Result<(), Box<dyn std::error::Error>> {
         [Value::Int(2), Value::Int(8), Value::Int(32)]
     )
 }
+
+#[test]
+fn snapshot_and_restore_test() -> Result<(), Box<dyn std::error::Error>> {
+    let quint_content = "module main {
+          var x: int
+          val input = x
+          action init = x' = 0
+          action step = {
+            nondet v = 1.to(1000).oneOf()
+            x' = x + v
+          }
+        }";
+
+    let parsed = helpers::parse(quint_content, "init", "step", None)?;
+    let init_def = parsed.find_definition_by_name("init")?;
+    let step_def = parsed.find_definition_by_name("step")?;
+    let input_def = parsed.find_definition_by_name("input")?;
+
+    let mut interpreter = Interpreter::new(&parsed.table);
+    let mut env = Env::with_rand_state(interpreter.var_storage.clone(), 123_456);
+
+    interpreter.eval(&mut env, init_def.expr.clone())?;
+    interpreter.shift();
+
+    let snapshot = env.snapshot();
+
+    // Take two steps and record the resulting value
+    for _ in 0..2 {
+        interpreter.eval(&mut env, step_def.expr.clone())?;
+        interpreter.shift();
+    }
+    let after_two_steps = interpreter.eval(&mut env, input_def.expr.clone())?;
+
+    // Go back to the snapshot, where `x` was 0
+    env.restore(&snapshot);
+    let input = interpreter.eval(&mut env, input_def.expr.clone())?;
+    assert_eq!(input, Value::Int(0));
+
+    // The random state is restored as well, so the same steps are taken again
+    for _ in 0..2 {
+        interpreter.eval(&mut env, step_def.expr.clone())?;
+        interpreter.shift();
+    }
+    let input = interpreter.eval(&mut env, input_def.expr.clone())?;
+    assert_eq!(input, after_two_steps);
+
+    Ok(())
+}
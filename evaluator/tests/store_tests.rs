@@ -0,0 +1,182 @@
+#![cfg(feature = "sqlite")]
+
+use std::fs::{self, File};
+
+use quint_evaluator::{
+    ir::QuintOutput,
+    simulator::{NamedProperty, ParsedQuint, SimulatorConfig},
+    store::{TraceStore, SCHEMA_VERSION},
+};
+use rusqlite::Connection;
+
+fn tictactoe() -> ParsedQuint {
+    let file = File::open("fixtures/tictactoe.json").unwrap();
+    let output: QuintOutput = serde_json::from_reader(file).unwrap();
+    let def = |name: &str| output.find_definition_by_name(name).unwrap().expr.clone();
+    ParsedQuint {
+        init: def("init"),
+        step: def("step"),
+        invariant: def("XHasNotWon"),
+        invariants: vec![NamedProperty {
+            name: "XHasNotWon".to_string(),
+            expr: def("XHasNotWon"),
+        }],
+        temporal: Vec::new(),
+        witnesses: Vec::new(),
+        state_label: None,
+        components: Vec::new(),
+        table: output.table.clone(),
+        types: output.types.clone(),
+    }
+}
+
+#[test]
+fn samples_are_stored_and_queried() {
+    let path = std::env::temp_dir().join("quint_store_tests.sqlite");
+    let _ = fs::remove_file(&path);
+    let parsed = tictactoe();
+    let config = SimulatorConfig {
+        steps: 10,
+        samples: 50,
+        seed: Some(1),
+        ..SimulatorConfig::default()
+    };
+
+    // Two runs in the same database
+    let mut runs = Vec::new();
+    for _ in 0..2 {
+        let mut store = TraceStore::open(&path, "tictactoe.qnt").unwrap();
+        let run = store.run();
+        let result = parsed
+            .simulate_with_hooks(&config, None, &mut store)
+            .unwrap();
+        store.finish().unwrap();
+        runs.push((run, result));
+    }
+    assert_ne!(runs[0].0, runs[1].0);
+
+    let db = Connection::open(&path).unwrap();
+    let count =
+        |sql: &str, run: i64| -> i64 { db.query_row(sql, [run], |row| row.get(0)).unwrap() };
+    for (run, result) in &runs {
+        assert_eq!(
+            count("SELECT COUNT(*) FROM samples WHERE run = ?1", *run),
+            result.samples as i64
+        );
+        assert_eq!(
+            count("SELECT SUM(length) FROM samples WHERE run = ?1", *run),
+            count("SELECT COUNT(*) FROM states WHERE run = ?1", *run)
+        );
+        // Every state has the two variables of the spec
+        assert_eq!(
+            count("SELECT COUNT(*) FROM state_variables WHERE run = ?1", *run),
+            2 * count("SELECT COUNT(*) FROM states WHERE run = ?1", *run)
+        );
+        // but only the changes are stored, and nothing changes once a game
+        // is over
+        assert!(
+            count("SELECT COUNT(*) FROM variables WHERE run = ?1", *run)
+                < count("SELECT COUNT(*) FROM state_variables WHERE run = ?1", *run)
+        );
+        assert_eq!(
+            count(
+                "SELECT COUNT(*) FROM variables WHERE run = ?1 AND step = 0",
+                *run
+            ),
+            2 * result.samples as i64
+        );
+        // The simulation stops at the first sample violating the invariant
+        assert_eq!(
+            count(
+                "SELECT COUNT(*) FROM samples WHERE run = ?1 AND violation = 0",
+                *run
+            ),
+            i64::from(!result.result)
+        );
+    }
+
+    // Every sample starts from the empty board
+    let initial: i64 = db
+        .query_row(
+            "SELECT COUNT(DISTINCT fingerprint) FROM states WHERE step = 0",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(initial, 1);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn stores_of_the_first_version_are_migrated() {
+    let path = std::env::temp_dir().join("quint_store_tests_v1.sqlite");
+    let _ = fs::remove_file(&path);
+    // A sample of two states where only `x` changes, as version 1 stored it
+    let db = Connection::open(&path).unwrap();
+    db.execute_batch(
+        r##"
+        CREATE TABLE runs (id INTEGER PRIMARY KEY, source TEXT NOT NULL, created TEXT NOT NULL);
+        CREATE TABLE samples (
+            run INTEGER NOT NULL, sample INTEGER NOT NULL, length INTEGER NOT NULL,
+            violation INTEGER, PRIMARY KEY (run, sample)
+        );
+        CREATE TABLE states (
+            run INTEGER NOT NULL, sample INTEGER NOT NULL, step INTEGER NOT NULL,
+            fingerprint INTEGER NOT NULL, action TEXT, state TEXT NOT NULL,
+            PRIMARY KEY (run, sample, step)
+        );
+        CREATE TABLE variables (
+            run INTEGER NOT NULL, sample INTEGER NOT NULL, step INTEGER NOT NULL,
+            name TEXT NOT NULL, value TEXT NOT NULL, number INTEGER
+        );
+        INSERT INTO runs VALUES (1, 'spec.qnt', '2024-01-01T00:00:00+00:00');
+        INSERT INTO samples VALUES (1, 1, 2, NULL);
+        INSERT INTO states VALUES
+            (1, 1, 0, 10, NULL, '{"x":{"#bigint":"0"},"y":"a"}'),
+            (1, 1, 1, 11, NULL, '{"x":{"#bigint":"1"},"y":"a"}');
+        INSERT INTO variables VALUES
+            (1, 1, 0, 'x', '{"#bigint":"0"}', 0), (1, 1, 0, 'y', '"a"', NULL),
+            (1, 1, 1, 'x', '{"#bigint":"1"}', 1), (1, 1, 1, 'y', '"a"', NULL);
+        "##,
+    )
+    .unwrap();
+    drop(db);
+
+    let store = TraceStore::open(&path, "spec.qnt").unwrap();
+    assert_eq!(store.run(), 2);
+    store.finish().unwrap();
+
+    let db = Connection::open(&path).unwrap();
+    let version: i64 = db
+        .pragma_query_value(None, "user_version", |row| row.get(0))
+        .unwrap();
+    assert_eq!(version, SCHEMA_VERSION);
+    let changes: String = db
+        .query_row("SELECT changes FROM states WHERE step = 1", [], |row| {
+            row.get(0)
+        })
+        .unwrap();
+    assert_eq!(changes, r##"{"x":{"#bigint":"1"}}"##);
+    let count = |sql: &str| -> i64 { db.query_row(sql, [], |row| row.get(0)).unwrap() };
+    assert_eq!(count("SELECT COUNT(*) FROM variables"), 3);
+    assert_eq!(count("SELECT COUNT(*) FROM state_variables"), 4);
+    drop(db);
+
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn stores_of_newer_versions_are_refused() {
+    let path = std::env::temp_dir().join("quint_store_tests_newer.sqlite");
+    let _ = fs::remove_file(&path);
+    let db = Connection::open(&path).unwrap();
+    db.pragma_update(None, "user_version", SCHEMA_VERSION + 1)
+        .unwrap();
+    drop(db);
+
+    let error = TraceStore::open(&path, "spec.qnt").err().unwrap();
+    assert!(error.to_string().contains("newer"), "{error}");
+
+    fs::remove_file(&path).unwrap();
+}
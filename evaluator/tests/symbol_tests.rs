@@ -0,0 +1,71 @@
+use std::hash::BuildHasher;
+
+use quint_evaluator::hashing::ValueHasher;
+use quint_evaluator::symbol::{self, Symbol};
+use quint_evaluator::value::Value;
+
+#[test]
+fn symbols_are_the_same_for_the_same_name() {
+    let name = String::from("balance");
+    assert_eq!(Symbol::intern("balance"), Symbol::from(&name));
+    assert_eq!(Symbol::intern("balance").as_str(), "balance");
+    assert_ne!(Symbol::intern("balance"), Symbol::intern("balances"));
+
+    // Interning on another thread gives the same symbol
+    let balance = Symbol::intern("balance");
+    let other = std::thread::spawn(|| Symbol::intern("balance"))
+        .join()
+        .unwrap();
+    assert!(std::ptr::eq(balance.as_str(), other.as_str()));
+}
+
+#[test]
+fn interned_names_are_counted_until_freed() {
+    // Longer than all the other names of these tests, which run at the same
+    // time, together
+    let name = "counted_field_".repeat(1000);
+    let symbol = Symbol::intern(&name);
+    let copy = Symbol::intern(&name);
+    let (names, bytes) = symbol::interned();
+    assert!(names >= 1);
+    assert!(bytes >= name.len());
+
+    drop((symbol, copy));
+    let (_, bytes) = symbol::interned();
+    assert!(bytes < name.len());
+}
+
+#[test]
+fn symbols_are_ordered_by_name() {
+    // Interned in the opposite order of their names
+    let z = Symbol::intern("zzz_field");
+    let a = Symbol::intern("aaa_field");
+    assert!(a < z);
+
+    let mut names = [z, a, Symbol::intern("mmm_field")];
+    names.sort();
+    assert_eq!(
+        names.iter().map(|s| s.as_str()).collect::<Vec<_>>(),
+        ["aaa_field", "mmm_field", "zzz_field"]
+    );
+}
+
+#[test]
+fn records_hash_the_same_however_they_are_built() {
+    let record = |fields: &[(&str, i64)]| {
+        Value::Record(
+            fields
+                .iter()
+                .map(|(name, i)| (Symbol::intern(name), Value::Int(*i)))
+                .collect(),
+        )
+    };
+    let forward = record(&[("first", 1), ("second", 2)]);
+    let backward = record(&[("second", 2), ("first", 1)]);
+    assert_eq!(forward, backward);
+    assert_eq!(
+        ValueHasher::default().hash_one(&forward),
+        ValueHasher::default().hash_one(&backward)
+    );
+    assert_ne!(forward, record(&[("first", 2), ("second", 1)]));
+}
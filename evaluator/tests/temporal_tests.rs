@@ -0,0 +1,138 @@
+use quint_evaluator::ir::{
+    LookupDefinition, LookupTable, QuintDeclaration, QuintEx, QuintId, QuintVar,
+};
+use quint_evaluator::temporal::{TemporalChecker, Verdict};
+use quint_evaluator::value::{ImmutableMap, Value};
+use std::cell::Cell;
+
+/// Builds expressions over a single state variable `x`, with unique ids
+struct Builder {
+    table: LookupTable,
+    next_id: Cell<QuintId>,
+}
+
+const X_ID: QuintId = 1;
+
+impl Builder {
+    fn new() -> Self {
+        Self {
+            table: LookupTable::default(),
+            next_id: Cell::new(X_ID + 1),
+        }
+    }
+
+    fn id(&self) -> QuintId {
+        let id = self.next_id.get();
+        self.next_id.set(id + 1);
+        id
+    }
+
+    fn x(&mut self) -> QuintEx {
+        let id = self.id();
+        self.table.insert(
+            id,
+            LookupDefinition::Definition(QuintDeclaration::QuintVar(QuintVar {
+                id: X_ID,
+                name: "x".into(),
+                imported_from: None,
+                namespaces: None,
+            })),
+        );
+        QuintEx::QuintName {
+            id,
+            name: "x".into(),
+        }
+    }
+
+    fn int(&self, value: i64) -> QuintEx {
+        QuintEx::QuintInt {
+            id: self.id(),
+            value,
+        }
+    }
+
+    fn app(&self, opcode: &str, args: Vec<QuintEx>) -> QuintEx {
+        QuintEx::QuintApp {
+            id: self.id(),
+            opcode: opcode.into(),
+            args,
+        }
+    }
+
+    fn x_is(&mut self, opcode: &str, value: i64) -> QuintEx {
+        let x = self.x();
+        let value = self.int(value);
+        self.app(opcode, vec![x, value])
+    }
+}
+
+fn check(builder: &Builder, property: &QuintEx, xs: &[i64]) -> Verdict {
+    let states = xs
+        .iter()
+        .map(|x| Value::Record(ImmutableMap::from_iter(vec![("x".into(), Value::Int(*x))])))
+        .collect::<Vec<_>>();
+
+    TemporalChecker::new(&builder.table, property)
+        .unwrap()
+        .check(&states)
+        .unwrap()
+}
+
+#[test]
+fn always_and_eventually() {
+    let mut b = Builder::new();
+    let nonnegative = b.x_is("igte", 0);
+    let always = b.app("always", vec![nonnegative]);
+    let two = b.x_is("eq", 2);
+    let eventually = b.app("eventually", vec![two]);
+
+    assert_eq!(check(&b, &always, &[0, 1, 2]), Verdict::PresumablySatisfied);
+    assert_eq!(check(&b, &always, &[0, -1, 2]), Verdict::Violated);
+    assert_eq!(check(&b, &eventually, &[0, 1, 2]), Verdict::Satisfied);
+    assert_eq!(check(&b, &eventually, &[0, 1]), Verdict::PresumablyViolated);
+}
+
+#[test]
+fn next_state_predicates_stutter_at_the_end() {
+    let mut b = Builder::new();
+    // always(x' >= x)
+    let x = b.x();
+    let next_x = b.app("next", vec![x]);
+    let x = b.x();
+    let increasing = b.app("igte", vec![next_x, x]);
+    let always = b.app("always", vec![increasing]);
+
+    assert_eq!(check(&b, &always, &[0, 1, 1]), Verdict::PresumablySatisfied);
+    assert_eq!(check(&b, &always, &[1, 0]), Verdict::Violated);
+}
+
+#[test]
+fn leads_to() {
+    let mut b = Builder::new();
+    // always(x == 0 implies eventually(x == 2))
+    let zero = b.x_is("eq", 0);
+    let two = b.x_is("eq", 2);
+    let eventually = b.app("eventually", vec![two]);
+    let implies = b.app("implies", vec![zero, eventually]);
+    let leads_to = b.app("always", vec![implies]);
+
+    assert_eq!(
+        check(&b, &leads_to, &[0, 1, 2]),
+        Verdict::PresumablySatisfied
+    );
+    assert_eq!(
+        check(&b, &leads_to, &[0, 2, 0]),
+        Verdict::PresumablyViolated
+    );
+    assert!(!check(&b, &leads_to, &[0, 2, 0]).holds());
+}
+
+#[test]
+fn fairness_is_not_supported() {
+    let mut b = Builder::new();
+    let action = b.x_is("eq", 0);
+    let x = b.x();
+    let fair = b.app("weakFair", vec![action, x]);
+
+    assert!(TemporalChecker::new(&b.table, &fair).is_err());
+}
@@ -0,0 +1,83 @@
+use std::path::Path;
+
+use quint_evaluator::helpers;
+use quint_evaluator::symbol::Symbol;
+use quint_evaluator::tester::{run_test, run_test_samples, TestOutcome};
+use quint_evaluator::value::Value;
+
+#[test]
+fn runs_report_the_failing_step() {
+    let file_path: &Path = Path::new("fixtures/runs.qnt");
+
+    let (runs, table) = helpers::parse_runs_from_path(file_path, None, None).unwrap();
+    let names: Vec<_> = runs.iter().map(|r| r.name.as_str()).collect();
+    assert_eq!(
+        names,
+        ["incrementTwice", "wrongExpectation", "disabledAction"]
+    );
+
+    let results: Vec<_> = runs.iter().map(|r| run_test(&table, r, 0x42)).collect();
+
+    assert!(results[0].passed());
+    let xs: Vec<_> = results[0]
+        .trace
+        .vars()
+        .map(|s| s.as_record_map()[&Symbol::from("x")].clone())
+        .collect();
+    assert_eq!(xs, [Value::Int(0), Value::Int(1), Value::Int(2)]);
+
+    // The expectation fails after the increment, the second step
+    match &results[1].outcome {
+        TestOutcome::Failed { step, error } => {
+            assert_eq!(*step, 1);
+            assert_eq!(error.as_ref().unwrap().code(), "QNT508");
+        }
+        TestOutcome::Passed => panic!("Expected wrongExpectation to fail"),
+    }
+    assert!(results[1].trace.violation);
+
+    assert_eq!(
+        results[2].outcome,
+        TestOutcome::Failed {
+            step: 1,
+            error: None
+        }
+    );
+    assert_eq!(results[2].trace.states.len(), 1);
+}
+
+#[test]
+fn runs_are_filtered_by_name() {
+    let file_path: &Path = Path::new("fixtures/runs.qnt");
+
+    let (runs, _) = helpers::parse_runs_from_path(file_path, Some("increment"), None).unwrap();
+    assert_eq!(runs.len(), 1);
+    assert_eq!(runs[0].name, "incrementTwice");
+}
+
+#[test]
+fn nondeterministic_runs_are_repeated_until_they_fail() {
+    let file_path: &Path = Path::new("fixtures/nondetRuns.qnt");
+
+    let (runs, table) = helpers::parse_runs_from_path(file_path, None, None).unwrap();
+
+    // Runs without nondeterministic choices are executed once
+    let result = run_test_samples(&table, &runs[0], 0x42, 100);
+    assert!(result.passed());
+    assert_eq!(result.samples, 1);
+
+    // `pick` gives 9 in one of 10 samples
+    let result = run_test_samples(&table, &runs[1], 0x42, 1000);
+    assert!(!result.passed());
+    assert!(result.samples > 1);
+    assert!(matches!(
+        &result.outcome,
+        TestOutcome::Failed { error: Some(error), .. } if error.code() == "QNT508"
+    ));
+
+    // The same seed gives the same samples
+    assert_eq!(
+        run_test_samples(&table, &runs[1], 0x42, 1000).samples,
+        result.samples
+    );
+}
@@ -0,0 +1,117 @@
+use quint_evaluator::tlc::{parse_trace, parse_value};
+use quint_evaluator::value::{ImmutableMap, ImmutableVec, Value};
+use std::rc::Rc;
+
+fn list(elems: Vec<Value>) -> Value {
+    Value::List(ImmutableVec::from(elems))
+}
+
+#[test]
+fn tla_values_are_read_as_quint_values() {
+    assert_eq!(parse_value("-3").unwrap(), Value::Int(-3));
+    assert_eq!(parse_value("TRUE").unwrap(), Value::Bool(true));
+    assert_eq!(
+        parse_value(r#""a \"b\"""#).unwrap(),
+        Value::Str("a \"b\"".into())
+    );
+    assert_eq!(parse_value("m1").unwrap(), Value::Str("m1".into()));
+    assert_eq!(
+        parse_value("{2, 1}").unwrap(),
+        Value::Set([Value::Int(1), Value::Int(2)].into_iter().collect())
+    );
+    assert_eq!(parse_value("1..2").unwrap(), parse_value("{1, 2}").unwrap());
+    assert_eq!(
+        parse_value("<<1, <<>>>>").unwrap(),
+        list(vec![Value::Int(1), list(vec![])])
+    );
+    assert_eq!(
+        parse_value("(\"a\" :> 1 @@ \"b\" :> {})").unwrap(),
+        Value::Map(ImmutableMap::from_iter(vec![
+            (Value::Str("a".into()), Value::Int(1)),
+            (Value::Str("b".into()), Value::Set(Default::default())),
+        ]))
+    );
+    assert_eq!(
+        parse_value("[a |-> 1, b |-> FALSE]").unwrap(),
+        Value::Record(ImmutableMap::from_iter(vec![
+            ("a".into(), Value::Int(1)),
+            ("b".into(), Value::Bool(false)),
+        ]))
+    );
+    // Quint variants are encoded as records with a tag and a value
+    assert_eq!(
+        parse_value("[tag |-> \"Some\", value |-> 1]").unwrap(),
+        Value::Variant("Some".into(), Rc::new(Value::Int(1)))
+    );
+
+    assert!(parse_value("{1, 2").is_err());
+    assert!(parse_value("1 2").is_err());
+}
+
+#[test]
+fn tlc_error_traces_are_read() {
+    let output = r#"
+Error: Invariant Inv is violated.
+Error: The behavior up to this point is:
+State 1: <Initial predicate>
+/\ x = 0
+/\ f = ( 1 :> "a" @@
+         2 :> "b" )
+
+State 2: <Next line 10, col 5 to line 12, col 20 of module M>
+/\ x = 1
+/\ f = (1 :> "a" @@ 2 :> "c")
+
+State 3: Stuttering
+12 states generated, 7 distinct states found, 0 states left on queue.
+"#;
+    let trace = parse_trace(output).unwrap();
+    assert!(trace.violation);
+    assert_eq!(trace.states.len(), 3);
+    assert_eq!(
+        trace.states[0].vars,
+        Value::Record(ImmutableMap::from_iter(vec![
+            ("x".into(), Value::Int(0)),
+            (
+                "f".into(),
+                parse_value(r#"(1 :> "a" @@ 2 :> "b")"#).unwrap()
+            ),
+        ]))
+    );
+    assert_eq!(trace.states[2], trace.states[1]);
+
+    // The conjunction is omitted with a single variable
+    let trace = parse_trace("State 1: <Initial predicate>\nx = 1\n").unwrap();
+    assert!(!trace.violation);
+    assert_eq!(
+        trace.vars().collect::<Vec<_>>(),
+        vec![&Value::Record(ImmutableMap::from_iter(vec![(
+            "x".into(),
+            Value::Int(1)
+        )]))]
+    );
+}
+
+#[test]
+fn values_are_written_in_tla() {
+    let value = parse_value(
+        r#"[b |-> {3, 1}, a |-> <<"x\"y", TRUE>>, f |-> (2 :> -1 @@ 1 :> 0), v |-> [tag |-> "Some", value |-> 1]]"#,
+    )
+    .unwrap();
+    assert_eq!(
+        value.to_tla().unwrap(),
+        r#"[a |-> <<"x\"y", TRUE>>, b |-> {1, 3}, f |-> (1 :> 0 @@ 2 :> -1), v |-> [tag |-> "Some", value |-> 1]]"#
+    );
+    assert_eq!(Value::Interval(1, 3).to_tla().unwrap(), "{1, 2, 3}");
+    assert_eq!(
+        Value::Map(ImmutableMap::default()).to_tla().unwrap(),
+        "<<>>"
+    );
+}
+
+#[test]
+fn traces_written_in_tla_are_read_back() {
+    let output = "State 1:\n/\\ x = 0\n/\\ y = {}\n\nState 2:\n/\\ x = 1\n/\\ y = {\"a\"}\n";
+    let trace = parse_trace(output).unwrap();
+    assert_eq!(trace.to_tla().unwrap(), output);
+}
@@ -0,0 +1,67 @@
+use quint_evaluator::trace::{Delta, DeltaTrace, State, StepMeta, Trace};
+use quint_evaluator::value::{ImmutableMap, Value};
+
+fn record(fields: &[(&str, Value)]) -> Value {
+    Value::Record(ImmutableMap::from_iter(
+        fields
+            .iter()
+            .map(|(name, value)| ((*name).into(), value.clone())),
+    ))
+}
+
+#[test]
+fn delta_traces_only_store_changes() {
+    let big = Value::Set((0..1000).map(Value::Int).collect());
+    let state = |x| record(&[("x", Value::Int(x)), ("big", big.clone())]);
+    let mut trace = Trace::new(
+        vec![
+            state(0),
+            state(1),
+            state(1),
+            record(&[("x", Value::Int(2))]),
+        ],
+        true,
+    );
+    trace.seed = Some(7);
+    trace.states[1].meta = Some(StepMeta {
+        action_taken: Some("step".to_string()),
+        ..StepMeta::default()
+    });
+
+    let deltas = DeltaTrace::from(&trace);
+    assert_eq!(deltas.len(), 4);
+    assert_eq!(
+        deltas.deltas().collect::<Vec<_>>(),
+        [
+            &Delta::Full(state(0)),
+            &Delta::Changes(vec![("x".into(), Value::Int(1))]),
+            &Delta::Changes(vec![]),
+            // A variable is missing, so the state is stored in full
+            &Delta::Full(record(&[("x", Value::Int(2))])),
+        ]
+    );
+
+    // Full states are reconstructed on demand
+    assert_eq!(deltas.state(2), State::new(state(1)));
+    assert_eq!(deltas.state(1), trace.states[1]);
+    assert_eq!(Trace::from(&deltas), trace);
+}
+
+#[test]
+fn delta_traces_are_built_state_by_state() {
+    let state = |x, y| record(&[("x", Value::Int(x)), ("y", Value::Int(y))]);
+    let mut deltas = DeltaTrace::new(false, None);
+    assert!(deltas.is_empty());
+    for (x, y) in [(0, 0), (1, 0), (1, 1)] {
+        deltas.push(State::new(state(x, y)));
+    }
+
+    assert_eq!(
+        deltas.states().map(|state| state.vars).collect::<Vec<_>>(),
+        [state(0, 0), state(1, 0), state(1, 1)]
+    );
+    assert_eq!(
+        deltas.deltas().last(),
+        Some(&Delta::Changes(vec![("y".into(), Value::Int(1))]))
+    );
+}
@@ -0,0 +1,84 @@
+#![cfg(feature = "tracing")]
+
+use std::fs::File;
+use std::sync::{Arc, Mutex};
+
+use quint_evaluator::{
+    ir::QuintOutput,
+    simulator::{NamedProperty, ParsedQuint, SimulatorConfig},
+};
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Metadata, Subscriber};
+
+fn tictactoe() -> ParsedQuint {
+    let file = File::open("fixtures/tictactoe.json").unwrap();
+    let output: QuintOutput = serde_json::from_reader(file).unwrap();
+    let def = |name: &str| output.find_definition_by_name(name).unwrap().expr.clone();
+    ParsedQuint {
+        init: def("init"),
+        step: def("step"),
+        invariant: def("XHasNotWon"),
+        invariants: vec![NamedProperty {
+            name: "XHasNotWon".to_string(),
+            expr: def("XHasNotWon"),
+        }],
+        temporal: Vec::new(),
+        witnesses: Vec::new(),
+        state_label: None,
+        components: Vec::new(),
+        table: output.table.clone(),
+        types: output.types.clone(),
+    }
+}
+
+/// A subscriber recording the names of the spans created.
+#[derive(Clone, Default)]
+struct Spans(Arc<Mutex<Vec<&'static str>>>);
+
+impl Subscriber for Spans {
+    fn enabled(&self, _metadata: &Metadata<'_>) -> bool {
+        true
+    }
+
+    fn new_span(&self, span: &Attributes<'_>) -> Id {
+        let mut spans = self.0.lock().unwrap();
+        spans.push(span.metadata().name());
+        Id::from_u64(spans.len() as u64)
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, _event: &Event<'_>) {}
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+#[test]
+fn simulation_phases_have_spans() {
+    let spans = Spans::default();
+    let config = SimulatorConfig {
+        steps: 10,
+        samples: 3,
+        seed: Some(1),
+        ..SimulatorConfig::default()
+    };
+    let result = tracing::subscriber::with_default(spans.clone(), || {
+        let result = tictactoe().simulate_with_config(&config, None).unwrap();
+        result.best_traces[0]
+            .clone()
+            .to_itf("tictactoe.qnt".to_string());
+        result
+    });
+
+    let spans = spans.0.lock().unwrap();
+    let count = |name: &str| spans.iter().filter(|&&s| s == name).count();
+    assert_eq!(count("compile"), 1);
+    assert_eq!(count("sample"), result.samples);
+    // The invariant is evaluated on every state
+    assert!(count("invariant") > result.samples);
+    assert_eq!(count("export"), 1);
+}
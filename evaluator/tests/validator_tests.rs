@@ -0,0 +1,168 @@
+mod common;
+
+use std::fs::File;
+
+use common::app;
+use quint_evaluator::ir::{QuintEx, QuintOutput};
+use quint_evaluator::simulator::{NamedProperty, ParsedQuint};
+
+fn load(fixture: &str) -> QuintOutput {
+    let file = File::open(format!("fixtures/{fixture}.json")).unwrap();
+    serde_json::from_reader(file).unwrap()
+}
+
+fn parsed(output: &QuintOutput, step: QuintEx) -> ParsedQuint {
+    let def = |name: &str| output.find_definition_by_name(name).unwrap().expr.clone();
+    ParsedQuint {
+        init: def("init"),
+        step,
+        invariant: QuintEx::QuintBool { id: 0, value: true },
+        invariants: Vec::new(),
+        temporal: Vec::new(),
+        witnesses: Vec::new(),
+        state_label: None,
+        components: Vec::new(),
+        table: output.table.clone(),
+        types: output.types.clone(),
+    }
+}
+
+/// The messages and references of the errors of an invalid spec.
+fn errors(parsed: &ParsedQuint) -> Vec<(String, Option<u64>)> {
+    let errors = parsed.validate().unwrap_err();
+    assert!(errors.iter().all(|e| e.code() == "QNT517"), "{errors:?}");
+    errors
+        .into_iter()
+        .map(|e| (e.message, e.reference))
+        .collect()
+}
+
+#[test]
+fn compiled_specs_are_valid() {
+    for fixture in ["tictactoe", "ewd426", "ewd840", "simple", "jmt"] {
+        let output = load(fixture);
+        assert!(output.validate().is_ok(), "{fixture}");
+    }
+}
+
+#[test]
+fn unknown_names_and_operators_are_reported() {
+    let output = load("tictactoe");
+    let step = app(
+        1_000_000,
+        "and",
+        vec![
+            QuintEx::QuintName {
+                id: 1_000_001,
+                name: "frobnicate".into(),
+            },
+            app(1_000_002, "frobnicate", vec![]),
+        ],
+    );
+    assert_eq!(
+        errors(&parsed(&output, step)),
+        [
+            ("Name frobnicate not found".to_string(), Some(1_000_001)),
+            (
+                "Unsupported operator frobnicate".to_string(),
+                Some(1_000_002)
+            ),
+        ]
+    );
+}
+
+#[test]
+fn arities_are_checked() {
+    let output = load("tictactoe");
+    let one = QuintEx::QuintInt {
+        id: 1_000_001,
+        value: 1,
+    };
+    let step = app(1_000_000, "iadd", vec![one.clone()]);
+    assert_eq!(
+        errors(&parsed(&output, step)),
+        [(
+            "iadd applied to 1 arguments, expected 2, in iadd(1)".to_string(),
+            Some(1_000_000)
+        )]
+    );
+
+    let step = app(1_000_000, "assign", vec![one.clone(), one]);
+    assert_eq!(
+        errors(&parsed(&output, step)),
+        [(
+            "Assignment to something other than a variable, in assign(1, 1)".to_string(),
+            Some(1_000_000)
+        )]
+    );
+}
+
+#[test]
+fn temporal_operators_are_only_used_in_temporal_properties() {
+    let output = load("tictactoe");
+    let truth = QuintEx::QuintBool {
+        id: 1_000_001,
+        value: true,
+    };
+    let always = app(1_000_002, "always", vec![truth.clone()]);
+    let step = app(
+        1_000_000,
+        "and",
+        vec![
+            always.clone(),
+            app(1_000_003, "eventually", vec![truth.clone()]),
+        ],
+    );
+    assert_eq!(
+        errors(&parsed(&output, step)),
+        [
+            (
+                "Temporal operator always can only be used in temporal properties, in always(true)"
+                    .to_string(),
+                Some(1_000_002)
+            ),
+            (
+                "Temporal operator eventually can only be used in temporal properties, in eventually(true)"
+                    .to_string(),
+                Some(1_000_003)
+            ),
+        ]
+    );
+
+    // Fairness can't be checked over finite traces, but the rest can
+    let mut spec = parsed(&output, truth.clone());
+    spec.temporal = vec![NamedProperty {
+        name: "prop".into(),
+        expr: always,
+    }];
+    assert!(spec.validate().is_ok());
+    spec.temporal.push(NamedProperty {
+        name: "fair".into(),
+        expr: app(1_000_004, "weakFair", vec![truth.clone(), truth]),
+    });
+    assert_eq!(
+        errors(&spec),
+        [(
+            "Temporal operator weakFair cannot be checked over finite traces, in weakFair(true, true)"
+                .to_string(),
+            Some(1_000_004)
+        )]
+    );
+}
+
+#[test]
+fn unsupported_versions_are_rejected() {
+    let mut output = load("tictactoe");
+    output.version = Some("0.26.1".to_string());
+    assert!(output.validate().is_ok());
+
+    output.version = Some("0.18.0".to_string());
+    let errors = output.validate().unwrap_err();
+    assert!(errors[0]
+        .message
+        .starts_with("Unsupported IR version 0.18.0"));
+
+    output.version = None;
+    output.stage = Some("parsing".to_string());
+    assert!(output.validate().is_err());
+}
@@ -0,0 +1,159 @@
+use std::rc::Rc;
+
+use quint_evaluator::counters;
+use quint_evaluator::evaluator::CompiledExpr;
+use quint_evaluator::value::{
+    CachedCardinality, Cardinality, ImmutableMap, ImmutableSet, Universe, Value,
+};
+
+fn interval(start: i64, end: i64) -> Rc<Value> {
+    Rc::new(Value::Interval(start, end))
+}
+
+fn map_set(domain: Rc<Value>, range: Rc<Value>) -> Value {
+    Value::MapSet(domain, range, CachedCardinality::default())
+}
+
+fn empty() -> Rc<Value> {
+    Rc::new(Value::Set(ImmutableSet::default()))
+}
+
+/// Run a check, asserting that it doesn't enumerate any intermediate set.
+fn without_enumeration<T>(check: impl FnOnce() -> T) -> T {
+    counters::set_enabled(true);
+    counters::reset();
+    let result = check();
+    assert_eq!(counters::get().set_enumerations, 0);
+    counters::set_enabled(false);
+    result
+}
+
+#[test]
+fn maps_are_checked_against_sets_of_maps_structurally() {
+    // 1_000_000 ^ 20 maps
+    let maps = map_set(interval(1, 20), interval(1, 1_000_000));
+    let map = |range: i64| {
+        Value::Map(ImmutableMap::from_iter(
+            (1..=20).map(|k| (Value::Int(k), Value::Int(range))),
+        ))
+    };
+    without_enumeration(|| {
+        assert!(maps.contains(&map(7)));
+        assert!(!maps.contains(&map(0)));
+        assert!(!maps.contains(&Value::Map(ImmutableMap::default())));
+    });
+
+    // Maps from an empty domain are the empty map, with any range
+    assert!(map_set(empty(), interval(1, 3)).contains(&Value::Map(ImmutableMap::default())));
+    assert!(!map_set(interval(1, 3), empty()).contains(&map(1)));
+}
+
+#[test]
+fn intermediate_sets_are_compared_structurally() {
+    let big = interval(1, 1_000_000);
+    without_enumeration(|| {
+        assert_eq!(
+            map_set(interval(1, 20), big.clone()),
+            map_set(interval(1, 20), big.clone())
+        );
+        assert_ne!(
+            map_set(interval(1, 20), big.clone()),
+            map_set(interval(1, 19), big.clone())
+        );
+        assert_eq!(
+            map_set(empty(), big.clone()),
+            map_set(empty(), interval(1, 2))
+        );
+        assert_eq!(
+            map_set(big.clone(), empty()),
+            map_set(interval(1, 2), empty())
+        );
+        assert_eq!(
+            Value::CrossProduct(vec![(*big).clone(), (*empty()).clone()], Default::default()),
+            (*empty()).clone()
+        );
+    });
+
+    // Other representations are compared by membership, only iterating over
+    // one of the sets
+    let enumerated = Value::Set((1..=5).map(Value::Int).collect());
+    assert_eq!(Value::Interval(1, 5), enumerated);
+    assert_ne!(Value::Interval(1, 6), enumerated);
+    assert_ne!(Value::Interval(0, 4), enumerated);
+}
+
+#[test]
+fn cardinalities_too_large_to_count_are_reported() {
+    let small = Value::PowerSet(interval(1, 10), CachedCardinality::default());
+    assert_eq!(small.checked_cardinality(), Some(1024));
+
+    let large = Value::PowerSet(interval(1, 100), CachedCardinality::default());
+    assert_eq!(large.checked_cardinality(), None);
+    assert_eq!(large.cardinality(), usize::MAX);
+
+    assert_eq!(Value::Universe(Universe::Int).checked_cardinality(), None);
+}
+
+#[test]
+fn cardinalities_too_large_to_count_are_compared() {
+    let large = Value::PowerSet(interval(1, 100), CachedCardinality::default());
+    assert_eq!(large.symbolic_cardinality(), Cardinality::TooLarge);
+    assert!(large.symbolic_cardinality() > i64::MAX);
+
+    let small = Value::PowerSet(interval(1, 10), CachedCardinality::default());
+    assert_eq!(small.symbolic_cardinality(), 1024_i64);
+    assert!(small.symbolic_cardinality() < large.symbolic_cardinality());
+    assert_eq!(
+        Value::Universe(Universe::Nat).symbolic_cardinality(),
+        Cardinality::Infinite
+    );
+}
+
+#[test]
+fn infinite_sets_are_compared_without_panicking() {
+    let ints = Value::PowerSet(
+        Rc::new(Value::Universe(Universe::Int)),
+        CachedCardinality::default(),
+    );
+    let nats = Value::PowerSet(
+        Rc::new(Value::Universe(Universe::Nat)),
+        CachedCardinality::default(),
+    );
+    assert_eq!(ints.cmp(&nats), nats.cmp(&ints).reverse());
+    assert_eq!(ints.cmp(&ints.clone()), std::cmp::Ordering::Equal);
+}
+
+#[test]
+fn lambdas_are_compared_by_identity() {
+    let lambda = || Value::Lambda(Vec::new(), CompiledExpr::new(|_| Ok(Value::Int(1))));
+    let (a, b) = (lambda(), lambda());
+    assert_eq!(a, a.clone());
+    assert_ne!(a, b);
+    assert_eq!(a.cmp(&b), b.cmp(&a).reverse());
+    assert_eq!(a.fingerprint(), b.fingerprint());
+    assert_eq!(
+        a.to_itf(),
+        serde_json::json!({ "#unserializable": "<lambda>" })
+    );
+    assert!(a.to_tla().is_err());
+}
+
+#[test]
+fn infinite_sets_are_written_as_they_were_built() {
+    let pairs = Value::CrossProduct(
+        vec![
+            Value::Universe(Universe::Int),
+            Value::Universe(Universe::Nat),
+        ],
+        CachedCardinality::default(),
+    );
+    assert_eq!(pairs.to_tla(), Ok("Int \\X Nat".to_string()));
+    assert_eq!(
+        pairs.to_itf(),
+        serde_json::json!({ "#unserializable": "tuples(Int, Nat)" })
+    );
+    assert_eq!(
+        pairs.try_set().map(|_| ()).unwrap_err().kind,
+        quint_evaluator::ir::ErrorKind::InfiniteSet
+    );
+}
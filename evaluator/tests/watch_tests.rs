@@ -0,0 +1,70 @@
+use std::fs::{self, File};
+use std::time::{Duration, SystemTime};
+
+use quint_evaluator::diagnostics::SourceMap;
+use quint_evaluator::watch::Watcher;
+use serde_json::json;
+
+/// Touch a file, with a modification time far enough from the current one to
+/// be noticed in file systems with coarse timestamps.
+fn touch(path: &std::path::Path) {
+    let file = File::options().write(true).open(path).unwrap();
+    file.set_modified(SystemTime::now() + Duration::from_secs(10))
+        .unwrap();
+}
+
+#[test]
+fn specs_are_watched_with_their_siblings() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = dir.path().join("spec.qnt");
+    let lib = dir.path().join("lib.qnt");
+    let notes = dir.path().join("notes.txt");
+    for path in [&spec, &lib, &notes] {
+        fs::write(path, "").unwrap();
+    }
+
+    let mut watcher = Watcher::for_spec(&spec).unwrap();
+    let mut files: Vec<_> = watcher.files().map(|p| p.to_path_buf()).collect();
+    files.sort();
+    assert_eq!(files, [lib.clone(), spec.clone()]);
+    assert!(watcher.changes().is_empty());
+
+    touch(&lib);
+    touch(&notes);
+    assert_eq!(watcher.changes(), [lib]);
+    assert!(watcher.changes().is_empty());
+
+    fs::remove_file(&spec).unwrap();
+    assert_eq!(watcher.wait(), [spec]);
+}
+
+#[test]
+fn specs_are_watched_with_the_files_they_import() {
+    let dir = tempfile::tempdir().unwrap();
+    let spec = dir.path().join("spec.qnt");
+    let sibling = dir.path().join("sibling.qnt");
+    let lib_dir = tempfile::tempdir().unwrap();
+    let lib = lib_dir.path().join("lib.qnt");
+    for path in [&spec, &sibling, &lib] {
+        fs::write(path, "").unwrap();
+    }
+
+    let location = json!({ "line": 0, "col": 0, "index": 0 });
+    let source_map = SourceMap::from_json(&json!({
+        "sourceIndex": { "0": spec, "1": lib },
+        "map": {
+            "1": [0, location, location],
+            "2": [1, location, location],
+            "3": [1, location, location],
+        },
+    }))
+    .unwrap();
+
+    let mut watcher = Watcher::for_sources(&spec, &source_map);
+    let files: Vec<_> = watcher.files().map(|p| p.to_path_buf()).collect();
+    assert_eq!(files, [spec, lib.clone()]);
+
+    touch(&sibling);
+    touch(&lib);
+    assert_eq!(watcher.changes(), [lib]);
+}
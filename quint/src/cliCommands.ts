@@ -45,6 +45,7 @@ import { Rng, newRng } from './rng'
 import { fileSourceResolver } from './parsing/sourceResolver'
 import { verify } from './quintVerifier'
 import { flattenModules } from './flattening/fullFlattener'
+import { version } from './version'
 import { AnalysisOutput, analyzeInc, analyzeModules } from './quintAnalyzer'
 import { ExecutionFrame, newTraceRecorder } from './runtime/trace'
 import { flow, isEqual, uniqWith } from 'lodash'
@@ -782,9 +783,11 @@ export async function outputCompilationTarget(compiled: CompiledStage): Promise<
     })
   }
 
-  const parsedSpecJson = jsonStringOfOutputStage(
-    pickOutputStage({ ...compiled, modules: [main.value], table: compiled.table })
-  )
+  // The version lets other tools reading the JSON (i.e. the Rust evaluator) know which IR format to expect
+  const parsedSpecJson = jsonStringOfOutputStage({
+    ...pickOutputStage({ ...compiled, modules: [main.value], table: compiled.table }),
+    version,
+  })
   switch (target) {
     case 'json':
       process.stdout.write(parsedSpecJson)
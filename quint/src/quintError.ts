@@ -117,6 +117,14 @@ export type ErrorCode =
   | 'QNT513'
   /* QNT514: Cardinality is infinite */
   | 'QNT514'
+  /* QNT515: Evaluation budget exceeded */
+  | 'QNT515'
+  /* QNT516: Invalid constant value */
+  | 'QNT516'
+  /* QNT517: Malformed IR */
+  | 'QNT517'
+  /* QNT518: Likely infinite recursion */
+  | 'QNT518'
 
 /* Additional data for a Quint error */
 export interface QuintErrorData {
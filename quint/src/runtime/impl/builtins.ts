@@ -384,7 +384,13 @@ export function builtinLambda(op: string): (ctx: Context, args: RuntimeValue[])
       }
     case 'imod':
       // Integer modulus
-      return (_, args) => right(rv.mkInt(args[0].toInt() % args[1].toInt()))
+      return (_, args) => {
+        const divisor = args[1].toInt()
+        if (divisor === 0n) {
+          return left({ code: 'QNT503', message: `Division by zero` })
+        }
+        return right(rv.mkInt(args[0].toInt() % divisor))
+      }
     case 'ipow':
       // Integer exponentiation
       return (_, args) => {
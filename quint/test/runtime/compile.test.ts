@@ -122,10 +122,12 @@ describe('compiling specs to runtime values', () => {
 
     it('computes division', () => {
       assertResultAsString('7 / 2', '3')
+      assertResultAsString('7 / 0', undefined)
     })
 
     it('computes remainder', () => {
       assertResultAsString('7 % 2', '1')
+      assertResultAsString('7 % 0', undefined)
     })
 
     it('computes power', () => {